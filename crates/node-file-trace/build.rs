@@ -1,5 +1,68 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::Path,
+    process::Command,
+};
+
 use turbo_tasks_build::generate_register;
 
 fn main() {
     generate_register();
+    println!("cargo:rustc-env=NFT_BUILD_ID={}", build_id());
+}
+
+/// Identifies the compiler, target, features and sources of this build. It's
+/// mixed into the epoch of the persistent cache, so caches written by another
+/// build are discarded, as the crate versions don't change between builds.
+fn build_id() -> String {
+    let mut hasher = DefaultHasher::new();
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    command_output(&rustc, &["-vV"]).hash(&mut hasher);
+    for (key, value) in std::env::vars() {
+        if key == "TARGET" || key == "PROFILE" || key.starts_with("CARGO_FEATURE_") {
+            (key, value).hash(&mut hasher);
+        }
+    }
+    let head = command_output("git", &["rev-parse", "HEAD"]);
+    head.hash(&mut hasher);
+    if !head.is_empty() {
+        // Uncommitted changes
+        command_output("git", &["diff", "HEAD"]).hash(&mut hasher);
+        command_output("git", &["status", "--porcelain", "--untracked-files=all"])
+            .hash(&mut hasher);
+        rerun_if_git_changed();
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// Runs the build script again when a commit is checked out or made, or
+/// changes are staged.
+fn rerun_if_git_changed() {
+    let git_dir = command_output("git", &["rev-parse", "--absolute-git-dir"]);
+    if git_dir.is_empty() {
+        return;
+    }
+    let git_dir = Path::new(&git_dir);
+    for file in ["HEAD", "index", "packed-refs"] {
+        println!("cargo:rerun-if-changed={}", git_dir.join(file).display());
+    }
+    let head_ref = command_output("git", &["symbolic-ref", "-q", "HEAD"]);
+    if !head_ref.is_empty() {
+        println!(
+            "cargo:rerun-if-changed={}",
+            git_dir.join(head_ref).display()
+        );
+    }
+}
+
+/// The trimmed stdout of a command, empty when it fails.
+fn command_output(program: &str, args: &[&str]) -> String {
+    Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_default()
 }
@@ -67,6 +67,14 @@ struct CacheArgs {
 
     #[clap(long)]
     cache_fully: bool,
+
+    /// Don't read or write the persistent cache.
+    #[clap(long)]
+    no_cache: bool,
+
+    /// Discard the persistent cache before starting.
+    #[clap(long)]
+    repair_cache: bool,
 }
 
 #[cfg(not(feature = "persistent_cache"))]
@@ -328,25 +336,44 @@ pub async fn start(
         visualize_graph,
         memory_limit,
         #[cfg(feature = "persistent_cache")]
-            cache: CacheArgs {
-            ref cache,
-            ref cache_fully,
-        },
+            cache:
+            CacheArgs {
+                ref cache,
+                ref cache_fully,
+                no_cache,
+                repair_cache,
+            },
         ..
     } = args.common();
     #[cfg(feature = "persistent_cache")]
-    if let Some(cache) = cache {
+    if let Some(cache) = cache.as_ref().filter(|_| !no_cache) {
         use tokio::time::timeout;
+        use turbo_tasks::persisted_graph::{CacheEpoch, CacheRecovery, JournaledPersistedGraph};
         use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
         use turbo_tasks_rocksdb::RocksDbPersistedGraph;
 
+        // The options decide the enabled transforms and defines
+        let epoch = CacheEpoch::new()
+            .component("node-file-trace", env!("NFT_BUILD_ID"))
+            .component("module-options", serde_json::to_string(&module_options)?)
+            .component("resolve-options", serde_json::to_string(&resolve_options)?);
         run(
             &args,
             || {
                 let start = Instant::now();
-                let backend = MemoryBackendWithPersistedGraph::new(
-                    RocksDbPersistedGraph::new(cache).unwrap(),
-                );
+                let (graph, recovery) =
+                    JournaledPersistedGraph::open(Path::new(cache), &epoch, repair_cache, |path| {
+                        RocksDbPersistedGraph::new(path)
+                    })
+                    .unwrap();
+                match recovery {
+                    CacheRecovery::Restored => {}
+                    CacheRecovery::Discarded(state) => {
+                        println!("discarded cache ({state:?}), starting with a cold build")
+                    }
+                    CacheRecovery::Repaired => println!("discarded cache"),
+                }
+                let backend = MemoryBackendWithPersistedGraph::new(graph);
                 let tt = TurboTasks::new(backend);
                 let elapsed = start.elapsed();
                 println!("restored cache {}", FormatDuration(elapsed));
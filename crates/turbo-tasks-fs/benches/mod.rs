@@ -1,4 +1,5 @@
 use std::{
+    collections::HashSet,
     fs,
     sync::{mpsc::channel, Arc},
     thread,
@@ -16,7 +17,10 @@ use notify_debouncer_full::{
 };
 use tokio::runtime::Runtime;
 use turbo_tasks::event::Event;
-use turbo_tasks_fs::rope::{Rope, RopeBuilder};
+use turbo_tasks_fs::{
+    atom::Atom,
+    rope::{Rope, RopeBuilder},
+};
 
 fn bench_file_watching(c: &mut Criterion) {
     let mut g = c.benchmark_group("turbo-tasks-fs");
@@ -107,9 +111,62 @@ fn bench_rope_iteration(c: &mut Criterion) {
     );
 }
 
+/// Clones and hashes the specifiers of a 10k module graph, where each module
+/// imports a few popular packages, like tasks passing specifiers around do.
+/// "Atom::new" includes interning the specifiers, like parsing requests does.
+fn bench_atom(c: &mut Criterion) {
+    let mut g = c.benchmark_group("turbo-tasks-fs");
+    g.sample_size(10);
+
+    let specifiers = (0..10_000)
+        .flat_map(|i| {
+            [
+                "react".to_string(),
+                "react-dom/client".to_string(),
+                "@scope/design-system/components/button".to_string(),
+                format!("./components/module-{i}.js"),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    g.bench_function(BenchmarkId::new("bench_atom", "String"), |b| {
+        b.iter(|| {
+            let mut set = HashSet::new();
+            for specifier in &specifiers {
+                set.insert(specifier.clone());
+            }
+            set
+        })
+    });
+
+    g.bench_function(BenchmarkId::new("bench_atom", "Atom::new"), |b| {
+        b.iter(|| {
+            let mut set = HashSet::new();
+            for specifier in &specifiers {
+                set.insert(Atom::new(specifier));
+            }
+            set
+        })
+    });
+
+    let atoms = specifiers
+        .iter()
+        .map(|specifier| Atom::new(specifier))
+        .collect::<Vec<_>>();
+    g.bench_function(BenchmarkId::new("bench_atom", "Atom"), |b| {
+        b.iter(|| {
+            let mut set = HashSet::new();
+            for atom in &atoms {
+                set.insert(atom.clone());
+            }
+            set
+        })
+    });
+}
+
 criterion_group!(
     name = benches;
     config = Criterion::default();
-    targets = bench_file_watching, bench_rope_iteration
+    targets = bench_file_watching, bench_rope_iteration, bench_atom
 );
 criterion_main!(benches);
@@ -0,0 +1,179 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, OnceLock},
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use turbo_tasks::trace::{TraceRawVcs, TraceRawVcsContext};
+use turbo_tasks_hash::{DeterministicHash, DeterministicHasher};
+
+/// An interned, immutable string, used for strings which are repeated a lot
+/// across tasks, like module specifiers.
+///
+/// Each distinct string is allocated only once. Cloning an Atom is a
+/// reference count increment, and comparing and hashing only look at the
+/// pointer instead of the contents.
+///
+/// Interned strings are never freed, so this must not be used for strings
+/// which are generated in unbounded numbers, like file contents.
+#[derive(Clone)]
+pub struct Atom(Arc<str>);
+
+fn interner() -> &'static DashMap<Arc<str>, ()> {
+    static INTERNER: OnceLock<DashMap<Arc<str>, ()>> = OnceLock::new();
+    INTERNER.get_or_init(Default::default)
+}
+
+impl Atom {
+    pub fn new(s: &str) -> Self {
+        let interner = interner();
+        if let Some(entry) = interner.get(s) {
+            return Atom(entry.key().clone());
+        }
+        // Another thread might have interned the same string in the meantime,
+        // the entry makes sure both end up with the same pointer.
+        match interner.entry(Arc::from(s)) {
+            Entry::Occupied(entry) => Atom(entry.key().clone()),
+            Entry::Vacant(entry) => {
+                let atom = Atom(entry.key().clone());
+                entry.insert(());
+                atom
+            }
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Atom {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Atom {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Atom {
+    fn from(s: &str) -> Self {
+        Atom::new(s)
+    }
+}
+
+impl From<String> for Atom {
+    fn from(s: String) -> Self {
+        Atom::new(&s)
+    }
+}
+
+impl From<Atom> for String {
+    fn from(atom: Atom) -> Self {
+        atom.0.to_string()
+    }
+}
+
+impl PartialEq for Atom {
+    fn eq(&self, other: &Self) -> bool {
+        // Equal strings are interned to the same allocation
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Atom {}
+
+impl PartialEq<str> for Atom {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Atom {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Hash for Atom {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const u8 as usize).hash(state)
+    }
+}
+
+impl PartialOrd for Atom {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Atom {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Debug for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl DeterministicHash for Atom {
+    /// Hashes the contents like a [String], pointers differ between
+    /// processes.
+    fn deterministic_hash<H: DeterministicHasher>(&self, state: &mut H) {
+        self.as_str().deterministic_hash(state)
+    }
+}
+
+impl TraceRawVcs for Atom {
+    fn trace_raw_vcs(&self, _trace_context: &mut TraceRawVcsContext) {}
+}
+
+impl Serialize for Atom {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Atom {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Atom::new(&s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::Atom;
+
+    #[test]
+    fn interned() {
+        let a = Atom::new("react");
+        let b = Atom::from("react".to_string());
+        assert_eq!(a, b);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_ne!(a, Atom::new("react-dom"));
+        assert_eq!(a, "react");
+
+        let set = HashSet::from([a, b, Atom::new("react-dom")]);
+        assert_eq!(set.len(), 2);
+    }
+}
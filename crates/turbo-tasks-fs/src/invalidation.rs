@@ -4,7 +4,7 @@ use indexmap::IndexSet;
 use turbo_tasks::{util::StaticOrArc, InvalidationReason, InvalidationReasonKind};
 
 /// Invalidation was caused by a file change detected by the file watcher
-#[derive(PartialEq, Eq, Hash)]
+#[derive(PartialEq, Eq, Hash, Clone)]
 pub struct WatchChange {
     pub path: String,
 }
@@ -6,13 +6,15 @@
 #![feature(round_char_boundary)]
 #![feature(arbitrary_self_types)]
 
+pub mod atom;
 pub mod attach;
 pub mod embed;
 pub mod glob;
-mod invalidation;
+pub mod invalidation;
 mod invalidator_map;
 pub mod json;
 mod mutex_map;
+mod read_dir_sorted;
 mod read_glob;
 mod retry;
 pub mod rope;
@@ -45,8 +47,10 @@ use glob::Glob;
 use invalidator_map::InvalidatorMap;
 use jsonc_parser::{parse_to_serde_value, ParseOptions};
 use mime::Mime;
-use read_glob::read_glob;
+use read_dir_sorted::{read_dir_batch, read_dir_sorted};
+pub use read_dir_sorted::{DirectoryBatch, DirectoryDiff, SortedDirectoryContent};
 pub use read_glob::ReadGlobResult;
+use read_glob::{read_glob, track_glob};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::{
@@ -64,7 +68,11 @@ use util::{extract_disk_access, join_path, normalize_path, sys_to_unix, unix_to_
 pub use virtual_fs::VirtualFileSystem;
 use watcher::DiskWatcher;
 
-use self::{invalidation::Write, json::UnparseableJson, mutex_map::MutexMap};
+use self::{
+    invalidation::{WatchChange, Write},
+    json::UnparseableJson,
+    mutex_map::MutexMap,
+};
 use crate::{
     attach::AttachedFileSystem,
     retry::{retry_blocking, retry_future},
@@ -185,6 +193,33 @@ impl DiskFileSystem {
         }
     }
 
+    /// Invalidates everything that read the file or directory at `path`,
+    /// which is relative to the root of the filesystem. Useful when a change
+    /// wasn't picked up by the file watcher.
+    pub fn invalidate_path(&self, path: &str) {
+        let full_path = self.root_path().join(&*unix_to_sys(path));
+        let reason = WatchChange {
+            path: format_absolute_fs_path(&full_path, &self.name, self.root_path())
+                .unwrap_or_else(|| path.to_string()),
+        };
+        let mut invalidators = Vec::new();
+        invalidators.extend(
+            self.invalidator_map
+                .lock()
+                .unwrap()
+                .remove(&path_to_key(&full_path)),
+        );
+        let mut dir_invalidator_map = self.dir_invalidator_map.lock().unwrap();
+        invalidators.extend(dir_invalidator_map.remove(&path_to_key(&full_path)));
+        if let Some(parent) = full_path.parent() {
+            invalidators.extend(dir_invalidator_map.remove(&path_to_key(parent)));
+        }
+        drop(dir_invalidator_map);
+        for invalidator in invalidators.into_iter().flatten() {
+            invalidator.invalidate_with_reason(reason.clone());
+        }
+    }
+
     pub fn start_watching(&self) -> Result<()> {
         self.start_watching_internal(false)
     }
@@ -218,6 +253,26 @@ impl DiskFileSystem {
         self.watcher.stop_watching();
     }
 
+    /// Only watches the directories `dirs`, which are relative to the root of
+    /// the filesystem, instead of all directories which were read. Changes
+    /// outside of them are not picked up. Can be called again to update the
+    /// scope, e. g. when the module graph it was derived from changes.
+    pub fn set_watch_scope<'a>(&self, dirs: impl IntoIterator<Item = &'a str>) {
+        let root_path = self.root_path();
+        self.watcher.set_scope(
+            root_path,
+            dirs.into_iter()
+                .map(|dir| {
+                    if dir.is_empty() {
+                        root_path.to_path_buf()
+                    } else {
+                        root_path.join(&*unix_to_sys(dir))
+                    }
+                })
+                .collect(),
+        );
+    }
+
     pub async fn to_sys_path(&self, fs_path: Vc<FileSystemPath>) -> Result<PathBuf> {
         // just in case there's a windows unc path prefix we remove it with `dunce`
         let path = self.root_path();
@@ -308,8 +363,29 @@ impl DiskFileSystem {
 
         Ok(Self::cell(instance))
     }
+
+    /// The directories, relative to the root, which are polled for changes
+    /// instead of watched, because the OS limit of watches or open files was
+    /// reached, e. g. `fs.inotify.max_user_watches` on Linux.
+    #[turbo_tasks::function]
+    pub fn polled_directories(&self) -> Vc<PolledDirectories> {
+        let root_path = self.root_path();
+        Vc::cell(
+            self.watcher
+                .polled_directories(turbo_tasks::get_invalidator())
+                .into_iter()
+                .filter_map(|dir| {
+                    let dir = dir.strip_prefix(root_path).ok()?;
+                    Some(sys_to_unix(&dir.to_string_lossy()).into_owned())
+                })
+                .collect(),
+        )
+    }
 }
 
+#[turbo_tasks::value(transparent)]
+pub struct PolledDirectories(Vec<String>);
+
 impl Debug for DiskFileSystem {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "name: {}, root: {}", self.name, self.root)
@@ -723,6 +799,26 @@ impl ValueToString for DiskFileSystem {
     }
 }
 
+/// Whether `path` is `dir` or inside of it, both relative to the root of the
+/// same filesystem.
+fn is_inside_or_equal(path: &str, dir: &str) -> bool {
+    dir.is_empty()
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Reports a join of `path` which would leave `base`, which can be `..`
+/// traversal from a request or another user-controlled specifier.
+fn audit_path_escape(base: &str, path: &str) {
+    tracing::warn!(
+        target: "turbo_tasks_fs::path_escape",
+        base,
+        path,
+        "joined path leaves its base directory"
+    );
+}
+
 #[turbo_tasks::value]
 #[derive(Debug, Clone)]
 pub struct FileSystemPath {
@@ -913,6 +1009,7 @@ impl FileSystemPath {
         if let Some(path) = join_path(&this.path, &path) {
             Ok(Self::new_normalized(this.fs, path))
         } else {
+            audit_path_escape(&this.path, &path);
             bail!(
                 "Vc<FileSystemPath>(\"{}\").join(\"{}\") leaves the filesystem root",
                 this.path,
@@ -982,7 +1079,7 @@ impl FileSystemPath {
     pub async fn try_join_inside(self: Vc<Self>, path: String) -> Result<Vc<FileSystemPathOption>> {
         let this = self.await?;
         if let Some(path) = join_path(&this.path, &path) {
-            if path.starts_with(&this.path) {
+            if is_inside_or_equal(&path, &this.path) {
                 return Ok(Vc::cell(Some(
                     Self::new_normalized(this.fs, path).resolve().await?,
                 )));
@@ -991,6 +1088,29 @@ impl FileSystemPath {
         Ok(FileSystemPathOption::none())
     }
 
+    /// Like [FileSystemPath::try_join_inside], for a `path` controlled by
+    /// users, e. g. the path of a request. Paths leaving the current path,
+    /// e. g. with `..` segments, are reported as path escapes.
+    ///
+    /// Server-facing code must use this instead of [FileSystemPath::join],
+    /// which only rejects paths leaving the root of the filesystem.
+    #[turbo_tasks::function]
+    pub async fn join_untrusted(self: Vc<Self>, path: String) -> Result<Vc<FileSystemPathOption>> {
+        let this = self.await?;
+        // Backslashes are separators on Windows, and `join_path` expects none
+        if !path.contains(['\\', '\0']) {
+            if let Some(joined) = join_path(&this.path, &path) {
+                if is_inside_or_equal(&joined, &this.path) {
+                    return Ok(Vc::cell(Some(
+                        Self::new_normalized(this.fs, joined).resolve().await?,
+                    )));
+                }
+            }
+        }
+        audit_path_escape(&this.path, &path);
+        Ok(FileSystemPathOption::none())
+    }
+
     #[turbo_tasks::function]
     pub async fn read_glob(
         self: Vc<Self>,
@@ -1000,6 +1120,12 @@ impl FileSystemPath {
         read_glob(self, glob, include_dot_files)
     }
 
+    /// Tracks the files matching `glob` in this directory, see [track_glob].
+    #[turbo_tasks::function]
+    pub fn track_glob(self: Vc<Self>, glob: Vc<Glob>, include_dot_files: bool) -> Vc<Completion> {
+        track_glob(self, glob, include_dot_files)
+    }
+
     #[turbo_tasks::function]
     pub fn root(self: Vc<Self>) -> Vc<Self> {
         self.fs().root()
@@ -1126,6 +1252,17 @@ impl FileSystemPath {
         self.fs().read_dir(self)
     }
 
+    /// Reads content of a directory, sorted by name.
+    pub fn read_dir_sorted(self: Vc<Self>) -> Vc<SortedDirectoryContent> {
+        read_dir_sorted(self)
+    }
+
+    /// Reads the `index`th batch of `batch_size` entries of a directory,
+    /// sorted by name.
+    pub fn read_dir_batch(self: Vc<Self>, index: usize, batch_size: usize) -> Vc<DirectoryBatch> {
+        read_dir_batch(self, index, batch_size)
+    }
+
     pub fn track(self: Vc<Self>) -> Vc<Completion> {
         self.fs().track(self)
     }
@@ -2008,4 +2145,37 @@ mod tests {
         .await
         .unwrap()
     }
+
+    #[tokio::test]
+    async fn join_untrusted() {
+        crate::register();
+
+        turbo_tasks_testing::VcStorage::with(async {
+            let fs = Vc::upcast::<Box<dyn FileSystem>>(VirtualFileSystem::new());
+            let dir = FileSystemPath::new_normalized(fs, "public".into());
+
+            let joined = |path: &str| {
+                let path = dir.join_untrusted(path.to_string());
+                async move {
+                    anyhow::Ok(match *path.await? {
+                        Some(path) => Some(path.await?.path.clone()),
+                        None => None,
+                    })
+                }
+            };
+
+            assert_eq!(joined("a/b.txt").await?.as_deref(), Some("public/a/b.txt"));
+            assert_eq!(joined("a/../b.txt").await?.as_deref(), Some("public/b.txt"));
+            assert_eq!(joined("../secret").await?, None);
+            assert_eq!(joined("../public2/secret").await?, None);
+            assert_eq!(joined("..\\secret").await?, None);
+
+            let inside = dir.try_join_inside("../public2".to_string()).await?;
+            assert!(inside.is_none());
+
+            anyhow::Ok(())
+        })
+        .await
+        .unwrap()
+    }
 }
@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+
+use anyhow::Result;
+use turbo_tasks::Vc;
+
+use crate::{DirectoryContent, DirectoryEntry, FileSystemPath};
+
+/// The entries of a directory, sorted by name.
+#[turbo_tasks::value]
+#[derive(Debug)]
+pub enum SortedDirectoryContent {
+    Entries(Vec<(String, DirectoryEntry)>),
+    NotFound,
+}
+
+impl SortedDirectoryContent {
+    pub fn entries(&self) -> &[(String, DirectoryEntry)] {
+        match self {
+            SortedDirectoryContent::Entries(entries) => entries,
+            SortedDirectoryContent::NotFound => &[],
+        }
+    }
+
+    /// Compares the entries with the `previous` content of the directory,
+    /// e. g. the content before an invalidation. Both are sorted, so this is
+    /// linear in the number of entries.
+    pub fn diff(&self, previous: &SortedDirectoryContent) -> DirectoryDiff {
+        let mut diff = DirectoryDiff::default();
+        let mut current = self.entries().iter().peekable();
+        let mut previous = previous.entries().iter().peekable();
+        loop {
+            let ordering = match (current.peek(), previous.peek()) {
+                (Some((name, _)), Some((previous_name, _))) => name.cmp(previous_name),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match ordering {
+                Ordering::Less => diff.added.extend(current.next().cloned()),
+                Ordering::Greater => {
+                    diff.removed
+                        .extend(previous.next().map(|(name, _)| name.clone()));
+                }
+                Ordering::Equal => {
+                    let (name, entry) = current.next().unwrap();
+                    let (_, previous_entry) = previous.next().unwrap();
+                    if entry != previous_entry {
+                        diff.changed.push((name.clone(), *entry));
+                    }
+                }
+            }
+        }
+        diff
+    }
+}
+
+/// The changes between two reads of a directory, see
+/// [SortedDirectoryContent::diff]. All lists are sorted by name.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DirectoryDiff {
+    pub added: Vec<(String, DirectoryEntry)>,
+    pub removed: Vec<String>,
+    /// Entries whose type changed, e. g. a file replaced by a directory.
+    pub changed: Vec<(String, DirectoryEntry)>,
+}
+
+impl DirectoryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// A batch of the sorted entries of a directory, see [read_dir_batch].
+#[turbo_tasks::value]
+#[derive(Debug)]
+pub struct DirectoryBatch {
+    pub entries: Vec<(String, DirectoryEntry)>,
+    /// Whether there are entries after this batch.
+    pub has_more: bool,
+}
+
+/// Reads the entries of a directory sorted by name, unlike
+/// [FileSystemPath::read_dir], whose order is random.
+#[turbo_tasks::function]
+pub async fn read_dir_sorted(directory: Vc<FileSystemPath>) -> Result<Vc<SortedDirectoryContent>> {
+    let DirectoryContent::Entries(entries) = &*directory.read_dir().await? else {
+        return Ok(SortedDirectoryContent::NotFound.cell());
+    };
+    let mut entries = entries
+        .iter()
+        .map(|(name, entry)| (name.clone(), *entry))
+        .collect::<Vec<_>>();
+    entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(SortedDirectoryContent::Entries(entries).cell())
+}
+
+/// Reads the `index`th batch of `batch_size` sorted entries of a directory,
+/// e. g. to process a directory of tens of thousands of pages in parallel.
+///
+/// Each batch is a task of its own, so when an entry is added or removed, only
+/// the tasks depending on a batch whose entries changed are invalidated.
+#[turbo_tasks::function]
+pub async fn read_dir_batch(
+    directory: Vc<FileSystemPath>,
+    index: usize,
+    batch_size: usize,
+) -> Result<Vc<DirectoryBatch>> {
+    let content = read_dir_sorted(directory).await?;
+    let entries = content.entries();
+    let start = index.saturating_mul(batch_size).min(entries.len());
+    let end = start.saturating_add(batch_size).min(entries.len());
+    Ok(DirectoryBatch {
+        entries: entries[start..end].to_vec(),
+        has_more: end < entries.len(),
+    }
+    .cell())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DirectoryDiff, SortedDirectoryContent};
+    use crate::DirectoryEntry;
+
+    fn content(names: &[&str]) -> SortedDirectoryContent {
+        SortedDirectoryContent::Entries(
+            names
+                .iter()
+                .map(|name| (name.to_string(), DirectoryEntry::Error))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn diff() {
+        let previous = content(&["a", "c", "d", "f"]);
+        let current = content(&["b", "c", "e", "f", "g"]);
+        let diff = current.diff(&previous);
+        assert_eq!(
+            diff.added.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            ["b", "e", "g"]
+        );
+        assert_eq!(diff.removed, ["a", "d"]);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn diff_not_found() {
+        let current = content(&["a"]);
+        let diff = current.diff(&SortedDirectoryContent::NotFound);
+        assert_eq!(diff.added.len(), 1);
+        assert!(SortedDirectoryContent::NotFound
+            .diff(&SortedDirectoryContent::NotFound)
+            .is_empty());
+        assert_eq!(current.diff(&content(&["a"])), DirectoryDiff::default());
+    }
+}
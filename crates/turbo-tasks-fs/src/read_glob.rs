@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
-use turbo_tasks::Vc;
+use turbo_tasks::{Completion, TryJoinIterExt, Vc};
 
 use crate::{glob::Glob, DirectoryContent, DirectoryEntry, FileSystemPath};
 
@@ -76,3 +76,86 @@ async fn read_glob_internal(
     }
     Ok(ReadGlobResult::cell(result))
 }
+
+/// Tracks the files matching a glob pattern, so the calling task is
+/// invalidated when a matching file is added, removed or modified. A matching
+/// directory is tracked with all of its content.
+///
+/// Unlike reading all files of a directory, this only lists the directories
+/// the glob can match in, and each directory is tracked by a task of its own,
+/// so a change only lists the changed directory again.
+#[turbo_tasks::function]
+pub async fn track_glob(
+    directory: Vc<FileSystemPath>,
+    glob: Vc<Glob>,
+    include_dot_files: bool,
+) -> Result<Vc<Completion>> {
+    track_glob_internal("", directory, glob, include_dot_files).await
+}
+
+#[turbo_tasks::function]
+async fn track_glob_inner(
+    prefix: String,
+    directory: Vc<FileSystemPath>,
+    glob: Vc<Glob>,
+    include_dot_files: bool,
+) -> Result<Vc<Completion>> {
+    track_glob_internal(&prefix, directory, glob, include_dot_files).await
+}
+
+async fn track_glob_internal(
+    prefix: &str,
+    directory: Vc<FileSystemPath>,
+    glob: Vc<Glob>,
+    include_dot_files: bool,
+) -> Result<Vc<Completion>> {
+    let dir = directory.read_dir().await?;
+    let glob_value = glob.await?;
+    let DirectoryContent::Entries(entries) = &*dir else {
+        return Ok(Completion::new());
+    };
+    let mut completions = Vec::new();
+    for (segment, entry) in entries.iter() {
+        if !include_dot_files && segment.starts_with('.') {
+            continue;
+        }
+        let full_path = format!("{prefix}{segment}");
+        match *entry {
+            DirectoryEntry::Directory(path) => {
+                if glob_value.execute(&full_path) {
+                    completions.push(track_glob_inner(
+                        String::new(),
+                        path,
+                        Glob::new("**".to_string()),
+                        include_dot_files,
+                    ));
+                } else if glob_value.execute(&format!("{full_path}/")) {
+                    completions.push(track_glob_inner(
+                        format!("{full_path}/"),
+                        path,
+                        glob,
+                        include_dot_files,
+                    ));
+                }
+            }
+            DirectoryEntry::File(path) => {
+                if glob_value.execute(&full_path) {
+                    completions.push(path.track());
+                }
+            }
+            DirectoryEntry::Symlink(path) => {
+                if glob_value.execute(&full_path) {
+                    path.read_link().await?;
+                }
+            }
+            DirectoryEntry::Other(path) => {
+                if glob_value.execute(&full_path) {
+                    path.get_type().await?;
+                }
+            }
+            DirectoryEntry::Error => {}
+        }
+    }
+    completions.into_iter().try_join().await?;
+    Ok(Completion::new())
+}
@@ -1,9 +1,11 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    ffi::OsString,
+    hash::{Hash, Hasher},
     mem::take,
     path::{Path, PathBuf},
     sync::{
-        mpsc::{channel, Receiver, RecvError, TryRecvError},
+        mpsc::{channel, Receiver, RecvError, RecvTimeoutError, Sender, TryRecvError},
         Arc, Mutex,
     },
     time::Duration,
@@ -43,6 +45,61 @@ pub(crate) struct DiskWatcher {
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     #[serde(skip)]
     watching: dashmap::DashSet<PathBuf>,
+
+    /// When set, only these directories are watched, each non-recursively.
+    /// See [DiskWatcher::set_scope].
+    #[serde(skip)]
+    scope: Mutex<Option<HashSet<PathBuf>>>,
+
+    /// Directories which couldn't be watched because the OS limit of watches
+    /// or open files was reached, with a snapshot of their entries. They are
+    /// polled for changes instead, see [DiskWatcher::poll_thread].
+    #[serde(skip)]
+    polled: Mutex<HashMap<PathBuf, DirectorySnapshot>>,
+
+    /// Invalidated when another directory starts to be polled.
+    #[serde(skip)]
+    polled_invalidators: Mutex<Vec<Invalidator>>,
+
+    /// Stops the poll thread when dropped.
+    #[serde(skip)]
+    stop_polling: Mutex<Option<Sender<()>>>,
+}
+
+/// How often directories which couldn't be watched are polled for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A hash of the metadata of each entry of a directory.
+type DirectorySnapshot = HashMap<OsString, u64>;
+
+fn snapshot_directory(dir_path: &Path) -> DirectorySnapshot {
+    let Ok(entries) = std::fs::read_dir(dir_path) else {
+        return DirectorySnapshot::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let mut hasher = DefaultHasher::new();
+            if let Ok(metadata) = entry.metadata() {
+                metadata.is_dir().hash(&mut hasher);
+                metadata.len().hash(&mut hasher);
+                metadata.modified().ok().hash(&mut hasher);
+            }
+            (entry.file_name(), hasher.finish())
+        })
+        .collect()
+}
+
+/// Whether watching failed because of the limit of inotify watches or of
+/// open files.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn is_watch_limit_error(err: &notify::Error) -> bool {
+    match &err.kind {
+        notify::ErrorKind::MaxFilesWatch => true,
+        // EMFILE
+        notify::ErrorKind::Io(err) => err.raw_os_error() == Some(24),
+        _ => false,
+    }
 }
 
 impl DiskWatcher {
@@ -64,7 +121,7 @@ impl DiskWatcher {
 
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     pub(crate) fn ensure_watching(&self, dir_path: &Path, root_path: &Path) -> Result<()> {
-        if self.watching.contains(dir_path) {
+        if self.watching.contains(dir_path) || self.scope.lock().unwrap().is_some() {
             return Ok(());
         }
         let mut watcher = self.watcher.lock().unwrap();
@@ -86,6 +143,10 @@ impl DiskWatcher {
         if let Some(watcher) = watcher.as_mut() {
             let mut path = dir_path;
             while let Err(err) = watcher.watcher().watch(path, RecursiveMode::NonRecursive) {
+                if is_watch_limit_error(&err) {
+                    self.poll_dir(dir_path);
+                    return Ok(());
+                }
                 if path == root_path {
                     return Err(err).context(format!(
                         "Unable to watch {} (tried up to {})",
@@ -106,6 +167,35 @@ impl DiskWatcher {
         Ok(())
     }
 
+    /// Polls `dir_path` for changes, instead of watching it.
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    fn poll_dir(&self, dir_path: &Path) {
+        let mut polled = self.polled.lock().unwrap();
+        if polled.contains_key(dir_path) {
+            return;
+        }
+        polled.insert(dir_path.to_path_buf(), snapshot_directory(dir_path));
+        drop(polled);
+        for invalidator in take(&mut *self.polled_invalidators.lock().unwrap()) {
+            invalidator.invalidate();
+        }
+    }
+
+    /// The directories which are polled instead of watched. `invalidator` is
+    /// invalidated when another directory starts to be polled.
+    pub(crate) fn polled_directories(&self, invalidator: Invalidator) -> Vec<PathBuf> {
+        self.polled_invalidators.lock().unwrap().push(invalidator);
+        let mut dirs = self
+            .polled
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        dirs.sort();
+        dirs
+    }
+
     /// Create a watcher and start watching by creating `debounced` watcher
     /// via `full debouncer`
     ///
@@ -147,20 +237,35 @@ impl DiskWatcher {
         // Create a watcher object, delivering debounced events.
         // The notification back-end is selected based on the platform.
         let mut debounced_watcher = notify_debouncer_full::new_debouncer(delay, None, tx)?;
-        // Add a path to be watched. All files and directories at that path and
-        // below will be monitored for changes.
-        #[cfg(any(target_os = "macos", target_os = "windows"))]
-        {
-            debounced_watcher
-                .watcher()
-                .watch(&root_path, RecursiveMode::Recursive)?;
-        }
+        if let Some(scope) = &*self.scope.lock().unwrap() {
+            for dir_path in scope {
+                // The directory might have been removed since the scope was set
+                let _ = debounced_watcher
+                    .watcher()
+                    .watch(dir_path, RecursiveMode::NonRecursive);
+            }
+        } else {
+            // Add a path to be watched. All files and directories at that path and
+            // below will be monitored for changes.
+            #[cfg(any(target_os = "macos", target_os = "windows"))]
+            {
+                debounced_watcher
+                    .watcher()
+                    .watch(&root_path, RecursiveMode::Recursive)?;
+            }
 
-        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-        for dir_path in self.watching.iter() {
-            debounced_watcher
-                .watcher()
-                .watch(&dir_path, RecursiveMode::NonRecursive)?;
+            #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+            for dir_path in self.watching.iter() {
+                if let Err(err) = debounced_watcher
+                    .watcher()
+                    .watch(&dir_path, RecursiveMode::NonRecursive)
+                {
+                    if !is_watch_limit_error(&err) {
+                        return Err(err.into());
+                    }
+                    self.poll_dir(&dir_path);
+                }
+            }
         }
 
         // We need to invalidate all reads that happened before watching
@@ -180,6 +285,25 @@ impl DiskWatcher {
         watcher_guard.replace(debounced_watcher);
         drop(watcher_guard);
 
+        let (stop_polling, stop_polling_rx) = channel();
+        self.stop_polling.lock().unwrap().replace(stop_polling);
+        {
+            let this = self.clone();
+            let report_invalidation_reason = report_invalidation_reason.clone();
+            let invalidation_lock = invalidation_lock.clone();
+            let invalidator_map = invalidator_map.clone();
+            let dir_invalidator_map = dir_invalidator_map.clone();
+            spawn_thread(move || {
+                this.poll_thread(
+                    stop_polling_rx,
+                    report_invalidation_reason,
+                    invalidation_lock,
+                    invalidator_map,
+                    dir_invalidator_map,
+                )
+            });
+        }
+
         spawn_thread(move || {
             self.watch_thread(
                 rx,
@@ -194,11 +318,121 @@ impl DiskWatcher {
         Ok(())
     }
 
+    /// Restricts watching to `dirs`, instead of the root or all directories
+    /// read so far, e. g. to the directories which contain the modules of an
+    /// app. Directories are watched non-recursively. Calling it again updates
+    /// the scope, directories which aren't part of it anymore are unwatched.
+    pub(crate) fn set_scope(&self, root_path: &Path, dirs: HashSet<PathBuf>) {
+        let mut watcher = self.watcher.lock().unwrap();
+        let mut scope = self.scope.lock().unwrap();
+        if let Some(watcher) = watcher.as_mut() {
+            let watcher = watcher.watcher();
+            // Unwatching fails for paths which aren't watched or don't exist
+            // anymore, which is fine.
+            match &*scope {
+                Some(previous) => {
+                    for dir_path in previous.difference(&dirs) {
+                        let _ = watcher.unwatch(dir_path);
+                    }
+                }
+                None => {
+                    // The root is watched recursively on macOS and Windows
+                    let _ = watcher.unwatch(root_path);
+                    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+                    for dir_path in self.watching.iter() {
+                        if !dirs.contains(&*dir_path) {
+                            let _ = watcher.unwatch(&dir_path);
+                        }
+                    }
+                }
+            }
+            for dir_path in &dirs {
+                if scope
+                    .as_ref()
+                    .map_or(true, |previous| !previous.contains(dir_path))
+                {
+                    let _ = watcher.watch(dir_path, RecursiveMode::NonRecursive);
+                }
+            }
+        }
+        #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+        self.watching.retain(|dir_path| dirs.contains(dir_path));
+        *scope = Some(dirs);
+    }
+
     pub(crate) fn stop_watching(&self) {
         if let Some(watcher) = self.watcher.lock().unwrap().take() {
             drop(watcher);
             // thread will detect the stop because the channel is disconnected
         }
+        self.stop_polling.lock().unwrap().take();
+    }
+
+    /// Internal thread that polls the directories which couldn't be watched
+    /// and invalidates the cache for changed entries. Changes are detected
+    /// by comparing hashes of the metadata of the entries.
+    ///
+    /// Should only be called once from `start_watching`.
+    fn poll_thread(
+        &self,
+        stop: Receiver<()>,
+        report_invalidation_reason: Option<(String, PathBuf)>,
+        invalidation_lock: Arc<RwLock<()>>,
+        invalidator_map: Arc<InvalidatorMap>,
+        dir_invalidator_map: Arc<InvalidatorMap>,
+    ) {
+        while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(POLL_INTERVAL) {
+            let mut changed_paths = Vec::new();
+            let mut changed_dirs = Vec::new();
+            for (dir_path, snapshot) in self.polled.lock().unwrap().iter_mut() {
+                let new_snapshot = snapshot_directory(dir_path);
+                if new_snapshot == *snapshot {
+                    continue;
+                }
+                let mut entries_changed = false;
+                for (name, hash) in snapshot.iter() {
+                    match new_snapshot.get(name) {
+                        Some(new_hash) if new_hash == hash => {}
+                        Some(_) => changed_paths.push(dir_path.join(name)),
+                        None => {
+                            changed_paths.push(dir_path.join(name));
+                            entries_changed = true;
+                        }
+                    }
+                }
+                for name in new_snapshot.keys() {
+                    if !snapshot.contains_key(name) {
+                        changed_paths.push(dir_path.join(name));
+                        entries_changed = true;
+                    }
+                }
+                if entries_changed {
+                    changed_dirs.push(dir_path.clone());
+                }
+                *snapshot = new_snapshot;
+            }
+            if changed_paths.is_empty() {
+                continue;
+            }
+
+            let _lock = invalidation_lock.blocking_write();
+            invalidate_path_and_children_execute(
+                &report_invalidation_reason,
+                &mut invalidator_map.lock().unwrap(),
+                changed_paths.iter().cloned(),
+            );
+            let mut dir_invalidator_map = dir_invalidator_map.lock().unwrap();
+            invalidate_path_and_children_execute(
+                &report_invalidation_reason,
+                &mut dir_invalidator_map,
+                changed_paths.into_iter(),
+            );
+            invalidate_path(
+                &report_invalidation_reason,
+                &mut dir_invalidator_map,
+                changed_dirs.into_iter(),
+            );
+        }
     }
 
     /// Internal thread that processes the events from the watcher
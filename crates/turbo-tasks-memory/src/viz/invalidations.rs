@@ -0,0 +1,59 @@
+use std::fmt::Write;
+
+use turbo_tasks::{InvalidationCause, InvalidationStep, StatsType};
+
+use super::*;
+
+pub fn wrap_html(content: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>turbo-tasks invalidations</title>
+  <style>
+    body {{ margin: 0; padding: 0 1rem; font-family: monospace; }}
+    ol {{ margin-bottom: 2rem; }}
+    li {{ padding: 0.2rem 0; }}
+    .cause {{ opacity: 0.6; }}
+  </style>
+</head>
+<body>
+  {content}
+</body>
+</html>"#
+    )
+}
+
+/// Renders the invalidation chains of tasks, each one as returned by
+/// [TurboTasks::explain_recomputation](turbo_tasks::TurboTasks::explain_recomputation).
+pub fn create_chains(chains: &[Vec<InvalidationStep>], stats_type: StatsType) -> String {
+    let mut out = String::new();
+    if !stats_type.is_full() {
+        out += "<p>Invalidations are only recorded with full stats. Run with --full-stats to \
+                enable it.</p>";
+    }
+    if chains.is_empty() {
+        out += "<p>No recomputed tasks match the query.</p>";
+    }
+    for chain in chains {
+        out += "<ol>";
+        for step in chain {
+            let cause = match &step.cause {
+                InvalidationCause::Task(_) => "recomputed because of".to_string(),
+                InvalidationCause::Reason(reason) => {
+                    format!("invalidated by {}", escape_html(reason))
+                }
+                InvalidationCause::Unknown => "invalidated without a reason".to_string(),
+            };
+            write!(
+                out,
+                "<li>{} <span class=\"cause\">{cause}</span></li>",
+                escape_html(&step.description)
+            )
+            .unwrap();
+        }
+        out += "</ol>";
+    }
+    out
+}
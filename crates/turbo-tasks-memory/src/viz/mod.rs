@@ -1,4 +1,5 @@
 pub mod graph;
+pub mod invalidations;
 pub mod table;
 
 use std::{
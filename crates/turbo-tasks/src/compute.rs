@@ -0,0 +1,44 @@
+use std::{
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+    thread::available_parallelism,
+};
+
+use once_cell::sync::Lazy;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use tokio::sync::oneshot;
+
+/// Overrides the number of threads of the compute pool. Defaults to the
+/// number of available cores.
+pub const COMPUTE_THREADS_ENV: &str = "TURBO_TASKS_COMPUTE_THREADS";
+
+static COMPUTE_POOL: Lazy<ThreadPool> = Lazy::new(|| {
+    let threads = std::env::var(COMPUTE_THREADS_ENV)
+        .ok()
+        .and_then(|threads| threads.parse().ok())
+        .unwrap_or_else(|| available_parallelism().map_or(1, |n| n.get()));
+    ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|i| format!("turbo-tasks-compute-{i}"))
+        .build()
+        .expect("failed to create the compute thread pool")
+});
+
+/// Runs CPU-heavy work (e. g. parsing) on a dedicated compute thread pool and
+/// waits for its result.
+///
+/// The async executor threads stay free for I/O and small tasks meanwhile, so
+/// a cold build with many large modules doesn't starve them. `f` is not run in
+/// the context of the current task, so it can't call or read turbo-tasks
+/// functions or emit collectibles. Panics are resumed in the awaiting task.
+pub async fn spawn_compute<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    let (sender, receiver) = oneshot::channel();
+    let span = tracing::Span::current();
+    COMPUTE_POOL.spawn(move || {
+        let _span = span.entered();
+        let _ = sender.send(catch_unwind(AssertUnwindSafe(f)));
+    });
+    match receiver.await.expect("compute job was dropped") {
+        Ok(result) => result,
+        Err(panic) => resume_unwind(panic),
+    }
+}
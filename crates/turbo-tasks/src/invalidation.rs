@@ -161,6 +161,17 @@ impl InvalidationReasonSet {
     pub fn len(&self) -> usize {
         self.map.len()
     }
+
+    /// Iterates over all reasons, including the ones that were merged by kind.
+    pub fn iter(&self) -> impl Iterator<Item = &StaticOrArc<dyn InvalidationReason>> {
+        self.map.values().flat_map(|entry| {
+            let (single, multiple) = match entry {
+                MapEntry::Single { reason } => (Some(reason), None),
+                MapEntry::Multiple { reasons } => (None, Some(reasons)),
+            };
+            single.into_iter().chain(multiple.into_iter().flatten())
+        })
+    }
 }
 
 impl Display for InvalidationReasonSet {
@@ -37,6 +37,7 @@
 pub mod backend;
 mod collectibles;
 mod completion;
+mod compute;
 pub mod debug;
 mod display;
 pub mod duration_span;
@@ -76,6 +77,7 @@ pub use anyhow::{Error, Result};
 use auto_hash_map::AutoSet;
 pub use collectibles::CollectiblesSource;
 pub use completion::{Completion, Completions};
+pub use compute::{spawn_compute, COMPUTE_THREADS_ENV};
 pub use display::ValueToString;
 pub use id::{
     with_task_id_mapping, without_task_id_mapping, FunctionId, IdMapping, TaskId, TraitTypeId,
@@ -89,8 +91,8 @@ pub use keyed_cell::{global_keyed_cell, keyed_cell};
 pub use manager::{
     dynamic_call, emit, get_invalidator, mark_finished, mark_stateful, run_once,
     run_once_with_reason, spawn_blocking, spawn_thread, trait_call, turbo_tasks, CurrentCellRef,
-    Invalidator, StatsType, TaskIdProvider, TurboTasks, TurboTasksApi, TurboTasksBackendApi,
-    TurboTasksCallApi, Unused, UpdateInfo,
+    InvalidationCause, InvalidationStep, Invalidator, StatsType, TaskIdProvider, TurboTasks,
+    TurboTasksApi, TurboTasksBackendApi, TurboTasksCallApi, Unused, UpdateInfo,
 };
 pub use native_function::NativeFunction;
 use nohash_hasher::BuildNoHashHasher;
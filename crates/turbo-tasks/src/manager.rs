@@ -1,6 +1,8 @@
 use std::{
     borrow::Cow,
     cell::RefCell,
+    collections::{HashMap, HashSet},
+    fmt::Display,
     future::Future,
     hash::Hash,
     mem::take,
@@ -18,6 +20,7 @@ use anyhow::{anyhow, Result};
 use auto_hash_map::AutoMap;
 use futures::FutureExt;
 use nohash_hasher::BuildNoHashHasher;
+use once_cell::sync::OnceCell;
 use serde::{de::Visitor, Deserialize, Serialize};
 use tokio::{runtime::Handle, select, task_local};
 use tracing::{info_span, instrument, trace_span, Instrument, Level};
@@ -276,9 +279,52 @@ pub struct TurboTasks<B: Backend + 'static> {
     // NOTE(alexkirsz) We use an atomic bool instead of a lock around `StatsType` to avoid the
     // locking overhead.
     enable_full_stats: AtomicBool,
+    /// The cause of the last invalidation of each task. Only recorded with
+    /// [StatsType::Full].
+    invalidation_causes: Mutex<HashMap<TaskId, InvalidationCause>>,
+    /// Called in the context of each task that panicked, see
+    /// [TurboTasks::set_task_panic_hook].
+    task_panic_hook: OnceCell<TaskPanicHook>,
+    /// Tasks that panicked since the last
+    /// [TurboTasks::restart_panicked_tasks].
+    panicked_tasks: Mutex<HashSet<TaskId>>,
     program_start: Instant,
 }
 
+/// Receives the description of the panicked task and the panic message.
+type TaskPanicHook = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+/// The reason of invalidations by [TurboTasks::restart_panicked_tasks].
+#[derive(PartialEq, Eq, Hash)]
+struct PanicRestart;
+
+impl InvalidationReason for PanicRestart {}
+
+impl Display for PanicRestart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "restart after panic")
+    }
+}
+
+/// What caused a task to be invalidated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvalidationCause {
+    /// A cell read by the task was updated by another task.
+    Task(TaskId),
+    /// The task was invalidated from outside, e. g. by a file watcher.
+    Reason(String),
+    /// The task was invalidated without a reason.
+    Unknown,
+}
+
+/// A step in the chain returned by [TurboTasks::explain_recomputation].
+#[derive(Debug, Clone)]
+pub struct InvalidationStep {
+    pub task: TaskId,
+    pub description: String,
+    pub cause: InvalidationCause,
+}
+
 #[derive(Default)]
 struct CurrentTaskState {
     /// Affected [Task]s, that are tracked during task execution
@@ -327,6 +373,9 @@ impl<B: Backend + 'static> TurboTasks<B> {
             event_foreground: Event::new(|| "TurboTasks::event_foreground".to_string()),
             event_background: Event::new(|| "TurboTasks::event_background".to_string()),
             enable_full_stats: AtomicBool::new(false),
+            invalidation_causes: Default::default(),
+            task_panic_hook: OnceCell::new(),
+            panicked_tasks: Default::default(),
             program_start: Instant::now(),
         });
         this.backend.startup(&*this);
@@ -487,6 +536,9 @@ impl<B: Backend + 'static> TurboTasks<B> {
                                         Err(_) => None,
                                     },
                                 });
+                                if let Err(message) = &result {
+                                    this.task_panicked(task_id, message.as_deref());
+                                }
                                 this.backend.task_execution_result(task_id, result, &*this);
                                 let stateful = this.finish_current_task_state();
                                 this.backend.task_execution_completed(
@@ -761,7 +813,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
                     }
                     let this2 = this.clone();
                     if !this.stopped.load(Ordering::Acquire) {
-                        func(this).await;
+                        catch_job_panic(func(this)).await;
                     }
                     if this2
                         .currently_scheduled_background_jobs
@@ -789,7 +841,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
             TURBO_TASKS
                 .scope(this.clone(), async move {
                     if !this.stopped.load(Ordering::Acquire) {
-                        func(this.clone()).await;
+                        catch_job_panic(func(this.clone())).await;
                     }
                     this.finish_foreground_job();
                 })
@@ -805,6 +857,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
             } = &mut *cell.borrow_mut();
             let tasks = take(tasks_to_notify);
             if !tasks.is_empty() {
+                self.record_invalidation_cause(&tasks, current_task_cause);
                 self.backend.invalidate_tasks(&tasks, self);
             }
             *stateful
@@ -814,6 +867,106 @@ impl<B: Backend + 'static> TurboTasks<B> {
     pub fn backend(&self) -> &B {
         &self.backend
     }
+
+    fn record_invalidation_cause<'a>(
+        &self,
+        tasks: impl IntoIterator<Item = &'a TaskId>,
+        cause: impl FnOnce() -> InvalidationCause,
+    ) {
+        if !self.enable_full_stats.load(Ordering::Acquire) {
+            return;
+        }
+        let cause = cause();
+        let mut causes = self.invalidation_causes.lock().unwrap();
+        for &task in tasks {
+            causes.insert(task, cause.clone());
+        }
+    }
+
+    /// Returns the chain of invalidations that caused the last recomputation
+    /// of `task`, e. g. changed file → parsed module → chunk → render. The
+    /// chain starts with `task` and ends with the step that was invalidated
+    /// from outside. It's empty when `task` was never invalidated.
+    ///
+    /// Invalidations are only recorded with [StatsType::Full].
+    pub fn explain_recomputation(&self, task: TaskId) -> Vec<InvalidationStep> {
+        let causes = self.invalidation_causes.lock().unwrap();
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = task;
+        while visited.insert(current) {
+            let Some(cause) = causes.get(&current) else {
+                break;
+            };
+            chain.push(InvalidationStep {
+                task: current,
+                description: self.backend.get_task_description(current),
+                cause: cause.clone(),
+            });
+            match cause {
+                InvalidationCause::Task(parent) => current = *parent,
+                InvalidationCause::Reason(_) | InvalidationCause::Unknown => break,
+            }
+        }
+        chain
+    }
+
+    /// Sets a hook that is called with the task description and the panic
+    /// message when a task panics. Panics are caught at
+    /// task boundaries and turned into a failed task output, so only the
+    /// tasks depending on the panicked task fail.
+    ///
+    /// The hook is called in the context of the panicked task, so it can emit
+    /// collectibles (e. g. an issue) that are reported with the failed
+    /// output. It can only be set once, returns `false` when a hook was
+    /// already set.
+    pub fn set_task_panic_hook(&self, hook: impl Fn(&str, &str) + Send + Sync + 'static) -> bool {
+        self.task_panic_hook.set(Box::new(hook)).is_ok()
+    }
+
+    fn task_panicked(&self, task: TaskId, message: Option<&str>) {
+        self.panicked_tasks.lock().unwrap().insert(task);
+        if let Some(hook) = self.task_panic_hook.get() {
+            let description = self.backend.get_task_description(task);
+            let message = message.unwrap_or("unknown panic");
+            // A panicking hook must not take down the worker either
+            let _ = std::panic::catch_unwind(AssertUnwindSafe(|| hook(&description, message)));
+        }
+    }
+
+    /// Invalidates all tasks that panicked since the last call, so the
+    /// subgraphs poisoned by the panics are recomputed. Returns the number of
+    /// restarted tasks.
+    ///
+    /// Panics might be caused by a corrupted state of a subsystem (e. g. a
+    /// global cache), so the embedder should call this after resetting it, or
+    /// when the inputs changed, e. g. after a file change.
+    pub fn restart_panicked_tasks(&self) -> usize {
+        let tasks = take(&mut *self.panicked_tasks.lock().unwrap());
+        for &task in &tasks {
+            self.invalidate_with_reason(task, StaticOrArc::Static(&PanicRestart));
+        }
+        tasks.len()
+    }
+
+    /// Returns the number of tasks that panicked since the last
+    /// [TurboTasks::restart_panicked_tasks].
+    pub fn panicked_task_count(&self) -> usize {
+        self.panicked_tasks.lock().unwrap().len()
+    }
+}
+
+/// Runs a background or foreground job, catching a panic so the job counters
+/// are still updated and waiting for the jobs doesn't hang.
+async fn catch_job_panic(job: impl Future<Output = ()>) {
+    if let Err(panic) = AssertUnwindSafe(job).catch_unwind().await {
+        let message = panic
+            .downcast_ref::<String>()
+            .map(|s| s.as_str())
+            .or_else(|| panic.downcast_ref::<&'static str>().copied())
+            .unwrap_or("unknown panic");
+        tracing::error!("turbo-tasks job panicked: {message}");
+    }
 }
 
 impl<B: Backend + 'static> TurboTasksCallApi for TurboTasks<B> {
@@ -881,11 +1034,13 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
 
     #[instrument(level = Level::INFO, skip_all, name = "invalidate")]
     fn invalidate(&self, task: TaskId) {
+        self.record_invalidation_cause([&task], || InvalidationCause::Unknown);
         self.backend.invalidate_task(task, self);
     }
 
     #[instrument(level = Level::INFO, skip_all, name = "invalidate", fields(name = display(&reason)))]
     fn invalidate_with_reason(&self, task: TaskId, reason: StaticOrArc<dyn InvalidationReason>) {
+        self.record_invalidation_cause([&task], || InvalidationCause::Reason(reason.to_string()));
         {
             let (_, reason_set) = &mut *self.aggregated_update.lock().unwrap();
             reason_set.insert(reason);
@@ -902,6 +1057,7 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
             if tasks.is_empty() {
                 return;
             }
+            self.record_invalidation_cause(&tasks, current_task_cause);
             self.backend.invalidate_tasks(&tasks, self);
         });
     }
@@ -1099,6 +1255,7 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
         });
         if result.is_err() {
             let _guard = trace_span!("schedule_notify_tasks", count = tasks.len()).entered();
+            self.record_invalidation_cause(tasks, || InvalidationCause::Unknown);
             self.backend.invalidate_tasks(tasks, self);
         }
     }
@@ -1114,6 +1271,7 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
         });
         if result.is_err() {
             let _guard = trace_span!("schedule_notify_tasks_set", count = tasks.len()).entered();
+            self.record_invalidation_cause(tasks, || InvalidationCause::Unknown);
             self.backend.invalidate_tasks_set(tasks, self);
         };
     }
@@ -1368,6 +1526,14 @@ pub fn current_task_for_testing() -> TaskId {
     CURRENT_TASK_ID.with(|id| *id)
 }
 
+/// The task that is currently executing as the cause of invalidating other
+/// tasks.
+fn current_task_cause() -> InvalidationCause {
+    CURRENT_TASK_ID
+        .try_with(|id| InvalidationCause::Task(*id))
+        .unwrap_or(InvalidationCause::Unknown)
+}
+
 /// Get an [Invalidator] that can be used to invalidate the current [Task]
 /// based on external events.
 pub fn get_invalidator() -> Invalidator {
@@ -1,11 +1,18 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+pub use self::{
+    epoch::CacheEpoch,
+    journal::{CacheRecovery, CacheState, JournaledPersistedGraph},
+};
 use crate::{
     backend::{CellContent, PersistentTaskType},
     CellId, RawVc, TaskId,
 };
 
+mod epoch;
+mod journal;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub enum TaskCell {
     Content(CellContent),
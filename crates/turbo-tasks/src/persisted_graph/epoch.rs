@@ -0,0 +1,45 @@
+use turbo_tasks_hash::{encode_hex, DeterministicHash, Xxh3Hash64Hasher};
+
+/// Identifies the compiler behavior a persistent cache was written with.
+///
+/// Task results are only valid as long as the code and options that computed
+/// them didn't change. Every component that influences them (the identity of
+/// the build, enabled transforms, defines, hashes of plugin binaries) should
+/// be mixed into the epoch. Crate versions aren't enough, as they don't change
+/// between builds of unpublished code. A cache written with a different epoch
+/// is discarded when it is opened, which is equivalent to mixing the epoch into
+/// the key of every task.
+pub struct CacheEpoch {
+    hasher: Xxh3Hash64Hasher,
+}
+
+impl CacheEpoch {
+    /// Creates an epoch that already includes the version of turbo-tasks. The
+    /// caller has to mix in the identity of its build, e. g. a hash embedded
+    /// by its build script.
+    pub fn new() -> Self {
+        CacheEpoch {
+            hasher: Xxh3Hash64Hasher::new(),
+        }
+        .component("turbo-tasks", env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Mixes a named component into the epoch, e.g.
+    /// `.component("my-crate", env!("CARGO_PKG_VERSION"))`.
+    pub fn component(mut self, name: &str, value: impl DeterministicHash) -> Self {
+        self.hasher.write_value(name);
+        self.hasher.write_value(value);
+        self
+    }
+
+    /// Returns the epoch as hex string.
+    pub fn finish(&self) -> String {
+        encode_hex(self.hasher.finish())
+    }
+}
+
+impl Default for CacheEpoch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
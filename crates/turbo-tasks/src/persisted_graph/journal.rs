@@ -0,0 +1,439 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::{Context, Result};
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64};
+
+use super::{
+    epoch::CacheEpoch, ActivateResult, DeactivateResult, PersistResult, PersistTaskState,
+    PersistedGraph, PersistedGraphApi, ReadTaskState, TaskData,
+};
+use crate::{backend::PersistentTaskType, RawVc, TaskId};
+
+const JOURNAL_FILE: &str = "journal";
+const GRAPH_DIRECTORY: &str = "graph";
+
+/// The state of a persistent cache when it's opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheState {
+    /// The last session that wrote to the cache completed.
+    Clean,
+    /// A session started writing to the cache, but never finished, e.g.
+    /// because the process crashed.
+    Interrupted,
+    /// The journal itself is damaged.
+    Corrupt(String),
+    /// The cache was written with a different [CacheEpoch].
+    Outdated,
+}
+
+/// What happened to the cache when it was opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheRecovery {
+    /// The cache is used as is.
+    Restored,
+    /// The cache was discarded because of its [CacheState].
+    Discarded(CacheState),
+    /// The cache was discarded on request.
+    Repaired,
+}
+
+/// A write-ahead journal for a persistent cache directory.
+///
+/// Before a session writes to the cache for the first time, a `begin` record
+/// is synced to disk. A `commit` record follows once the cache has been
+/// flushed on stop. Every record carries its own checksum, so torn writes are
+/// detected as well. A cache whose journal doesn't end with a valid `commit`
+/// might contain partially written task results and is discarded when it is
+/// opened, which degrades to a cold build instead of restoring corrupt state.
+struct Journal {
+    file: Mutex<File>,
+    session: usize,
+    /// Whether the `begin` record of this session has been written.
+    started: AtomicBool,
+    /// Number of write operations in this session.
+    writes: AtomicUsize,
+}
+
+impl Journal {
+    fn read_state(path: &Path, epoch: &str) -> Result<(CacheState, usize)> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((CacheState::Clean, 0));
+            }
+            Err(e) => return Err(e).context("opening the cache journal"),
+        };
+        let mut state = CacheState::Clean;
+        let mut sessions = 0;
+        let mut has_epoch = false;
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let Ok(line) = line else {
+                return Ok((
+                    CacheState::Corrupt(format!("line {} is not valid UTF-8", index + 1)),
+                    sessions,
+                ));
+            };
+            let Some((record, checksum)) = line.rsplit_once(' ') else {
+                return Ok((
+                    CacheState::Corrupt(format!("line {} has no checksum", index + 1)),
+                    sessions,
+                ));
+            };
+            if encode_hex(hash_xxh3_hash64(record)) != checksum {
+                return Ok((
+                    CacheState::Corrupt(format!("checksum mismatch in line {}", index + 1)),
+                    sessions,
+                ));
+            }
+            let mut parts = record.split(' ');
+            match parts.next() {
+                Some("epoch") => {
+                    if parts.next() != Some(epoch) {
+                        return Ok((CacheState::Outdated, sessions));
+                    }
+                    has_epoch = true;
+                }
+                Some("begin") => {
+                    sessions += 1;
+                    state = CacheState::Interrupted;
+                }
+                Some("commit") => state = CacheState::Clean,
+                _ => {
+                    return Ok((
+                        CacheState::Corrupt(format!("unknown record in line {}", index + 1)),
+                        sessions,
+                    ));
+                }
+            }
+        }
+        if !has_epoch && sessions > 0 {
+            // Written before epochs were recorded
+            return Ok((CacheState::Outdated, sessions));
+        }
+        Ok((state, sessions))
+    }
+
+    fn open(path: &Path, previous_sessions: usize, epoch: &str) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context("opening the cache journal")?;
+        if file.metadata()?.len() == 0 {
+            Self::append(&mut file, &format!("epoch {epoch}"))
+                .context("writing the epoch to the cache journal")?;
+        }
+        Ok(Journal {
+            file: Mutex::new(file),
+            session: previous_sessions + 1,
+            started: AtomicBool::new(false),
+            writes: AtomicUsize::new(0),
+        })
+    }
+
+    fn append(file: &mut File, record: &str) -> Result<()> {
+        let checksum = encode_hex(hash_xxh3_hash64(record));
+        writeln!(file, "{record} {checksum}")?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Must be called before every write to the cache.
+    fn before_write(&self) -> Result<()> {
+        if !self.started.load(Ordering::Acquire) {
+            let mut file = self.file.lock().unwrap();
+            // Other writes wait for the lock, so they can't reach the cache
+            // before the begin record is on disk
+            if !self.started.load(Ordering::Acquire) {
+                Self::append(&mut file, &format!("begin {}", self.session))
+                    .context("writing the begin record to the cache journal")?;
+                self.started.store(true, Ordering::Release);
+            }
+        }
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Must be called after the cache has been flushed.
+    fn commit(&self) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if !self.started.swap(false, Ordering::AcqRel) {
+            // Nothing was written in this session
+            return Ok(());
+        }
+        let writes = self.writes.swap(0, Ordering::Relaxed);
+        Self::append(&mut file, &format!("commit {} {writes}", self.session))
+            .context("writing the commit record to the cache journal")
+    }
+}
+
+/// Wraps a [PersistedGraph] with a write-ahead [Journal], so that a crash
+/// while writing the cache leads to a cold build on the next start.
+pub struct JournaledPersistedGraph<G: PersistedGraph> {
+    graph: G,
+    journal: Journal,
+}
+
+impl<G: PersistedGraph> JournaledPersistedGraph<G> {
+    /// Opens the cache in `directory`. The graph is created by `create` in a
+    /// subdirectory. The cache is discarded before that when it's not in a
+    /// clean state, when it was written with a different `epoch` or when
+    /// `repair` is set.
+    pub fn open(
+        directory: &Path,
+        epoch: &CacheEpoch,
+        repair: bool,
+        create: impl FnOnce(&Path) -> Result<G>,
+    ) -> Result<(Self, CacheRecovery)> {
+        let epoch = epoch.finish();
+        let journal_path = directory.join(JOURNAL_FILE);
+        let (state, sessions) = Journal::read_state(&journal_path, &epoch)?;
+        let recovery = if repair {
+            CacheRecovery::Repaired
+        } else if state != CacheState::Clean {
+            CacheRecovery::Discarded(state)
+        } else {
+            CacheRecovery::Restored
+        };
+        let sessions = if recovery == CacheRecovery::Restored {
+            sessions
+        } else {
+            discard(directory)?;
+            0
+        };
+        fs::create_dir_all(directory).context("creating the cache directory")?;
+        let graph = create(&directory.join(GRAPH_DIRECTORY))?;
+        let journal = Journal::open(&journal_path, sessions, &epoch)?;
+        Ok((JournaledPersistedGraph { graph, journal }, recovery))
+    }
+}
+
+/// Removes the journal and the graph of the cache in `directory`. Other files
+/// in the directory are kept, it can be a directory chosen by the user.
+fn discard(directory: &Path) -> Result<()> {
+    let removed = [
+        fs::remove_file(directory.join(JOURNAL_FILE)),
+        fs::remove_dir_all(directory.join(GRAPH_DIRECTORY)),
+    ];
+    for result in removed {
+        match result {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e)
+                    .with_context(|| format!("discarding the cache in {}", directory.display()));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+impl<G: PersistedGraph> PersistedGraph for JournaledPersistedGraph<G> {
+    fn read(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<(TaskData, ReadTaskState)>> {
+        self.graph.read(task, api)
+    }
+
+    fn lookup(
+        &self,
+        partial_task_type: &PersistentTaskType,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<bool> {
+        self.graph.lookup(partial_task_type, api)
+    }
+
+    fn lookup_one(
+        &self,
+        task_type: &PersistentTaskType,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<TaskId>> {
+        self.graph.lookup_one(task_type, api)
+    }
+
+    fn is_persisted(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        self.graph.is_persisted(task, api)
+    }
+
+    fn persist(
+        &self,
+        task: TaskId,
+        data: TaskData,
+        state: PersistTaskState,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<PersistResult>> {
+        self.journal.before_write()?;
+        self.graph.persist(task, data, state, api)
+    }
+
+    fn activate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<ActivateResult>> {
+        self.journal.before_write()?;
+        self.graph.activate_when_needed(task, api)
+    }
+
+    fn deactivate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<DeactivateResult>> {
+        self.journal.before_write()?;
+        self.graph.deactivate_when_needed(task, api)
+    }
+
+    fn set_externally_active(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        self.journal.before_write()?;
+        self.graph.set_externally_active(task, api)
+    }
+
+    fn unset_externally_active(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        self.journal.before_write()?;
+        self.graph.unset_externally_active(task, api)
+    }
+
+    fn remove_outdated_externally_active(
+        &self,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Vec<TaskId>> {
+        self.journal.before_write()?;
+        self.graph.remove_outdated_externally_active(api)
+    }
+
+    fn make_dirty(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        self.journal.before_write()?;
+        self.graph.make_dirty(task, api)
+    }
+
+    fn make_clean(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<()> {
+        self.journal.before_write()?;
+        self.graph.make_clean(task, api)
+    }
+
+    fn make_dependent_dirty(&self, vc: RawVc, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        self.journal.before_write()?;
+        self.graph.make_dependent_dirty(vc, api)
+    }
+
+    fn get_active_external_tasks(&self, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        self.graph.get_active_external_tasks(api)
+    }
+
+    fn get_dirty_active_tasks(&self, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        self.graph.get_dirty_active_tasks(api)
+    }
+
+    fn get_pending_active_update(
+        &self,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<(Vec<TaskId>, Vec<TaskId>)> {
+        self.graph.get_pending_active_update(api)
+    }
+
+    fn stop(&self, api: &dyn PersistedGraphApi) -> Result<()> {
+        self.graph.stop(api)?;
+        self.journal.commit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("turbo-tasks-journal-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn detects_interrupted_sessions() -> Result<()> {
+        let dir = temp_dir("interrupted");
+        let (graph, recovery) =
+            JournaledPersistedGraph::open(&dir, &CacheEpoch::new(), false, |_| Ok(()))?;
+        assert_eq!(recovery, CacheRecovery::Restored);
+        graph.journal.before_write()?;
+        drop(graph);
+
+        let (graph, recovery) =
+            JournaledPersistedGraph::open(&dir, &CacheEpoch::new(), false, |_| Ok(()))?;
+        assert_eq!(recovery, CacheRecovery::Discarded(CacheState::Interrupted));
+        graph.journal.before_write()?;
+        graph.journal.commit()?;
+        drop(graph);
+
+        let (_, recovery) =
+            JournaledPersistedGraph::open(&dir, &CacheEpoch::new(), false, |_| Ok(()))?;
+        assert_eq!(recovery, CacheRecovery::Restored);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn detects_corrupt_journals() -> Result<()> {
+        let dir = temp_dir("corrupt");
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join(JOURNAL_FILE), "begin 1 0000000000000000\n")?;
+        let (_, recovery) =
+            JournaledPersistedGraph::open(&dir, &CacheEpoch::new(), false, |_| Ok(()))?;
+        assert!(matches!(
+            recovery,
+            CacheRecovery::Discarded(CacheState::Corrupt(_))
+        ));
+
+        let (_, recovery) =
+            JournaledPersistedGraph::open(&dir, &CacheEpoch::new(), true, |_| Ok(()))?;
+        assert_eq!(recovery, CacheRecovery::Repaired);
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn discards_outdated_caches() -> Result<()> {
+        let dir = temp_dir("outdated");
+        let epoch = CacheEpoch::new().component("transforms", "emotion");
+        let (graph, _) = JournaledPersistedGraph::open(&dir, &epoch, false, |_| Ok(()))?;
+        graph.journal.before_write()?;
+        graph.journal.commit()?;
+        drop(graph);
+
+        let (_, recovery) = JournaledPersistedGraph::open(&dir, &epoch, false, |_| Ok(()))?;
+        assert_eq!(recovery, CacheRecovery::Restored);
+
+        let epoch = CacheEpoch::new().component("transforms", "styled-components");
+        let (_, recovery) = JournaledPersistedGraph::open(&dir, &epoch, false, |_| Ok(()))?;
+        assert_eq!(recovery, CacheRecovery::Discarded(CacheState::Outdated));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn discarding_keeps_other_files() -> Result<()> {
+        let dir = temp_dir("other-files");
+        fs::create_dir_all(dir.join(GRAPH_DIRECTORY))?;
+        fs::write(dir.join(GRAPH_DIRECTORY).join("data"), "")?;
+        fs::write(dir.join("other"), "")?;
+        fs::write(dir.join(JOURNAL_FILE), "begin 1 0000000000000000\n")?;
+        let (_, recovery) =
+            JournaledPersistedGraph::open(&dir, &CacheEpoch::new(), false, |_| Ok(()))?;
+        assert!(matches!(recovery, CacheRecovery::Discarded(_)));
+        assert!(dir.join("other").exists());
+        assert!(!dir.join(GRAPH_DIRECTORY).join("data").exists());
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+// The server of a standalone build, started with `node server.js` in the
+// output directory. It serves the emitted files, runs the middleware and
+// renders pages on the server with their render entries, as described by
+// `standalone-manifest.json` next to it.
+//
+// `PORT` and `HOSTNAME` configure where it listens.
+
+const fs = require("fs");
+const http = require("http");
+const path = require("path");
+const { Readable } = require("stream");
+
+const manifest = require("./standalone-manifest.json");
+
+const port = parseInt(process.env.PORT || "3000", 10);
+const hostname = process.env.HOSTNAME || "0.0.0.0";
+
+const CONTENT_TYPES = {
+  ".css": "text/css; charset=utf-8",
+  ".gif": "image/gif",
+  ".html": "text/html; charset=utf-8",
+  ".ico": "image/x-icon",
+  ".jpeg": "image/jpeg",
+  ".jpg": "image/jpeg",
+  ".js": "application/javascript; charset=utf-8",
+  ".json": "application/json; charset=utf-8",
+  ".map": "application/json; charset=utf-8",
+  ".mjs": "application/javascript; charset=utf-8",
+  ".png": "image/png",
+  ".svg": "image/svg+xml",
+  ".txt": "text/plain; charset=utf-8",
+  ".wasm": "application/wasm",
+  ".webp": "image/webp",
+  ".woff": "font/woff",
+  ".woff2": "font/woff2",
+};
+
+// The files of the server itself are not served.
+const SERVER_FILES = new Set(["server.js", "standalone-manifest.json"]);
+const RENDER_DIR = "server";
+
+const routes = manifest.routes.map((route) => ({
+  ...route,
+  regex: new RegExp(route.namedRegex),
+}));
+const notFoundRoute = routes.find((route) => route.page === "/404");
+
+const middleware =
+  manifest.middleware != null
+    ? require(path.join(__dirname, manifest.middleware))
+    : null;
+const middlewareMatchers = middlewareMatcherRegexes(middleware);
+
+function escapeHtml(text) {
+  return text
+    .replace(/&/g, "&amp;")
+    .replace(/"/g, "&quot;")
+    .replace(/</g, "&lt;")
+    .replace(/>/g, "&gt;");
+}
+
+function documentFor(files, rendered) {
+  const href = (file) => escapeHtml(`/${file}`);
+  const styles = files
+    .filter((file) => file.endsWith(".css"))
+    .map((file) => `<link rel="stylesheet" href="${href(file)}" />`);
+  const scripts = files
+    .filter((file) => file.endsWith(".js"))
+    .map((file) => `<script src="${href(file)}"></script>`);
+  // The props are read by the client to hydrate the rendered page
+  const props =
+    rendered != null
+      ? `<script id="__TURBOPACK_PROPS__" type="application/json">${JSON.stringify(
+          rendered.props
+        ).replace(/</g, "\\u003c")}</script>`
+      : "";
+  return `<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8" />
+    ${styles.join("\n    ")}
+  </head>
+  <body>
+    <div id="root">${rendered != null ? rendered.html : ""}</div>
+    ${props}
+    ${scripts.join("\n    ")}
+  </body>
+</html>
+`;
+}
+
+function send(req, res, status, headers, body) {
+  res.writeHead(status, {
+    ...headers,
+    "content-length": Buffer.byteLength(body),
+  });
+  res.end(req.method === "HEAD" ? undefined : body);
+}
+
+function sendDocument(req, res, status, files, rendered) {
+  send(
+    req,
+    res,
+    status,
+    {
+      "content-type": "text/html; charset=utf-8",
+      "cache-control": "no-cache",
+    },
+    documentFor(files, rendered)
+  );
+}
+
+function sendNotFound(req, res) {
+  send(req, res, 404, { "content-type": "text/plain" }, "Not Found");
+}
+
+// Serves the file of `pathname` in the output directory, returns whether
+// there is one.
+async function serveFile(req, res, pathname) {
+  const file = path.join(__dirname, pathname);
+  const relative = path.relative(__dirname, file);
+  if (
+    relative === "" ||
+    relative.startsWith("..") ||
+    path.isAbsolute(relative) ||
+    SERVER_FILES.has(relative) ||
+    relative.split(path.sep)[0] === RENDER_DIR
+  ) {
+    return false;
+  }
+  let stats;
+  try {
+    stats = await fs.promises.stat(file);
+  } catch {
+    return false;
+  }
+  if (!stats.isFile()) {
+    return false;
+  }
+  // Chunk URLs contain the build ID, so they never change
+  const immutable = relative.split(path.sep)[0] === manifest.buildId;
+  res.writeHead(200, {
+    "content-type":
+      CONTENT_TYPES[path.extname(file)] || "application/octet-stream",
+    "content-length": stats.size,
+    "cache-control": immutable
+      ? "public, max-age=31536000, immutable"
+      : "public, max-age=0, must-revalidate",
+  });
+  if (req.method === "HEAD") {
+    res.end();
+  } else {
+    fs.createReadStream(file).pipe(res);
+  }
+  return true;
+}
+
+// The regexes of the `config.matcher` of the middleware, e. g.
+// `/about/:path*`. Without a matcher, the middleware runs for every path.
+function middlewareMatcherRegexes(middleware) {
+  const matcher = middleware?.config?.matcher;
+  if (matcher == null) {
+    return null;
+  }
+  return (Array.isArray(matcher) ? matcher : [matcher]).map((matcher) => {
+    const source = typeof matcher === "string" ? matcher : matcher.source;
+    const pattern = source
+      .split("/")
+      .slice(1)
+      .map((segment) => {
+        const param = /^:\w+([*+?]?)$/.exec(segment);
+        if (param == null) {
+          return `/${segment.replace(/[.*+?^${}()|[\]\\]/g, "\\$&")}`;
+        }
+        switch (param[1]) {
+          case "*":
+            return "(?:/.*)?";
+          case "+":
+            return "/.+";
+          case "?":
+            return "(?:/[^/]+)?";
+          default:
+            return "/[^/]+";
+        }
+      })
+      .join("");
+    return new RegExp(`^${pattern || "/"}/?$`);
+  });
+}
+
+function webHeaders(headers) {
+  const result = new Headers();
+  for (const [name, value] of Object.entries(headers)) {
+    for (const item of Array.isArray(value) ? value : [value]) {
+      result.append(name, item);
+    }
+  }
+  return result;
+}
+
+// Runs the middleware for the request. Returns the response of the
+// middleware, or the URL to continue with and the headers to add to its
+// response.
+async function runMiddleware(req, url) {
+  const hasBody = req.method !== "GET" && req.method !== "HEAD";
+  const request = new Request(url, {
+    method: req.method,
+    headers: webHeaders(req.headers),
+    body: hasBody ? Readable.toWeb(req) : undefined,
+    duplex: hasBody ? "half" : undefined,
+  });
+  const handler = middleware.middleware ?? middleware.default;
+  const response = await handler(request, { waitUntil() {} });
+  if (response == null) {
+    return { url, headers: [] };
+  }
+  const rewrite = response.headers.get("x-middleware-rewrite");
+  if (rewrite == null && response.headers.get("x-middleware-next") == null) {
+    return { response };
+  }
+  const headers = [...response.headers].filter(
+    ([name]) => !name.startsWith("x-middleware-")
+  );
+  return {
+    url: rewrite != null ? new URL(rewrite, url) : url,
+    headers,
+  };
+}
+
+async function sendWebResponse(req, res, response) {
+  const body = Buffer.from(await response.arrayBuffer());
+  res.writeHead(response.status, [...response.headers].flat());
+  res.end(req.method === "HEAD" ? undefined : body);
+}
+
+// The params of a page, e. g. `{ slug: "hello" }` for `/blog/[slug]`.
+function routeParams(route, pathname) {
+  const groups = route.regex.exec(pathname)?.groups ?? {};
+  const params = {};
+  for (const [key, name] of Object.entries(route.routeKeys)) {
+    const value = groups[key];
+    if (value === undefined) {
+      continue;
+    }
+    params[name] = route.page.includes(`...${name}]`)
+      ? value.split("/")
+      : value;
+  }
+  return params;
+}
+
+async function renderRoute(req, res, route, url, pathname, status) {
+  if (route.render == null) {
+    sendDocument(req, res, status, route.files);
+    return;
+  }
+  const page = require(path.join(__dirname, route.render));
+  let props = {};
+  if (typeof page.getServerSideProps === "function") {
+    const result = await page.getServerSideProps({
+      req,
+      res,
+      params: routeParams(route, pathname),
+      query: Object.fromEntries(url.searchParams),
+      resolvedUrl: url.pathname + url.search,
+    });
+    if (result.redirect != null) {
+      const redirectStatus =
+        result.redirect.statusCode ?? (result.redirect.permanent ? 308 : 307);
+      send(
+        req,
+        res,
+        redirectStatus,
+        { location: result.redirect.destination },
+        ""
+      );
+      return;
+    }
+    if (result.notFound) {
+      if (notFoundRoute != null && route !== notFoundRoute) {
+        await renderRoute(req, res, notFoundRoute, url, pathname, 404);
+      } else {
+        sendNotFound(req, res);
+      }
+      return;
+    }
+    props = (await result.props) ?? {};
+  }
+  sendDocument(req, res, status, route.files, {
+    html: page.render(props),
+    props,
+  });
+}
+
+async function handle(req, res) {
+  let url;
+  let pathname;
+  try {
+    url = new URL(req.url, `http://${req.headers.host || "localhost"}`);
+    pathname = decodeURIComponent(url.pathname);
+  } catch {
+    send(req, res, 400, {}, "Bad Request");
+    return;
+  }
+
+  if (
+    middleware != null &&
+    (middlewareMatchers == null ||
+      middlewareMatchers.some((regex) => regex.test(pathname)))
+  ) {
+    const result = await runMiddleware(req, url);
+    if (result.response != null) {
+      await sendWebResponse(req, res, result.response);
+      return;
+    }
+    for (const [name, value] of result.headers) {
+      res.appendHeader(name, value);
+    }
+    if (result.url !== url) {
+      url = result.url;
+      pathname = decodeURIComponent(url.pathname);
+    }
+  }
+
+  if (req.method !== "GET" && req.method !== "HEAD") {
+    send(req, res, 405, { allow: "GET, HEAD" }, "Method Not Allowed");
+    return;
+  }
+
+  if (await serveFile(req, res, pathname)) {
+    return;
+  }
+
+  // Without pages, every path renders the entries of the app.
+  if (routes.length === 0) {
+    sendDocument(req, res, 200, manifest.fallbackFiles);
+    return;
+  }
+  const route = routes.find((route) => route.regex.test(pathname));
+  if (route != null) {
+    await renderRoute(req, res, route, url, pathname, 200);
+  } else if (notFoundRoute != null) {
+    await renderRoute(req, res, notFoundRoute, url, pathname, 404);
+  } else {
+    sendNotFound(req, res);
+  }
+}
+
+http
+  .createServer((req, res) => {
+    handle(req, res).catch((err) => {
+      console.error(err);
+      if (!res.headersSent) {
+        send(req, res, 500, {}, "Internal Server Error");
+      } else {
+        res.destroy();
+      }
+    });
+  })
+  .listen(port, hostname, () => {
+    console.log(`Listening on http://${hostname}:${port}`);
+  });
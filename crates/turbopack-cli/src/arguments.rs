@@ -6,11 +6,21 @@ use std::{
 use clap::{Args, Parser};
 use turbopack_cli_utils::issue::IssueSeverityCliOption;
 
+use crate::{
+    build::{build_id::BuildIdGenerator, strict::StrictRule},
+    environment::EntryTarget,
+    export::{i18n::LocaleDomain, manifest::Shard, output::Dedupe},
+};
+
 #[derive(Debug, Parser)]
 #[clap(author, version, about, long_about = None)]
 pub enum Arguments {
     Build(BuildArguments),
     Dev(DevArguments),
+    Compat(CompatArguments),
+    Daemon(DaemonArguments),
+    Export(ExportArguments),
+    Affected(AffectedArguments),
 }
 
 impl Arguments {
@@ -19,6 +29,10 @@ impl Arguments {
         match self {
             Arguments::Build(args) => args.common.dir.as_deref(),
             Arguments::Dev(args) => args.common.dir.as_deref(),
+            Arguments::Compat(args) => args.dir.as_deref(),
+            Arguments::Daemon(_) => None,
+            Arguments::Export(args) => args.common.dir.as_deref(),
+            Arguments::Affected(args) => args.common.dir.as_deref(),
         }
     }
 }
@@ -85,10 +99,79 @@ pub struct DevArguments {
     #[clap(long)]
     pub eager_compile: bool,
 
+    /// Only watch the directories which contain modules of the app and
+    /// config files affecting them, instead of every directory read while
+    /// resolving. Computes the whole module graph up front, like
+    /// --eager-compile.
+    #[clap(long)]
+    pub scoped_watching: bool,
+
     /// Don't open the browser automatically when the dev server has started.
     #[clap(long)]
     pub no_open: bool,
 
+    /// Listen for control commands on a Unix domain socket (or a named pipe
+    /// on Windows) at this path, e.g. to invalidate files or render routes
+    /// from scripts and editors.
+    #[clap(long, value_parser)]
+    pub control_socket: Option<PathBuf>,
+
+    /// Compile and render in a separate process, while a lightweight front
+    /// process serves HTTP. The front process stays responsive during heavy
+    /// rebuilds and restarts the compiler process when it crashes.
+    #[clap(long, conflicts_with = "backend_process")]
+    pub multi_process: bool,
+
+    /// Run as the compiler process of a `--multi-process` front process.
+    #[clap(long, hide = true)]
+    pub backend_process: bool,
+
+    /// The maximum number of concurrent requests of a route which likely
+    /// render, e.g. of a page. Further requests wait in a queue.
+    #[clap(long, value_parser, default_value_t = 4)]
+    pub max_concurrent_route_requests: usize,
+
+    /// The maximum number of requests of a route waiting in the queue.
+    /// Further requests are answered with 503 and `retry-after`.
+    #[clap(long, value_parser, default_value_t = 32)]
+    pub max_queued_route_requests: usize,
+
+    /// Listen with `SO_REUSEPORT` (Unix only), so a new dev server can start
+    /// listening on the port before this one is drained with the `drain`
+    /// control command or SIGTERM, without refusing connections.
+    #[clap(long)]
+    pub reuse_port: bool,
+
+    /// Don't add security headers (`x-content-type-options`,
+    /// `x-frame-options`, `referrer-policy`) to responses.
+    #[clap(long)]
+    pub no_security_headers: bool,
+
+    /// Add a content security policy to pages, which allows the chunks and
+    /// inline scripts of the page.
+    #[clap(long, conflicts_with = "no_security_headers")]
+    pub csp: bool,
+
+    /// Like `--csp`, but only reports violations.
+    #[clap(long, conflicts_with_all = ["no_security_headers", "csp"])]
+    pub csp_report_only: bool,
+
+    /// Require a shared token, e. g. for a preview exposed over a tunnel. The
+    /// token is accepted as `authorization: Bearer <token>` header or as
+    /// `turbopack_token` query parameter, which sets a cookie for the page.
+    #[clap(long, env = "TURBOPACK_AUTH_TOKEN")]
+    pub auth_token: Option<String>,
+
+    /// Require basic auth credentials, written as `<user>:<password>`.
+    #[clap(long, value_name = "USER:PASSWORD", env = "TURBOPACK_BASIC_AUTH")]
+    pub basic_auth: Option<String>,
+
+    /// A path accessible without `--auth-token` or `--basic-auth`, in
+    /// addition to `/healthz` and `/readyz`. A path ending with `/` excludes
+    /// all paths below it.
+    #[clap(long)]
+    pub auth_exclude: Vec<String>,
+
     // ==
     // = Inherited options from next-dev, need revisit later.
     // ==
@@ -107,4 +190,197 @@ pub struct BuildArguments {
     /// Don't minify build output.
     #[clap(long)]
     pub no_minify: bool,
+
+    /// The environment preset entries are compiled with: `development`,
+    /// `production` or `test`.
+    #[clap(long, default_value = "production")]
+    pub preset: String,
+
+    /// Compiles an entry for another target than the preset, written as
+    /// `<entry>=<target>`, e. g. `src/worker.ts=worker`. The target is one of
+    /// `browser`, `node-js`, `edge` and `worker`. Its output is written into a
+    /// directory named after the target.
+    #[clap(long = "entry-target", value_parser)]
+    pub entry_targets: Vec<EntryTarget>,
+
+    /// A JSON file describing A/B experiments, mapping experiments to arms to
+    /// module replacements. A variant of the client bundle is built for every
+    /// experiment arm.
+    #[clap(long, value_parser)]
+    pub experiments: Option<PathBuf>,
+
+    /// The build ID, embedded in chunk URLs, exposed to client code as
+    /// `process.env.TURBOPACK_PUBLIC_BUILD_ID` and written to `BUILD_ID`.
+    #[clap(long, conflicts_with = "build_id_generator")]
+    pub build_id: Option<String>,
+
+    /// How to generate the build ID when `--build-id` is not passed.
+    #[clap(long, value_enum, default_value_t)]
+    pub build_id_generator: BuildIdGenerator,
+
+    /// Write a webpack compatible `stats.json` describing the modules and
+    /// chunks of the build into the output directory, for bundle analysis
+    /// tools.
+    #[clap(long)]
+    pub stats: bool,
+
+    /// Write the `routes-manifest.json`, `prerender-manifest.json` and
+    /// `middleware-manifest.json` the Next.js production server reads into the
+    /// output directory, for entries in a `pages` directory and a `middleware`
+    /// entry.
+    #[clap(long)]
+    pub next_manifests: bool,
+
+    /// Write a `server.js` into the output directory which serves the build
+    /// with `node server.js`. It runs the `middleware` entry and renders the
+    /// entries in a `pages` directory on the server.
+    #[clap(long)]
+    pub standalone: bool,
+
+    /// Write an `asset-manifest.json` into the output directory, mapping the
+    /// logical names of the emitted assets to their fingerprinted URLs, with
+    /// their content types and sizes, e.g. for CDN configuration generators.
+    #[clap(long)]
+    pub asset_manifest: bool,
+
+    /// Store the results of webpack loaders in this directory, keyed by the
+    /// content they transformed. Later builds reuse them for unchanged files.
+    #[clap(long, value_parser)]
+    pub transform_cache_dir: Option<PathBuf>,
+
+    /// Write gzip and brotli compressed `.gz` and `.br` files next to the
+    /// emitted text files, for hosts serving precompressed files.
+    #[clap(long)]
+    pub precompress: bool,
+
+    /// Fail the build when a warning matches a rule: `warnings`,
+    /// `unresolved`, `dual-packages` or `title:<text>`. Can be passed
+    /// multiple times.
+    #[clap(long)]
+    pub strict: Vec<StrictRule>,
+
+    /// Only build the entries affected by the files changed since this git
+    /// ref, e.g. `origin/main` in CI, and write the affected entries to
+    /// `affected-entries.json`.
+    #[clap(long)]
+    pub affected_since: Option<String>,
+}
+
+/// Prints the source files affected by changed files as JSON, with the
+/// package and the entries each of them belongs to. The module graphs of the
+/// entries are resolved like in a build, but nothing is built, e.g. to select
+/// the tests to run for a change.
+#[derive(Debug, Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct AffectedArguments {
+    #[clap(flatten)]
+    pub common: CommonArguments,
+
+    /// The environment preset the module graphs are resolved with:
+    /// `development`, `production` or `test`.
+    #[clap(long, default_value = "test")]
+    pub preset: String,
+
+    /// A changed file, relative to the root directory (`--root`).
+    #[clap(long = "changed", value_parser)]
+    pub changed_files: Vec<String>,
+
+    /// Adds the files changed since this git ref, e.g. `origin/main`.
+    #[clap(long)]
+    pub since: Option<String>,
+}
+
+/// Scans a project for features that are supported natively, supported via
+/// compat layers, or unsupported, and validates the options of its
+/// next.config.
+#[derive(Debug, Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct CompatArguments {
+    /// The directory of the application.
+    /// If no directory is provided, the current directory will be used.
+    #[clap(short, long, value_parser)]
+    pub dir: Option<PathBuf>,
+
+    /// Print the report as JSON.
+    #[clap(long)]
+    pub json: bool,
+
+    /// Also read the webpack config function of next.config.js, and print the
+    /// equivalent `experimental.turbo` options for the customizations which
+    /// map cleanly, e.g. loader rules and aliases.
+    #[clap(long)]
+    pub migrate: bool,
+}
+
+/// Keeps the turbo-tasks graph in memory and runs builds sent over a control
+/// socket, so repeated builds reuse everything computed by previous builds.
+#[derive(Debug, Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct DaemonArguments {
+    /// The Unix domain socket (or named pipe on Windows) to listen on for
+    /// commands.
+    #[clap(long, value_parser)]
+    pub socket: PathBuf,
+
+    /// Enable experimental garbage collection with the provided memory limit in
+    /// MB.
+    #[clap(long)]
+    pub memory_limit: Option<usize>,
+}
+
+/// Exports routes to static files.
+#[derive(Debug, Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct ExportArguments {
+    #[clap(flatten)]
+    pub common: CommonArguments,
+
+    /// The output directory, relative to the project directory.
+    #[clap(long, value_parser, default_value = "out")]
+    pub out_dir: PathBuf,
+
+    /// A route to export. Can be passed multiple times, defaults to `/`.
+    #[clap(long = "route", value_parser)]
+    pub routes: Vec<String>,
+
+    /// A file listing routes to export, one per line.
+    #[clap(long, value_parser)]
+    pub routes_file: Option<PathBuf>,
+
+    /// Only export a part of the routes, e.g. `2/4` for the second of four
+    /// shards. Shards can run on multiple processes or machines writing to the
+    /// same output directory.
+    #[clap(long)]
+    pub shard: Option<Shard>,
+
+    /// A locale to render every page for, into its own directory tree. Can
+    /// be passed multiple times.
+    #[clap(long = "locale", value_parser)]
+    pub locales: Vec<String>,
+
+    /// Writes the tree of a locale into a directory named after a domain
+    /// instead of the locale, e.g. `de=example.de`. Can be passed multiple
+    /// times.
+    #[clap(long = "locale-domain")]
+    pub locale_domains: Vec<LocaleDomain>,
+
+    /// How many routes are rendered in parallel.
+    #[clap(long, default_value_t = 8)]
+    pub concurrency: usize,
+
+    /// Stores routes with the same content as an already exported route only
+    /// once: `hardlink` or `symlink` link the file to the first one, `map`
+    /// doesn't write it and points the route to the first file in the export
+    /// manifest.
+    #[clap(long)]
+    pub dedupe: Option<Dedupe>,
+
+    /// Also writes gzip and brotli compressed `.gz` and `.br` siblings of
+    /// text files, for static hosts serving precompressed files.
+    #[clap(long)]
+    pub precompress: bool,
+
+    /// Export all routes, instead of resuming a previous export.
+    #[clap(long)]
+    pub force: bool,
 }
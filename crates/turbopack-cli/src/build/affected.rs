@@ -0,0 +1,270 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::Path,
+    process::Command,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{trace::TraceRawVcs, TurboTasks, Vc};
+use turbo_tasks_fs::{FileJsonContent, FileSystemPath};
+use turbo_tasks_memory::MemoryBackend;
+use turbopack_core::{
+    module::{Module, Modules},
+    reference::{all_modules_and_affecting_sources, referenced_modules_and_affecting_sources},
+    resolve::{find_context_file, package_json, FindContextFileResult},
+};
+
+use super::TurbopackBuildBuilder;
+use crate::{
+    arguments::AffectedArguments,
+    environment::EnvironmentPreset,
+    shutdown::cancel_on_exit_signal,
+    util::{normalize_dirs, normalize_entries, EntryRequest, NormalizedDirs},
+};
+
+/// Files which affect every entry without being part of its module graph,
+/// e. g. the dotenv files or lock files of changed dependencies.
+const GLOBAL_INPUTS: &[&str] = &[
+    ".browserslistrc",
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+];
+
+/// The files changed since `git_ref` in the working tree of `root_dir`,
+/// relative to `root_dir`. Files outside of it are ignored.
+pub fn changed_files(root_dir: &Path, git_ref: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--relative", git_ref, "--"])
+        .current_dir(root_dir)
+        .output()
+        .context("running git")?;
+    if !output.status.success() {
+        bail!(
+            "Can't list the files changed since `{git_ref}`: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Prints the [AffectedFiles] of the changed files passed with `--changed`
+/// and `--since` as JSON.
+pub async fn affected(args: &AffectedArguments) -> Result<()> {
+    let tt = TurboTasks::new(MemoryBackend::new(
+        args.common
+            .memory_limit
+            .map_or(usize::MAX, |l| l * 1024 * 1024),
+    ));
+
+    let NormalizedDirs {
+        project_dir,
+        root_dir,
+    } = normalize_dirs(&args.common.dir, &args.common.root)?;
+    let mut changed = args.changed_files.clone();
+    if let Some(git_ref) = &args.since {
+        for file in changed_files(Path::new(&root_dir), git_ref)? {
+            if !changed.contains(&file) {
+                changed.push(file);
+            }
+        }
+    }
+
+    let mut builder = TurbopackBuildBuilder::new(tt.clone(), project_dir, root_dir)
+        .preset(EnvironmentPreset::named(&args.preset)?)
+        .changed_files(Some(changed));
+    for entry in normalize_entries(&args.common.entries) {
+        builder = builder.entry_request(EntryRequest::Relative(entry));
+    }
+
+    let affected = cancel_on_exit_signal(&tt, builder.affected_files()).await?;
+    println!("{}", serde_json::to_string_pretty(&affected)?);
+    Ok(())
+}
+
+/// The entries of a build affected by a diff, written as
+/// `affected-entries.json` when building with `--affected-since`.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedEntries {
+    pub changed_files: Vec<String>,
+    pub affected: Vec<String>,
+    pub unaffected: Vec<String>,
+}
+
+/// Whether one of the `changed_files`, relative to `root`, is a module of
+/// the module graph of `entry` or a file affecting it, e. g. a
+/// `package.json`.
+#[turbo_tasks::function]
+pub async fn is_affected(
+    entry: Vc<Box<dyn Module>>,
+    root: Vc<FileSystemPath>,
+    changed_files: Vec<String>,
+) -> Result<Vc<bool>> {
+    if changed_files.iter().any(|file| is_global_input(file)) {
+        return Ok(Vc::cell(true));
+    }
+    let changed_files = changed_files
+        .iter()
+        .map(String::as_str)
+        .collect::<HashSet<_>>();
+    let root = root.await?;
+    for module in all_modules_and_affecting_sources(entry).await?.iter() {
+        let path = module.ident().path().await?;
+        if let Some(path) = root.get_path_to(&path) {
+            if changed_files.contains(path) {
+                return Ok(Vc::cell(true));
+            }
+        }
+    }
+    Ok(Vc::cell(false))
+}
+
+fn is_global_input(file: &str) -> bool {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    name.starts_with(".env") || GLOBAL_INPUTS.contains(&name)
+}
+
+/// The source files affected by a diff, i. e. the changed files and the files
+/// which import them transitively, e. g. for test runners to select the tests
+/// to run. Written by the `affected` command.
+#[turbo_tasks::value(shared)]
+#[derive(Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedFiles {
+    pub changed_files: Vec<String>,
+    pub files: Vec<AffectedFile>,
+}
+
+#[derive(Serialize, Deserialize, TraceRawVcs, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct AffectedFile {
+    /// The path of the file, relative to the project.
+    pub path: String,
+    /// The name of the package the file belongs to, from the closest
+    /// `package.json`.
+    pub package: Option<String>,
+    /// The entries (pages) with the file in their module graph.
+    pub entries: Vec<String>,
+}
+
+/// Computes the files affected by the `changed_files`, relative to the root
+/// of `project_path`, in the module graphs of `entries`. The graphs are
+/// walked in reverse, from the changed files to the modules importing them.
+///
+/// Modules are identified by their path, so all layers of a file count as
+/// one file.
+#[turbo_tasks::function]
+pub async fn affected_files(
+    entries: Vc<Modules>,
+    project_path: Vc<FileSystemPath>,
+    changed_files: Vec<String>,
+) -> Result<Vc<AffectedFiles>> {
+    let project_path_value = project_path.await?;
+    let root = project_path.root().await?;
+
+    // The importers of each file and the entries of each file
+    let mut importers: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut file_entries: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for &entry in entries.await?.iter() {
+        let entry_path = entry.ident().path().await?;
+        let entry_name = project_path_value
+            .get_path_to(&entry_path)
+            .map_or_else(|| entry_path.path.clone(), |path| path.to_string());
+        let mut visited = HashSet::from([entry]);
+        let mut queue = vec![entry];
+        while let Some(module) = queue.pop() {
+            let path = module.ident().path().await?;
+            let path = root.get_path_to(&path).map(|path| path.to_string());
+            if let Some(path) = &path {
+                file_entries
+                    .entry(path.clone())
+                    .or_default()
+                    .insert(entry_name.clone());
+            }
+            for &referenced in referenced_modules_and_affecting_sources(module)
+                .await?
+                .iter()
+            {
+                if let Some(path) = &path {
+                    let referenced_path = referenced.ident().path().await?;
+                    if let Some(referenced_path) = root.get_path_to(&referenced_path) {
+                        if referenced_path != path {
+                            importers
+                                .entry(referenced_path.to_string())
+                                .or_default()
+                                .insert(path.clone());
+                        }
+                    }
+                }
+                if visited.insert(referenced) {
+                    queue.push(referenced);
+                }
+            }
+        }
+    }
+
+    let affected: BTreeSet<String> = if changed_files.iter().any(|file| is_global_input(file)) {
+        file_entries.keys().cloned().collect()
+    } else {
+        let mut affected = BTreeSet::new();
+        let mut queue = changed_files
+            .iter()
+            .filter(|file| file_entries.contains_key(*file))
+            .cloned()
+            .collect::<Vec<_>>();
+        while let Some(file) = queue.pop() {
+            if let Some(file_importers) = importers.get(&file) {
+                queue.extend(
+                    file_importers
+                        .iter()
+                        .filter(|importer| !affected.contains(*importer))
+                        .cloned(),
+                );
+            }
+            affected.insert(file);
+        }
+        affected
+    };
+
+    let mut files = Vec::with_capacity(affected.len());
+    for path in affected {
+        let file_path = project_path.root().join(path.clone());
+        let file_path_value = file_path.await?;
+        files.push(AffectedFile {
+            path: project_path_value
+                .get_path_to(&file_path_value)
+                .map_or_else(|| path.clone(), |path| path.to_string()),
+            package: package_name(file_path).await?,
+            entries: file_entries
+                .get(&path)
+                .map(|entries| entries.iter().cloned().collect())
+                .unwrap_or_default(),
+        });
+    }
+    Ok(AffectedFiles {
+        changed_files,
+        files,
+    }
+    .cell())
+}
+
+/// The name of the package `path` belongs to, from the closest
+/// `package.json`.
+async fn package_name(path: Vc<FileSystemPath>) -> Result<Option<String>> {
+    let FindContextFileResult::Found(package_json_path, _) =
+        &*find_context_file(path.parent(), package_json()).await?
+    else {
+        return Ok(None);
+    };
+    Ok(match &*package_json_path.read_json().await? {
+        FileJsonContent::Content(json) => json["name"].as_str().map(|name| name.to_string()),
+        _ => None,
+    })
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{FileContent, FileSystemPath};
+use turbopack_core::{asset::Asset, output::OutputAsset};
+
+use crate::export::output::{is_compressible, precompressed};
+
+/// `asset-manifest.json`, mapping the logical names of the emitted assets to
+/// their fingerprinted URLs, e. g. `logo.png` to `/logo.0123abcd.png`, for CDN
+/// configuration generators and server-side templates outside of Next.js.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetManifest {
+    /// Chunks are fingerprinted by the directory of the build ID.
+    pub build_id: String,
+    pub assets: BTreeMap<String, ManifestAsset>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestAsset {
+    pub url: String,
+    pub content_type: String,
+    pub size: u64,
+    /// The sizes of the gzip and brotli compressed content, for text files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_size: Option<CompressedSize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CompressedSize {
+    pub gzip: u64,
+    pub br: u64,
+}
+
+/// Collects the asset manifest of the `assets` emitted into `output_root`.
+/// Assets outside of it are not served, so they are not listed.
+pub async fn asset_manifest(
+    output_root: Vc<FileSystemPath>,
+    build_id: &str,
+    assets: &[Vc<Box<dyn OutputAsset>>],
+) -> Result<AssetManifest> {
+    let output_root = output_root.await?;
+
+    let mut manifest = AssetManifest {
+        build_id: build_id.to_string(),
+        ..Default::default()
+    };
+    for &asset in assets {
+        let path = asset.ident().path().await?;
+        let Some(file) = output_root.get_path_to(&path) else {
+            continue;
+        };
+        let file_content = asset.content().file_content().await?;
+        let FileContent::Content(content) = &*file_content else {
+            continue;
+        };
+        let bytes = content.content().to_bytes()?;
+        let compressed_size = if is_compressible(file) {
+            let [(_, gzip), (_, br)] = precompressed(&bytes).await?;
+            Some(CompressedSize {
+                gzip: gzip.len() as u64,
+                br: br.len() as u64,
+            })
+        } else {
+            None
+        };
+        let manifest_asset = ManifestAsset {
+            url: format!("/{file}"),
+            content_type: content
+                .content_type()
+                .cloned()
+                .unwrap_or_else(|| mime_guess::from_path(file).first_or_octet_stream())
+                .to_string(),
+            size: bytes.len() as u64,
+            compressed_size,
+        };
+        // Assets with the same logical name, e. g. images of the same name in
+        // different directories, are listed by their path instead
+        let name = logical_name(file, build_id);
+        if manifest.assets.contains_key(&name) {
+            manifest.assets.insert(file.to_string(), manifest_asset);
+        } else {
+            manifest.assets.insert(name, manifest_asset);
+        }
+    }
+    Ok(manifest)
+}
+
+/// The path of an asset without its fingerprint, i. e. without the directory
+/// of the build ID and without the content hash of static assets
+/// (`<name>.<hash>.<extension>`).
+fn logical_name(file: &str, build_id: &str) -> String {
+    let file = file
+        .strip_prefix(build_id)
+        .and_then(|file| file.strip_prefix('/'))
+        .unwrap_or(file);
+    let (dir, name) = file
+        .rsplit_once('/')
+        .map_or(("", file), |(dir, name)| (&file[..dir.len() + 1], name));
+    let mut segments = name.split('.').collect::<Vec<_>>();
+    if segments.len() >= 3 {
+        let hash = segments[segments.len() - 2];
+        if hash.len() == 8 && hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            segments.remove(segments.len() - 2);
+        }
+    }
+    format!("{dir}{}", segments.join("."))
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+    path::Path,
+    process::Command,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use turbo_tasks_hash::{encode_hex, Xxh3Hash64Hasher};
+
+/// The env variable the build ID is exposed as to client code, as
+/// `process.env.TURBOPACK_PUBLIC_BUILD_ID`.
+pub const BUILD_ID_ENV: &str = "TURBOPACK_PUBLIC_BUILD_ID";
+
+/// How the build ID is generated when it's not passed explicitly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BuildIdGenerator {
+    /// The commit hash of the project's git checkout. Stable across rebuilds
+    /// of the same commit, falls back to a random ID outside of git
+    /// checkouts. A checkout with uncommitted changes gets a `-dirty-` suffix
+    /// with a hash of the changes.
+    #[default]
+    Git,
+    /// A new random ID for every build.
+    Random,
+}
+
+/// Generates a build ID for the project in `project_dir`.
+pub fn generate_build_id(generator: BuildIdGenerator, project_dir: &Path) -> Result<String> {
+    match generator {
+        BuildIdGenerator::Git => Ok(git_version(project_dir).unwrap_or_else(|_| random_build_id())),
+        BuildIdGenerator::Random => Ok(random_build_id()),
+    }
+}
+
+/// Checks that a build ID can be used as path segment in chunk URLs.
+pub fn validate_build_id(build_id: &str) -> Result<()> {
+    if build_id.is_empty()
+        || !build_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        || build_id.starts_with('.')
+    {
+        bail!(
+            "Invalid build ID `{build_id}`, only ASCII letters, digits, `-`, `_` and `.` are \
+             allowed"
+        );
+    }
+    Ok(())
+}
+
+/// The commit of the checkout, with a hash of its uncommitted changes when
+/// there are any. Builds of a dirty checkout get another ID than builds of
+/// the clean commit, and than builds with other changes.
+pub fn git_version(project_dir: &Path) -> Result<String> {
+    let commit = git(project_dir, &["rev-parse", "--short=12", "HEAD"])?;
+    let commit = String::from_utf8(commit)?.trim().to_string();
+    let status = git(
+        project_dir,
+        &["status", "--porcelain", "-z", "--untracked-files=all"],
+    )?;
+    if status.is_empty() {
+        return Ok(commit);
+    }
+    let mut hasher = Xxh3Hash64Hasher::new();
+    hasher.write_value(git(project_dir, &["diff", "HEAD", "--binary"])?.as_slice());
+    // Untracked files are not part of the diff
+    let untracked = git(
+        project_dir,
+        &["ls-files", "-z", "--others", "--exclude-standard"],
+    )?;
+    for path in untracked
+        .split(|&byte| byte == 0)
+        .filter(|path| !path.is_empty())
+    {
+        let path = String::from_utf8_lossy(path);
+        hasher.write_value(&*path);
+        if let Ok(content) = std::fs::read(project_dir.join(&*path)) {
+            hasher.write_value(content.as_slice());
+        }
+    }
+    Ok(format!("{commit}-dirty-{}", encode_hex(hasher.finish())))
+}
+
+fn git(project_dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project_dir)
+        .output()
+        .context("running git")?;
+    if !output.status.success() {
+        bail!("{} is not in a git checkout", project_dir.display());
+    }
+    Ok(output.stdout)
+}
+
+fn random_build_id() -> String {
+    // `RandomState` is seeded randomly per instance, the time makes it unique
+    // even when the seeds repeat
+    let mut hasher = RandomState::new().build_hasher();
+    SystemTime::now().hash(&mut hasher);
+    std::process::id().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
@@ -0,0 +1,40 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{Context, Result};
+use turbo_tasks::Vc;
+
+/// An arm of an A/B experiment. A separate client bundle is built for every
+/// arm, in which `aliases` replace the resolution of specific requests.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug)]
+pub struct ExperimentArm {
+    pub experiment: String,
+    pub arm: String,
+    /// Pairs of request and replacement request.
+    pub aliases: Vec<(String, String)>,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct ExperimentArms(pub Vec<Vc<ExperimentArm>>);
+
+/// Maps experiment → arm → request → replacement request.
+type ExperimentsConfig = BTreeMap<String, BTreeMap<String, BTreeMap<String, String>>>;
+
+/// Reads the experiment arms from a JSON file of the shape
+/// `{ "<experiment>": { "<arm>": { "<request>": "<replacement>" } } }`.
+pub fn read_experiments_config(path: &Path) -> Result<Vec<ExperimentArm>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("reading experiments config {}", path.display()))?;
+    let config: ExperimentsConfig = serde_json::from_str(&content)
+        .with_context(|| format!("parsing experiments config {}", path.display()))?;
+    Ok(config
+        .into_iter()
+        .flat_map(|(experiment, arms)| {
+            arms.into_iter().map(move |(arm, aliases)| ExperimentArm {
+                experiment: experiment.clone(),
+                arm,
+                aliases: aliases.into_iter().collect(),
+            })
+        })
+        .collect())
+}
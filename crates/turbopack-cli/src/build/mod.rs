@@ -1,26 +1,31 @@
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     env::current_dir,
-    path::{PathBuf, MAIN_SEPARATOR},
+    path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::Arc,
 };
 
 use anyhow::{bail, Context, Result};
 use turbo_tasks::{TransientInstance, TryJoinIterExt, TurboTasks, Value, Vc};
-use turbo_tasks_fs::FileSystem;
+use turbo_tasks_env::{CustomProcessEnv, EnvMap, ProcessEnv};
+use turbo_tasks_fs::{glob::Glob, File, FileContent, FileSystem, FileSystemPath};
 use turbo_tasks_memory::MemoryBackend;
-use turbopack::ecmascript::EcmascriptModuleAsset;
+use turbopack::ecmascript::{
+    dual_package_hazard::check_dual_package_hazards, EcmascriptModuleAsset,
+};
 use turbopack_cli_utils::issue::{ConsoleUi, LogOptions};
 use turbopack_core::{
     asset::Asset,
     chunk::{
-        availability_info::AvailabilityInfo, ChunkableModule, ChunkingContextExt,
+        availability_info::AvailabilityInfo, ChunkableModule, ChunkingContext, ChunkingContextExt,
         EvaluatableAssets, MinifyType,
     },
-    environment::{BrowserEnvironment, Environment, ExecutionEnvironment},
+    context::AssetContext,
+    emit_hook::{derived_assets, EmitHooks},
+    environment::Environment,
     issue::{handle_issues, IssueReporter, IssueSeverity},
     module::Module,
-    output::OutputAsset,
+    output::{OutputAsset, OutputAssets},
     reference::all_assets_from_entries,
     reference_type::{EntryReferenceSubType, ReferenceType},
     resolve::{
@@ -28,20 +33,44 @@ use turbopack_core::{
         parse::Request,
     },
 };
-use turbopack_ecmascript_runtime::RuntimeType;
 use turbopack_env::dotenv::load_env;
-use turbopack_node::execution_context::ExecutionContext;
+use turbopack_node::{execution_context::ExecutionContext, transforms::cache::transform_cache};
 use turbopack_nodejs::NodeJsChunkingContext;
 
+use self::{
+    affected::{affected_files, is_affected, AffectedEntries, AffectedFiles},
+    build_id::{generate_build_id, validate_build_id, BuildIdGenerator, BUILD_ID_ENV},
+    experiments::{read_experiments_config, ExperimentArm, ExperimentArms},
+    precompress::{PrecompressEmitHook, PRECOMPRESS_FILTER},
+    standalone::StandaloneOptions,
+    stats::webpack_stats,
+    strict::{check_strict_rules, StrictRule},
+};
 use crate::{
     arguments::BuildArguments,
-    contexts::{get_client_asset_context, get_client_compile_time_info, NodeEnv},
+    contexts::{
+        get_client_asset_context, get_client_compile_time_info, get_client_variant_asset_context,
+    },
+    embed_js::embed_file,
+    environment::{EnvironmentPreset, EnvironmentTarget},
+    i18n::{project_message_catalogs, MESSAGES_DIR},
+    shutdown::cancel_on_exit_signal,
     util::{
         normalize_dirs, normalize_entries, output_fs, project_fs, EntryRequest, EntryRequests,
         NormalizedDirs,
     },
 };
 
+pub mod affected;
+pub mod asset_manifest;
+pub mod build_id;
+pub mod experiments;
+pub mod next_manifests;
+pub mod precompress;
+pub mod standalone;
+pub mod stats;
+pub mod strict;
+
 pub fn register() {
     turbopack::register();
     include!(concat!(env!("OUT_DIR"), "/register.rs"));
@@ -52,11 +81,23 @@ pub struct TurbopackBuildBuilder {
     project_dir: String,
     root_dir: String,
     entry_requests: Vec<EntryRequest>,
+    targeted_entry_requests: Vec<(EnvironmentTarget, EntryRequest)>,
+    experiment_arms: Vec<ExperimentArm>,
+    preset: EnvironmentPreset,
     browserslist_query: String,
     log_level: IssueSeverity,
     show_all: bool,
     log_detail: bool,
     minify_type: MinifyType,
+    build_id: Option<String>,
+    stats: bool,
+    next_manifests: bool,
+    standalone: bool,
+    asset_manifest: bool,
+    transform_cache_dir: Option<String>,
+    precompress: bool,
+    strict_rules: Vec<StrictRule>,
+    changed_files: Option<Vec<String>>,
 }
 
 impl TurbopackBuildBuilder {
@@ -70,11 +111,23 @@ impl TurbopackBuildBuilder {
             project_dir,
             root_dir,
             entry_requests: vec![],
+            targeted_entry_requests: vec![],
+            experiment_arms: vec![],
+            preset: EnvironmentPreset::production(),
             browserslist_query: "chrome 64, edge 79, firefox 67, opera 51, safari 12".to_owned(),
             log_level: IssueSeverity::Warning,
             show_all: false,
             log_detail: false,
             minify_type: MinifyType::Minify,
+            build_id: None,
+            stats: false,
+            next_manifests: false,
+            standalone: false,
+            asset_manifest: false,
+            transform_cache_dir: None,
+            precompress: false,
+            strict_rules: Vec::new(),
+            changed_files: None,
         }
     }
 
@@ -83,6 +136,31 @@ impl TurbopackBuildBuilder {
         self
     }
 
+    /// Adds an entry which runs on another target than the other entries,
+    /// e. g. a web worker. It's compiled with the preset of the build for
+    /// `target` and emitted into a directory named after the target.
+    pub fn entry_request_for_target(
+        mut self,
+        target: EnvironmentTarget,
+        entry_asset_path: EntryRequest,
+    ) -> Self {
+        self.targeted_entry_requests
+            .push((target, entry_asset_path));
+        self
+    }
+
+    /// Sets the environment preset entries are compiled with, see
+    /// [EnvironmentPreset::named]. Defaults to the production preset.
+    pub fn preset(mut self, preset: EnvironmentPreset) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    pub fn experiment_arm(mut self, experiment_arm: ExperimentArm) -> Self {
+        self.experiment_arms.push(experiment_arm);
+        self
+    }
+
     pub fn browserslist_query(mut self, browserslist_query: String) -> Self {
         self.browserslist_query = browserslist_query;
         self
@@ -108,7 +186,77 @@ impl TurbopackBuildBuilder {
         self
     }
 
+    /// Sets the build ID. It's generated with [BuildIdGenerator::Git] when
+    /// not set.
+    pub fn build_id(mut self, build_id: String) -> Self {
+        self.build_id = Some(build_id);
+        self
+    }
+
+    /// Writes a webpack compatible `stats.json` describing the modules and
+    /// chunks of the build, see [stats::WebpackStats].
+    pub fn stats(mut self, stats: bool) -> Self {
+        self.stats = stats;
+        self
+    }
+
+    /// Writes the manifests the Next.js production server reads, see
+    /// [next_manifests::NextManifests].
+    pub fn next_manifests(mut self, next_manifests: bool) -> Self {
+        self.next_manifests = next_manifests;
+        self
+    }
+
+    /// Writes a `server.js` which serves the build with `node server.js`, see
+    /// [standalone::StandaloneManifest].
+    pub fn standalone(mut self, standalone: bool) -> Self {
+        self.standalone = standalone;
+        self
+    }
+
+    /// Writes an `asset-manifest.json` mapping the logical names of the
+    /// emitted assets to their fingerprinted URLs, see
+    /// [asset_manifest::AssetManifest].
+    pub fn asset_manifest(mut self, asset_manifest: bool) -> Self {
+        self.asset_manifest = asset_manifest;
+        self
+    }
+
+    /// Stores the results of webpack loaders in `transform_cache_dir`, so
+    /// later builds skip them for unchanged files.
+    pub fn transform_cache_dir(mut self, transform_cache_dir: Option<String>) -> Self {
+        self.transform_cache_dir = transform_cache_dir;
+        self
+    }
+
+    /// Writes gzip and brotli compressed siblings of text files, see
+    /// [precompress::PrecompressEmitHook].
+    pub fn precompress(mut self, precompress: bool) -> Self {
+        self.precompress = precompress;
+        self
+    }
+
+    /// Fails the build when a warning matches one of the rules, after all
+    /// issues were reported.
+    pub fn strict_rules(mut self, strict_rules: Vec<StrictRule>) -> Self {
+        self.strict_rules = strict_rules;
+        self
+    }
+
+    /// Only builds the entries affected by the changed files, relative to
+    /// the root directory, and writes an `affected-entries.json` report, see
+    /// [affected::AffectedEntries].
+    pub fn changed_files(mut self, changed_files: Option<Vec<String>>) -> Self {
+        self.changed_files = changed_files;
+        self
+    }
+
     pub async fn build(self) -> Result<()> {
+        let build_id = match self.build_id {
+            Some(build_id) => build_id,
+            None => generate_build_id(BuildIdGenerator::default(), Path::new(&self.project_dir))?,
+        };
+        validate_build_id(&build_id)?;
         let task = self.turbo_tasks.spawn_once_task::<(), _>(async move {
             let build_result = build_internal(
                 self.project_dir.clone(),
@@ -121,8 +269,31 @@ impl TurbopackBuildBuilder {
                         .collect(),
                 )
                 .cell(),
+                TargetedEntryRequests(
+                    self.targeted_entry_requests
+                        .iter()
+                        .map(|(target, entry_request)| (*target, entry_request.clone().cell()))
+                        .collect(),
+                )
+                .cell(),
+                ExperimentArms(
+                    self.experiment_arms
+                        .into_iter()
+                        .map(ExperimentArm::cell)
+                        .collect(),
+                )
+                .cell(),
+                self.preset.cell(),
                 self.browserslist_query,
                 self.minify_type,
+                build_id,
+                self.stats,
+                self.next_manifests,
+                self.standalone,
+                self.asset_manifest,
+                self.transform_cache_dir,
+                self.precompress,
+                self.changed_files,
             );
 
             // Await the result to propagate any errors.
@@ -145,6 +316,7 @@ impl TurbopackBuildBuilder {
                 None,
             )
             .await?;
+            check_strict_rules(build_result, &self.strict_rules).await?;
 
             Ok(Default::default())
         });
@@ -153,25 +325,62 @@ impl TurbopackBuildBuilder {
 
         Ok(())
     }
+
+    /// Computes the source files affected by the changed files in the module
+    /// graphs of the entries, e. g. to select the tests to run. Nothing is
+    /// built.
+    pub async fn affected_files(self) -> Result<AffectedFiles> {
+        let changed_files = self.changed_files.unwrap_or_default();
+        self.turbo_tasks
+            .run_once(async move {
+                let affected = affected_internal(
+                    self.project_dir,
+                    self.root_dir,
+                    EntryRequests(
+                        self.entry_requests
+                            .into_iter()
+                            .map(EntryRequest::cell)
+                            .collect(),
+                    )
+                    .cell(),
+                    self.preset.cell(),
+                    self.browserslist_query,
+                    self.transform_cache_dir,
+                    changed_files,
+                );
+                Ok(affected.await?.clone_value())
+            })
+            .await
+    }
 }
 
+/// Entries which run on another target than the other entries of a build.
+#[turbo_tasks::value(transparent)]
+struct TargetedEntryRequests(Vec<(EnvironmentTarget, Vc<EntryRequest>)>);
+
 #[turbo_tasks::function]
 async fn build_internal(
     project_dir: String,
     root_dir: String,
     entry_requests: Vc<EntryRequests>,
+    targeted_entry_requests: Vc<TargetedEntryRequests>,
+    experiment_arms: Vc<ExperimentArms>,
+    preset: Vc<EnvironmentPreset>,
     browserslist_query: String,
     minify_type: MinifyType,
+    build_id: String,
+    stats: bool,
+    next_manifests: bool,
+    standalone: bool,
+    asset_manifest: bool,
+    transform_cache_dir: Option<String>,
+    precompress: bool,
+    changed_files: Option<Vec<String>>,
 ) -> Result<Vc<()>> {
-    let env = Environment::new(Value::new(ExecutionEnvironment::Browser(
-        BrowserEnvironment {
-            dom: true,
-            web_worker: false,
-            service_worker: false,
-            browserslist_query: browserslist_query.clone(),
-        }
-        .into(),
-    )));
+    let preset_value = preset.await?;
+    let env = Environment::new(Value::new(
+        preset_value.execution_environment(browserslist_query.clone()),
+    ));
     let output_fs = output_fs(project_dir.clone());
     let project_fs = project_fs(root_dir.clone());
     let project_relative = project_dir.strip_prefix(&root_dir).unwrap();
@@ -181,75 +390,379 @@ async fn build_internal(
         .replace(MAIN_SEPARATOR, "/");
     let project_path = project_fs.root().join(project_relative);
     let build_output_root = output_fs.root().join("dist".to_string());
+    let emit_hooks: Vc<EmitHooks> = if precompress {
+        Vc::cell(vec![(
+            Glob::new(PRECOMPRESS_FILTER.to_string()),
+            Vc::upcast(PrecompressEmitHook::new()),
+        )])
+    } else {
+        EmitHooks::empty()
+    };
+
+    let chunking_context = get_chunking_context(
+        project_path,
+        build_output_root,
+        env,
+        preset,
+        minify_type,
+        build_id.clone(),
+    );
 
-    let node_env = NodeEnv::Production.cell();
+    // Client code can read the build ID to detect version skew
+    let process_env: Vc<Box<dyn ProcessEnv>> = Vc::upcast(CustomProcessEnv::new(
+        load_env(project_path),
+        Vc::<EnvMap>::cell(
+            [(BUILD_ID_ENV.to_string(), build_id.clone())]
+                .into_iter()
+                .collect(),
+        ),
+    ));
+    let compile_time_info = get_client_compile_time_info(
+        project_path,
+        browserslist_query.clone(),
+        preset,
+        process_env,
+    );
+    let execution_context = ExecutionContext::new(project_path, chunking_context, process_env)
+        .with_transform_cache(transform_cache(transform_cache_dir));
+    let asset_context =
+        get_client_asset_context(project_path, execution_context, compile_time_info, preset);
 
-    let chunking_context = Vc::upcast(
+    // The standalone server renders the pages with render entries compiled for
+    // Node.js
+    let standalone = if standalone {
+        let render_preset = preset_value
+            .clone_value()
+            .with_target(EnvironmentTarget::NodeJs);
+        let render_env = Environment::new(Value::new(
+            render_preset.execution_environment(browserslist_query.clone()),
+        ));
+        let render_preset = render_preset.cell();
+        let render_compile_time_info = get_client_compile_time_info(
+            project_path,
+            browserslist_query.clone(),
+            render_preset,
+            process_env,
+        );
+        Some(
+            StandaloneOptions {
+                build_id: build_id.clone(),
+                render_asset_context: get_client_asset_context(
+                    project_path,
+                    execution_context,
+                    render_compile_time_info,
+                    render_preset,
+                ),
+                render_chunking_context: get_chunking_context(
+                    project_path,
+                    build_output_root.join(standalone::RENDER_DIR.to_string()),
+                    render_env,
+                    render_preset,
+                    minify_type,
+                    "chunks".to_string(),
+                ),
+            }
+            .cell(),
+        )
+    } else {
+        None
+    };
+
+    emit_entries(
+        project_dir.clone(),
+        entry_requests,
+        asset_context,
+        chunking_context,
+        build_output_root,
+        output_fs.root(),
+        project_path,
+        stats,
+        next_manifests.then(|| build_id.clone()),
+        standalone,
+        asset_manifest.then(|| build_id.clone()),
+        emit_hooks,
+        changed_files.clone(),
+    )
+    .await?;
+
+    // Entries of other targets are compiled with contexts of their own, into a
+    // directory named after the target
+    let targeted_entry_requests = targeted_entry_requests.await?;
+    let mut targets = Vec::new();
+    for (target, _) in targeted_entry_requests.iter() {
+        if !targets.contains(target) {
+            targets.push(*target);
+        }
+    }
+    for target in targets {
+        let target_entry_requests = EntryRequests(
+            targeted_entry_requests
+                .iter()
+                .filter(|(entry_target, _)| *entry_target == target)
+                .map(|(_, entry_request)| *entry_request)
+                .collect(),
+        )
+        .cell();
+        let target_preset = preset_value.clone_value().with_target(target);
+        let target_env = Environment::new(Value::new(
+            target_preset.execution_environment(browserslist_query.clone()),
+        ));
+        let target_preset = target_preset.cell();
+        let target_output_root = build_output_root.join(target.to_string());
+        let target_compile_time_info = get_client_compile_time_info(
+            project_path,
+            browserslist_query.clone(),
+            target_preset,
+            process_env,
+        );
+        emit_entries(
+            project_dir.clone(),
+            target_entry_requests,
+            get_client_asset_context(
+                project_path,
+                execution_context,
+                target_compile_time_info,
+                target_preset,
+            ),
+            get_chunking_context(
+                project_path,
+                target_output_root,
+                target_env,
+                target_preset,
+                minify_type,
+                build_id.clone(),
+            ),
+            target_output_root,
+            output_fs.root(),
+            project_path,
+            false,
+            None,
+            None,
+            asset_manifest.then(|| build_id.clone()),
+            emit_hooks,
+            changed_files.clone(),
+        )
+        .await?;
+    }
+
+    // Deployments switch to the new build atomically by pointing to its
+    // `BUILD_ID`
+    build_output_root
+        .join("BUILD_ID".to_string())
+        .write(FileContent::Content(File::from(build_id.clone())).cell())
+        .await?;
+
+    let experiment_arms = experiment_arms.await?;
+    if experiment_arms.is_empty() {
+        return Ok(Default::default());
+    }
+
+    // Every experiment arm gets its own client bundle in
+    // `dist/experiments/<experiment>/<arm>`, the manifest maps the arms to
+    // their chunks.
+    let build_output_root_value = build_output_root.await?;
+    let mut manifest: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+    for arm in experiment_arms.iter() {
+        let arm = arm.await?;
+        let arm_output_root =
+            build_output_root.join(format!("experiments/{}/{}", arm.experiment, arm.arm));
+        let arm_asset_context = get_client_variant_asset_context(
+            project_path,
+            execution_context,
+            compile_time_info,
+            preset,
+            Vc::cell(arm.aliases.clone()),
+        );
+        let chunks = emit_entries(
+            project_dir.clone(),
+            entry_requests,
+            arm_asset_context,
+            get_chunking_context(
+                project_path,
+                arm_output_root,
+                env,
+                preset,
+                minify_type,
+                build_id.clone(),
+            ),
+            arm_output_root,
+            output_fs.root(),
+            project_path,
+            stats,
+            None,
+            None,
+            asset_manifest.then(|| build_id.clone()),
+            emit_hooks,
+            changed_files.clone(),
+        )
+        .await?;
+
+        let mut paths = Vec::new();
+        for chunk in chunks.iter() {
+            let path = chunk.ident().path().await?;
+            if let Some(path) = build_output_root_value.get_path_to(&path) {
+                paths.push(path.to_string());
+            }
+        }
+        paths.sort();
+        manifest
+            .entry(arm.experiment.clone())
+            .or_default()
+            .insert(arm.arm.clone(), paths);
+    }
+
+    build_output_root
+        .join("experiments-manifest.json".to_string())
+        .write(FileContent::Content(File::from(serde_json::to_string_pretty(&manifest)?)).cell())
+        .await?;
+
+    Ok(Default::default())
+}
+
+/// Computes the source files affected by `changed_files` in the module graphs
+/// of the entries, without emitting anything.
+#[turbo_tasks::function]
+async fn affected_internal(
+    project_dir: String,
+    root_dir: String,
+    entry_requests: Vc<EntryRequests>,
+    preset: Vc<EnvironmentPreset>,
+    browserslist_query: String,
+    transform_cache_dir: Option<String>,
+    changed_files: Vec<String>,
+) -> Result<Vc<AffectedFiles>> {
+    let env = Environment::new(Value::new(
+        preset
+            .await?
+            .execution_environment(browserslist_query.clone()),
+    ));
+    let output_fs = output_fs(project_dir.clone());
+    let project_fs = project_fs(root_dir.clone());
+    let project_relative = project_dir.strip_prefix(&root_dir).unwrap();
+    let project_relative = project_relative
+        .strip_prefix(MAIN_SEPARATOR)
+        .unwrap_or(project_relative)
+        .replace(MAIN_SEPARATOR, "/");
+    let project_path = project_fs.root().join(project_relative);
+
+    // Nothing is emitted, but webpack loaders run in the chunks of an
+    // execution context
+    let chunking_context = get_chunking_context(
+        project_path,
+        output_fs.root().join("dist".to_string()),
+        env,
+        preset,
+        MinifyType::NoMinify,
+        String::new(),
+    );
+    let process_env = load_env(project_path);
+    let compile_time_info =
+        get_client_compile_time_info(project_path, browserslist_query, preset, process_env);
+    let execution_context = ExecutionContext::new(project_path, chunking_context, process_env)
+        .with_transform_cache(transform_cache(transform_cache_dir));
+    let asset_context =
+        get_client_asset_context(project_path, execution_context, compile_time_info, preset);
+
+    let entries = resolve_entries(
+        entry_requests,
+        asset_context,
+        output_fs.root(),
+        &project_dir,
+    )
+    .await?;
+    Ok(affected_files(
+        Vc::cell(entries),
+        project_path,
+        changed_files,
+    ))
+}
+
+#[turbo_tasks::function]
+async fn get_chunking_context(
+    project_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    env: Vc<Environment>,
+    preset: Vc<EnvironmentPreset>,
+    minify_type: MinifyType,
+    build_id: String,
+) -> Result<Vc<Box<dyn ChunkingContext>>> {
+    Ok(Vc::upcast(
         NodeJsChunkingContext::builder(
             project_path,
-            build_output_root,
-            build_output_root,
-            build_output_root,
-            build_output_root,
+            output_root,
+            output_root,
+            // Chunk URLs contain the build ID, so that chunks of different
+            // builds never collide
+            output_root.join(build_id),
+            output_root,
             env,
-            match *node_env.await? {
-                NodeEnv::Development => RuntimeType::Development,
-                NodeEnv::Production => RuntimeType::Production,
-            },
+            preset.await?.runtime_type(),
         )
         .minify_type(minify_type)
         .build(),
-    );
+    ))
+}
 
-    let compile_time_info = get_client_compile_time_info(browserslist_query, node_env);
-    let execution_context =
-        ExecutionContext::new(project_path, chunking_context, load_env(project_path));
-    let asset_context =
-        get_client_asset_context(project_path, execution_context, compile_time_info, node_env);
+/// Emits the entry chunk groups of `entry_requests` into `output_root` and
+/// returns all emitted assets. With `stats`, a `stats.json` describing them is
+/// written too. With the build ID in `next_manifests`, the manifests of the
+/// Next.js production server are written too. With `standalone`, a
+/// `server.js` serving the output and rendering its pages is written too.
+/// With the
+/// build ID in `asset_manifest`, an `asset-manifest.json` is written too.
+#[turbo_tasks::function]
+async fn emit_entries(
+    project_dir: String,
+    entry_requests: Vc<EntryRequests>,
+    asset_context: Vc<Box<dyn AssetContext>>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    output_root: Vc<FileSystemPath>,
+    origin_root: Vc<FileSystemPath>,
+    project_path: Vc<FileSystemPath>,
+    stats: bool,
+    next_manifests: Option<String>,
+    standalone: Option<Vc<StandaloneOptions>>,
+    asset_manifest: Option<String>,
+    emit_hooks: Vc<EmitHooks>,
+    changed_files: Option<Vec<String>>,
+) -> Result<Vc<OutputAssets>> {
+    let entries = resolve_entries(entry_requests, asset_context, origin_root, &project_dir).await?;
 
-    let entry_requests = (*entry_requests
-        .await?
-        .iter()
-        .cloned()
-        .map(|r| async move {
-            Ok(match &*r.await? {
-                EntryRequest::Relative(p) => {
-                    Request::relative(Value::new(p.clone().into()), Default::default(), false)
-                }
-                EntryRequest::Module(m, p) => {
-                    Request::module(m.clone(), Value::new(p.clone().into()), Default::default())
+    // With the files changed by a diff, only the entries they affect are built
+    let entries = match changed_files {
+        Some(changed_files) => {
+            let project_path_value = project_path.await?;
+            let mut report = AffectedEntries::default();
+            let mut affected_entries = Vec::new();
+            for entry in entries {
+                let path = entry.ident().path().await?;
+                let name = project_path_value
+                    .get_path_to(&path)
+                    .map_or_else(|| path.path.clone(), |path| path.to_string());
+                if *is_affected(entry, project_path.root(), changed_files.clone()).await? {
+                    report.affected.push(name);
+                    affected_entries.push(entry);
+                } else {
+                    report.unaffected.push(name);
                 }
-            })
-        })
-        .try_join()
-        .await?)
-        .to_vec();
+            }
+            report.changed_files = changed_files;
+            output_root
+                .join("affected-entries.json".to_string())
+                .write(
+                    FileContent::Content(File::from(serde_json::to_string_pretty(&report)?)).cell(),
+                )
+                .await?;
+            affected_entries
+        }
+        None => entries,
+    };
 
-    let origin = PlainResolveOrigin::new(asset_context, output_fs.root().join("_".to_string()));
-    let project_dir = &project_dir;
-    let entries = entry_requests
-        .into_iter()
-        .map(|request_vc| async move {
-            let ty = Value::new(ReferenceType::Entry(EntryReferenceSubType::Undefined));
-            let request = request_vc.await?;
-            origin
-                .resolve_asset(request_vc, origin.resolve_options(ty.clone()), ty)
-                .first_module()
-                .await?
-                .with_context(|| {
-                    format!(
-                        "Unable to resolve entry {} from directory {}.",
-                        request.request().unwrap(),
-                        project_dir
-                    )
-                })
-        })
-        .try_join()
-        .await?;
+    check_dual_package_hazards(Vc::cell(entries.clone())).await?;
 
     let entry_chunk_groups = entries
-        .into_iter()
-        .map(|entry_module| async move {
+        .iter()
+        .map(|&entry_module| async move {
             Ok(
                 if let Some(ecmascript) =
                     Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(entry_module).await?
@@ -259,7 +772,7 @@ async fn build_internal(
                             .await?
                             .unwrap()
                             .entry_chunk_group(
-                                build_output_root
+                                output_root
                                     .join(
                                         ecmascript
                                             .ident()
@@ -295,7 +808,7 @@ async fn build_internal(
         .await?;
 
     let mut chunks: HashSet<Vc<Box<dyn OutputAsset>>> = HashSet::new();
-    for chunk_group in entry_chunk_groups {
+    for &chunk_group in &entry_chunk_groups {
         chunks.extend(&*all_assets_from_entries(chunk_group).await?);
     }
 
@@ -305,22 +818,192 @@ async fn build_internal(
         .try_join()
         .await?;
 
-    Ok(Default::default())
+    // Assets derived by the emit hooks, e. g. precompressed siblings, are emitted
+    // along with the chunks, but not listed in the stats and manifests
+    derived_assets(
+        Vc::cell(chunks.iter().copied().collect()),
+        output_root,
+        emit_hooks,
+    )
+    .await?
+    .iter()
+    .map(|asset| asset.content().write(asset.ident().path()))
+    .try_join()
+    .await?;
+
+    let catalogs = project_message_catalogs(project_path, Vc::cell(entries.clone())).await?;
+    for (locale, messages) in catalogs.iter() {
+        output_root
+            .join(format!("{MESSAGES_DIR}/{locale}.json"))
+            .write(FileContent::Content(File::from(serde_json::to_string(messages)?)).cell())
+            .await?;
+    }
+
+    let chunks = chunks.into_iter().collect::<Vec<_>>();
+    if stats {
+        let mut entrypoints = Vec::new();
+        for (&entry_module, &chunk_group) in entries.iter().zip(&entry_chunk_groups) {
+            let name = entry_module
+                .ident()
+                .path()
+                .file_stem()
+                .await?
+                .as_deref()
+                .unwrap_or("main")
+                .to_string();
+            entrypoints.push((name, entry_module, chunk_group));
+        }
+        let stats = webpack_stats(project_path, output_root, &entrypoints, &chunks).await?;
+        output_root
+            .join("stats.json".to_string())
+            .write(FileContent::Content(File::from(serde_json::to_string_pretty(&stats)?)).cell())
+            .await?;
+    }
+
+    let entries = entries
+        .iter()
+        .copied()
+        .zip(entry_chunk_groups.iter().copied())
+        .collect::<Vec<_>>();
+    if let Some(build_id) = next_manifests {
+        let manifests =
+            next_manifests::next_manifests(project_path, output_root, &build_id, &entries).await?;
+        for (name, manifest) in [
+            (
+                "routes-manifest.json",
+                serde_json::to_string_pretty(&manifests.routes)?,
+            ),
+            (
+                "prerender-manifest.json",
+                serde_json::to_string_pretty(&manifests.prerender)?,
+            ),
+            (
+                "middleware-manifest.json",
+                serde_json::to_string_pretty(&manifests.middleware)?,
+            ),
+        ] {
+            output_root
+                .join(name.to_string())
+                .write(FileContent::Content(File::from(manifest)).cell())
+                .await?;
+        }
+    }
+
+    if let Some(standalone) = standalone {
+        let standalone = standalone.await?;
+        let render_entries =
+            standalone::emit_render_entries(project_path, output_root, &standalone, &entries)
+                .await?;
+        let manifest = standalone::standalone_manifest(
+            project_path,
+            output_root,
+            &standalone.build_id,
+            &entries,
+            &render_entries,
+        )
+        .await?;
+        output_root
+            .join("standalone-manifest.json".to_string())
+            .write(
+                FileContent::Content(File::from(serde_json::to_string_pretty(&manifest)?)).cell(),
+            )
+            .await?;
+        output_root
+            .join("server.js".to_string())
+            .write(embed_file(standalone::STANDALONE_SERVER.to_string()))
+            .await?;
+    }
+
+    if let Some(build_id) = asset_manifest {
+        let manifest = asset_manifest::asset_manifest(output_root, &build_id, &chunks).await?;
+        output_root
+            .join("asset-manifest.json".to_string())
+            .write(
+                FileContent::Content(File::from(serde_json::to_string_pretty(&manifest)?)).cell(),
+            )
+            .await?;
+    }
+
+    Ok(Vc::cell(chunks))
 }
 
-pub async fn build(args: &BuildArguments) -> Result<()> {
-    let NormalizedDirs {
-        project_dir,
-        root_dir,
-    } = normalize_dirs(&args.common.dir, &args.common.root)?;
+/// Resolves `entry_requests` to the entry modules of a build, relative to
+/// `origin_root`.
+async fn resolve_entries(
+    entry_requests: Vc<EntryRequests>,
+    asset_context: Vc<Box<dyn AssetContext>>,
+    origin_root: Vc<FileSystemPath>,
+    project_dir: &str,
+) -> Result<Vec<Vc<Box<dyn Module>>>> {
+    let entry_requests = (*entry_requests
+        .await?
+        .iter()
+        .cloned()
+        .map(|r| async move {
+            Ok(match &*r.await? {
+                EntryRequest::Relative(p) => {
+                    Request::relative(Value::new(p.clone().into()), Default::default(), false)
+                }
+                EntryRequest::Module(m, p) => {
+                    Request::module(m.clone(), Value::new(p.clone().into()), Default::default())
+                }
+            })
+        })
+        .try_join()
+        .await?)
+        .to_vec();
 
+    let origin = PlainResolveOrigin::new(asset_context, origin_root.join("_".to_string()));
+    entry_requests
+        .into_iter()
+        .map(|request_vc| async move {
+            let ty = Value::new(ReferenceType::Entry(EntryReferenceSubType::Undefined));
+            let request = request_vc.await?;
+            origin
+                .resolve_asset(request_vc, origin.resolve_options(ty.clone()), ty)
+                .first_module()
+                .await?
+                .with_context(|| {
+                    format!(
+                        "Unable to resolve entry {} from directory {}.",
+                        request.request().unwrap(),
+                        project_dir
+                    )
+                })
+        })
+        .try_join()
+        .await
+}
+
+pub async fn build(args: &BuildArguments) -> Result<()> {
     let tt = TurboTasks::new(MemoryBackend::new(
         args.common
             .memory_limit
             .map_or(usize::MAX, |l| l * 1024 * 1024),
     ));
 
+    cancel_on_exit_signal(&tt, build_with_turbo_tasks(tt.clone(), args)).await
+}
+
+/// Builds with an existing turbo-tasks instance. Everything computed by
+/// previous builds with the same instance is reused, see
+/// [crate::daemon].
+pub async fn build_with_turbo_tasks(
+    tt: Arc<TurboTasks<MemoryBackend>>,
+    args: &BuildArguments,
+) -> Result<()> {
+    let NormalizedDirs {
+        project_dir,
+        root_dir,
+    } = normalize_dirs(&args.common.dir, &args.common.root)?;
+    let changed_files = args
+        .affected_since
+        .as_deref()
+        .map(|git_ref| affected::changed_files(Path::new(&root_dir), git_ref))
+        .transpose()?;
+
     let mut builder = TurbopackBuildBuilder::new(tt, project_dir, root_dir)
+        .preset(EnvironmentPreset::named(&args.preset)?)
         .log_detail(args.common.log_detail)
         .log_level(
             args.common
@@ -332,10 +1015,41 @@ pub async fn build(args: &BuildArguments) -> Result<()> {
         } else {
             MinifyType::Minify
         })
-        .show_all(args.common.show_all);
+        .show_all(args.common.show_all)
+        .stats(args.stats)
+        .next_manifests(args.next_manifests)
+        .standalone(args.standalone)
+        .asset_manifest(args.asset_manifest)
+        .precompress(args.precompress)
+        .strict_rules(args.strict.clone())
+        .changed_files(changed_files)
+        .transform_cache_dir(
+            args.transform_cache_dir
+                .as_ref()
+                .map(|dir| dir.to_string_lossy().into_owned()),
+        )
+        .build_id(match &args.build_id {
+            Some(build_id) => build_id.clone(),
+            None => generate_build_id(args.build_id_generator, Path::new(&project_dir))?,
+        });
 
     for entry in normalize_entries(&args.common.entries) {
-        builder = builder.entry_request(EntryRequest::Relative(entry));
+        builder = match args
+            .entry_targets
+            .iter()
+            .find(|entry_target| entry_target.entry == entry)
+        {
+            Some(entry_target) => {
+                builder.entry_request_for_target(entry_target.target, EntryRequest::Relative(entry))
+            }
+            None => builder.entry_request(EntryRequest::Relative(entry)),
+        };
+    }
+
+    if let Some(experiments) = &args.experiments {
+        for experiment_arm in read_experiments_config(experiments)? {
+            builder = builder.experiment_arm(experiment_arm);
+        }
     }
 
     builder.build().await?;
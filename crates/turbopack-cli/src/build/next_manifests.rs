@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use rand::RngCore;
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+use turbo_tasks::Vc;
+use turbo_tasks_fs::FileSystemPath;
+use turbopack::ecmascript::{
+    segment_config::{SegmentConfig, SegmentDynamic, SegmentRevalidate},
+    EcmascriptModuleAsset,
+};
+use turbopack_core::{
+    module::Module,
+    output::{OutputAsset, OutputAssets},
+};
+
+/// The manifests the Next.js production server (`next start`) reads to route
+/// requests, in the shapes it expects. Pages are the entries in a `pages`
+/// directory of the project (or of its `src` directory), the middleware is a
+/// `middleware` entry next to it.
+///
+/// Only routing information is emitted. Rewrites, redirects and headers of a
+/// `next.config.js` are not read, and prerendered pages are declared but not
+/// rendered.
+#[derive(Debug)]
+pub struct NextManifests {
+    pub routes: RoutesManifest,
+    pub prerender: PrerenderManifest,
+    pub middleware: MiddlewareManifest,
+}
+
+/// `routes-manifest.json`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutesManifest {
+    pub version: u32,
+    pub pages404: bool,
+    pub base_path: String,
+    pub redirects: Vec<JsonValue>,
+    pub headers: Vec<JsonValue>,
+    pub rewrites: RoutesManifestRewrites,
+    pub static_routes: Vec<ManifestRoute>,
+    pub dynamic_routes: Vec<ManifestRoute>,
+    pub data_routes: Vec<JsonValue>,
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RoutesManifestRewrites {
+    pub before_files: Vec<JsonValue>,
+    pub after_files: Vec<JsonValue>,
+    pub fallback: Vec<JsonValue>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestRoute {
+    pub page: String,
+    pub regex: String,
+    /// The names of the capture groups of `named_regex`, by the params they
+    /// match.
+    pub route_keys: BTreeMap<String, String>,
+    pub named_regex: String,
+}
+
+/// `prerender-manifest.json`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PrerenderManifest {
+    pub version: u32,
+    pub routes: BTreeMap<String, PrerenderRoute>,
+    pub dynamic_routes: BTreeMap<String, PrerenderDynamicRoute>,
+    pub not_found_routes: Vec<String>,
+    pub preview: PreviewKeys,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PrerenderRoute {
+    /// The seconds after which the page is revalidated, `false` to cache it
+    /// indefinitely.
+    pub initial_revalidate_seconds: JsonValue,
+    pub src_route: Option<String>,
+    pub data_route: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PrerenderDynamicRoute {
+    pub route_regex: String,
+    pub data_route: String,
+    pub data_route_regex: String,
+    /// `null` renders params which weren't prerendered on the first request,
+    /// like `fallback: 'blocking'`.
+    pub fallback: Option<String>,
+}
+
+/// The keys of preview (draft) mode, random for every build.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewKeys {
+    pub preview_mode_id: String,
+    pub preview_mode_signing_key: String,
+    pub preview_mode_encryption_key: String,
+}
+
+/// `middleware-manifest.json`
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MiddlewareManifest {
+    pub version: u32,
+    pub sorted_middleware: Vec<String>,
+    pub middleware: BTreeMap<String, MiddlewareEntry>,
+    pub functions: BTreeMap<String, JsonValue>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MiddlewareEntry {
+    /// The chunks of the middleware, relative to the output directory.
+    pub files: Vec<String>,
+    pub name: String,
+    pub page: String,
+    pub matchers: Vec<MiddlewareMatcher>,
+    pub wasm: Vec<JsonValue>,
+    pub assets: Vec<JsonValue>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MiddlewareMatcher {
+    pub regexp: String,
+    pub original_source: String,
+}
+
+/// Collects the Next.js manifests of a build from its entry modules and the
+/// assets of their chunk groups.
+pub async fn next_manifests(
+    project_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    build_id: &str,
+    entries: &[(Vc<Box<dyn Module>>, Vc<OutputAssets>)],
+) -> Result<NextManifests> {
+    let project_path = project_path.await?;
+    let output_root = output_root.await?;
+
+    let mut pages = Vec::new();
+    let mut middleware = BTreeMap::new();
+    for &(module, assets) in entries {
+        let path = module.ident().path().await?;
+        let Some(relative) = project_path.get_path_to(&path) else {
+            continue;
+        };
+        if is_middleware(relative) {
+            let mut files = Vec::new();
+            for asset in assets.await?.iter() {
+                let path = asset.ident().path().await?;
+                if let Some(file) = output_root.get_path_to(&path) {
+                    if file.ends_with(".js") {
+                        files.push(file.to_string());
+                    }
+                }
+            }
+            files.sort();
+            middleware.insert(
+                "/".to_string(),
+                MiddlewareEntry {
+                    files,
+                    name: "middleware".to_string(),
+                    page: "/".to_string(),
+                    // The default matcher, `config.matcher` isn't read
+                    matchers: vec![MiddlewareMatcher {
+                        regexp: "^/.*$".to_string(),
+                        original_source: "/:path*".to_string(),
+                    }],
+                    wasm: Vec::new(),
+                    assets: Vec::new(),
+                },
+            );
+        } else if let Some(page) = page_of(relative) {
+            let config =
+                match Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(module).await? {
+                    Some(module) => module.segment_config().await?.clone_value(),
+                    None => SegmentConfig::default(),
+                };
+            pages.push((page, config));
+        }
+    }
+    pages.sort_by(|(a, _), (b, _)| route_order(a).cmp(&route_order(b)).then(a.cmp(b)));
+
+    let mut routes = RoutesManifest {
+        version: 3,
+        pages404: pages.iter().any(|(page, _)| page == "/404"),
+        base_path: String::new(),
+        redirects: Vec::new(),
+        headers: Vec::new(),
+        rewrites: Default::default(),
+        static_routes: Vec::new(),
+        dynamic_routes: Vec::new(),
+        data_routes: Vec::new(),
+    };
+    let mut prerender = PrerenderManifest {
+        version: 4,
+        routes: BTreeMap::new(),
+        dynamic_routes: BTreeMap::new(),
+        not_found_routes: Vec::new(),
+        preview: PreviewKeys {
+            preview_mode_id: random_hex(16),
+            preview_mode_signing_key: random_hex(32),
+            preview_mode_encryption_key: random_hex(32),
+        },
+    };
+    for (page, config) in pages {
+        let route = route_regex(&page);
+        let dynamic = is_dynamic_page(&page);
+        if let Some(revalidate) = initial_revalidate_seconds(&config) {
+            let data_route = format!(
+                "/_next/data/{build_id}{}.json",
+                if page == "/" { "/index" } else { &page }
+            );
+            if dynamic {
+                let data_route_regex = format!(
+                    "^/_next/data/{}{}\\.json$",
+                    escape_regex(build_id),
+                    route
+                        .regex
+                        .trim_start_matches('^')
+                        .trim_end_matches("(?:/)?$")
+                );
+                prerender.dynamic_routes.insert(
+                    page.clone(),
+                    PrerenderDynamicRoute {
+                        route_regex: route.regex.clone(),
+                        data_route,
+                        data_route_regex,
+                        fallback: None,
+                    },
+                );
+            } else {
+                prerender.routes.insert(
+                    page.clone(),
+                    PrerenderRoute {
+                        initial_revalidate_seconds: revalidate,
+                        src_route: None,
+                        data_route,
+                    },
+                );
+            }
+        }
+        if dynamic {
+            routes.dynamic_routes.push(route);
+        } else {
+            routes.static_routes.push(route);
+        }
+    }
+
+    Ok(NextManifests {
+        routes,
+        prerender,
+        middleware: MiddlewareManifest {
+            version: 2,
+            sorted_middleware: middleware.keys().cloned().collect(),
+            middleware,
+            functions: BTreeMap::new(),
+        },
+    })
+}
+
+/// The page of an entry in a `pages` directory, e. g. `/blog/[slug]` for
+/// `src/pages/blog/[slug].tsx`.
+pub(super) fn page_of(relative: &str) -> Option<String> {
+    let path = relative.strip_prefix("src/").unwrap_or(relative);
+    let path = path.strip_prefix("pages/")?;
+    let path = match path.rsplit_once('.') {
+        Some((stem, extension)) if !extension.contains([']', '/']) => stem,
+        _ => path,
+    };
+    let path = path.strip_suffix("/index").unwrap_or(path);
+    Some(if path == "index" {
+        "/".to_string()
+    } else {
+        format!("/{path}")
+    })
+}
+
+/// Whether an entry is the middleware of the project, a `middleware` module
+/// in the project or its `src` directory.
+pub(super) fn is_middleware(relative: &str) -> bool {
+    let path = relative.strip_prefix("src/").unwrap_or(relative);
+    matches!(
+        path.rsplit_once('.'),
+        Some(("middleware", "js" | "mjs" | "ts"))
+    )
+}
+
+/// A dynamic segment of a page, e. g. `[slug]`.
+enum Segment<'a> {
+    Static(&'a str),
+    Dynamic(&'a str),
+    CatchAll(&'a str),
+    OptionalCatchAll(&'a str),
+}
+
+fn segments(page: &str) -> impl Iterator<Item = Segment<'_>> {
+    page.split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| {
+            if let Some(name) = segment
+                .strip_prefix("[[...")
+                .and_then(|s| s.strip_suffix("]]"))
+            {
+                Segment::OptionalCatchAll(name)
+            } else if let Some(name) = segment
+                .strip_prefix("[...")
+                .and_then(|s| s.strip_suffix(']'))
+            {
+                Segment::CatchAll(name)
+            } else if let Some(name) = segment.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                Segment::Dynamic(name)
+            } else {
+                Segment::Static(segment)
+            }
+        })
+}
+
+fn is_dynamic_page(page: &str) -> bool {
+    segments(page).any(|segment| !matches!(segment, Segment::Static(_)))
+}
+
+/// Orders routes like the Next.js router matches them: static segments
+/// before dynamic segments before catch-all segments.
+pub(super) fn route_order(page: &str) -> Vec<u8> {
+    segments(page)
+        .map(|segment| match segment {
+            Segment::Static(_) => 0,
+            Segment::Dynamic(_) => 1,
+            Segment::CatchAll(_) => 2,
+            Segment::OptionalCatchAll(_) => 3,
+        })
+        .collect()
+}
+
+/// The regular expressions matching the pathnames of a page, like the
+/// `getRouteRegex` of Next.js.
+pub(super) fn route_regex(page: &str) -> ManifestRoute {
+    let mut regex = String::new();
+    let mut named_regex = String::new();
+    let mut route_keys = BTreeMap::new();
+    let mut group = |name: &str, pattern: &str| {
+        let key = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect::<String>();
+        route_keys.insert(key.clone(), name.to_string());
+        format!("(?<{key}>{pattern})")
+    };
+    for segment in segments(page) {
+        match segment {
+            Segment::Static(segment) => {
+                regex.push_str(&format!("/{}", escape_regex(segment)));
+                named_regex.push_str(&format!("/{}", escape_regex(segment)));
+            }
+            Segment::Dynamic(name) => {
+                regex.push_str("/([^/]+?)");
+                named_regex.push_str(&format!("/{}", group(name, "[^/]+?")));
+            }
+            Segment::CatchAll(name) => {
+                regex.push_str("/(.+?)");
+                named_regex.push_str(&format!("/{}", group(name, ".+?")));
+            }
+            Segment::OptionalCatchAll(name) => {
+                regex.push_str("(?:/(.+?))?");
+                named_regex.push_str(&format!("(?:/{})?", group(name, ".+?")));
+            }
+        }
+    }
+    if regex.is_empty() {
+        regex.push('/');
+        named_regex.push('/');
+    }
+    ManifestRoute {
+        page: page.to_string(),
+        regex: format!("^{regex}(?:/)?$"),
+        route_keys,
+        named_regex: format!("^{named_regex}(?:/)?$"),
+    }
+}
+
+fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "|\\{}()[]^$+*?.-".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The `initialRevalidateSeconds` of a page which can be prerendered, `None`
+/// for pages which are rendered on every request or don't declare caching.
+fn initial_revalidate_seconds(config: &SegmentConfig) -> Option<JsonValue> {
+    if config.is_dynamic() {
+        return None;
+    }
+    match config.revalidate {
+        Some(SegmentRevalidate::Seconds(seconds)) => Some(json!(seconds)),
+        Some(SegmentRevalidate::Never) => Some(json!(false)),
+        None if matches!(config.dynamic, Some(SegmentDynamic::ForceStatic)) => Some(json!(false)),
+        None => None,
+    }
+}
+
+fn random_hex(bytes: usize) -> String {
+    let mut random = vec![0; bytes];
+    rand::thread_rng().fill_bytes(&mut random);
+    random.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{File, FileContent};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    emit_hook::EmitHook,
+    output::{OutputAsset, OutputAssets},
+    virtual_output::VirtualOutputAsset,
+};
+
+use crate::export::output::precompressed;
+
+/// The assets [PrecompressEmitHook] processes: text files, like
+/// [crate::export::output::is_compressible].
+pub const PRECOMPRESS_FILTER: &str = "**/*.{html,htm,js,mjs,cjs,css,json,map,svg,xml,txt}";
+
+/// An [EmitHook] writing gzip and brotli compressed `.gz` and `.br` siblings
+/// of assets, for static hosts serving precompressed files.
+#[turbo_tasks::value]
+pub struct PrecompressEmitHook;
+
+#[turbo_tasks::value_impl]
+impl PrecompressEmitHook {
+    #[turbo_tasks::function]
+    pub fn new() -> Vc<Self> {
+        PrecompressEmitHook.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl EmitHook for PrecompressEmitHook {
+    #[turbo_tasks::function]
+    async fn process(&self, asset: Vc<Box<dyn OutputAsset>>) -> Result<Vc<OutputAssets>> {
+        let file_content = asset.content().file_content().await?;
+        let FileContent::Content(file) = &*file_content else {
+            return Ok(OutputAssets::empty());
+        };
+        let path = asset.ident().path();
+        Ok(Vc::cell(
+            precompressed(&file.content().to_bytes()?)
+                .await?
+                .into_iter()
+                .map(|(extension, content)| {
+                    Vc::upcast(VirtualOutputAsset::new(
+                        path.append(format!(".{extension}")),
+                        AssetContent::file(File::from(content).into()),
+                    ))
+                })
+                .collect(),
+        ))
+    }
+}
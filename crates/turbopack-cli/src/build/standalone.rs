@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+use turbo_tasks::{TryJoinIterExt, Value, Vc};
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbopack::ecmascript::EcmascriptModuleAsset;
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    chunk::{availability_info::AvailabilityInfo, ChunkingContext, EvaluatableAssets},
+    context::AssetContext,
+    module::Module,
+    output::{OutputAsset, OutputAssets},
+    reference::all_assets_from_entries,
+    reference_type::{EntryReferenceSubType, ReferenceType},
+    source::Source,
+    virtual_source::VirtualSource,
+};
+use turbopack_nodejs::NodeJsChunkingContext;
+
+use super::next_manifests::{is_middleware, page_of, route_order, route_regex};
+
+/// The path of the server of a standalone build in the embedded JS files. It
+/// is copied into the output directory as `server.js`.
+pub const STANDALONE_SERVER: &str = "standalone/server.js";
+
+/// The directory of the render entries in the output directory. The server
+/// doesn't serve it.
+pub const RENDER_DIR: &str = "server";
+
+/// How the pages of a standalone build are compiled for rendering them on the
+/// server.
+#[turbo_tasks::value(shared)]
+pub struct StandaloneOptions {
+    pub build_id: String,
+    /// The context of a Node.js target, with which the render entries are
+    /// processed.
+    pub render_asset_context: Vc<Box<dyn AssetContext>>,
+    /// Chunks the render entries into [RENDER_DIR].
+    pub render_chunking_context: Vc<Box<dyn ChunkingContext>>,
+}
+
+/// `standalone-manifest.json`, from which the `server.js` of a standalone
+/// build routes requests. Pages are routed like in [super::next_manifests],
+/// rendered on the server by their render entry, and served as a document
+/// loading the chunks of the page.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StandaloneManifest {
+    /// Files in the directory of the build ID are cached indefinitely.
+    pub build_id: String,
+    /// The pages of the build, in the order they are matched.
+    pub routes: Vec<StandaloneRoute>,
+    /// The chunks of all entries, loaded for every path when the build has no
+    /// pages.
+    pub fallback_files: Vec<String>,
+    /// The entry chunk of the middleware, which runs before every request it
+    /// matches.
+    pub middleware: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StandaloneRoute {
+    pub page: String,
+    pub regex: String,
+    /// The names of the capture groups of `named_regex`, by the params they
+    /// match.
+    pub route_keys: BTreeMap<String, String>,
+    pub named_regex: String,
+    /// The chunks of the page, relative to the output directory.
+    pub files: Vec<String>,
+    /// The render entry of the page, see [page_render_source].
+    pub render: Option<String>,
+}
+
+/// A module rendering the page `page_path` to HTML with `react-dom/server`.
+/// It exports `render(props)` and the `getServerSideProps` of the page, which
+/// the server calls to get the props.
+#[turbo_tasks::function]
+pub async fn page_render_source(page_path: Vc<FileSystemPath>) -> Result<Vc<Box<dyn Source>>> {
+    let page = page_path.await?;
+    let file_name = page.file_name();
+    let stem = page
+        .extension_ref()
+        .and_then(|extension| file_name.strip_suffix(&format!(".{extension}")))
+        .unwrap_or(file_name);
+    let specifier = serde_json::to_string(&format!("./{file_name}"))?;
+    let source = VirtualSource::new(
+        page_path
+            .parent()
+            .join(format!("__turbopack_render_{stem}__.js")),
+        AssetContent::file(
+            File::from(format!(
+                r#"import {{ createElement }} from "react";
+import {{ renderToString }} from "react-dom/server";
+import * as page from {specifier};
+
+export const getServerSideProps = page.getServerSideProps;
+
+export function render(props) {{
+  return renderToString(createElement(page.default, props));
+}}
+"#
+            ))
+            .into(),
+        ),
+    );
+    Ok(Vc::upcast(source))
+}
+
+/// Compiles and writes the render entries of the pages among `entries`.
+/// Returns their files by page, relative to `output_root`.
+pub async fn emit_render_entries(
+    project_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    options: &StandaloneOptions,
+    entries: &[(Vc<Box<dyn Module>>, Vc<OutputAssets>)],
+) -> Result<BTreeMap<String, String>> {
+    let project_path_value = project_path.await?;
+    let output_root_value = output_root.await?;
+    let Some(chunking_context) =
+        Vc::try_resolve_downcast_type::<NodeJsChunkingContext>(options.render_chunking_context)
+            .await?
+    else {
+        bail!("Render entries need a Node.js chunking context");
+    };
+
+    let mut pages = Vec::new();
+    for &(module, _) in entries {
+        let path = module.ident().path();
+        let path_value = path.await?;
+        let Some(relative) = project_path_value.get_path_to(&path_value) else {
+            continue;
+        };
+        if is_middleware(relative) {
+            continue;
+        }
+        if let Some(page) = page_of(relative) {
+            pages.push((page, path));
+        }
+    }
+
+    let mut render_entries = BTreeMap::new();
+    for (page, path) in pages {
+        let module = options
+            .render_asset_context
+            .process(
+                page_render_source(path),
+                Value::new(ReferenceType::Entry(EntryReferenceSubType::Undefined)),
+            )
+            .module();
+        let Some(ecmascript) =
+            Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(module).await?
+        else {
+            bail!("The render entry of {page} is not an ECMAScript module");
+        };
+        let name = if page == "/" { "/index" } else { &page };
+        let entry_path = output_root.join(format!("{RENDER_DIR}/pages{name}.js"));
+        let entry = chunking_context
+            .entry_chunk_group(
+                entry_path,
+                Vc::upcast(ecmascript),
+                EvaluatableAssets::one(Vc::upcast(ecmascript)),
+                Value::new(AvailabilityInfo::Root),
+            )
+            .await?
+            .asset;
+        all_assets_from_entries(Vc::cell(vec![entry]))
+            .await?
+            .iter()
+            .map(|asset| asset.content().write(asset.ident().path()))
+            .try_join()
+            .await?;
+        if let Some(file) = output_root_value.get_path_to(&*entry_path.await?) {
+            render_entries.insert(page, file.to_string());
+        }
+    }
+    Ok(render_entries)
+}
+
+/// Collects the routing table of a standalone build from its entry modules,
+/// the assets of their chunk groups and the render entries of the pages.
+pub async fn standalone_manifest(
+    project_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    build_id: &str,
+    entries: &[(Vc<Box<dyn Module>>, Vc<OutputAssets>)],
+    render_entries: &BTreeMap<String, String>,
+) -> Result<StandaloneManifest> {
+    let project_path = project_path.await?;
+    let output_root = output_root.await?;
+
+    let mut routes = Vec::new();
+    let mut fallback_files = Vec::new();
+    let mut middleware = None;
+    for &(module, assets) in entries {
+        let path = module.ident().path().await?;
+        let relative = project_path.get_path_to(&path);
+        if relative.map_or(false, is_middleware) {
+            // The entry chunk exports the `middleware` function and `config`
+            for asset in assets.await?.iter() {
+                let path = asset.ident().path().await?;
+                if let Some(file) = output_root.get_path_to(&path) {
+                    if file.ends_with(".js") {
+                        middleware = Some(file.to_string());
+                    }
+                }
+            }
+            continue;
+        }
+        let mut files = Vec::new();
+        for asset in assets.await?.iter() {
+            let path = asset.ident().path().await?;
+            if let Some(file) = output_root.get_path_to(&path) {
+                if (file.ends_with(".js") || file.ends_with(".css"))
+                    && !files.iter().any(|f| f == file)
+                {
+                    files.push(file.to_string());
+                }
+            }
+        }
+        for file in &files {
+            if !fallback_files.contains(file) {
+                fallback_files.push(file.clone());
+            }
+        }
+        if let Some(page) = relative.and_then(page_of) {
+            routes.push((page, files));
+        }
+    }
+    routes.sort_by(|(a, _), (b, _)| route_order(a).cmp(&route_order(b)).then(a.cmp(b)));
+
+    Ok(StandaloneManifest {
+        build_id: build_id.to_string(),
+        routes: routes
+            .into_iter()
+            .map(|(page, files)| {
+                let route = route_regex(&page);
+                StandaloneRoute {
+                    regex: route.regex,
+                    route_keys: route.route_keys,
+                    named_regex: route.named_regex,
+                    render: render_entries.get(&page).cloned(),
+                    page,
+                    files,
+                }
+            })
+            .collect(),
+        fallback_files,
+        middleware,
+    })
+}
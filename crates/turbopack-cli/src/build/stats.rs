@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use anyhow::Result;
+use serde::Serialize;
+use turbo_tasks::{ValueToString, Vc};
+use turbo_tasks_fs::{FileContent, FileSystemPath};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    chunk::{ChunkableModuleReference, ChunkingType},
+    introspect::Introspectable,
+    module::Module,
+    output::{OutputAsset, OutputAssets},
+};
+
+/// A description of a build in the format of webpack's `stats.json`, so
+/// bundle analysis tools like statoscope or bundle-buddy work with Turbopack
+/// output. Only the parts of the format these tools read are emitted.
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WebpackStats {
+    pub output_path: String,
+    pub assets: Vec<StatsAsset>,
+    pub chunks: Vec<StatsChunk>,
+    pub modules: Vec<StatsModule>,
+    pub entrypoints: BTreeMap<String, StatsEntrypoint>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsAsset {
+    pub name: String,
+    pub size: u64,
+    pub chunks: Vec<usize>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsChunk {
+    pub id: usize,
+    pub names: Vec<String>,
+    pub files: Vec<String>,
+    pub size: u64,
+    pub entry: bool,
+    pub initial: bool,
+    /// The identifiers of the modules in the chunk.
+    pub modules: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsModule {
+    pub id: usize,
+    pub identifier: String,
+    /// The path of the module relative to the project, e. g.
+    /// `./src/index.js`.
+    pub name: String,
+    pub size: u64,
+    pub chunks: Vec<usize>,
+    pub reasons: Vec<StatsReason>,
+}
+
+/// A reference to a module from another module.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsReason {
+    pub module_identifier: String,
+    pub module_name: String,
+    /// `import()` for references which load the module on demand, `import`
+    /// otherwise.
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    pub user_request: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsEntrypoint {
+    pub name: String,
+    pub chunks: Vec<usize>,
+    pub assets: Vec<StatsEntrypointAsset>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StatsEntrypointAsset {
+    pub name: String,
+}
+
+/// Collects the stats of a build from its entry modules, with the assets of
+/// the chunk group of each entry, and all emitted assets.
+pub async fn webpack_stats(
+    project_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    entries: &[(String, Vc<Box<dyn Module>>, Vc<OutputAssets>)],
+    assets: &[Vc<Box<dyn OutputAsset>>],
+) -> Result<WebpackStats> {
+    let project_path = project_path.await?;
+    let output_root_value = output_root.await?;
+    let mut stats = WebpackStats {
+        output_path: output_root_value.path.clone(),
+        ..Default::default()
+    };
+
+    let mut module_ids = HashMap::new();
+    let mut queue = entries
+        .iter()
+        .map(|&(_, module, _)| module)
+        .collect::<VecDeque<_>>();
+    let mut seen = queue.iter().copied().collect::<HashSet<_>>();
+    let mut modules = Vec::new();
+    while let Some(module) = queue.pop_front() {
+        modules.push(module);
+        let ident = module.ident();
+        let identifier = ident.to_string().await?.clone_value();
+        let path = ident.path().await?;
+        let name = match project_path.get_path_to(&path) {
+            Some(path) => format!("./{path}"),
+            None => identifier.clone(),
+        };
+        module_ids.insert(identifier.clone(), stats.modules.len());
+        stats.modules.push(StatsModule {
+            id: stats.modules.len(),
+            identifier,
+            name,
+            size: content_size(module.content()).await?,
+            chunks: Vec::new(),
+            reasons: Vec::new(),
+        });
+        for &reference in module.references().await?.iter() {
+            for &referenced in reference
+                .resolve_reference()
+                .primary_modules()
+                .await?
+                .iter()
+            {
+                if seen.insert(referenced) {
+                    queue.push_back(referenced);
+                }
+            }
+        }
+    }
+
+    // Reasons need the identifiers of all referenced modules, so they are
+    // collected in a second pass.
+    for module in modules {
+        let identifier = module.ident().to_string().await?;
+        let module_name = stats.modules[module_ids[&*identifier]].name.clone();
+        for &reference in module.references().await?.iter() {
+            let ty = match Vc::try_resolve_downcast::<Box<dyn ChunkableModuleReference>>(reference)
+                .await?
+            {
+                Some(chunkable) => match &*chunkable.chunking_type().await? {
+                    Some(ChunkingType::Async) => "import()",
+                    _ => "import",
+                },
+                None => "import",
+            };
+            let user_request = reference.to_string().await?.clone_value();
+            for &referenced in reference
+                .resolve_reference()
+                .primary_modules()
+                .await?
+                .iter()
+            {
+                let referenced = referenced.ident().to_string().await?;
+                if let Some(&id) = module_ids.get(&*referenced) {
+                    stats.modules[id].reasons.push(StatsReason {
+                        module_identifier: identifier.clone_value(),
+                        module_name: module_name.clone(),
+                        ty,
+                        user_request: user_request.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    let mut initial_assets = HashSet::new();
+    let mut entry_asset_lists = Vec::new();
+    for &(_, _, entry_assets) in entries {
+        let entry_assets = entry_assets.await?.clone_value();
+        initial_assets.extend(entry_assets.iter().copied());
+        entry_asset_lists.push(entry_assets);
+    }
+
+    let mut named_assets = Vec::new();
+    for &asset in assets {
+        let path = asset.ident().path().await?;
+        if let Some(name) = output_root_value.get_path_to(&path) {
+            named_assets.push((name.to_string(), asset));
+        }
+    }
+    named_assets.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut asset_names = HashMap::new();
+    for (name, asset) in named_assets {
+        let size = content_size(asset.content()).await?;
+        asset_names.insert(asset, name.clone());
+
+        let modules = chunk_modules(asset).await?;
+        if modules.is_empty() {
+            stats.assets.push(StatsAsset {
+                name,
+                size,
+                chunks: Vec::new(),
+            });
+            continue;
+        }
+        let id = stats.chunks.len();
+        for identifier in &modules {
+            if let Some(&module) = module_ids.get(identifier) {
+                stats.modules[module].chunks.push(id);
+            }
+        }
+        let initial = initial_assets.contains(&asset);
+        stats.chunks.push(StatsChunk {
+            id,
+            names: Vec::new(),
+            files: vec![name.clone()],
+            size,
+            entry: initial,
+            initial,
+            modules,
+        });
+        stats.assets.push(StatsAsset {
+            name,
+            size,
+            chunks: vec![id],
+        });
+    }
+
+    let chunk_ids = stats
+        .chunks
+        .iter()
+        .map(|chunk| (chunk.files[0].clone(), chunk.id))
+        .collect::<HashMap<_, _>>();
+    for ((name, _, _), entry_assets) in entries.iter().zip(entry_asset_lists) {
+        let names = entry_assets
+            .iter()
+            .filter_map(|asset| asset_names.get(asset).cloned())
+            .collect::<Vec<_>>();
+        let chunks = names
+            .iter()
+            .filter_map(|name| chunk_ids.get(name).copied())
+            .collect::<Vec<_>>();
+        for &chunk in &chunks {
+            stats.chunks[chunk].names.push(name.clone());
+        }
+        stats.entrypoints.insert(
+            name.clone(),
+            StatsEntrypoint {
+                name: name.clone(),
+                chunks,
+                assets: names
+                    .into_iter()
+                    .map(|name| StatsEntrypointAsset { name })
+                    .collect(),
+            },
+        );
+    }
+
+    Ok(stats)
+}
+
+async fn content_size(content: Vc<AssetContent>) -> Result<u64> {
+    Ok(match &*content.await? {
+        AssetContent::File(file) => match &*file.await? {
+            FileContent::Content(file) => file.content().len() as u64,
+            FileContent::NotFound => 0,
+        },
+        AssetContent::Redirect { .. } => 0,
+    })
+}
+
+/// The identifiers of the modules in a chunk, read from the introspection of
+/// the chunk. Empty for other assets.
+async fn chunk_modules(asset: Vc<Box<dyn OutputAsset>>) -> Result<Vec<String>> {
+    let Some(introspectable) = Vc::try_resolve_sidecast::<Box<dyn Introspectable>>(asset).await?
+    else {
+        return Ok(Vec::new());
+    };
+    let mut modules = Vec::new();
+    // Output chunks wrap the chunk, which has the modules as children.
+    let mut queue = vec![(introspectable, 0)];
+    while let Some((introspectable, depth)) = queue.pop() {
+        for &(key, child) in introspectable.children().await?.iter() {
+            match key.await?.as_str() {
+                "module" | "entry module" => {
+                    modules.push(child.title().await?.clone_value());
+                }
+                "chunk" if depth == 0 => queue.push((child, depth + 1)),
+                _ => {}
+            }
+        }
+    }
+    Ok(modules)
+}
@@ -0,0 +1,109 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+use turbo_tasks::Vc;
+use turbopack_core::issue::{
+    IssueDescriptionExt, IssueSeverity, IssueStage, PlainIssue, StyledString,
+};
+
+/// A rule of the strict mode, which fails the build when a warning matches
+/// it. Written as `warnings`, `unresolved`, `dual-packages` or
+/// `title:<text>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StrictRule {
+    /// Every warning.
+    Warnings,
+    /// Requests which can't be resolved but are reported as warnings, e. g.
+    /// optional dependencies imported in a `try` block.
+    Unresolved,
+    /// Packages whose ES module and CommonJS builds are both included, see
+    /// [turbopack::ecmascript::dual_package_hazard].
+    DualPackages,
+    /// Warnings whose title contains the text, e. g. of a plugin.
+    Title(String),
+}
+
+impl StrictRule {
+    fn matches(&self, issue: &PlainIssue) -> bool {
+        if issue.severity != IssueSeverity::Warning {
+            return false;
+        }
+        let title = plain_text(&issue.title);
+        match self {
+            StrictRule::Warnings => true,
+            StrictRule::Unresolved => {
+                issue.stage == IssueStage::Resolve && title.starts_with("Module not found")
+            }
+            StrictRule::DualPackages => {
+                title.starts_with("Both the ES module and the CommonJS build of")
+            }
+            StrictRule::Title(text) => title.contains(text.as_str()),
+        }
+    }
+}
+
+impl FromStr for StrictRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "warnings" => StrictRule::Warnings,
+            "unresolved" => StrictRule::Unresolved,
+            "dual-packages" => StrictRule::DualPackages,
+            _ => match s.strip_prefix("title:") {
+                Some(text) if !text.is_empty() => StrictRule::Title(text.to_string()),
+                _ => bail!(
+                    "strict rule must be `warnings`, `unresolved`, `dual-packages` or \
+                     `title:<text>`"
+                ),
+            },
+        })
+    }
+}
+
+impl fmt::Display for StrictRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StrictRule::Warnings => write!(f, "warnings"),
+            StrictRule::Unresolved => write!(f, "unresolved"),
+            StrictRule::DualPackages => write!(f, "dual-packages"),
+            StrictRule::Title(text) => write!(f, "title:{text}"),
+        }
+    }
+}
+
+/// Fails when a warning of `source` matches one of the `rules`. Called at
+/// the end of a build, after its issues were reported.
+pub async fn check_strict_rules<T: Send>(source: Vc<T>, rules: &[StrictRule]) -> Result<()> {
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let issues = source.peek_issues_with_path().await?;
+    let mut failures = Vec::new();
+    for issue in issues.get_plain_issues().await? {
+        if let Some(rule) = rules.iter().find(|rule| rule.matches(&issue)) {
+            failures.push(format!(
+                "{} ({}, strict rule `{rule}`)",
+                plain_text(&issue.title),
+                issue.file_path
+            ));
+        }
+    }
+    if !failures.is_empty() {
+        bail!(
+            "The build has warnings failing the strict mode:\n  {}",
+            failures.join("\n  ")
+        );
+    }
+    Ok(())
+}
+
+fn plain_text(styled_string: &StyledString) -> String {
+    match styled_string {
+        StyledString::Line(parts) => parts.iter().map(plain_text).collect(),
+        StyledString::Stack(parts) => parts.iter().map(plain_text).collect::<Vec<_>>().join("\n"),
+        StyledString::Text(string) | StyledString::Code(string) | StyledString::Strong(string) => {
+            string.clone()
+        }
+    }
+}
@@ -0,0 +1,819 @@
+use std::{fmt, path::Path};
+
+use anyhow::{anyhow, Result};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use serde_json::{json, Map, Value as JsonValue};
+use swc_core::{
+    common::{sync::Lrc, FileName, SourceMap, Span, Spanned},
+    ecma::{
+        ast::{
+            ArrowExpr, AssignExpr, AssignOp, BlockStmtOrExpr, CallExpr, Callee, EsVersion, Expr,
+            ExprOrSpread, Function, Lit, MemberProp, ObjectLit, Pat, Program, Prop, PropName,
+            PropOrSpread, ReturnStmt, Stmt,
+        },
+        parser::{lexer::Lexer, EsConfig, Parser, StringInput, Syntax},
+        visit::{Visit, VisitWith},
+    },
+};
+use turbopack::ecmascript::utils::unparen;
+
+use super::NATIVE_LOADERS;
+
+pub(super) const NEXT_CONFIG_FILES: &[&str] = &["next.config.js", "next.config.mjs"];
+
+/// Loaders which output JavaScript. Rules with only these loaders are
+/// generated with `as: "*.js"`, so their output is processed as JavaScript.
+const JS_OUTPUT_LOADERS: &[&str] = &[
+    "@svgr/webpack",
+    "@mdx-js/loader",
+    "graphql-tag/loader",
+    "raw-loader",
+    "yaml-loader",
+];
+
+/// The default `resolveExtensions`, which extensions pushed by the webpack
+/// config are added to.
+const DEFAULT_RESOLVE_EXTENSIONS: &[&str] = &[".tsx", ".ts", ".jsx", ".js", ".mjs", ".json"];
+
+/// How an option of the next.config is migrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MigrationStatus {
+    /// The generated config covers the option.
+    Migrated,
+    /// The generated config covers the option, but it runs through a compat
+    /// layer, e. g. webpack loaders running in Node.js.
+    CompatLayer,
+    /// The option has to be migrated by hand.
+    Manual,
+}
+
+impl fmt::Display for MigrationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationStatus::Migrated => f.write_str("migrated"),
+            MigrationStatus::CompatLayer => f.write_str("migrated to the compat layer"),
+            MigrationStatus::Manual => f.write_str("needs manual migration"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationItem {
+    pub status: MigrationStatus,
+    /// The line of the option in the config file.
+    pub line: usize,
+    pub option: String,
+    pub note: String,
+}
+
+/// The migration of the webpack customizations of a next.config to the
+/// Turbopack options in `experimental.turbo`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    /// The config file, relative to the project directory.
+    pub file: String,
+    pub items: Vec<MigrationItem>,
+    /// The generated `experimental.turbo` options, if any option could be
+    /// migrated.
+    pub turbo: Option<JsonValue>,
+}
+
+impl fmt::Display for MigrationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", format!("Migration of {}", self.file).bold())?;
+        if self.items.is_empty() {
+            writeln!(f, "  No webpack customizations were found.")?;
+        }
+        for item in &self.items {
+            let status = match item.status {
+                MigrationStatus::Migrated => item.status.to_string().green().to_string(),
+                MigrationStatus::CompatLayer => item.status.to_string().yellow().to_string(),
+                MigrationStatus::Manual => item.status.to_string().red().to_string(),
+            };
+            writeln!(
+                f,
+                "  {} {} {}\n    {}",
+                status,
+                item.option,
+                format!("(line {})", item.line).dimmed(),
+                item.note
+            )?;
+        }
+        if let Some(turbo) = &self.turbo {
+            let config = json!({ "experimental": { "turbo": turbo } });
+            writeln!(f, "\nMerge into the config in {}:", self.file)?;
+            writeln!(
+                f,
+                "{}",
+                serde_json::to_string_pretty(&config).map_err(|_| fmt::Error)?
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the webpack config function and the config plugins of the
+/// next.config of the project, and generates the equivalent
+/// `experimental.turbo` options for the customizations which map cleanly:
+/// loader rules, `resolve.alias` and `resolve.extensions`. Everything else is
+/// reported for manual migration.
+///
+/// Returns `None` when the project has no next.config.
+pub fn migrate_next_config(project_dir: &Path) -> Result<Option<MigrationReport>> {
+    for file in NEXT_CONFIG_FILES {
+        if let Ok(content) = std::fs::read_to_string(project_dir.join(file)) {
+            return migrate(file, content).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+/// Parses the config `file` with SWC.
+pub(super) fn parse_config(file: &str, content: String) -> Result<(Lrc<SourceMap>, Program)> {
+    let source_map: Lrc<SourceMap> = Default::default();
+    let source_file = source_map.new_source_file(FileName::Custom(file.to_string()), content);
+    let lexer = Lexer::new(
+        Syntax::Es(EsConfig::default()),
+        EsVersion::latest(),
+        StringInput::from(&*source_file),
+        None,
+    );
+    let program = Parser::new_from(lexer)
+        .parse_program()
+        .map_err(|err| anyhow!("parsing {file}: {}", err.kind().msg()))?;
+    Ok((source_map, program))
+}
+
+fn migrate(file: &str, content: String) -> Result<MigrationReport> {
+    let (source_map, program) = parse_config(file, content)?;
+
+    let mut finder = ConfigFinder::default();
+    program.visit_with(&mut finder);
+
+    let mut migration = Migration {
+        source_map: &source_map,
+        items: Vec::new(),
+        rules: Map::new(),
+        resolve_alias: Map::new(),
+        resolve_extensions: None,
+    };
+    for (plugin, span) in finder.plugins {
+        migration.add(
+            MigrationStatus::Manual,
+            span,
+            plugin,
+            "Config plugins can customize the webpack config, which Turbopack doesn't apply. \
+             Check whether the plugin supports Turbopack.",
+        );
+    }
+    if let Some(webpack) = finder.webpack {
+        migration.migrate_webpack(&webpack);
+    }
+    Ok(migration.finish(file))
+}
+
+/// The webpack config function of a next.config.
+struct WebpackFunction {
+    /// The name of the parameter with the webpack config.
+    config: Option<String>,
+    body: Vec<Stmt>,
+    span: Span,
+}
+
+/// Finds the webpack config function and the calls of config plugins, e. g.
+/// `withBundleAnalyzer(...)`.
+#[derive(Default)]
+struct ConfigFinder {
+    webpack: Option<WebpackFunction>,
+    plugins: Vec<(String, Span)>,
+}
+
+impl Visit for ConfigFinder {
+    fn visit_prop(&mut self, prop: &Prop) {
+        if self.webpack.is_none() {
+            self.webpack = match prop {
+                Prop::Method(method) if prop_name(&method.key) == Some("webpack") => {
+                    Some(webpack_function(&method.function))
+                }
+                Prop::KeyValue(key_value) if prop_name(&key_value.key) == Some("webpack") => {
+                    match unparen(&key_value.value) {
+                        Expr::Fn(function) => Some(webpack_function(&function.function)),
+                        Expr::Arrow(arrow) => Some(webpack_arrow(arrow)),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+        }
+        prop.visit_children_with(self);
+    }
+
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        if let Callee::Expr(callee) = &call.callee {
+            if let Expr::Ident(ident) = unparen(callee) {
+                let is_plugin = ident
+                    .sym
+                    .strip_prefix("with")
+                    .and_then(|name| name.chars().next())
+                    .map_or(false, |c| c.is_ascii_uppercase());
+                if is_plugin {
+                    self.plugins.push((ident.sym.to_string(), call.span));
+                }
+            }
+        }
+        call.visit_children_with(self);
+    }
+}
+
+fn webpack_function(function: &Function) -> WebpackFunction {
+    WebpackFunction {
+        config: function
+            .params
+            .first()
+            .and_then(|param| pat_name(&param.pat)),
+        body: function
+            .body
+            .as_ref()
+            .map(|body| body.stmts.clone())
+            .unwrap_or_default(),
+        span: function.span,
+    }
+}
+
+fn webpack_arrow(arrow: &ArrowExpr) -> WebpackFunction {
+    WebpackFunction {
+        config: arrow.params.first().and_then(pat_name),
+        body: match &*arrow.body {
+            BlockStmtOrExpr::BlockStmt(block) => block.stmts.clone(),
+            BlockStmtOrExpr::Expr(expr) => vec![Stmt::Return(ReturnStmt {
+                span: expr.span(),
+                arg: Some(expr.clone()),
+            })],
+        },
+        span: arrow.span,
+    }
+}
+
+fn pat_name(pat: &Pat) -> Option<String> {
+    Some(pat.as_ident()?.id.sym.to_string())
+}
+
+pub(super) fn prop_name(name: &PropName) -> Option<&str> {
+    match name {
+        PropName::Ident(ident) => Some(&*ident.sym),
+        PropName::Str(str) => Some(&*str.value),
+        _ => None,
+    }
+}
+
+/// The dotted path of a member expression, e. g. `config.resolve.alias`.
+pub(super) fn member_path(expr: &Expr) -> Option<String> {
+    match unparen(expr) {
+        Expr::Ident(ident) => Some(ident.sym.to_string()),
+        Expr::Member(member) => {
+            let object = member_path(&member.obj)?;
+            let property = member.prop.as_ident()?;
+            Some(format!("{object}.{}", property.sym))
+        }
+        _ => None,
+    }
+}
+
+fn string_value(expr: &Expr) -> Option<String> {
+    match unparen(expr) {
+        Expr::Lit(Lit::Str(str)) => Some(str.value.to_string()),
+        Expr::Tpl(tpl) if tpl.exprs.is_empty() => {
+            Some(tpl.quasis.first()?.cooked.as_ref()?.to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Converts a literal expression, e. g. loader options, to JSON.
+fn json_value(expr: &Expr) -> Option<JsonValue> {
+    match unparen(expr) {
+        Expr::Lit(Lit::Str(str)) => Some(JsonValue::String(str.value.to_string())),
+        Expr::Lit(Lit::Bool(bool)) => Some(JsonValue::Bool(bool.value)),
+        Expr::Lit(Lit::Null(_)) => Some(JsonValue::Null),
+        Expr::Lit(Lit::Num(num)) => Some(json!(num.value)),
+        Expr::Array(array) => array
+            .elems
+            .iter()
+            .map(|elem| match elem {
+                Some(ExprOrSpread { spread: None, expr }) => json_value(expr),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(JsonValue::Array),
+        Expr::Object(object) => object
+            .props
+            .iter()
+            .map(|prop| {
+                let key_value = prop.as_prop()?.as_key_value()?;
+                Some((
+                    prop_name(&key_value.key)?.to_string(),
+                    json_value(&key_value.value)?,
+                ))
+            })
+            .collect::<Option<Map<_, _>>>()
+            .map(JsonValue::Object),
+        _ => string_value(expr).map(JsonValue::String),
+    }
+}
+
+/// Converts the `test` regex of a loader rule, e. g. `/\.svg$/` or
+/// `/\.(md|mdx)$/`, to the globs of `experimental.turbo.rules`.
+fn regex_globs(regex: &str) -> Option<Vec<String>> {
+    let extensions = regex.strip_prefix("\\.")?.strip_suffix('$')?;
+    let alternatives = match extensions
+        .strip_prefix("(?:")
+        .or_else(|| extensions.strip_prefix('('))
+    {
+        Some(group) => group.strip_suffix(')')?.split('|').collect::<Vec<_>>(),
+        None => vec![extensions],
+    };
+    let mut globs = Vec::new();
+    for alternative in alternatives {
+        // `mdx?` matches `md` and `mdx`
+        let (extension, optional) = match alternative.strip_suffix('?') {
+            Some(extension) => (extension, true),
+            None => (alternative, false),
+        };
+        if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        if optional {
+            globs.push(format!("*.{}", &extension[..extension.len() - 1]));
+        }
+        globs.push(format!("*.{extension}"));
+    }
+    Some(globs)
+}
+
+/// The loaders of a `use`, `loader` or `loaders` value of a rule.
+fn loaders(expr: &Expr) -> Option<Vec<JsonValue>> {
+    match unparen(expr) {
+        Expr::Array(array) => array
+            .elems
+            .iter()
+            .map(|elem| match elem {
+                Some(ExprOrSpread { spread: None, expr }) => loader(expr),
+                _ => None,
+            })
+            .collect(),
+        expr => Some(vec![loader(expr)?]),
+    }
+}
+
+fn loader(expr: &Expr) -> Option<JsonValue> {
+    if let Some(loader) = string_value(expr) {
+        return Some(JsonValue::String(loader));
+    }
+    let JsonValue::Object(loader) = json_value(expr)? else {
+        return None;
+    };
+    let name = loader.get("loader")?.as_str()?;
+    Some(match loader.get("options") {
+        Some(options) => json!({ "loader": name, "options": options }),
+        None => JsonValue::String(name.to_string()),
+    })
+}
+
+fn loader_name(loader: &JsonValue) -> Option<&str> {
+    match loader {
+        JsonValue::String(name) => Some(name),
+        JsonValue::Object(loader) => loader.get("loader")?.as_str(),
+        _ => None,
+    }
+}
+
+/// Resolves an alias target: a string, or `path.resolve(__dirname, ...)` and
+/// `path.join(__dirname, ...)` with string segments, which are converted to
+/// paths relative to the project directory.
+fn alias_target(expr: &Expr) -> Option<String> {
+    if let Some(target) = string_value(expr) {
+        return Some(target);
+    }
+    let call = unparen(expr).as_call()?;
+    let callee = member_path(call.callee.as_expr()?)?;
+    if callee != "path.resolve" && callee != "path.join" {
+        return None;
+    }
+    let (dirname, segments) = call.args.split_first()?;
+    if member_path(&dirname.expr)? != "__dirname" {
+        return None;
+    }
+    let segments = segments
+        .iter()
+        .map(|segment| string_value(&segment.expr))
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!(
+        "./{}",
+        segments
+            .iter()
+            .map(|segment| segment.trim_matches('/'))
+            .filter(|segment| !segment.is_empty() && *segment != ".")
+            .collect::<Vec<_>>()
+            .join("/")
+    ))
+}
+
+struct Migration<'a> {
+    source_map: &'a SourceMap,
+    items: Vec<MigrationItem>,
+    rules: Map<String, JsonValue>,
+    resolve_alias: Map<String, JsonValue>,
+    resolve_extensions: Option<Vec<String>>,
+}
+
+impl Migration<'_> {
+    fn add(
+        &mut self,
+        status: MigrationStatus,
+        span: Span,
+        option: impl Into<String>,
+        note: impl Into<String>,
+    ) {
+        self.items.push(MigrationItem {
+            status,
+            line: self.source_map.lookup_char_pos(span.lo).line,
+            option: option.into(),
+            note: note.into(),
+        });
+    }
+
+    /// Reports a part of the webpack config function which isn't migrated.
+    fn add_manual(&mut self, span: Span, note: &str) {
+        let snippet = self.source_map.span_to_snippet(span).unwrap_or_default();
+        let mut lines = snippet.lines();
+        let mut option = lines.next().unwrap_or_default().trim().to_string();
+        let mut truncated = lines.next().is_some();
+        if let Some((index, _)) = option.char_indices().nth(60) {
+            option.truncate(index);
+            truncated = true;
+        }
+        if truncated {
+            option.push_str(" …");
+        }
+        self.add(MigrationStatus::Manual, span, option, note);
+    }
+
+    fn migrate_webpack(&mut self, webpack: &WebpackFunction) {
+        let Some(config) = &webpack.config else {
+            self.add_manual(
+                webpack.span,
+                "The webpack config function doesn't use the webpack config.",
+            );
+            return;
+        };
+        for stmt in &webpack.body {
+            match stmt {
+                Stmt::Return(ReturnStmt { arg: None, .. }) => {}
+                Stmt::Return(ReturnStmt { arg: Some(arg), .. })
+                    if member_path(arg).as_ref() == Some(config) => {}
+                Stmt::Expr(expr) if self.migrate_expr(config, &expr.expr) => {}
+                stmt => self.add_manual(
+                    stmt.span(),
+                    "Not migrated automatically, Turbopack doesn't apply the webpack config \
+                     function.",
+                ),
+            }
+        }
+    }
+
+    /// Migrates a statement of the webpack config function. Returns whether
+    /// it was recognized.
+    fn migrate_expr(&mut self, config: &str, expr: &Expr) -> bool {
+        match unparen(expr) {
+            Expr::Call(call) => self.migrate_call(config, call),
+            Expr::Assign(assign) => self.migrate_assign(config, assign),
+            _ => false,
+        }
+    }
+
+    fn migrate_call(&mut self, config: &str, call: &CallExpr) -> bool {
+        let Some(callee) = call.callee.as_expr().and_then(|callee| member_path(callee)) else {
+            return false;
+        };
+        let Some(target) = callee.strip_prefix(config) else {
+            if callee == "Object.assign" {
+                return self.migrate_object_assign(config, call);
+            }
+            return false;
+        };
+        match target {
+            ".module.rules.push" => {
+                for arg in &call.args {
+                    match unparen(&arg.expr) {
+                        Expr::Object(rule) if arg.spread.is_none() => self.migrate_rule(rule),
+                        _ => self.add_manual(
+                            arg.span(),
+                            "Only object literals can be converted to loader rules.",
+                        ),
+                    }
+                }
+                true
+            }
+            ".plugins.push" => {
+                self.add(
+                    MigrationStatus::Manual,
+                    call.span,
+                    "webpack plugins",
+                    "Webpack plugins are not supported.",
+                );
+                true
+            }
+            ".resolve.extensions.push" | ".resolve.extensions.unshift" => {
+                let Some(mut added) = call
+                    .args
+                    .iter()
+                    .map(|arg| string_value(&arg.expr))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    return false;
+                };
+                let mut extensions = self.resolve_extensions.take().unwrap_or_else(|| {
+                    DEFAULT_RESOLVE_EXTENSIONS
+                        .iter()
+                        .map(|extension| extension.to_string())
+                        .collect()
+                });
+                if target.ends_with("push") {
+                    extensions.extend(added);
+                } else {
+                    added.extend(extensions);
+                    extensions = added;
+                }
+                self.resolve_extensions = Some(extensions);
+                self.add(
+                    MigrationStatus::Migrated,
+                    call.span,
+                    "resolve.extensions",
+                    "Added to `resolveExtensions`, together with the default extensions.",
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// `Object.assign(config.resolve.alias, { ... })`
+    fn migrate_object_assign(&mut self, config: &str, call: &CallExpr) -> bool {
+        let Some((target, sources)) = call.args.split_first() else {
+            return false;
+        };
+        if member_path(&target.expr) != Some(format!("{config}.resolve.alias")) {
+            return false;
+        }
+        for source in sources {
+            match unparen(&source.expr) {
+                Expr::Object(aliases) if source.spread.is_none() => {
+                    self.migrate_aliases(config, aliases)
+                }
+                _ => self.add_manual(
+                    source.span(),
+                    "Only object literals can be converted to `resolveAlias`.",
+                ),
+            }
+        }
+        true
+    }
+
+    fn migrate_assign(&mut self, config: &str, assign: &AssignExpr) -> bool {
+        if assign.op != AssignOp::Assign {
+            return false;
+        }
+        let Some(target) = assign
+            .left
+            .as_simple()
+            .and_then(|target| target.as_member())
+        else {
+            return false;
+        };
+        let alias_path = format!("{config}.resolve.alias");
+        let target_path = member_path(&Expr::Member(target.clone()));
+        if target_path.as_ref() == Some(&alias_path) {
+            let Expr::Object(aliases) = unparen(&assign.right) else {
+                return false;
+            };
+            self.migrate_aliases(config, aliases);
+            return true;
+        }
+        if target_path == Some(format!("{config}.resolve.extensions")) {
+            let Expr::Array(array) = unparen(&assign.right) else {
+                return false;
+            };
+            let mut extensions = Vec::new();
+            for elem in array.elems.iter().flatten() {
+                if elem.spread.is_some() {
+                    // `[...config.resolve.extensions, ".mdx"]`
+                    extensions.extend(
+                        DEFAULT_RESOLVE_EXTENSIONS
+                            .iter()
+                            .map(|extension| extension.to_string()),
+                    );
+                } else if let Some(extension) = string_value(&elem.expr) {
+                    extensions.push(extension);
+                } else {
+                    return false;
+                }
+            }
+            self.resolve_extensions = Some(extensions);
+            self.add(
+                MigrationStatus::Migrated,
+                assign.span,
+                "resolve.extensions",
+                "Added to `resolveExtensions`.",
+            );
+            return true;
+        }
+        // `config.resolve.alias["name"] = ...`
+        if member_path(&target.obj).as_ref() == Some(&alias_path) {
+            let name = match &target.prop {
+                MemberProp::Ident(ident) => Some(ident.sym.to_string()),
+                MemberProp::Computed(computed) => string_value(&computed.expr),
+                _ => None,
+            };
+            if let Some(name) = name {
+                self.migrate_alias(name, &assign.right, assign.span);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn migrate_aliases(&mut self, config: &str, aliases: &ObjectLit) {
+        for prop in &aliases.props {
+            match prop {
+                // `{ ...config.resolve.alias, ... }` keeps the existing aliases
+                PropOrSpread::Spread(spread)
+                    if member_path(&spread.expr) == Some(format!("{config}.resolve.alias")) => {}
+                PropOrSpread::Prop(prop) => {
+                    match prop
+                        .as_key_value()
+                        .and_then(|key_value| Some((prop_name(&key_value.key)?, key_value)))
+                    {
+                        Some((name, key_value)) => {
+                            self.migrate_alias(name.to_string(), &key_value.value, prop.span())
+                        }
+                        None => self.add_manual(
+                            prop.span(),
+                            "Only aliases with a static name can be converted to `resolveAlias`.",
+                        ),
+                    }
+                }
+                prop => self.add_manual(
+                    prop.span(),
+                    "Only aliases with a static name can be converted to `resolveAlias`.",
+                ),
+            }
+        }
+    }
+
+    fn migrate_alias(&mut self, name: String, target: &Expr, span: Span) {
+        match alias_target(target) {
+            Some(target) => {
+                self.resolve_alias
+                    .insert(name.clone(), JsonValue::String(target));
+                self.add(
+                    MigrationStatus::Migrated,
+                    span,
+                    format!("alias {name}"),
+                    "Added to `resolveAlias`.",
+                );
+            }
+            None => self.add(
+                MigrationStatus::Manual,
+                span,
+                format!("alias {name}"),
+                "Only strings and paths relative to `__dirname` can be converted to \
+                 `resolveAlias`.",
+            ),
+        }
+    }
+
+    fn migrate_rule(&mut self, rule: &ObjectLit) {
+        let span = rule.span;
+        let mut test = None;
+        let mut rule_loaders = Vec::new();
+        let mut rule_options = None;
+        let mut conditions = Vec::new();
+        for prop in &rule.props {
+            let Some((key, value)) = prop
+                .as_prop()
+                .and_then(|prop| prop.as_key_value())
+                .and_then(|key_value| Some((prop_name(&key_value.key)?, &*key_value.value)))
+            else {
+                self.add_manual(prop.span(), "The rule can't be converted.");
+                return;
+            };
+            match key {
+                "test" => test = Some(value),
+                "use" | "loader" | "loaders" => match loaders(value) {
+                    Some(loaders) => rule_loaders.extend(loaders),
+                    None => {
+                        self.add_manual(
+                            value.span(),
+                            "Only loaders with literal options can be converted to rules.",
+                        );
+                        return;
+                    }
+                },
+                "options" => rule_options = json_value(value),
+                condition => conditions.push(condition.to_string()),
+            }
+        }
+        if let Some(options) = rule_options {
+            if let [JsonValue::String(loader)] = &rule_loaders[..] {
+                let loader = json!({ "loader": loader, "options": options });
+                rule_loaders = vec![loader];
+            }
+        }
+
+        let globs = match test {
+            Some(Expr::Lit(Lit::Regex(regex))) if regex.flags.is_empty() => regex_globs(&regex.exp),
+            _ => None,
+        };
+        let Some(globs) = globs else {
+            self.add_manual(
+                span,
+                "The `test` of the rule can't be converted to globs, only regexes matching \
+                 extensions like `/\\.svg$/` can.",
+            );
+            return;
+        };
+        let option = format!("loader rule for {}", globs.join(", "));
+        if !conditions.is_empty() {
+            self.add(
+                MigrationStatus::Manual,
+                span,
+                option,
+                format!(
+                    "Turbopack rules don't support the conditions {}.",
+                    conditions.join(", ")
+                ),
+            );
+            return;
+        }
+        let names = rule_loaders
+            .iter()
+            .filter_map(loader_name)
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            self.add_manual(span, "The rule has no loaders.");
+            return;
+        }
+        if names.iter().all(|name| NATIVE_LOADERS.contains(name)) {
+            self.add(
+                MigrationStatus::Migrated,
+                span,
+                option,
+                "The rule is not needed, Turbopack handles these files natively.",
+            );
+            return;
+        }
+
+        let outputs_js = names.iter().all(|name| JS_OUTPUT_LOADERS.contains(name));
+        let mut turbo_rule = Map::new();
+        turbo_rule.insert("loaders".to_string(), JsonValue::Array(rule_loaders));
+        let note = if outputs_js {
+            turbo_rule.insert("as".to_string(), JsonValue::String("*.js".to_string()));
+            "Added to `rules`, the loaders run through the webpack loaders compat layer."
+        } else {
+            "Added to `rules`, the loaders run through the webpack loaders compat layer. Add `as: \
+             \"*.js\"` when the loaders output JavaScript."
+        };
+        for glob in globs {
+            self.rules
+                .insert(glob, JsonValue::Object(turbo_rule.clone()));
+        }
+        self.add(MigrationStatus::CompatLayer, span, option, note);
+    }
+
+    fn finish(self, file: &str) -> MigrationReport {
+        let mut turbo = Map::new();
+        if !self.rules.is_empty() {
+            turbo.insert("rules".to_string(), JsonValue::Object(self.rules));
+        }
+        if !self.resolve_alias.is_empty() {
+            turbo.insert(
+                "resolveAlias".to_string(),
+                JsonValue::Object(self.resolve_alias),
+            );
+        }
+        if let Some(extensions) = self.resolve_extensions {
+            turbo.insert("resolveExtensions".to_string(), json!(extensions));
+        }
+        let mut items = self.items;
+        items.sort_by_key(|item| item.line);
+        MigrationReport {
+            file: file.to_string(),
+            items,
+            turbo: (!turbo.is_empty()).then_some(JsonValue::Object(turbo)),
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
+use self::{
+    migration::{migrate_next_config, MigrationReport},
+    validation::{validate_next_config, ValidationReport},
+};
+use crate::{arguments::CompatArguments, util::normalize_dirs};
+
+pub mod migration;
+pub mod validation;
+
+/// How well a feature used by the project is supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SupportLevel {
+    /// Handled by Turbopack itself.
+    Native,
+    /// Works through a compatibility layer, e. g. webpack loaders or PostCSS
+    /// running in Node.js.
+    CompatLayer,
+    /// Not supported, the project needs to be changed.
+    Unsupported,
+}
+
+impl fmt::Display for SupportLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SupportLevel::Native => f.write_str("supported natively"),
+            SupportLevel::CompatLayer => f.write_str("supported via compat layer"),
+            SupportLevel::Unsupported => f.write_str("unsupported"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompatFinding {
+    pub support: SupportLevel,
+    pub feature: String,
+    /// The file the feature was found in, relative to the project directory.
+    pub file: String,
+    pub note: &'static str,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct CompatReport {
+    pub findings: Vec<CompatFinding>,
+    /// The validation of the next.config, see [validate_next_config].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ValidationReport>,
+    /// The migration of the next.config, see [migrate_next_config].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub migration: Option<MigrationReport>,
+}
+
+impl CompatReport {
+    fn add(
+        &mut self,
+        support: SupportLevel,
+        feature: impl Into<String>,
+        file: &str,
+        note: &'static str,
+    ) {
+        self.findings.push(CompatFinding {
+            support,
+            feature: feature.into(),
+            file: file.to_string(),
+            note,
+        });
+    }
+
+    pub fn has_unsupported(&self) -> bool {
+        self.findings
+            .iter()
+            .any(|finding| finding.support == SupportLevel::Unsupported)
+    }
+
+    /// Whether the project uses unsupported features or the next.config has
+    /// values of the wrong type.
+    pub fn has_errors(&self) -> bool {
+        self.has_unsupported()
+            || self
+                .validation
+                .as_ref()
+                .map_or(false, |validation| validation.has_errors())
+    }
+}
+
+impl fmt::Display for CompatReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.findings.is_empty() {
+            writeln!(f, "No features that need attention were found.")?;
+        }
+        for level in [
+            SupportLevel::Unsupported,
+            SupportLevel::CompatLayer,
+            SupportLevel::Native,
+        ] {
+            let findings = self
+                .findings
+                .iter()
+                .filter(|finding| finding.support == level)
+                .collect::<Vec<_>>();
+            if findings.is_empty() {
+                continue;
+            }
+            let heading = match level {
+                SupportLevel::Native => level.to_string().green().to_string(),
+                SupportLevel::CompatLayer => level.to_string().yellow().to_string(),
+                SupportLevel::Unsupported => level.to_string().red().to_string(),
+            };
+            writeln!(f, "{} ({})", heading.bold(), findings.len())?;
+            for finding in findings {
+                writeln!(
+                    f,
+                    "  {} {}\n    {}",
+                    finding.feature,
+                    format!("({})", finding.file).dimmed(),
+                    finding.note
+                )?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(validation) = &self.validation {
+            write!(f, "{validation}")?;
+        }
+        if let Some(migration) = &self.migration {
+            write!(f, "{migration}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Known packages and how they are supported.
+const PACKAGES: &[(&str, SupportLevel, &str)] = &[
+    (
+        "typescript",
+        SupportLevel::Native,
+        "TypeScript is transformed by SWC.",
+    ),
+    (
+        "@emotion/react",
+        SupportLevel::Native,
+        "The emotion transform runs in SWC.",
+    ),
+    (
+        "styled-components",
+        SupportLevel::Native,
+        "The styled-components transform runs in SWC.",
+    ),
+    (
+        "styled-jsx",
+        SupportLevel::Native,
+        "The styled-jsx transform runs in SWC.",
+    ),
+    (
+        "tailwindcss",
+        SupportLevel::CompatLayer,
+        "Runs as PostCSS plugin in Node.js.",
+    ),
+    (
+        "postcss",
+        SupportLevel::CompatLayer,
+        "PostCSS config is evaluated in Node.js.",
+    ),
+    (
+        "sass",
+        SupportLevel::Unsupported,
+        "Sass files are not supported yet.",
+    ),
+    (
+        "node-sass",
+        SupportLevel::Unsupported,
+        "Sass files are not supported yet.",
+    ),
+    (
+        "less",
+        SupportLevel::CompatLayer,
+        "Use less-loader through the webpack loaders compat layer.",
+    ),
+    (
+        "stylus",
+        SupportLevel::CompatLayer,
+        "Use stylus-loader through the webpack loaders compat layer.",
+    ),
+    (
+        "@svgr/webpack",
+        SupportLevel::CompatLayer,
+        "Runs through the webpack loaders compat layer.",
+    ),
+];
+
+/// Loaders that are not needed, because Turbopack handles the file types
+/// natively.
+const NATIVE_LOADERS: &[&str] = &[
+    "babel-loader",
+    "css-loader",
+    "file-loader",
+    "style-loader",
+    "ts-loader",
+    "url-loader",
+];
+
+const CONFIG_FILES: &[&str] = &[
+    "next.config.js",
+    "next.config.mjs",
+    "webpack.config.js",
+    "webpack.config.ts",
+];
+
+const BABEL_CONFIG_FILES: &[&str] = &[
+    ".babelrc",
+    ".babelrc.json",
+    "babel.config.json",
+    "babel.config.js",
+    ".babelrc.js",
+];
+
+const SOURCE_EXTENSIONS: &[&str] = &["js", "jsx", "mjs", "cjs", "ts", "tsx", "mts", "cts"];
+
+const IGNORED_DIRECTORIES: &[&str] = &["node_modules", ".git", ".next", ".turbopack", "dist"];
+
+/// Scans a project for features that Turbopack supports natively, through a
+/// compat layer, or not at all.
+pub fn scan_project(project_dir: &Path) -> Result<CompatReport> {
+    let mut report = CompatReport::default();
+    scan_package_json(project_dir, &mut report)?;
+    for file in CONFIG_FILES {
+        if let Ok(content) = std::fs::read_to_string(project_dir.join(file)) {
+            scan_bundler_config(file, &content, &mut report);
+        }
+    }
+    for file in BABEL_CONFIG_FILES {
+        if let Ok(content) = std::fs::read_to_string(project_dir.join(file)) {
+            scan_babel_config(file, &content, &mut report);
+        }
+    }
+    let mut sources = Vec::new();
+    collect_sources(project_dir, &mut sources)?;
+    sources.sort();
+    for path in sources {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let file = relative_path(project_dir, &path);
+        scan_inline_loaders(&file, &content, &mut report);
+    }
+    report.findings.sort();
+    report.findings.dedup();
+    Ok(report)
+}
+
+fn scan_package_json(project_dir: &Path, report: &mut CompatReport) -> Result<()> {
+    let Ok(content) = std::fs::read_to_string(project_dir.join("package.json")) else {
+        return Ok(());
+    };
+    let package_json: JsonValue = serde_json::from_str(&content).context("parsing package.json")?;
+    let dependencies = ["dependencies", "devDependencies"]
+        .iter()
+        .filter_map(|key| package_json.get(key)?.as_object())
+        .flat_map(|dependencies| dependencies.keys())
+        .collect::<BTreeSet<_>>();
+    for dependency in dependencies {
+        if let Some((_, support, note)) = PACKAGES.iter().find(|(name, ..)| name == dependency) {
+            report.add(*support, dependency.as_str(), "package.json", note);
+        } else if dependency.ends_with("-loader") {
+            add_loader(dependency, "package.json", report);
+        } else if dependency.starts_with("babel-plugin-")
+            || dependency.starts_with("@babel/plugin-")
+        {
+            report.add(
+                SupportLevel::CompatLayer,
+                dependency.as_str(),
+                "package.json",
+                "Babel plugins only run through babel-loader in the webpack loaders compat layer.",
+            );
+        } else if dependency.ends_with("-webpack-plugin") {
+            report.add(
+                SupportLevel::Unsupported,
+                dependency.as_str(),
+                "package.json",
+                "Webpack plugins are not supported.",
+            );
+        }
+    }
+    Ok(())
+}
+
+fn add_loader(loader: &str, file: &str, report: &mut CompatReport) {
+    if NATIVE_LOADERS.contains(&loader) {
+        report.add(
+            SupportLevel::Native,
+            loader,
+            file,
+            "The loader is not needed, Turbopack handles these files natively.",
+        );
+    } else {
+        report.add(
+            SupportLevel::CompatLayer,
+            loader,
+            file,
+            "Runs through the webpack loaders compat layer.",
+        );
+    }
+}
+
+fn scan_bundler_config(file: &str, content: &str, report: &mut CompatReport) {
+    if content.contains("webpack(") || content.contains("webpack:") {
+        report.add(
+            SupportLevel::Unsupported,
+            "custom webpack config",
+            file,
+            "The webpack config function is not applied, move loaders to webpack loader rules.",
+        );
+    }
+    if content.contains("plugins:") && file.starts_with("webpack.config") {
+        report.add(
+            SupportLevel::Unsupported,
+            "webpack plugins",
+            file,
+            "Webpack plugins are not supported.",
+        );
+    }
+    for loader in find_loaders(content) {
+        add_loader(loader, file, report);
+    }
+}
+
+fn scan_babel_config(file: &str, content: &str, report: &mut CompatReport) {
+    report.add(
+        SupportLevel::CompatLayer,
+        "babel config",
+        file,
+        "Babel only runs through babel-loader in the webpack loaders compat layer, SWC is used \
+         otherwise.",
+    );
+    if let Ok(config) = serde_json::from_str::<JsonValue>(content) {
+        let plugins = config
+            .get("plugins")
+            .and_then(|plugins| plugins.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|plugin| match plugin {
+                JsonValue::String(name) => Some(name.as_str()),
+                JsonValue::Array(items) => items.first()?.as_str(),
+                _ => None,
+            });
+        for plugin in plugins {
+            report.add(
+                SupportLevel::CompatLayer,
+                format!("babel plugin {plugin}"),
+                file,
+                "Babel plugins only run through babel-loader in the webpack loaders compat layer.",
+            );
+        }
+    }
+}
+
+/// Finds inline loader usages like `require("!!raw-loader!./file.txt")`.
+fn scan_inline_loaders(file: &str, content: &str, report: &mut CompatReport) {
+    for (index, _) in content.match_indices("-loader!") {
+        let start = content[..index]
+            .rfind(|c: char| c == '"' || c == '\'' || c == '`' || c == '!')
+            .map_or(0, |start| start + 1);
+        let loader = &content[start..index + "-loader".len()];
+        report.add(
+            SupportLevel::Unsupported,
+            format!("inline loader {loader}"),
+            file,
+            "Inline loader syntax is not supported, configure a webpack loader rule instead.",
+        );
+    }
+}
+
+fn find_loaders(content: &str) -> impl Iterator<Item = &str> {
+    content.match_indices("-loader").filter_map(|(index, _)| {
+        let start = content[..index].rfind(|c: char| c == '"' || c == '\'')? + 1;
+        let end = index + "-loader".len();
+        let quote = content[start - 1..start].chars().next()?;
+        content[end..]
+            .starts_with(quote)
+            .then(|| &content[start..end])
+    })
+}
+
+fn collect_sources(dir: &Path, sources: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let name = entry.file_name();
+            if !IGNORED_DIRECTORIES.iter().any(|ignored| name == *ignored) {
+                collect_sources(&path, sources)?;
+            }
+        } else if file_type.is_file()
+            && path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| SOURCE_EXTENSIONS.contains(&ext))
+        {
+            sources.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn relative_path(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
+
+pub fn report(args: &CompatArguments) -> Result<()> {
+    let project_dir = normalize_dirs(&args.dir, &None)?.project_dir;
+    let mut report = scan_project(Path::new(&project_dir))?;
+    report.validation = validate_next_config(Path::new(&project_dir))?;
+    if args.migrate {
+        report.migration = migrate_next_config(Path::new(&project_dir))?;
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{report}");
+    }
+    if report.has_errors() {
+        bail!("The project uses unsupported features or its next.config has invalid options");
+    }
+    Ok(())
+}
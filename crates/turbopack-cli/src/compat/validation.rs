@@ -0,0 +1,695 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use anyhow::Result;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use swc_core::{
+    common::{SourceMap, Span, Spanned},
+    ecma::ast::{
+        AssignOp, BlockStmtOrExpr, Callee, Decl, Expr, Lit, ModuleDecl, ModuleItem, ObjectLit,
+        Program, Prop, PropOrSpread, ReturnStmt, Stmt, UnaryOp,
+    },
+};
+use turbopack::ecmascript::utils::unparen;
+
+use super::migration::{member_path, parse_config, prop_name, NEXT_CONFIG_FILES};
+
+/// What is wrong with an option of the next.config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigProblemKind {
+    /// The value has a type the option doesn't accept.
+    TypeMismatch,
+    /// The option isn't known, so it is ignored.
+    UnknownOption,
+    /// The option still works, but will be removed.
+    Deprecated,
+}
+
+impl fmt::Display for ConfigProblemKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigProblemKind::TypeMismatch => f.write_str("type mismatch"),
+            ConfigProblemKind::UnknownOption => f.write_str("unknown option"),
+            ConfigProblemKind::Deprecated => f.write_str("deprecated"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigProblem {
+    /// The line of the option in the config file.
+    pub line: usize,
+    /// The column of the option in the config file, starting at 1.
+    pub column: usize,
+    pub kind: ConfigProblemKind,
+    /// The dotted path of the option, e. g. `experimental.turbo.rules`.
+    pub option: String,
+    pub note: String,
+}
+
+/// The validation of the options of a next.config against the known options
+/// and their types.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    /// The config file, relative to the project directory.
+    pub file: String,
+    pub problems: Vec<ConfigProblem>,
+    /// Whether the config object was found. Configs which are computed at
+    /// runtime can't be validated statically.
+    pub validated: bool,
+}
+
+impl ValidationReport {
+    pub fn has_errors(&self) -> bool {
+        self.problems
+            .iter()
+            .any(|problem| problem.kind == ConfigProblemKind::TypeMismatch)
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", format!("Validation of {}", self.file).bold())?;
+        if !self.validated {
+            writeln!(
+                f,
+                "  The config object is computed at runtime and can't be validated."
+            )?;
+        } else if self.problems.is_empty() {
+            writeln!(f, "  No problems were found.")?;
+        }
+        for problem in &self.problems {
+            let kind = match problem.kind {
+                ConfigProblemKind::TypeMismatch => problem.kind.to_string().red().to_string(),
+                ConfigProblemKind::UnknownOption | ConfigProblemKind::Deprecated => {
+                    problem.kind.to_string().yellow().to_string()
+                }
+            };
+            writeln!(
+                f,
+                "  {} {} {}\n    {}",
+                kind,
+                problem.option,
+                format!("({}:{}:{})", self.file, problem.line, problem.column).dimmed(),
+                problem.note
+            )?;
+        }
+        writeln!(f)
+    }
+}
+
+/// The types of JavaScript values the schema distinguishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Boolean,
+    String,
+    Number,
+    Null,
+    Array,
+    Object,
+    Function,
+}
+
+impl ValueKind {
+    fn description(self) -> &'static str {
+        match self {
+            ValueKind::Boolean => "a boolean",
+            ValueKind::String => "a string",
+            ValueKind::Number => "a number",
+            ValueKind::Null => "null",
+            ValueKind::Array => "an array",
+            ValueKind::Object => "an object",
+            ValueKind::Function => "a function",
+        }
+    }
+}
+
+/// The options of an object in the config.
+struct ObjectSchema {
+    options: &'static [ConfigOption],
+    /// Whether unknown options are expected, e. g. for `experimental`, whose
+    /// options change with every release.
+    allows_unknown: bool,
+}
+
+struct ConfigOption {
+    name: &'static str,
+    kinds: &'static [ValueKind],
+    /// The options of the value, when it is an object.
+    object: Option<&'static ObjectSchema>,
+    /// What to use instead, when the option is deprecated.
+    deprecated: Option<&'static str>,
+}
+
+impl ConfigOption {
+    const fn new(name: &'static str, kinds: &'static [ValueKind]) -> Self {
+        ConfigOption {
+            name,
+            kinds,
+            object: None,
+            deprecated: None,
+        }
+    }
+
+    const fn object(name: &'static str, object: &'static ObjectSchema) -> Self {
+        ConfigOption {
+            name,
+            kinds: OBJECT,
+            object: Some(object),
+            deprecated: None,
+        }
+    }
+
+    const fn deprecated(mut self, note: &'static str) -> Self {
+        self.deprecated = Some(note);
+        self
+    }
+}
+
+const BOOLEAN: &[ValueKind] = &[ValueKind::Boolean];
+const STRING: &[ValueKind] = &[ValueKind::String];
+const NUMBER: &[ValueKind] = &[ValueKind::Number];
+const ARRAY: &[ValueKind] = &[ValueKind::Array];
+const OBJECT: &[ValueKind] = &[ValueKind::Object];
+const FUNCTION: &[ValueKind] = &[ValueKind::Function];
+
+static NEXT_CONFIG: ObjectSchema = ObjectSchema {
+    options: &[
+        ConfigOption::new("amp", OBJECT),
+        ConfigOption::new("assetPrefix", STRING),
+        ConfigOption::new("basePath", STRING),
+        ConfigOption::new("cleanDistDir", BOOLEAN),
+        ConfigOption::new("compiler", OBJECT),
+        ConfigOption::new("compress", BOOLEAN),
+        ConfigOption::new("crossOrigin", &[ValueKind::String, ValueKind::Boolean]),
+        ConfigOption::new("devIndicators", OBJECT),
+        ConfigOption::new("distDir", STRING),
+        ConfigOption::new("env", OBJECT),
+        ConfigOption::object("eslint", &ESLINT),
+        ConfigOption::new("excludeDefaultMomentLocales", BOOLEAN),
+        ConfigOption::object("experimental", &EXPERIMENTAL),
+        ConfigOption::new("exportPathMap", FUNCTION),
+        ConfigOption::new("generateBuildId", FUNCTION),
+        ConfigOption::new("generateEtags", BOOLEAN),
+        ConfigOption::new("headers", FUNCTION),
+        ConfigOption::new("httpAgentOptions", OBJECT),
+        ConfigOption::object("i18n", &I18N),
+        ConfigOption::object("images", &IMAGES),
+        ConfigOption::new("logging", OBJECT),
+        ConfigOption::new("modularizeImports", OBJECT),
+        ConfigOption::new("onDemandEntries", OBJECT),
+        ConfigOption::new("optimizeFonts", BOOLEAN),
+        ConfigOption::new("output", STRING),
+        ConfigOption::new("outputFileTracing", BOOLEAN),
+        ConfigOption::new("pageExtensions", ARRAY),
+        ConfigOption::new("poweredByHeader", BOOLEAN),
+        ConfigOption::new("productionBrowserSourceMaps", BOOLEAN),
+        ConfigOption::new("publicRuntimeConfig", OBJECT),
+        ConfigOption::new("reactStrictMode", &[ValueKind::Boolean, ValueKind::Null]),
+        ConfigOption::new("redirects", FUNCTION),
+        ConfigOption::new("rewrites", FUNCTION),
+        ConfigOption::new("sassOptions", OBJECT),
+        ConfigOption::new("serverRuntimeConfig", OBJECT),
+        ConfigOption::new("skipMiddlewareUrlNormalize", BOOLEAN),
+        ConfigOption::new("skipTrailingSlashRedirect", BOOLEAN),
+        ConfigOption::new("staticPageGenerationTimeout", NUMBER),
+        ConfigOption::new("swcMinify", BOOLEAN),
+        ConfigOption::new("target", STRING)
+            .deprecated("`target` was removed in Next.js 13, use `output` instead."),
+        ConfigOption::new("trailingSlash", BOOLEAN),
+        ConfigOption::new("transpilePackages", ARRAY),
+        ConfigOption::object("typescript", &TYPESCRIPT),
+        ConfigOption::new("useFileSystemPublicRoutes", BOOLEAN),
+        ConfigOption::new("webpack", FUNCTION),
+    ],
+    allows_unknown: false,
+};
+
+static ESLINT: ObjectSchema = ObjectSchema {
+    options: &[
+        ConfigOption::new("dirs", ARRAY),
+        ConfigOption::new("ignoreDuringBuilds", BOOLEAN),
+    ],
+    allows_unknown: false,
+};
+
+static EXPERIMENTAL: ObjectSchema = ObjectSchema {
+    options: &[
+        ConfigOption::new("appDir", BOOLEAN)
+            .deprecated("The app directory is stable since Next.js 13.4, remove the option."),
+        ConfigOption::new("serverComponentsExternalPackages", ARRAY),
+        ConfigOption::object("turbo", &TURBO),
+        ConfigOption::new("typedRoutes", BOOLEAN),
+    ],
+    allows_unknown: true,
+};
+
+static TURBO: ObjectSchema = ObjectSchema {
+    options: &[
+        ConfigOption::new("loaders", OBJECT)
+            .deprecated("Use `rules` instead, which also configure the loaders of files."),
+        ConfigOption::new("memoryLimit", NUMBER),
+        ConfigOption::new("resolveAlias", OBJECT),
+        ConfigOption::new("resolveExtensions", ARRAY),
+        ConfigOption::new("rules", OBJECT),
+    ],
+    allows_unknown: false,
+};
+
+static I18N: ObjectSchema = ObjectSchema {
+    options: &[
+        ConfigOption::new("defaultLocale", STRING),
+        ConfigOption::new("domains", ARRAY),
+        ConfigOption::new("localeDetection", BOOLEAN),
+        ConfigOption::new("locales", ARRAY),
+    ],
+    allows_unknown: false,
+};
+
+static IMAGES: ObjectSchema = ObjectSchema {
+    options: &[
+        ConfigOption::new("contentSecurityPolicy", STRING),
+        ConfigOption::new("dangerouslyAllowSVG", BOOLEAN),
+        ConfigOption::new("deviceSizes", ARRAY),
+        ConfigOption::new("domains", ARRAY)
+            .deprecated("Use `remotePatterns` instead, which also restrict the protocol and path."),
+        ConfigOption::new("formats", ARRAY),
+        ConfigOption::new("imageSizes", ARRAY),
+        ConfigOption::new("loader", STRING),
+        ConfigOption::new("loaderFile", STRING),
+        ConfigOption::new("minimumCacheTTL", NUMBER),
+        ConfigOption::new("path", STRING),
+        ConfigOption::new("remotePatterns", ARRAY),
+        ConfigOption::new("unoptimized", BOOLEAN),
+    ],
+    allows_unknown: false,
+};
+
+static TYPESCRIPT: ObjectSchema = ObjectSchema {
+    options: &[
+        ConfigOption::new("ignoreBuildErrors", BOOLEAN),
+        ConfigOption::new("tsconfigPath", STRING),
+    ],
+    allows_unknown: false,
+};
+
+/// Validates the options of the next.config of the project: options which
+/// aren't known, values of the wrong type and deprecated options are reported
+/// with their location.
+///
+/// Only the statically written config object is validated, values computed
+/// at runtime, e. g. `process.env` lookups or spread objects, are skipped.
+///
+/// Returns `None` when the project has no next.config.
+pub fn validate_next_config(project_dir: &Path) -> Result<Option<ValidationReport>> {
+    for file in NEXT_CONFIG_FILES {
+        if let Ok(content) = std::fs::read_to_string(project_dir.join(file)) {
+            return validate(file, content).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+fn validate(file: &str, content: String) -> Result<ValidationReport> {
+    let (source_map, program) = parse_config(file, content)?;
+    let mut validation = Validation {
+        source_map: &source_map,
+        problems: Vec::new(),
+    };
+    let config = ConfigObjectFinder::new(&program).find();
+    if let Some(config) = config {
+        validation.validate_object(config, &NEXT_CONFIG, "");
+    }
+    validation.problems.sort();
+    Ok(ValidationReport {
+        file: file.to_string(),
+        problems: validation.problems,
+        validated: config.is_some(),
+    })
+}
+
+/// How many variables and config plugins are followed to the config object.
+const MAX_CONFIG_DEPTH: usize = 8;
+
+/// Finds the exported config object, following variables, config plugins,
+/// e. g. `withBundleAnalyzer(nextConfig)`, and config functions.
+struct ConfigObjectFinder<'a> {
+    /// The initializers of the top-level variables.
+    variables: HashMap<&'a str, &'a Expr>,
+    export: Option<&'a Expr>,
+}
+
+impl<'a> ConfigObjectFinder<'a> {
+    fn new(program: &'a Program) -> Self {
+        let mut finder = ConfigObjectFinder {
+            variables: HashMap::new(),
+            export: None,
+        };
+        match program {
+            Program::Module(module) => {
+                for item in &module.body {
+                    match item {
+                        ModuleItem::Stmt(stmt) => finder.add_stmt(stmt),
+                        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(export)) => {
+                            finder.export = Some(&*export.expr);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Program::Script(script) => {
+                for stmt in &script.body {
+                    finder.add_stmt(stmt);
+                }
+            }
+        }
+        finder
+    }
+
+    fn add_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Decl(Decl::Var(var)) => {
+                for decl in &var.decls {
+                    if let (Some(ident), Some(init)) = (decl.name.as_ident(), &decl.init) {
+                        self.variables.insert(&*ident.id.sym, &**init);
+                    }
+                }
+            }
+            Stmt::Expr(expr) => {
+                let Expr::Assign(assign) = unparen(&expr.expr) else {
+                    return;
+                };
+                let is_module_exports = assign.op == AssignOp::Assign
+                    && assign
+                        .left
+                        .as_simple()
+                        .and_then(|target| target.as_member())
+                        .and_then(|target| member_path(&Expr::Member(target.clone())))
+                        .map_or(false, |path| path == "module.exports");
+                if is_module_exports {
+                    self.export = Some(&*assign.right);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn find(&self) -> Option<&'a ObjectLit> {
+        self.resolve(self.export?, 0)
+    }
+
+    fn resolve(&self, expr: &'a Expr, depth: usize) -> Option<&'a ObjectLit> {
+        if depth > MAX_CONFIG_DEPTH {
+            return None;
+        }
+        match unparen(expr) {
+            Expr::Object(object) => Some(object),
+            Expr::Ident(ident) => self.resolve(*self.variables.get(&*ident.sym)?, depth + 1),
+            Expr::Call(call) => {
+                let Callee::Expr(_) = &call.callee else {
+                    return None;
+                };
+                let arg = call.args.first()?;
+                if arg.spread.is_some() {
+                    return None;
+                }
+                self.resolve(&arg.expr, depth + 1)
+            }
+            Expr::Arrow(arrow) => match &*arrow.body {
+                BlockStmtOrExpr::Expr(body) => self.resolve(body, depth + 1),
+                BlockStmtOrExpr::BlockStmt(block) => self.resolve_return(&block.stmts, depth),
+            },
+            Expr::Fn(function) => {
+                self.resolve_return(&function.function.body.as_ref()?.stmts, depth)
+            }
+            _ => None,
+        }
+    }
+
+    /// Resolves the value returned at the end of a config function.
+    fn resolve_return(&self, stmts: &'a [Stmt], depth: usize) -> Option<&'a ObjectLit> {
+        let Some(Stmt::Return(ReturnStmt { arg: Some(arg), .. })) = stmts.last() else {
+            return None;
+        };
+        self.resolve(arg, depth + 1)
+    }
+}
+
+struct Validation<'a> {
+    source_map: &'a SourceMap,
+    problems: Vec<ConfigProblem>,
+}
+
+impl Validation<'_> {
+    fn add(&mut self, kind: ConfigProblemKind, span: Span, option: &str, note: impl Into<String>) {
+        let location = self.source_map.lookup_char_pos(span.lo);
+        self.problems.push(ConfigProblem {
+            line: location.line,
+            column: location.col_display + 1,
+            kind,
+            option: option.to_string(),
+            note: note.into(),
+        });
+    }
+
+    fn validate_object(&mut self, object: &ObjectLit, schema: &ObjectSchema, path: &str) {
+        for prop in &object.props {
+            let PropOrSpread::Prop(prop) = prop else {
+                continue;
+            };
+            let (name, key_span, value) = match &**prop {
+                Prop::KeyValue(key_value) => {
+                    let Some(name) = prop_name(&key_value.key) else {
+                        continue;
+                    };
+                    (name, key_value.key.span(), Some(&*key_value.value))
+                }
+                Prop::Method(method) => {
+                    let Some(name) = prop_name(&method.key) else {
+                        continue;
+                    };
+                    (name, method.key.span(), None)
+                }
+                Prop::Shorthand(ident) => (&*ident.sym, ident.span, None),
+                _ => continue,
+            };
+            let option_path = if path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{path}.{name}")
+            };
+            let Some(option) = schema.options.iter().find(|option| option.name == name) else {
+                if !schema.allows_unknown {
+                    self.add(
+                        ConfigProblemKind::UnknownOption,
+                        key_span,
+                        &option_path,
+                        "The option isn't known and is ignored. Check the spelling.",
+                    );
+                }
+                continue;
+            };
+            if let Some(note) = option.deprecated {
+                self.add(ConfigProblemKind::Deprecated, key_span, &option_path, note);
+            }
+            let kind = match (&**prop, value) {
+                (Prop::Method(_), _) => Some(ValueKind::Function),
+                (_, Some(value)) => value_kind(value),
+                _ => None,
+            };
+            let Some(kind) = kind else {
+                continue;
+            };
+            if !option.kinds.contains(&kind) {
+                let expected = option
+                    .kinds
+                    .iter()
+                    .map(|kind| kind.description())
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                self.add(
+                    ConfigProblemKind::TypeMismatch,
+                    value.map_or(key_span, |value| value.span()),
+                    &option_path,
+                    format!("Expected {expected}, found {}.", kind.description()),
+                );
+            } else if let (Some(object_schema), Some(Expr::Object(value))) =
+                (option.object, value.map(unparen))
+            {
+                self.validate_object(value, object_schema, &option_path);
+            }
+        }
+    }
+}
+
+/// The kind of a literal value, `None` for values computed at runtime.
+fn value_kind(expr: &Expr) -> Option<ValueKind> {
+    match unparen(expr) {
+        Expr::Lit(Lit::Bool(_)) => Some(ValueKind::Boolean),
+        Expr::Lit(Lit::Str(_)) | Expr::Tpl(_) => Some(ValueKind::String),
+        Expr::Lit(Lit::Num(_)) => Some(ValueKind::Number),
+        Expr::Lit(Lit::Null(_)) => Some(ValueKind::Null),
+        Expr::Unary(unary) if unary.op == UnaryOp::Minus => match unparen(&unary.arg) {
+            Expr::Lit(Lit::Num(_)) => Some(ValueKind::Number),
+            _ => None,
+        },
+        Expr::Array(_) => Some(ValueKind::Array),
+        Expr::Object(_) => Some(ValueKind::Object),
+        Expr::Fn(_) | Expr::Arrow(_) => Some(ValueKind::Function),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(content: &str) -> Vec<(ConfigProblemKind, String)> {
+        let report = validate("next.config.js", content.to_string()).unwrap();
+        assert!(report.validated);
+        report
+            .problems
+            .into_iter()
+            .map(|problem| (problem.kind, problem.option))
+            .collect()
+    }
+
+    #[test]
+    fn unknown_options() {
+        assert_eq!(
+            problems(
+                r#"module.exports = {
+                    reactStrictMod: true,
+                    images: { unoptimised: true },
+                    experimental: { someNewFlag: true },
+                };"#
+            ),
+            vec![
+                (
+                    ConfigProblemKind::UnknownOption,
+                    "reactStrictMod".to_string()
+                ),
+                (
+                    ConfigProblemKind::UnknownOption,
+                    "images.unoptimised".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_mismatches() {
+        let report = validate(
+            "next.config.js",
+            r#"module.exports = {
+                basePath: 42,
+                reactStrictMode: null,
+                eslint: { dirs: "src" },
+                headers: async () => [],
+                webpack(config) { return config; },
+                env: process.env.CUSTOM_ENV,
+            };"#
+            .to_string(),
+        )
+        .unwrap();
+        assert!(report.has_errors());
+        assert_eq!(
+            report
+                .problems
+                .iter()
+                .map(|problem| (problem.kind, problem.option.as_str(), problem.note.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    ConfigProblemKind::TypeMismatch,
+                    "basePath",
+                    "Expected a string, found a number."
+                ),
+                (
+                    ConfigProblemKind::TypeMismatch,
+                    "eslint.dirs",
+                    "Expected an array, found a string."
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn deprecated_options() {
+        let report = validate(
+            "next.config.js",
+            r#"module.exports = {
+                target: "serverless",
+                experimental: { appDir: true, turbo: { loaders: {} } },
+            };"#
+            .to_string(),
+        )
+        .unwrap();
+        assert!(!report.has_errors());
+        assert_eq!(
+            report
+                .problems
+                .iter()
+                .map(|problem| (problem.kind, problem.option.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (ConfigProblemKind::Deprecated, "target"),
+                (ConfigProblemKind::Deprecated, "experimental.appDir"),
+                (ConfigProblemKind::Deprecated, "experimental.turbo.loaders"),
+            ]
+        );
+    }
+
+    #[test]
+    fn problem_positions() {
+        let report = validate(
+            "next.config.mjs",
+            "const nextConfig = {\n  trailingSlash: \"yes\",\n    poweredBy: false,\n};\n\nexport \
+             default withPlugin(nextConfig);\n"
+                .to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            report
+                .problems
+                .iter()
+                .map(|problem| (problem.line, problem.column, problem.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (2, 18, ConfigProblemKind::TypeMismatch),
+                (3, 5, ConfigProblemKind::UnknownOption),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_config_functions() {
+        assert_eq!(
+            problems(r#"module.exports = (phase) => ({ swcMinify: "true" });"#),
+            vec![(ConfigProblemKind::TypeMismatch, "swcMinify".to_string())]
+        );
+        assert_eq!(
+            problems(
+                r#"const config = { swcMinify: "true" };
+                module.exports = (phase) => {
+                    return config;
+                };"#
+            ),
+            vec![(ConfigProblemKind::TypeMismatch, "swcMinify".to_string())]
+        );
+    }
+
+    #[test]
+    fn computed_configs_are_not_validated() {
+        let report = validate(
+            "next.config.js",
+            "module.exports = require('./config');".to_string(),
+        )
+        .unwrap();
+        assert!(!report.validated);
+        assert!(report.problems.is_empty());
+    }
+}
@@ -1,7 +1,9 @@
 use std::{collections::HashMap, fmt};
 
 use anyhow::Result;
+use serde_json::Value as JsonValue;
 use turbo_tasks::{Value, Vc};
+use turbo_tasks_env::ProcessEnv;
 use turbo_tasks_fs::{FileSystem, FileSystemPath};
 use turbopack::{
     ecmascript::{EcmascriptInputTransform, TreeShakingMode},
@@ -17,7 +19,7 @@ use turbopack_core::{
     compile_time_info::{CompileTimeDefines, CompileTimeInfo},
     condition::ContextCondition,
     context::AssetContext,
-    environment::{BrowserEnvironment, Environment, ExecutionEnvironment},
+    environment::Environment,
     resolve::options::{ImportMap, ImportMapping},
 };
 use turbopack_ecmascript_plugins::transform::{
@@ -30,10 +32,21 @@ use turbopack_node::{
 };
 use turbopack_resolve::resolve_options_context::ResolveOptionsContext;
 
+use crate::{
+    environment::EnvironmentPreset,
+    feature_flags::{
+        feature_flag_defines, feature_flag_free_var_references, feature_flags_import_mapping,
+        load_feature_flags, FEATURE_FLAGS_MODULE,
+    },
+    i18n::{messages_import_mapping, MESSAGES_MODULE},
+};
+
 #[turbo_tasks::value(shared)]
+#[derive(Clone, Copy, Debug)]
 pub enum NodeEnv {
     Development,
     Production,
+    Test,
 }
 
 impl fmt::Display for NodeEnv {
@@ -41,6 +54,7 @@ impl fmt::Display for NodeEnv {
         match self {
             NodeEnv::Development => f.write_str("development"),
             NodeEnv::Production => f.write_str("production"),
+            NodeEnv::Test => f.write_str("test"),
         }
     }
 }
@@ -58,6 +72,12 @@ pub async fn get_client_import_map(project_path: Vc<FileSystemPath>) -> Result<V
     import_map.insert_singleton_alias("react", project_path);
     import_map.insert_singleton_alias("react-dom", project_path);
 
+    import_map.insert_exact_alias(
+        FEATURE_FLAGS_MODULE,
+        feature_flags_import_mapping(project_path),
+    );
+    import_map.insert_exact_alias(MESSAGES_MODULE, messages_import_mapping(project_path));
+
     import_map.insert_wildcard_alias(
         "@vercel/turbopack-ecmascript-runtime/",
         ImportMapping::PrimaryAlternative(
@@ -70,16 +90,46 @@ pub async fn get_client_import_map(project_path: Vc<FileSystemPath>) -> Result<V
     Ok(import_map.cell())
 }
 
+/// Requests that are aliased to other requests, e. g. to swap modules in a
+/// variant of the client bundle.
+#[turbo_tasks::value(transparent)]
+pub struct ModuleAliases(Vec<(String, String)>);
+
+#[turbo_tasks::function]
+async fn get_client_variant_import_map(
+    project_path: Vc<FileSystemPath>,
+    aliases: Vc<ModuleAliases>,
+) -> Result<Vc<ImportMap>> {
+    let mut import_map = get_client_import_map(project_path).await?.clone_value();
+    for (request, replacement) in aliases.await?.iter() {
+        import_map.insert_exact_alias(
+            request,
+            ImportMapping::PrimaryAlternative(replacement.clone(), Some(project_path)).cell(),
+        );
+    }
+    Ok(import_map.cell())
+}
+
+#[turbo_tasks::function]
+pub fn get_client_resolve_options_context(
+    project_path: Vc<FileSystemPath>,
+    preset: Vc<EnvironmentPreset>,
+) -> Vc<ResolveOptionsContext> {
+    client_resolve_options_context(project_path, get_client_import_map(project_path), preset)
+}
+
 #[turbo_tasks::function]
-pub async fn get_client_resolve_options_context(
+async fn client_resolve_options_context(
     project_path: Vc<FileSystemPath>,
+    import_map: Vc<ImportMap>,
+    preset: Vc<EnvironmentPreset>,
 ) -> Result<Vc<ResolveOptionsContext>> {
-    let next_client_import_map = get_client_import_map(project_path);
+    let preset = preset.await?;
     let module_options_context = ResolveOptionsContext {
         enable_node_modules: Some(project_path.root().resolve().await?),
-        custom_conditions: vec!["development".to_string()],
-        import_map: Some(next_client_import_map),
-        browser: true,
+        custom_conditions: preset.resolve_conditions(),
+        import_map: Some(import_map),
+        browser: preset.target.resolves_browser_field(),
         module: true,
         ..Default::default()
     };
@@ -100,18 +150,20 @@ async fn get_client_module_options_context(
     project_path: Vc<FileSystemPath>,
     execution_context: Vc<ExecutionContext>,
     env: Vc<Environment>,
-    node_env: Vc<NodeEnv>,
+    preset: Vc<EnvironmentPreset>,
 ) -> Result<Vc<ModuleOptionsContext>> {
+    let preset_value = preset.await?;
     let module_options_context = ModuleOptionsContext {
-        preset_env_versions: Some(env),
+        preset_env_versions: preset_value.target.has_runtime_versions().then_some(env),
         execution_context: Some(execution_context),
         tree_shaking_mode: Some(TreeShakingMode::ReexportsOnly),
         ..Default::default()
     };
 
-    let resolve_options_context = get_client_resolve_options_context(project_path);
+    let resolve_options_context = get_client_resolve_options_context(project_path, preset);
 
-    let enable_react_refresh = matches!(*node_env.await?, NodeEnv::Development)
+    let enable_react_refresh = preset_value.react_refresh
+        && preset_value.hot_module_replacement()
         && assert_can_resolve_react_refresh(project_path, resolve_options_context)
             .await?
             .is_found();
@@ -124,34 +176,43 @@ async fn get_client_module_options_context(
         .cell(),
     );
 
-    let versions = *env.runtime_versions().await?;
-
-    let conditions = ModuleRuleCondition::any(vec![
-        ModuleRuleCondition::ResourcePathEndsWith(".js".to_string()),
-        ModuleRuleCondition::ResourcePathEndsWith(".jsx".to_string()),
-        ModuleRuleCondition::ResourcePathEndsWith(".ts".to_string()),
-        ModuleRuleCondition::ResourcePathEndsWith(".tsx".to_string()),
-    ]);
-
-    let custom_rules = ModuleRule::new(
-        conditions,
-        vec![ModuleRuleEffect::ExtendEcmascriptTransforms {
-            prepend: Vc::cell(vec![
-                EcmascriptInputTransform::Plugin(Vc::cell(Box::new(
-                    EmotionTransformer::new(&EmotionTransformConfig::default())
-                        .expect("Should be able to create emotion transformer"),
-                ) as _)),
-                EcmascriptInputTransform::Plugin(Vc::cell(Box::new(
-                    StyledComponentsTransformer::new(&StyledComponentsTransformConfig::default()),
-                ) as _)),
-                EcmascriptInputTransform::Plugin(Vc::cell(Box::new(StyledJsxTransformer::new(
-                    !module_options_context.use_swc_css,
-                    versions,
-                )) as _)),
-            ]),
-            append: Vc::cell(vec![]),
-        }],
-    );
+    let mut custom_rules = Vec::new();
+    if preset_value.css_in_js {
+        let versions = if preset_value.target.has_runtime_versions() {
+            *env.runtime_versions().await?
+        } else {
+            Default::default()
+        };
+
+        let conditions = ModuleRuleCondition::any(vec![
+            ModuleRuleCondition::ResourcePathEndsWith(".js".to_string()),
+            ModuleRuleCondition::ResourcePathEndsWith(".jsx".to_string()),
+            ModuleRuleCondition::ResourcePathEndsWith(".ts".to_string()),
+            ModuleRuleCondition::ResourcePathEndsWith(".tsx".to_string()),
+        ]);
+
+        custom_rules.push(ModuleRule::new(
+            conditions,
+            vec![ModuleRuleEffect::ExtendEcmascriptTransforms {
+                prepend: Vc::cell(vec![
+                    EcmascriptInputTransform::Plugin(Vc::cell(Box::new(
+                        EmotionTransformer::new(&EmotionTransformConfig::default())
+                            .expect("Should be able to create emotion transformer"),
+                    ) as _)),
+                    EcmascriptInputTransform::Plugin(Vc::cell(Box::new(
+                        StyledComponentsTransformer::new(
+                            &StyledComponentsTransformConfig::default(),
+                        ),
+                    ) as _)),
+                    EcmascriptInputTransform::Plugin(Vc::cell(Box::new(StyledJsxTransformer::new(
+                        !module_options_context.use_swc_css,
+                        versions,
+                    )) as _)),
+                ]),
+                append: Vc::cell(vec![]),
+            }],
+        ));
+    }
 
     let module_options_context = ModuleOptionsContext {
         enable_jsx,
@@ -161,7 +222,7 @@ async fn get_client_module_options_context(
             foreign_code_context_condition().await?,
             module_options_context.clone().cell(),
         )],
-        custom_rules: vec![custom_rules],
+        custom_rules,
         ..module_options_context
     }
     .cell();
@@ -174,14 +235,53 @@ pub fn get_client_asset_context(
     project_path: Vc<FileSystemPath>,
     execution_context: Vc<ExecutionContext>,
     compile_time_info: Vc<CompileTimeInfo>,
-    node_env: Vc<NodeEnv>,
+    preset: Vc<EnvironmentPreset>,
+) -> Vc<Box<dyn AssetContext>> {
+    client_asset_context(
+        project_path,
+        execution_context,
+        compile_time_info,
+        preset,
+        get_client_resolve_options_context(project_path, preset),
+    )
+}
+
+/// Creates a client asset context in which `aliases` replace the resolution of
+/// specific requests.
+#[turbo_tasks::function]
+pub fn get_client_variant_asset_context(
+    project_path: Vc<FileSystemPath>,
+    execution_context: Vc<ExecutionContext>,
+    compile_time_info: Vc<CompileTimeInfo>,
+    preset: Vc<EnvironmentPreset>,
+    aliases: Vc<ModuleAliases>,
 ) -> Vc<Box<dyn AssetContext>> {
-    let resolve_options_context = get_client_resolve_options_context(project_path);
+    client_asset_context(
+        project_path,
+        execution_context,
+        compile_time_info,
+        preset,
+        client_resolve_options_context(
+            project_path,
+            get_client_variant_import_map(project_path, aliases),
+            preset,
+        ),
+    )
+}
+
+#[turbo_tasks::function]
+async fn client_asset_context(
+    project_path: Vc<FileSystemPath>,
+    execution_context: Vc<ExecutionContext>,
+    compile_time_info: Vc<CompileTimeInfo>,
+    preset: Vc<EnvironmentPreset>,
+    resolve_options_context: Vc<ResolveOptionsContext>,
+) -> Result<Vc<Box<dyn AssetContext>>> {
     let module_options_context = get_client_module_options_context(
         project_path,
         execution_context,
         compile_time_info.environment(),
-        node_env,
+        preset,
     );
 
     let asset_context: Vc<Box<dyn AssetContext>> = Vc::upcast(ModuleAssetContext::new(
@@ -189,37 +289,63 @@ pub fn get_client_asset_context(
         compile_time_info,
         module_options_context,
         resolve_options_context,
-        Vc::cell("client".to_string()),
+        Vc::cell(preset.await?.layer()),
     ));
 
-    asset_context
+    Ok(asset_context)
 }
 
-fn client_defines(node_env: &NodeEnv) -> Vc<CompileTimeDefines> {
-    compile_time_defines!(
+/// Environment variables with this prefix are inlined into client code, e. g.
+/// `process.env.TURBOPACK_PUBLIC_API_URL`.
+const PUBLIC_ENV_PREFIX: &str = "TURBOPACK_PUBLIC_";
+
+#[turbo_tasks::function]
+async fn client_defines(
+    preset: Vc<EnvironmentPreset>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    feature_flags: Vc<JsonValue>,
+) -> Result<Vc<CompileTimeDefines>> {
+    let preset = preset.await?;
+    let mut defines = compile_time_defines!(
         process.turbopack = true,
         process.env.TURBOPACK = true,
-        process.env.NODE_ENV = node_env.to_string()
-    )
-    .cell()
+        process.env.NODE_ENV = preset.mode.to_string()
+    );
+    for (path, value) in &preset.defines {
+        defines.0.insert(
+            path.split('.').map(str::to_string).collect(),
+            value.clone().into(),
+        );
+    }
+    // The env is read through turbo-tasks, so editing an `.env` file updates
+    // the defines without restarting the dev server. Modules are only
+    // recompiled when the public variables actually changed.
+    for (name, value) in env.read_all().await?.iter() {
+        if name.starts_with(PUBLIC_ENV_PREFIX) {
+            defines.0.insert(
+                vec!["process".to_string(), "env".to_string(), name.clone()],
+                value.clone().into(),
+            );
+        }
+    }
+    defines
+        .0
+        .extend(feature_flag_defines(feature_flags).await?.clone_value());
+    Ok(defines.cell())
 }
 
 #[turbo_tasks::function]
 pub async fn get_client_compile_time_info(
+    project_path: Vc<FileSystemPath>,
     browserslist_query: String,
-    node_env: Vc<NodeEnv>,
+    preset: Vc<EnvironmentPreset>,
+    env: Vc<Box<dyn ProcessEnv>>,
 ) -> Result<Vc<CompileTimeInfo>> {
-    Ok(
-        CompileTimeInfo::builder(Environment::new(Value::new(ExecutionEnvironment::Browser(
-            BrowserEnvironment {
-                dom: true,
-                web_worker: false,
-                service_worker: false,
-                browserslist_query,
-            }
-            .into(),
-        ))))
-        .defines(client_defines(&*node_env.await?))
-        .cell(),
-    )
+    let feature_flags = load_feature_flags(project_path, preset.node_env(), env);
+    Ok(CompileTimeInfo::builder(Environment::new(Value::new(
+        preset.await?.execution_environment(browserslist_query),
+    )))
+    .defines(client_defines(preset, env, feature_flags))
+    .free_var_references(feature_flag_free_var_references(feature_flags))
+    .cell())
 }
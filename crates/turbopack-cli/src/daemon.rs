@@ -0,0 +1,101 @@
+use std::{sync::Arc, time::Instant};
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use owo_colors::OwoColorize;
+use serde_json::{json, Value as JsonValue};
+use tokio::sync::{Mutex, Notify};
+use turbo_tasks::{util::FormatDuration, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+
+use crate::{
+    arguments::{BuildArguments, DaemonArguments},
+    build::build_with_turbo_tasks,
+    dev::control::{handle_commands, listen},
+};
+
+/// A long-lived process holding a warm turbo-tasks graph. It accepts commands
+/// on a control socket, with the same line protocol as the dev server's
+/// control socket:
+///
+/// * `build <arguments>` runs a build with the arguments of `turbopack build`,
+///   either separated by spaces or as a JSON array of strings. Relative paths
+///   are resolved against the working directory of the daemon.
+/// * `shutdown` stops the daemon
+///
+/// Files are watched, so a build only recomputes what changed since the last
+/// build of the daemon.
+pub async fn start_daemon(args: &DaemonArguments) -> Result<()> {
+    let state = Arc::new(DaemonState {
+        turbo_tasks: TurboTasks::new(MemoryBackend::new(
+            args.memory_limit.map_or(usize::MAX, |l| l * 1024 * 1024),
+        )),
+        build_lock: Mutex::new(()),
+        shutdown: Notify::new(),
+    });
+
+    let shutdown = state.shutdown.notified();
+    let listen_state = state.clone();
+    listen(&args.socket, move |stream| {
+        let state = listen_state.clone();
+        async move {
+            let state = &state;
+            handle_commands(
+                stream,
+                |command, argument| async move { run_command(state, &command, &argument).await },
+                &state.shutdown,
+            )
+            .await
+        }
+    })?;
+    println!(
+        "{} - daemon listening on {}",
+        "ready".green(),
+        args.socket.display()
+    );
+
+    shutdown.await;
+    Ok(())
+}
+
+struct DaemonState {
+    turbo_tasks: Arc<TurboTasks<MemoryBackend>>,
+    /// Builds write to the same output directories, so they run one at a time.
+    build_lock: Mutex<()>,
+    shutdown: Notify,
+}
+
+/// Parses the arguments of a `build` command like the CLI does.
+#[derive(Parser)]
+#[clap(no_binary_name = true)]
+struct BuildCommand {
+    #[clap(flatten)]
+    args: BuildArguments,
+}
+
+async fn run_command(state: &DaemonState, command: &str, argument: &str) -> Result<JsonValue> {
+    match command {
+        "build" => {
+            let words = if argument.starts_with('[') {
+                serde_json::from_str::<Vec<String>>(argument)
+                    .context("build arguments must be a JSON array of strings")?
+            } else {
+                argument.split_whitespace().map(str::to_string).collect()
+            };
+            let BuildCommand { args } = BuildCommand::try_parse_from(words)?;
+
+            let _lock = state.build_lock.lock().await;
+            let start = Instant::now();
+            build_with_turbo_tasks(state.turbo_tasks.clone(), &args).await?;
+            let duration = start.elapsed();
+            println!(
+                "{} - build finished in {}",
+                "event".purple(),
+                FormatDuration(duration)
+            );
+            Ok(json!({ "durationMs": duration.as_millis() as u64 }))
+        }
+        "shutdown" => Ok(JsonValue::Null),
+        _ => bail!("unknown command `{command}`"),
+    }
+}
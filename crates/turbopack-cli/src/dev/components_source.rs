@@ -0,0 +1,171 @@
+use anyhow::Result;
+use mime::APPLICATION_JSON;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{trace::TraceRawVcs, TryJoinIterExt, Value, Vc};
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbopack::ecmascript::{components::ComponentDeclaration, EcmascriptModuleAsset};
+use turbopack_core::{
+    asset::AssetContent,
+    chunk::{ChunkItemExt, ChunkableModule, ChunkingContext, ModuleId},
+    module::{Module, Modules},
+    reference::all_modules_and_affecting_sources,
+    version::VersionedContentExt,
+};
+use turbopack_dev_server::source::{
+    query::QueryValue,
+    route_tree::{BaseSegment, RouteTree, RouteType},
+    ContentSource, ContentSourceContent, ContentSourceData, ContentSourceDataFilter,
+    ContentSourceDataVary, GetContentSourceContent,
+};
+
+/// Serves the likely React components of the app at `__turbopack_components__`
+/// as JSON, with the source file, position and module id of each, for "open
+/// component source" features of React DevTools integrations.
+///
+/// `?name=Button` only returns the components named or displayed as `Button`.
+#[turbo_tasks::value]
+pub struct ComponentsContentSource {
+    project_path: Vc<FileSystemPath>,
+    entries: Vc<Modules>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+}
+
+#[turbo_tasks::value_impl]
+impl ComponentsContentSource {
+    #[turbo_tasks::function]
+    pub fn new(
+        project_path: Vc<FileSystemPath>,
+        entries: Vc<Modules>,
+        chunking_context: Vc<Box<dyn ChunkingContext>>,
+    ) -> Vc<Self> {
+        ComponentsContentSource {
+            project_path,
+            entries,
+            chunking_context,
+        }
+        .cell()
+    }
+}
+
+#[derive(Serialize, Deserialize, TraceRawVcs, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct ComponentLocation {
+    #[serde(flatten)]
+    declaration: ComponentDeclaration,
+    /// Path of the module, relative to the project when it is in it.
+    path: String,
+    /// The id of the module in the chunks served to the browser, which is the
+    /// key of its factory in the runtime.
+    module_id: ModuleId,
+}
+
+#[turbo_tasks::value(transparent)]
+struct ComponentLocations(Vec<ComponentLocation>);
+
+/// Collects the components of all ecmascript modules in the graph below
+/// `entries`. It is re-executed when the graph or a module changes.
+#[turbo_tasks::function]
+async fn component_locations(
+    project_path: Vc<FileSystemPath>,
+    entries: Vc<Modules>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+) -> Result<Vc<ComponentLocations>> {
+    let project_path = project_path.await?;
+    let mut modules = Vec::new();
+    for &entry in entries.await?.iter() {
+        for &module in all_modules_and_affecting_sources(entry).await?.iter() {
+            if let Some(ecmascript) =
+                Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(module).await?
+            {
+                if !modules.contains(&ecmascript) {
+                    modules.push(ecmascript);
+                }
+            }
+        }
+    }
+
+    let locations = modules
+        .into_iter()
+        .map(|module| {
+            let project_path = &project_path;
+            async move {
+                let declarations = module.component_declarations().await?;
+                if declarations.is_empty() {
+                    return Ok(Vec::new());
+                }
+                let path = module.ident().path().await?;
+                let path = project_path
+                    .get_path_to(&path)
+                    .map_or_else(|| path.to_string(), str::to_string);
+                let module_id = module
+                    .as_chunk_item(chunking_context)
+                    .id()
+                    .await?
+                    .clone_value();
+                Ok(declarations
+                    .iter()
+                    .map(|declaration| ComponentLocation {
+                        declaration: declaration.clone(),
+                        path: path.clone(),
+                        module_id: module_id.clone(),
+                    })
+                    .collect::<Vec<_>>())
+            }
+        })
+        .try_join()
+        .await?;
+
+    Ok(Vc::cell(locations.into_iter().flatten().collect()))
+}
+
+#[turbo_tasks::value_impl]
+impl ContentSource for ComponentsContentSource {
+    #[turbo_tasks::function]
+    fn get_routes(self: Vc<Self>) -> Vc<RouteTree> {
+        RouteTree::new_route(
+            vec![BaseSegment::Static("__turbopack_components__".to_string())],
+            RouteType::Exact,
+            Vc::upcast(self),
+        )
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl GetContentSourceContent for ComponentsContentSource {
+    #[turbo_tasks::function]
+    fn vary(&self) -> Vc<ContentSourceDataVary> {
+        ContentSourceDataVary {
+            query: Some(ContentSourceDataFilter::Subset(["name".to_string()].into())),
+            ..Default::default()
+        }
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    async fn get(
+        &self,
+        _path: String,
+        data: Value<ContentSourceData>,
+    ) -> Result<Vc<ContentSourceContent>> {
+        let name = match data.query.as_ref().and_then(|query| query.get("name")) {
+            Some(QueryValue::String(name)) => Some(name.as_str()),
+            _ => None,
+        };
+        let locations =
+            component_locations(self.project_path, self.entries, self.chunking_context).await?;
+        let locations = locations
+            .iter()
+            .filter(|location| {
+                name.map_or(true, |name| {
+                    location.declaration.name == name
+                        || location.declaration.display_name.as_deref() == Some(name)
+                })
+            })
+            .collect::<Vec<_>>();
+        let json = serde_json::to_string(&locations)?;
+        Ok(ContentSourceContent::static_content(
+            AssetContent::file(File::from(json).with_content_type(APPLICATION_JSON).into())
+                .versioned(),
+        ))
+    }
+}
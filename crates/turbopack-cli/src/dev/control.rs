@@ -0,0 +1,349 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    path::{Component, Path, PathBuf, MAIN_SEPARATOR},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value as JsonValue};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    sync::Notify,
+};
+use turbo_tasks::{ReadRef, TurboTasks, Vc};
+use turbo_tasks_fs::DiskFileSystem;
+use turbo_tasks_memory::MemoryBackend;
+use turbopack_core::issue::PlainIssue;
+use turbopack_dev_server::{RouteLimiter, ServerHealth};
+use turbopack_node::render::{
+    isr::isr_cache,
+    stats::{render_stats, reset_render_stats},
+};
+
+use crate::util::project_fs;
+
+/// A control interface of a running dev server on a Unix domain socket (or a
+/// named pipe on Windows).
+///
+/// Clients send one command per line and receive one line of JSON per command,
+/// either `{ "ok": true, "result": ... }` or `{ "ok": false, "error": "..." }`.
+/// Supported commands:
+///
+/// * `invalidate <path>` invalidates a file or directory, relative to the
+///   project directory and without `..` segments
+/// * `render <route>` renders a route and returns the response body
+/// * `issues` returns all current issues
+/// * `render-stats` returns the aggregated resource usage of the renders of
+///   each page, the most expensive pages first. `render-stats reset` clears
+///   them.
+/// * `revalidate [<path>]` revalidates the cached renders of the page at the
+///   URL path `path`, or of all pages, see
+///   [IsrCache](turbopack_node::render::isr::IsrCache)
+/// * `route-stats` returns the number of active, queued, completed and rejected
+///   requests of each route which likely renders, see [RouteLimiter]
+/// * `drain` stops accepting connections and fails `/readyz`, then stops the
+///   dev server once the pending requests are answered. Used when a new dev
+///   server took over the port.
+/// * `shutdown` stops the dev server
+pub struct ControlSocket {
+    state: Arc<ControlState>,
+}
+
+struct ControlState {
+    turbo_tasks: Arc<TurboTasks<MemoryBackend>>,
+    root_dir: String,
+    project_dir: String,
+    server_addr: SocketAddr,
+    health: Arc<ServerHealth>,
+    route_limiter: Arc<RouteLimiter>,
+    issues: Mutex<Vec<ReadRef<PlainIssue>>>,
+    shutdown: Notify,
+}
+
+impl ControlSocket {
+    pub fn new(
+        turbo_tasks: Arc<TurboTasks<MemoryBackend>>,
+        root_dir: String,
+        project_dir: String,
+        server_addr: SocketAddr,
+        health: Arc<ServerHealth>,
+        route_limiter: Arc<RouteLimiter>,
+    ) -> Self {
+        ControlSocket {
+            state: Arc::new(ControlState {
+                turbo_tasks,
+                root_dir,
+                project_dir,
+                server_addr,
+                health,
+                route_limiter,
+                issues: Default::default(),
+                shutdown: Notify::new(),
+            }),
+        }
+    }
+
+    /// Updates the issues returned by the `issues` command.
+    pub fn set_issues(&self, issues: Vec<ReadRef<PlainIssue>>) {
+        *self.state.issues.lock().unwrap() = issues;
+    }
+
+    /// Resolves when a client sent the `shutdown` command.
+    pub async fn shutdown_requested(&self) {
+        self.state.shutdown.notified().await
+    }
+
+    /// Listens on `path` and handles connections in the background.
+    pub fn listen(&self, path: &Path) -> Result<()> {
+        let state = self.state.clone();
+        listen(path, move |stream| {
+            let state = state.clone();
+            async move {
+                let state = &state;
+                handle_commands(
+                    stream,
+                    |command, argument| async move { run_command(state, &command, &argument).await },
+                    &state.shutdown,
+                )
+                .await
+            }
+        })
+    }
+}
+
+#[cfg(unix)]
+pub(crate) type ControlStream = tokio::net::UnixStream;
+
+#[cfg(windows)]
+pub(crate) type ControlStream = tokio::net::windows::named_pipe::NamedPipeServer;
+
+/// Listens on a Unix domain socket (or a named pipe on Windows) at `path` and
+/// handles each connection with `handle` in the background.
+#[cfg(unix)]
+pub(crate) fn listen<F, Fut>(path: &Path, handle: F) -> Result<()>
+where
+    F: Fn(ControlStream) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    // A socket left behind by a previous process would fail the bind
+    let _ = std::fs::remove_file(path);
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("binding control socket {}", path.display()))?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle(stream));
+        }
+    });
+    Ok(())
+}
+
+/// Listens on a Unix domain socket (or a named pipe on Windows) at `path` and
+/// handles each connection with `handle` in the background.
+#[cfg(windows)]
+pub(crate) fn listen<F, Fut>(path: &Path, handle: F) -> Result<()>
+where
+    F: Fn(ControlStream) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(path)
+        .with_context(|| format!("creating control pipe {}", path.display()))?;
+    let path = path.to_path_buf();
+    tokio::spawn(async move {
+        while server.connect().await.is_ok() {
+            let Ok(next) = ServerOptions::new().create(&path) else {
+                break;
+            };
+            let connected = std::mem::replace(&mut server, next);
+            tokio::spawn(handle(connected));
+        }
+    });
+    Ok(())
+}
+
+/// Runs the commands sent over `stream`, one per line, and responds with one
+/// line of JSON per command, see [ControlSocket]. Notifies `shutdown` and
+/// returns after the `shutdown` command.
+pub(crate) async fn handle_commands<F, Fut>(
+    stream: impl AsyncRead + AsyncWrite + Unpin,
+    run_command: F,
+    shutdown: &Notify,
+) -> Result<()>
+where
+    F: Fn(String, String) -> Fut,
+    Fut: Future<Output = Result<JsonValue>>,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let shutdown_requested = command == "shutdown";
+        let response = match run_command(command.to_string(), argument.trim().to_string()).await {
+            Ok(result) => json!({ "ok": true, "result": result }),
+            Err(error) => json!({ "ok": false, "error": format!("{error:#}") }),
+        };
+        writer.write_all(format!("{response}\n").as_bytes()).await?;
+        writer.flush().await?;
+        if shutdown_requested {
+            shutdown.notify_waiters();
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn run_command(state: &ControlState, command: &str, argument: &str) -> Result<JsonValue> {
+    match command {
+        "invalidate" => {
+            if argument.is_empty() {
+                bail!("usage: invalidate <path>");
+            }
+            invalidate(state, argument).await?;
+            Ok(JsonValue::Null)
+        }
+        "render" => {
+            if !argument.starts_with('/') {
+                bail!("usage: render <route>, the route must start with /");
+            }
+            render(state.server_addr, argument).await
+        }
+        "issues" => {
+            let issues = state.issues.lock().unwrap().clone();
+            Ok(JsonValue::Array(
+                issues
+                    .iter()
+                    .map(|issue| {
+                        json!({
+                            "severity": issue.severity.as_str(),
+                            "stage": issue.stage.to_string(),
+                            "filePath": issue.file_path,
+                            "title": issue.title,
+                        })
+                    })
+                    .collect(),
+            ))
+        }
+        "render-stats" => {
+            if argument == "reset" {
+                reset_render_stats();
+                return Ok(JsonValue::Null);
+            }
+            Ok(JsonValue::Array(
+                render_stats()
+                    .into_iter()
+                    .map(|(page, stats)| json!({ "page": page, "stats": stats }))
+                    .collect(),
+            ))
+        }
+        "revalidate" => {
+            if !argument.is_empty() && !argument.starts_with('/') {
+                bail!("usage: revalidate [<path>], the path must start with /");
+            }
+            revalidate(state, argument).await?;
+            Ok(JsonValue::Null)
+        }
+        "route-stats" => Ok(JsonValue::Array(
+            state
+                .route_limiter
+                .stats()
+                .into_iter()
+                .map(|(route, stats)| json!({ "route": route, "stats": stats }))
+                .collect(),
+        )),
+        "drain" => {
+            state.health.drain();
+            Ok(JsonValue::Null)
+        }
+        "shutdown" => Ok(JsonValue::Null),
+        _ => bail!("unknown command `{command}`"),
+    }
+}
+
+async fn invalidate(state: &ControlState, path: &str) -> Result<()> {
+    if Path::new(path)
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        bail!("{path} must not contain .. segments");
+    }
+    // Without `.` segments, which the paths of the file system don't have
+    let path = Path::new(&state.project_dir)
+        .join(path)
+        .components()
+        .filter(|component| *component != Component::CurDir)
+        .collect::<PathBuf>();
+    let path = path
+        .strip_prefix(&state.root_dir)
+        .map_err(|_| anyhow!("{} is outside of the root directory", path.display()))?
+        .to_string_lossy()
+        .replace(MAIN_SEPARATOR, "/");
+    let root_dir = state.root_dir.clone();
+    state
+        .turbo_tasks
+        .run_once(async move {
+            let fs = project_fs(root_dir);
+            let Some(disk_fs) = Vc::try_resolve_downcast_type::<DiskFileSystem>(fs).await? else {
+                bail!("the project filesystem is not a disk filesystem");
+            };
+            disk_fs.await?.invalidate_path(&path);
+            Ok(())
+        })
+        .await
+}
+
+/// Revalidates the renders of the page at `path` in the ISR cache, or all
+/// renders when `path` is empty.
+async fn revalidate(state: &ControlState, path: &str) -> Result<()> {
+    let path = path.to_string();
+    state
+        .turbo_tasks
+        .run_once(async move {
+            let cache = isr_cache().await?;
+            if path.is_empty() {
+                cache.revalidate_all();
+            } else {
+                cache.revalidate(&path);
+            }
+            Ok(())
+        })
+        .await
+}
+
+/// Renders a route by requesting it from the dev server.
+async fn render(server_addr: SocketAddr, route: &str) -> Result<JsonValue> {
+    let mut addr = server_addr;
+    if addr.ip().is_unspecified() {
+        addr.set_ip(if addr.is_ipv6() {
+            std::net::Ipv6Addr::LOCALHOST.into()
+        } else {
+            std::net::Ipv4Addr::LOCALHOST.into()
+        });
+    }
+    let mut stream = TcpStream::connect(addr).await?;
+    stream
+        .write_all(
+            format!("GET {route} HTTP/1.0\r\nHost: {addr}\r\nConnection: close\r\n\r\n").as_bytes(),
+        )
+        .await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .context("malformed response from the dev server")?;
+    let status = head
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .context("malformed status line from the dev server")?;
+    Ok(json!({ "status": status, "body": body }))
+}
@@ -0,0 +1,237 @@
+use std::{
+    convert::Infallible,
+    ffi::OsString,
+    net::{SocketAddr, TcpListener},
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context, Result};
+use hyper::{
+    client::HttpConnector,
+    header::UPGRADE,
+    service::{make_service_fn, service_fn},
+    Body, Client, Method, Request, Response, Server, StatusCode,
+};
+use owo_colors::OwoColorize;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command,
+    select,
+    sync::watch,
+    time::{sleep, timeout},
+};
+use turbopack_dev_server::ServerHealth;
+
+use super::print_ready;
+use crate::arguments::DevArguments;
+
+/// The line a backend process prints once it's listening, followed by its
+/// address.
+pub(super) const BACKEND_READY: &str = "turbopack-backend-ready";
+
+/// How long requests wait for a backend process to become ready, e. g. during
+/// the initial compilation or after a crash.
+const BACKEND_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Backend processes crashing faster than this are restarted with a delay, to
+/// avoid a restart loop hogging the CPU.
+const MIN_BACKEND_UPTIME: Duration = Duration::from_secs(5);
+
+type BackendAddr = watch::Receiver<Option<SocketAddr>>;
+
+/// Serves HTTP in this process and proxies all requests to a backend process
+/// doing the compilation and rendering. The backend process is restarted when
+/// it exits unexpectedly, requests wait for the restarted backend instead of
+/// failing.
+///
+/// The health endpoints are answered by this process, so they don't wait for
+/// the backend: `/readyz` fails while no backend process is ready.
+pub(super) async fn start_front_server(
+    args: &DevArguments,
+    listener: Option<TcpListener>,
+) -> Result<()> {
+    let (sender, backend) = watch::channel(None);
+    let health = Arc::new(ServerHealth::default());
+    {
+        let backend = backend.clone();
+        health.add_readiness_check("compiler", move || match *backend.borrow() {
+            Some(_) => Ok(()),
+            None => Err("the compiler process is starting".to_string()),
+        });
+    }
+    let client = Client::new();
+    let make_service = make_service_fn(move |_| {
+        let backend = backend.clone();
+        let client = client.clone();
+        let health = health.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                proxy(request, backend.clone(), client.clone(), health.clone())
+            }))
+        }
+    });
+    let server = match listener {
+        Some(listener) => Server::from_tcp(listener).context("serving the inherited socket")?,
+        None => {
+            let addr = SocketAddr::new(args.hostname, args.port);
+            Server::try_bind(&addr).with_context(|| format!("binding {addr}"))?
+        }
+    }
+    .serve(make_service);
+    print_ready(server.local_addr(), !args.no_open);
+
+    select! {
+        result = server => result?,
+        result = supervise_backend(sender) => result?,
+    }
+    Ok(())
+}
+
+/// Runs the backend process, restarting it whenever it exits with a failure.
+/// Returns when it exits successfully, e. g. after a `shutdown` control
+/// command.
+async fn supervise_backend(sender: watch::Sender<Option<SocketAddr>>) -> Result<()> {
+    let exe = std::env::current_exe().context("locating the turbopack executable")?;
+    let args = std::env::args_os()
+        .skip(1)
+        .filter(|arg| arg != "--multi-process")
+        .chain([OsString::from("--backend-process")])
+        .collect::<Vec<_>>();
+    loop {
+        let started = Instant::now();
+        let mut child = Command::new(&exe)
+            .args(&args)
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("spawning the compiler process")?;
+        let stdout = child.stdout.take().context("stdout is piped")?;
+        let forward_output = async {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match line.strip_prefix(BACKEND_READY) {
+                    Some(addr) => {
+                        sender.send_replace(addr.trim().parse().ok());
+                    }
+                    None => println!("{line}"),
+                }
+            }
+        };
+        let ((), status) = futures::join!(forward_output, child.wait());
+        sender.send_replace(None);
+        let status = status.context("waiting for the compiler process")?;
+        if status.success() {
+            return Ok(());
+        }
+        println!(
+            "{} - compiler process exited ({status}), restarting",
+            "warn".yellow()
+        );
+        if started.elapsed() < MIN_BACKEND_UPTIME {
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+async fn proxy(
+    request: Request<Body>,
+    backend: BackendAddr,
+    client: Client<HttpConnector>,
+    health: Arc<ServerHealth>,
+) -> Result<Response<Body>, Infallible> {
+    let result = if let Some(response) = health.response(request.uri().path()) {
+        response
+    } else if request.headers().contains_key(UPGRADE) {
+        proxy_upgrade(request, backend, client).await
+    } else {
+        proxy_request(request, backend, client).await
+    };
+    Ok(result.unwrap_or_else(|error| {
+        let mut response = Response::new(Body::from(format!("{error:#}")));
+        *response.status_mut() = StatusCode::BAD_GATEWAY;
+        response
+    }))
+}
+
+async fn proxy_request(
+    request: Request<Body>,
+    mut backend: BackendAddr,
+    client: Client<HttpConnector>,
+) -> Result<Response<Body>> {
+    let (parts, body) = request.into_parts();
+    // Buffered, so the request can be resent to a restarted backend
+    let body = hyper::body::to_bytes(body).await?;
+    let idempotent = matches!(parts.method, Method::GET | Method::HEAD | Method::OPTIONS);
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let addr = backend_addr(&mut backend).await?;
+        let mut backend_request = Request::builder()
+            .method(parts.method.clone())
+            .uri(backend_uri(addr, &parts.uri))
+            .version(parts.version)
+            .body(Body::from(body.clone()))?;
+        *backend_request.headers_mut() = parts.headers.clone();
+        match client.request(backend_request).await {
+            Ok(response) => return Ok(response),
+            // The backend crashed or is restarting. Requests that weren't received
+            // or can be repeated are retried once it's back.
+            Err(error) if attempts < 3 && (error.is_connect() || idempotent) => {
+                let _ = timeout(BACKEND_TIMEOUT, backend.changed()).await;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+/// Proxies a protocol upgrade, e. g. the HMR WebSocket, by connecting the
+/// upgraded connections of the client and the backend.
+async fn proxy_upgrade(
+    mut request: Request<Body>,
+    mut backend: BackendAddr,
+    client: Client<HttpConnector>,
+) -> Result<Response<Body>> {
+    let addr = backend_addr(&mut backend).await?;
+    let mut backend_request = Request::builder()
+        .method(request.method().clone())
+        .uri(backend_uri(addr, request.uri()))
+        .body(Body::empty())?;
+    *backend_request.headers_mut() = request.headers().clone();
+    let mut response = client.request(backend_request).await?;
+    if response.status() == StatusCode::SWITCHING_PROTOCOLS {
+        let backend_upgrade = hyper::upgrade::on(&mut response);
+        let client_upgrade = hyper::upgrade::on(&mut request);
+        tokio::spawn(async move {
+            let (Ok(mut backend), Ok(mut client)) = futures::join!(backend_upgrade, client_upgrade)
+            else {
+                return;
+            };
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut backend).await;
+        });
+    }
+    Ok(response)
+}
+
+/// Waits until a backend process is ready.
+async fn backend_addr(backend: &mut BackendAddr) -> Result<SocketAddr> {
+    let wait = async {
+        loop {
+            if let Some(addr) = *backend.borrow_and_update() {
+                return Ok(addr);
+            }
+            if backend.changed().await.is_err() {
+                bail!("the compiler process supervisor stopped");
+            }
+        }
+    };
+    timeout(BACKEND_TIMEOUT, wait)
+        .await
+        .context("timed out waiting for the compiler process")?
+}
+
+fn backend_uri(addr: SocketAddr, uri: &hyper::Uri) -> String {
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+    format!("http://{addr}{path}")
+}
@@ -0,0 +1,75 @@
+use anyhow::Result;
+use mime::APPLICATION_JSON;
+use turbo_tasks::{Value, Vc};
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbopack_core::{asset::AssetContent, module::Modules, version::VersionedContentExt};
+use turbopack_dev_server::source::{
+    route_tree::{BaseSegment, RouteTree, RouteType},
+    ContentSource, ContentSourceContent, ContentSourceData, GetContentSourceContent,
+};
+
+use crate::i18n::{project_message_catalogs, MESSAGES_DIR};
+
+/// Serves the message catalog of each locale at `_messages/<locale>.json`,
+/// for `loadMessages` of [crate::i18n::MESSAGES_MODULE]. Catalogs only
+/// contain the messages used by the app, and are updated when a catalog or a
+/// module using messages changes.
+#[turbo_tasks::value]
+pub struct MessagesContentSource {
+    project_path: Vc<FileSystemPath>,
+    entries: Vc<Modules>,
+}
+
+#[turbo_tasks::value_impl]
+impl MessagesContentSource {
+    #[turbo_tasks::function]
+    pub fn new(project_path: Vc<FileSystemPath>, entries: Vc<Modules>) -> Vc<Self> {
+        MessagesContentSource {
+            project_path,
+            entries,
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ContentSource for MessagesContentSource {
+    #[turbo_tasks::function]
+    fn get_routes(self: Vc<Self>) -> Vc<RouteTree> {
+        RouteTree::new_route(
+            vec![
+                BaseSegment::Static(MESSAGES_DIR.to_string()),
+                BaseSegment::Dynamic,
+            ],
+            RouteType::Exact,
+            Vc::upcast(self),
+        )
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl GetContentSourceContent for MessagesContentSource {
+    #[turbo_tasks::function]
+    async fn get(
+        &self,
+        path: String,
+        _data: Value<ContentSourceData>,
+    ) -> Result<Vc<ContentSourceContent>> {
+        let Some(locale) = path
+            .strip_prefix(MESSAGES_DIR)
+            .and_then(|path| path.strip_prefix('/'))
+            .and_then(|file| file.strip_suffix(".json"))
+        else {
+            return Ok(ContentSourceContent::not_found());
+        };
+        let catalogs = project_message_catalogs(self.project_path, self.entries).await?;
+        let Some(messages) = catalogs.get(locale) else {
+            return Ok(ContentSourceContent::not_found());
+        };
+        let json = serde_json::to_string(messages)?;
+        Ok(ContentSourceContent::static_content(
+            AssetContent::file(File::from(json).with_content_type(APPLICATION_JSON).into())
+                .versioned(),
+        ))
+    }
+}
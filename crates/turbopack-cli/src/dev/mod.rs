@@ -1,19 +1,21 @@
 use std::{
     collections::HashSet,
     env::current_dir,
-    future::{join, Future},
+    future::{pending, Future},
     io::{stdout, Write},
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener},
     path::{PathBuf, MAIN_SEPARATOR},
     sync::Arc,
     time::{Duration, Instant},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use futures::{channel::mpsc, StreamExt};
 use owo_colors::OwoColorize;
+use tokio::select;
 use turbo_tasks::{
     util::{FormatBytes, FormatDuration},
-    StatsType, TransientInstance, TurboTasks, TurboTasksBackendApi, UpdateInfo, Value, Vc,
+    StatsType, TransientInstance, TurboTasks, TurboTasksBackendApi, Value, Vc,
 };
 use turbo_tasks_fs::FileSystem;
 use turbo_tasks_malloc::TurboMalloc;
@@ -22,7 +24,7 @@ use turbopack::evaluate_context::node_build_environment;
 use turbopack_browser::BrowserChunkingContext;
 use turbopack_cli_utils::issue::{ConsoleUi, LogOptions};
 use turbopack_core::{
-    issue::{IssueReporter, IssueSeverity},
+    issue::{panic::PanicIssue, IssueExt, IssueReporter, IssueSeverity},
     resolve::parse::Request,
     server_fs::ServerFileSystem,
 };
@@ -32,22 +34,36 @@ use turbopack_dev_server::{
         combined::CombinedContentSource, router::PrefixedRouterContentSource,
         static_assets::StaticAssetsContentSource, ContentSource,
     },
-    DevServer, DevServerBuilder,
+    AccessControl, BasicAuth, ContentSecurityPolicy, DevServer, DevServerBuilder, RouteLimiter,
+    RouteLimits, SecurityHeaders, ServerHealth,
 };
 use turbopack_ecmascript_runtime::RuntimeType;
 use turbopack_env::dotenv::load_env;
-use turbopack_node::execution_context::ExecutionContext;
-
-use self::web_entry_source::create_web_entry_source;
+use turbopack_node::{execution_context::ExecutionContext, failed_process_bootups};
+
+pub use self::watch::{WatchEvent, WatchEvents};
+use self::{
+    control::ControlSocket,
+    watch::{forward_updates, WatchIssueReporterProvider},
+    watch_limit::report_polled_directories,
+    web_entry_source::create_web_entry_source,
+};
 use crate::{
     arguments::DevArguments,
-    contexts::NodeEnv,
+    environment::EnvironmentPreset,
     util::{
         normalize_dirs, normalize_entries, output_fs, project_fs, EntryRequest, NormalizedDirs,
     },
 };
 
+mod components_source;
+pub(crate) mod control;
+mod front;
+mod messages_source;
 pub(crate) mod turbo_tasks_viz;
+mod watch;
+mod watch_limit;
+pub(crate) mod watch_scope;
 pub(crate) mod web_entry_source;
 
 pub struct TurbopackDevServerBuilder {
@@ -56,6 +72,7 @@ pub struct TurbopackDevServerBuilder {
     root_dir: String,
     entry_requests: Vec<EntryRequest>,
     eager_compile: bool,
+    scoped_watching: bool,
     hostname: Option<IpAddr>,
     issue_reporter: Option<Box<dyn IssueReporterProvider>>,
     port: Option<u16>,
@@ -64,6 +81,12 @@ pub struct TurbopackDevServerBuilder {
     show_all: bool,
     log_detail: bool,
     allow_retry: bool,
+    reuse_port: bool,
+    listener: Option<TcpListener>,
+    security_headers: SecurityHeaders,
+    access_control: AccessControl,
+    health: Arc<ServerHealth>,
+    route_limiter: Arc<RouteLimiter>,
 }
 
 impl TurbopackDevServerBuilder {
@@ -78,6 +101,7 @@ impl TurbopackDevServerBuilder {
             root_dir,
             entry_requests: vec![],
             eager_compile: false,
+            scoped_watching: false,
             hostname: None,
             issue_reporter: None,
             port: None,
@@ -88,6 +112,12 @@ impl TurbopackDevServerBuilder {
             show_all: false,
             log_detail: false,
             allow_retry: false,
+            reuse_port: false,
+            listener: None,
+            security_headers: Default::default(),
+            access_control: Default::default(),
+            health: Default::default(),
+            route_limiter: Default::default(),
         }
     }
 
@@ -101,6 +131,13 @@ impl TurbopackDevServerBuilder {
         self
     }
 
+    /// Only watches the directories which contain modules of the app, see
+    /// [watch_scope::scope_watching].
+    pub fn scoped_watching(mut self, scoped_watching: bool) -> TurbopackDevServerBuilder {
+        self.scoped_watching = scoped_watching;
+        self
+    }
+
     pub fn hostname(mut self, hostname: IpAddr) -> TurbopackDevServerBuilder {
         self.hostname = Some(hostname);
         self
@@ -131,11 +168,50 @@ impl TurbopackDevServerBuilder {
         self
     }
 
+    /// Listens with `SO_REUSEPORT` on Unix, so a new dev server can take over
+    /// the port while this one drains, see [ServerHealth::drain].
+    pub fn reuse_port(mut self, reuse_port: bool) -> TurbopackDevServerBuilder {
+        self.reuse_port = reuse_port;
+        self
+    }
+
+    /// Serves on a listening socket passed by a process supervisor instead of
+    /// binding the hostname and port, see [take_inherited_listener].
+    pub fn listener(mut self, listener: Option<TcpListener>) -> TurbopackDevServerBuilder {
+        self.listener = listener;
+        self
+    }
+
     pub fn log_detail(mut self, log_detail: bool) -> TurbopackDevServerBuilder {
         self.log_detail = log_detail;
         self
     }
 
+    pub fn security_headers(
+        mut self,
+        security_headers: SecurityHeaders,
+    ) -> TurbopackDevServerBuilder {
+        self.security_headers = security_headers;
+        self
+    }
+
+    pub fn access_control(mut self, access_control: AccessControl) -> TurbopackDevServerBuilder {
+        self.access_control = access_control;
+        self
+    }
+
+    /// Sets the state reported by the `/healthz` and `/readyz` endpoints.
+    pub fn health(mut self, health: Arc<ServerHealth>) -> TurbopackDevServerBuilder {
+        self.health = health;
+        self
+    }
+
+    /// Sets the limits of concurrent requests of routes which likely render.
+    pub fn route_limiter(mut self, route_limiter: Arc<RouteLimiter>) -> TurbopackDevServerBuilder {
+        self.route_limiter = route_limiter;
+        self
+    }
+
     pub fn issue_reporter(
         mut self,
         issue_reporter: Box<dyn IssueReporterProvider>,
@@ -152,7 +228,17 @@ impl TurbopackDevServerBuilder {
         loop {
             let current_port = port + attempts;
             let addr = SocketAddr::new(host, current_port);
-            let listen_result = DevServer::listen(addr);
+            #[cfg(unix)]
+            let listen_result = if self.reuse_port {
+                DevServer::listen_reuse_port(addr)
+            } else {
+                DevServer::listen(addr)
+            };
+            #[cfg(not(unix))]
+            let listen_result = {
+                let _ = self.reuse_port;
+                DevServer::listen(addr)
+            };
 
             if let Err(e) = &listen_result {
                 if self.allow_retry && attempts < max_attempts {
@@ -184,31 +270,30 @@ impl TurbopackDevServerBuilder {
         }
     }
 
-    pub async fn build(self) -> Result<DevServer> {
+    pub async fn build(mut self) -> Result<DevServer> {
         let port = self.port.context("port must be set")?;
         let host = self.hostname.context("hostname must be set")?;
 
-        let server = self.find_port(host, port, 10)?;
+        let server = match self.listener.take() {
+            Some(listener) => DevServer::from_listener(listener)?,
+            None => self.find_port(host, port, 10)?,
+        }
+        .security_headers(self.security_headers)
+        .access_control(self.access_control)
+        .health(self.health)
+        .route_limiter(self.route_limiter);
 
         let turbo_tasks = self.turbo_tasks;
         let project_dir = self.project_dir;
         let root_dir = self.root_dir;
         let eager_compile = self.eager_compile;
-        let show_all = self.show_all;
-        let log_detail = self.log_detail;
+        let scoped_watching = self.scoped_watching;
         let browserslist_query = self.browserslist_query;
-        let log_args = Arc::new(LogOptions {
-            current_dir: current_dir().unwrap(),
-            project_dir: PathBuf::from(project_dir.clone()),
-            show_all,
-            log_detail,
-            log_level: self.log_level,
-        });
         let entry_requests = Arc::new(self.entry_requests);
         let tasks = turbo_tasks.clone();
         let issue_provider = self.issue_reporter.unwrap_or_else(|| {
             // Initialize a ConsoleUi reporter if no custom reporter was provided
-            Box::new(move || Vc::upcast(ConsoleUi::new(log_args.clone().into())))
+            console_issue_reporter(&project_dir, self.show_all, self.log_detail, self.log_level)
         });
 
         let source = move || {
@@ -217,6 +302,7 @@ impl TurbopackDevServerBuilder {
                 project_dir.clone(),
                 entry_requests.clone().into(),
                 eager_compile,
+                scoped_watching,
                 turbo_tasks.clone().into(),
                 browserslist_query.clone(),
             )
@@ -225,6 +311,83 @@ impl TurbopackDevServerBuilder {
         let issue_reporter_arc = Arc::new(move || issue_provider.get_issue_reporter());
         Ok(server.serve(tasks, source, issue_reporter_arc))
     }
+
+    /// Builds the dev server like [TurbopackDevServerBuilder::build] and
+    /// returns a stream of [WatchEvent]s, which allows editors, test runners
+    /// and other tools to follow rebuilds and issues without parsing logs.
+    ///
+    /// Issues are still reported to the configured issue reporter. The events
+    /// are derived from the aggregated update info of the turbo tasks
+    /// instance, which must not be consumed elsewhere.
+    pub async fn watch(mut self) -> Result<(DevServer, WatchEvents)> {
+        let (sender, events) = mpsc::unbounded();
+        let sender = Arc::new(sender);
+        let inner = match self.issue_reporter.take() {
+            Some(issue_reporter) => issue_reporter,
+            None => console_issue_reporter(
+                &self.project_dir,
+                self.show_all,
+                self.log_detail,
+                self.log_level,
+            ),
+        };
+        self.issue_reporter = Some(Box::new(WatchIssueReporterProvider {
+            inner,
+            sender: sender.clone(),
+        }));
+
+        let turbo_tasks = self.turbo_tasks.clone();
+        let server = self.build().await?;
+        let _ = sender.unbounded_send(WatchEvent::ServerReady { addr: server.addr });
+        tokio::spawn(forward_updates(turbo_tasks, sender));
+        Ok((server, events))
+    }
+}
+
+/// The listening socket passed by a process supervisor or by the dev server
+/// being replaced, following the protocol of systemd socket activation: the
+/// socket is fd 3, `LISTEN_FDS` is set and `LISTEN_PID` is this process.
+///
+/// This clears the `LISTEN_*` environment variables, so it must be called
+/// before any other threads are started, e. g. before the tokio runtime.
+#[cfg(unix)]
+pub fn take_inherited_listener() -> Option<TcpListener> {
+    use std::os::unix::io::FromRawFd;
+
+    const LISTEN_FDS_START: i32 = 3;
+
+    let pid: u32 = std::env::var("LISTEN_PID").ok()?.parse().ok()?;
+    let fds: u32 = std::env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    if pid != std::process::id() || fds == 0 {
+        return None;
+    }
+    // Child processes must not take over the socket too
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    // SAFETY: the socket activation protocol passes an open listening socket
+    // as fd 3, which nothing else in this process owns
+    Some(unsafe { TcpListener::from_raw_fd(LISTEN_FDS_START) })
+}
+
+#[cfg(not(unix))]
+pub fn take_inherited_listener() -> Option<TcpListener> {
+    None
+}
+
+fn console_issue_reporter(
+    project_dir: &str,
+    show_all: bool,
+    log_detail: bool,
+    log_level: IssueSeverity,
+) -> Box<dyn IssueReporterProvider> {
+    let log_args = Arc::new(LogOptions {
+        current_dir: current_dir().unwrap(),
+        project_dir: PathBuf::from(project_dir),
+        show_all,
+        log_detail,
+        log_level,
+    });
+    Box::new(move || Vc::upcast(ConsoleUi::new(log_args.clone().into())))
 }
 
 #[turbo_tasks::function]
@@ -233,6 +396,7 @@ async fn source(
     project_dir: String,
     entry_requests: TransientInstance<Vec<EntryRequest>>,
     eager_compile: bool,
+    scoped_watching: bool,
     turbo_tasks: TransientInstance<TurboTasks<MemoryBackend>>,
     browserslist_query: String,
 ) -> Result<Vc<Box<dyn ContentSource>>> {
@@ -244,6 +408,7 @@ async fn source(
 
     let output_fs = output_fs(project_dir);
     let fs = project_fs(root_dir);
+    report_polled_directories(fs).await?;
     let project_path: Vc<turbo_tasks_fs::FileSystemPath> = fs.root().join(project_relative);
 
     let env = load_env(project_path);
@@ -284,7 +449,8 @@ async fn source(
         server_root,
         env,
         eager_compile,
-        NodeEnv::Development.cell(),
+        scoped_watching,
+        EnvironmentPreset::development().cell(),
         browserslist_query,
     );
     let viz = Vc::upcast(turbo_tasks_viz::TurboTasksSource::new(turbo_tasks.into()));
@@ -317,10 +483,15 @@ pub fn register() {
     include!(concat!(env!("OUT_DIR"), "/register.rs"));
 }
 
-/// Start a devserver with the given args.
-pub async fn start_server(args: &DevArguments) -> Result<()> {
+/// Start a devserver with the given args. It serves on `listener` when the
+/// process was passed one, see [take_inherited_listener].
+pub async fn start_server(args: &DevArguments, listener: Option<TcpListener>) -> Result<()> {
     let start = Instant::now();
 
+    if args.multi_process {
+        return front::start_front_server(args, listener).await;
+    }
+
     #[cfg(feature = "tokio_console")]
     console_subscriber::init();
     register();
@@ -342,19 +513,56 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
     };
     tt.set_stats_type(stats_type);
 
+    // A panicking task only fails the parts of the app depending on it, the
+    // panic is reported like any other issue
+    tt.set_task_panic_hook(|task, message| {
+        PanicIssue {
+            task: task.to_string(),
+            message: message.to_string(),
+        }
+        .cell()
+        .emit();
+    });
+
     let tt_clone = tt.clone();
 
-    let mut server = TurbopackDevServerBuilder::new(tt, project_dir, root_dir)
+    let health = Arc::new(ServerHealth::default());
+    health.add_readiness_check("renderer", || match failed_process_bootups() {
+        0 => Ok(()),
+        failed => Err(format!("{failed} renderer processes failed to start")),
+    });
+
+    let route_limiter = Arc::new(RouteLimiter::new(RouteLimits {
+        max_concurrent: args.max_concurrent_route_requests,
+        max_queued: args.max_queued_route_requests,
+        ..Default::default()
+    }));
+
+    // A backend process is only reachable through its front process
+    let (hostname, port) = if args.backend_process {
+        (IpAddr::from(Ipv4Addr::LOCALHOST), 0)
+    } else {
+        (args.hostname, args.port)
+    };
+
+    let mut server = TurbopackDevServerBuilder::new(tt, project_dir.clone(), root_dir.clone())
         .eager_compile(args.eager_compile)
-        .hostname(args.hostname)
-        .port(args.port)
+        .scoped_watching(args.scoped_watching)
+        .hostname(hostname)
+        .port(port)
         .log_detail(args.common.log_detail)
         .show_all(args.common.show_all)
         .log_level(
             args.common
                 .log_level
                 .map_or_else(|| IssueSeverity::Warning, |l| l.0),
-        );
+        )
+        .reuse_port(args.reuse_port)
+        .listener(listener)
+        .security_headers(security_headers(args))
+        .access_control(access_control(args)?)
+        .health(health.clone())
+        .route_limiter(route_limiter.clone());
 
     for entry in normalize_entries(&args.common.entries) {
         server = server.entry_request(EntryRequest::Relative(entry))
@@ -365,33 +573,43 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
         server = server.allow_retry(args.allow_retry);
     }
 
-    let server = server.build().await?;
+    let (server, mut events) = server.watch().await?;
+
+    let control_socket = match &args.control_socket {
+        Some(path) => {
+            let control_socket = ControlSocket::new(
+                tt_clone.clone(),
+                root_dir,
+                project_dir,
+                server.addr,
+                health.clone(),
+                route_limiter,
+            );
+            control_socket.listen(path)?;
+            Some(control_socket)
+        }
+        None => None,
+    };
+    let control_socket = control_socket.as_ref();
+
+    if args.backend_process {
+        println!("{} {}", front::BACKEND_READY, server.addr);
+    } else {
+        print_ready(server.addr, !args.no_open);
+    }
 
+    // A supervisor stops the server with SIGTERM, which drains it, so
+    // requests accepted before a new server took over are still answered
+    #[cfg(unix)]
     {
-        let addr = &server.addr;
-        let hostname = if addr.ip().is_loopback() || addr.ip().is_unspecified() {
-            "localhost".to_string()
-        } else if addr.is_ipv6() {
-            // When using an IPv6 address, we need to surround the IP in brackets to
-            // distinguish it from the port's `:`.
-            format!("[{}]", addr.ip())
-        } else {
-            addr.ip().to_string()
-        };
-        let index_uri = match addr.port() {
-            443 => format!("https://{hostname}"),
-            80 => format!("http://{hostname}"),
-            port => format!("http://{hostname}:{port}"),
-        };
-        println!(
-            "{} - started server on {}, url: {}",
-            "ready".green(),
-            server.addr,
-            index_uri
-        );
-        if !args.no_open {
-            let _ = webbrowser::open(&index_uri);
-        }
+        let health = health.clone();
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::spawn(async move {
+            if terminate.recv().await.is_some() {
+                health.drain();
+            }
+        });
     }
 
     let stats_future = async move {
@@ -404,82 +622,157 @@ pub async fn start_server(args: &DevArguments) -> Result<()> {
             );
         }
 
-        let mut progress_counter = 0;
+        let mut rebuild_started = None;
+        let mut progress = tokio::time::interval(Duration::from_secs(1));
         loop {
-            let update_future = profile_timeout(
-                tt_clone.as_ref(),
-                tt_clone.aggregated_update_info(Duration::from_millis(100), Duration::MAX),
-            );
-
-            if let Some(UpdateInfo {
-                duration,
-                tasks,
-                reasons,
-                ..
-            }) = update_future.await
-            {
-                progress_counter = 0;
-                match (args.common.log_detail, !reasons.is_empty()) {
-                    (true, true) => {
-                        println!(
-                            "\x1b[2K{event_type} - {reasons} {duration} ({tasks} tasks, {memory})",
-                            event_type = "event".purple(),
-                            duration = FormatDuration(duration),
-                            tasks = tasks,
-                            memory = FormatBytes(TurboMalloc::memory_usage())
-                        );
-                    }
-                    (true, false) => {
-                        println!(
-                            "\x1b[2K{event_type} - compilation {duration} ({tasks} tasks, \
-                             {memory})",
-                            event_type = "event".purple(),
-                            duration = FormatDuration(duration),
-                            tasks = tasks,
-                            memory = FormatBytes(TurboMalloc::memory_usage())
-                        );
-                    }
-                    (false, true) => {
-                        println!(
-                            "\x1b[2K{event_type} - {reasons} {duration}",
-                            event_type = "event".purple(),
-                            duration = FormatDuration(duration),
-                        );
+            let event = select! {
+                event = profile_timeout(tt_clone.as_ref(), events.next()) => event,
+                _ = progress.tick(), if rebuild_started.is_some() => {
+                    let seconds = rebuild_started
+                        .map_or(0, |started: Instant| started.elapsed().as_secs());
+                    if seconds > 0 {
+                        if args.common.log_detail {
+                            print!(
+                                "\x1b[2K{event_type} - updating for {seconds}s... ({memory})\r",
+                                event_type = "event".purple(),
+                                memory = FormatBytes(TurboMalloc::memory_usage())
+                            );
+                        } else {
+                            print!(
+                                "\x1b[2K{event_type} - updating for {seconds}s...\r",
+                                event_type = "event".purple(),
+                            );
+                        }
+                        let _ = stdout().lock().flush();
                     }
-                    (false, false) => {
-                        if duration > Duration::from_secs(1) {
+                    continue;
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                WatchEvent::RebuildStarted => {
+                    health.set_compiling(true);
+                    rebuild_started = Some(Instant::now());
+                    progress.reset();
+                }
+                WatchEvent::RebuildFinished {
+                    duration,
+                    tasks,
+                    reasons,
+                } => {
+                    health.set_compiling(false);
+                    rebuild_started = None;
+                    match (args.common.log_detail, !reasons.is_empty()) {
+                        (true, true) => {
+                            println!(
+                                "\x1b[2K{event_type} - {reasons} {duration} ({tasks} tasks, \
+                                 {memory})",
+                                event_type = "event".purple(),
+                                duration = FormatDuration(duration),
+                                tasks = tasks,
+                                memory = FormatBytes(TurboMalloc::memory_usage())
+                            );
+                        }
+                        (true, false) => {
+                            println!(
+                                "\x1b[2K{event_type} - compilation {duration} ({tasks} tasks, \
+                                 {memory})",
+                                event_type = "event".purple(),
+                                duration = FormatDuration(duration),
+                                tasks = tasks,
+                                memory = FormatBytes(TurboMalloc::memory_usage())
+                            );
+                        }
+                        (false, true) => {
                             println!(
-                                "\x1b[2K{event_type} - compilation {duration}",
+                                "\x1b[2K{event_type} - {reasons} {duration}",
                                 event_type = "event".purple(),
                                 duration = FormatDuration(duration),
                             );
                         }
+                        (false, false) => {
+                            if duration > Duration::from_secs(1) {
+                                println!(
+                                    "\x1b[2K{event_type} - compilation {duration}",
+                                    event_type = "event".purple(),
+                                    duration = FormatDuration(duration),
+                                );
+                            }
+                        }
                     }
                 }
-            } else {
-                progress_counter += 1;
-                if args.common.log_detail {
-                    print!(
-                        "\x1b[2K{event_type} - updating for {progress_counter}s... ({memory})\r",
-                        event_type = "event".purple(),
-                        memory = FormatBytes(TurboMalloc::memory_usage())
-                    );
-                } else {
-                    print!(
-                        "\x1b[2K{event_type} - updating for {progress_counter}s...\r",
-                        event_type = "event".purple(),
+                WatchEvent::AssetsChanged { .. } => {
+                    // The change might have fixed what caused a panic
+                    let restarted = tt_clone.restart_panicked_tasks();
+                    if restarted > 0 && args.common.log_detail {
+                        println!(
+                            "{event_type} - retrying {restarted} panicked tasks",
+                            event_type = "event".purple(),
+                        );
+                    }
+                }
+                WatchEvent::IssuesChanged { issues } => {
+                    health.set_errors(
+                        issues
+                            .iter()
+                            .filter(|issue| issue.severity <= IssueSeverity::Error)
+                            .count(),
                     );
+                    if let Some(control_socket) = control_socket {
+                        control_socket.set_issues(issues);
+                    }
                 }
-                let _ = stdout().lock().flush();
+                _ => {}
             }
         }
     };
 
-    join!(stats_future, async { server.future.await.unwrap() }).await;
+    let shutdown_requested = async {
+        match control_socket {
+            Some(control_socket) => control_socket.shutdown_requested().await,
+            None => pending().await,
+        }
+    };
+    // The server future resolves when the server is drained
+    select! {
+        _ = stats_future => {}
+        result = server.future => result?,
+        _ = shutdown_requested => {}
+    }
 
     Ok(())
 }
 
+/// Prints the address the server is listening on, and opens it in the browser
+/// when `open` is set.
+fn print_ready(addr: SocketAddr, open: bool) {
+    let hostname = if addr.ip().is_loopback() || addr.ip().is_unspecified() {
+        "localhost".to_string()
+    } else if addr.is_ipv6() {
+        // When using an IPv6 address, we need to surround the IP in brackets to
+        // distinguish it from the port's `:`.
+        format!("[{}]", addr.ip())
+    } else {
+        addr.ip().to_string()
+    };
+    let index_uri = match addr.port() {
+        443 => format!("https://{hostname}"),
+        80 => format!("http://{hostname}"),
+        port => format!("http://{hostname}:{port}"),
+    };
+    println!(
+        "{} - started server on {}, url: {}",
+        "ready".green(),
+        addr,
+        index_uri
+    );
+    if open {
+        let _ = webbrowser::open(&index_uri);
+    }
+}
+
 #[cfg(feature = "profile")]
 // When profiling, exits the process when no new updates have been received for
 // a given timeout and there are no more tasks in progress.
@@ -521,3 +814,39 @@ where
         self()
     }
 }
+
+fn access_control(args: &DevArguments) -> Result<AccessControl> {
+    let basic_auth = args
+        .basic_auth
+        .as_deref()
+        .map(|credentials| {
+            let Some((user, password)) = credentials.split_once(':') else {
+                bail!("--basic-auth must be written as <user>:<password>");
+            };
+            Ok(BasicAuth {
+                user: user.to_string(),
+                password: password.to_string(),
+            })
+        })
+        .transpose()?;
+    Ok(AccessControl {
+        token: args.auth_token.clone().filter(|token| !token.is_empty()),
+        basic_auth,
+        exclude: args.auth_exclude.clone(),
+    })
+}
+
+fn security_headers(args: &DevArguments) -> SecurityHeaders {
+    if args.no_security_headers {
+        return SecurityHeaders::none();
+    }
+    SecurityHeaders {
+        content_security_policy: (args.csp || args.csp_report_only).then(|| {
+            ContentSecurityPolicy {
+                report_only: args.csp_report_only,
+                ..Default::default()
+            }
+        }),
+        ..Default::default()
+    }
+}
@@ -2,7 +2,7 @@ use std::{sync::Arc, time::Duration};
 
 use anyhow::{bail, Result};
 use mime::TEXT_HTML_UTF_8;
-use turbo_tasks::{get_invalidator, TurboTasks, TurboTasksBackendApi, Value, Vc};
+use turbo_tasks::{backend::Backend, get_invalidator, TurboTasks, TurboTasksBackendApi, Value, Vc};
 use turbo_tasks_fs::File;
 use turbo_tasks_memory::{
     stats::{ReferenceType, Stats},
@@ -10,6 +10,7 @@ use turbo_tasks_memory::{
 };
 use turbopack_core::{asset::AssetContent, version::VersionedContentExt};
 use turbopack_dev_server::source::{
+    query::QueryValue,
     route_tree::{BaseSegment, RouteTree, RouteTrees, RouteType},
     ContentSource, ContentSourceContent, ContentSourceData, ContentSourceDataFilter,
     ContentSourceDataVary, GetContentSourceContent,
@@ -49,6 +50,11 @@ impl ContentSource for TurboTasksSource {
                 RouteType::Exact,
                 Vc::upcast(self),
             ),
+            RouteTree::new_route(
+                vec![BaseSegment::Static("why".to_string())],
+                RouteType::Exact,
+                Vc::upcast(self),
+            ),
             RouteTree::new_route(
                 vec![BaseSegment::Static("reset".to_string())],
                 RouteType::Exact,
@@ -125,6 +131,28 @@ impl GetContentSourceContent for TurboTasksSource {
                 let table = viz::table::create_table(tree, tt.stats_type());
                 viz::table::wrap_html(&table)
             }
+            "why" => {
+                // e. g. `why?task=render_static` explains why all matching tasks recomputed
+                let Some(QueryValue::String(filter)) =
+                    data.query.as_ref().and_then(|query| query.get("task"))
+                else {
+                    bail!("Missing task query, e. g. ?task=render_static");
+                };
+                let b = tt.backend();
+                let mut tasks = Vec::new();
+                b.with_all_cached_tasks(|task| {
+                    if b.get_task_description(task).contains(filter.as_str()) {
+                        tasks.push(task);
+                    }
+                });
+                let chains = tasks
+                    .into_iter()
+                    .map(|task| tt.explain_recomputation(task))
+                    .filter(|chain| !chain.is_empty())
+                    .collect::<Vec<_>>();
+                let content = viz::invalidations::create_chains(&chains, tt.stats_type());
+                viz::invalidations::wrap_html(&content)
+            }
             "reset" => {
                 let b = tt.backend();
                 b.with_all_cached_tasks(|task| {
@@ -0,0 +1,195 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use futures::channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use turbo_tasks::{
+    RawVc, ReadRef, TransientInstance, TransientValue, TryJoinIterExt, TurboTasks, UpdateInfo, Vc,
+};
+use turbo_tasks_fs::invalidation::WatchChange;
+use turbo_tasks_memory::MemoryBackend;
+use turbopack_core::issue::{CapturedIssues, IssueReporter, IssueSeverity, PlainIssue};
+
+use super::IssueReporterProvider;
+
+/// An event emitted by a dev server started with
+/// [TurbopackDevServerBuilder::watch](super::TurbopackDevServerBuilder::watch).
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    /// The server is listening for requests.
+    ServerReady { addr: SocketAddr },
+    /// Tasks have been scheduled, e. g. because files changed.
+    RebuildStarted,
+    /// All scheduled tasks have finished.
+    RebuildFinished {
+        duration: Duration,
+        tasks: usize,
+        /// A readable description of what caused the rebuild.
+        reasons: String,
+    },
+    /// Files changed on disk and caused a rebuild. Emitted before
+    /// [WatchEvent::RebuildFinished].
+    AssetsChanged { paths: Vec<String> },
+    /// The reported issues changed. Contains all current issues, not only the
+    /// new ones.
+    IssuesChanged { issues: Vec<ReadRef<PlainIssue>> },
+}
+
+/// The stream of [WatchEvent]s. Events are no longer forwarded once it is
+/// dropped.
+pub type WatchEvents = UnboundedReceiver<WatchEvent>;
+
+pub(super) type WatchEventSender = Arc<UnboundedSender<WatchEvent>>;
+
+/// Forwards the update info of `turbo_tasks` as [WatchEvent]s until the
+/// receiver is dropped.
+///
+/// This consumes the aggregated update info, which must not be read
+/// concurrently by anything else.
+pub(super) async fn forward_updates(
+    turbo_tasks: Arc<TurboTasks<MemoryBackend>>,
+    sender: WatchEventSender,
+) {
+    loop {
+        // Without a timeout this returns `None` as soon as tasks are scheduled,
+        // or the update info when an update finished in the meantime.
+        let update = turbo_tasks
+            .aggregated_update_info(Duration::ZERO, Duration::ZERO)
+            .await;
+        if sender.unbounded_send(WatchEvent::RebuildStarted).is_err() {
+            return;
+        }
+        let UpdateInfo {
+            duration,
+            tasks,
+            reasons,
+            ..
+        } = match update {
+            Some(update) => update,
+            None => {
+                turbo_tasks
+                    .get_or_wait_aggregated_update_info(Duration::from_millis(100))
+                    .await
+            }
+        };
+        let paths = reasons
+            .iter()
+            .filter_map(|reason| reason.as_any().downcast_ref::<WatchChange>())
+            .map(|change| change.path.clone())
+            .collect::<Vec<_>>();
+        if !paths.is_empty() {
+            let _ = sender.unbounded_send(WatchEvent::AssetsChanged { paths });
+        }
+        let finished = WatchEvent::RebuildFinished {
+            duration,
+            tasks,
+            reasons: reasons.to_string(),
+        };
+        if sender.unbounded_send(finished).is_err() {
+            return;
+        }
+    }
+}
+
+/// Wraps an [IssueReporterProvider] to additionally emit
+/// [WatchEvent::IssuesChanged].
+pub(super) struct WatchIssueReporterProvider {
+    pub inner: Box<dyn IssueReporterProvider>,
+    pub sender: WatchEventSender,
+}
+
+impl IssueReporterProvider for WatchIssueReporterProvider {
+    fn get_issue_reporter(&self) -> Vc<Box<dyn IssueReporter>> {
+        Vc::upcast(WatchIssueReporter::new(
+            self.inner.get_issue_reporter(),
+            self.sender.clone().into(),
+        ))
+    }
+}
+
+#[turbo_tasks::value(shared, serialization = "none", eq = "manual")]
+struct WatchIssueReporter {
+    inner: Vc<Box<dyn IssueReporter>>,
+
+    #[turbo_tasks(trace_ignore, debug_ignore)]
+    sender: WatchEventSender,
+
+    /// The issues last reported per source, with their hashes.
+    #[turbo_tasks(trace_ignore, debug_ignore)]
+    seen: Arc<Mutex<HashMap<RawVc, Vec<(ReadRef<PlainIssue>, u64)>>>>,
+}
+
+impl PartialEq for WatchIssueReporter {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner && Arc::ptr_eq(&self.sender, &other.sender)
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl WatchIssueReporter {
+    #[turbo_tasks::function]
+    fn new(
+        inner: Vc<Box<dyn IssueReporter>>,
+        sender: TransientInstance<UnboundedSender<WatchEvent>>,
+    ) -> Vc<Self> {
+        WatchIssueReporter {
+            inner,
+            sender: sender.into(),
+            seen: Default::default(),
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl IssueReporter for WatchIssueReporter {
+    #[turbo_tasks::function]
+    async fn report_issues(
+        &self,
+        issues: TransientInstance<CapturedIssues>,
+        source: TransientValue<RawVc>,
+        min_failing_severity: Vc<IssueSeverity>,
+    ) -> Result<Vc<bool>> {
+        let source = source.into_value();
+        let plain_issues = issues
+            .iter_with_shortest_path()
+            .map(|(issue, path)| async move {
+                let plain_issue = issue.into_plain(path);
+                let id = plain_issue.internal_hash(false).await?;
+                Ok((plain_issue.await?, *id))
+            })
+            .try_join()
+            .await?;
+        let ids = |issues: &[(ReadRef<PlainIssue>, u64)]| {
+            issues.iter().map(|(_, id)| *id).collect::<HashSet<_>>()
+        };
+        let mut seen = self.seen.lock().unwrap();
+        let changed = seen
+            .get(&source)
+            .map_or(!plain_issues.is_empty(), |previous| {
+                ids(previous) != ids(&plain_issues)
+            });
+        if changed {
+            seen.insert(source, plain_issues);
+            let mut seen_ids = HashSet::new();
+            let issues = seen
+                .values()
+                .flatten()
+                .filter(|(_, id)| seen_ids.insert(*id))
+                .map(|(issue, _)| issue.clone())
+                .collect();
+            let _ = self
+                .sender
+                .unbounded_send(WatchEvent::IssuesChanged { issues });
+        }
+        drop(seen);
+
+        Ok(self
+            .inner
+            .report_issues(issues, TransientValue::new(source), min_failing_severity))
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use turbo_tasks::{Completion, Vc};
+use turbo_tasks_fs::{DiskFileSystem, FileSystem, FileSystemPath};
+use turbopack_core::issue::{
+    Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString,
+};
+
+/// Reports the directories of `fs` which are polled for changes because the
+/// OS limit of watched files was reached. Re-executed when more directories
+/// start to be polled.
+#[turbo_tasks::function]
+pub async fn report_polled_directories(fs: Vc<Box<dyn FileSystem>>) -> Result<Vc<Completion>> {
+    let Some(disk_fs) = Vc::try_resolve_downcast_type::<DiskFileSystem>(fs).await? else {
+        return Ok(Completion::new());
+    };
+    let directories = disk_fs.polled_directories().await?;
+    if !directories.is_empty() {
+        WatchLimitIssue {
+            root: fs.root(),
+            directories: directories.clone_value(),
+        }
+        .cell()
+        .emit();
+    }
+    Ok(Completion::new())
+}
+
+#[turbo_tasks::value(shared)]
+pub struct WatchLimitIssue {
+    root: Vc<FileSystemPath>,
+    directories: Vec<String>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for WatchLimitIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Warning.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.root
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Misc.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(format!(
+            "Too many files to watch, polling {} directories for changes instead",
+            self.directories.len()
+        ))
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Stack(vec![
+                StyledString::Text(
+                    "The limit of watched files of the operating system was reached. Changes are \
+                     still picked up, but polling is slower and uses more CPU."
+                        .to_string(),
+                ),
+                StyledString::Line(vec![
+                    StyledString::Text("On Linux, raise the limit with ".to_string()),
+                    StyledString::Code(
+                        "sudo sysctl fs.inotify.max_user_watches=524288".to_string(),
+                    ),
+                    StyledString::Text(
+                        ", or only watch the directories of the app with --scoped-watching."
+                            .to_string(),
+                    ),
+                ]),
+            ])
+            .cell(),
+        ))
+    }
+
+    #[turbo_tasks::function]
+    fn detail(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Stack(
+                self.directories
+                    .iter()
+                    .map(|dir| StyledString::Code(dir.clone()))
+                    .collect(),
+            )
+            .cell(),
+        ))
+    }
+}
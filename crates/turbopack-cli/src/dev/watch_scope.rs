@@ -0,0 +1,44 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use turbo_tasks::{Completion, Vc};
+use turbo_tasks_fs::{DiskFileSystem, FileSystemPath};
+use turbopack_core::{
+    module::{Module, Modules},
+    reference::all_modules_and_affecting_sources,
+};
+
+/// Restricts watching of the project filesystem to the directories which
+/// contain modules of the app, or config files affecting them, like
+/// `package.json` and `tsconfig.json`, instead of every directory that was
+/// read while resolving. The project directory is always watched.
+///
+/// This walks the whole module graph below `entries`. It is re-executed when
+/// the graph changes, which updates the watched directories.
+#[turbo_tasks::function]
+pub async fn scope_watching(
+    project_path: Vc<FileSystemPath>,
+    entries: Vc<Modules>,
+) -> Result<Vc<Completion>> {
+    let project_path = project_path.await?;
+    let Some(disk_fs) = Vc::try_resolve_downcast_type::<DiskFileSystem>(project_path.fs).await?
+    else {
+        return Ok(Completion::new());
+    };
+
+    let mut dirs = BTreeSet::from([project_path.path.clone()]);
+    for &entry in entries.await?.iter() {
+        for &module in all_modules_and_affecting_sources(entry).await?.iter() {
+            let path = module.ident().path().parent().await?;
+            // Modules of other filesystems, e. g. embedded runtime code
+            if path.fs.resolve().await? == project_path.fs.resolve().await? {
+                dirs.insert(path.path.clone());
+            }
+        }
+    }
+    disk_fs
+        .await?
+        .set_watch_scope(dirs.iter().map(String::as_str));
+
+    Ok(Completion::new())
+}
@@ -9,6 +9,7 @@ use turbopack_core::{
     chunk::{ChunkableModule, ChunkingContext},
     environment::Environment,
     file_source::FileSource,
+    module::Modules,
     reference_type::{EntryReferenceSubType, ReferenceType},
     resolve::{
         origin::{PlainResolveOrigin, ResolveOriginExt},
@@ -17,7 +18,9 @@ use turbopack_core::{
 };
 use turbopack_dev_server::{
     html::DevHtmlAsset,
-    source::{asset_graph::AssetGraphContentSource, ContentSource},
+    source::{
+        asset_graph::AssetGraphContentSource, combined::CombinedContentSource, ContentSource,
+    },
 };
 use turbopack_ecmascript_runtime::RuntimeType;
 use turbopack_node::execution_context::ExecutionContext;
@@ -25,44 +28,55 @@ use turbopack_node::execution_context::ExecutionContext;
 use crate::{
     contexts::{
         get_client_asset_context, get_client_compile_time_info, get_client_resolve_options_context,
-        NodeEnv,
+    },
+    dev::{
+        components_source::ComponentsContentSource, messages_source::MessagesContentSource,
+        watch_scope::scope_watching,
     },
     embed_js::embed_file_path,
+    environment::EnvironmentPreset,
 };
 
 #[turbo_tasks::function]
-pub fn get_client_chunking_context(
+pub async fn get_client_chunking_context(
     project_path: Vc<FileSystemPath>,
     server_root: Vc<FileSystemPath>,
     environment: Vc<Environment>,
-) -> Vc<Box<dyn ChunkingContext>> {
-    Vc::upcast(
-        BrowserChunkingContext::builder(
-            project_path,
-            server_root,
-            server_root,
-            server_root.join("/_chunks".to_string()),
-            server_root.join("/_assets".to_string()),
-            environment,
-            RuntimeType::Development,
-        )
-        .hot_module_replacement()
-        .build(),
-    )
+    preset: Vc<EnvironmentPreset>,
+) -> Result<Vc<Box<dyn ChunkingContext>>> {
+    let preset = preset.await?;
+    let mut builder = BrowserChunkingContext::builder(
+        project_path,
+        server_root,
+        server_root,
+        server_root.join("/_chunks".to_string()),
+        server_root.join("/_assets".to_string()),
+        environment,
+        preset.runtime_type(),
+    );
+    if preset.hot_module_replacement() {
+        builder = builder.hot_module_replacement();
+    }
+    Ok(Vc::upcast(builder.build()))
 }
 
 #[turbo_tasks::function]
 pub async fn get_client_runtime_entries(
     project_path: Vc<FileSystemPath>,
+    preset: Vc<EnvironmentPreset>,
 ) -> Result<Vc<RuntimeEntries>> {
-    let resolve_options_context = get_client_resolve_options_context(project_path);
+    let resolve_options_context = get_client_resolve_options_context(project_path, preset);
 
     let mut runtime_entries = Vec::new();
 
-    let enable_react_refresh =
+    let preset = preset.await?;
+    let enable_react_refresh = if preset.react_refresh && preset.hot_module_replacement() {
         assert_can_resolve_react_refresh(project_path, resolve_options_context)
             .await?
-            .as_request();
+            .as_request()
+    } else {
+        None
+    };
     // It's important that React Refresh come before the regular bootstrap file,
     // because the bootstrap contains JSX which requires Refresh's global
     // functions to be available.
@@ -87,17 +101,23 @@ pub async fn create_web_entry_source(
     execution_context: Vc<ExecutionContext>,
     entry_requests: Vec<Vc<Request>>,
     server_root: Vc<FileSystemPath>,
-    _env: Vc<Box<dyn ProcessEnv>>,
+    env: Vc<Box<dyn ProcessEnv>>,
     eager_compile: bool,
-    node_env: Vc<NodeEnv>,
+    scoped_watching: bool,
+    preset: Vc<EnvironmentPreset>,
     browserslist_query: String,
 ) -> Result<Vc<Box<dyn ContentSource>>> {
-    let compile_time_info = get_client_compile_time_info(browserslist_query, node_env);
+    let compile_time_info =
+        get_client_compile_time_info(project_path, browserslist_query, preset, env);
     let asset_context =
-        get_client_asset_context(project_path, execution_context, compile_time_info, node_env);
-    let chunking_context =
-        get_client_chunking_context(project_path, server_root, compile_time_info.environment());
-    let entries = get_client_runtime_entries(project_path);
+        get_client_asset_context(project_path, execution_context, compile_time_info, preset);
+    let chunking_context = get_client_chunking_context(
+        project_path,
+        server_root,
+        compile_time_info.environment(),
+        preset,
+    );
+    let entries = get_client_runtime_entries(project_path, preset);
 
     let runtime_entries = entries.resolve_entries(asset_context);
 
@@ -145,6 +165,16 @@ pub async fn create_web_entry_source(
         .try_join()
         .await?;
 
+    let entry_modules: Vc<Modules> = Vc::cell(
+        entries
+            .iter()
+            .map(|&(module, _, _)| Vc::upcast(module))
+            .collect(),
+    );
+    if scoped_watching {
+        scope_watching(project_path, entry_modules).await?;
+    }
+
     let entry_asset = Vc::upcast(DevHtmlAsset::new(
         server_root.join("index.html".to_string()),
         entries,
@@ -155,5 +185,13 @@ pub async fn create_web_entry_source(
     } else {
         AssetGraphContentSource::new_lazy(server_root, entry_asset)
     });
-    Ok(graph)
+    let components = Vc::upcast(ComponentsContentSource::new(
+        project_path,
+        entry_modules,
+        chunking_context,
+    ));
+    let messages = Vc::upcast(MessagesContentSource::new(project_path, entry_modules));
+    Ok(Vc::upcast(CombinedContentSource::new(vec![
+        graph, components, messages,
+    ])))
 }
@@ -0,0 +1,258 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{trace::TraceRawVcs, Vc};
+use turbopack_core::environment::{
+    BrowserEnvironment, EdgeWorkerEnvironment, ExecutionEnvironment, NodeJsEnvironment,
+};
+use turbopack_ecmascript_runtime::RuntimeType;
+
+pub use crate::contexts::NodeEnv;
+
+/// Where the compiled code runs.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs, ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvironmentTarget {
+    /// A page in the browser.
+    Browser,
+    /// A Node.js process.
+    NodeJs,
+    /// An edge runtime, which provides web APIs without a DOM.
+    Edge,
+    /// A web worker in the browser.
+    Worker,
+}
+
+impl EnvironmentTarget {
+    fn execution_environment(self, browserslist_query: String) -> ExecutionEnvironment {
+        match self {
+            EnvironmentTarget::Browser => ExecutionEnvironment::Browser(
+                BrowserEnvironment {
+                    dom: true,
+                    web_worker: false,
+                    service_worker: false,
+                    browserslist_query,
+                }
+                .into(),
+            ),
+            EnvironmentTarget::Worker => ExecutionEnvironment::Browser(
+                BrowserEnvironment {
+                    dom: false,
+                    web_worker: true,
+                    service_worker: false,
+                    browserslist_query,
+                }
+                .into(),
+            ),
+            EnvironmentTarget::NodeJs => {
+                ExecutionEnvironment::NodeJsLambda(NodeJsEnvironment::default().into())
+            }
+            EnvironmentTarget::Edge => {
+                ExecutionEnvironment::EdgeWorker(EdgeWorkerEnvironment {}.into())
+            }
+        }
+    }
+
+    /// The `exports` conditions of packages matching the target, in order of
+    /// preference.
+    fn resolve_conditions(self) -> &'static [&'static str] {
+        match self {
+            EnvironmentTarget::Browser => &["browser"],
+            EnvironmentTarget::NodeJs => &["node"],
+            EnvironmentTarget::Edge => &["edge-light", "worker", "browser"],
+            EnvironmentTarget::Worker => &["worker", "browser"],
+        }
+    }
+
+    /// Whether the `browser` field of packages replaces their modules.
+    pub fn resolves_browser_field(self) -> bool {
+        !matches!(self, EnvironmentTarget::NodeJs)
+    }
+
+    /// Whether the target has known runtime versions, which the code is
+    /// downleveled to. Edge runtimes don't.
+    pub fn has_runtime_versions(self) -> bool {
+        !matches!(self, EnvironmentTarget::Edge)
+    }
+}
+
+impl fmt::Display for EnvironmentTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            EnvironmentTarget::Browser => "browser",
+            EnvironmentTarget::NodeJs => "node-js",
+            EnvironmentTarget::Edge => "edge",
+            EnvironmentTarget::Worker => "worker",
+        })
+    }
+}
+
+/// The environment entries are compiled for: where they run, the mode, and
+/// what both imply for compile time defines, resolve conditions and
+/// transforms.
+///
+/// The contexts derive everything from the preset instead of assuming a
+/// browser in development or production mode, so a new target only needs a
+/// variant of [EnvironmentTarget].
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug)]
+pub struct EnvironmentPreset {
+    pub name: String,
+    pub target: EnvironmentTarget,
+    pub mode: NodeEnv,
+    /// Compile time defines in addition to those of the mode, by their dotted
+    /// path, e. g. `process.env.API_URL`.
+    pub defines: Vec<(String, String)>,
+    /// Resolve conditions in addition to those of the target and mode.
+    pub resolve_conditions: Vec<String>,
+    /// Whether edited React components are refreshed in place. Only applies
+    /// with hot module replacement, see
+    /// [EnvironmentPreset::hot_module_replacement].
+    pub react_refresh: bool,
+    /// Whether the transforms of CSS-in-JS libraries (emotion,
+    /// styled-components, styled-jsx) are applied.
+    pub css_in_js: bool,
+}
+
+/// The names of the built-in presets, see [EnvironmentPreset::named].
+pub const PRESET_NAMES: &[&str] = &["development", "production", "test"];
+
+impl EnvironmentPreset {
+    /// The preset of the dev server: the browser in development mode.
+    pub fn development() -> Self {
+        EnvironmentPreset {
+            name: "development".to_string(),
+            target: EnvironmentTarget::Browser,
+            mode: NodeEnv::Development,
+            defines: Vec::new(),
+            resolve_conditions: Vec::new(),
+            react_refresh: true,
+            css_in_js: true,
+        }
+    }
+
+    /// The preset of builds: the browser in production mode.
+    pub fn production() -> Self {
+        EnvironmentPreset {
+            name: "production".to_string(),
+            mode: NodeEnv::Production,
+            react_refresh: false,
+            ..EnvironmentPreset::development()
+        }
+    }
+
+    /// The preset of test runners: Node.js in test mode.
+    pub fn test() -> Self {
+        EnvironmentPreset {
+            name: "test".to_string(),
+            target: EnvironmentTarget::NodeJs,
+            mode: NodeEnv::Test,
+            react_refresh: false,
+            ..EnvironmentPreset::development()
+        }
+    }
+
+    /// The built-in preset with the name `name`, see [PRESET_NAMES].
+    pub fn named(name: &str) -> Result<Self> {
+        Ok(match name {
+            "development" => EnvironmentPreset::development(),
+            "production" => EnvironmentPreset::production(),
+            "test" => EnvironmentPreset::test(),
+            _ => bail!(
+                "unknown environment preset `{name}`, expected one of {}",
+                PRESET_NAMES.join(", ")
+            ),
+        })
+    }
+
+    /// The preset for entries which run on another target, e. g. a web worker
+    /// of a browser app.
+    pub fn with_target(mut self, target: EnvironmentTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn execution_environment(&self, browserslist_query: String) -> ExecutionEnvironment {
+        self.target.execution_environment(browserslist_query)
+    }
+
+    /// The `exports` conditions entries are resolved with: the condition of
+    /// the mode, those of the target, and the custom ones. Tests use the
+    /// development builds of packages.
+    pub fn resolve_conditions(&self) -> Vec<String> {
+        let mode = match self.mode {
+            NodeEnv::Development | NodeEnv::Test => "development",
+            NodeEnv::Production => "production",
+        };
+        std::iter::once(mode)
+            .chain(self.target.resolve_conditions().iter().copied())
+            .map(str::to_string)
+            .chain(self.resolve_conditions.iter().cloned())
+            .collect()
+    }
+
+    pub fn runtime_type(&self) -> RuntimeType {
+        match self.mode {
+            NodeEnv::Development | NodeEnv::Test => RuntimeType::Development,
+            NodeEnv::Production => RuntimeType::Production,
+        }
+    }
+
+    /// Only pages in development mode are updated in place.
+    pub fn hot_module_replacement(&self) -> bool {
+        self.mode == NodeEnv::Development && self.target == EnvironmentTarget::Browser
+    }
+
+    /// Modules of different targets are compiled differently, so each target
+    /// has a layer of its own.
+    pub fn layer(&self) -> String {
+        match self.target {
+            EnvironmentTarget::Browser => "client".to_string(),
+            target => target.to_string(),
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl EnvironmentPreset {
+    #[turbo_tasks::function]
+    pub async fn node_env(self: Vc<Self>) -> Result<Vc<NodeEnv>> {
+        Ok(self.await?.mode.cell())
+    }
+}
+
+/// An entry which runs on another target than the other entries, passed as
+/// `<entry>=<target>`, e. g. `src/worker.ts=worker`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryTarget {
+    pub entry: String,
+    pub target: EnvironmentTarget,
+}
+
+impl FromStr for EntryTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((entry, target)) = s.rsplit_once('=') else {
+            bail!("entry target must be written as <entry>=<target>, e. g. src/worker.ts=worker");
+        };
+        let Ok(target) = EnvironmentTarget::from_str(target, true) else {
+            bail!(
+                "unknown target `{target}`, expected one of {}",
+                EnvironmentTarget::value_variants()
+                    .iter()
+                    .map(|target| target.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        };
+        Ok(EntryTarget {
+            entry: entry.to_string(),
+            target,
+        })
+    }
+}
@@ -0,0 +1,100 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+
+use crate::arguments::ExportArguments;
+
+/// A locale pages are exported for. Every locale has its own output tree, in
+/// a directory named after the locale, or after its domain when the locale
+/// is mapped to a domain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Locale {
+    pub name: String,
+    pub tree: String,
+}
+
+impl Locale {
+    /// The route relative to the output directory, which identifies the page
+    /// of this locale in the manifest and for sharding.
+    pub fn key(&self, route: &str) -> String {
+        format!("/{}{route}", self.tree)
+    }
+
+    /// Strips the locale prefix from a link of a page of this locale, so
+    /// `/de/about` and `/about` refer to the same page.
+    pub fn strip_prefix<'a>(&self, link: &'a str) -> &'a str {
+        match link
+            .strip_prefix('/')
+            .and_then(|link| link.strip_prefix(self.name.as_str()))
+        {
+            Some("") => "/",
+            Some(rest) if rest.starts_with('/') => rest,
+            _ => link,
+        }
+    }
+}
+
+/// Maps a locale to a domain, written as `<locale>=<domain>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocaleDomain {
+    pub locale: String,
+    pub domain: String,
+}
+
+impl FromStr for LocaleDomain {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((locale, domain)) = s.split_once('=') else {
+            bail!("locale domain must be written as <locale>=<domain>, e. g. de=example.de");
+        };
+        if locale.is_empty() || domain.is_empty() || domain.contains(['/', '\\']) {
+            bail!("invalid locale domain `{s}`");
+        }
+        Ok(LocaleDomain {
+            locale: locale.to_string(),
+            domain: domain.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for LocaleDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.locale, self.domain)
+    }
+}
+
+/// The configured locales, empty when the export is not internationalized.
+pub fn locales(args: &ExportArguments) -> Result<Vec<Locale>> {
+    for LocaleDomain { locale, .. } in &args.locale_domains {
+        if !args.locales.contains(locale) {
+            bail!("locale domain for `{locale}`, which is not passed as --locale");
+        }
+    }
+    args.locales
+        .iter()
+        .map(|name| {
+            if name.is_empty() || name.contains(['/', '\\', '.']) {
+                bail!("invalid locale `{name}`");
+            }
+            let tree = args
+                .locale_domains
+                .iter()
+                .find(|domain| &domain.locale == name)
+                .map_or_else(|| name.clone(), |domain| domain.domain.clone());
+            Ok(Locale {
+                name: name.clone(),
+                tree,
+            })
+        })
+        .collect()
+}
+
+/// Whether a link refers to an asset like a chunk or an image, which is the
+/// same for all locales. These are exported once into the root of the output
+/// directory and shared by all locale trees.
+pub fn is_shared_asset(route: &str) -> bool {
+    let path = route.split(['?', '#']).next().unwrap_or_default();
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    file_name.contains('.') && !file_name.ends_with(".html") && !file_name.ends_with(".htm")
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64};
+
+/// The version of the manifest format. Manifests of other versions are
+/// ignored, which restarts the export.
+const MANIFEST_VERSION: u32 = 2;
+
+/// The checkpoint of an export: every route which was exported completely,
+/// with the file it was written to and the hash of its content. An interrupted
+/// export skips these routes when it's resumed from the same sources.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportManifest {
+    version: u32,
+    /// The version of the sources the routes were exported from, see
+    /// [git_version]. `None` outside of git checkouts.
+    ///
+    /// [git_version]: crate::build::build_id::git_version
+    pub source_version: Option<String>,
+    /// Whether the export finished without failures. A complete export is
+    /// rendered again by the next run instead of being resumed.
+    pub complete: bool,
+    pub routes: BTreeMap<String, ExportedRoute>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedRoute {
+    /// The output file, relative to the output directory.
+    pub file: String,
+    pub hash: String,
+}
+
+impl ExportManifest {
+    /// The file name of the manifest of `shard`. Every shard has its own
+    /// manifest, so shards can write to the same output directory.
+    pub fn file_name(shard: Option<Shard>) -> String {
+        match shard {
+            None => "export-manifest.json".to_string(),
+            Some(Shard { index, count }) => {
+                format!("export-manifest.shard-{index}-of-{count}.json")
+            }
+        }
+    }
+
+    /// Reads a manifest, returns an empty one when it doesn't exist or was
+    /// written by another version.
+    pub async fn read(path: &Path) -> Result<Self> {
+        let content = match tokio::fs::read(path).await {
+            Ok(content) => content,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Default::default())
+            }
+            Err(error) => return Err(error).with_context(|| format!("reading {}", path.display())),
+        };
+        let manifest: ExportManifest = serde_json::from_slice(&content)
+            .with_context(|| format!("parsing export manifest {}", path.display()))?;
+        Ok(if manifest.version == MANIFEST_VERSION {
+            manifest
+        } else {
+            Default::default()
+        })
+    }
+
+    /// Whether an export of `source_version` resumes this one, i. e. whether
+    /// its routes can be skipped. Only an interrupted export of the same
+    /// sources is resumed, everything else might render differently now.
+    pub fn resumes(&self, source_version: &Option<String>) -> bool {
+        !self.complete && self.source_version == *source_version
+    }
+
+    pub async fn write(&mut self, path: &Path) -> Result<()> {
+        self.version = MANIFEST_VERSION;
+        write_atomic(path, &serde_json::to_vec_pretty(self)?).await
+    }
+}
+
+pub fn content_hash(content: &[u8]) -> String {
+    encode_hex(hash_xxh3_hash64(content))
+}
+
+/// Writes a file via a temporary file and a rename, so an interrupted export
+/// or another shard never sees a partially written file.
+pub async fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("creating directory {}", parent.display()))?;
+    }
+    let temp_file = TempFile::new(path);
+    tokio::fs::write(temp_file.path(), content)
+        .await
+        .with_context(|| format!("writing {}", temp_file.path().display()))?;
+    temp_file.rename().await
+}
+
+/// A temporary file next to a file which is replaced atomically, unique to
+/// the process. It's removed when it's dropped before it was renamed, so an
+/// interrupted write, e. g. of an export cancelled with Ctrl-C, doesn't leave
+/// it behind.
+pub struct TempFile {
+    path: PathBuf,
+    target: PathBuf,
+    renamed: bool,
+}
+
+impl TempFile {
+    pub fn new(target: &Path) -> Self {
+        let mut path = PathBuf::from(target);
+        path.set_file_name(format!(
+            ".{}.{}.tmp",
+            target.file_name().unwrap_or_default().to_string_lossy(),
+            std::process::id()
+        ));
+        TempFile {
+            path,
+            target: target.to_path_buf(),
+            renamed: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Renames the temporary file to the file it replaces.
+    pub async fn rename(mut self) -> Result<()> {
+        tokio::fs::rename(&self.path, &self.target)
+            .await
+            .with_context(|| {
+                format!(
+                    "renaming {} to {}",
+                    self.path.display(),
+                    self.target.display()
+                )
+            })?;
+        self.renamed = true;
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.renamed {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// A part of the routes of an export, to distribute a large export across
+/// processes or machines. Written as `<index>/<count>`, starting at 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl Shard {
+    /// Whether this shard exports `route`. Routes are assigned by their hash,
+    /// so the assignment is stable regardless of the order of the routes.
+    pub fn contains(&self, route: &str) -> bool {
+        hash_xxh3_hash64(route) % self.count as u64 == (self.index - 1) as u64
+    }
+}
+
+impl FromStr for Shard {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((index, count)) = s.split_once('/') else {
+            bail!("shard must be written as <index>/<count>, e. g. 1/4");
+        };
+        let index: u32 = index.parse().context("invalid shard index")?;
+        let count: u32 = count.parse().context("invalid shard count")?;
+        if index == 0 || index > count {
+            bail!("shard index must be between 1 and {count}");
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_shard() {
+        assert_eq!(
+            "2/4".parse::<Shard>().unwrap(),
+            Shard { index: 2, count: 4 }
+        );
+        assert!("0/4".parse::<Shard>().is_err());
+        assert!("5/4".parse::<Shard>().is_err());
+        assert!("2".parse::<Shard>().is_err());
+        assert!("a/4".parse::<Shard>().is_err());
+    }
+
+    #[test]
+    fn every_route_is_in_one_shard() {
+        let shards = (1..=3)
+            .map(|index| Shard { index, count: 3 })
+            .collect::<Vec<_>>();
+        for route in ["/", "/about", "/blog/post-1", "/logo.png", "de:/about"] {
+            let owners = shards.iter().filter(|shard| shard.contains(route)).count();
+            assert_eq!(owners, 1, "{route}");
+        }
+    }
+}
@@ -0,0 +1,568 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use anyhow::{bail, Context, Result};
+use futures::{stream::FuturesUnordered, StreamExt};
+use hyper::{
+    body::Bytes,
+    client::HttpConnector,
+    header::{ACCEPT_LANGUAGE, CONTENT_TYPE},
+    Body, Client, Request, StatusCode,
+};
+use owo_colors::OwoColorize;
+use tokio::sync::Mutex;
+use turbo_tasks::{util::FormatDuration, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbopack_core::issue::IssueSeverity;
+
+use self::{
+    i18n::{is_shared_asset, locales, Locale},
+    manifest::{content_hash, write_atomic, ExportManifest, ExportedRoute},
+    output::{is_compressible, link_atomic, precompressed, Dedupe},
+};
+use crate::{
+    arguments::ExportArguments,
+    build::build_id::git_version,
+    dev::TurbopackDevServerBuilder,
+    shutdown::cancel_on_exit_signal,
+    util::{normalize_dirs, normalize_entries, EntryRequest, NormalizedDirs},
+};
+
+pub mod i18n;
+pub mod manifest;
+pub mod output;
+
+/// Exports routes to static files by rendering them with an in-process dev
+/// server. Same-origin links and assets of exported HTML pages are exported
+/// too.
+///
+/// Completed routes are checkpointed in a manifest in the output directory,
+/// so an interrupted export resumes where it left off, as long as the sources
+/// didn't change since. With `--shard`, only the routes assigned to the shard
+/// are exported, so multiple processes can export to the same output
+/// directory. Every shard still crawls the pages of the other shards, without
+/// writing them, so pages only linked from another shard's pages are found.
+///
+/// With `--locale`, every page is rendered once per locale into a directory
+/// tree of the locale (or of its domain, with `--locale-domain`). Assets are
+/// the same for all locales, so they are exported only once and shared by
+/// all trees.
+///
+/// With `--dedupe`, routes with the same content as an exported route are
+/// linked to its file, or mapped to it in the manifest. With `--precompress`,
+/// gzip and brotli compressed siblings of text files are written too.
+///
+/// Ctrl-C or SIGTERM cancel the export, see [cancel_on_exit_signal].
+pub async fn export(args: &ExportArguments) -> Result<()> {
+    let tt = TurboTasks::new(MemoryBackend::new(
+        args.common
+            .memory_limit
+            .map_or(usize::MAX, |l| l * 1024 * 1024),
+    ));
+
+    cancel_on_exit_signal(&tt, export_with_turbo_tasks(tt.clone(), args)).await
+}
+
+async fn export_with_turbo_tasks(
+    tt: Arc<TurboTasks<MemoryBackend>>,
+    args: &ExportArguments,
+) -> Result<()> {
+    let start = Instant::now();
+    let NormalizedDirs {
+        project_dir,
+        root_dir,
+    } = normalize_dirs(&args.common.dir, &args.common.root)?;
+
+    let mut server = TurbopackDevServerBuilder::new(tt, project_dir.clone(), root_dir)
+        .hostname(IpAddr::from(Ipv4Addr::LOCALHOST))
+        .port(0)
+        .log_detail(args.common.log_detail)
+        .show_all(args.common.show_all)
+        .log_level(
+            args.common
+                .log_level
+                .map_or_else(|| IssueSeverity::Warning, |l| l.0),
+        );
+    for entry in normalize_entries(&args.common.entries) {
+        server = server.entry_request(EntryRequest::Relative(entry));
+    }
+    let server = server.build().await?;
+    let addr = server.addr;
+    tokio::spawn(server.future);
+
+    let out_dir = Path::new(&project_dir).join(&args.out_dir);
+    let locales = locales(args)?;
+    let targets = read_routes(args)?
+        .into_iter()
+        .flat_map(|route| {
+            if locales.is_empty() || is_shared_asset(&route) {
+                vec![ExportTarget {
+                    route,
+                    locale: None,
+                }]
+            } else {
+                locales
+                    .iter()
+                    .map(|locale| ExportTarget {
+                        route: route.clone(),
+                        locale: Some(locale.clone()),
+                    })
+                    .collect()
+            }
+        })
+        .collect::<Vec<_>>();
+    let manifest_path = out_dir.join(ExportManifest::file_name(args.shard));
+    let mut manifest = if args.force {
+        ExportManifest::default()
+    } else {
+        ExportManifest::read(&manifest_path).await?
+    };
+    // Files of a previous export can be shared too
+    let mut contents = HashMap::new();
+    for route in manifest.routes.values() {
+        if !out_dir.join(&route.file).exists() {
+            continue;
+        }
+        contents
+            .entry(route.hash.clone())
+            .or_insert_with(|| route.file.clone());
+    }
+    let source_version = git_version(Path::new(&project_dir)).ok();
+    if !manifest.resumes(&source_version) {
+        manifest.routes.clear();
+    }
+    manifest.source_version = source_version;
+    manifest.complete = false;
+    let exporter = Exporter {
+        client: Client::new(),
+        addr,
+        out_dir,
+        manifest_path,
+        manifest: Mutex::new(manifest),
+        dedupe: args.dedupe,
+        precompress: args.precompress,
+        contents: Mutex::new(contents),
+    };
+
+    let mut seen = targets.iter().cloned().collect::<HashSet<_>>();
+    let mut queue = VecDeque::from(targets);
+    let mut running = FuturesUnordered::new();
+    let mut exported = 0;
+    let mut skipped = 0;
+    let mut failed = vec![];
+    loop {
+        while running.len() < args.concurrency.max(1) {
+            let Some(target) = queue.pop_front() else {
+                break;
+            };
+            let owned = args
+                .shard
+                .map_or(true, |shard| shard.contains(&target.key()));
+            if !owned && !is_page(&target.route) {
+                // Only pages have links to crawl
+                continue;
+            }
+            let exporter = &exporter;
+            running.push(async move {
+                let result = if owned {
+                    exporter.export_route(&target).await
+                } else {
+                    exporter.crawl_route(&target).await
+                };
+                (target, owned, result)
+            });
+        }
+        let Some((target, owned, result)) = running.next().await else {
+            break;
+        };
+        match result {
+            Ok(RouteExport {
+                links,
+                skipped: was_skipped,
+            }) => {
+                if owned && was_skipped {
+                    skipped += 1;
+                } else if owned {
+                    exported += 1;
+                }
+                for link in links {
+                    let link = target.link(&link);
+                    if seen.insert(link.clone()) {
+                        queue.push_back(link);
+                    }
+                }
+            }
+            Err(error) if owned => {
+                println!("{} - failed to export {target}: {error:#}", "error".red());
+                failed.push(target);
+            }
+            Err(error) => {
+                // The shard of the route reports the failure
+                println!(
+                    "{} - failed to crawl {target} of another shard: {error:#}",
+                    "warn ".yellow()
+                );
+            }
+        }
+    }
+
+    println!(
+        "{} - exported {exported} routes, {skipped} resumed from an interrupted export, in {}",
+        "event".purple(),
+        FormatDuration(start.elapsed())
+    );
+    if !failed.is_empty() {
+        bail!(
+            "{} routes failed to export, run the export again to retry them",
+            failed.len()
+        );
+    }
+    let mut manifest = exporter.manifest.lock().await;
+    manifest.complete = true;
+    manifest.write(&exporter.manifest_path).await
+}
+
+/// The routes passed as arguments and listed in the routes file, `/` when
+/// there are none.
+fn read_routes(args: &ExportArguments) -> Result<Vec<String>> {
+    let mut routes = args.routes.clone();
+    if let Some(routes_file) = &args.routes_file {
+        let content = std::fs::read_to_string(routes_file)
+            .with_context(|| format!("reading routes file {}", routes_file.display()))?;
+        routes.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_string),
+        );
+    }
+    if routes.is_empty() {
+        routes.push("/".to_string());
+    }
+    for route in &routes {
+        output_file(route, true)?;
+    }
+    Ok(routes)
+}
+
+/// A route to export, rendered for a locale, or once for all locales when
+/// the export isn't internationalized or the route is a shared asset.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ExportTarget {
+    route: String,
+    locale: Option<Locale>,
+}
+
+impl ExportTarget {
+    /// Identifies the target in the manifest and for sharding.
+    fn key(&self) -> String {
+        match &self.locale {
+            Some(locale) => locale.key(&self.route),
+            None => self.route.clone(),
+        }
+    }
+
+    /// The target of a link on the page of this target. Pages stay in the
+    /// locale of the linking page, assets are shared.
+    fn link(&self, link: &str) -> ExportTarget {
+        match &self.locale {
+            Some(locale) if !is_shared_asset(link) => ExportTarget {
+                route: locale.strip_prefix(link).to_string(),
+                locale: Some(locale.clone()),
+            },
+            _ => ExportTarget {
+                route: link.to_string(),
+                locale: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for ExportTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key())
+    }
+}
+
+struct Exporter {
+    client: Client<HttpConnector>,
+    addr: SocketAddr,
+    out_dir: PathBuf,
+    manifest_path: PathBuf,
+    manifest: Mutex<ExportManifest>,
+    dedupe: Option<Dedupe>,
+    precompress: bool,
+    /// The first file written with a content, by content hash.
+    contents: Mutex<HashMap<String, String>>,
+}
+
+struct RouteExport {
+    /// Same-origin links and assets of an exported HTML page.
+    links: Vec<String>,
+    /// Whether the route was already exported by a previous run.
+    skipped: bool,
+}
+
+impl Exporter {
+    async fn export_route(&self, target: &ExportTarget) -> Result<RouteExport> {
+        let key = target.key();
+        let previous = self.manifest.lock().await.routes.get(&key).cloned();
+        if let Some(previous) = previous {
+            // The output might have been modified or deleted since
+            if let Ok(content) = tokio::fs::read(self.out_dir.join(&previous.file)).await {
+                if content_hash(&content) == previous.hash {
+                    return Ok(RouteExport {
+                        links: html_links(&previous.file, &content),
+                        skipped: true,
+                    });
+                }
+            }
+        }
+
+        let (is_html, content) = self.fetch(target).await?;
+
+        let file = output_file(&target.route, is_html)?;
+        let file = match &target.locale {
+            Some(locale) => format!("{}/{file}", locale.tree),
+            None => file,
+        };
+        let hash = content_hash(&content);
+        let file = self.write_output(file, &hash, &content).await?;
+
+        // Checkpoint after every route, so an interrupted export loses no work
+        let mut manifest = self.manifest.lock().await;
+        manifest.routes.insert(
+            key,
+            ExportedRoute {
+                file: file.clone(),
+                hash,
+            },
+        );
+        manifest.write(&self.manifest_path).await?;
+
+        Ok(RouteExport {
+            links: html_links(&file, &content),
+            skipped: false,
+        })
+    }
+
+    /// Renders a route of another shard, only to find the links of the page.
+    async fn crawl_route(&self, target: &ExportTarget) -> Result<RouteExport> {
+        let (is_html, content) = self.fetch(target).await?;
+        let file = output_file(&target.route, is_html)?;
+        Ok(RouteExport {
+            links: html_links(&file, &content),
+            skipped: false,
+        })
+    }
+
+    /// Requests a route from the dev server. Returns whether the response is
+    /// an HTML page, and its content.
+    async fn fetch(&self, target: &ExportTarget) -> Result<(bool, Bytes)> {
+        let mut request = Request::get(format!("http://{}{}", self.addr, target.route));
+        if let Some(locale) = &target.locale {
+            request = request.header(ACCEPT_LANGUAGE, &locale.name);
+        }
+        let response = self.client.request(request.body(Body::empty())?).await?;
+        if response.status() != StatusCode::OK {
+            bail!("responded with status {}", response.status());
+        }
+        let is_html = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .map_or(false, |content_type| content_type.starts_with("text/html"));
+        let content = hyper::body::to_bytes(response.into_body()).await?;
+        Ok((is_html, content))
+    }
+
+    /// Writes the content of a route to `file`, with its precompressed
+    /// siblings. Returns the file the route is stored in, which is another
+    /// file with the same content when deduplicating with [Dedupe::Map].
+    async fn write_output(&self, file: String, hash: &str, content: &[u8]) -> Result<String> {
+        let existing = match self.dedupe {
+            Some(_) => self
+                .contents
+                .lock()
+                .await
+                .get(hash)
+                .filter(|existing| **existing != file)
+                .cloned(),
+            None => None,
+        };
+        let compress = self.precompress && is_compressible(&file);
+        if !matches!((self.dedupe, &existing), (Some(Dedupe::Map), Some(_))) {
+            // The file is replaced, so it doesn't have the content of a previous
+            // export anymore
+            self.contents
+                .lock()
+                .await
+                .retain(|_, existing| *existing != file);
+        }
+        match (self.dedupe, existing) {
+            (Some(Dedupe::Map), Some(existing)) => return Ok(existing),
+            (Some(dedupe), Some(existing)) => {
+                link_atomic(&self.out_dir, &existing, &file, dedupe).await?;
+                if compress {
+                    let existing_precompressed = ["gz", "br"].iter().all(|extension| {
+                        self.out_dir
+                            .join(format!("{existing}.{extension}"))
+                            .exists()
+                    });
+                    if existing_precompressed {
+                        for extension in ["gz", "br"] {
+                            link_atomic(
+                                &self.out_dir,
+                                &format!("{existing}.{extension}"),
+                                &format!("{file}.{extension}"),
+                                dedupe,
+                            )
+                            .await?;
+                        }
+                    } else {
+                        self.write_precompressed(&file, content).await?;
+                    }
+                }
+            }
+            _ => {
+                write_atomic(&self.out_dir.join(&file), content).await?;
+                if compress {
+                    self.write_precompressed(&file, content).await?;
+                }
+                // Only shared once it's complete, concurrent routes with the same
+                // content are written separately.
+                self.contents
+                    .lock()
+                    .await
+                    .entry(hash.to_string())
+                    .or_insert_with(|| file.clone());
+            }
+        }
+        Ok(file)
+    }
+
+    async fn write_precompressed(&self, file: &str, content: &[u8]) -> Result<()> {
+        for (extension, compressed) in precompressed(content).await? {
+            write_atomic(
+                &self.out_dir.join(format!("{file}.{extension}")),
+                &compressed,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// The output file of a route, relative to the output directory. HTML pages
+/// without a file extension are written as `index.html` of their directory.
+fn output_file(route: &str, is_html: bool) -> Result<String> {
+    let Some(path) = route.strip_prefix('/') else {
+        bail!("route `{route}` must start with /");
+    };
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    if path
+        .split('/')
+        .any(|segment| segment == ".." || segment == ".")
+    {
+        bail!("route `{route}` must not contain . or .. segments");
+    }
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    Ok(if path.is_empty() || path.ends_with('/') {
+        format!("{path}index.html")
+    } else if is_html && !file_name.contains('.') {
+        format!("{path}/index.html")
+    } else {
+        path.to_string()
+    })
+}
+
+/// Whether a route might be an HTML page, i. e. it has no file extension or
+/// an `.html` one.
+fn is_page(route: &str) -> bool {
+    output_file(route, true).map_or(false, |file| file.ends_with(".html"))
+}
+
+/// Extracts the same-origin `href` and `src` links of an HTML page.
+fn html_links(file: &str, content: &[u8]) -> Vec<String> {
+    if !file.ends_with(".html") {
+        return vec![];
+    }
+    let html = String::from_utf8_lossy(content);
+    let mut links = vec![];
+    for attribute in ["href=\"", "src=\""] {
+        for (index, _) in html.match_indices(attribute) {
+            let value = &html[index + attribute.len()..];
+            let Some(end) = value.find('"') else {
+                continue;
+            };
+            let link = &value[..end];
+            let link = link.split(['?', '#']).next().unwrap_or_default();
+            if link.starts_with('/') && !link.starts_with("//") && output_file(link, false).is_ok()
+            {
+                links.push(link.to_string());
+            }
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_file_of_routes() {
+        assert_eq!(output_file("/", true).unwrap(), "index.html");
+        assert_eq!(output_file("/about", true).unwrap(), "about/index.html");
+        assert_eq!(output_file("/blog/", true).unwrap(), "blog/index.html");
+        assert_eq!(output_file("/page.html", true).unwrap(), "page.html");
+        assert_eq!(output_file("/app.js", false).unwrap(), "app.js");
+        assert_eq!(output_file("/data", false).unwrap(), "data");
+        assert_eq!(
+            output_file("/about?tab=team#top", true).unwrap(),
+            "about/index.html"
+        );
+    }
+
+    #[test]
+    fn output_file_rejects_escaping_routes() {
+        assert!(output_file("about", true).is_err());
+        assert!(output_file("/../secret", true).is_err());
+        assert!(output_file("/a/./b", true).is_err());
+    }
+
+    #[test]
+    fn is_page_by_extension() {
+        assert!(is_page("/"));
+        assert!(is_page("/about"));
+        assert!(is_page("/page.html"));
+        assert!(!is_page("/app.js"));
+        assert!(!is_page("/../about"));
+    }
+
+    #[test]
+    fn html_links_are_same_origin() {
+        let html = br#"<a href="/about?x=1">About</a>
+            <a href="https://example.com/">External</a>
+            <a href="//cdn.example.com/lib.js">Protocol relative</a>
+            <img src="/logo.png">
+            <a href="relative">Relative</a>
+            <a href="/../escape">Escape</a>"#;
+        assert_eq!(
+            html_links("index.html", html),
+            vec!["/about".to_string(), "/logo.png".to_string()]
+        );
+    }
+
+    #[test]
+    fn html_links_only_in_html() {
+        assert!(html_links("app.js", br#"x = "<a href="/about">""#).is_empty());
+    }
+}
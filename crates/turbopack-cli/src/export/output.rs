@@ -0,0 +1,110 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder};
+use tokio::io::AsyncReadExt;
+
+use super::manifest::TempFile;
+
+/// How files with the same content as an already exported file are stored,
+/// e. g. the same fallback page rendered for many paths of a catch-all route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dedupe {
+    /// The file is a hard link to the first file with the content.
+    Hardlink,
+    /// The file is a relative symbolic link to the first file with the
+    /// content. Falls back to hard links on platforms without symlinks.
+    Symlink,
+    /// The file isn't written. The route points to the first file with the
+    /// content in the export manifest, which servers use as a shared content
+    /// map.
+    Map,
+}
+
+impl FromStr for Dedupe {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "hardlink" => Dedupe::Hardlink,
+            "symlink" => Dedupe::Symlink,
+            "map" => Dedupe::Map,
+            _ => bail!("dedupe must be one of hardlink, symlink or map"),
+        })
+    }
+}
+
+impl fmt::Display for Dedupe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dedupe::Hardlink => "hardlink",
+            Dedupe::Symlink => "symlink",
+            Dedupe::Map => "map",
+        })
+    }
+}
+
+/// Writes `file` as a link to `target`, both relative to `out_dir`. Like
+/// [super::manifest::write_atomic], the link is created under a temporary
+/// name and renamed, so it replaces an existing file atomically.
+pub async fn link_atomic(out_dir: &Path, target: &str, file: &str, dedupe: Dedupe) -> Result<()> {
+    let path = out_dir.join(file);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("creating directory {}", parent.display()))?;
+    }
+    let temp_file = TempFile::new(&path);
+    let target_path = out_dir.join(target);
+    match dedupe {
+        #[cfg(unix)]
+        Dedupe::Symlink => {
+            // Relative, so the output directory can be moved
+            let mut relative = PathBuf::new();
+            for _ in 0..file.matches('/').count() {
+                relative.push("..");
+            }
+            relative.push(target);
+            tokio::fs::symlink(&relative, temp_file.path()).await
+        }
+        _ => tokio::fs::hard_link(&target_path, temp_file.path()).await,
+    }
+    .with_context(|| {
+        format!(
+            "linking {} to {}",
+            temp_file.path().display(),
+            target_path.display()
+        )
+    })?;
+    temp_file.rename().await
+}
+
+/// Whether precompressed siblings are emitted for `file`. Images, fonts and
+/// other binary formats are already compressed.
+pub fn is_compressible(file: &str) -> bool {
+    let extension = file.rsplit_once('.').map_or("", |(_, extension)| extension);
+    matches!(
+        extension,
+        "html" | "htm" | "js" | "mjs" | "cjs" | "css" | "json" | "map" | "svg" | "xml" | "txt"
+    )
+}
+
+/// The file extensions and contents of the precompressed siblings of a file,
+/// served by static hosts to clients accepting the encoding.
+pub async fn precompressed(content: &[u8]) -> Result<[(&'static str, Vec<u8>); 2]> {
+    let mut gzip = Vec::new();
+    GzipEncoder::new(content)
+        .read_to_end(&mut gzip)
+        .await
+        .context("gzip compression")?;
+    let mut brotli = Vec::new();
+    BrotliEncoder::new(content)
+        .read_to_end(&mut brotli)
+        .await
+        .context("brotli compression")?;
+    Ok([("gz", gzip), ("br", brotli)])
+}
@@ -0,0 +1,161 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{Map, Value as JsonValue};
+use turbo_tasks::Vc;
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fetch::fetch;
+use turbo_tasks_fs::{File, FileJsonContent, FileSystemPath};
+use turbopack_core::{
+    asset::AssetContent,
+    compile_time_info::{
+        CompileTimeDefineValue, CompileTimeDefines, FreeVarReference, FreeVarReferences,
+    },
+    issue::{IssueExt, IssueSeverity},
+    resolve::{options::ImportMapping, ResolveResult},
+    virtual_source::VirtualSource,
+};
+
+use crate::contexts::NodeEnv;
+
+/// The variable through which client code reads feature flags, e. g.
+/// `if (FEATURE_FLAGS.newCheckout) { ... }`. Each flag is inlined as a
+/// constant, so the branches of disabled flags are removed from the chunks.
+const FEATURE_FLAGS_VARIABLE: &str = "FEATURE_FLAGS";
+
+/// A module whose default export contains all flags, for code which looks
+/// them up dynamically, e. g. a debug panel.
+pub const FEATURE_FLAGS_MODULE: &str = "@turbopack/feature-flags";
+
+/// Env variables with this prefix override single flags, e. g.
+/// `TURBOPACK_FLAG_newCheckout=false`. The value is parsed as JSON, or used
+/// as a string when it isn't valid JSON.
+const FLAG_ENV_PREFIX: &str = "TURBOPACK_FLAG_";
+
+/// The env variable with the URL of a JSON snapshot of the flags of a remote
+/// flag service. It is fetched once per session.
+const FLAGS_URL_ENV: &str = "TURBOPACK_FEATURE_FLAGS_URL";
+
+/// Loads the feature flags of the client, as a JSON object. Later sources
+/// override flags of earlier ones:
+///
+/// 1. the remote snapshot at `TURBOPACK_FEATURE_FLAGS_URL`,
+/// 2. `feature-flags.json` in the project directory,
+/// 3. `feature-flags.<NODE_ENV>.json`, e. g. to disable unfinished features in
+///    production,
+/// 4. `TURBOPACK_FLAG_*` env variables.
+///
+/// The files and the env are read through turbo-tasks, so editing them
+/// recompiles the modules reading the changed flags.
+#[turbo_tasks::function]
+pub async fn load_feature_flags(
+    project_path: Vc<FileSystemPath>,
+    node_env: Vc<NodeEnv>,
+    env: Vc<Box<dyn ProcessEnv>>,
+) -> Result<Vc<JsonValue>> {
+    let mut flags = Map::new();
+
+    if let Some(url) = &*env.read(FLAGS_URL_ENV.to_string()).await? {
+        match &*fetch(Vc::cell(url.clone()), Vc::cell(None), Vc::cell(None)).await? {
+            Ok(response) => {
+                let body = response.await?.body.to_string().await?;
+                let snapshot = serde_json::from_str(&body)
+                    .with_context(|| format!("feature flag snapshot of {url} is not valid JSON"))?;
+                insert_flags(&mut flags, snapshot, url)?;
+            }
+            Err(err) => {
+                // Keep compiling with the local flags when the service is unreachable
+                err.to_issue(IssueSeverity::Warning.into(), project_path)
+                    .emit();
+            }
+        }
+    }
+
+    for name in [
+        "feature-flags.json".to_string(),
+        format!("feature-flags.{}.json", node_env.await?),
+    ] {
+        let path = project_path.join(name.clone());
+        match &*path.read_json().await? {
+            FileJsonContent::Content(json) => insert_flags(&mut flags, json.clone(), &name)?,
+            FileJsonContent::Unparseable(e) => {
+                return Err(anyhow!("{name} is not valid JSON: {e}"));
+            }
+            FileJsonContent::NotFound => {}
+        }
+    }
+
+    for (name, value) in env.read_all().await?.iter() {
+        if let Some(flag) = name.strip_prefix(FLAG_ENV_PREFIX) {
+            let value =
+                serde_json::from_str(value).unwrap_or_else(|_| JsonValue::String(value.clone()));
+            flags.insert(flag.to_string(), value);
+        }
+    }
+
+    Ok(Vc::cell(JsonValue::Object(flags)))
+}
+
+fn insert_flags(flags: &mut Map<String, JsonValue>, json: JsonValue, origin: &str) -> Result<()> {
+    let JsonValue::Object(json) = json else {
+        bail!("feature flags of {origin} must be a JSON object");
+    };
+    flags.extend(json);
+    Ok(())
+}
+
+fn define_value(value: &JsonValue) -> CompileTimeDefineValue {
+    match value {
+        JsonValue::Bool(value) => (*value).into(),
+        JsonValue::String(value) => value.as_str().into(),
+        value => value.clone().into(),
+    }
+}
+
+/// Defines each flag, so conditions on flags are evaluated at compile time.
+#[turbo_tasks::function]
+pub async fn feature_flag_defines(flags: Vc<JsonValue>) -> Result<Vc<CompileTimeDefines>> {
+    let flags = flags.await?;
+    let mut defines = CompileTimeDefines(Default::default());
+    for (name, value) in flags.as_object().into_iter().flatten() {
+        defines.0.insert(
+            vec![FEATURE_FLAGS_VARIABLE.to_string(), name.clone()],
+            define_value(value),
+        );
+    }
+    Ok(defines.cell())
+}
+
+/// Replaces reads of flags with their values. `FEATURE_FLAGS` itself is
+/// replaced with the object of all flags, which only [FEATURE_FLAGS_MODULE]
+/// should read.
+#[turbo_tasks::function]
+pub async fn feature_flag_free_var_references(
+    flags: Vc<JsonValue>,
+) -> Result<Vc<FreeVarReferences>> {
+    let flags = flags.await?;
+    let mut references = FreeVarReferences(Default::default());
+    references.0.insert(
+        vec![FEATURE_FLAGS_VARIABLE.to_string()],
+        FreeVarReference::Value(flags.clone_value().into()),
+    );
+    for (name, value) in flags.as_object().into_iter().flatten() {
+        references.0.insert(
+            vec![FEATURE_FLAGS_VARIABLE.to_string(), name.clone()],
+            FreeVarReference::Value(define_value(value)),
+        );
+    }
+    Ok(references.cell())
+}
+
+/// Maps [FEATURE_FLAGS_MODULE] to a virtual module in `project_path`. The
+/// flags are inlined into it like into any other module, so it doesn't
+/// depend on the flags itself.
+#[turbo_tasks::function]
+pub fn feature_flags_import_mapping(project_path: Vc<FileSystemPath>) -> Vc<ImportMapping> {
+    let source = VirtualSource::new(
+        project_path.join("__turbopack_feature_flags__.js".to_string()),
+        AssetContent::file(
+            File::from(format!("export default {FEATURE_FLAGS_VARIABLE};\n")).into(),
+        ),
+    );
+    ImportMapping::Direct(ResolveResult::source(Vc::upcast(source)).cell()).cell()
+}
@@ -0,0 +1,64 @@
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbopack::ecmascript::i18n::{extract_message_catalogs, I18nAdapter, MessageCatalogs};
+use turbopack_core::{
+    asset::AssetContent,
+    module::Modules,
+    resolve::{options::ImportMapping, ResolveResult},
+    virtual_source::VirtualSource,
+};
+
+/// A module exporting `loadMessages(locale)`, which loads the messages of a
+/// locale on demand, e. g. when the user switches the language.
+pub const MESSAGES_MODULE: &str = "@turbopack/messages";
+
+/// The path at which the extracted catalogs are served by the dev server and
+/// emitted by builds, e. g. `_messages/en.json`.
+pub const MESSAGES_DIR: &str = "_messages";
+
+/// The message catalogs in the `messages` directory of the project, e. g.
+/// `messages/en.json`, only containing the messages which are used by the
+/// modules below `entries`.
+#[turbo_tasks::function]
+pub fn project_message_catalogs(
+    project_path: Vc<FileSystemPath>,
+    entries: Vc<Modules>,
+) -> Vc<MessageCatalogs> {
+    extract_message_catalogs(
+        entries,
+        project_path.join("messages".to_string()),
+        I18nAdapter::defaults(),
+    )
+}
+
+/// Maps [MESSAGES_MODULE] to a virtual module in `project_path`. Each catalog
+/// is fetched once and shared by all callers.
+#[turbo_tasks::function]
+pub fn messages_import_mapping(project_path: Vc<FileSystemPath>) -> Vc<ImportMapping> {
+    let source = VirtualSource::new(
+        project_path.join("__turbopack_messages__.js".to_string()),
+        AssetContent::file(
+            File::from(format!(
+                r#"const catalogs = new Map();
+
+export function loadMessages(locale) {{
+  let catalog = catalogs.get(locale);
+  if (catalog == null) {{
+    catalog = fetch(`/{MESSAGES_DIR}/${{encodeURIComponent(locale)}}.json`).then((res) => {{
+      if (!res.ok) {{
+        throw new Error(`Failed to load the messages of ${{locale}}: ${{res.status}}`);
+      }}
+      return res.json();
+    }});
+    catalog.catch(() => catalogs.delete(locale));
+    catalogs.set(locale, catalog);
+  }}
+  return catalog;
+}}
+"#
+            ))
+            .into(),
+        ),
+    );
+    ImportMapping::Direct(ResolveResult::source(Vc::upcast(source)).cell()).cell()
+}
@@ -4,9 +4,16 @@
 
 pub mod arguments;
 pub mod build;
+pub mod compat;
 pub(crate) mod contexts;
+pub mod daemon;
 pub mod dev;
 pub(crate) mod embed_js;
+pub mod environment;
+pub mod export;
+pub(crate) mod feature_flags;
+pub(crate) mod i18n;
+pub(crate) mod shutdown;
 pub(crate) mod util;
 
 pub fn register() {
@@ -1,7 +1,7 @@
 #![feature(future_join)]
 #![feature(min_specialization)]
 
-use std::path::Path;
+use std::{net::TcpListener, path::Path};
 
 use anyhow::{Context, Result};
 use clap::Parser;
@@ -24,6 +24,13 @@ fn main() {
 
     let args = Arguments::parse();
 
+    // Taken before the runtime starts its threads, as this clears the
+    // `LISTEN_*` environment variables
+    let listener = match &args {
+        Arguments::Dev(_) => turbopack_cli::dev::take_inherited_listener(),
+        _ => None,
+    };
+
     let trace = std::env::var("TURBOPACK_TRACING").ok();
 
     let _guard = if let Some(mut trace) = trace {
@@ -73,15 +80,19 @@ fn main() {
         })
         .build()
         .unwrap()
-        .block_on(main_inner(args))
+        .block_on(main_inner(args, listener))
         .unwrap();
 }
 
-async fn main_inner(args: Arguments) -> Result<()> {
+async fn main_inner(args: Arguments, listener: Option<TcpListener>) -> Result<()> {
     register();
 
     match args {
         Arguments::Build(args) => turbopack_cli::build::build(&args).await,
-        Arguments::Dev(args) => turbopack_cli::dev::start_server(&args).await,
+        Arguments::Dev(args) => turbopack_cli::dev::start_server(&args, listener).await,
+        Arguments::Compat(args) => turbopack_cli::compat::report(&args),
+        Arguments::Daemon(args) => turbopack_cli::daemon::start_daemon(&args).await,
+        Arguments::Export(args) => turbopack_cli::export::export(&args).await,
+        Arguments::Affected(args) => turbopack_cli::build::affected::affected(&args).await,
     }
 }
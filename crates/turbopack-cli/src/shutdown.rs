@@ -0,0 +1,51 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::{bail, Result};
+use owo_colors::OwoColorize;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbopack_node::kill_all_processes;
+
+/// How long in-flight tasks get to finish after an exit signal, before the
+/// process exits anyway.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Resolves on Ctrl-C, or SIGTERM on Unix.
+async fn exit_signal() -> Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+/// Runs `future`, e.g. a build or an export, until it completes or the
+/// process receives an exit signal. On an exit signal, the future is dropped,
+/// the Node.js processes are killed and in-flight tasks are stopped, so no
+/// orphaned processes are left behind. Temporary files of interrupted writes
+/// are removed when the future is dropped.
+pub(crate) async fn cancel_on_exit_signal<T>(
+    turbo_tasks: &TurboTasks<MemoryBackend>,
+    future: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        result = future => return result,
+        result = exit_signal() => result?,
+    }
+
+    println!("{} - interrupted, cleaning up", "event".purple());
+    // Killing the processes first fails pending operations, so tasks waiting
+    // for them finish instead of waiting for the timeout
+    kill_all_processes();
+    let _ = tokio::time::timeout(STOP_TIMEOUT, turbo_tasks.stop_and_wait()).await;
+    // Tasks which were still running may have started new processes
+    kill_all_processes();
+    bail!("interrupted")
+}
@@ -0,0 +1,313 @@
+use anyhow::{anyhow, bail, Result};
+use serde_json::{Map, Value as JsonValue};
+use turbo_tasks::{ValueToString, Vc};
+use turbo_tasks_fs::{
+    glob::Glob, DirectoryEntry, FileContent, FileJsonContent, FileSystemPath, ReadGlobResult,
+};
+
+use crate::{
+    asset::AssetContent,
+    resolve::{
+        options::{ImportMapResult, ImportMapping, ImportMappingReplacement},
+        parse::Request,
+        ResolveResult,
+    },
+    virtual_source::VirtualSource,
+};
+
+/// A data loader runs at build time in Rust and exposes its result to modules
+/// as a JSON module, without a round-trip to Node.js.
+///
+/// Loaders must read their inputs through turbo-tasks (e. g. with
+/// [FileSystemPath::read]), so importing modules are invalidated when the
+/// inputs change.
+#[turbo_tasks::value_trait]
+pub trait DataLoader {
+    fn load(self: Vc<Self>) -> Vc<JsonValue>;
+}
+
+/// Loads the content of a file as string.
+#[turbo_tasks::value]
+pub struct FileDataLoader {
+    path: Vc<FileSystemPath>,
+}
+
+#[turbo_tasks::value_impl]
+impl FileDataLoader {
+    #[turbo_tasks::function]
+    pub fn new(path: Vc<FileSystemPath>) -> Vc<Self> {
+        FileDataLoader { path }.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl DataLoader for FileDataLoader {
+    #[turbo_tasks::function]
+    async fn load(&self) -> Result<Vc<JsonValue>> {
+        Ok(Vc::cell(read_string(self.path).await?))
+    }
+}
+
+/// Loads and parses a JSON file.
+#[turbo_tasks::value]
+pub struct JsonDataLoader {
+    path: Vc<FileSystemPath>,
+}
+
+#[turbo_tasks::value_impl]
+impl JsonDataLoader {
+    #[turbo_tasks::function]
+    pub fn new(path: Vc<FileSystemPath>) -> Vc<Self> {
+        JsonDataLoader { path }.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl DataLoader for JsonDataLoader {
+    #[turbo_tasks::function]
+    async fn load(&self) -> Result<Vc<JsonValue>> {
+        Ok(Vc::cell(read_json(self.path).await?))
+    }
+}
+
+/// Loads all files in `directory` matching `glob` into an object keyed by
+/// their relative path. JSON files are parsed, other files are loaded as
+/// strings.
+#[turbo_tasks::value]
+pub struct GlobDataLoader {
+    directory: Vc<FileSystemPath>,
+    glob: Vc<Glob>,
+}
+
+#[turbo_tasks::value_impl]
+impl GlobDataLoader {
+    #[turbo_tasks::function]
+    pub fn new(directory: Vc<FileSystemPath>, glob: Vc<Glob>) -> Vc<Self> {
+        GlobDataLoader { directory, glob }.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl DataLoader for GlobDataLoader {
+    #[turbo_tasks::function]
+    async fn load(&self) -> Result<Vc<JsonValue>> {
+        let mut files = collect_glob_files(self.directory.read_glob(self.glob, false)).await?;
+        // read_glob returns the files in random order
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut map = Map::new();
+        for (name, path) in files {
+            let value = if name.ends_with(".json") {
+                read_json(path).await?
+            } else {
+                read_string(path).await?
+            };
+            map.insert(name, value);
+        }
+        Ok(Vc::cell(JsonValue::Object(map)))
+    }
+}
+
+async fn collect_glob_files(
+    result: Vc<ReadGlobResult>,
+) -> Result<Vec<(String, Vc<FileSystemPath>)>> {
+    let mut files = Vec::new();
+    let mut queue = vec![result];
+    while let Some(result) = queue.pop() {
+        let result = result.await?;
+        for (name, entry) in result.results.iter() {
+            if let DirectoryEntry::File(path) = entry {
+                files.push((name.clone(), *path));
+            }
+        }
+        queue.extend(result.inner.values().copied());
+    }
+    Ok(files)
+}
+
+async fn read_string(path: Vc<FileSystemPath>) -> Result<JsonValue> {
+    match &*path.read().await? {
+        FileContent::Content(file) => Ok(JsonValue::String(file.content().to_str()?.into_owned())),
+        FileContent::NotFound => bail!("data file {} not found", path.to_string().await?),
+    }
+}
+
+async fn read_json(path: Vc<FileSystemPath>) -> Result<JsonValue> {
+    match &*path.read_json().await? {
+        FileJsonContent::Content(json) => Ok(json.clone()),
+        FileJsonContent::Unparseable(e) => Err(anyhow!(
+            "data file {} is not valid JSON: {}",
+            path.to_string().await?,
+            e
+        )),
+        FileJsonContent::NotFound => bail!("data file {} not found", path.to_string().await?),
+    }
+}
+
+/// Resolves a request to the result of a [DataLoader]. Use it with
+/// [ImportMap::insert_exact_alias](crate::resolve::options::ImportMap::insert_exact_alias),
+/// e. g. to make `import posts from "data:posts"` import the posts of a local
+/// CMS export.
+#[turbo_tasks::value(shared)]
+pub struct DataLoaderReplacement {
+    /// Path of the virtual JSON module that contains the data.
+    path: Vc<FileSystemPath>,
+    loader: Vc<Box<dyn DataLoader>>,
+}
+
+#[turbo_tasks::value_impl]
+impl DataLoaderReplacement {
+    /// Creates a replacement whose virtual module is located at
+    /// `<root>/__data__/<name>.json`.
+    #[turbo_tasks::function]
+    pub fn new(
+        root: Vc<FileSystemPath>,
+        name: String,
+        loader: Vc<Box<dyn DataLoader>>,
+    ) -> Vc<Self> {
+        DataLoaderReplacement {
+            path: root.join(format!("__data__/{name}.json")),
+            loader,
+        }
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    pub fn import_mapping(self: Vc<Self>) -> Vc<ImportMapping> {
+        ImportMapping::Dynamic(Vc::upcast(self)).cell()
+    }
+
+    #[turbo_tasks::function]
+    async fn source(&self) -> Result<Vc<VirtualSource>> {
+        let data = self.loader.load().await?;
+        Ok(VirtualSource::new(
+            self.path,
+            AssetContent::file(FileContent::Content(data.to_string().into()).cell()),
+        ))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ImportMappingReplacement for DataLoaderReplacement {
+    #[turbo_tasks::function]
+    fn replace(self: Vc<Self>, _capture: String) -> Vc<ImportMapping> {
+        self.import_mapping()
+    }
+
+    #[turbo_tasks::function]
+    fn result(
+        self: Vc<Self>,
+        _lookup_path: Vc<FileSystemPath>,
+        _request: Vc<Request>,
+    ) -> Vc<ImportMapResult> {
+        ImportMapResult::Result(ResolveResult::source(Vc::upcast(self.source())).cell()).cell()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use anyhow::Result;
+    use serde_json::{json, Value as JsonValue};
+    use turbo_tasks::Vc;
+    use turbo_tasks_fs::{glob::Glob, DiskFileSystem, FileSystem};
+    use turbo_tasks_memory::MemoryBackend;
+
+    use super::{DataLoader, GlobDataLoader, JsonDataLoader};
+
+    /// Loads the data of `loader` from a directory with the `files`.
+    async fn load(
+        name: &str,
+        files: &[(&str, &str)],
+        loader: fn(Vc<Box<dyn FileSystem>>) -> Result<Vc<Box<dyn DataLoader>>>,
+    ) -> Result<JsonValue> {
+        crate::register();
+        let root = std::env::temp_dir().join(format!(
+            "turbopack-data-loader-{name}-{}",
+            std::process::id()
+        ));
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, content)?;
+        }
+
+        let tt = turbo_tasks::TurboTasks::new(MemoryBackend::default());
+        let root_path = root.to_string_lossy().to_string();
+        let result = tt
+            .run_once(async move {
+                let disk_fs = DiskFileSystem::new("test".to_string(), root_path, vec![]);
+                let data = loader(Vc::upcast(disk_fs))?.load().await?;
+                Ok(data.clone_value())
+            })
+            .await;
+        fs::remove_dir_all(&root)?;
+        result
+    }
+
+    #[tokio::test]
+    async fn glob_data_is_keyed_by_relative_path() -> Result<()> {
+        let data = load(
+            "glob",
+            &[
+                ("posts/b.json", r#"{ "title": "B" }"#),
+                ("posts/a.md", "# A"),
+                ("posts/2023/c.json", "[1, 2]"),
+                ("other.json", "{}"),
+            ],
+            |fs| {
+                Ok(Vc::upcast(GlobDataLoader::new(
+                    fs.root().join("posts".to_string()),
+                    Glob::new("**/*".to_string())?,
+                )))
+            },
+        )
+        .await?;
+
+        assert_eq!(
+            data,
+            json!({
+                "2023/c.json": [1, 2],
+                "a.md": "# A",
+                "b.json": { "title": "B" },
+            })
+        );
+        // Sorted, so the module doesn't change when the files are read in
+        // another order
+        let keys = data.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["2023/c.json", "a.md", "b.json"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn glob_data_only_contains_matching_files() -> Result<()> {
+        let data = load(
+            "glob-filter",
+            &[("a.json", "1"), ("b.md", "# B"), ("nested/c.json", "3")],
+            |fs| {
+                Ok(Vc::upcast(GlobDataLoader::new(
+                    fs.root(),
+                    Glob::new("*.json".to_string())?,
+                )))
+            },
+        )
+        .await?;
+
+        assert_eq!(data, json!({ "a.json": 1 }));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_json_is_an_error() {
+        let result = load("invalid-json", &[("data.json", "{ invalid")], |fs| {
+            Ok(Vc::upcast(JsonDataLoader::new(
+                fs.root().join("data.json".to_string()),
+            )))
+        })
+        .await;
+
+        let error = format!("{:#}", result.unwrap_err());
+        assert!(error.contains("is not valid JSON"), "{error}");
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{glob::Glob, FileSystemPath};
+
+use crate::output::{OutputAsset, OutputAssets};
+
+/// Post-processes an emitted asset, e. g. to precompress it or to optimize a
+/// WASM binary or an image.
+#[turbo_tasks::value_trait]
+pub trait EmitHook {
+    /// Returns the assets derived from `asset`, which are emitted along with
+    /// it, e. g. `chunk.js.br` next to `chunk.js`.
+    fn process(self: Vc<Self>, asset: Vc<Box<dyn OutputAsset>>) -> Vc<OutputAssets>;
+}
+
+/// The emit hooks of a build, each with a filter of the assets it processes.
+/// The filter is matched against the path of an asset relative to the output
+/// root, e. g. `**/*.wasm`.
+#[turbo_tasks::value(transparent)]
+pub struct EmitHooks(Vec<(Vc<Glob>, Vc<Box<dyn EmitHook>>)>);
+
+#[turbo_tasks::value_impl]
+impl EmitHooks {
+    #[turbo_tasks::function]
+    pub fn empty() -> Vc<Self> {
+        Vc::cell(Vec::new())
+    }
+}
+
+/// Returns the assets derived from `assets` by the matching `hooks`. Assets
+/// outside of `output_root` are not processed.
+///
+/// Each asset is processed in a task of its own, so only the assets which
+/// changed are processed again.
+#[turbo_tasks::function]
+pub async fn derived_assets(
+    assets: Vc<OutputAssets>,
+    output_root: Vc<FileSystemPath>,
+    hooks: Vc<EmitHooks>,
+) -> Result<Vc<OutputAssets>> {
+    let hooks = hooks.await?;
+    if hooks.is_empty() {
+        return Ok(OutputAssets::empty());
+    }
+    let output_root = output_root.await?;
+    let mut derived = Vec::new();
+    for &asset in assets.await?.iter() {
+        let path = asset.ident().path().await?;
+        let Some(relative_path) = output_root.get_path_to(&path) else {
+            continue;
+        };
+        for &(filter, hook) in hooks.iter() {
+            if filter.await?.execute(relative_path) {
+                derived.extend(hook.process(asset).await?.iter().copied());
+            }
+        }
+    }
+    Ok(Vc::cell(derived))
+}
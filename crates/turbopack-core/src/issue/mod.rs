@@ -1,5 +1,6 @@
 pub mod analyze;
 pub mod code_gen;
+pub mod panic;
 pub mod resolve;
 pub mod unsupported_module;
 
@@ -0,0 +1,54 @@
+use turbo_tasks::Vc;
+use turbo_tasks_fs::FileSystemPath;
+
+use super::{Issue, IssueSeverity, IssueStage, OptionStyledString, StyledString};
+use crate::server_fs::ServerFileSystem;
+
+/// A task panicked. The panic is contained to the task and the tasks
+/// depending on it, see `TurboTasks::set_task_panic_hook`.
+#[turbo_tasks::value(shared)]
+pub struct PanicIssue {
+    /// The description of the panicked task.
+    pub task: String,
+    pub message: String,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for PanicIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Fatal.into()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Other("panic".to_string()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        ServerFileSystem::new().root()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(format!("Panic in {}", self.task)).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(StyledString::Text(self.message.clone()).cell()))
+    }
+
+    #[turbo_tasks::function]
+    fn detail(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(
+                "This is a bug. The rest of the application keeps working, the failed part is \
+                 retried after the next file change."
+                    .to_string(),
+            )
+            .cell(),
+        ))
+    }
+}
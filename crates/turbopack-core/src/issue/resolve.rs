@@ -107,6 +107,145 @@ impl Issue for ResolvingIssue {
     // TODO add source link
 }
 
+/// A relative request whose names differ in case from the names on disk,
+/// e. g. `./Button` for `button.tsx`. It resolves on case-insensitive file
+/// systems like the default ones of macOS and Windows, but not on Linux.
+#[turbo_tasks::value(shared)]
+pub struct CaseMismatchIssue {
+    pub severity: Vc<IssueSeverity>,
+    pub file_path: Vc<FileSystemPath>,
+    pub request: Vc<Request>,
+    /// The request with the case of the names on disk.
+    pub corrected_request: String,
+    /// Whether the request was resolved, i. e. the file system ignores case.
+    pub resolved: bool,
+    pub source: Option<Vc<IssueSource>>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for CaseMismatchIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        self.severity
+    }
+
+    #[turbo_tasks::function]
+    async fn title(&self) -> Result<Vc<StyledString>> {
+        let request = self.request.to_string().await?.clone_value();
+        Ok(StyledString::Line(if self.resolved {
+            vec![
+                StyledString::Strong("Case mismatch".to_string()),
+                StyledString::Text(": '".to_string()),
+                StyledString::Code(request),
+                StyledString::Text("' differs in case from the file on disk".to_string()),
+            ]
+        } else {
+            vec![
+                StyledString::Strong("Module not found".to_string()),
+                StyledString::Text(": Can't resolve '".to_string()),
+                StyledString::Code(request),
+                StyledString::Text("', the case differs from the file on disk".to_string()),
+            ]
+        })
+        .cell())
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Resolve.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Stack(vec![
+                StyledString::Line(vec![
+                    StyledString::Text("Did you mean '".to_string()),
+                    StyledString::Code(self.corrected_request.clone()),
+                    StyledString::Text("'?".to_string()),
+                ]),
+                StyledString::Text(
+                    "Imports are case-sensitive, like file systems on Linux, even when the file \
+                     system ignores case."
+                        .to_string(),
+                ),
+            ])
+            .cell(),
+        ))
+    }
+
+    #[turbo_tasks::function]
+    fn source(&self) -> Vc<OptionIssueSource> {
+        Vc::cell(self.source)
+    }
+}
+
+/// An import of a file outside of the project, reported according to the
+/// [OutOfProjectImportPolicy](crate::resolve::options::OutOfProjectImportPolicy).
+#[turbo_tasks::value(shared)]
+pub struct OutOfProjectImportIssue {
+    pub severity: Vc<IssueSeverity>,
+    /// The path resolving has started in.
+    pub file_path: Vc<FileSystemPath>,
+    pub request: Vc<Request>,
+    pub resolved_path: Vc<FileSystemPath>,
+    pub project_root: Vc<FileSystemPath>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for OutOfProjectImportIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        self.severity
+    }
+
+    #[turbo_tasks::function]
+    async fn title(&self) -> Result<Vc<StyledString>> {
+        Ok(StyledString::Line(vec![
+            StyledString::Text("Import of ".to_string()),
+            StyledString::Code(self.request.to_string().await?.clone_value()),
+            StyledString::Text(" resolves to a file outside of the project".to_string()),
+        ])
+        .cell())
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Resolve.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<Vc<OptionStyledString>> {
+        Ok(Vc::cell(Some(
+            StyledString::Stack(vec![
+                StyledString::Line(vec![
+                    StyledString::Code(self.resolved_path.to_string().await?.clone_value()),
+                    StyledString::Text(" is not inside of the project root ".to_string()),
+                    StyledString::Code(self.project_root.to_string().await?.clone_value()),
+                    StyledString::Text(".".to_string()),
+                ]),
+                StyledString::Text(
+                    "Files outside of the project are usually missing when deploying the project. \
+                     Add the directory to the allowed roots of the out of project import policy \
+                     when it is deployed with the project."
+                        .to_string(),
+                ),
+            ])
+            .cell(),
+        )))
+    }
+}
+
 async fn lookup_import_map(
     import_map: Vc<ImportMap>,
     file_path: Vc<FileSystemPath>,
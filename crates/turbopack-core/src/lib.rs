@@ -14,14 +14,18 @@ pub mod code_builder;
 pub mod compile_time_info;
 pub mod condition;
 pub mod context;
+pub mod data_loader;
 pub mod diagnostics;
+pub mod emit_hook;
 pub mod environment;
 pub mod error;
 pub mod file_source;
 pub mod ident;
 pub mod introspect;
 pub mod issue;
+pub mod lossy_utf8;
 pub mod module;
+pub mod module_metadata;
 pub mod output;
 pub mod package_json;
 pub mod proxied_asset;
@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+
+use anyhow::Result;
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{File, FileContent};
+
+use crate::{
+    asset::{Asset, AssetContent},
+    ident::AssetIdent,
+    source::Source,
+    source_transform::SourceTransform,
+};
+
+/// Decodes sources which aren't valid UTF-8 lossily, replacing invalid bytes
+/// with U+FFFD. An opt-in for legacy files, e. g. vendored scripts in
+/// Latin-1, which can't be converted. Valid sources are unchanged.
+#[turbo_tasks::value]
+pub struct LossyUtf8Decode;
+
+#[turbo_tasks::value_impl]
+impl LossyUtf8Decode {
+    #[turbo_tasks::function]
+    pub fn new() -> Vc<Self> {
+        LossyUtf8Decode.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl SourceTransform for LossyUtf8Decode {
+    #[turbo_tasks::function]
+    fn transform(&self, source: Vc<Box<dyn Source>>) -> Vc<Box<dyn Source>> {
+        Vc::upcast(LossyUtf8DecodedSource { source }.cell())
+    }
+}
+
+#[turbo_tasks::value]
+struct LossyUtf8DecodedSource {
+    source: Vc<Box<dyn Source>>,
+}
+
+#[turbo_tasks::value_impl]
+impl Source for LossyUtf8DecodedSource {
+    #[turbo_tasks::function]
+    fn ident(&self) -> Vc<AssetIdent> {
+        self.source.ident()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Asset for LossyUtf8DecodedSource {
+    #[turbo_tasks::function]
+    async fn content(&self) -> Result<Vc<AssetContent>> {
+        let content = self.source.content();
+        let AssetContent::File(file) = &*content.await? else {
+            return Ok(content);
+        };
+        let FileContent::Content(file) = &*file.await? else {
+            return Ok(content);
+        };
+        let bytes = file.content().to_bytes()?;
+        Ok(match String::from_utf8_lossy(&bytes) {
+            Cow::Borrowed(_) => content,
+            Cow::Owned(string) => AssetContent::file(File::from(string).into()),
+        })
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{debug::ValueDebugFormat, trace::TraceRawVcs, TryJoinIterExt, Vc};
+
+use crate::{
+    module::{Module, Modules},
+    reference::all_modules_and_affecting_sources,
+};
+
+/// The module uses React hooks which only work on the client.
+pub const USES_CLIENT_HOOKS: &str = "uses-client-hooks";
+/// The module contains or imports CSS.
+pub const CONTAINS_CSS: &str = "contains-css";
+/// The module must not be bundled for the client.
+pub const SERVER_ONLY: &str = "server-only";
+
+/// A value of [ModuleMetadata].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs, ValueDebugFormat)]
+pub enum MetadataValue {
+    /// The key applies to the module, e. g. [SERVER_ONLY].
+    Flag,
+    /// A set of values, e. g. the names of the client hooks used by the
+    /// module. Merging metadata merges the sets.
+    Values(BTreeSet<String>),
+}
+
+/// Metadata attached to a module by [ModuleMetadataAnalyzer]s, keyed by
+/// well-known names like [SERVER_ONLY]. Framework features read it when
+/// chunking or assembling HTML, e. g. to reject server-only modules in client
+/// chunks.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+pub struct ModuleMetadata {
+    entries: BTreeMap<String, MetadataValue>,
+}
+
+#[turbo_tasks::value_impl]
+impl ModuleMetadata {
+    #[turbo_tasks::function]
+    pub fn empty() -> Vc<Self> {
+        ModuleMetadata::default().cell()
+    }
+}
+
+impl ModuleMetadata {
+    /// Sets the flag `key`.
+    pub fn with_flag(mut self, key: impl Into<String>) -> Self {
+        self.merge_entry(key.into(), MetadataValue::Flag);
+        self
+    }
+
+    /// Adds `value` to the values of `key`.
+    pub fn with_value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.merge_entry(
+            key.into(),
+            MetadataValue::Values(BTreeSet::from([value.into()])),
+        );
+        self
+    }
+
+    /// Whether `key` is set, as a flag or with values.
+    pub fn has(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// The values of `key`. Empty for flags and unset keys.
+    pub fn values(&self, key: &str) -> impl Iterator<Item = &str> {
+        let values = match self.entries.get(key) {
+            Some(MetadataValue::Values(values)) => Some(values),
+            _ => None,
+        };
+        values.into_iter().flatten().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &MetadataValue)> {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Merges `other` into this metadata. Values of the same key are
+    /// combined, a key with values takes precedence over a flag of the same
+    /// name.
+    pub fn merge(&mut self, other: &ModuleMetadata) {
+        for (key, value) in &other.entries {
+            self.merge_entry(key.clone(), value.clone());
+        }
+    }
+
+    fn merge_entry(&mut self, key: String, value: MetadataValue) {
+        let Some(existing) = self.entries.get_mut(&key) else {
+            self.entries.insert(key, value);
+            return;
+        };
+        match (existing, value) {
+            (MetadataValue::Values(existing), MetadataValue::Values(values)) => {
+                existing.extend(values);
+            }
+            (existing, MetadataValue::Values(values)) => {
+                *existing = MetadataValue::Values(values);
+            }
+            (_, MetadataValue::Flag) => {}
+        }
+    }
+}
+
+/// Attaches metadata to modules, e. g. by looking at their source code or
+/// their path. Implemented by transforms and analyzers which know facts
+/// about modules that framework features need later.
+#[turbo_tasks::value_trait]
+pub trait ModuleMetadataAnalyzer {
+    /// The metadata of `module` itself, without the modules it references.
+    fn analyze(self: Vc<Self>, module: Vc<Box<dyn Module>>) -> Vc<ModuleMetadata>;
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct ModuleMetadataAnalyzers(Vec<Vc<Box<dyn ModuleMetadataAnalyzer>>>);
+
+#[turbo_tasks::value_impl]
+impl ModuleMetadataAnalyzers {
+    #[turbo_tasks::function]
+    pub fn empty() -> Vc<Self> {
+        Vc::cell(Vec::new())
+    }
+}
+
+/// The metadata all `analyzers` attach to `module` itself.
+#[turbo_tasks::function]
+pub async fn module_metadata(
+    module: Vc<Box<dyn Module>>,
+    analyzers: Vc<ModuleMetadataAnalyzers>,
+) -> Result<Vc<ModuleMetadata>> {
+    let mut metadata = ModuleMetadata::default();
+    for analyzer_metadata in analyzers
+        .await?
+        .iter()
+        .map(|analyzer| analyzer.analyze(module))
+        .try_join()
+        .await?
+    {
+        metadata.merge(&analyzer_metadata);
+    }
+    Ok(metadata.cell())
+}
+
+/// The metadata of `module` and of all modules it references, directly or
+/// transitively, e. g. whether a server-only module is imported somewhere
+/// below a page.
+#[turbo_tasks::function]
+pub async fn aggregated_module_metadata(
+    module: Vc<Box<dyn Module>>,
+    analyzers: Vc<ModuleMetadataAnalyzers>,
+) -> Result<Vc<ModuleMetadata>> {
+    if analyzers.await?.is_empty() {
+        return Ok(ModuleMetadata::empty());
+    }
+    Ok(modules_metadata(
+        all_modules_and_affecting_sources(module),
+        analyzers,
+    ))
+}
+
+/// The merged metadata of `modules` themselves, e. g. of the modules in a
+/// chunk.
+#[turbo_tasks::function]
+pub async fn modules_metadata(
+    modules: Vc<Modules>,
+    analyzers: Vc<ModuleMetadataAnalyzers>,
+) -> Result<Vc<ModuleMetadata>> {
+    let mut metadata = ModuleMetadata::default();
+    for metadata_of_module in modules
+        .await?
+        .iter()
+        .map(|&module| module_metadata(module, analyzers))
+        .try_join()
+        .await?
+    {
+        metadata.merge(&metadata_of_module);
+    }
+    Ok(metadata.cell())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge() {
+        let mut metadata = ModuleMetadata::default()
+            .with_flag(SERVER_ONLY)
+            .with_value(USES_CLIENT_HOOKS, "useState");
+        metadata.merge(
+            &ModuleMetadata::default()
+                .with_flag(CONTAINS_CSS)
+                .with_value(SERVER_ONLY, "db")
+                .with_value(USES_CLIENT_HOOKS, "useEffect")
+                .with_flag(USES_CLIENT_HOOKS),
+        );
+        assert!(metadata.has(CONTAINS_CSS));
+        assert_eq!(metadata.values(SERVER_ONLY).collect::<Vec<_>>(), ["db"]);
+        assert_eq!(
+            metadata.values(USES_CLIENT_HOOKS).collect::<Vec<_>>(),
+            ["useEffect", "useState"]
+        );
+        assert!(!metadata.has("other"));
+    }
+}
@@ -14,13 +14,14 @@ use tracing::{Instrument, Level};
 use turbo_tasks::{trace::TraceRawVcs, TryJoinIterExt, Value, ValueToString, Vc};
 use turbo_tasks_fs::{
     util::{normalize_path, normalize_request},
-    FileSystemEntryType, FileSystemPath, RealPathResult,
+    DirectoryContent, FileSystemEntryType, FileSystemPath, RealPathResult,
 };
 
 use self::{
     options::{
-        resolve_modules_options, ConditionValue, ImportMapResult, ResolveInPackage,
-        ResolveIntoPackage, ResolveModules, ResolveModulesOptions, ResolveOptions,
+        resolve_modules_options, ConditionValue, ImportMapResult, OutOfProjectImportAction,
+        OutOfProjectImportPolicy, ResolveInPackage, ResolveIntoPackage, ResolveModules,
+        ResolveModulesOptions, ResolveOptions,
     },
     origin::{ResolveOrigin, ResolveOriginExt},
     parse::Request,
@@ -30,7 +31,10 @@ use self::{
 use crate::{
     context::AssetContext,
     file_source::FileSource,
-    issue::{resolve::ResolvingIssue, IssueExt, IssueSource},
+    issue::{
+        resolve::{CaseMismatchIssue, OutOfProjectImportIssue, ResolvingIssue},
+        IssueExt, IssueSource,
+    },
     module::{Module, Modules, OptionModule},
     output::{OutputAsset, OutputAssets},
     package_json::{read_package_json, PackageJsonIssue},
@@ -2110,6 +2114,13 @@ async fn resolve_into_package(
     options: Vc<ResolveOptions>,
 ) -> Result<Vc<ResolveResult>> {
     let path = path.into_value();
+    let options = if options.await?.workspace_source.is_some()
+        && *is_workspace_package(package_path).await?
+    {
+        options.with_workspace_source().resolve().await?
+    } else {
+        options
+    };
     let options_value = options.await?;
     let mut results = Vec::new();
 
@@ -2179,6 +2190,19 @@ async fn resolve_into_package(
     Ok(merge_results(results))
 }
 
+/// Whether the package at `package_path` is linked into `node_modules` from
+/// outside of it, like the packages of a workspace are.
+#[turbo_tasks::function]
+async fn is_workspace_package(package_path: Vc<FileSystemPath>) -> Result<Vc<bool>> {
+    let real_path = package_path.realpath().await?;
+    Ok(Vc::cell(
+        !real_path
+            .path
+            .split('/')
+            .any(|segment| segment == "node_modules"),
+    ))
+}
+
 #[tracing::instrument(level = Level::TRACE, skip_all)]
 async fn resolve_import_map_result(
     result: &ImportMapResult,
@@ -2259,6 +2283,14 @@ async fn resolved(
     let RealPathResult { path, symlinks } = &*fs_path.realpath_with_links().await?;
 
     let path_ref = &*path.await?;
+    if let Some(policy) = &options_value.out_of_project_imports {
+        if !apply_out_of_project_import_policy(policy, *path, original_context, original_request)
+            .await?
+        {
+            return Ok(ResolveResult::unresolveable().into());
+        }
+    }
+
     // Check alias field for path aliases first
     if let Some(result) = apply_in_package(
         path.parent().resolve().await?,
@@ -2305,6 +2337,56 @@ async fn resolved(
     .into())
 }
 
+/// Reports an import of `path` when it's outside of the project and not
+/// allowed by the policy. `path` is the real path, so packages linked into
+/// `node_modules`, e. g. of a monorepo, are checked where they are. Returns
+/// `false` when the import is denied.
+async fn apply_out_of_project_import_policy(
+    policy: &OutOfProjectImportPolicy,
+    path: Vc<FileSystemPath>,
+    original_context: Vc<FileSystemPath>,
+    original_request: Vc<Request>,
+) -> Result<bool> {
+    let severity = match policy.action {
+        OutOfProjectImportAction::Allow => return Ok(true),
+        OutOfProjectImportAction::Warn => IssueSeverity::Warning,
+        OutOfProjectImportAction::Deny => IssueSeverity::Error,
+    };
+    let path_value = path.await?;
+    let project_root = policy.project_root.await?;
+    // Files of other file systems, e. g. embedded runtime code, are not
+    // deployed from the project directory anyway.
+    if path_value.fs.resolve().await? != project_root.fs.resolve().await?
+        || path_value.is_inside_or_equal_ref(&project_root)
+    {
+        return Ok(true);
+    }
+    // Installed packages are deployed with the dependencies, even when they are
+    // hoisted to the root `node_modules` of a monorepo or in the store of pnpm
+    if path_value
+        .path
+        .split('/')
+        .any(|segment| segment == "node_modules")
+    {
+        return Ok(true);
+    }
+    for &root in &policy.allowed_roots {
+        if path_value.is_inside_or_equal_ref(&*root.await?) {
+            return Ok(true);
+        }
+    }
+    OutOfProjectImportIssue {
+        severity: severity.cell(),
+        file_path: original_context,
+        request: original_request,
+        resolved_path: path,
+        project_root: policy.project_root,
+    }
+    .cell()
+    .emit();
+    Ok(policy.action != OutOfProjectImportAction::Deny)
+}
+
 async fn handle_exports_imports_field(
     package_path: Vc<FileSystemPath>,
     package_json_path: Vc<FileSystemPath>,
@@ -2407,6 +2489,12 @@ async fn resolve_package_internal_with_imports_field(
     .await
 }
 
+/// Whether the file system of the platform ignores the case of names by
+/// default. Resolving looks up names exactly, so on other file systems a
+/// request with a different case never resolves and doesn't need to be checked
+/// for case mismatches.
+const CASE_INSENSITIVE_FILE_SYSTEM: bool = cfg!(any(target_os = "macos", target_os = "windows"));
+
 pub async fn handle_resolve_error(
     result: Vc<ModuleResolveResult>,
     reference_type: Value<ReferenceType>,
@@ -2419,6 +2507,21 @@ pub async fn handle_resolve_error(
     Ok(match result.is_unresolveable().await {
         Ok(unresolveable) => {
             if *unresolveable {
+                if let Some(corrected_request) =
+                    find_case_mismatch(origin_path.parent(), request, resolve_options).await?
+                {
+                    CaseMismatchIssue {
+                        severity,
+                        file_path: origin_path,
+                        request,
+                        corrected_request,
+                        resolved: false,
+                        source,
+                    }
+                    .cell()
+                    .emit();
+                    return Ok(result);
+                }
                 ResolvingIssue {
                     severity,
                     file_path: origin_path,
@@ -2430,6 +2533,22 @@ pub async fn handle_resolve_error(
                 }
                 .cell()
                 .emit();
+            } else if CASE_INSENSITIVE_FILE_SYSTEM {
+                // Resolved on a case-insensitive file system, but it would fail on others
+                if let Some(corrected_request) =
+                    find_case_mismatch(origin_path.parent(), request, resolve_options).await?
+                {
+                    CaseMismatchIssue {
+                        severity: IssueSeverity::Warning.cell(),
+                        file_path: origin_path,
+                        request,
+                        corrected_request,
+                        resolved: true,
+                        source,
+                    }
+                    .cell()
+                    .emit();
+                }
             }
             result
         }
@@ -2450,6 +2569,83 @@ pub async fn handle_resolve_error(
     })
 }
 
+/// Compares the segments of a relative request with the names of the files
+/// and directories on disk, e. g. `button.tsx` for `./Button`. Such imports
+/// work on case-insensitive file systems, but break on others. Returns the
+/// request with the case of the names on disk when they differ.
+async fn find_case_mismatch(
+    lookup_dir: Vc<FileSystemPath>,
+    request: Vc<Request>,
+    resolve_options: Vc<ResolveOptions>,
+) -> Result<Option<String>> {
+    let Request::Relative {
+        path: Pattern::Constant(path),
+        ..
+    } = &*request.await?
+    else {
+        return Ok(None);
+    };
+    let extensions = &resolve_options.await?.extensions;
+    let segments = path.split('/').collect::<Vec<_>>();
+    let mut dir = lookup_dir;
+    let mut corrected = Vec::with_capacity(segments.len());
+    let mut mismatch = false;
+    for (index, &segment) in segments.iter().enumerate() {
+        match segment {
+            "" | "." => {
+                corrected.push(segment.to_string());
+                continue;
+            }
+            ".." => {
+                dir = dir.parent();
+                corrected.push(segment.to_string());
+                continue;
+            }
+            _ => {}
+        }
+        let DirectoryContent::Entries(entries) = &*dir.read_dir().await? else {
+            return Ok(None);
+        };
+        let is_last = index == segments.len() - 1;
+        let name = if entries.get(segment).is_some()
+            || (is_last
+                && extensions
+                    .iter()
+                    .any(|ext| entries.get(&format!("{segment}{ext}")).is_some()))
+        {
+            segment.to_string()
+        } else if let Some((name, _)) = entries
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(segment))
+        {
+            mismatch = true;
+            name.clone()
+        } else if let Some(stem) = is_last
+            .then(|| {
+                entries.iter().find_map(|(name, _)| {
+                    let stem = name.get(..segment.len())?;
+                    (stem.eq_ignore_ascii_case(segment)
+                        && extensions.iter().any(|ext| &name[segment.len()..] == ext))
+                    .then_some(stem)
+                })
+            })
+            .flatten()
+        {
+            // The request omits the extension
+            if stem == segment {
+                return Ok(None);
+            }
+            mismatch = true;
+            stem.to_string()
+        } else {
+            return Ok(None);
+        };
+        dir = dir.join(name.clone());
+        corrected.push(name);
+    }
+    Ok(mismatch.then(|| corrected.join("/")))
+}
+
 // TODO this should become a TaskInput instead of a Vc
 /// ModulePart represents a part of a module.
 ///
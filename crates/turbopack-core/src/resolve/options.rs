@@ -84,6 +84,53 @@ pub enum ResolveInPackage {
     },
 }
 
+/// The build of a dual package, a package which exports both an ES module
+/// and a CommonJS build through the `import` and `require` conditions.
+#[derive(TraceRawVcs, Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum DualPackageVariant {
+    /// The build for the `import` condition.
+    Esm,
+    /// The build for the `require` condition.
+    Cjs,
+}
+
+/// Resolves workspace packages, packages which are linked into
+/// `node_modules` from outside of it, to their source instead of their build
+/// output, so changes to them apply without building the package.
+#[derive(TraceRawVcs, Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct WorkspaceSource {
+    /// A condition set for the `exports` field of workspace packages, e. g.
+    /// `source` for `"exports": { "source": "./src/index.ts", ... }`.
+    pub condition: String,
+    /// A field of the package.json which points to the source entry, e. g.
+    /// `source` for `"source": "./src/index.ts"`. It takes precedence over
+    /// the other main fields.
+    pub main_field: String,
+}
+
+/// What happens to imports which resolve to files outside of the project.
+#[derive(TraceRawVcs, Hash, PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum OutOfProjectImportAction {
+    Allow,
+    /// Reports a warning, but resolves the import.
+    Warn,
+    /// Reports an error, and the import is not resolved.
+    Deny,
+}
+
+/// A policy for imports which escape the project root. They work in
+/// development, but break deployments which only include the project
+/// directory. Files in `node_modules` directories are installed packages and
+/// always allowed.
+#[derive(TraceRawVcs, Hash, PartialEq, Eq, Clone, Debug, Serialize, Deserialize)]
+pub struct OutOfProjectImportPolicy {
+    pub project_root: Vc<FileSystemPath>,
+    /// Directories outside of the project which may be imported from anyway,
+    /// e. g. the packages of a monorepo which are deployed with the project.
+    pub allowed_roots: Vec<Vc<FileSystemPath>>,
+    pub action: OutOfProjectImportAction,
+}
+
 #[turbo_tasks::value(shared)]
 #[derive(Clone)]
 pub enum ImportMapping {
@@ -433,6 +480,15 @@ pub struct ResolveOptions {
     pub fallback_import_map: Option<Vc<ImportMap>>,
     pub resolved_map: Option<Vc<ResolvedMap>>,
     pub plugins: Vec<Vc<Box<dyn ResolvePlugin>>>,
+    /// When set, imports and requires both resolve dual packages to this
+    /// variant, so a package is only included once even when it is imported
+    /// and required.
+    pub dedupe_dual_packages: Option<DualPackageVariant>,
+    /// When set, workspace packages are resolved to their source.
+    pub workspace_source: Option<WorkspaceSource>,
+    /// Checks the files requests are resolved to against a policy for files
+    /// outside of the project.
+    pub out_of_project_imports: Option<OutOfProjectImportPolicy>,
     pub placeholder_for_future_extensions: (),
 }
 
@@ -491,6 +547,30 @@ impl ResolveOptions {
         resolve_options.fully_specified = fully_specified;
         Ok(resolve_options.cell())
     }
+
+    /// Returns a new [Vc<ResolveOptions>] which resolves into packages with
+    /// the condition and the main field of [WorkspaceSource]. Unchanged when
+    /// no workspace source is configured.
+    #[turbo_tasks::function]
+    pub async fn with_workspace_source(self: Vc<Self>) -> Result<Vc<Self>> {
+        let resolve_options = self.await?;
+        let Some(workspace_source) = &resolve_options.workspace_source else {
+            return Ok(self);
+        };
+        let mut resolve_options = resolve_options.clone_value();
+        for resolve_into_package in resolve_options.into_package.iter_mut() {
+            if let ResolveIntoPackage::ExportsField { conditions, .. } = resolve_into_package {
+                conditions.insert(workspace_source.condition.clone(), ConditionValue::Set);
+            }
+        }
+        resolve_options.into_package.insert(
+            0,
+            ResolveIntoPackage::MainField {
+                field: workspace_source.main_field.clone(),
+            },
+        );
+        Ok(resolve_options.cell())
+    }
 }
 
 #[turbo_tasks::value(shared)]
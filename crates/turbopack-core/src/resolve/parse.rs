@@ -2,6 +2,7 @@ use anyhow::Result;
 use lazy_static::lazy_static;
 use regex::Regex;
 use turbo_tasks::{TryJoinIterExt, Value, ValueToString, Vc};
+use turbo_tasks_fs::atom::Atom;
 
 use super::pattern::Pattern;
 
@@ -19,7 +20,7 @@ pub enum Request {
         force_in_lookup_dir: bool,
     },
     Module {
-        module: String,
+        module: Atom,
         path: Pattern,
         query: Vc<String>,
     },
@@ -153,7 +154,7 @@ impl Request {
                         let (path, query) = split_off_query(path.as_str().to_string());
 
                         return Request::Module {
-                            module: module.as_str().to_string(),
+                            module: module.as_str().into(),
                             path,
                             query,
                         };
@@ -252,7 +253,7 @@ impl Request {
     #[turbo_tasks::function]
     pub fn module(module: String, path: Value<Pattern>, query: Vc<String>) -> Vc<Self> {
         Self::cell(Request::Module {
-            module,
+            module: module.into(),
             path: path.into_value(),
             query,
         })
@@ -383,7 +384,12 @@ impl Request {
             } => {
                 let mut pat = Pattern::concat([path.clone(), suffix.into()]);
                 pat.normalize();
-                Self::module(module.clone(), Value::new(pat), *query)
+                Self::Module {
+                    module: module.clone(),
+                    path: pat,
+                    query: *query,
+                }
+                .cell()
             }
             Request::ServerRelative { path, query } => {
                 let mut pat = Pattern::concat([path.clone(), suffix.into()]);
@@ -0,0 +1,360 @@
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hyper::{
+    header::{AUTHORIZATION, COOKIE, LOCATION, SET_COOKIE, WWW_AUTHENTICATE},
+    Body, Request, Response, StatusCode,
+};
+
+/// The query parameter granting access with the token, e. g. in a link to a
+/// preview. The server answers with a redirect setting [TOKEN_COOKIE].
+const TOKEN_PARAM: &str = "turbopack_token";
+
+/// The cookie granting access with the token to the requests of the page,
+/// e. g. of its chunks and of HMR.
+const TOKEN_COOKIE: &str = "__turbopack_token";
+
+/// Restricts access to the server, e. g. to a preview exposed over a tunnel.
+/// The health endpoints are always accessible.
+///
+/// Requests are accepted when they have one of the configured credentials:
+/// the token as `authorization: Bearer <token>`, as [TOKEN_PARAM] query
+/// parameter or as [TOKEN_COOKIE] cookie, or the basic auth credentials.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccessControl {
+    /// A shared token.
+    pub token: Option<String>,
+    /// Basic auth credentials, which browsers ask for.
+    pub basic_auth: Option<BasicAuth>,
+    /// Paths accessible without credentials, in addition to the health
+    /// endpoints. A path ending with `/` excludes all paths below it.
+    pub exclude: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    pub user: String,
+    pub password: String,
+}
+
+impl AccessControl {
+    /// Whether any credentials are required.
+    pub fn is_enabled(&self) -> bool {
+        self.token.is_some() || self.basic_auth.is_some()
+    }
+
+    /// The response denying `request`, or redirecting it to set the token
+    /// cookie. `None` when it is accepted.
+    pub(crate) fn response(&self, request: &Request<Body>) -> Option<Result<Response<Body>>> {
+        if !self.is_enabled() || self.is_excluded(request.uri().path()) {
+            return None;
+        }
+        if let Some(token) = &self.token {
+            if bearer_token(request).is_some_and(|bearer| secure_eq(bearer, token))
+                || cookie(request, TOKEN_COOKIE).is_some_and(|cookie| secure_eq(&cookie, token))
+            {
+                return None;
+            }
+            if let Some((param, location)) = token_param(request) {
+                if secure_eq(&param, token) {
+                    return Some(
+                        Response::builder()
+                            .status(StatusCode::TEMPORARY_REDIRECT)
+                            .header(LOCATION, location)
+                            .header(
+                                SET_COOKIE,
+                                format!(
+                                    "{TOKEN_COOKIE}={}; Path=/; HttpOnly; SameSite=Lax",
+                                    urlencoding::encode(token)
+                                ),
+                            )
+                            .header("cache-control", "no-store")
+                            .body(Body::empty())
+                            .map_err(Into::into),
+                    );
+                }
+            }
+        }
+        if let Some(basic_auth) = &self.basic_auth {
+            if basic_credentials(request).is_some_and(|(user, password)| {
+                // Both are compared, so the time doesn't tell which one is wrong
+                secure_eq(&user, &basic_auth.user) & secure_eq(&password, &basic_auth.password)
+            }) {
+                return None;
+            }
+        }
+
+        let mut response = Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header("cache-control", "no-store");
+        if self.basic_auth.is_some() {
+            response = response.header(WWW_AUTHENTICATE, "Basic realm=\"turbopack\"");
+        }
+        Some(
+            response
+                .body(Body::from("Unauthorized"))
+                .map_err(Into::into),
+        )
+    }
+
+    /// Whether `path` is excluded from access control. The path is matched
+    /// decoded, like it is routed, and paths with `.` or `..` segments are
+    /// never excluded, as they could escape an excluded directory.
+    fn is_excluded(&self, path: &str) -> bool {
+        let Ok(path) = urlencoding::decode(path) else {
+            return false;
+        };
+        if path
+            .split(['/', '\\'])
+            .any(|segment| segment == "." || segment == "..")
+        {
+            return false;
+        }
+        self.exclude.iter().any(|exclude| {
+            if exclude.ends_with('/') {
+                path.starts_with(exclude.as_str())
+            } else {
+                path == exclude.as_str()
+            }
+        })
+    }
+}
+
+fn bearer_token(request: &Request<Body>) -> Option<&str> {
+    request
+        .headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+fn basic_credentials(request: &Request<Body>) -> Option<(String, String)> {
+    let encoded = request
+        .headers()
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = String::from_utf8(BASE64.decode(encoded.trim()).ok()?).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.to_string(), password.to_string()))
+}
+
+/// The value of the cookie `name`, URL decoded like it is set.
+fn cookie(request: &Request<Body>, name: &str) -> Option<String> {
+    request
+        .headers()
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .find_map(|cookie| {
+            let (key, value) = cookie.trim().split_once('=')?;
+            (key == name).then_some(value)
+        })
+        .and_then(|value| urlencoding::decode(value).ok())
+        .map(|value| value.into_owned())
+}
+
+/// The value of the [TOKEN_PARAM] query parameter and the URL without it.
+fn token_param(request: &Request<Body>) -> Option<(String, String)> {
+    let query = request.uri().query()?;
+    let mut token = None;
+    let rest = query
+        .split('&')
+        .filter(|param| match param.split_once('=') {
+            Some((TOKEN_PARAM, value)) => {
+                token = urlencoding::decode(value)
+                    .ok()
+                    .map(|value| value.into_owned());
+                false
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+    let path = request.uri().path();
+    let location = if rest.is_empty() {
+        path.to_string()
+    } else {
+        format!("{path}?{}", rest.join("&"))
+    };
+    Some((token?, location))
+}
+
+/// Compares credentials in constant time.
+fn secure_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access_control() -> AccessControl {
+        AccessControl {
+            token: Some("secret".to_string()),
+            basic_auth: Some(BasicAuth {
+                user: "user".to_string(),
+                password: "pass".to_string(),
+            }),
+            exclude: vec!["/public/".to_string(), "/robots.txt".to_string()],
+        }
+    }
+
+    fn request(uri: &str, headers: &[(&str, &str)]) -> Request<Body> {
+        let mut request = Request::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.body(Body::empty()).unwrap()
+    }
+
+    fn status(access_control: &AccessControl, request: &Request<Body>) -> Option<StatusCode> {
+        access_control
+            .response(request)
+            .map(|response| response.unwrap().status())
+    }
+
+    #[test]
+    fn disabled_accepts_everything() {
+        assert_eq!(
+            status(&AccessControl::default(), &request("/page", &[])),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_without_credentials() {
+        let response = access_control()
+            .response(&request("/page", &[]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response.headers().contains_key(WWW_AUTHENTICATE));
+    }
+
+    #[test]
+    fn accepts_bearer_token() {
+        let access_control = access_control();
+        assert_eq!(
+            status(
+                &access_control,
+                &request("/page", &[("authorization", "Bearer secret")])
+            ),
+            None
+        );
+        assert_eq!(
+            status(
+                &access_control,
+                &request("/page", &[("authorization", "Bearer wrong")])
+            ),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn accepts_token_cookie() {
+        let access_control = access_control();
+        assert_eq!(
+            status(
+                &access_control,
+                &request(
+                    "/page",
+                    &[("cookie", "theme=dark; __turbopack_token=secret")]
+                )
+            ),
+            None
+        );
+        assert_eq!(
+            status(
+                &access_control,
+                &request("/page", &[("cookie", "__turbopack_token=wrong")])
+            ),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn redirects_token_param_to_cookie() {
+        let response = access_control()
+            .response(&request("/page?a=1&turbopack_token=secret&b=2", &[]))
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.headers()[LOCATION], "/page?a=1&b=2");
+        assert!(response.headers()[SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .starts_with("__turbopack_token=secret;"));
+
+        assert_eq!(
+            status(
+                &access_control(),
+                &request("/page?turbopack_token=wrong", &[])
+            ),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn accepts_basic_auth() {
+        let access_control = access_control();
+        let valid = format!("Basic {}", BASE64.encode("user:pass"));
+        let invalid = format!("Basic {}", BASE64.encode("user:wrong"));
+        assert_eq!(
+            status(
+                &access_control,
+                &request("/page", &[("authorization", &valid)])
+            ),
+            None
+        );
+        assert_eq!(
+            status(
+                &access_control,
+                &request("/page", &[("authorization", &invalid)])
+            ),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn accepts_excluded_paths() {
+        let access_control = access_control();
+        assert_eq!(
+            status(&access_control, &request("/public/logo.png", &[])),
+            None
+        );
+        assert_eq!(status(&access_control, &request("/robots.txt", &[])), None);
+        assert_eq!(
+            status(&access_control, &request("/robots.txt/other", &[])),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(&access_control, &request("/publicity", &[])),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[test]
+    fn rejects_traversal_out_of_excluded_paths() {
+        let access_control = access_control();
+        for uri in [
+            "/public/../page",
+            "/public/%2e%2e/page",
+            "/public/%2E%2E/page",
+            "/public%2F..%2Fpage",
+            "/public/..%5Cpage",
+            "/public/./page",
+        ] {
+            assert_eq!(
+                status(&access_control, &request(uri, &[])),
+                Some(StatusCode::UNAUTHORIZED),
+                "{uri}"
+            );
+        }
+    }
+}
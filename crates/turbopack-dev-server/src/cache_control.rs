@@ -0,0 +1,73 @@
+use turbo_tasks_fs::rope::Rope;
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64};
+
+/// The `cache-control` header of content that can never change under its url.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// The `cache-control` header of everything else, the dev server contents
+/// might change at any time.
+const MUST_REVALIDATE: &str = "must-revalidate";
+
+/// How HTML responses are cached.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HtmlCacheControl {
+    /// HTML is never cached. This is the right choice during development.
+    #[default]
+    NoStore,
+    /// HTML may be cached by shared caches (e. g. a CDN in front of a preview
+    /// server of a production build) for `s_maxage` seconds, and served stale
+    /// while revalidating for `stale_while_revalidate` seconds.
+    Shared {
+        s_maxage: u32,
+        stale_while_revalidate: Option<u32>,
+    },
+}
+
+impl HtmlCacheControl {
+    fn header_value(&self) -> String {
+        match self {
+            HtmlCacheControl::NoStore => "no-store".to_string(),
+            HtmlCacheControl::Shared {
+                s_maxage,
+                stale_while_revalidate: None,
+            } => format!("public, max-age=0, s-maxage={s_maxage}"),
+            HtmlCacheControl::Shared {
+                s_maxage,
+                stale_while_revalidate: Some(swr),
+            } => format!("public, max-age=0, s-maxage={s_maxage}, stale-while-revalidate={swr}"),
+        }
+    }
+}
+
+/// Returns the `cache-control` header for a response without an explicit one.
+pub(crate) fn cache_control(
+    path: &str,
+    is_html: bool,
+    content: &Rope,
+    html: HtmlCacheControl,
+) -> String {
+    if is_html {
+        html.header_value()
+    } else if is_content_hashed(path, content) {
+        IMMUTABLE.to_string()
+    } else {
+        MUST_REVALIDATE.to_string()
+    }
+}
+
+/// Whether the file name of `path` embeds the hash of `content`, as assets
+/// named by `ChunkingContext::asset_path` do, e. g. `logo.0123abcd.png`.
+///
+/// The hash is verified instead of trusting the file name pattern, so a stale
+/// url can never be cached forever with the wrong content.
+fn is_content_hashed(path: &str, content: &Rope) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let Some(hash) = file_name
+        .split('.')
+        .skip(1)
+        .find(|segment| segment.len() == 8 && segment.bytes().all(|b| b.is_ascii_hexdigit()))
+    else {
+        return false;
+    };
+    encode_hex(hash_xxh3_hash64(content)).starts_with(hash)
+}
@@ -0,0 +1,131 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use anyhow::Result;
+use hyper::{Body, Response};
+use parking_lot::Mutex;
+use serde_json::json;
+use tokio::sync::watch;
+
+type ReadinessCheck = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// The state reported by the `/healthz` and `/readyz` endpoints of a
+/// [crate::DevServer], for load balancers and process supervisors.
+///
+/// `/healthz` responds with 200 while the server accepts requests.
+/// `/readyz` responds with 200 when the server should receive traffic, and
+/// with 503 while it's draining or when a readiness check fails. Both
+/// respond with JSON describing the state of the compiler.
+pub struct ServerHealth {
+    compiling: AtomicBool,
+    errors: AtomicUsize,
+    draining: watch::Sender<bool>,
+    readiness_checks: Mutex<Vec<(String, ReadinessCheck)>>,
+}
+
+impl fmt::Debug for ServerHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerHealth")
+            .field("compiling", &self.compiling)
+            .field("errors", &self.errors)
+            .field("draining", &self.is_draining())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ServerHealth {
+    fn default() -> Self {
+        ServerHealth {
+            compiling: AtomicBool::new(false),
+            errors: AtomicUsize::new(0),
+            draining: watch::channel(false).0,
+            readiness_checks: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl ServerHealth {
+    /// Sets whether the compiler is working on changes.
+    pub fn set_compiling(&self, compiling: bool) {
+        self.compiling.store(compiling, Ordering::Relaxed);
+    }
+
+    /// Sets the number of current issues with at least error severity.
+    pub fn set_errors(&self, errors: usize) {
+        self.errors.store(errors, Ordering::Relaxed);
+    }
+
+    /// Adds a check which fails readiness while it returns an error, e. g.
+    /// while renderer processes fail to start.
+    pub fn add_readiness_check(
+        &self,
+        name: impl Into<String>,
+        check: impl Fn() -> Result<(), String> + Send + Sync + 'static,
+    ) {
+        self.readiness_checks
+            .lock()
+            .push((name.into(), Box::new(check)));
+    }
+
+    /// Starts draining the server: `/readyz` fails, no new connections are
+    /// accepted, and the server future resolves once the pending requests
+    /// are answered. Used when another server took over the port.
+    pub fn drain(&self) {
+        self.draining.send_replace(true);
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+
+    /// Resolves when [ServerHealth::drain] was called.
+    pub(crate) async fn drained(&self) {
+        let mut draining = self.draining.subscribe();
+        while !*draining.borrow_and_update() {
+            if draining.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// The response of the health endpoint at `path`, if it is one.
+    pub fn response(&self, path: &str) -> Option<Result<Response<Body>>> {
+        let (ready, failed_checks) = match path {
+            "/healthz" => (true, Vec::new()),
+            "/readyz" => {
+                let failed_checks = self
+                    .readiness_checks
+                    .lock()
+                    .iter()
+                    .filter_map(|(name, check)| {
+                        check()
+                            .err()
+                            .map(|error| json!({ "name": name, "error": error }))
+                    })
+                    .collect::<Vec<_>>();
+                (
+                    !self.is_draining() && failed_checks.is_empty(),
+                    failed_checks,
+                )
+            }
+            _ => return None,
+        };
+        let body = json!({
+            "ready": ready,
+            "draining": self.is_draining(),
+            "compiling": self.compiling.load(Ordering::Relaxed),
+            "errors": self.errors.load(Ordering::Relaxed),
+            "failedChecks": failed_checks,
+        });
+        Some(
+            Response::builder()
+                .status(if ready { 200 } else { 503 })
+                .header("content-type", "application/json")
+                .header("cache-control", "no-store")
+                .body(Body::from(body.to_string()))
+                .map_err(Into::into),
+        )
+    }
+}
@@ -4,7 +4,7 @@ use anyhow::{anyhow, Result};
 use auto_hash_map::AutoSet;
 use futures::{StreamExt, TryStreamExt};
 use hyper::{
-    header::{HeaderName, CONTENT_ENCODING, CONTENT_LENGTH},
+    header::{HeaderName, CONTENT_ENCODING, CONTENT_LENGTH, ETAG, IF_NONE_MATCH},
     http::HeaderValue,
     Request, Response,
 };
@@ -14,16 +14,21 @@ use tokio_util::io::{ReaderStream, StreamReader};
 use turbo_tasks::{util::SharedError, CollectiblesSource, ReadRef, TransientInstance, Vc};
 use turbo_tasks_bytes::Bytes;
 use turbo_tasks_fs::FileContent;
+use turbo_tasks_hash::encode_hex;
 use turbopack_core::{
     asset::AssetContent,
     issue::{handle_issues, IssueReporter, IssueSeverity},
     version::VersionedContent,
 };
 
-use crate::source::{
-    request::SourceRequest,
-    resolve::{resolve_source_request, ResolveSourceRequestResult},
-    Body, ContentSource, ContentSourceSideEffect, HeaderList, ProxyResult,
+use crate::{
+    cache_control::{cache_control, HtmlCacheControl},
+    security_headers::{apply_security_headers, SecurityHeaders},
+    source::{
+        request::SourceRequest,
+        resolve::{resolve_source_request, ResolveSourceRequestResult},
+        Body, ContentSource, ContentSourceSideEffect, HeaderList, ProxyResult,
+    },
 };
 
 #[turbo_tasks::value(serialization = "none")]
@@ -33,6 +38,8 @@ enum GetFromSourceResult {
         status_code: u16,
         headers: ReadRef<HeaderList>,
         header_overwrites: ReadRef<HeaderList>,
+        /// A strong ETag derived from the content.
+        etag: String,
     },
     HttpProxy(ReadRef<ProxyResult>),
     NotFound,
@@ -54,6 +61,9 @@ async fn get_from_source(
                     status_code: static_content.status_code,
                     headers: static_content.headers.await?,
                     header_overwrites: header_overwrites.await?,
+                    // The hash is cached per content, so it's only computed when the content
+                    // changed
+                    etag: format!("\"{}\"", encode_hex(*file.hash().await?)),
                 }
             } else {
                 GetFromSourceResult::NotFound
@@ -73,11 +83,18 @@ pub async fn process_request_with_content_source(
     source: Vc<Box<dyn ContentSource>>,
     request: Request<hyper::Body>,
     issue_reporter: Vc<Box<dyn IssueReporter>>,
+    html_cache_control: HtmlCacheControl,
+    security_headers: &SecurityHeaders,
 ) -> Result<(
     Response<hyper::Body>,
     AutoSet<Vc<Box<dyn ContentSourceSideEffect>>>,
 )> {
     let original_path = request.uri().path().to_string();
+    let if_none_match = request
+        .headers()
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
     let request = http_request_to_source_request(request).await?;
     let result = get_from_source(source, TransientInstance::new(request));
     let resolved_result = result.resolve_strongly_consistent().await?;
@@ -96,6 +113,7 @@ pub async fn process_request_with_content_source(
             status_code,
             headers,
             header_overwrites,
+            etag,
         } => {
             if let FileContent::Content(file) = &**content {
                 let mut response = Response::builder().status(*status_code);
@@ -159,14 +177,45 @@ pub async fn process_request_with_content_source(
                     )?);
                 }
 
+                let is_html = header_map
+                    .get("content-type")
+                    .and_then(|content_type| content_type.to_str().ok())
+                    .map_or(false, |content_type| content_type.starts_with("text/html"));
                 if !header_map.contains_key("cache-control") {
-                    // The dev server contents might change at any time, we can't cache them.
                     header_map.append(
                         "cache-control",
-                        hyper::header::HeaderValue::try_from("must-revalidate")?,
+                        hyper::header::HeaderValue::try_from(cache_control(
+                            &original_path,
+                            is_html,
+                            file.content(),
+                            html_cache_control,
+                        ))?,
                     );
                 }
 
+                let html = if is_html {
+                    file.content().to_str().ok()
+                } else {
+                    None
+                };
+                apply_security_headers(header_map, security_headers, html.as_deref());
+
+                // An ETag set by the content source takes precedence
+                let etag = header_map
+                    .entry(ETAG)
+                    .or_insert(HeaderValue::try_from(etag.as_str())?)
+                    .to_str()
+                    .unwrap_or_default()
+                    .to_string();
+                if *status_code == 200
+                    && if_none_match
+                        .as_deref()
+                        .map_or(false, |if_none_match| etag_matches(if_none_match, &etag))
+                {
+                    let response = response.status(304).body(hyper::Body::empty())?;
+                    return Ok((response, side_effects));
+                }
+
                 let content = file.content();
                 let response = if should_compress {
                     header_map.insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
@@ -205,6 +254,9 @@ pub async fn process_request_with_content_source(
                     hyper::header::HeaderValue::from_str(value)?,
                 );
             }
+            // The body is streamed, so there's no document to assemble a content
+            // security policy from
+            apply_security_headers(headers, security_headers, None);
 
             return Ok((
                 response.body(hyper::Body::wrap_stream(proxy_result.body.read()))?,
@@ -220,6 +272,16 @@ pub async fn process_request_with_content_source(
     ))
 }
 
+/// Whether an `If-None-Match` header value matches `etag`. Uses the weak
+/// comparison, as required for `If-None-Match`.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match.split(',').any(|candidate| {
+        let candidate = candidate.trim();
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
+}
+
 async fn http_request_to_source_request(request: Request<hyper::Body>) -> Result<SourceRequest> {
     let (parts, body) = request.into_parts();
 
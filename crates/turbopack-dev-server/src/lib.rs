@@ -5,10 +5,16 @@
 #![feature(str_split_remainder)]
 #![feature(arbitrary_self_types)]
 
+mod access_control;
+mod cache_control;
+mod health;
 pub mod html;
 mod http;
 pub mod introspect;
 mod invalidation;
+mod route_limits;
+mod security_headers;
+pub mod server_logs;
 pub mod source;
 pub mod update;
 
@@ -39,6 +45,13 @@ use turbopack_core::{
     issue::{handle_issues, IssueReporter, IssueSeverity},
 };
 
+pub use self::{
+    access_control::{AccessControl, BasicAuth},
+    cache_control::HtmlCacheControl,
+    health::ServerHealth,
+    route_limits::{RouteLimiter, RouteLimits, RouteStats},
+    security_headers::{ContentSecurityPolicy, FrameOptions, SecurityHeaders},
+};
 use self::{source::ContentSource, update::UpdateServer};
 use crate::{
     invalidation::{ServerRequest, ServerRequestSideEffects},
@@ -65,6 +78,16 @@ pub struct DevServerBuilder {
     pub addr: SocketAddr,
     #[turbo_tasks(trace_ignore)]
     server: Builder<AddrIncoming>,
+    #[turbo_tasks(trace_ignore)]
+    html_cache_control: HtmlCacheControl,
+    #[turbo_tasks(trace_ignore)]
+    security_headers: SecurityHeaders,
+    #[turbo_tasks(trace_ignore)]
+    health: Arc<ServerHealth>,
+    #[turbo_tasks(trace_ignore)]
+    route_limiter: Arc<RouteLimiter>,
+    #[turbo_tasks(trace_ignore)]
+    access_control: AccessControl,
 }
 
 #[derive(TraceRawVcs)]
@@ -77,40 +100,109 @@ pub struct DevServer {
 
 impl DevServer {
     pub fn listen(addr: SocketAddr) -> Result<DevServerBuilder, anyhow::Error> {
-        // This is annoying. The hyper::Server doesn't allow us to know which port was
-        // bound (until we build it with a request handler) when using the standard
-        // `server::try_bind` approach. This is important when binding the `0` port,
-        // because the OS will remap that to an actual free port, and we need to know
-        // that port before we build the request handler. So we need to construct a
-        // real TCP listener, see if it bound, and get its bound address.
-        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))
-            .context("unable to create socket")?;
-        // Allow the socket to be reused immediately after closing. This ensures that
-        // the dev server can be restarted on the same address without a buffer time for
-        // the OS to release the socket.
-        // https://docs.microsoft.com/en-us/windows/win32/winsock/using-so-reuseaddr-and-so-exclusiveaddruse
-        #[cfg(not(windows))]
-        let _ = socket.set_reuse_address(true);
-        if matches!(addr, SocketAddr::V6(_)) {
-            // When possible bind to v4 and v6, otherwise ignore the error
-            let _ = socket.set_only_v6(false);
-        }
-        let sock_addr = addr.into();
-        socket
-            .bind(&sock_addr)
-            .context("not able to bind address")?;
-        socket.listen(128).context("not able to listen on socket")?;
+        Self::from_listener(bind(addr, false)?)
+    }
+
+    /// Like [DevServer::listen], but allows other processes to listen on the
+    /// same address with `SO_REUSEPORT`. A new server can start listening
+    /// before the current one is drained with [ServerHealth::drain], so
+    /// restarts don't refuse connections.
+    #[cfg(unix)]
+    pub fn listen_reuse_port(addr: SocketAddr) -> Result<DevServerBuilder, anyhow::Error> {
+        Self::from_listener(bind(addr, true)?)
+    }
 
-        let listener: TcpListener = socket.into();
+    /// Serves on a listening socket, e. g. one handed over by the server
+    /// being replaced or by a process supervisor.
+    pub fn from_listener(listener: TcpListener) -> Result<DevServerBuilder, anyhow::Error> {
         let addr = listener
             .local_addr()
             .context("not able to get bound address")?;
         let server = Server::from_tcp(listener).context("Not able to start server")?;
-        Ok(DevServerBuilder { addr, server })
+        Ok(DevServerBuilder {
+            addr,
+            server,
+            html_cache_control: Default::default(),
+            security_headers: Default::default(),
+            health: Default::default(),
+            route_limiter: Default::default(),
+            access_control: Default::default(),
+        })
     }
 }
 
+fn bind(addr: SocketAddr, reuse_port: bool) -> Result<TcpListener> {
+    // This is annoying. The hyper::Server doesn't allow us to know which port was
+    // bound (until we build it with a request handler) when using the standard
+    // `server::try_bind` approach. This is important when binding the `0` port,
+    // because the OS will remap that to an actual free port, and we need to know
+    // that port before we build the request handler. So we need to construct a
+    // real TCP listener, see if it bound, and get its bound address.
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))
+        .context("unable to create socket")?;
+    // Allow the socket to be reused immediately after closing. This ensures that
+    // the dev server can be restarted on the same address without a buffer time for
+    // the OS to release the socket.
+    // https://docs.microsoft.com/en-us/windows/win32/winsock/using-so-reuseaddr-and-so-exclusiveaddruse
+    #[cfg(not(windows))]
+    let _ = socket.set_reuse_address(true);
+    #[cfg(unix)]
+    if reuse_port {
+        socket
+            .set_reuse_port(true)
+            .context("not able to reuse the port")?;
+    }
+    #[cfg(not(unix))]
+    let _ = reuse_port;
+    if matches!(addr, SocketAddr::V6(_)) {
+        // When possible bind to v4 and v6, otherwise ignore the error
+        let _ = socket.set_only_v6(false);
+    }
+    let sock_addr = addr.into();
+    socket
+        .bind(&sock_addr)
+        .context("not able to bind address")?;
+    socket.listen(128).context("not able to listen on socket")?;
+    Ok(socket.into())
+}
+
 impl DevServerBuilder {
+    /// Sets how HTML responses are cached, [HtmlCacheControl::NoStore] by
+    /// default. Content-hashed assets are always served as immutable.
+    pub fn html_cache_control(mut self, html_cache_control: HtmlCacheControl) -> Self {
+        self.html_cache_control = html_cache_control;
+        self
+    }
+
+    /// Sets the security headers added to responses. By default, MIME type
+    /// sniffing and framing by other origins are disabled and a strict
+    /// referrer policy is set, but no content security policy.
+    pub fn security_headers(mut self, security_headers: SecurityHeaders) -> Self {
+        self.security_headers = security_headers;
+        self
+    }
+
+    /// Sets the state reported by the `/healthz` and `/readyz` endpoints.
+    /// Draining it shuts the server down gracefully.
+    pub fn health(mut self, health: Arc<ServerHealth>) -> Self {
+        self.health = health;
+        self
+    }
+
+    /// Sets the limits of concurrent requests of routes which likely render,
+    /// [RouteLimits::default] by default.
+    pub fn route_limiter(mut self, route_limiter: Arc<RouteLimiter>) -> Self {
+        self.route_limiter = route_limiter;
+        self
+    }
+
+    /// Requires credentials for all requests except the health endpoints,
+    /// see [AccessControl]. No credentials are required by default.
+    pub fn access_control(mut self, access_control: AccessControl) -> Self {
+        self.access_control = access_control;
+        self
+    }
+
     pub fn serve(
         self,
         turbo_tasks: Arc<dyn TurboTasksApi>,
@@ -120,11 +212,21 @@ impl DevServerBuilder {
         let ongoing_side_effects = Arc::new(Mutex::new(VecDeque::<
             Arc<tokio::sync::Mutex<Option<JoinHandle<Result<()>>>>>,
         >::with_capacity(16)));
+        let html_cache_control = self.html_cache_control;
+        let security_headers = Arc::new(self.security_headers);
+        let health = self.health;
+        let shutdown_health = health.clone();
+        let route_limiter = self.route_limiter;
+        let access_control = Arc::new(self.access_control);
         let make_svc = make_service_fn(move |_| {
             let tt = turbo_tasks.clone();
             let source_provider = source_provider.clone();
             let get_issue_reporter = get_issue_reporter.clone();
             let ongoing_side_effects = ongoing_side_effects.clone();
+            let security_headers = security_headers.clone();
+            let health = health.clone();
+            let route_limiter = route_limiter.clone();
+            let access_control = access_control.clone();
             async move {
                 let handler = move |request: Request<hyper::Body>| {
                     let request_span = info_span!(parent: None, "request", name = ?request.uri());
@@ -133,8 +235,19 @@ impl DevServerBuilder {
                     let get_issue_reporter = get_issue_reporter.clone();
                     let ongoing_side_effects = ongoing_side_effects.clone();
                     let source_provider = source_provider.clone();
+                    let security_headers = security_headers.clone();
+                    let health = health.clone();
+                    let route_limiter = route_limiter.clone();
+                    let access_control = access_control.clone();
                     let future = async move {
                         event!(parent: Span::current(), Level::DEBUG, "request start");
+                        // Health checks must not wait for compilation
+                        if let Some(response) = health.response(request.uri().path()) {
+                            return response;
+                        }
+                        if let Some(response) = access_control.response(&request) {
+                            return response;
+                        }
                         // Wait until all ongoing side effects are completed
                         // We only need to wait for the ongoing side effects that were started
                         // before this request. Later added side effects are not relevant for this.
@@ -218,11 +331,30 @@ impl DevServerBuilder {
                                 Some("get source"),
                             )
                             .await?;
+                            // Held until the response is ready
+                            let _route_permit = if route_limits::is_render_request(&request) {
+                                let route =
+                                    route_limits::route_pattern(resolved_source, &path).await?;
+                                match route_limiter.acquire(&route).await {
+                                    Ok(permit) => Some(permit),
+                                    Err(response) => {
+                                        println!(
+                                            "[503] {path} (too many concurrent requests of \
+                                             {route})"
+                                        );
+                                        return Ok(response);
+                                    }
+                                }
+                            } else {
+                                None
+                            };
                             let (response, side_effects) =
                                 http::process_request_with_content_source(
                                     resolved_source,
                                     request,
                                     issue_reporter,
+                                    html_cache_control,
+                                    &security_headers,
                                 )
                                 .await?;
                             let status = response.status().as_u16();
@@ -277,7 +409,10 @@ impl DevServerBuilder {
                 anyhow::Ok(service_fn(handler))
             }
         });
-        let server = self.server.serve(make_svc);
+        let server = self
+            .server
+            .serve(make_svc)
+            .with_graceful_shutdown(async move { shutdown_health.drained().await });
 
         DevServer {
             addr: self.addr,
@@ -0,0 +1,230 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use hyper::{header, Body, Request, Response};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use turbo_tasks::Vc;
+
+use crate::source::ContentSource;
+
+/// The number of routes whose state is kept while they are idle. Beyond that,
+/// routes are forgotten, with their stats, once they have no more requests.
+const MAX_IDLE_ROUTES: usize = 1000;
+
+/// Limits of the concurrent requests of a route which likely render in
+/// Node.js, so a misbehaving client or aggressive prefetching can't occupy
+/// the renderer pool and delay rebuilds. Routes are identified by the pattern
+/// of the route of the content source matching the request, see
+/// [route_pattern], so all requests of a dynamic route share its limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteLimits {
+    /// The maximum number of concurrent requests of a route. Further requests
+    /// wait in a queue.
+    pub max_concurrent: usize,
+    /// The maximum number of requests of a route waiting in the queue.
+    /// Further requests are answered with 503 and `retry-after`.
+    pub max_queued: usize,
+    /// Sent as `retry-after` of rejected requests.
+    pub retry_after: Duration,
+}
+
+impl Default for RouteLimits {
+    fn default() -> Self {
+        RouteLimits {
+            max_concurrent: 4,
+            max_queued: 32,
+            retry_after: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The requests of a route since the server started.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteStats {
+    pub active: usize,
+    pub queued: usize,
+    pub completed: u64,
+    pub rejected: u64,
+}
+
+struct RouteState {
+    semaphore: Arc<Semaphore>,
+    stats: RouteStats,
+}
+
+/// Applies [RouteLimits] to the requests of a [crate::DevServer], and keeps
+/// [RouteStats] of each route.
+pub struct RouteLimiter {
+    limits: RouteLimits,
+    routes: Mutex<HashMap<String, RouteState>>,
+}
+
+impl Default for RouteLimiter {
+    fn default() -> Self {
+        RouteLimiter::new(RouteLimits::default())
+    }
+}
+
+impl std::fmt::Debug for RouteLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RouteLimiter")
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RouteLimiter {
+    pub fn new(limits: RouteLimits) -> Self {
+        RouteLimiter {
+            limits,
+            routes: Default::default(),
+        }
+    }
+
+    /// The stats of all routes which received requests, sorted by route.
+    pub fn stats(&self) -> Vec<(String, RouteStats)> {
+        let mut stats = self
+            .routes
+            .lock()
+            .iter()
+            .map(|(route, state)| (route.clone(), state.stats.clone()))
+            .collect::<Vec<_>>();
+        stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+        stats
+    }
+
+    /// Waits until a request of `route` may be handled. Returns the 503
+    /// response for the request when the queue of the route is full.
+    pub(crate) async fn acquire(
+        self: &Arc<Self>,
+        route: &str,
+    ) -> Result<RoutePermit, Response<Body>> {
+        let semaphore = {
+            let mut routes = self.routes.lock();
+            let state = routes
+                .entry(route.to_string())
+                .or_insert_with(|| RouteState {
+                    semaphore: Arc::new(Semaphore::new(self.limits.max_concurrent.max(1))),
+                    stats: Default::default(),
+                });
+            if state.semaphore.available_permits() == 0
+                && state.stats.queued >= self.limits.max_queued
+            {
+                state.stats.rejected += 1;
+                return Err(self.rejection(route));
+            }
+            state.stats.queued += 1;
+            state.semaphore.clone()
+        };
+        // Leaves the queue when the request is dropped while waiting
+        let queued = Queued {
+            limiter: self,
+            route,
+        };
+        let permit = semaphore
+            .acquire_owned()
+            .await
+            .expect("the semaphore of a route is never closed");
+        // Active before it leaves the queue, so the route is never idle in between
+        self.update(route, |stats| stats.active += 1);
+        drop(queued);
+        Ok(RoutePermit {
+            limiter: self.clone(),
+            route: route.to_string(),
+            _permit: permit,
+        })
+    }
+
+    /// Updates the stats of `route`, and forgets it when it's idle afterwards
+    /// and there are too many routes.
+    fn update(&self, route: &str, update: impl FnOnce(&mut RouteStats)) {
+        let mut routes = self.routes.lock();
+        let Some(state) = routes.get_mut(route) else {
+            return;
+        };
+        update(&mut state.stats);
+        if state.stats.active == 0 && state.stats.queued == 0 && routes.len() > MAX_IDLE_ROUTES {
+            routes.remove(route);
+        }
+    }
+
+    fn rejection(&self, route: &str) -> Response<Body> {
+        let retry_after = self.limits.retry_after.as_secs_f64().ceil() as u64;
+        let mut response = Response::new(Body::from(format!(
+            "Too many concurrent requests of {route}, retry in {retry_after}s"
+        )));
+        *response.status_mut() = hyper::StatusCode::SERVICE_UNAVAILABLE;
+        let headers = response.headers_mut();
+        headers.insert(header::RETRY_AFTER, retry_after.into());
+        headers.insert(header::CACHE_CONTROL, "no-store".parse().unwrap());
+        response
+    }
+}
+
+struct Queued<'a> {
+    limiter: &'a RouteLimiter,
+    route: &'a str,
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        self.limiter.update(self.route, |stats| stats.queued -= 1);
+    }
+}
+
+/// Allows handling a request of a route until it's dropped.
+pub(crate) struct RoutePermit {
+    limiter: Arc<RouteLimiter>,
+    route: String,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Drop for RoutePermit {
+    fn drop(&mut self) {
+        self.limiter.update(&self.route, |stats| {
+            stats.active -= 1;
+            stats.completed += 1;
+        });
+    }
+}
+
+/// The pattern of the route of `source` which `path` (starting with `/`)
+/// matches, e. g. `/blog/[]`, see [RouteTree::route_pattern]. Paths which
+/// match no route share the `[unmatched]` route.
+///
+/// [RouteTree::route_pattern]: crate::source::route_tree::RouteTree::route_pattern
+pub(crate) async fn route_pattern(
+    source: Vc<Box<dyn ContentSource>>,
+    path: &str,
+) -> Result<String> {
+    let path = urlencoding::decode(path.strip_prefix('/').unwrap_or(path))?;
+    let pattern = source
+        .get_routes()
+        .route_pattern(path.into_owned())
+        .strongly_consistent()
+        .await?;
+    Ok(match &*pattern {
+        Some(pattern) if pattern.is_empty() => "/".to_string(),
+        Some(pattern) => pattern.clone(),
+        None => "[unmatched]".to_string(),
+    })
+}
+
+/// Whether a request likely renders a page or an API route, instead of
+/// serving an asset or connecting to HMR: a request of an HTML document, or
+/// of a path without a file extension.
+pub(crate) fn is_render_request(request: &Request<Body>) -> bool {
+    if hyper_tungstenite::is_upgrade_request(request) {
+        return false;
+    }
+    let accepts_html = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map_or(false, |accept| accept.contains("text/html"));
+    let file_name = request.uri().path().rsplit('/').next().unwrap_or_default();
+    accepts_html || !file_name.contains('.')
+}
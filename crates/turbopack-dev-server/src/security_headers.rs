@@ -0,0 +1,212 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hyper::{
+    header::{
+        HeaderName, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY, REFERRER_POLICY,
+        X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+    },
+    http::HeaderValue,
+    HeaderMap,
+};
+use indexmap::IndexSet;
+use sha2::{Digest, Sha256};
+
+/// Security headers added to every response of the server, rendered pages as
+/// well as static assets. Headers set by the content source take precedence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecurityHeaders {
+    /// Sends `x-content-type-options: nosniff`.
+    pub no_sniff: bool,
+    pub frame_options: Option<FrameOptions>,
+    /// The `referrer-policy` header.
+    pub referrer_policy: Option<String>,
+    /// A `content-security-policy` for HTML pages, see
+    /// [ContentSecurityPolicy].
+    pub content_security_policy: Option<ContentSecurityPolicy>,
+}
+
+impl Default for SecurityHeaders {
+    fn default() -> Self {
+        SecurityHeaders {
+            no_sniff: true,
+            frame_options: Some(FrameOptions::SameOrigin),
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+            content_security_policy: None,
+        }
+    }
+}
+
+impl SecurityHeaders {
+    /// No security headers at all.
+    pub fn none() -> Self {
+        SecurityHeaders {
+            no_sniff: false,
+            frame_options: None,
+            referrer_policy: None,
+            content_security_policy: None,
+        }
+    }
+}
+
+/// Whether the page may be embedded in a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOptions {
+    Deny,
+    SameOrigin,
+}
+
+impl FrameOptions {
+    fn header_value(self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "DENY",
+            FrameOptions::SameOrigin => "SAMEORIGIN",
+        }
+    }
+
+    fn frame_ancestors(self) -> &'static str {
+        match self {
+            FrameOptions::Deny => "'none'",
+            FrameOptions::SameOrigin => "'self'",
+        }
+    }
+}
+
+/// A content security policy assembled from the chunks and inline scripts a
+/// page references: scripts and styles are allowed from the same origin and
+/// from the origins of the chunks, inline scripts by their hash. Connections
+/// to the same origin are allowed for HMR.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    /// Sends `content-security-policy-report-only` instead, which only
+    /// reports violations.
+    pub report_only: bool,
+    /// Directives appended to the policy, e. g. `img-src *`. A directive
+    /// replaces the generated directive of the same name.
+    pub directives: Vec<String>,
+}
+
+impl ContentSecurityPolicy {
+    fn header_value(&self, html: &str, frame_options: Option<FrameOptions>) -> String {
+        let mut script_src = IndexSet::from(["'self'".to_string()]);
+        let mut style_src = IndexSet::from(["'self'".to_string(), "'unsafe-inline'".to_string()]);
+        for (tag, attribute, sources) in [
+            ("<script", "src=\"", &mut script_src),
+            ("<link", "href=\"", &mut style_src),
+        ] {
+            for element in elements(html, tag) {
+                if let Some(origin) = attribute_value(element, attribute).and_then(origin) {
+                    sources.insert(origin.to_string());
+                }
+            }
+        }
+        for script in inline_scripts(html) {
+            script_src.insert(format!(
+                "'sha256-{}'",
+                BASE64.encode(Sha256::digest(script.as_bytes()))
+            ));
+        }
+
+        let mut directives = vec![
+            "default-src 'self'".to_string(),
+            format!("script-src {}", join(&script_src)),
+            format!("style-src {}", join(&style_src)),
+            "img-src 'self' data: blob:".to_string(),
+            "font-src 'self' data:".to_string(),
+            "connect-src 'self' ws: wss:".to_string(),
+            "base-uri 'self'".to_string(),
+            "object-src 'none'".to_string(),
+        ];
+        if let Some(frame_options) = frame_options {
+            directives.push(format!(
+                "frame-ancestors {}",
+                frame_options.frame_ancestors()
+            ));
+        }
+        for directive in &self.directives {
+            let name = directive.split_whitespace().next().unwrap_or_default();
+            directives.retain(|generated| generated.split_whitespace().next() != Some(name));
+            directives.push(directive.clone());
+        }
+        directives.join("; ")
+    }
+}
+
+fn join(sources: &IndexSet<String>) -> String {
+    sources
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Adds the configured security headers which aren't already set. `html` is
+/// the content of an HTML page, the content security policy is only added to
+/// these.
+pub(crate) fn apply_security_headers(
+    headers: &mut HeaderMap,
+    security_headers: &SecurityHeaders,
+    html: Option<&str>,
+) {
+    let mut insert = |name: HeaderName, value: &str| {
+        if let Ok(value) = HeaderValue::try_from(value) {
+            headers.entry(name).or_insert(value);
+        }
+    };
+    if security_headers.no_sniff {
+        insert(X_CONTENT_TYPE_OPTIONS, "nosniff");
+    }
+    if let Some(frame_options) = security_headers.frame_options {
+        insert(X_FRAME_OPTIONS, frame_options.header_value());
+    }
+    if let Some(referrer_policy) = &security_headers.referrer_policy {
+        insert(REFERRER_POLICY, referrer_policy);
+    }
+    if let (Some(policy), Some(html)) = (&security_headers.content_security_policy, html) {
+        let name = if policy.report_only {
+            CONTENT_SECURITY_POLICY_REPORT_ONLY
+        } else {
+            CONTENT_SECURITY_POLICY
+        };
+        insert(
+            name,
+            &policy.header_value(html, security_headers.frame_options),
+        );
+    }
+}
+
+/// The opening tags of all `tag` elements.
+fn elements<'a>(html: &'a str, tag: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    html.match_indices(tag).filter_map(move |(index, _)| {
+        let element = &html[index..];
+        let end = element.find('>')?;
+        Some(&element[..end])
+    })
+}
+
+fn attribute_value<'a>(element: &'a str, attribute: &str) -> Option<&'a str> {
+    let start = element.find(attribute)? + attribute.len();
+    let value = &element[start..];
+    Some(&value[..value.find('"')?])
+}
+
+/// The origin of an absolute url, `None` for same-origin urls.
+fn origin(url: &str) -> Option<&str> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let end = rest.find('/').unwrap_or(rest.len());
+    Some(&url[..url.len() - rest.len() + end])
+}
+
+/// The contents of all inline `<script>` elements.
+fn inline_scripts(html: &str) -> impl Iterator<Item = &str> {
+    html.match_indices("<script").filter_map(|(index, _)| {
+        let element = &html[index..];
+        let open_end = element.find('>')?;
+        if element[..open_end].contains("src=") {
+            return None;
+        }
+        let content = &element[open_end + 1..];
+        let content = &content[..content.find("</script>")?];
+        (!content.is_empty()).then_some(content)
+    })
+}
@@ -0,0 +1,28 @@
+use once_cell::sync::Lazy;
+use tokio::sync::broadcast;
+pub use turbopack_ecmascript_hmr_protocol::{ServerLog, ServerLogLevel};
+
+/// The number of logs buffered for a connected browser. A browser which
+/// falls further behind misses the oldest logs.
+const SERVER_LOG_CAPACITY: usize = 256;
+
+static SERVER_LOGS: Lazy<broadcast::Sender<ServerLog>> =
+    Lazy::new(|| broadcast::channel(SERVER_LOG_CAPACITY).0);
+
+/// Sends console output of server-side code to all browsers connected to the
+/// HMR socket. The output is dropped when no browser is connected.
+pub fn publish_server_log(log: ServerLog) {
+    let _ = SERVER_LOGS.send(log);
+}
+
+/// Whether any browser is connected to receive server logs, so output
+/// doesn't need to be prepared for them otherwise.
+pub fn has_server_log_subscribers() -> bool {
+    SERVER_LOGS.receiver_count() > 0
+}
+
+/// Receives the server logs published from now on, e. g. to show them in a
+/// terminal UI grouped by page. The HMR socket forwards them to browsers.
+pub fn subscribe_server_logs() -> broadcast::Receiver<ServerLog> {
+    SERVER_LOGS.subscribe()
+}
@@ -306,6 +306,70 @@ impl RouteTree {
         Ok(Vc::cell(results))
     }
 
+    /// The pattern of the first route [RouteTree::get] returns for `path`,
+    /// with `[]` for dynamic segments and `[...]` for the remainder of
+    /// catch-all, fallback and not found routes, e. g. `/blog/[]`. `None` when
+    /// no route matches.
+    #[turbo_tasks::function]
+    pub async fn route_pattern(self: Vc<Self>, path: String) -> Result<Vc<Option<String>>> {
+        let RouteTree {
+            base,
+            sources,
+            static_segments,
+            dynamic_segments,
+            catch_all_sources,
+            fallback_sources,
+            not_found_sources,
+        } = &*self.await?;
+        let mut pattern = String::new();
+        if path.is_empty() {
+            if !base.is_empty() {
+                return Ok(Vc::cell(None));
+            }
+            if !sources.is_empty() {
+                return Ok(Vc::cell(Some(pattern)));
+            }
+        } else {
+            let mut segments = path.split('/');
+            for base in base.iter() {
+                let Some(segment) = segments.next() else {
+                    return Ok(Vc::cell(None));
+                };
+                match base {
+                    BaseSegment::Static(str) => {
+                        if str != segment {
+                            return Ok(Vc::cell(None));
+                        }
+                        write!(pattern, "/{str}")?;
+                    }
+                    BaseSegment::Dynamic => pattern.push_str("/[]"),
+                }
+            }
+
+            if let Some(segment) = segments.next() {
+                let remainder = segments.remainder().unwrap_or("");
+                if let Some(tree) = static_segments.get(segment) {
+                    if let Some(rest) = &*tree.route_pattern(remainder.to_string()).await? {
+                        return Ok(Vc::cell(Some(format!("{pattern}/{segment}{rest}"))));
+                    }
+                }
+                for tree in dynamic_segments.iter() {
+                    if let Some(rest) = &*tree.route_pattern(remainder.to_string()).await? {
+                        return Ok(Vc::cell(Some(format!("{pattern}/[]{rest}"))));
+                    }
+                }
+            } else if !sources.is_empty() {
+                return Ok(Vc::cell(Some(pattern)));
+            }
+        }
+        Ok(Vc::cell(
+            (!catch_all_sources.is_empty()
+                || !fallback_sources.is_empty()
+                || !not_found_sources.is_empty())
+            .then(|| format!("{pattern}/[...]")),
+        ))
+    }
+
     /// Prepends a base path to all routes.
     #[turbo_tasks::function]
     pub async fn with_prepended_base(
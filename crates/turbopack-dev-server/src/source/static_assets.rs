@@ -42,8 +42,12 @@ impl StaticAssetsContentSource {
 }
 
 // TODO(WEB-1251) It would be better to lazily enumerate the directory
+/// `root` is the directory of the content source, which `dir` is inside of.
 #[turbo_tasks::function]
-async fn get_routes_from_directory(dir: Vc<FileSystemPath>) -> Result<Vc<RouteTree>> {
+async fn get_routes_from_directory(
+    dir: Vc<FileSystemPath>,
+    root: Vc<FileSystemPath>,
+) -> Result<Vc<RouteTree>> {
     let dir = dir.read_dir().await?;
     let DirectoryContent::Entries(entries) = &*dir else {
         return Ok(RouteTree::empty());
@@ -56,11 +60,11 @@ async fn get_routes_from_directory(dir: Vc<FileSystemPath>) -> Result<Vc<RouteTr
                 Some(RouteTree::new_route(
                     vec![BaseSegment::Static(name.clone())],
                     RouteType::Exact,
-                    Vc::upcast(StaticAssetsContentSourceItem::new(*path)),
+                    Vc::upcast(StaticAssetsContentSourceItem::new(*path, root)),
                 ))
             }
             DirectoryEntry::Directory(path) => Some(
-                get_routes_from_directory(*path)
+                get_routes_from_directory(*path, root)
                     .with_prepended_base(vec![BaseSegment::Static(name.clone())]),
             ),
             _ => None,
@@ -75,29 +79,48 @@ impl ContentSource for StaticAssetsContentSource {
     async fn get_routes(&self) -> Result<Vc<RouteTree>> {
         let prefix = self.prefix.await?;
         let prefix = BaseSegment::from_static_pathname(prefix.as_str()).collect::<Vec<_>>();
-        Ok(get_routes_from_directory(self.dir).with_prepended_base(prefix))
+        Ok(get_routes_from_directory(self.dir, self.dir).with_prepended_base(prefix))
     }
 }
 
 #[turbo_tasks::value]
 struct StaticAssetsContentSourceItem {
     path: Vc<FileSystemPath>,
+    root: Vc<FileSystemPath>,
 }
 
 #[turbo_tasks::value_impl]
 impl StaticAssetsContentSourceItem {
     #[turbo_tasks::function]
-    pub fn new(path: Vc<FileSystemPath>) -> Vc<StaticAssetsContentSourceItem> {
-        StaticAssetsContentSourceItem { path }.cell()
+    pub fn new(
+        path: Vc<FileSystemPath>,
+        root: Vc<FileSystemPath>,
+    ) -> Vc<StaticAssetsContentSourceItem> {
+        StaticAssetsContentSourceItem { path, root }.cell()
     }
 }
 
 #[turbo_tasks::value_impl]
 impl GetContentSourceContent for StaticAssetsContentSourceItem {
     #[turbo_tasks::function]
-    fn get(&self, _path: String, _data: Value<ContentSourceData>) -> Vc<ContentSourceContent> {
+    async fn get(
+        &self,
+        _path: String,
+        _data: Value<ContentSourceData>,
+    ) -> Result<Vc<ContentSourceContent>> {
+        // Symlinks must not expose files outside of the directory, e. g. a link
+        // to the `.env` file of the project
+        let real_path = self.path.realpath().await?;
+        if !real_path.is_inside_ref(&*self.root.realpath().await?) {
+            tracing::warn!(
+                target: "turbo_tasks_fs::path_escape",
+                path = real_path.path.as_str(),
+                "static asset links outside of its directory"
+            );
+            return Ok(ContentSourceContent::not_found());
+        }
         let content = Vc::upcast::<Box<dyn Asset>>(FileSource::new(self.path)).content();
-        ContentSourceContent::static_content(content.versioned())
+        Ok(ContentSourceContent::static_content(content.versioned()))
     }
 }
 
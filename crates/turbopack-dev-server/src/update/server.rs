@@ -8,7 +8,8 @@ use futures::{prelude::*, ready, stream::FusedStream, SinkExt};
 use hyper::{upgrade::Upgraded, HeaderMap, Uri};
 use hyper_tungstenite::{tungstenite::Message, HyperWebsocket, WebSocketStream};
 use pin_project_lite::pin_project;
-use tokio::select;
+use serde::Serialize;
+use tokio::{select, sync::broadcast::error::RecvError};
 use tokio_stream::StreamMap;
 use tracing::{instrument, Level};
 use turbo_tasks::{TransientInstance, TurboTasksApi, Vc};
@@ -20,6 +21,7 @@ use turbopack_ecmascript_hmr_protocol::{
 
 use super::stream::UpdateStream;
 use crate::{
+    server_logs::subscribe_server_logs,
     source::{request::SourceRequest, resolve::resolve_source_request, Body},
     update::stream::UpdateStreamItem,
     SourceProvider,
@@ -56,6 +58,7 @@ impl<P: SourceProvider + Clone + Send + Sync> UpdateServer<P> {
         let mut client: UpdateClient = ws.await?.into();
 
         let mut streams = StreamMap::new();
+        let mut server_logs = subscribe_server_logs();
 
         loop {
             select! {
@@ -105,6 +108,16 @@ impl<P: SourceProvider + Clone + Send + Sync> UpdateServer<P> {
                         }
                     }
                 }
+                log = server_logs.recv() => {
+                    match log {
+                        Ok(log) => {
+                            client.send(log).await?;
+                        }
+                        // Logs this client missed are not worth stalling the updates for.
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => unreachable!("server log sender is static"),
+                    }
+                }
                 else => break
             }
         }
@@ -231,7 +244,7 @@ impl FusedStream for UpdateClient {
     }
 }
 
-impl<'a> Sink<ClientUpdateInstruction<'a>> for UpdateClient {
+impl<T: Serialize> Sink<T> for UpdateClient {
     type Error = Error;
 
     fn poll_ready(
@@ -244,10 +257,7 @@ impl<'a> Sink<ClientUpdateInstruction<'a>> for UpdateClient {
             .map(|res| res.context("polling WebSocket ready"))
     }
 
-    fn start_send(
-        self: Pin<&mut Self>,
-        item: ClientUpdateInstruction<'a>,
-    ) -> std::result::Result<(), Self::Error> {
+    fn start_send(self: Pin<&mut Self>, item: T) -> std::result::Result<(), Self::Error> {
         let msg = Message::text(serde_json::to_string(&item)?);
 
         self.project()
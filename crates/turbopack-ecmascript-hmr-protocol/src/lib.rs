@@ -109,6 +109,31 @@ pub enum ClientUpdateInstructionType<'a> {
     Issues,
 }
 
+/// Console output of server-side code rendering `page`, forwarded to the
+/// browsers viewing the page.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename = "serverLog", rename_all = "camelCase")]
+pub struct ServerLog {
+    /// The pathname of the rendered page.
+    pub page: String,
+    /// Identifies the render, to tell apart the output of concurrent renders
+    /// of the same page.
+    pub operation: u64,
+    /// When the output was written, in milliseconds since the UNIX epoch.
+    pub timestamp: u64,
+    pub level: ServerLogLevel,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ServerLogLevel {
+    /// Written to stdout, e. g. by `console.log`.
+    Log,
+    /// Written to stderr, e. g. by `console.error`.
+    Error,
+}
+
 #[derive(Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum ServerError {
@@ -33,10 +33,12 @@ export function connect({
         try {
           if (Array.isArray(msg.data)) {
             for (let i = 0; i < msg.data.length; i++) {
-              handleSocketMessage(msg.data[i] as ServerMessage);
+              handleSocketMessage(
+                msg.data[i] as ServerMessage | ServerLogMessage
+              );
             }
           } else {
-            handleSocketMessage(msg.data as ServerMessage);
+            handleSocketMessage(msg.data as ServerMessage | ServerLogMessage);
           }
           applyAggregatedUpdates();
         } catch (e: unknown) {
@@ -492,7 +494,12 @@ export function setHooks(newHooks: typeof hooks) {
   Object.assign(hooks, newHooks);
 }
 
-function handleSocketMessage(msg: ServerMessage) {
+function handleSocketMessage(msg: ServerMessage | ServerLogMessage) {
+  if (msg.type === "serverLog") {
+    handleServerLog(msg);
+    return;
+  }
+
   sortIssues(msg.issues);
 
   handleIssues(msg);
@@ -515,6 +522,17 @@ function handleSocketMessage(msg: ServerMessage) {
   }
 }
 
+/**
+ * Prints the console output of the server-side render of the current page,
+ * so it doesn't have to be looked up in the terminal.
+ */
+function handleServerLog(msg: ServerLogMessage) {
+  if (msg.page !== location.pathname) return;
+  const log = msg.level === "error" ? console.error : console.log;
+  const time = new Date(msg.timestamp).toLocaleTimeString();
+  log(`%c[server ${time}]`, "color: #888", msg.message);
+}
+
 function finalizeUpdate() {
   hooks.refresh();
   hooks.buildOk();
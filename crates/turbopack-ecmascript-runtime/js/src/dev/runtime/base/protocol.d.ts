@@ -26,6 +26,20 @@ type ServerMessage = {
   | UnknownType
 );
 
+/**
+ * Console output of server-side code rendering `page`.
+ */
+type ServerLogMessage = {
+  type: "serverLog";
+  page: string;
+  /** Identifies the render, concurrent renders of a page have their own. */
+  operation: number;
+  /** When the output was written, in milliseconds since the UNIX epoch. */
+  timestamp: number;
+  level: "log" | "error";
+  message: string;
+};
+
 type UnknownType = {
   type: "future-type-marker-do-not-use-or-you-will-be-fired";
 };
@@ -30,6 +30,11 @@ static ANNOTATION_TRANSITION: Lazy<JsWord> = Lazy::new(|| "transition".into());
 /// Changes the chunking type for the annotated import
 static ANNOTATION_CHUNKING_TYPE: Lazy<JsWord> = Lazy::new(|| "chunking-type".into());
 
+/// Sets an additional resolution condition for the annotated import, e. g.
+/// `import cfg from "lib/config" with { env: "server" }` resolves the
+/// `server` conditions of the `exports` and `imports` fields
+static ANNOTATION_ENV: Lazy<JsWord> = Lazy::new(|| "env".into());
+
 impl ImportAnnotations {
     fn insert(&mut self, key: JsWord, value: Option<JsWord>) {
         self.map.insert(key, value);
@@ -52,6 +57,39 @@ impl ImportAnnotations {
             .get(&ANNOTATION_CHUNKING_TYPE)
             .and_then(|w| w.as_ref().map(|w| &**w))
     }
+
+    /// Returns the content on the env annotation
+    pub fn env(&self) -> Option<&str> {
+        self.map
+            .get(&ANNOTATION_ENV)
+            .and_then(|w| w.as_ref().map(|w| &**w))
+    }
+
+    /// Adds the import attributes of an import which are annotations, so
+    /// `with { env: "server" }` works like `"TURBOPACK { env: server }"`.
+    fn with_import_attributes(mut self, attributes: Option<&ObjectLit>) -> Self {
+        let Some(attributes) = attributes else {
+            return self;
+        };
+        for prop in &attributes.props {
+            let Some(KeyValueProp { key, value }) = prop.as_prop().and_then(|p| p.as_key_value())
+            else {
+                continue;
+            };
+            let key = match key {
+                PropName::Ident(ident) => &ident.sym,
+                PropName::Str(str) => &str.value,
+                _ => continue,
+            };
+            if *key != *ANNOTATION_ENV {
+                continue;
+            }
+            if let Expr::Lit(Lit::Str(value)) = &**value {
+                self.insert(key.clone(), Some(value.value.clone()));
+            }
+        }
+        self
+    }
 }
 
 impl Display for ImportAnnotations {
@@ -257,7 +295,8 @@ impl Visit for Analyzer<'_> {
     }
 
     fn visit_import_decl(&mut self, import: &ImportDecl) {
-        let annotations = take(&mut self.current_annotations);
+        let annotations =
+            take(&mut self.current_annotations).with_import_attributes(import.with.as_deref());
         self.ensure_reference(
             import.span,
             import.src.value.clone(),
@@ -295,7 +334,8 @@ impl Visit for Analyzer<'_> {
     fn visit_export_all(&mut self, export: &ExportAll) {
         self.data.has_exports = true;
 
-        let annotations = take(&mut self.current_annotations);
+        let annotations =
+            take(&mut self.current_annotations).with_import_attributes(export.with.as_deref());
         self.ensure_reference(
             export.span,
             export.src.value.clone(),
@@ -314,7 +354,8 @@ impl Visit for Analyzer<'_> {
     fn visit_named_export(&mut self, export: &NamedExport) {
         self.data.has_exports = true;
         if let Some(ref src) = export.src {
-            let annotations = take(&mut self.current_annotations);
+            let annotations =
+                take(&mut self.current_annotations).with_import_attributes(export.with.as_deref());
 
             self.ensure_reference(
                 export.span,
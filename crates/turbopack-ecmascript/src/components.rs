@@ -0,0 +1,152 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use swc_core::{
+    common::{SourceMap, Span},
+    ecma::{
+        ast::{
+            AssignExpr, ClassDecl, DefaultDecl, Expr, FnDecl, Lit, Pat, SimpleAssignTarget,
+            VarDeclarator,
+        },
+        visit::{Visit, VisitWith},
+    },
+};
+use turbo_tasks::{debug::ValueDebugFormat, trace::TraceRawVcs, Value, Vc};
+use turbopack_core::source::Source;
+
+use crate::{
+    parse::{parse, ParseResult},
+    EcmascriptInputTransforms, EcmascriptModuleAssetType,
+};
+
+/// A declaration in a module which is likely a React component: a function,
+/// class or variable with a capitalized name, e. g. `function Button() {}`
+/// or `const Button = memo(...)`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs, ValueDebugFormat)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentDeclaration {
+    pub name: String,
+    /// Set by a `Button.displayName = "..."` assignment in the module.
+    pub display_name: Option<String>,
+    /// 1-based line of the declaration in the source of the module.
+    pub line: usize,
+    /// 0-based column of the declaration in the source of the module.
+    pub column: usize,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct ComponentDeclarations(Vec<ComponentDeclaration>);
+
+/// The likely React components declared in a module, with their positions in
+/// its source. Positions are taken from the parsed and transformed program,
+/// so they point to the original source.
+#[turbo_tasks::function]
+pub async fn component_declarations(
+    source: Vc<Box<dyn Source>>,
+    ty: Value<EcmascriptModuleAssetType>,
+    transforms: Vc<EcmascriptInputTransforms>,
+) -> Result<Vc<ComponentDeclarations>> {
+    let parsed = parse(source, ty, transforms).await?;
+    let ParseResult::Ok {
+        program,
+        source_map,
+        ..
+    } = &*parsed
+    else {
+        return Ok(Vc::cell(Vec::new()));
+    };
+    let mut visitor = ComponentVisitor {
+        source_map,
+        components: Vec::new(),
+        display_names: Vec::new(),
+    };
+    program.visit_with(&mut visitor);
+    let ComponentVisitor {
+        mut components,
+        display_names,
+        ..
+    } = visitor;
+    for (name, display_name) in display_names {
+        if let Some(component) = components.iter_mut().find(|c| c.name == name) {
+            component.display_name = Some(display_name);
+        }
+    }
+    Ok(Vc::cell(components))
+}
+
+struct ComponentVisitor<'a> {
+    source_map: &'a SourceMap,
+    components: Vec<ComponentDeclaration>,
+    /// `(component, display name)` of `displayName` assignments.
+    display_names: Vec<(String, String)>,
+}
+
+impl ComponentVisitor<'_> {
+    fn add(&mut self, name: &str, span: Span) {
+        if !name.starts_with(|c: char| c.is_ascii_uppercase()) || span.is_dummy() {
+            return;
+        }
+        let loc = self.source_map.lookup_char_pos(span.lo);
+        self.components.push(ComponentDeclaration {
+            name: name.to_string(),
+            display_name: None,
+            line: loc.line,
+            column: loc.col.0,
+        });
+    }
+}
+
+/// Whether `init` of a variable can be a component: a function, a class, or
+/// a call like `memo(...)` or `forwardRef(...)`.
+fn is_component_init(init: &Expr) -> bool {
+    matches!(
+        init,
+        Expr::Arrow(_) | Expr::Fn(_) | Expr::Class(_) | Expr::Call(_)
+    )
+}
+
+impl Visit for ComponentVisitor<'_> {
+    fn visit_fn_decl(&mut self, decl: &FnDecl) {
+        self.add(&decl.ident.sym, decl.ident.span);
+        decl.visit_children_with(self);
+    }
+
+    fn visit_class_decl(&mut self, decl: &ClassDecl) {
+        self.add(&decl.ident.sym, decl.ident.span);
+        decl.visit_children_with(self);
+    }
+
+    fn visit_default_decl(&mut self, decl: &DefaultDecl) {
+        let ident = match decl {
+            DefaultDecl::Fn(f) => f.ident.as_ref(),
+            DefaultDecl::Class(c) => c.ident.as_ref(),
+            DefaultDecl::TsInterfaceDecl(_) => None,
+        };
+        if let Some(ident) = ident {
+            self.add(&ident.sym, ident.span);
+        }
+        decl.visit_children_with(self);
+    }
+
+    fn visit_var_declarator(&mut self, decl: &VarDeclarator) {
+        if let (Pat::Ident(ident), Some(init)) = (&decl.name, &decl.init) {
+            if is_component_init(init) {
+                self.add(&ident.id.sym, ident.id.span);
+            }
+        }
+        decl.visit_children_with(self);
+    }
+
+    fn visit_assign_expr(&mut self, assign: &AssignExpr) {
+        if let Some(SimpleAssignTarget::Member(member)) = assign.left.as_simple() {
+            if let (Expr::Ident(object), Some(prop), Expr::Lit(Lit::Str(value))) =
+                (&*member.obj, member.prop.as_ident(), &*assign.right)
+            {
+                if &*prop.sym == "displayName" {
+                    self.display_names
+                        .push((object.sym.to_string(), value.value.to_string()));
+                }
+            }
+        }
+        assign.visit_children_with(self);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use anyhow::Result;
+use turbo_tasks::{Completion, ValueToString, Vc};
+use turbo_tasks_fs::FileSystemPath;
+use turbopack_core::{
+    issue::{Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString},
+    module::{Module, Modules},
+    reference::ModuleReference,
+    resolve::{options::DualPackageVariant, parse::Request, pattern::Pattern},
+};
+
+use crate::references::{
+    cjs::{CjsAssetReference, CjsRequireAssetReference},
+    esm::{EsmAssetReference, EsmAsyncAssetReference},
+};
+
+/// Where a package request was resolved to by references of one variant,
+/// with the first module which referenced it.
+#[derive(Default)]
+struct VariantResolutions {
+    paths: BTreeMap<String, (Vc<FileSystemPath>, Vc<Box<dyn Module>>)>,
+}
+
+/// Looks for dual package hazards in the module graph below `entries`: a
+/// package request which is resolved to one file by imports and to another
+/// file by requires, so both builds of the package end up in the graph, each
+/// with its own module state. Emits a [DualPackageHazardIssue] with the
+/// import chains of both variants for each of them.
+///
+/// Setting `dedupe_dual_packages` in the resolve options resolves both to the
+/// same variant.
+#[turbo_tasks::function]
+pub async fn check_dual_package_hazards(entries: Vc<Modules>) -> Result<Vc<Completion>> {
+    let mut queue = entries.await?.iter().copied().collect::<VecDeque<_>>();
+    let mut seen = queue.iter().copied().collect::<HashSet<_>>();
+    let mut parents = HashMap::new();
+    let mut resolutions: BTreeMap<String, (VariantResolutions, VariantResolutions)> =
+        BTreeMap::new();
+    while let Some(module) = queue.pop_front() {
+        for &reference in module.references().await?.iter() {
+            let package_request = package_request(reference).await?;
+            for &referenced in reference
+                .resolve_reference()
+                .primary_modules()
+                .await?
+                .iter()
+            {
+                if let Some((variant, request)) = &package_request {
+                    let path = referenced.ident().path();
+                    let (esm, cjs) = resolutions.entry(request.clone()).or_default();
+                    let resolutions = match variant {
+                        DualPackageVariant::Esm => esm,
+                        DualPackageVariant::Cjs => cjs,
+                    };
+                    resolutions
+                        .paths
+                        .entry(path.to_string().await?.clone_value())
+                        .or_insert((path, module));
+                }
+                if seen.insert(referenced) {
+                    parents.insert(referenced, module);
+                    queue.push_back(referenced);
+                }
+            }
+        }
+    }
+
+    for (request, (esm, cjs)) in resolutions {
+        if esm.paths.keys().any(|path| cjs.paths.contains_key(path)) {
+            continue;
+        }
+        let (Some((_, &(esm_path, esm_importer))), Some((_, &(cjs_path, cjs_importer)))) =
+            (esm.paths.iter().next(), cjs.paths.iter().next())
+        else {
+            continue;
+        };
+        DualPackageHazardIssue {
+            esm_path,
+            cjs_path,
+            request,
+            esm_chain: import_chain(esm_importer, &parents).await?,
+            cjs_chain: import_chain(cjs_importer, &parents).await?,
+        }
+        .cell()
+        .emit();
+    }
+
+    Ok(Completion::new())
+}
+
+/// The variant and the request of references to packages, e. g.
+/// `(Esm, "react/jsx-runtime")` for an import of `react/jsx-runtime`.
+async fn package_request(
+    reference: Vc<Box<dyn ModuleReference>>,
+) -> Result<Option<(DualPackageVariant, String)>> {
+    let (variant, request) = if let Some(reference) =
+        Vc::try_resolve_downcast_type::<EsmAssetReference>(reference).await?
+    {
+        (DualPackageVariant::Esm, reference.await?.request)
+    } else if let Some(reference) =
+        Vc::try_resolve_downcast_type::<EsmAsyncAssetReference>(reference).await?
+    {
+        (DualPackageVariant::Esm, reference.await?.request)
+    } else if let Some(reference) =
+        Vc::try_resolve_downcast_type::<CjsRequireAssetReference>(reference).await?
+    {
+        (DualPackageVariant::Cjs, reference.await?.request)
+    } else if let Some(reference) =
+        Vc::try_resolve_downcast_type::<CjsAssetReference>(reference).await?
+    {
+        (DualPackageVariant::Cjs, reference.await?.request)
+    } else {
+        return Ok(None);
+    };
+    Ok(match &*request.await? {
+        Request::Module {
+            module,
+            path: Pattern::Constant(path),
+            ..
+        } => Some((variant, format!("{module}{path}"))),
+        _ => None,
+    })
+}
+
+/// The modules from an entry to `module`, along the first path the graph
+/// was traversed.
+async fn import_chain(
+    module: Vc<Box<dyn Module>>,
+    parents: &HashMap<Vc<Box<dyn Module>>, Vc<Box<dyn Module>>>,
+) -> Result<Vec<String>> {
+    let mut chain = vec![module.ident().to_string().await?.clone_value()];
+    let mut module = module;
+    while let Some(&parent) = parents.get(&module) {
+        chain.push(parent.ident().to_string().await?.clone_value());
+        module = parent;
+    }
+    chain.reverse();
+    Ok(chain)
+}
+
+/// A package which is included with both its ES module and its CommonJS
+/// build.
+#[turbo_tasks::value(shared)]
+pub struct DualPackageHazardIssue {
+    pub esm_path: Vc<FileSystemPath>,
+    pub cjs_path: Vc<FileSystemPath>,
+    pub request: String,
+    /// The modules from an entry to the first module importing the package.
+    pub esm_chain: Vec<String>,
+    /// The modules from an entry to the first module requiring the package.
+    pub cjs_chain: Vec<String>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for DualPackageHazardIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Warning.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.esm_path
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Resolve.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Line(vec![
+            StyledString::Text("Both the ES module and the CommonJS build of ".to_string()),
+            StyledString::Code(self.request.clone()),
+            StyledString::Text(" are included".to_string()),
+        ])
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    async fn description(&self) -> Result<Vc<OptionStyledString>> {
+        let chain = |chain: &[String]| {
+            StyledString::Stack(
+                chain
+                    .iter()
+                    .map(|module| StyledString::Code(module.clone()))
+                    .collect(),
+            )
+        };
+        Ok(Vc::cell(Some(
+            StyledString::Stack(vec![
+                StyledString::Text(
+                    "The package is imported in some places and required in others, so it is \
+                     instantiated twice and its state, e. g. singletons or instanceof checks, \
+                     isn't shared. Set dedupe_dual_packages in the resolve options to resolve \
+                     both to the same build."
+                        .to_string(),
+                ),
+                StyledString::Line(vec![
+                    StyledString::Text("Imported as ".to_string()),
+                    StyledString::Code(self.esm_path.to_string().await?.clone_value()),
+                    StyledString::Text(" by:".to_string()),
+                ]),
+                chain(&self.esm_chain),
+                StyledString::Line(vec![
+                    StyledString::Text("Required as ".to_string()),
+                    StyledString::Code(self.cjs_path.to_string().await?.clone_value()),
+                    StyledString::Text(" by:".to_string()),
+                ]),
+                chain(&self.cjs_chain),
+            ])
+            .cell(),
+        )))
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::Result;
+use swc_core::ecma::{
+    ast::{
+        AssignExpr, ClassMember, Decl, ExportDecl, ExportSpecifier, ModuleDecl, ModuleExportName,
+        NamedExport, Pat, Program, PropName, SimpleAssignTarget,
+    },
+    visit::{Visit, VisitWith},
+};
+use turbo_tasks::{Value, Vc};
+use turbopack_core::source::Source;
+
+use crate::{
+    parse::{parse, ParseResult},
+    EcmascriptInputTransforms, EcmascriptModuleAssetType,
+};
+
+/// Whether a module defines the legacy `getInitialProps` data fetching
+/// method of a page, either assigned to a component
+/// (`Page.getInitialProps = ...`) or as static member of a class component.
+/// The page runtime calls it before rendering and merges its result into the
+/// props of the page.
+#[turbo_tasks::function]
+pub async fn uses_get_initial_props(
+    source: Vc<Box<dyn Source>>,
+    ty: Value<EcmascriptModuleAssetType>,
+    transforms: Vc<EcmascriptInputTransforms>,
+) -> Result<Vc<bool>> {
+    let parsed = parse(source, ty, transforms).await?;
+    let ParseResult::Ok { program, .. } = &*parsed else {
+        return Ok(Vc::cell(false));
+    };
+    let mut visitor = GetInitialPropsVisitor { found: false };
+    program.visit_with(&mut visitor);
+    Ok(Vc::cell(visitor.found))
+}
+
+struct GetInitialPropsVisitor {
+    found: bool,
+}
+
+impl Visit for GetInitialPropsVisitor {
+    fn visit_assign_expr(&mut self, assign: &AssignExpr) {
+        if let Some(SimpleAssignTarget::Member(member)) = assign.left.as_simple() {
+            if member
+                .prop
+                .as_ident()
+                .map_or(false, |prop| &*prop.sym == "getInitialProps")
+            {
+                self.found = true;
+            }
+        }
+        assign.visit_children_with(self);
+    }
+
+    fn visit_class_member(&mut self, member: &ClassMember) {
+        let key = match member {
+            ClassMember::Method(method) if method.is_static => Some(&method.key),
+            ClassMember::ClassProp(prop) if prop.is_static => Some(&prop.key),
+            _ => None,
+        };
+        if let Some(PropName::Ident(ident)) = key {
+            if &*ident.sym == "getInitialProps" {
+                self.found = true;
+            }
+        }
+        member.visit_children_with(self);
+    }
+}
+
+/// Whether a module exports a `getServerSideProps` data fetching function,
+/// as declaration (`export async function getServerSideProps`,
+/// `export const getServerSideProps = ...`) or by name
+/// (`export { load as getServerSideProps }`). It is called in a data
+/// fetching phase before the page is rendered, whose result is passed to
+/// the render.
+#[turbo_tasks::function]
+pub async fn exports_get_server_side_props(
+    source: Vc<Box<dyn Source>>,
+    ty: Value<EcmascriptModuleAssetType>,
+    transforms: Vc<EcmascriptInputTransforms>,
+) -> Result<Vc<bool>> {
+    let parsed = parse(source, ty, transforms).await?;
+    let ParseResult::Ok {
+        program: Program::Module(module),
+        ..
+    } = &*parsed
+    else {
+        return Ok(Vc::cell(false));
+    };
+    let found = module
+        .body
+        .iter()
+        .filter_map(|item| item.as_module_decl())
+        .any(exports_server_side_props);
+    Ok(Vc::cell(found))
+}
+
+const GET_SERVER_SIDE_PROPS: &str = "getServerSideProps";
+
+fn exports_server_side_props(decl: &ModuleDecl) -> bool {
+    match decl {
+        ModuleDecl::ExportDecl(ExportDecl {
+            decl: Decl::Fn(function),
+            ..
+        }) => &*function.ident.sym == GET_SERVER_SIDE_PROPS,
+        ModuleDecl::ExportDecl(ExportDecl {
+            decl: Decl::Var(var),
+            ..
+        }) => var.decls.iter().any(|declarator| match &declarator.name {
+            Pat::Ident(ident) => &*ident.id.sym == GET_SERVER_SIDE_PROPS,
+            _ => false,
+        }),
+        ModuleDecl::ExportNamed(NamedExport {
+            specifiers,
+            type_only: false,
+            ..
+        }) => specifiers.iter().any(|specifier| match specifier {
+            ExportSpecifier::Named(named) => match named.exported.as_ref().unwrap_or(&named.orig) {
+                ModuleExportName::Ident(ident) => &*ident.sym == GET_SERVER_SIDE_PROPS,
+                ModuleExportName::Str(name) => &*name.value == GET_SERVER_SIDE_PROPS,
+            },
+            _ => false,
+        }),
+        _ => false,
+    }
+}
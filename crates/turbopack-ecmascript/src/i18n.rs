@@ -0,0 +1,224 @@
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use swc_core::ecma::{
+    ast::{CallExpr, Callee, Expr, Lit, MemberProp, ObjectLit, Prop, PropName, PropOrSpread},
+    visit::{Visit, VisitWith},
+};
+use turbo_tasks::{trace::TraceRawVcs, TaskInput, Value, Vc};
+use turbo_tasks_fs::{DirectoryContent, DirectoryEntry, FileJsonContent, FileSystemPath};
+use turbopack_core::{
+    module::Modules, reference::all_modules_and_affecting_sources, source::Source,
+};
+
+use crate::{
+    parse::{parse, ParseResult},
+    EcmascriptInputTransforms, EcmascriptModuleAsset, EcmascriptModuleAssetType,
+};
+
+/// How an i18n library references messages in code. Only messages referenced
+/// by string literals are detected.
+#[derive(TaskInput, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs)]
+pub enum I18nAdapter {
+    /// A function taking the message id, e. g. `t("home.title")` or
+    /// `i18n.t("home.title")`.
+    Function(String),
+    /// A function taking a message descriptor, e. g.
+    /// `intl.formatMessage({ id: "home.title" })`.
+    DescriptorFunction(String),
+    /// A component taking the message id as `id` prop, e. g.
+    /// `<FormattedMessage id="home.title" />`.
+    Component(String),
+}
+
+impl I18nAdapter {
+    /// Adapters for the APIs of i18next and react-intl.
+    pub fn defaults() -> Vec<I18nAdapter> {
+        vec![
+            I18nAdapter::Function("t".to_string()),
+            I18nAdapter::DescriptorFunction("formatMessage".to_string()),
+            I18nAdapter::Component("FormattedMessage".to_string()),
+        ]
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct MessageIds(Vec<String>);
+
+/// The ids of the i18n messages referenced by a module, in order of their
+/// first reference.
+#[turbo_tasks::function]
+pub async fn message_ids(
+    source: Vc<Box<dyn Source>>,
+    ty: Value<EcmascriptModuleAssetType>,
+    transforms: Vc<EcmascriptInputTransforms>,
+    adapters: Vec<I18nAdapter>,
+) -> Result<Vc<MessageIds>> {
+    let parsed = parse(source, ty, transforms).await?;
+    let ParseResult::Ok { program, .. } = &*parsed else {
+        return Ok(Vc::cell(Vec::new()));
+    };
+    let mut visitor = MessageVisitor {
+        adapters: &adapters,
+        ids: Vec::new(),
+    };
+    program.visit_with(&mut visitor);
+    Ok(Vc::cell(visitor.ids))
+}
+
+struct MessageVisitor<'a> {
+    adapters: &'a [I18nAdapter],
+    ids: Vec<String>,
+}
+
+impl MessageVisitor<'_> {
+    fn add(&mut self, id: &str) {
+        if !self.ids.iter().any(|existing| existing == id) {
+            self.ids.push(id.to_string());
+        }
+    }
+}
+
+fn callee_name(callee: &Callee) -> Option<&str> {
+    match callee {
+        Callee::Expr(box Expr::Ident(ident)) => Some(&ident.sym),
+        Callee::Expr(box Expr::Member(member)) => match &member.prop {
+            MemberProp::Ident(prop) => Some(&prop.sym),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn string_literal(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Lit(Lit::Str(str)) => Some(&str.value),
+        _ => None,
+    }
+}
+
+/// The `id` property of an object literal, if it's a string literal.
+fn id_property(object: &ObjectLit) -> Option<&str> {
+    object.props.iter().find_map(|prop| {
+        let PropOrSpread::Prop(box Prop::KeyValue(prop)) = prop else {
+            return None;
+        };
+        let key: &str = match &prop.key {
+            PropName::Ident(ident) => &ident.sym,
+            PropName::Str(str) => &str.value,
+            _ => return None,
+        };
+        if key == "id" {
+            string_literal(&prop.value)
+        } else {
+            None
+        }
+    })
+}
+
+impl Visit for MessageVisitor<'_> {
+    fn visit_call_expr(&mut self, call: &CallExpr) {
+        let name = callee_name(&call.callee);
+        let arg = |index: usize| call.args.get(index).map(|arg| &*arg.expr);
+        for adapter in self.adapters {
+            let id = match adapter {
+                I18nAdapter::Function(function) if name == Some(function.as_str()) => {
+                    arg(0).and_then(string_literal)
+                }
+                I18nAdapter::DescriptorFunction(function) if name == Some(function.as_str()) => {
+                    match arg(0) {
+                        Some(Expr::Object(descriptor)) => id_property(descriptor),
+                        _ => None,
+                    }
+                }
+                // JSX has already been transformed into calls of the JSX runtime or of
+                // `React.createElement`, which take the component first and the props second.
+                I18nAdapter::Component(component) => match (arg(0), arg(1)) {
+                    (Some(Expr::Ident(ident)), Some(Expr::Object(props)))
+                        if &*ident.sym == component.as_str() =>
+                    {
+                        id_property(props)
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(id) = id {
+                self.add(id);
+            }
+        }
+        call.visit_children_with(self);
+    }
+}
+
+/// The messages of each locale which are referenced by the modules of an
+/// app, keyed by locale.
+#[turbo_tasks::value(transparent)]
+pub struct MessageCatalogs(#[turbo_tasks(trace_ignore)] IndexMap<String, JsonValue>);
+
+/// Extracts the messages referenced by the module graph below `entries` from
+/// the catalogs in `catalog_dir`, one flat JSON object of messages by id per
+/// locale, e. g. `messages/en.json`. Messages which aren't referenced are left
+/// out, so the catalog of a locale can be loaded on demand without loading
+/// the messages of unused code.
+#[turbo_tasks::function]
+pub async fn extract_message_catalogs(
+    entries: Vc<Modules>,
+    catalog_dir: Vc<FileSystemPath>,
+    adapters: Vec<I18nAdapter>,
+) -> Result<Vc<MessageCatalogs>> {
+    let DirectoryContent::Entries(files) = &*catalog_dir.read_dir().await? else {
+        return Ok(Vc::cell(IndexMap::new()));
+    };
+    let mut catalogs = files
+        .iter()
+        .filter_map(|(name, entry)| match entry {
+            DirectoryEntry::File(path) => name
+                .strip_suffix(".json")
+                .map(|locale| (locale.to_string(), *path)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    // read_dir returns the files in random order
+    catalogs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut ids = BTreeSet::new();
+    for &entry in entries.await?.iter() {
+        for &module in all_modules_and_affecting_sources(entry).await?.iter() {
+            if let Some(ecmascript) =
+                Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(module).await?
+            {
+                ids.extend(
+                    ecmascript
+                        .message_ids(adapters.clone())
+                        .await?
+                        .iter()
+                        .cloned(),
+                );
+            }
+        }
+    }
+
+    let mut result = IndexMap::new();
+    for (locale, path) in catalogs {
+        let messages = match &*path.read_json().await? {
+            FileJsonContent::Content(JsonValue::Object(messages)) => messages
+                .iter()
+                .filter(|(id, _)| ids.contains(*id))
+                .map(|(id, message)| (id.clone(), message.clone()))
+                .collect::<Map<_, _>>(),
+            FileJsonContent::Content(_) => {
+                bail!("message catalog {locale}.json must be a JSON object")
+            }
+            FileJsonContent::Unparseable(e) => {
+                bail!("message catalog {locale}.json is not valid JSON: {e}")
+            }
+            FileJsonContent::NotFound => continue,
+        };
+        result.insert(locale, JsonValue::Object(messages));
+    }
+    Ok(Vc::cell(result))
+}
@@ -11,13 +11,18 @@ pub mod async_chunk;
 pub mod chunk;
 pub mod chunk_group_files_asset;
 pub mod code_gen;
+pub mod components;
+pub mod dual_package_hazard;
 mod errors;
+pub mod get_initial_props;
+pub mod i18n;
 pub mod magic_identifier;
 pub mod manifest;
 pub mod minify;
 pub mod parse;
 mod path_visitor;
 pub mod references;
+pub mod segment_config;
 pub mod side_effect_optimization;
 pub(crate) mod special_cases;
 pub(crate) mod static_code;
@@ -84,7 +89,11 @@ use self::{
 };
 use crate::{
     chunk::EcmascriptChunkPlaceable,
+    components::{component_declarations, ComponentDeclarations},
+    get_initial_props::{exports_get_server_side_props, uses_get_initial_props},
+    i18n::{message_ids, I18nAdapter, MessageIds},
     references::{analyse_ecmascript_module, async_module::OptionAsyncModule},
+    segment_config::{parse_segment_config, SegmentConfig},
     transform::remove_shebang,
 };
 
@@ -427,6 +436,39 @@ impl EcmascriptModuleAsset {
         parse(self.source, Value::new(self.ty), self.transforms)
     }
 
+    /// Returns the statically declared route segment config of this module.
+    #[turbo_tasks::function]
+    pub fn segment_config(&self) -> Vc<SegmentConfig> {
+        parse_segment_config(self.source, Value::new(self.ty), self.transforms)
+    }
+
+    /// Whether this module defines the legacy `getInitialProps` method of a
+    /// page.
+    #[turbo_tasks::function]
+    pub fn uses_get_initial_props(&self) -> Vc<bool> {
+        uses_get_initial_props(self.source, Value::new(self.ty), self.transforms)
+    }
+
+    /// Whether this module exports the `getServerSideProps` data fetching
+    /// function of a page.
+    #[turbo_tasks::function]
+    pub fn exports_get_server_side_props(&self) -> Vc<bool> {
+        exports_get_server_side_props(self.source, Value::new(self.ty), self.transforms)
+    }
+
+    /// The likely React components declared in this module.
+    #[turbo_tasks::function]
+    pub fn component_declarations(&self) -> Vc<ComponentDeclarations> {
+        component_declarations(self.source, Value::new(self.ty), self.transforms)
+    }
+
+    /// The ids of the i18n messages this module references through
+    /// `adapters`.
+    #[turbo_tasks::function]
+    pub fn message_ids(&self, adapters: Vec<I18nAdapter>) -> Vc<MessageIds> {
+        message_ids(self.source, Value::new(self.ty), self.transforms, adapters)
+    }
+
     #[turbo_tasks::function]
     pub(crate) async fn determine_module_type(self: Vc<Self>) -> Result<Vc<ModuleTypeResult>> {
         let this = self.await?;
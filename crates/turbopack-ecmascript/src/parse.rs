@@ -21,7 +21,7 @@ use swc_core::{
     },
 };
 use tracing::Instrument;
-use turbo_tasks::{util::WrapFuture, Value, ValueToString, Vc};
+use turbo_tasks::{spawn_compute, util::WrapFuture, Value, ValueToString, Vc};
 use turbo_tasks_fs::{FileContent, FileSystemPath};
 use turbo_tasks_hash::hash_xxh3_hash64;
 use turbopack_core::{
@@ -205,8 +205,12 @@ async fn parse_internal(
             FileContent::Content(file) => match file.content().to_str() {
                 Ok(string) => {
                     let transforms = &*transforms.await?;
+                    let string = match string.strip_prefix('\u{feff}') {
+                        Some(string) => string.to_string(),
+                        None => string.into_owned(),
+                    };
                     match parse_content(
-                        string.into_owned(),
+                        string,
                         fs_path_vc,
                         fs_path,
                         ident,
@@ -227,13 +231,29 @@ async fn parse_internal(
                     }
                 }
                 Err(error) => {
-                    let error = PrettyPrintError(&error).to_string();
-                    ReadSourceIssue {
-                        source,
-                        error: error.clone(),
-                    }
-                    .cell()
-                    .emit();
+                    let bytes = file.content().to_bytes()?;
+                    let error = match invalid_encoding(&bytes) {
+                        Some((encoding, line)) => {
+                            InvalidEncodingIssue {
+                                source,
+                                encoding,
+                                line,
+                            }
+                            .cell()
+                            .emit();
+                            format!("{} is not valid UTF-8", ident)
+                        }
+                        None => {
+                            let error = PrettyPrintError(&error).to_string();
+                            ReadSourceIssue {
+                                source,
+                                error: error.clone(),
+                            }
+                            .cell()
+                            .emit();
+                            error
+                        }
+                    };
                     ParseResult::Unparseable {
                         messages: Some(vec![error]),
                     }
@@ -245,6 +265,23 @@ async fn parse_internal(
     })
 }
 
+/// The likely encoding of a source which is not valid UTF-8, and the line of
+/// the first invalid byte. `None` when the source is valid UTF-8.
+fn invalid_encoding(bytes: &[u8]) -> Option<(Option<String>, usize)> {
+    let error = std::str::from_utf8(bytes).err()?;
+    let encoding = match bytes {
+        [0xff, 0xfe, ..] => Some("UTF-16LE".to_string()),
+        [0xfe, 0xff, ..] => Some("UTF-16BE".to_string()),
+        _ => None,
+    };
+    let line = bytes[..error.valid_up_to()]
+        .iter()
+        .filter(|&&b| b == b'\n')
+        .count()
+        + 1;
+    Some((encoding, line))
+}
+
 async fn parse_content(
     string: String,
     fs_path_vc: Vc<FileSystemPath>,
@@ -275,57 +312,27 @@ async fn parse_content(
     let globals = Arc::new(Globals::new());
     let globals_ref = &globals;
     let helpers = GLOBALS.set(globals_ref, || Helpers::new(true));
+    let file_name = FileName::Custom(ident.to_string());
+    let fm = source_map.new_source_file(file_name, string);
+    let comments = SwcComments::default();
+    // Parsing is CPU-heavy and doesn't need the task context, so it runs on the
+    // compute pool. Errors are emitted afterwards, as that emits issues.
+    let ParsedProgram {
+        program_result,
+        errors,
+        unresolved_mark,
+        top_level_mark,
+    } = spawn_compute({
+        let globals = globals.clone();
+        let comments = comments.clone();
+        move || GLOBALS.set(&globals, || parse_program(&fm, ty, &comments))
+    })
+    .await;
     let mut result = WrapFuture::new(
         async {
-            let file_name = FileName::Custom(ident.to_string());
-            let fm = source_map.new_source_file(file_name.clone(), string);
-
-            let comments = SwcComments::default();
-
             let mut parsed_program = {
-                let lexer = Lexer::new(
-                    match ty {
-                        EcmascriptModuleAssetType::Ecmascript => Syntax::Es(EsConfig {
-                            jsx: true,
-                            fn_bind: true,
-                            decorators: true,
-                            decorators_before_export: true,
-                            export_default_from: true,
-                            import_attributes: true,
-                            allow_super_outside_method: true,
-                            allow_return_outside_function: true,
-                            auto_accessors: true,
-                            explicit_resource_management: true,
-                        }),
-                        EcmascriptModuleAssetType::Typescript { tsx, .. } => {
-                            Syntax::Typescript(TsConfig {
-                                decorators: true,
-                                dts: false,
-                                no_early_errors: true,
-                                tsx,
-                                disallow_ambiguous_jsx_like: false,
-                            })
-                        }
-                        EcmascriptModuleAssetType::TypescriptDeclaration => {
-                            Syntax::Typescript(TsConfig {
-                                decorators: true,
-                                dts: true,
-                                no_early_errors: true,
-                                tsx: false,
-                                disallow_ambiguous_jsx_like: false,
-                            })
-                        }
-                    },
-                    EsVersion::latest(),
-                    StringInput::from(&*fm),
-                    Some(&comments),
-                );
-
-                let mut parser = Parser::new_from(lexer);
-                let program_result = parser.parse_program();
-
                 let mut has_errors = vec![];
-                for e in parser.take_errors() {
+                for e in errors {
                     let mut e = e.into_diagnostic(&parser_handler);
                     has_errors.extend(e.message.iter().map(|m| m.0.clone()));
                     e.emit();
@@ -352,20 +359,6 @@ async fn parse_content(
                 }
             };
 
-            let unresolved_mark = Mark::new();
-            let top_level_mark = Mark::new();
-
-            let is_typescript = matches!(
-                ty,
-                EcmascriptModuleAssetType::Typescript { .. }
-                    | EcmascriptModuleAssetType::TypescriptDeclaration
-            );
-            parsed_program.visit_mut_with(&mut resolver(
-                unresolved_mark,
-                top_level_mark,
-                is_typescript,
-            ));
-
             let lint_config = LintConfig::default();
             let rules = swc_core::ecma::lints::rules::all(LintParams {
                 program: &parsed_program,
@@ -441,6 +434,135 @@ async fn parse_content(
     Ok(result.cell())
 }
 
+struct ParsedProgram {
+    program_result: Result<Program, swc_core::ecma::parser::error::Error>,
+    /// Recoverable errors, the program is unparseable when there are any.
+    errors: Vec<swc_core::ecma::parser::error::Error>,
+    unresolved_mark: Mark,
+    top_level_mark: Mark,
+}
+
+/// Parses a source file and applies the resolver. This needs to be called with
+/// the [GLOBALS] of the module set.
+fn parse_program(
+    fm: &swc_core::common::SourceFile,
+    ty: EcmascriptModuleAssetType,
+    comments: &SwcComments,
+) -> ParsedProgram {
+    let lexer = Lexer::new(
+        match ty {
+            EcmascriptModuleAssetType::Ecmascript => Syntax::Es(EsConfig {
+                jsx: true,
+                fn_bind: true,
+                decorators: true,
+                decorators_before_export: true,
+                export_default_from: true,
+                import_attributes: true,
+                allow_super_outside_method: true,
+                allow_return_outside_function: true,
+                auto_accessors: true,
+                explicit_resource_management: true,
+            }),
+            EcmascriptModuleAssetType::Typescript { tsx, .. } => Syntax::Typescript(TsConfig {
+                decorators: true,
+                dts: false,
+                no_early_errors: true,
+                tsx,
+                disallow_ambiguous_jsx_like: false,
+            }),
+            EcmascriptModuleAssetType::TypescriptDeclaration => Syntax::Typescript(TsConfig {
+                decorators: true,
+                dts: true,
+                no_early_errors: true,
+                tsx: false,
+                disallow_ambiguous_jsx_like: false,
+            }),
+        },
+        EsVersion::latest(),
+        StringInput::from(fm),
+        Some(comments),
+    );
+
+    let mut parser = Parser::new_from(lexer);
+    let mut program_result = parser.parse_program();
+    let errors = parser.take_errors();
+
+    let unresolved_mark = Mark::new();
+    let top_level_mark = Mark::new();
+
+    if let Ok(program) = &mut program_result {
+        let is_typescript = matches!(
+            ty,
+            EcmascriptModuleAssetType::Typescript { .. }
+                | EcmascriptModuleAssetType::TypescriptDeclaration
+        );
+        program.visit_mut_with(&mut resolver(
+            unresolved_mark,
+            top_level_mark,
+            is_typescript,
+        ));
+    }
+
+    ParsedProgram {
+        program_result,
+        errors,
+        unresolved_mark,
+        top_level_mark,
+    }
+}
+
+#[turbo_tasks::value]
+struct InvalidEncodingIssue {
+    source: Vc<Box<dyn Source>>,
+    /// The encoding detected from the byte order mark, if any.
+    encoding: Option<String>,
+    /// The line of the first byte which is not valid UTF-8.
+    line: usize,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for InvalidEncodingIssue {
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.source.ident().path()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(match &self.encoding {
+            Some(encoding) => format!("Source code is encoded as {encoding} instead of UTF-8"),
+            None => format!(
+                "Source code is not valid UTF-8, starting at line {}",
+                self.line
+            ),
+        })
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(
+                "Source code must be encoded as UTF-8. Convert the file to UTF-8, or, for legacy \
+                 files which can't be changed, decode them lossily with the LossyUtf8Decode \
+                 source transform in a module rule, which replaces invalid bytes."
+                    .to_string(),
+            )
+            .cell(),
+        ))
+    }
+
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Error.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Load.cell()
+    }
+}
+
 #[turbo_tasks::value]
 struct ReadSourceIssue {
     source: Vc<Box<dyn Source>>,
@@ -21,7 +21,7 @@ use turbopack_core::{
         ExternalType, ModulePart, ModuleResolveResult, ModuleResolveResultItem,
     },
 };
-use turbopack_resolve::ecmascript::esm_resolve;
+use turbopack_resolve::ecmascript::{esm_resolve, esm_resolve_with_condition};
 
 use crate::{
     analyzer::imports::ImportAnnotations,
@@ -147,8 +147,19 @@ impl ModuleReference for EsmAssetReference {
             None => EcmaScriptModulesReferenceSubType::Import,
         });
 
+        let origin = self.get_origin().resolve().await?;
+        if let Some(env) = self.annotations.env() {
+            return Ok(esm_resolve_with_condition(
+                origin,
+                self.request,
+                ty,
+                env.to_string(),
+                IssueSeverity::Error.cell(),
+                self.issue_source,
+            ));
+        }
         Ok(esm_resolve(
-            self.get_origin().resolve().await?,
+            origin,
             self.request,
             ty,
             IssueSeverity::Error.cell(),
@@ -0,0 +1,265 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use swc_core::{
+    common::{Span, Spanned},
+    ecma::ast::{Decl, ExportDecl, Expr, Lit, ModuleDecl, ModuleItem, Pat, Program, VarDeclKind},
+};
+use turbo_tasks::{trace::TraceRawVcs, Value, Vc};
+use turbopack_core::{
+    issue::{analyze::AnalyzeIssue, IssueExt, IssueSeverity, IssueSource, StyledString},
+    source::Source,
+};
+
+use crate::{
+    parse::{parse, ParseResult},
+    utils::unparen,
+    EcmascriptInputTransforms, EcmascriptModuleAssetType,
+};
+
+/// The value of the `dynamic` route segment config export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, TraceRawVcs)]
+#[serde(rename_all = "kebab-case")]
+pub enum SegmentDynamic {
+    #[default]
+    Auto,
+    ForceDynamic,
+    Error,
+    ForceStatic,
+}
+
+/// The value of the `revalidate` route segment config export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs)]
+#[serde(rename_all = "camelCase")]
+pub enum SegmentRevalidate {
+    /// `revalidate = false`, the result is cached indefinitely.
+    Never,
+    /// `revalidate = <seconds>`. A value of `0` means the segment is always
+    /// rendered dynamically.
+    Seconds(u32),
+}
+
+/// The value of the `fetchCache` route segment config export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, TraceRawVcs)]
+#[serde(rename_all = "kebab-case")]
+pub enum SegmentFetchCache {
+    #[default]
+    Auto,
+    DefaultCache,
+    OnlyCache,
+    ForceCache,
+    DefaultNoStore,
+    OnlyNoStore,
+    ForceNoStore,
+}
+
+/// Route segment config statically extracted from the `export const`
+/// declarations of a module (e. g. `export const revalidate = 60`).
+///
+/// Fields are `None` when the module doesn't declare them.
+#[turbo_tasks::value(shared)]
+#[derive(Debug, Default, Clone)]
+pub struct SegmentConfig {
+    pub dynamic: Option<SegmentDynamic>,
+    pub revalidate: Option<SegmentRevalidate>,
+    pub fetch_cache: Option<SegmentFetchCache>,
+}
+
+impl SegmentConfig {
+    /// Whether the segment must be rendered on every request.
+    pub fn is_dynamic(&self) -> bool {
+        matches!(self.dynamic, Some(SegmentDynamic::ForceDynamic))
+            || matches!(self.revalidate, Some(SegmentRevalidate::Seconds(0)))
+    }
+
+    /// The number of seconds a rendered result can be served from cache, or
+    /// `None` when it can be cached indefinitely.
+    pub fn revalidate_seconds(&self) -> Option<u32> {
+        if self.is_dynamic() {
+            return Some(0);
+        }
+        match self.revalidate {
+            Some(SegmentRevalidate::Seconds(seconds)) => Some(seconds),
+            Some(SegmentRevalidate::Never) | None => None,
+        }
+    }
+
+    /// A `cache-control` header value matching the config, if the config
+    /// declares any caching behavior.
+    pub fn cache_control(&self) -> Option<String> {
+        if self.is_dynamic() {
+            return Some("private, no-cache, no-store, max-age=0, must-revalidate".to_string());
+        }
+        match self.revalidate {
+            Some(SegmentRevalidate::Seconds(seconds)) => {
+                Some(format!("s-maxage={seconds}, stale-while-revalidate"))
+            }
+            Some(SegmentRevalidate::Never) => {
+                Some("s-maxage=31536000, stale-while-revalidate".to_string())
+            }
+            None if matches!(self.dynamic, Some(SegmentDynamic::ForceStatic)) => {
+                Some("s-maxage=31536000, stale-while-revalidate".to_string())
+            }
+            None => None,
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl SegmentConfig {
+    #[turbo_tasks::function]
+    pub fn empty() -> Vc<Self> {
+        SegmentConfig::default().cell()
+    }
+}
+
+/// Statically extracts the route segment config (`dynamic`, `revalidate`,
+/// `fetchCache`) from the top level exports of a module. Values that can't be
+/// statically analyzed are reported as issues and ignored.
+#[turbo_tasks::function]
+pub async fn parse_segment_config(
+    source: Vc<Box<dyn Source>>,
+    ty: Value<EcmascriptModuleAssetType>,
+    transforms: Vc<EcmascriptInputTransforms>,
+) -> Result<Vc<SegmentConfig>> {
+    let parsed = parse(source, ty, transforms).await?;
+    let ParseResult::Ok { program, .. } = &*parsed else {
+        return Ok(SegmentConfig::empty());
+    };
+    let Program::Module(module) = program else {
+        return Ok(SegmentConfig::empty());
+    };
+
+    let mut config = SegmentConfig::default();
+    for item in &module.body {
+        let ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+            decl: Decl::Var(var_decl),
+            ..
+        })) = item
+        else {
+            continue;
+        };
+        if var_decl.kind != VarDeclKind::Const {
+            continue;
+        }
+        for decl in &var_decl.decls {
+            let Pat::Ident(ident) = &decl.name else {
+                continue;
+            };
+            let key = &*ident.id.sym;
+            if !matches!(key, "dynamic" | "revalidate" | "fetchCache") {
+                continue;
+            }
+            let Some(init) = decl.init.as_deref() else {
+                continue;
+            };
+            let init = unparen(init);
+            match key {
+                "dynamic" => match string_value(init) {
+                    Some("auto") => config.dynamic = Some(SegmentDynamic::Auto),
+                    Some("force-dynamic") => config.dynamic = Some(SegmentDynamic::ForceDynamic),
+                    Some("error") => config.dynamic = Some(SegmentDynamic::Error),
+                    Some("force-static") => config.dynamic = Some(SegmentDynamic::ForceStatic),
+                    _ => invalid_config_issue(
+                        source,
+                        init.span(),
+                        key,
+                        "\"auto\", \"force-dynamic\", \"error\" or \"force-static\"",
+                    ),
+                },
+                "revalidate" => match init {
+                    Expr::Lit(Lit::Bool(b)) if !b.value => {
+                        config.revalidate = Some(SegmentRevalidate::Never)
+                    }
+                    Expr::Lit(Lit::Num(n)) if n.value >= 0.0 && n.value.is_finite() => {
+                        config.revalidate = Some(SegmentRevalidate::Seconds(
+                            n.value.min(u32::MAX as f64) as u32,
+                        ))
+                    }
+                    Expr::Ident(i) if &*i.sym == "Infinity" => {
+                        config.revalidate = Some(SegmentRevalidate::Never)
+                    }
+                    _ => invalid_config_issue(
+                        source,
+                        init.span(),
+                        key,
+                        "`false` or a non-negative number of seconds",
+                    ),
+                },
+                "fetchCache" => match string_value(init) {
+                    Some("auto") => config.fetch_cache = Some(SegmentFetchCache::Auto),
+                    Some("default-cache") => {
+                        config.fetch_cache = Some(SegmentFetchCache::DefaultCache)
+                    }
+                    Some("only-cache") => config.fetch_cache = Some(SegmentFetchCache::OnlyCache),
+                    Some("force-cache") => config.fetch_cache = Some(SegmentFetchCache::ForceCache),
+                    Some("default-no-store") => {
+                        config.fetch_cache = Some(SegmentFetchCache::DefaultNoStore)
+                    }
+                    Some("only-no-store") => {
+                        config.fetch_cache = Some(SegmentFetchCache::OnlyNoStore)
+                    }
+                    Some("force-no-store") => {
+                        config.fetch_cache = Some(SegmentFetchCache::ForceNoStore)
+                    }
+                    _ => invalid_config_issue(
+                        source,
+                        init.span(),
+                        key,
+                        "one of \"auto\", \"default-cache\", \"only-cache\", \"force-cache\", \
+                         \"default-no-store\", \"only-no-store\" or \"force-no-store\"",
+                    ),
+                },
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    Ok(config.cell())
+}
+
+fn string_value(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Lit(Lit::Str(s)) => Some(&*s.value),
+        Expr::Tpl(tpl) if tpl.exprs.is_empty() && tpl.quasis.len() == 1 => {
+            tpl.quasis[0].cooked.as_deref()
+        }
+        _ => None,
+    }
+}
+
+fn invalid_config_issue(source: Vc<Box<dyn Source>>, span: Span, key: &str, expected: &str) {
+    AnalyzeIssue {
+        code: None,
+        message: StyledString::Text(format!(
+            "The `{key}` route segment config must be a statically analyzable literal, expected \
+             {expected}. The value is ignored."
+        ))
+        .cell(),
+        source_ident: source.ident(),
+        severity: IssueSeverity::Warning.into(),
+        source: Some(IssueSource::from_swc_offsets(
+            source,
+            span.lo.to_usize(),
+            span.hi.to_usize(),
+        )),
+        title: Vc::cell(format!("invalid route segment config `{key}`")),
+    }
+    .cell()
+    .emit();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_dynamic_disables_caching() {
+        let config = SegmentConfig {
+            dynamic: Some(SegmentDynamic::ForceDynamic),
+            revalidate: Some(SegmentRevalidate::Seconds(60)),
+            fetch_cache: None,
+        };
+        assert!(config.is_dynamic());
+        assert_eq!(config.revalidate_seconds(), Some(0));
+    }
+}
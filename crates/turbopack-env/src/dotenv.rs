@@ -1,7 +1,7 @@
 use anyhow::Result;
 use indexmap::indexmap;
 use turbo_tasks::Vc;
-use turbo_tasks_env::{CommandLineProcessEnv, CustomProcessEnv, ProcessEnv};
+use turbo_tasks_env::{CommandLineProcessEnv, CustomProcessEnv, FilterProcessEnv, ProcessEnv};
 use turbo_tasks_fs::FileSystemPath;
 
 use crate::TryDotenvProcessEnv;
@@ -10,9 +10,37 @@ use crate::TryDotenvProcessEnv;
 /// https://nextjs.org/docs/basic-features/environment-variables#environment-variable-load-order
 #[turbo_tasks::function]
 pub async fn load_env(project_path: Vc<FileSystemPath>) -> Result<Vc<Box<dyn ProcessEnv>>> {
-    let env: Vc<Box<dyn ProcessEnv>> = Vc::upcast(CommandLineProcessEnv::new());
+    load_dotenv_files(project_path, Vc::upcast(CommandLineProcessEnv::new())).await
+}
+
+/// Like [load_env], but only includes the variables of the process env which
+/// start with one of `prefixes` (ignoring casing), e. g. to keep the secrets
+/// of the shell out of renderer processes. `NODE_ENV` and the variables of
+/// the dotenv files are always included.
+///
+/// The dotenv files are read through the filesystem, so everything using the
+/// env, like a renderer pool, is recreated when they change.
+#[turbo_tasks::function]
+pub async fn load_env_with_prefixes(
+    project_path: Vc<FileSystemPath>,
+    prefixes: Vec<String>,
+) -> Result<Vc<Box<dyn ProcessEnv>>> {
+    load_dotenv_files(
+        project_path,
+        Vc::upcast(FilterProcessEnv::new(
+            Vc::upcast(CommandLineProcessEnv::new()),
+            prefixes,
+        )),
+    )
+    .await
+}
 
-    let node_env = env.read("NODE_ENV".to_string()).await?;
+async fn load_dotenv_files(
+    project_path: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+) -> Result<Vc<Box<dyn ProcessEnv>>> {
+    let process_env: Vc<Box<dyn ProcessEnv>> = Vc::upcast(CommandLineProcessEnv::new());
+    let node_env = process_env.read("NODE_ENV".to_string()).await?;
     let node_env = node_env.as_deref().unwrap_or("development");
 
     let env = Vc::upcast(CustomProcessEnv::new(
@@ -0,0 +1,64 @@
+// Runs an entrypoint whose files are not on disk, e.g. of a virtual file
+// system in tests. It's passed to `node -e`, Turbopack writes the files to
+// stdin as `{ entry, files }`, with the base64 encoded content of each file
+// by its absolute path.
+//
+// This file is not bundled, so it must run as is in all supported Node.js
+// versions.
+const fs = require("fs");
+const path = require("path");
+const Module = require("module");
+
+const { entry, files } = JSON.parse(fs.readFileSync(0, "utf8"));
+const contents = new Map(
+  Object.entries(files).map(([file, content]) => [
+    path.resolve(file),
+    Buffer.from(content, "base64"),
+  ])
+);
+
+const resolveFilename = Module._resolveFilename;
+Module._resolveFilename = function (request, parent, isMain, options) {
+  const inMemoryParent = parent != null && contents.has(parent.filename);
+  if (request.startsWith(".") || path.isAbsolute(request)) {
+    const base = inMemoryParent ? path.dirname(parent.filename) : process.cwd();
+    const file = path.resolve(base, request);
+    for (const candidate of [
+      file,
+      `${file}.js`,
+      `${file}.json`,
+      path.join(file, "index.js"),
+    ]) {
+      if (contents.has(candidate)) {
+        return candidate;
+      }
+    }
+  } else if (inMemoryParent && options == null) {
+    // Externals are resolved from the working directory, there are no
+    // `node_modules` next to the in-memory files
+    return resolveFilename.call(this, request, parent, isMain, {
+      paths: [process.cwd()],
+    });
+  }
+  return resolveFilename.call(this, request, parent, isMain, options);
+};
+
+for (const extension of [".js", ".json"]) {
+  const load = Module._extensions[extension];
+  Module._extensions[extension] = function (module, filename) {
+    const content = contents.get(filename);
+    if (content === undefined) {
+      return load(module, filename);
+    }
+    if (extension === ".json") {
+      module.exports = JSON.parse(content.toString("utf8"));
+    } else {
+      module._compile(content.toString("utf8"), filename);
+    }
+  };
+}
+
+// The entrypoint expects to be run as `node <entry> <port> <marker>`
+const entryPath = path.resolve(entry);
+process.argv.splice(1, 0, entryPath);
+require(entryPath);
@@ -0,0 +1,63 @@
+import { relative } from "node:path";
+import type { Ipc } from "./index";
+
+export type DependencyOutgoingMessage =
+  | {
+      type: "fileDependency";
+      path: string;
+    }
+  | {
+      type: "dirDependency";
+      path: string;
+      glob: string;
+    };
+
+/**
+ * The message reporting a file read by a job, which Turbopack can't see.
+ * Evaluated code, e.g. emit hooks and HTML transforms, sends it with
+ * `ipc.sendInfo`, renders and API routes with `trackFileDependency`.
+ */
+export function fileDependency(file: string): DependencyOutgoingMessage {
+  return {
+    type: "fileDependency",
+    path: relative(process.cwd(), file),
+  };
+}
+
+/**
+ * Like `fileDependency`, for the files matching `glob` in a directory.
+ */
+export function dirDependency(
+  dir: string,
+  glob = "**"
+): DependencyOutgoingMessage {
+  return {
+    type: "dirDependency",
+    path: relative(process.cwd(), dir),
+    glob,
+  };
+}
+
+/**
+ * Reports a file read by a render, e.g. the markdown content of a page.
+ * Turbopack renders the page again when it changes, without rebuilding the
+ * bundles of the page, which only depend on its modules.
+ */
+export function trackFileDependency(
+  ipc: Ipc<unknown, DependencyOutgoingMessage>,
+  file: string
+): Promise<void> {
+  return ipc.send(fileDependency(file));
+}
+
+/**
+ * Like `trackFileDependency`, for the files matching `glob` in a directory,
+ * e.g. a directory of blog posts listed by a page.
+ */
+export function trackDirDependency(
+  ipc: Ipc<unknown, DependencyOutgoingMessage>,
+  dir: string,
+  glob = "**"
+): Promise<void> {
+  return ipc.send(dirDependency(dir, glob));
+}
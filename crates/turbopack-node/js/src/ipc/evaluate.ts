@@ -78,6 +78,14 @@ export const run = async (
     }
     getValue = module.default;
     await ipc.sendReady();
+    // The module is imported again after an update
+    ipc.onModulesUpdated(async () => {
+      const module = await moduleFactory();
+      if (typeof module.init === "function") {
+        await module.init();
+      }
+      getValue = module.default;
+    });
   } catch (err) {
     await ipc.sendReady();
     await ipc.sendError(err as Error);
@@ -0,0 +1,201 @@
+import type { Ipc } from "./index";
+
+type CachedResponse = {
+  url: string;
+  status: number;
+  statusText: string;
+  headers: [string, string][];
+  // base64 encoded
+  body: string;
+};
+
+type FetchCacheIncomingMessage = {
+  type: "fetchCacheResult";
+  data: CachedResponse | null;
+  stale: boolean;
+};
+
+type FetchCacheOutgoingMessage =
+  | {
+      type: "fetchCacheGet";
+      key: string;
+    }
+  | {
+      type: "fetchCacheSet";
+      key: string;
+      data: CachedResponse;
+      revalidate: number | null;
+    }
+  | {
+      type: "fetchRecord";
+      key: string;
+      data: CachedResponse;
+    }
+  | {
+      type: "fetchReplay";
+      key: string;
+    };
+
+type FetchInit = {
+  method?: string;
+  headers?: any;
+  body?: any;
+  cache?: string;
+  next?: { revalidate?: number | false };
+};
+
+const PATCHED = Symbol.for("turbopack.fetchCache");
+
+// Mirrors `TURBOPACK_FETCH_CASSETTE_MODE` on the Rust side.
+function cassetteMode(): "record" | "replay" | null {
+  if (process.env.TURBOPACK_FETCH_CASSETTE == null) {
+    return null;
+  }
+  return process.env.TURBOPACK_FETCH_CASSETTE_MODE === "record"
+    ? "record"
+    : "replay";
+}
+
+async function serializeResponse(response: any): Promise<CachedResponse> {
+  const body = Buffer.from(await response.clone().arrayBuffer());
+  return {
+    url: response.url,
+    status: response.status,
+    statusText: response.statusText,
+    headers: [...response.headers],
+    body: body.toString("base64"),
+  };
+}
+
+function deserializeResponse(data: CachedResponse): any {
+  const response = new (globalThis as any).Response(
+    Buffer.from(data.body, "base64"),
+    {
+      status: data.status,
+      statusText: data.statusText,
+      headers: data.headers,
+    }
+  );
+  Object.defineProperty(response, "url", { value: data.url });
+  return response;
+}
+
+/**
+ * Patches the global `fetch` so GET requests are served from the fetch cache
+ * in the Rust process. Stale entries are returned immediately and refreshed
+ * in the background.
+ *
+ * When a fetch cassette is configured, all requests are instead recorded to
+ * or replayed from the cassette.
+ */
+export function installFetchCache(ipc: Ipc<unknown, unknown>) {
+  const globalObject = globalThis as any;
+  const originalFetch = globalObject.fetch;
+  if (typeof originalFetch !== "function" || originalFetch[PATCHED]) {
+    return;
+  }
+
+  const cacheIpc = ipc as Ipc<
+    FetchCacheIncomingMessage,
+    FetchCacheOutgoingMessage
+  >;
+
+  async function request(
+    message: FetchCacheOutgoingMessage
+  ): Promise<FetchCacheIncomingMessage> {
+    // Register the receiver before sending, so responses are matched to
+    // requests in order even with concurrent fetches.
+    const response = cacheIpc.recv();
+    await cacheIpc.send(message);
+    return response;
+  }
+
+  async function fetchAndStore(
+    key: string,
+    input: any,
+    init: FetchInit | undefined,
+    revalidate: number | null
+  ) {
+    const response = await originalFetch(input, init);
+    if (response.ok) {
+      await cacheIpc.send({
+        type: "fetchCacheSet",
+        key,
+        data: await serializeResponse(response),
+        revalidate,
+      });
+    }
+    return response;
+  }
+
+  async function cassetteFetch(
+    mode: "record" | "replay",
+    key: string,
+    input: any,
+    init: FetchInit | undefined
+  ) {
+    if (mode === "record") {
+      const response = await originalFetch(input, init);
+      await cacheIpc.send({
+        type: "fetchRecord",
+        key,
+        data: await serializeResponse(response),
+      });
+      return response;
+    }
+
+    const { data } = await request({ type: "fetchReplay", key });
+    if (data == null) {
+      throw new Error(
+        `fetch of ${key} was not recorded in the fetch cassette, record it again with TURBOPACK_FETCH_CASSETTE_MODE=record`
+      );
+    }
+    return deserializeResponse(data);
+  }
+
+  const mode = cassetteMode();
+
+  async function cachedFetch(input: any, init?: FetchInit) {
+    const url = typeof input === "string" ? input : String(input?.url ?? input);
+    const method = (init?.method ?? input?.method ?? "GET").toUpperCase();
+    const body = typeof init?.body === "string" ? init.body : null;
+    const key = JSON.stringify([
+      url,
+      method,
+      init?.headers != null ? [...new globalObject.Headers(init.headers)] : [],
+      body,
+    ]);
+
+    if (mode != null) {
+      return cassetteFetch(mode, key, input, init);
+    }
+
+    const revalidate = init?.next?.revalidate;
+    if (
+      method !== "GET" ||
+      init?.cache === "no-store" ||
+      revalidate === 0 ||
+      (init?.body != null && body == null)
+    ) {
+      return originalFetch(input, init);
+    }
+
+    const revalidateSeconds =
+      typeof revalidate === "number" ? revalidate : null;
+
+    const { data, stale } = await request({ type: "fetchCacheGet", key });
+    if (data == null) {
+      return fetchAndStore(key, input, init, revalidateSeconds);
+    }
+    if (stale) {
+      fetchAndStore(key, input, init, revalidateSeconds).catch(() => {
+        // keep serving the stale entry
+      });
+    }
+
+    return deserializeResponse(data);
+  }
+
+  (cachedFetch as any)[PATCHED] = true;
+  globalObject.fetch = cachedFetch;
+}
@@ -2,6 +2,8 @@ import { createConnection } from "node:net";
 import type { StackFrame } from "../compiled/stacktrace-parser";
 import { parse as parseStackTrace } from "../compiled/stacktrace-parser";
 import { getProperError } from "./error";
+import { installFetchCache } from "./fetch-cache";
+import { applyModulesUpdate, onModulesUpdated } from "./update";
 
 export type StructuredError = {
   name: string;
@@ -33,21 +35,56 @@ export type Ipc<TIncoming, TOutgoing> = {
   send(message: TOutgoing): Promise<void>;
   sendError(error: Error): Promise<never>;
   sendReady(): Promise<void>;
+  /**
+   * Aborted when Turbopack cancels the current operation, e.g. because the
+   * browser went away during a render. Pass it to cancelable work like
+   * `renderToReadableStream` or `fetch`, and end the operation as usual, with
+   * an error or the end of the response. It's replaced after a cancellation,
+   * so read it at the start of each operation.
+   */
+  readonly signal: AbortSignal;
+  /**
+   * Registers a listener which is called after Turbopack updated modules of
+   * the process, e.g. after an edit. It should import the modules it uses
+   * again, as the previous instances are outdated. Without a listener,
+   * Turbopack replaces the process on updates instead.
+   */
+  onModulesUpdated(listener: () => void | Promise<void>): void;
 };
 
 function createIpc<TIncoming, TOutgoing>(
   port: number
 ): Ipc<TIncoming, TOutgoing> {
   const socket = createConnection(port, "127.0.0.1");
-  const packetQueue: Buffer[] = [];
+  const packetQueue: TIncoming[] = [];
   const recvPromiseResolveQueue: Array<(message: TIncoming) => void> = [];
+  let abortController = new AbortController();
 
   function pushPacket(packet: Buffer) {
+    const message = JSON.parse(packet.toString("utf8"));
+    // Cancellations are handled out of band, as the operation they cancel
+    // isn't waiting for a message.
+    if (message?.type === "cancel") {
+      abortController.abort();
+      abortController = new AbortController();
+      return;
+    }
+    // Updates are only sent to idle processes and answered right away.
+    if (message?.type === "modulesUpdated") {
+      applyModulesUpdate(message.chunks).then(
+        (applied) => send({ type: "modulesUpdated", applied }),
+        (err) => {
+          console.error("failed to apply modules update:", err);
+          return send({ type: "modulesUpdated", applied: false });
+        }
+      );
+      return;
+    }
     const recvPromiseResolve = recvPromiseResolveQueue.shift();
     if (recvPromiseResolve != null) {
-      recvPromiseResolve(JSON.parse(packet.toString("utf8")) as TIncoming);
+      recvPromiseResolve(message as TIncoming);
     } else {
-      packetQueue.push(packet);
+      packetQueue.push(message as TIncoming);
     }
   }
 
@@ -99,8 +136,8 @@ function createIpc<TIncoming, TOutgoing>(
 
     return new Promise((resolve, reject) => {
       socket.write(packet, (err) => {
-        process.stderr.write(`TURBOPACK_OUTPUT_D\n`);
-        process.stdout.write(`TURBOPACK_OUTPUT_D\n`);
+        process.stderr.write(`${MARKER}D\n`);
+        process.stdout.write(`${MARKER}D\n`);
         if (err != null) {
           reject(err);
         } else {
@@ -114,8 +151,8 @@ function createIpc<TIncoming, TOutgoing>(
     const length = Buffer.from([0, 0, 0, 0]);
     return new Promise((resolve, reject) => {
       socket.write(length, (err) => {
-        process.stderr.write(`TURBOPACK_OUTPUT_D\n`);
-        process.stdout.write(`TURBOPACK_OUTPUT_D\n`);
+        process.stderr.write(`${MARKER}D\n`);
+        process.stdout.write(`${MARKER}D\n`);
         if (err != null) {
           reject(err);
         } else {
@@ -127,9 +164,8 @@ function createIpc<TIncoming, TOutgoing>(
 
   return {
     async recv() {
-      const packet = packetQueue.shift();
-      if (packet != null) {
-        return JSON.parse(packet.toString("utf8")) as TIncoming;
+      if (packetQueue.length > 0) {
+        return packetQueue.shift()!;
       }
 
       const result = await new Promise<TIncoming>((resolve) => {
@@ -147,6 +183,12 @@ function createIpc<TIncoming, TOutgoing>(
 
     sendReady,
 
+    onModulesUpdated,
+
+    get signal() {
+      return abortController.signal;
+    },
+
     async sendError(error: Error): Promise<never> {
       try {
         await send({
@@ -166,8 +208,22 @@ function createIpc<TIncoming, TOutgoing>(
 
 const PORT = process.argv[2];
 
+/**
+ * The prefix of the lines delimiting console calls and operations in stdout
+ * and stderr. It contains a random nonce chosen by Turbopack, so output of
+ * user code can't be mistaken for them.
+ */
+const MARKER = process.argv[3];
+
 export const IPC = createIpc<unknown, unknown>(parseInt(PORT, 10));
 
+// Render processes answer `fetch()` from the fetch cache of Turbopack, or from
+// the fetch cassette. The flag is set by the bootstrap of their bundle, so the
+// patch is installed before the page runtime is evaluated.
+if ((globalThis as any).__turbopack_render__) {
+  installFetchCache(IPC);
+}
+
 process.on("uncaughtException", (err) => {
   IPC.sendError(err);
 });
@@ -179,14 +235,14 @@ const improveConsole = (name: string, stream: string, addStack: boolean) => {
   const stdio = process[stream];
   // @ts-ignore
   console[name] = (...args: any[]) => {
-    stdio.write(`TURBOPACK_OUTPUT_B\n`);
+    stdio.write(`${MARKER}B\n`);
     original(...args);
     if (addStack) {
       const stack = new Error().stack?.replace(/^.+\n.+\n/, "") + "\n";
-      stdio.write("TURBOPACK_OUTPUT_S\n");
+      stdio.write(`${MARKER}S\n`);
       stdio.write(stack);
     }
-    stdio.write("TURBOPACK_OUTPUT_E\n");
+    stdio.write(`${MARKER}E\n`);
   };
 };
 
@@ -0,0 +1,96 @@
+import { createRequire } from "node:module";
+
+type ModuleFactory = (...args: any[]) => unknown;
+
+declare const __turbopack_modules__: Record<string, ModuleFactory>;
+declare const __turbopack_cache__: Record<string, { parents: string[] }>;
+
+// Chunks are loaded with the require of Node.js, whose cache is shared.
+const nodeRequire = createRequire(process.argv[1]);
+
+const listeners: Array<() => void | Promise<void>> = [];
+
+export function onModulesUpdated(listener: () => void | Promise<void>) {
+  listeners.push(listener);
+}
+
+/**
+ * A chunk of modules exports their factories, which were registered in the
+ * runtime when it was loaded. The runtime and entry chunks export something
+ * else.
+ */
+function isModuleChunk(exports: unknown): boolean {
+  if (typeof exports !== "object" || exports === null) {
+    return false;
+  }
+  const entries = Object.entries(exports);
+  return (
+    entries.length > 0 &&
+    entries.every(
+      ([id, factory]) =>
+        typeof factory === "function" && id in __turbopack_modules__
+    )
+  );
+}
+
+/**
+ * Applies a `modulesUpdated` message of Turbopack: loads the changed chunks
+ * again, replaces the factories of changed modules, and removes them and the
+ * modules depending on them from the module cache, so they are instantiated
+ * again when they are imported the next time. Changed chunks which were
+ * never loaded are loaded with their new content when they are needed.
+ *
+ * Returns whether the update was applied. It isn't when a changed chunk is
+ * not a chunk of modules, e.g. the runtime, or when nothing imports the
+ * invalidated modules again, see `Ipc.onModulesUpdated`. Turbopack replaces
+ * the process then.
+ */
+export async function applyModulesUpdate(chunks: string[]): Promise<boolean> {
+  if (listeners.length === 0) {
+    return false;
+  }
+
+  const changed: string[] = [];
+  for (const chunk of chunks) {
+    // The cache is keyed by the real path of the chunk
+    let filename;
+    try {
+      filename = nodeRequire.resolve(chunk);
+    } catch {
+      continue;
+    }
+    const loaded = nodeRequire.cache[filename];
+    if (loaded == null) {
+      continue;
+    }
+    if (!isModuleChunk(loaded.exports)) {
+      return false;
+    }
+    delete nodeRequire.cache[filename];
+    const factories: Record<string, ModuleFactory> = nodeRequire(filename);
+    for (const [id, factory] of Object.entries(factories)) {
+      const previous = __turbopack_modules__[id];
+      if (previous != null && previous.toString() === factory.toString()) {
+        continue;
+      }
+      __turbopack_modules__[id] = factory;
+      changed.push(id);
+    }
+  }
+
+  const queue = changed;
+  while (queue.length > 0) {
+    const id = queue.pop()!;
+    const module = __turbopack_cache__[id];
+    if (module == null) {
+      continue;
+    }
+    delete __turbopack_cache__[id];
+    queue.push(...module.parents);
+  }
+
+  for (const listener of listeners) {
+    await listener();
+  }
+  return true;
+}
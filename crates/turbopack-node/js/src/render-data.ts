@@ -0,0 +1,326 @@
+import type { StructuredError } from "./ipc/index";
+import { structuredError } from "./ipc/index";
+
+/**
+ * The version of the render data contract, must match
+ * `RENDER_PROTOCOL_VERSION` in `turbopack-node/src/render/mod.rs`.
+ *
+ * Page runtimes report it as `protocolVersion` in their first `headers` or
+ * `response` message.
+ */
+export const RENDER_PROTOCOL_VERSION = 8;
+
+type Param = string | string[];
+
+type QueryValue = string | QueryValue[] | { [key: string]: QueryValue };
+
+type HeaderValue = string | number[] | string[] | number[][];
+
+export type RenderData = {
+  protocolVersion: number;
+  params: Record<string, Param>;
+  method: string;
+  url: string;
+  originalUrl: string;
+  query: Record<string, QueryValue>;
+  rawQuery: string;
+  headers: Record<string, HeaderValue>;
+  rawHeaders: [string, string][];
+  cookies: Record<string, string>;
+  locale: string | null;
+  /**
+   * The i18n messages of `locale` by id, or of the default locale when there
+   * is no catalog for it. Only messages referenced by the app are included.
+   */
+  messages: Record<string, unknown> | null;
+  preview: boolean;
+  buildId: string;
+  experimentArms: Record<string, string>;
+  path: string;
+  slowRenderThresholdMs: number | null;
+  /** Whether the page defines the legacy `getInitialProps` method. */
+  getInitialProps: boolean;
+  /** Set when rendering `pages/_error` for a page whose rendering failed. */
+  error: RenderError | null;
+  /**
+   * The request fetches the props of the page as JSON for a client-side
+   * navigation (`/_next/data/<buildId>/<page>.json`). Respond with the JSON
+   * instead of the HTML of the page.
+   */
+  dataRequest: boolean;
+  /**
+   * The result of the data fetching phase of the page, e.g. of its
+   * `getServerSideProps`, which was sent with `sendData`. Render the page
+   * with it instead of fetching its data again. `null` for pages without a
+   * data fetching phase.
+   */
+  data: unknown;
+};
+
+export type RenderError = {
+  statusCode: number;
+  message: string;
+};
+
+const STRING_FIELDS = [
+  "method",
+  "url",
+  "originalUrl",
+  "rawQuery",
+  "buildId",
+  "path",
+] as const;
+
+const OBJECT_FIELDS = [
+  "params",
+  "query",
+  "headers",
+  "cookies",
+  "experimentArms",
+] as const;
+
+/**
+ * A cookie to set, which page runtimes can pass as `cookies` in their
+ * `headers` or `response` message. Turbopack adds a `set-cookie` header for
+ * each of them.
+ */
+export type SetCookie = {
+  name: string;
+  value: string;
+  path?: string;
+  domain?: string;
+  /** In seconds, `0` deletes the cookie. */
+  maxAge?: number;
+  /** An HTTP date. */
+  expires?: string;
+  httpOnly?: boolean;
+  secure?: boolean;
+  sameSite?: "strict" | "lax" | "none";
+};
+
+/**
+ * A segment whose rendering failed and which was rendered with the fallback
+ * of its error boundary, which page runtimes can pass as `erroredSegments` in
+ * their `response` or `bodyEnd` message. The partially rendered page is
+ * served, and Turbopack reports each errored segment as an issue.
+ */
+export type ErroredSegment = {
+  /** Identifies the segment, e.g. `/dashboard/settings`. */
+  segment: string;
+  error: StructuredError;
+};
+
+export function erroredSegment(
+  segment: string,
+  error: unknown
+): ErroredSegment {
+  return { segment, error: structuredError(error as Error) };
+}
+
+/**
+ * A non-fatal problem of a render, e.g. a hydration mismatch, which page
+ * runtimes can pass as `warnings` in their `response` or `bodyEnd` message.
+ * The render isn't failed, and Turbopack reports each warning as an issue
+ * pointing at the project file the stack of `error` was raised in.
+ */
+export type RenderWarning = {
+  /** Defaults to `warning`. */
+  severity?: "warning" | "info";
+  error: StructuredError;
+};
+
+export function renderWarning(
+  warning: string | Error,
+  severity: "warning" | "info" = "warning"
+): RenderWarning {
+  const error = typeof warning === "string" ? new Error(warning) : warning;
+  return { severity, error: structuredError(error) };
+}
+
+/**
+ * Validates the render data sent by Turbopack. Throws when it was sent by a
+ * Turbopack version with another protocol version, instead of rendering
+ * with misinterpreted data.
+ */
+export function validateRenderData(data: unknown): RenderData {
+  if (typeof data !== "object" || data === null) {
+    throw new Error("render data must be an object");
+  }
+  const record = data as Record<string, unknown>;
+  if (record.protocolVersion !== RENDER_PROTOCOL_VERSION) {
+    throw new Error(
+      `Turbopack sent render data with protocol version ${
+        record.protocolVersion ?? "1"
+      }, but this page runtime requires version ${RENDER_PROTOCOL_VERSION}`
+    );
+  }
+  for (const field of STRING_FIELDS) {
+    if (typeof record[field] !== "string") {
+      throw new Error(`render data field \`${field}\` must be a string`);
+    }
+  }
+  for (const field of OBJECT_FIELDS) {
+    if (typeof record[field] !== "object" || record[field] === null) {
+      throw new Error(`render data field \`${field}\` must be an object`);
+    }
+  }
+  if (!Array.isArray(record.rawHeaders)) {
+    throw new Error("render data field `rawHeaders` must be an array");
+  }
+  if (record.locale !== null && typeof record.locale !== "string") {
+    throw new Error("render data field `locale` must be a string or null");
+  }
+  if (
+    record.messages !== null &&
+    (typeof record.messages !== "object" || Array.isArray(record.messages))
+  ) {
+    throw new Error("render data field `messages` must be an object or null");
+  }
+  if (typeof record.preview !== "boolean") {
+    throw new Error("render data field `preview` must be a boolean");
+  }
+  if (typeof record.getInitialProps !== "boolean") {
+    throw new Error("render data field `getInitialProps` must be a boolean");
+  }
+  if (typeof record.dataRequest !== "boolean") {
+    throw new Error("render data field `dataRequest` must be a boolean");
+  }
+  if (record.data === undefined) {
+    throw new Error("render data field `data` is missing");
+  }
+  return record as RenderData;
+}
+
+/**
+ * The resources used by a render, which page runtimes can pass as `usage` in
+ * their `response` or `bodyEnd` message.
+ */
+export type RenderUsage = {
+  cpuMicros: number;
+  peakMemoryBytes: number;
+};
+
+/**
+ * Starts measuring the resources used by a render. Call the returned
+ * function when the render has finished.
+ *
+ * CPU time is measured for the whole process, so it includes concurrent
+ * renders of the same worker. The peak heap usage is sampled.
+ */
+export function measureRenderUsage(): () => RenderUsage {
+  const startCpu = process.cpuUsage();
+  const startHeap = process.memoryUsage().heapUsed;
+  let peakHeap = startHeap;
+  const sample = () => {
+    peakHeap = Math.max(peakHeap, process.memoryUsage().heapUsed);
+  };
+  const interval = setInterval(sample, 10);
+  interval.unref();
+  return () => {
+    clearInterval(interval);
+    sample();
+    const cpu = process.cpuUsage(startCpu);
+    return {
+      cpuMicros: cpu.user + cpu.system,
+      peakMemoryBytes: peakHeap - startHeap,
+    };
+  };
+}
+
+/**
+ * The summarized CPU profile of a slow render, which page runtimes can pass as
+ * `profile` in their `response` or `bodyEnd` message.
+ */
+export type SlowRenderProfile = {
+  hotStacks: { frames: string[]; selfMicros: number }[];
+};
+
+const HOT_STACKS = 10;
+
+/**
+ * Starts a CPU profile of a render when `data` has a slow render threshold.
+ * Call the returned function when the render has finished, it returns the
+ * hot stacks of the profile when the render exceeded the threshold.
+ */
+export async function profileSlowRender(
+  data: RenderData
+): Promise<() => Promise<SlowRenderProfile | undefined>> {
+  const threshold = data.slowRenderThresholdMs;
+  if (threshold == null) {
+    return async () => undefined;
+  }
+  const { Session } = await import("node:inspector");
+  const session = new Session();
+  session.connect();
+  const post = (method: string, params?: object) =>
+    new Promise<any>((resolve, reject) =>
+      session.post(method, params, (err, result) =>
+        err ? reject(err) : resolve(result)
+      )
+    );
+  await post("Profiler.enable");
+  await post("Profiler.setSamplingInterval", { interval: 1000 });
+  await post("Profiler.start");
+  const start = Date.now();
+  return async () => {
+    try {
+      const { profile } = await post("Profiler.stop");
+      if (Date.now() - start <= threshold) {
+        return undefined;
+      }
+      return { hotStacks: hotStacks(profile) };
+    } finally {
+      session.disconnect();
+    }
+  };
+}
+
+type ProfileNode = {
+  id: number;
+  callFrame: {
+    functionName: string;
+    url: string;
+    lineNumber: number;
+    columnNumber: number;
+  };
+  children?: number[];
+};
+
+function hotStacks(profile: {
+  nodes: ProfileNode[];
+  samples: number[];
+  timeDeltas: number[];
+}) {
+  const parents = new Map<number, number>();
+  const nodes = new Map<number, ProfileNode>();
+  for (const node of profile.nodes) {
+    nodes.set(node.id, node);
+    for (const child of node.children ?? []) {
+      parents.set(child, node.id);
+    }
+  }
+  const selfMicros = new Map<number, number>();
+  profile.samples.forEach((id, i) => {
+    selfMicros.set(id, (selfMicros.get(id) ?? 0) + (profile.timeDeltas[i] ?? 0));
+  });
+  return [...selfMicros.entries()]
+    .filter(([id]) => {
+      const name = nodes.get(id)?.callFrame.functionName;
+      return name !== "(idle)" && name !== "(program)";
+    })
+    .sort(([, a], [, b]) => b - a)
+    .slice(0, HOT_STACKS)
+    .map(([id, micros]) => {
+      const frames = [];
+      for (let node = nodes.get(id); node; node = nodes.get(parents.get(node.id)!)) {
+        const { functionName, url, lineNumber, columnNumber } = node.callFrame;
+        if (functionName === "(root)") break;
+        frames.push(
+          `${functionName || "(anonymous)"} (${url}:${lineNumber + 1}:${
+            columnNumber + 1
+          })`
+        );
+      }
+      return { frames, selfMicros: micros };
+    });
+}
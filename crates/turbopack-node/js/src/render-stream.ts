@@ -0,0 +1,119 @@
+import type { Ipc } from "./ipc/index";
+import type {
+  ErroredSegment,
+  RenderUsage,
+  RenderWarning,
+  SetCookie,
+  SlowRenderProfile,
+} from "./render-data";
+import { RENDER_PROTOCOL_VERSION } from "./render-data";
+import type { CollectedStyle } from "./styles";
+
+export type StreamedResponseInit = {
+  statusCode: number;
+  headers: [string, string][];
+  cookies?: SetCookie[];
+  /** Styles collected while rendering the shell. */
+  styles?: CollectedStyle[];
+};
+
+export type StreamedResponseEnd = {
+  usage?: RenderUsage;
+  profile?: SlowRenderProfile;
+  erroredSegments?: ErroredSegment[];
+  warnings?: RenderWarning[];
+};
+
+/**
+ * Streams a rendered page to Turbopack, which flushes each chunk to the
+ * browser as it arrives, e.g. the output of React's
+ * `renderToReadableStream`, or of `renderToPipeableStream` piped into a
+ * `PassThrough`.
+ *
+ * The headers are sent before the first chunk, so they can't depend on
+ * the rest of the body. `end` is called when the body is complete and
+ * returns the data of the `bodyEnd` message.
+ */
+export async function streamResponse(
+  ipc: Ipc<unknown, unknown>,
+  init: StreamedResponseInit,
+  body: AsyncIterable<Uint8Array | string>,
+  end?: () => StreamedResponseEnd | Promise<StreamedResponseEnd>
+): Promise<void> {
+  await ipc.send({
+    type: "headers",
+    data: { status: init.statusCode, headers: init.headers },
+    protocolVersion: RENDER_PROTOCOL_VERSION,
+    cookies: init.cookies ?? [],
+    styles: init.styles ?? [],
+  });
+  const encoder = new TextEncoder();
+  for await (const chunk of body) {
+    const bytes = typeof chunk === "string" ? encoder.encode(chunk) : chunk;
+    if (bytes.length === 0) {
+      continue;
+    }
+    await ipc.send({ type: "bodyChunk", data: toBase64(bytes) });
+  }
+  await ipc.send({ type: "bodyEnd", ...(await end?.()) });
+}
+
+export type ResponseInit = StreamedResponseInit & StreamedResponseEnd;
+
+/**
+ * Sends a complete response to Turbopack, e.g. the JSON of an API route,
+ * an RSS feed, or the PNG of an og-image route. It is served with the
+ * `content-type` of its headers, HTML when there is none.
+ *
+ * Binary bodies are sent base64 encoded, so their bytes are served
+ * unchanged.
+ */
+export async function sendResponse(
+  ipc: Ipc<unknown, unknown>,
+  init: ResponseInit,
+  body: Uint8Array | string
+): Promise<void> {
+  const headers = init.headers.some(
+    ([name]) => name.toLowerCase() === "content-type"
+  )
+    ? init.headers
+    : [...init.headers, ["content-type", "text/html; charset=utf-8"]];
+  await ipc.send({
+    type: "response",
+    statusCode: init.statusCode,
+    headers,
+    body: typeof body === "string" ? body : toBase64(body),
+    bodyEncoding: typeof body === "string" ? "utf8" : "base64",
+    protocolVersion: RENDER_PROTOCOL_VERSION,
+    cookies: init.cookies ?? [],
+    styles: init.styles ?? [],
+    usage: init.usage,
+    profile: init.profile,
+    erroredSegments: init.erroredSegments ?? [],
+    warnings: init.warnings ?? [],
+  });
+}
+
+/**
+ * Sends the result of the data fetching phase of a render, e.g. the result
+ * of `getServerSideProps`, in response to a `dataFetching` message. Its
+ * render data contains the request context, but no `data` yet.
+ *
+ * Turbopack caches the result separately from the render, and passes it as
+ * `data` of the render data to the render of the page.
+ */
+export async function sendData(
+  ipc: Ipc<unknown, unknown>,
+  data: unknown
+): Promise<void> {
+  await ipc.send({
+    type: "data",
+    data: data ?? null,
+    protocolVersion: RENDER_PROTOCOL_VERSION,
+  });
+}
+
+function toBase64(bytes: Uint8Array): string {
+  const buffer = Buffer.from(bytes.buffer, bytes.byteOffset, bytes.byteLength);
+  return buffer.toString("base64");
+}
@@ -0,0 +1,67 @@
+/**
+ * A style sheet collected by a CSS-in-JS library while rendering a page,
+ * which page runtimes can pass as `styles` in their `headers` or `response`
+ * message. Turbopack injects it into the `<head>` of the document.
+ */
+export type CollectedStyle = {
+  /** The `id` of the `<style>` tag, used when hydrating. */
+  id?: string;
+  css: string;
+  nonce?: string;
+};
+
+/**
+ * Collects the styles of one CSS-in-JS library during a render. Emotion or
+ * styled-components integrations implement it with their server-side
+ * rendering APIs.
+ */
+export type StyleCollector = {
+  /** Returns the styles rendered since the last call and forgets them. */
+  flush(): CollectedStyle[];
+};
+
+/**
+ * Returns the styles rendered since the last call by the `collectors` of a
+ * render. Call it after rendering the shell of a streamed page or the whole
+ * page. Create the collectors for each render, so concurrent renders of a
+ * worker don't mix their styles.
+ */
+export function flushStyles(collectors: StyleCollector[]): CollectedStyle[] {
+  return collectors.flatMap((collector) => collector.flush());
+}
+
+/**
+ * The registry of styled-jsx, as created by `createStyleRegistry` from
+ * `styled-jsx`.
+ */
+type StyledJsxRegistry = {
+  styles(options?: { nonce?: string }): {
+    props: {
+      id?: string;
+      nonce?: string;
+      dangerouslySetInnerHTML?: { __html: string };
+    };
+  }[];
+  flush(): void;
+};
+
+/**
+ * A collector of the styles of a styled-jsx registry, which the page runtime
+ * passes to the `StyleRegistry` provider of the rendered tree.
+ */
+export function styledJsxCollector(
+  registry: StyledJsxRegistry,
+  nonce?: string
+): StyleCollector {
+  return {
+    flush() {
+      const styles = registry.styles({ nonce }).map(({ props }) => ({
+        id: props.id,
+        css: props.dangerouslySetInnerHTML?.__html ?? "",
+        nonce: props.nonce,
+      }));
+      registry.flush();
+      return styles;
+    },
+  };
+}
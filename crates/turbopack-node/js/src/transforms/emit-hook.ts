@@ -0,0 +1,50 @@
+// @ts-ignore
+import hookModule from "HOOK";
+import { dirDependency, fileDependency } from "../ipc/dependencies";
+import type { Ipc } from "../ipc/evaluate";
+
+type DerivedAsset = { path: string; content: string | Uint8Array };
+
+type EmitHook = (
+  content: Buffer,
+  context: {
+    path: string;
+    /**
+     * Reports a file read by the hook. The asset is processed again when the
+     * file changes.
+     */
+    trackFileDependency(file: string): Promise<void>;
+    trackDirDependency(dir: string, glob?: string): Promise<void>;
+  }
+) => DerivedAsset[] | Promise<DerivedAsset[]>;
+
+export default async function emitHook(
+  ipc: Ipc<unknown, unknown>,
+  content: string,
+  path: string
+) {
+  const hook: EmitHook | undefined =
+    typeof hookModule === "function" ? hookModule : hookModule?.default;
+  if (typeof hook !== "function") {
+    throw new Error("An emit hook must export a function as default");
+  }
+  const derived = await hook(Buffer.from(content, "base64"), {
+    path,
+    trackFileDependency: (file) => ipc.sendInfo(fileDependency(file)),
+    trackDirDependency: (dir, glob) => ipc.sendInfo(dirDependency(dir, glob)),
+  });
+  if (!Array.isArray(derived)) {
+    throw new Error(
+      `An emit hook must return an array of assets, but returned ${typeof derived}`
+    );
+  }
+  return derived.map((asset) => {
+    if (typeof asset?.path !== "string") {
+      throw new Error("The assets returned by an emit hook must have a path");
+    }
+    return {
+      path: asset.path,
+      content: Buffer.from(asset.content).toString("base64"),
+    };
+  });
+}
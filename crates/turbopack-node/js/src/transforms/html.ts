@@ -0,0 +1,42 @@
+// @ts-ignore
+import transformModule from "TRANSFORM";
+import { dirDependency, fileDependency } from "../ipc/dependencies";
+import type { Ipc } from "../ipc/evaluate";
+
+type HtmlTransform = (
+  html: string,
+  context: {
+    pathname: string;
+    /**
+     * Reports a file read by the transform. It runs again when the file
+     * changes.
+     */
+    trackFileDependency(file: string): Promise<void>;
+    trackDirDependency(dir: string, glob?: string): Promise<void>;
+  }
+) => string | Promise<string>;
+
+export default async function transform(
+  ipc: Ipc<unknown, unknown>,
+  html: string,
+  pathname: string
+) {
+  const transform: HtmlTransform | undefined =
+    typeof transformModule === "function"
+      ? transformModule
+      : transformModule?.default;
+  if (typeof transform !== "function") {
+    throw new Error("An HTML transform must export a function as default");
+  }
+  const result = await transform(html, {
+    pathname,
+    trackFileDependency: (file) => ipc.sendInfo(fileDependency(file)),
+    trackDirDependency: (dir, glob) => ipc.sendInfo(dirDependency(dir, glob)),
+  });
+  if (typeof result !== "string") {
+    throw new Error(
+      `An HTML transform must return a string, but returned ${typeof result}`
+    );
+  }
+  return result;
+}
@@ -180,6 +180,12 @@ const transform = (
     const loadersWithOptions = loaders.map((loader) =>
       typeof loader === "string" ? { loader, options: {} } : loader
     );
+    // Results which depend on more than the content and the file dependencies,
+    // e. g. on resolving or on directories, can't be cached by their content.
+    let cacheable = true;
+    const markUncacheable = () => {
+      cacheable = false;
+    };
 
     runLoaders(
       {
@@ -199,6 +205,7 @@ const transform = (
               : {};
           },
           getResolve: (options: ResolveOptions) => {
+            markUncacheable();
             const rustOptions = {
               noAlias: false,
               aliasFields: undefined as undefined | string[],
@@ -330,8 +337,8 @@ const transform = (
               }
             };
           },
-          emitWarning: makeErrorEmitter("warning", ipc),
-          emitError: makeErrorEmitter("error", ipc),
+          emitWarning: makeErrorEmitter("warning", ipc, markUncacheable),
+          emitError: makeErrorEmitter("error", ipc, markUncacheable),
           getLogger(name: unknown) {
             const logger = (type: unknown, args: unknown) => {
               let trace;
@@ -402,6 +409,11 @@ const transform = (
               : typeof map === "object"
               ? JSON.stringify(map)
               : undefined,
+          cacheable:
+            cacheable &&
+            result.cacheable &&
+            result.contextDependencies.length === 0,
+          fileDependencies: result.fileDependencies.map(toPath),
         });
       }
     );
@@ -412,9 +424,11 @@ export { transform as default };
 
 function makeErrorEmitter(
   severity: "warning" | "error",
-  ipc: Ipc<IpcInfoMessage, IpcRequestMessage>
+  ipc: Ipc<IpcInfoMessage, IpcRequestMessage>,
+  onEmit: () => void
 ) {
   return function (error: Error | string) {
+    onEmit();
     ipc.sendInfo({
       type: "emittedError",
       severity: severity,
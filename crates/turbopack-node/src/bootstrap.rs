@@ -1,7 +1,8 @@
 use std::fmt::Write;
 
 use anyhow::Result;
-use turbo_tasks::{Value, Vc};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{trace::TraceRawVcs, Value, Vc};
 use turbo_tasks_fs::{File, FileSystemPath};
 use turbopack_core::{
     asset::{Asset, AssetContent},
@@ -13,11 +14,56 @@ use turbopack_core::{
 };
 use turbopack_ecmascript::utils::StringifyJs;
 
+/// Customizes the bootstrap of an intermediate bundle, which loads its
+/// chunks, see [crate::get_intermediate_asset_with_template].
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+pub struct BootstrapTemplate {
+    /// Code run before the chunks are loaded, e. g. polyfills, the setup of an
+    /// `AsyncLocalStorage` request context or `unhandledRejection` handlers.
+    pub prelude: Vec<String>,
+    /// Code run after the chunks were loaded.
+    pub epilogue: Vec<String>,
+    pub format: BootstrapFormat,
+}
+
+#[turbo_tasks::value_impl]
+impl BootstrapTemplate {
+    #[turbo_tasks::function]
+    pub fn default() -> Vc<Self> {
+        Self::cell(Default::default())
+    }
+}
+
+/// The module format of the bootstrap. The chunks are CommonJS in both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs)]
+pub enum BootstrapFormat {
+    /// A `.js` file requiring the chunks.
+    #[default]
+    CommonJs,
+    /// A `.mjs` file importing the chunks in order, e. g. for a prelude using
+    /// ESM-only packages. It can only run from a disk filesystem.
+    Esm,
+}
+
+impl BootstrapFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            BootstrapFormat::CommonJs => ".js",
+            BootstrapFormat::Esm => ".mjs",
+        }
+    }
+}
+
 #[turbo_tasks::value(shared)]
 pub(super) struct NodeJsBootstrapAsset {
     pub(super) path: Vc<FileSystemPath>,
     pub(super) chunking_context: Vc<Box<dyn ChunkingContext>>,
     pub(super) evaluatable_assets: Vc<EvaluatableAssets>,
+    pub(super) template: Vc<BootstrapTemplate>,
+    /// Whether the bootstrap starts a render process, whose `fetch()` is
+    /// answered from the fetch cache, see `ipc/fetch-cache.ts`.
+    pub(super) render: bool,
 }
 
 #[turbo_tasks::function]
@@ -53,20 +99,41 @@ impl Asset for NodeJsBootstrapAsset {
     #[turbo_tasks::function]
     async fn content(&self) -> Result<Vc<AssetContent>> {
         let context_path = self.path.parent().await?;
+        let template = self.template.await?;
 
         // TODO(sokra) We need to have a chunk format for node.js
         // but until then this is a simple hack to make it work for now
         let mut output = "Error.stackTraceLimit = 100;\nglobal.self = global;\n".to_string();
+        if self.render {
+            // Read by `ipc/index.ts` when the chunks are loaded, before the page runtime
+            // runs
+            writeln!(&mut output, "global.__turbopack_render__ = true;")?;
+        }
+        for prelude in &template.prelude {
+            writeln!(&mut output, "{prelude}")?;
+        }
 
         for chunk in self.chunks().await?.iter() {
             let path = &*chunk.ident().path().await?;
             if let Some(p) = context_path.get_relative_path_to(path) {
                 if p.ends_with(".js") {
-                    writeln!(&mut output, "require({});", StringifyJs(&p))?;
+                    match template.format {
+                        BootstrapFormat::CommonJs => {
+                            writeln!(&mut output, "require({});", StringifyJs(&p))?
+                        }
+                        // Imports are hoisted above the prelude, dynamic imports run in order
+                        BootstrapFormat::Esm => {
+                            writeln!(&mut output, "await import({});", StringifyJs(&p))?
+                        }
+                    }
                 }
             }
         }
 
+        for epilogue in &template.epilogue {
+            writeln!(&mut output, "{epilogue}")?;
+        }
+
         Ok(AssetContent::file(File::from(output).into()))
     }
 }
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use serde::Deserialize;
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{glob::Glob, FileSystemPath};
+
+/// A file read by the JavaScript code of a job which turbo-tasks can't see,
+/// e. g. a template or the translations of a page, reported with the helpers
+/// of `js/src/ipc/dependencies.ts`. Paths are relative to the working
+/// directory of the pool.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub(crate) enum DependencyMessage {
+    FileDependency {
+        path: String,
+    },
+    /// The files matching `glob` in a directory.
+    DirDependency {
+        path: String,
+        glob: String,
+    },
+}
+
+impl DependencyMessage {
+    /// Makes the reported files dependencies of the current task, so it
+    /// runs again when they change.
+    pub(crate) async fn track(self, cwd: Vc<FileSystemPath>) -> Result<()> {
+        match self {
+            DependencyMessage::FileDependency { path } => track_file_dependency(cwd, path).await,
+            DependencyMessage::DirDependency { path, glob } => {
+                track_dir_dependency(cwd, path, glob).await
+            }
+        }
+    }
+}
+
+/// Makes the file at `path`, relative to `cwd`, a dependency of the current
+/// task.
+// TODO We might miss some changes that happened while the job was running
+pub(crate) async fn track_file_dependency(cwd: Vc<FileSystemPath>, path: String) -> Result<()> {
+    cwd.join(path).track().await?;
+    Ok(())
+}
+
+/// Makes the files matching `glob` in the directory at `path`, relative to
+/// `cwd`, dependencies of the current task.
+pub(crate) async fn track_dir_dependency(
+    cwd: Vc<FileSystemPath>,
+    path: String,
+    glob: String,
+) -> Result<()> {
+    cwd.join(path).track_glob(Glob::new(glob), false).await?;
+    Ok(())
+}
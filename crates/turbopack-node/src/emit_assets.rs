@@ -0,0 +1,106 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use turbo_tasks::{TryJoinIterExt, Vc};
+use turbo_tasks_fs::{FileContent, FileSystemPath};
+use turbo_tasks_hash::hash_xxh3_hash64;
+use turbopack_core::{
+    asset::Asset,
+    issue::{Issue, IssueExt, IssueStage, OptionStyledString, StyledString},
+    output::OutputAsset,
+};
+
+use crate::path::node_sys_path;
+
+/// How many assets are written concurrently.
+const EMIT_BATCH_SIZE: usize = 64;
+
+/// The hash of the content this process last wrote to each file.
+static EMITTED: Lazy<Mutex<HashMap<PathBuf, u64>>> = Lazy::new(Default::default);
+
+/// Writes `assets` concurrently, in batches of [EMIT_BATCH_SIZE].
+///
+/// A file which couldn't be written is reported as an issue, the other
+/// assets are still written.
+pub(crate) async fn emit_assets(
+    assets: impl IntoIterator<Item = Vc<Box<dyn OutputAsset>>>,
+) -> Result<()> {
+    let assets = assets.into_iter().collect::<Vec<_>>();
+    for batch in assets.chunks(EMIT_BATCH_SIZE) {
+        let failures = batch
+            .iter()
+            .map(|&asset| async move {
+                Ok(emit_asset(asset)
+                    .await
+                    .err()
+                    .map(|err| (asset.ident().path(), err)))
+            })
+            .try_join()
+            .await?;
+        for (path, err) in failures.into_iter().flatten() {
+            EmitFailedIssue {
+                path,
+                error: format!("{err:#}"),
+            }
+            .cell()
+            .emit();
+        }
+    }
+    Ok(())
+}
+
+/// Writes `asset`, unless it has the content this process last wrote to its
+/// file, which is known from the hash of the content without reading the file
+/// to compare it. Files modified by other tools are written again by
+/// [crate::integrity::restore_emitted_assets].
+async fn emit_asset(asset: Vc<Box<dyn OutputAsset>>) -> Result<()> {
+    let path = asset.ident().path();
+    let content = asset.content();
+    let hash = match &*content.file_content().await? {
+        FileContent::Content(file) => Some(hash_xxh3_hash64(file.content())),
+        FileContent::NotFound => None,
+    };
+    let sys_path = node_sys_path(path).await?;
+    if let (Some(hash), Some(sys_path)) = (hash, &sys_path) {
+        if EMITTED.lock().get(sys_path) == Some(&hash) && sys_path.exists() {
+            return Ok(());
+        }
+    }
+    content.write(path).await?;
+    if let (Some(hash), Some(sys_path)) = (hash, sys_path) {
+        EMITTED.lock().insert(sys_path, hash);
+    }
+    Ok(())
+}
+
+/// A file of an intermediate bundle which couldn't be written.
+#[turbo_tasks::value(shared)]
+struct EmitFailedIssue {
+    path: Vc<FileSystemPath>,
+    error: String,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for EmitFailedIssue {
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text("Writing a file of the intermediate bundle failed".to_string()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::CodeGen.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(StyledString::Text(self.error.clone()).cell()))
+    }
+}
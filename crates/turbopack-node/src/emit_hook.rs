@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use indexmap::indexmap;
+use serde::Deserialize;
+use turbo_tasks::{Completion, Value, Vc};
+use turbo_tasks_bytes::stream::SingleValue;
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fs::{json::parse_json_with_source_context, File, FileContent, FileSystemPath};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    chunk::ChunkingContext,
+    context::AssetContext,
+    emit_hook::EmitHook,
+    file_source::FileSource,
+    output::{OutputAsset, OutputAssets},
+    reference_type::{EntryReferenceSubType, ReferenceType},
+    virtual_output::VirtualOutputAsset,
+    virtual_source::VirtualSource,
+};
+
+use crate::{embed_js::embed_file, evaluate::evaluate};
+
+/// An asset derived by a [NodeJsEmitHook], with base64 encoded content. Its
+/// path is relative to the directory of the processed asset.
+#[derive(Deserialize)]
+struct DerivedAsset {
+    path: String,
+    content: String,
+}
+
+/// An [EmitHook] implemented in JavaScript and run in the Node.js process
+/// pool, e. g. to run `wasm-opt` or an image optimizer. The module's default
+/// export is called with the content of the asset as `Buffer` and
+/// `{ path, trackFileDependency, trackDirDependency }`, with the file name of
+/// the asset and functions reporting the files the hook reads. It returns the
+/// derived assets as `{ path, content }`, or a promise of them.
+#[turbo_tasks::value]
+pub struct NodeJsEmitHook {
+    module_path: Vc<FileSystemPath>,
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    evaluate_context: Vc<Box<dyn AssetContext>>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+}
+
+#[turbo_tasks::value_impl]
+impl NodeJsEmitHook {
+    #[turbo_tasks::function]
+    pub fn new(
+        module_path: Vc<FileSystemPath>,
+        cwd: Vc<FileSystemPath>,
+        env: Vc<Box<dyn ProcessEnv>>,
+        evaluate_context: Vc<Box<dyn AssetContext>>,
+        chunking_context: Vc<Box<dyn ChunkingContext>>,
+    ) -> Vc<Self> {
+        NodeJsEmitHook {
+            module_path,
+            cwd,
+            env,
+            evaluate_context,
+            chunking_context,
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl EmitHook for NodeJsEmitHook {
+    #[turbo_tasks::function]
+    async fn process(&self, asset: Vc<Box<dyn OutputAsset>>) -> Result<Vc<OutputAssets>> {
+        let file_content = asset.content().file_content().await?;
+        let FileContent::Content(file) = &*file_content else {
+            return Ok(OutputAssets::empty());
+        };
+        let path = asset.ident().path();
+
+        let hook_module = self
+            .evaluate_context
+            .process(
+                Vc::upcast(FileSource::new(self.module_path)),
+                Value::new(ReferenceType::Entry(EntryReferenceSubType::Undefined)),
+            )
+            .module();
+        let executor = self
+            .evaluate_context
+            .process(
+                Vc::upcast(VirtualSource::new(
+                    self.module_path.join("emit-hook.ts".to_string()),
+                    AssetContent::File(embed_file("transforms/emit-hook.ts".to_string())).cell(),
+                )),
+                Value::new(ReferenceType::Internal(Vc::cell(indexmap! {
+                    "HOOK".to_string() => hook_module
+                }))),
+            )
+            .module();
+
+        let result = evaluate(
+            executor,
+            self.cwd,
+            self.env,
+            asset.ident(),
+            self.evaluate_context,
+            self.chunking_context,
+            None,
+            vec![
+                Vc::cell(BASE64.encode(file.content().to_bytes()?).into()),
+                Vc::cell(path.await?.file_name().into()),
+            ],
+            Completion::immutable(),
+            false,
+        )
+        .await?;
+        let SingleValue::Single(value) = result.try_into_single().await? else {
+            // An error happened, which has already been converted into an issue.
+            return Ok(OutputAssets::empty());
+        };
+        let derived: Vec<DerivedAsset> = parse_json_with_source_context(value.to_str()?)
+            .context("emit hooks must return an array of assets")?;
+
+        let dir = path.parent();
+        Ok(Vc::cell(
+            derived
+                .into_iter()
+                .map(|derived| {
+                    let content = BASE64
+                        .decode(derived.content)
+                        .context("decoding the content of a derived asset")?;
+                    Ok(Vc::upcast(VirtualOutputAsset::new(
+                        dir.join(derived.path),
+                        AssetContent::file(File::from(content).into()),
+                    )))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        ))
+    }
+}
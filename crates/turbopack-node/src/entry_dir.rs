@@ -0,0 +1,169 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use turbo_tasks::{ValueToString, Vc};
+use turbo_tasks_fs::FileSystemPath;
+use turbo_tasks_hash::{encode_hex, Xxh3Hash64Hasher};
+use turbopack_core::{
+    chunk::{EvaluatableAsset, EvaluatableAssets},
+    module::Module,
+    output::OutputAsset,
+};
+
+use crate::path::node_sys_path;
+
+/// The prefix of the directories entries are isolated in, see
+/// [isolated_entry_path].
+const ENTRY_DIR_PREFIX: &str = "entry-";
+
+/// The file in an entry directory whose modification time is renewed while a
+/// process uses the directory.
+const LEASE_FILE_NAME: &str = ".lease";
+
+/// How often the leases of the entry directories of this process are renewed.
+const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How long after its last renewal an entry directory is considered stale.
+/// A multiple of [LEASE_RENEW_INTERVAL], so a late renewal doesn't lose it.
+const LEASE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// Moves the bootstrap asset at `path` into a directory of its own, named by
+/// the hash of the entry module and the runtime entries.
+///
+/// The bootstrap asset and its chunk group are named after the entry module
+/// only. Renders of a page with other runtime entries, e. g. the error page
+/// rendered for different pages, would otherwise overwrite each other's
+/// files while both pools are running.
+#[turbo_tasks::function]
+pub(crate) async fn isolated_entry_path(
+    path: Vc<FileSystemPath>,
+    main_entry: Vc<Box<dyn EvaluatableAsset>>,
+    other_entries: Vc<EvaluatableAssets>,
+) -> Result<Vc<FileSystemPath>> {
+    let mut hasher = Xxh3Hash64Hasher::new();
+    hasher.write_value(&*main_entry.ident().to_string().await?);
+    for entry in other_entries.await?.iter() {
+        hasher.write_value(&*entry.ident().to_string().await?);
+    }
+    let file_name = path.await?.file_name().to_string();
+    Ok(path.parent().join(format!(
+        "{ENTRY_DIR_PREFIX}{}/{file_name}",
+        encode_hex(hasher.finish())
+    )))
+}
+
+#[derive(Default)]
+struct EntryDirs {
+    /// The entry directories of the renderer pools of this process.
+    live: HashSet<PathBuf>,
+    /// The directories whose stale entry directories were removed.
+    collected: HashSet<PathBuf>,
+    /// Whether the leases of the live entry directories are being renewed.
+    renewing: bool,
+}
+
+static ENTRY_DIRS: Lazy<Mutex<EntryDirs>> = Lazy::new(Default::default);
+
+/// Marks the entry directory of `intermediate_asset` as used, see
+/// [isolated_entry_path]. Must be called before emitting the asset.
+///
+/// The first time an entry directory of a parent directory is claimed, the
+/// entry directories left there by previous processes are removed, except
+/// for the ones claimed so far. Other processes, e. g. a second dev server
+/// with the same output directory, renew the lease of the directories they
+/// use, so only directories whose lease expired are removed. Directories
+/// which became stale while the process is running, e. g. when the runtime
+/// entries changed, are kept until the next restart, as another pool might
+/// still be using them.
+pub(crate) async fn claim_entry_dir(intermediate_asset: Vc<Box<dyn OutputAsset>>) -> Result<()> {
+    let Some(path) = node_sys_path(intermediate_asset.ident().path()).await? else {
+        return Ok(());
+    };
+    let Some(entry_dir) = path.parent().filter(|dir| is_entry_dir(dir)) else {
+        return Ok(());
+    };
+    let Some(parent) = entry_dir.parent() else {
+        return Ok(());
+    };
+    // The lock is held while removing, so that no directory is claimed and
+    // emitted in the meantime.
+    let mut entry_dirs = ENTRY_DIRS.lock();
+    if entry_dirs.live.insert(entry_dir.to_path_buf()) {
+        renew_lease(entry_dir);
+    }
+    if !entry_dirs.renewing {
+        entry_dirs.renewing = true;
+        tokio::spawn(renew_leases());
+    }
+    if entry_dirs.collected.insert(parent.to_path_buf()) {
+        remove_stale_entry_dirs(parent, &entry_dirs.live);
+    }
+    Ok(())
+}
+
+/// Renews the leases of the entry directories of this process every
+/// [LEASE_RENEW_INTERVAL], for as long as the process runs.
+async fn renew_leases() {
+    let mut interval = tokio::time::interval(LEASE_RENEW_INTERVAL);
+    // The first tick completes immediately, while the leases were just renewed
+    // by claiming the directories.
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        let live = ENTRY_DIRS.lock().live.clone();
+        for entry_dir in live {
+            renew_lease(&entry_dir);
+        }
+    }
+}
+
+/// Renewing is best effort, a failure only makes the directory look stale to
+/// other processes.
+fn renew_lease(entry_dir: &Path) {
+    let _ = std::fs::create_dir_all(entry_dir)
+        .and_then(|_| std::fs::write(entry_dir.join(LEASE_FILE_NAME), b""));
+}
+
+/// Whether another process renewed the lease of `entry_dir` recently.
+/// Directories without a lease are stale.
+fn is_leased(entry_dir: &Path) -> bool {
+    std::fs::metadata(entry_dir.join(LEASE_FILE_NAME))
+        .and_then(|metadata| metadata.modified())
+        .map_or(false, |modified| {
+            SystemTime::now()
+                .duration_since(modified)
+                .map_or(true, |age| age < LEASE_DURATION)
+        })
+}
+
+fn is_entry_dir(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map_or(false, |name| name.starts_with(ENTRY_DIR_PREFIX))
+}
+
+/// Removing stale directories is best effort, failures only leave them on
+/// disk.
+fn remove_stale_entry_dirs(parent: &Path, live: &HashSet<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(parent) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if is_entry_dir(&path)
+            && !live.contains(&path)
+            && entry
+                .file_type()
+                .map_or(false, |file_type| file_type.is_dir())
+            && !is_leased(&path)
+        {
+            let _ = std::fs::remove_dir_all(&path);
+        }
+    }
+}
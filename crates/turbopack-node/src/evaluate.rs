@@ -1,4 +1,4 @@
-use std::{borrow::Cow, ops::ControlFlow, thread::available_parallelism, time::Duration};
+use std::{borrow::Cow, ops::ControlFlow, time::Duration};
 
 use anyhow::{anyhow, bail, Result};
 use async_stream::try_stream as generator;
@@ -18,7 +18,7 @@ use turbo_tasks::{
 };
 use turbo_tasks_bytes::{Bytes, Stream};
 use turbo_tasks_env::ProcessEnv;
-use turbo_tasks_fs::{to_sys_path, File, FileSystemPath};
+use turbo_tasks_fs::{File, FileSystemPath};
 use turbopack_core::{
     asset::AssetContent,
     chunk::{ChunkingContext, EvaluatableAsset, EvaluatableAssets},
@@ -33,10 +33,14 @@ use turbopack_core::{
 };
 
 use crate::{
-    bootstrap::NodeJsBootstrapAsset,
+    bootstrap::{BootstrapTemplate, NodeJsBootstrapAsset},
+    dependencies::DependencyMessage,
     embed_js::embed_file_path,
-    emit, emit_package_json, internal_assets_for_source_mapping,
-    pool::{FormattingMode, NodeJsOperation, NodeJsPool},
+    emit, emit_package_json,
+    integrity::restore_emitted_assets,
+    internal_assets_for_source_mapping,
+    path::node_sys_path,
+    pool::{FormattingMode, NodeJsOperation, NodeJsPool, NodeJsPoolOptions},
     source_map::StructuredError,
     AssetsForSourceMapping,
 };
@@ -130,7 +134,8 @@ pub async fn get_evaluate_pool(
         bail!("Internal module is not evaluatable");
     };
 
-    let (Some(cwd), Some(entrypoint)) = (to_sys_path(cwd).await?, to_sys_path(path).await?) else {
+    let (Some(cwd), Some(entrypoint)) = (node_sys_path(cwd).await?, node_sys_path(path).await?)
+    else {
         panic!("can only evaluate from a disk filesystem");
     };
 
@@ -163,6 +168,8 @@ pub async fn get_evaluate_pool(
             path,
             chunking_context,
             evaluatable_assets: runtime_entries.with_entry(entry_module),
+            template: BootstrapTemplate::default(),
+            render: false,
         }
         .cell(),
     );
@@ -173,6 +180,7 @@ pub async fn get_evaluate_pool(
     let assets_for_source_mapping = internal_assets_for_source_mapping(bootstrap, output_root);
     emit_package.await?;
     emit.await?;
+    restore_emitted_assets(bootstrap, output_root).await?;
     let pool = NodeJsPool::new(
         cwd,
         entrypoint,
@@ -184,7 +192,7 @@ pub async fn get_evaluate_pool(
         assets_for_source_mapping,
         output_root,
         chunking_context.context_path().root(),
-        available_parallelism().map_or(1, |v| v.get()),
+        NodeJsPoolOptions::default(),
         debug,
     );
     additional_invalidation.await?;
@@ -471,7 +479,7 @@ struct BasicEvaluateContext {
 
 #[async_trait]
 impl EvaluateContext for BasicEvaluateContext {
-    type InfoMessage = ();
+    type InfoMessage = DependencyMessage;
     type RequestMessage = ();
     type ResponseMessage = ();
 
@@ -517,8 +525,8 @@ impl EvaluateContext for BasicEvaluateContext {
         Ok(())
     }
 
-    async fn info(&self, _data: Self::InfoMessage, _pool: &NodeJsPool) -> Result<()> {
-        bail!("BasicEvaluateContext does not support info messages")
+    async fn info(&self, data: Self::InfoMessage, _pool: &NodeJsPool) -> Result<()> {
+        data.track(self.cwd).await
     }
 
     async fn request(
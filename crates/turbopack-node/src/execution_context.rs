@@ -4,11 +4,15 @@ use turbo_tasks_env::ProcessEnv;
 use turbo_tasks_fs::FileSystemPath;
 use turbopack_core::chunk::ChunkingContext;
 
+use crate::transforms::cache::{transform_cache, TransformCache};
+
 #[turbo_tasks::value]
 pub struct ExecutionContext {
     pub project_path: Vc<FileSystemPath>,
     pub chunking_context: Vc<Box<dyn ChunkingContext>>,
     pub env: Vc<Box<dyn ProcessEnv>>,
+    /// Caches the results of transforms running in Node.js by their input.
+    pub transform_cache: Vc<TransformCache>,
 }
 
 #[turbo_tasks::value_impl]
@@ -23,10 +27,28 @@ impl ExecutionContext {
             project_path,
             chunking_context,
             env,
+            transform_cache: transform_cache(None),
         }
         .cell()
     }
 
+    /// The execution context with another transform cache, e. g. one which
+    /// persists results on disk.
+    #[turbo_tasks::function]
+    pub async fn with_transform_cache(
+        self: Vc<Self>,
+        transform_cache: Vc<TransformCache>,
+    ) -> Result<Vc<Self>> {
+        let this = self.await?;
+        Ok(ExecutionContext {
+            project_path: this.project_path,
+            chunking_context: this.chunking_context,
+            env: this.env,
+            transform_cache,
+        }
+        .cell())
+    }
+
     #[turbo_tasks::function]
     pub async fn project_path(self: Vc<Self>) -> Result<Vc<FileSystemPath>> {
         Ok(self.await?.project_path)
@@ -0,0 +1,190 @@
+use anyhow::Result;
+use turbo_tasks::{TryJoinIterExt, Value, Vc};
+use turbopack_dev_server::source::ContentSourceData;
+
+use crate::node_entry::{NodeEntry, NodeRenderingEntries, NodeRenderingEntry};
+
+/// Describes how the flavor of a request is selected.
+#[turbo_tasks::value(serialization = "auto_for_input")]
+#[derive(Hash, Debug, Clone)]
+pub enum FlavorSelector {
+    /// The value of a request header, e. g. `x-tenant`.
+    Header(String),
+    /// The host of the request without the port.
+    Host,
+    /// The value of a query parameter.
+    Query(String),
+}
+
+impl FlavorSelector {
+    /// Returns the flavor name of a request. `data` must contain the raw
+    /// headers and the raw query.
+    fn select<'a>(&self, data: &'a ContentSourceData) -> Option<&'a str> {
+        let header = |name: &str| {
+            data.raw_headers.as_ref().and_then(|headers| {
+                headers
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| value.as_str())
+            })
+        };
+        match self {
+            FlavorSelector::Header(name) => header(name),
+            FlavorSelector::Host => {
+                header("host").map(|host| host.rsplit_once(':').map_or(host, |(host, _)| host))
+            }
+            FlavorSelector::Query(name) => data.raw_query.as_deref().and_then(|query| {
+                query
+                    .split('&')
+                    .find_map(|pair| match pair.split_once('=') {
+                        Some((key, value)) if key == name => Some(value),
+                        _ => None,
+                    })
+            }),
+        }
+    }
+}
+
+/// A [NodeEntry] that renders a different entry depending on the flavor of
+/// the request, e. g. for white-label multi-tenant apps.
+///
+/// Each flavor entry is usually created from an asset context with its own
+/// import map, which swaps the resolution of specific modules (a theme
+/// package or a tenant config module), so turbo-tasks caches the module graph
+/// and chunk groups of every flavor separately. Flavors also get their own
+/// intermediate output directory and thus their own Node.js pool. Requests
+/// that don't select a known flavor render the `default` entry.
+#[turbo_tasks::value(shared)]
+pub struct FlavoredNodeEntry {
+    selector: FlavorSelector,
+    default: Vc<Box<dyn NodeEntry>>,
+    flavors: Vec<(String, Vc<Box<dyn NodeEntry>>)>,
+}
+
+#[turbo_tasks::value_impl]
+impl FlavoredNodeEntry {
+    #[turbo_tasks::function]
+    pub fn new(
+        selector: Value<FlavorSelector>,
+        default: Vc<Box<dyn NodeEntry>>,
+        flavors: Vec<(String, Vc<Box<dyn NodeEntry>>)>,
+    ) -> Vc<Self> {
+        FlavoredNodeEntry {
+            selector: selector.into_value(),
+            default,
+            flavors,
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl NodeEntry for FlavoredNodeEntry {
+    #[turbo_tasks::function]
+    fn entry(&self, data: Value<ContentSourceData>) -> Vc<NodeRenderingEntry> {
+        let flavor = self
+            .selector
+            .select(&data)
+            .and_then(|name| self.flavors.iter().find(|(flavor, _)| flavor == name));
+        match flavor {
+            Some((name, entry)) => flavored_entry(entry.entry(data), name.clone()),
+            None => self.default.entry(data),
+        }
+    }
+
+    #[turbo_tasks::function]
+    async fn entries(&self) -> Result<Vc<NodeRenderingEntries>> {
+        let mut entries = self.default.entries().await?.clone_value();
+        let flavors = self
+            .flavors
+            .iter()
+            .map(|(name, entry)| async move {
+                Ok(entry
+                    .entries()
+                    .await?
+                    .iter()
+                    .map(|&entry| flavored_entry(entry, name.clone()))
+                    .collect::<Vec<_>>())
+            })
+            .try_join()
+            .await?;
+        entries.extend(flavors.into_iter().flatten());
+        Ok(Vc::cell(entries))
+    }
+}
+
+/// Moves the intermediate output of an entry into a directory for the flavor.
+#[turbo_tasks::function]
+async fn flavored_entry(
+    entry: Vc<NodeRenderingEntry>,
+    flavor: String,
+) -> Result<Vc<NodeRenderingEntry>> {
+    let entry = entry.await?;
+    Ok(NodeRenderingEntry {
+        runtime_entries: entry.runtime_entries,
+        module: entry.module,
+        chunking_context: entry.chunking_context,
+        intermediate_output_path: entry
+            .intermediate_output_path
+            .join(format!("flavors/{flavor}")),
+        output_root: entry.output_root,
+        project_dir: entry.project_dir,
+    }
+    .cell())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(headers: &[(&str, &str)], query: Option<&str>) -> ContentSourceData {
+        ContentSourceData {
+            raw_headers: Some(
+                headers
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            ),
+            raw_query: query.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn select_by_header() {
+        let selector = FlavorSelector::Header("x-tenant".to_string());
+        assert_eq!(
+            selector.select(&data(&[("X-Tenant", "acme")], None)),
+            Some("acme")
+        );
+        assert_eq!(selector.select(&data(&[("x-other", "acme")], None)), None);
+        assert_eq!(selector.select(&ContentSourceData::default()), None);
+    }
+
+    #[test]
+    fn select_by_host_without_port() {
+        let selector = FlavorSelector::Host;
+        assert_eq!(
+            selector.select(&data(&[("host", "acme.localhost:3000")], None)),
+            Some("acme.localhost")
+        );
+        assert_eq!(
+            selector.select(&data(&[("Host", "acme.example.com")], None)),
+            Some("acme.example.com")
+        );
+    }
+
+    #[test]
+    fn select_by_query() {
+        let selector = FlavorSelector::Query("tenant".to_string());
+        assert_eq!(
+            selector.select(&data(&[], Some("page=2&tenant=acme"))),
+            Some("acme")
+        );
+        assert_eq!(
+            selector.select(&data(&[], Some("tenants=acme&tenant"))),
+            None
+        );
+        assert_eq!(selector.select(&data(&[], None)), None);
+    }
+}
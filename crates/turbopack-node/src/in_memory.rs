@@ -0,0 +1,82 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Serialize;
+use turbo_tasks::{TryJoinIterExt, Vc};
+use turbo_tasks_fs::{FileContent, FileSystemPath};
+use turbopack_core::{asset::Asset, output::OutputAsset};
+
+use crate::internal_assets;
+
+/// Runs an [InMemoryEntry] with `node -e`, see `js/src/in-memory-loader.js`.
+pub(crate) const IN_MEMORY_LOADER: &str = include_str!("../js/src/in-memory-loader.js");
+
+/// The directory the paths of in-memory files are made up in. Nothing is
+/// written there, it only gives them absolute paths Node.js resolves
+/// relative requires from.
+const IN_MEMORY_DIR: &str = "turbopack-in-memory";
+
+/// The files of an entrypoint which isn't on disk, e. g. because its output
+/// root is a virtual file system. They are written to the stdin of each
+/// process, which loads them without touching the disk.
+#[derive(Serialize, Debug)]
+pub(crate) struct InMemoryEntry {
+    /// The made-up absolute path of the entrypoint.
+    pub entry: PathBuf,
+    /// The base64 encoded files of the "internal" subgraph by their made-up
+    /// absolute path.
+    files: HashMap<String, String>,
+}
+
+impl InMemoryEntry {
+    /// Collects the "internal" assets of `intermediate_asset`, see
+    /// [internal_assets]. Unlike assets on disk, they're not updated in
+    /// running processes, the pool is recreated when they change.
+    pub(crate) async fn new(
+        intermediate_asset: Vc<Box<dyn OutputAsset>>,
+        output_root: Vc<FileSystemPath>,
+    ) -> Result<Self> {
+        let root = std::env::temp_dir().join(IN_MEMORY_DIR);
+        let output_root_value = output_root.await?;
+        let entry_path = intermediate_asset.ident().path().await?;
+        let entry = output_root_value
+            .get_path_to(&entry_path)
+            .map(|entry| root.join(entry))
+            .context("the entrypoint must be inside of the output root")?;
+        if entry
+            .extension()
+            .is_some_and(|extension| extension == "mjs")
+        {
+            // The in-memory loader only hooks into `require`
+            bail!("ESM bootstraps can only run from a disk filesystem");
+        }
+        let files = internal_assets(intermediate_asset, output_root)
+            .await?
+            .iter()
+            .map(|asset| {
+                let root = &root;
+                let output_root_value = &output_root_value;
+                async move {
+                    let path = asset.ident().path().await?;
+                    let Some(relative) = output_root_value.get_path_to(&path) else {
+                        return Ok(None);
+                    };
+                    let file_content = asset.content().file_content().await?;
+                    let FileContent::Content(file) = &*file_content else {
+                        return Ok(None);
+                    };
+                    Ok(Some((
+                        root.join(relative).to_string_lossy().into_owned(),
+                        BASE64.encode(file.content().to_bytes()?),
+                    )))
+                }
+            })
+            .try_join()
+            .await?
+            .into_iter()
+            .flatten()
+            .collect();
+        Ok(InMemoryEntry { entry, files })
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{Context, Result};
+use turbo_tasks::{TryJoinIterExt, Vc};
+use turbo_tasks_fs::{FileContent, FileSystemPath};
+use turbopack_core::{
+    asset::Asset,
+    issue::{Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString},
+    output::OutputAsset,
+};
+
+use crate::{internal_assets, path::node_sys_path};
+
+/// Verifies that the "internal" assets of `intermediate_asset` are on disk
+/// with the content they were emitted with, and writes missing or modified
+/// ones again.
+///
+/// The emit tasks don't notice when another tool cleans or modifies the
+/// output directory, e. g. a `rm -rf .next` while the dev server is running.
+/// The processes of the pool would fail with confusing `MODULE_NOT_FOUND`
+/// errors then.
+pub(crate) async fn restore_emitted_assets(
+    intermediate_asset: Vc<Box<dyn OutputAsset>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+) -> Result<()> {
+    let restored = internal_assets(intermediate_asset, intermediate_output_path)
+        .await?
+        .iter()
+        .map(|asset| async move {
+            let Some(path) = node_sys_path(asset.ident().path()).await? else {
+                return Ok(None);
+            };
+            let file_content = asset.content().file_content().await?;
+            let FileContent::Content(file) = &*file_content else {
+                return Ok(None);
+            };
+            let content = file.content().to_bytes()?;
+            if let Ok(on_disk) = tokio::fs::read(&path).await {
+                if on_disk == *content {
+                    return Ok(None);
+                }
+            }
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .with_context(|| format!("creating {}", parent.display()))?;
+            }
+            tokio::fs::write(&path, &*content)
+                .await
+                .with_context(|| format!("restoring {}", path.display()))?;
+            Ok(Some(path.display().to_string()))
+        })
+        .try_join()
+        .await?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    if !restored.is_empty() {
+        RestoredAssetsIssue {
+            file_path: intermediate_asset.ident().path(),
+            restored,
+        }
+        .cell()
+        .emit();
+    }
+    Ok(())
+}
+
+/// Files of an intermediate bundle which were removed or modified on disk
+/// since they were emitted, and were written again.
+#[turbo_tasks::value(shared)]
+struct RestoredAssetsIssue {
+    file_path: Vc<FileSystemPath>,
+    restored: Vec<String>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for RestoredAssetsIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Warning.into()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(
+            "Files of the intermediate bundle were removed or modified by another tool".to_string(),
+        )
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::CodeGen.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(
+                "They were written again. Avoid cleaning the output directory while Turbopack is \
+                 running."
+                    .to_string(),
+            )
+            .cell(),
+        ))
+    }
+
+    #[turbo_tasks::function]
+    fn detail(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Stack(
+                self.restored
+                    .iter()
+                    .map(|path| StyledString::Text(path.clone()))
+                    .collect(),
+            )
+            .cell(),
+        ))
+    }
+}
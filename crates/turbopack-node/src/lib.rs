@@ -4,17 +4,21 @@
 #![feature(arbitrary_self_types)]
 #![feature(extract_if)]
 
-use std::{collections::HashMap, iter::once, thread::available_parallelism};
+use std::{collections::HashMap, iter::once, path::PathBuf};
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use indexmap::IndexSet;
 pub use node_entry::{NodeEntry, NodeRenderingEntries, NodeRenderingEntry};
+pub use pool::{
+    failed_process_bootups, kill_all_processes, JsRuntime, NodeJsPoolOptions, ScaleUpPolicy,
+};
 use turbo_tasks::{
     graph::{AdjacencyMap, GraphTraversal},
-    Completion, Completions, TryJoinIterExt, ValueToString, Vc,
+    Completion, TryJoinIterExt, ValueToString, Vc,
 };
 use turbo_tasks_env::ProcessEnv;
-use turbo_tasks_fs::{to_sys_path, File, FileSystemPath};
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbo_tasks_hash::hash_xxh3_hash64;
 use turbopack_core::{
     asset::{Asset, AssetContent},
     chunk::{ChunkingContext, EvaluatableAsset, EvaluatableAssets},
@@ -24,34 +28,55 @@ use turbopack_core::{
     virtual_output::VirtualOutputAsset,
 };
 
-use self::{bootstrap::NodeJsBootstrapAsset, pool::NodeJsPool, source_map::StructuredError};
+use self::{
+    bootstrap::{BootstrapTemplate, NodeJsBootstrapAsset},
+    emit_assets::emit_assets,
+    entry_dir::{claim_entry_dir, isolated_entry_path},
+    in_memory::InMemoryEntry,
+    integrity::restore_emitted_assets,
+    native_addons::is_native_binary,
+    path::{node_sys_path, path_inside},
+    pool::NodeJsPool,
+    source_map::StructuredError,
+};
 
 pub mod bootstrap;
 pub mod debug;
+mod dependencies;
 pub mod embed_js;
+mod emit_assets;
+pub mod emit_hook;
+mod entry_dir;
 pub mod evaluate;
 pub mod execution_context;
+pub mod flavor;
+mod in_memory;
+mod integrity;
+pub mod native_addons;
+pub mod nft_json;
 mod node_entry;
+mod path;
 mod pool;
 pub mod render;
 pub mod route_matcher;
 pub mod source_map;
 pub mod transforms;
 
+/// Writes the "internal" assets of `intermediate_asset`, see [emit_assets].
 #[turbo_tasks::function]
 async fn emit(
     intermediate_asset: Vc<Box<dyn OutputAsset>>,
     intermediate_output_path: Vc<FileSystemPath>,
 ) -> Result<Vc<Completion>> {
-    Ok(Vc::<Completions>::cell(
+    emit_assets(
         internal_assets(intermediate_asset, intermediate_output_path)
             .strongly_consistent()
             .await?
             .iter()
-            .map(|a| a.content().write(a.ident().path()))
-            .collect(),
+            .copied(),
     )
-    .completed())
+    .await?;
+    Ok(Completion::new())
 }
 
 /// List of the all assets of the "internal" subgraph and a list of boundary
@@ -96,7 +121,7 @@ async fn internal_assets_for_source_mapping(
         if let Some(generate_source_map) =
             Vc::try_resolve_sidecast::<Box<dyn GenerateSourceMap>>(*asset).await?
         {
-            if let Some(path) = intermediate_output_path.get_path_to(&*asset.ident().path().await?)
+            if let Some(path) = path_inside(&*asset.ident().path().await?, intermediate_output_path)
             {
                 internal_assets_for_source_mapping.insert(path.to_string(), generate_source_map);
             }
@@ -106,7 +131,7 @@ async fn internal_assets_for_source_mapping(
 }
 
 /// Returns a set of "external" assets on the boundary of the "internal"
-/// subgraph
+/// subgraph. [nft_json::entrypoint_nft_json] lists them for deployment.
 #[turbo_tasks::function]
 pub async fn external_asset_entrypoints(
     module: Vc<Box<dyn EvaluatableAsset>>,
@@ -150,12 +175,11 @@ async fn separate_assets(
                 // Assets within the output directory are considered as "internal" and all
                 // others as "external". We follow references on "internal" assets, but do not
                 // look into references of "external" assets, since there are no "internal"
-                // assets behind "externals"
-                if asset
-                    .ident()
-                    .path()
-                    .await?
-                    .is_inside_ref(intermediate_output_path)
+                // assets behind "externals". Native binaries are always "external", they are
+                // loaded from their original location.
+                let path = asset.ident().path().await?;
+                if path_inside(&path, intermediate_output_path).is_some()
+                    && !is_native_binary(&path)
                 {
                     Ok(Type::Internal(*asset))
                 } else {
@@ -208,6 +232,16 @@ fn emit_package_json(dir: Vc<FileSystemPath>) -> Vc<Completion> {
 }
 
 /// Creates a node.js renderer pool for an entrypoint.
+///
+/// The processes only get the variables of `env`, e. g. the dotenv files and
+/// prefixed process env of `turbopack_env::dotenv::load_env_with_prefixes`.
+/// The pool is recreated when they change. When only chunks of the
+/// entrypoint change, the processes of the previous pool are kept and load
+/// the changed chunks instead, see [NodeJsPool::new_or_updated].
+///
+/// When the output root is not on disk, e. g. a virtual file system in tests,
+/// nothing is emitted. The processes load the files from memory instead, see
+/// [InMemoryEntry]. Stack traces of their errors are not source mapped then.
 #[turbo_tasks::function]
 pub async fn get_renderer_pool(
     cwd: Vc<FileSystemPath>,
@@ -216,59 +250,127 @@ pub async fn get_renderer_pool(
     intermediate_output_path: Vc<FileSystemPath>,
     output_root: Vc<FileSystemPath>,
     project_dir: Vc<FileSystemPath>,
+    pool_options: NodeJsPoolOptions,
     debug: bool,
 ) -> Result<Vc<NodeJsPool>> {
-    emit_package_json(intermediate_output_path).await?;
-
-    let emit = emit(intermediate_asset, output_root);
     let assets_for_source_mapping =
         internal_assets_for_source_mapping(intermediate_asset, output_root);
+    let env = env
+        .read_all()
+        .await?
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
 
     let entrypoint = intermediate_asset.ident().path();
-
-    let Some(cwd) = to_sys_path(cwd).await? else {
+    let Some(entrypoint) = node_sys_path(entrypoint).await? else {
+        let cwd = match node_sys_path(cwd).await? {
+            Some(cwd) => cwd,
+            None => std::env::current_dir().context("getting the current directory")?,
+        };
+        return Ok(NodeJsPool::new_in_memory(
+            cwd,
+            InMemoryEntry::new(intermediate_asset, output_root).await?,
+            env,
+            assets_for_source_mapping,
+            output_root,
+            project_dir,
+            pool_options,
+            debug,
+        )
+        .cell());
+    };
+    let Some(cwd) = node_sys_path(cwd).await? else {
         bail!(
             "can only render from a disk filesystem, but `cwd = {}`",
             cwd.to_string().await?
         );
     };
-    let Some(entrypoint) = to_sys_path(entrypoint).await? else {
-        bail!(
-            "can only render from a disk filesystem, but `entrypoint = {}`",
-            entrypoint.to_string().await?
-        );
-    };
 
-    emit.await?;
-    Ok(NodeJsPool::new(
+    emit_package_json(intermediate_output_path).await?;
+    claim_entry_dir(intermediate_asset).await?;
+    emit(intermediate_asset, output_root).await?;
+    restore_emitted_assets(intermediate_asset, output_root).await?;
+    Ok(NodeJsPool::new_or_updated(
         cwd,
         entrypoint,
-        env.read_all()
-            .await?
-            .iter()
-            .map(|(k, v)| (k.clone(), v.clone()))
-            .collect(),
+        env,
+        chunk_hashes(intermediate_asset, output_root).await?,
         assets_for_source_mapping,
         output_root,
         project_dir,
-        available_parallelism().map_or(1, |v| v.get()),
+        pool_options,
         debug,
     )
     .cell())
 }
 
-/// Converts a module graph into node.js executable assets
+/// The hashes of the emitted JavaScript files of the "internal" subgraph, by
+/// their path on disk.
+async fn chunk_hashes(
+    intermediate_asset: Vc<Box<dyn OutputAsset>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+) -> Result<HashMap<PathBuf, u64>> {
+    Ok(
+        internal_assets(intermediate_asset, intermediate_output_path)
+            .await?
+            .iter()
+            .map(|asset| async move {
+                let Some(path) = node_sys_path(asset.ident().path()).await? else {
+                    return Ok(None);
+                };
+                if path.extension().map_or(true, |extension| extension != "js") {
+                    return Ok(None);
+                }
+                let content = asset.content().file_content().await?;
+                Ok(Some((path, hash_xxh3_hash64(&*content))))
+            })
+            .try_join()
+            .await?
+            .into_iter()
+            .flatten()
+            .collect(),
+    )
+}
+
+/// Converts a module graph into node.js executable assets. The bootstrap
+/// asset is placed in a directory of its own, see [isolated_entry_path].
+#[turbo_tasks::function]
+pub fn get_intermediate_asset(
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    main_entry: Vc<Box<dyn EvaluatableAsset>>,
+    other_entries: Vc<EvaluatableAssets>,
+) -> Vc<Box<dyn OutputAsset>> {
+    get_intermediate_asset_with_template(
+        chunking_context,
+        main_entry,
+        other_entries,
+        BootstrapTemplate::default(),
+    )
+}
+
+/// Like [get_intermediate_asset], with a bootstrap customized by `template`,
+/// e. g. with a prelude setting up a request context, or as an ES module.
+/// The intermediate asset can be rendered with [get_renderer_pool].
 #[turbo_tasks::function]
-pub async fn get_intermediate_asset(
+pub async fn get_intermediate_asset_with_template(
     chunking_context: Vc<Box<dyn ChunkingContext>>,
     main_entry: Vc<Box<dyn EvaluatableAsset>>,
     other_entries: Vc<EvaluatableAssets>,
+    template: Vc<BootstrapTemplate>,
 ) -> Result<Vc<Box<dyn OutputAsset>>> {
+    let extension = template.await?.format.extension();
     Ok(Vc::upcast(
         NodeJsBootstrapAsset {
-            path: chunking_context.chunk_path(main_entry.ident(), ".js".to_string()),
+            path: isolated_entry_path(
+                chunking_context.chunk_path(main_entry.ident(), extension.to_string()),
+                main_entry,
+                other_entries,
+            ),
             chunking_context,
             evaluatable_assets: other_entries.with_entry(main_entry),
+            template,
+            render: true,
         }
         .cell(),
     ))
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use turbo_tasks::{Value, Vc};
+use turbo_tasks_fs::{glob::Glob, FileSystemPath};
+use turbopack_core::{
+    reference_type::ReferenceType,
+    resolve::{
+        parse::Request,
+        plugin::{ResolvePlugin, ResolvePluginCondition},
+        ExternalType, ResolveResult, ResolveResultItem, ResolveResultOption,
+    },
+};
+
+use crate::path::node_sys_path;
+
+/// Extensions of native binaries, i. e. Node.js addons and the shared
+/// libraries they load. They can't be placed in chunks.
+const NATIVE_EXTENSIONS: [&str; 4] = ["node", "so", "dylib", "dll"];
+
+/// Whether `path` is a native binary, which is loaded from its original
+/// location instead of being copied into the intermediate output.
+pub(crate) fn is_native_binary(path: &FileSystemPath) -> bool {
+    path.extension_ref()
+        .is_some_and(|extension| NATIVE_EXTENSIONS.contains(&extension))
+}
+
+/// Resolves requests of Node.js addons (`.node` files) to externals, which
+/// `require` the addon from its original path.
+///
+/// Addons are binaries which can't be placed in chunks, and they load shared
+/// libraries relative to their own location, so they can't be copied either.
+/// Addons on a filesystem without a path on disk are resolved as usual.
+#[turbo_tasks::value]
+pub struct NativeAddonResolvePlugin {
+    root: Vc<FileSystemPath>,
+}
+
+#[turbo_tasks::value_impl]
+impl NativeAddonResolvePlugin {
+    #[turbo_tasks::function]
+    pub fn new(root: Vc<FileSystemPath>) -> Vc<Self> {
+        NativeAddonResolvePlugin { root }.cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl ResolvePlugin for NativeAddonResolvePlugin {
+    #[turbo_tasks::function]
+    fn after_resolve_condition(&self) -> Vc<ResolvePluginCondition> {
+        ResolvePluginCondition::new(self.root, Glob::new("**/*.node".to_string()))
+    }
+
+    #[turbo_tasks::function]
+    async fn after_resolve(
+        &self,
+        fs_path: Vc<FileSystemPath>,
+        _lookup_path: Vc<FileSystemPath>,
+        _reference_type: Value<ReferenceType>,
+        _request: Vc<Request>,
+    ) -> Result<Vc<ResolveResultOption>> {
+        let Some(sys_path) = node_sys_path(fs_path).await? else {
+            return Ok(ResolveResultOption::none());
+        };
+        Ok(ResolveResultOption::some(
+            ResolveResult::primary(ResolveResultItem::External(
+                sys_path.to_string_lossy().into_owned(),
+                ExternalType::CommonJs,
+            ))
+            .cell(),
+        ))
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::Result;
+use serde_json::json;
+use turbo_tasks::{
+    graph::{AdjacencyMap, GraphTraversal},
+    Completion, Vc,
+};
+use turbo_tasks_fs::{File, FileSystemPath};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    chunk::{ChunkingContext, EvaluatableAsset, EvaluatableAssets},
+    ident::AssetIdent,
+    output::OutputAsset,
+};
+
+use crate::{get_intermediate_asset, separate_assets};
+
+/// A `.nft.json` manifest next to the intermediate asset of a rendered
+/// entrypoint, in the format of `@vercel/nft`. It lists the files the
+/// entrypoint needs at runtime, relative to the manifest: the chunks of the
+/// entrypoint, and the external assets they reference, transitively.
+/// Deployment tooling copies these files together with the entrypoint.
+///
+/// Files on another filesystem than the intermediate asset can't be expressed
+/// as relative paths and are left out.
+#[turbo_tasks::value(shared)]
+pub struct NftJsonAsset {
+    intermediate_asset: Vc<Box<dyn OutputAsset>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+}
+
+#[turbo_tasks::value_impl]
+impl NftJsonAsset {
+    #[turbo_tasks::function]
+    pub fn new(
+        intermediate_asset: Vc<Box<dyn OutputAsset>>,
+        intermediate_output_path: Vc<FileSystemPath>,
+    ) -> Vc<Self> {
+        NftJsonAsset {
+            intermediate_asset,
+            intermediate_output_path,
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl OutputAsset for NftJsonAsset {
+    #[turbo_tasks::function]
+    async fn ident(&self) -> Result<Vc<AssetIdent>> {
+        let path = self.intermediate_asset.ident().path().await?;
+        Ok(AssetIdent::from_path(
+            path.fs.root().join(format!("{}.nft.json", path.path)),
+        ))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Asset for NftJsonAsset {
+    #[turbo_tasks::function]
+    async fn content(&self) -> Result<Vc<AssetContent>> {
+        let entry_path = self.intermediate_asset.ident().path();
+        let parent_dir = entry_path.parent().await?;
+        let entry_path = &*entry_path.await?;
+        let separated = separate_assets(self.intermediate_asset, self.intermediate_output_path)
+            .strongly_consistent()
+            .await?;
+
+        // External assets can reference further assets, which are needed too
+        let external_assets = AdjacencyMap::new()
+            .skip_duplicates()
+            .visit(
+                separated
+                    .external_asset_entrypoints
+                    .await?
+                    .iter()
+                    .copied()
+                    .collect::<Vec<_>>(),
+                |asset: Vc<Box<dyn OutputAsset>>| async move {
+                    Ok(asset.references().await?.clone_value())
+                },
+            )
+            .await
+            .completed()?
+            .into_inner()
+            .into_reverse_topological()
+            .collect::<Vec<_>>();
+
+        let mut files = Vec::new();
+        for asset in separated
+            .internal_assets
+            .await?
+            .iter()
+            .chain(external_assets.iter())
+        {
+            let path = asset.ident().path().await?;
+            if *path == *entry_path {
+                continue;
+            }
+            if let Some(relative_path) = parent_dir.get_relative_path_to(&path) {
+                files.push(relative_path);
+            }
+        }
+        files.sort();
+        files.dedup();
+
+        let json = json!({
+          "version": 1,
+          "files": files
+        });
+
+        Ok(AssetContent::file(File::from(json.to_string()).into()))
+    }
+}
+
+/// The `.nft.json` manifest of a rendered entrypoint, see [NftJsonAsset].
+#[turbo_tasks::function]
+pub fn entrypoint_nft_json(
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+) -> Vc<Box<dyn OutputAsset>> {
+    Vc::upcast(NftJsonAsset::new(
+        get_intermediate_asset(chunking_context, module, runtime_entries),
+        intermediate_output_path,
+    ))
+}
+
+/// Writes the `.nft.json` manifest of a rendered entrypoint next to its
+/// intermediate asset, see [NftJsonAsset].
+#[turbo_tasks::function]
+pub fn emit_entrypoint_nft_json(
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+) -> Vc<Completion> {
+    let asset = entrypoint_nft_json(
+        module,
+        runtime_entries,
+        chunking_context,
+        intermediate_output_path,
+    );
+    asset.content().write(asset.ident().path())
+}
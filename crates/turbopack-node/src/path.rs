@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{to_sys_path, FileSystemPath};
+
+/// Returns the path of `path` relative to `dir` when it's inside of it.
+///
+/// File systems on Windows are case-insensitive, so there `dir` matches
+/// regardless of case, e. g. when the output directory was configured as
+/// `.Next` but the chunks are emitted into `.next`.
+pub(crate) fn path_inside<'a>(path: &'a FileSystemPath, dir: &FileSystemPath) -> Option<&'a str> {
+    if !cfg!(windows) {
+        return dir
+            .get_path_to(path)
+            .filter(|relative| !relative.is_empty());
+    }
+    if path.fs != dir.fs {
+        return None;
+    }
+    if dir.path.is_empty() {
+        return Some(path.path.as_str()).filter(|path| !path.is_empty());
+    }
+    let (prefix, rest) = (
+        path.path.as_bytes().get(..dir.path.len())?,
+        path.path.get(dir.path.len()..)?,
+    );
+    if prefix.eq_ignore_ascii_case(dir.path.as_bytes()) {
+        rest.strip_prefix('/').filter(|rest| !rest.is_empty())
+    } else {
+        None
+    }
+}
+
+/// Like [to_sys_path], but in a form Node.js resolves modules from.
+///
+/// Paths of long directories on Windows keep their verbatim prefix
+/// (`\\?\C:\...`). Node.js handles long paths itself, but doesn't resolve
+/// relative requires of modules loaded from verbatim paths, so the prefix is
+/// removed.
+pub(crate) async fn node_sys_path(path: Vc<FileSystemPath>) -> Result<Option<PathBuf>> {
+    Ok(to_sys_path(path).await?.map(strip_verbatim_prefix))
+}
+
+#[cfg(windows)]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path;
+    };
+    let root = match prefix.kind() {
+        Prefix::VerbatimDisk(drive) => format!("{}:\\", drive as char),
+        Prefix::VerbatimUNC(server, share) => format!(
+            "\\\\{}\\{}\\",
+            server.to_string_lossy(),
+            share.to_string_lossy()
+        ),
+        _ => return path,
+    };
+    let rest = components.as_path();
+    PathBuf::from(root).join(rest.strip_prefix("\\").unwrap_or(rest))
+}
+
+#[cfg(not(windows))]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+    path
+}
@@ -7,16 +7,23 @@ use std::{
     mem::take,
     path::{Path, PathBuf},
     process::{ExitStatus, Stdio},
-    sync::Arc,
-    time::{Duration, Instant},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Weak,
+    },
+    thread::available_parallelism,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::{bail, Context, Result};
 use futures::join;
-use indexmap::IndexSet;
+use indexmap::{IndexMap, IndexSet};
+use once_cell::sync::Lazy;
 use owo_colors::{OwoColorize, Style};
 use parking_lot::Mutex;
-use serde::{de::DeserializeOwned, Serialize};
+use rand::Rng;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
 use tokio::{
     io::{
         stderr, stdout, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
@@ -26,13 +33,21 @@ use tokio::{
     process::{Child, ChildStderr, ChildStdout, Command},
     select,
     sync::{OwnedSemaphorePermit, Semaphore},
-    time::{sleep, timeout},
+    time::{sleep, timeout, timeout_at},
 };
-use turbo_tasks::{duration_span, Vc};
+use turbo_tasks::{duration_span, trace::TraceRawVcs, TaskInput, Vc};
 use turbo_tasks_fs::{json::parse_json_with_source_context, FileSystemPath};
+use turbopack_core::issue::{Issue, IssueExt, IssueStage, OptionStyledString, StyledString};
+use turbopack_dev_server::server_logs::{
+    has_server_log_subscribers, publish_server_log, ServerLog, ServerLogLevel,
+};
 use turbopack_ecmascript::magic_identifier::unmangle_identifiers;
 
-use crate::{source_map::apply_source_mapping, AssetsForSourceMapping};
+use crate::{
+    in_memory::{InMemoryEntry, IN_MEMORY_LOADER},
+    source_map::apply_source_mapping,
+    AssetsForSourceMapping,
+};
 
 #[derive(Clone, Copy)]
 pub enum FormattingMode {
@@ -68,16 +83,38 @@ impl FormattingMode {
 }
 
 struct NodeJsPoolProcess {
+    /// Identifies the process for session affinity.
+    id: u64,
     child: Option<Child>,
+    // This is used for drop
+    #[allow(dead_code)]
+    running: RunningProcess,
     connection: TcpStream,
     assets_for_source_mapping: Vc<AssetsForSourceMapping>,
     assets_root: Vc<FileSystemPath>,
     project_dir: Vc<FileSystemPath>,
     stdout_handler: OutputStreamHandler<ChildStdout, Stdout>,
     stderr_handler: OutputStreamHandler<ChildStderr, Stderr>,
+    /// When the process last finished an operation, see
+    /// [NodeJsPoolOptions::idle_timeout_ms].
+    idle_since: Instant,
+    /// The [ModuleUpdates::generation] the modules of the process are at.
+    generation: u64,
+    /// The render the current operation is, see
+    /// [NodeJsOperation::forward_output].
+    forwarded_output: Option<ForwardedOutput>,
     debug: bool,
 }
 
+/// Identifies the render whose console output is forwarded as [ServerLog]s.
+#[derive(Clone)]
+struct ForwardedOutput {
+    /// The pathname of the rendered page.
+    page: String,
+    /// Tells apart the output of concurrent renders of the same page.
+    operation: u64,
+}
+
 impl NodeJsPoolProcess {
     pub async fn apply_source_mapping<'a>(
         &self,
@@ -113,6 +150,10 @@ impl NodeJsPoolProcess {
 
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// How long to wait for a process to exit after its connection broke, to tell
+/// whether it crashed.
+const CRASH_EXIT_TIMEOUT: Duration = Duration::from_millis(100);
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 struct OutputEntry {
     data: Arc<[u8]>,
@@ -122,16 +163,85 @@ struct OutputEntry {
 type SharedOutputSet = Arc<Mutex<IndexSet<(OutputEntry, u32)>>>;
 
 static GLOBAL_OUTPUT_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
-static MARKER: &[u8] = b"TURBOPACK_OUTPUT_";
-static MARKER_STR: &str = "TURBOPACK_OUTPUT_";
+
+/// The pids of all running Node.js processes, see [kill_all_processes].
+static RUNNING_PROCESSES: Mutex<Vec<u32>> = parking_lot::const_mutex(Vec::new());
+
+/// Kills the Node.js processes of all pools, e. g. before exiting on a signal.
+/// Processes are otherwise only killed when they are dropped, which doesn't
+/// happen when the process exits.
+pub fn kill_all_processes() {
+    for pid in RUNNING_PROCESSES.lock().drain(..) {
+        kill(pid);
+    }
+}
+
+#[cfg(unix)]
+fn kill(pid: u32) {
+    // SAFETY: kill doesn't access memory of this process
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill(pid: u32) {
+    let _ = std::process::Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .output();
+}
+
+/// Registers a running process in [RUNNING_PROCESSES] until it's dropped.
+struct RunningProcess(Option<u32>);
+
+impl RunningProcess {
+    fn new(child: &Child) -> Self {
+        let pid = child.id();
+        if let Some(pid) = pid {
+            RUNNING_PROCESSES.lock().push(pid);
+        }
+        RunningProcess(pid)
+    }
+}
+
+impl Drop for RunningProcess {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            RUNNING_PROCESSES.lock().retain(|running| *running != pid);
+        }
+    }
+}
+
+/// The number of Node.js processes which failed to boot up since the last one
+/// which booted up successfully, in any pool.
+static FAILED_BOOTUPS: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of Node.js processes which failed to boot up in a row, e. g.
+/// because `node` is missing or the renderer entry throws on import. Servers
+/// report themselves as not ready while this isn't zero.
+pub fn failed_process_bootups() -> usize {
+    FAILED_BOOTUPS.load(Ordering::Relaxed)
+}
+
+/// Creates the prefix of the lines which the Node.js process writes to
+/// stdout and stderr to delimit console calls and operations. It contains a
+/// random nonce, so output of user code can't be mistaken for these lines.
+fn output_marker() -> Arc<str> {
+    let nonce: u64 = rand::thread_rng().gen();
+    format!("TURBOPACK_OUTPUT_{nonce:016x}_").into()
+}
 
 struct OutputStreamHandler<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> {
     stream: BufReader<R>,
+    /// See [output_marker].
+    marker: Arc<str>,
     shared: SharedOutputSet,
     assets_for_source_mapping: Vc<AssetsForSourceMapping>,
     root: Vc<FileSystemPath>,
     project_dir: Vc<FileSystemPath>,
     final_stream: W,
+    /// The level of the output when it's forwarded to the browser.
+    level: ServerLogLevel,
 }
 
 impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> OutputStreamHandler<R, W> {
@@ -139,14 +249,19 @@ impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> OutputStreamHandler<R, W> {
     /// lines that has beem emitted by other [OutputStreamHandler] instances
     /// with the same `shared` before.
     /// Returns when one operation is done.
-    pub async fn handle_operation(&mut self) -> Result<()> {
+    ///
+    /// When the operation is a render of a page, the output is also forwarded
+    /// to the browsers viewing the page.
+    async fn handle_operation(&mut self, forwarded_output: Option<&ForwardedOutput>) -> Result<()> {
         let Self {
             stream,
+            marker,
             shared,
             assets_for_source_mapping,
             root,
             project_dir,
             final_stream,
+            level,
         } = self;
 
         async fn write_final<W: AsyncWrite + Unpin>(
@@ -202,6 +317,53 @@ impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> OutputStreamHandler<R, W> {
             Ok(())
         }
 
+        async fn forward_to_browser(
+            bytes: &[u8],
+            forwarded_output: Option<&ForwardedOutput>,
+            level: ServerLogLevel,
+            assets_for_source_mapping: Vc<AssetsForSourceMapping>,
+            root: Vc<FileSystemPath>,
+            project_dir: Vc<FileSystemPath>,
+        ) {
+            let Some(forwarded_output) = forwarded_output else {
+                return;
+            };
+            if !has_server_log_subscribers() {
+                return;
+            }
+            let Ok(text) = std::str::from_utf8(bytes) else {
+                return;
+            };
+            let text = unmangle_identifiers(text, |content| {
+                FormattingMode::Plain.magic_identifier(content).to_string()
+            });
+            let message = match apply_source_mapping(
+                text.as_ref(),
+                assets_for_source_mapping,
+                root,
+                project_dir,
+                FormattingMode::Plain,
+            )
+            .await
+            {
+                Ok(mapped) => mapped.trim_end().to_string(),
+                // The terminal output reports the error already.
+                Err(_) => text.trim_end().to_string(),
+            };
+            if message.is_empty() {
+                return;
+            }
+            publish_server_log(ServerLog {
+                page: forwarded_output.page.clone(),
+                operation: forwarded_output.operation,
+                timestamp: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_millis() as u64),
+                level,
+                message,
+            });
+        }
+
         let mut buffer = Vec::new();
         let mut own_output = HashMap::new();
         let mut nesting: u32 = 0;
@@ -217,8 +379,8 @@ impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> OutputStreamHandler<R, W> {
             {
                 bail!("stream closed unexpectedly")
             }
-            if buffer.len() - start == MARKER.len() + 2
-                && &buffer[start..buffer.len() - 2] == MARKER
+            if buffer.len() - start == marker.len() + 2
+                && &buffer[start..buffer.len() - 2] == marker.as_bytes()
             {
                 // This is new line
                 buffer.pop();
@@ -273,6 +435,15 @@ impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> OutputStreamHandler<R, W> {
                                 final_stream,
                             )
                             .await?;
+                            forward_to_browser(
+                                &entry.data,
+                                forwarded_output,
+                                *level,
+                                *assets_for_source_mapping,
+                                *root,
+                                *project_dir,
+                            )
+                            .await;
                         }
                     }
                     Some(b'S') => {
@@ -300,6 +471,15 @@ impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> OutputStreamHandler<R, W> {
                 final_stream,
             )
             .await?;
+            forward_to_browser(
+                &buffer,
+                forwarded_output,
+                *level,
+                *assets_for_source_mapping,
+                *root,
+                *project_dir,
+            )
+            .await;
             buffer.clear();
         }
         Ok(())
@@ -311,11 +491,13 @@ impl NodeJsPoolProcess {
         cwd: &Path,
         env: &HashMap<String, String>,
         entrypoint: &Path,
+        in_memory: Option<&InMemoryEntry>,
         assets_for_source_mapping: Vc<AssetsForSourceMapping>,
         assets_root: Vc<FileSystemPath>,
         project_dir: Vc<FileSystemPath>,
         shared_stdout: SharedOutputSet,
         shared_stderr: SharedOutputSet,
+        runtime: JsRuntime,
         debug: bool,
     ) -> Result<Self> {
         let guard = Box::new(duration_span!("Node.js process startup"));
@@ -323,13 +505,14 @@ impl NodeJsPoolProcess {
             .await
             .context("binding to a port")?;
         let port = listener.local_addr().context("getting port")?.port();
-        let mut cmd = Command::new("node");
+        let marker = output_marker();
+        let mut cmd = match in_memory {
+            Some(_) => runtime.eval_command(IN_MEMORY_LOADER, debug)?,
+            None => runtime.command(entrypoint, debug),
+        };
         cmd.current_dir(cwd);
-        if debug {
-            cmd.arg("--inspect-brk");
-        }
-        cmd.arg(entrypoint);
         cmd.arg(port.to_string());
+        cmd.arg(&*marker);
         cmd.env_clear();
         cmd.env(
             "PATH",
@@ -341,12 +524,33 @@ impl NodeJsPoolProcess {
             std::env::var("SystemRoot")
                 .expect("the SystemRoot environment variable should always be set"),
         );
+        for name in runtime.inherited_env() {
+            if let Ok(value) = std::env::var(name) {
+                cmd.env(name, value);
+            }
+        }
         cmd.envs(env);
         cmd.stderr(Stdio::piped());
         cmd.stdout(Stdio::piped());
+        if in_memory.is_some() {
+            cmd.stdin(Stdio::piped());
+        }
         cmd.kill_on_drop(true);
 
-        let mut child = cmd.spawn().context("spawning node pooled process")?;
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("spawning {runtime} pooled process"))?;
+        let running = RunningProcess::new(&child);
+        if let (Some(in_memory), Some(mut stdin)) = (in_memory, child.stdin.take()) {
+            // The loader reads stdin to its end before loading the entrypoint.
+            // It's written in the background, as a process waiting for the
+            // debugger doesn't read it yet. When writing fails, the process
+            // fails to connect.
+            let files = serde_json::to_vec(in_memory)?;
+            tokio::spawn(async move {
+                let _ = stdin.write_all(&files).await;
+            });
+        }
 
         let timeout = if debug {
             Duration::MAX
@@ -354,7 +558,7 @@ impl NodeJsPoolProcess {
             CONNECT_TIMEOUT
         };
 
-        async fn get_output(child: &mut Child) -> Result<(String, String)> {
+        async fn get_output(child: &mut Child, marker: &str) -> Result<(String, String)> {
             let mut stdout = Vec::new();
             let mut stderr = Vec::new();
             child
@@ -369,15 +573,13 @@ impl NodeJsPoolProcess {
                 .unwrap()
                 .read_to_end(&mut stderr)
                 .await?;
-            fn clean(buffer: Vec<u8>) -> Result<String> {
+            let clean = |buffer: Vec<u8>| -> Result<String> {
                 Ok(String::from_utf8(buffer)?
                     .lines()
-                    .filter(|line| {
-                        line.len() != MARKER_STR.len() + 1 && !line.starts_with(MARKER_STR)
-                    })
+                    .filter(|line| line.len() != marker.len() + 1 || !line.starts_with(marker))
                     .collect::<Vec<_>>()
                     .join("\n"))
-            }
+            };
             Ok((clean(stdout)?, clean(stderr)?))
         }
 
@@ -386,19 +588,19 @@ impl NodeJsPoolProcess {
             status = child.wait() => {
                 match status {
                     Ok(status) => {
-                        let (stdout, stderr) = get_output(&mut child).await?;
-                        bail!("node process exited before we could connect to it with {status}\nProcess output:\n{stdout}\nProcess error output:\n{stderr}");
+                        let (stdout, stderr) = get_output(&mut child, &marker).await?;
+                        bail!("{runtime} process exited before we could connect to it with {status}\nProcess output:\n{stdout}\nProcess error output:\n{stderr}");
                     }
                     Err(err) => {
                         let _ = child.start_kill();
-                        let (stdout, stderr) = get_output(&mut child).await?;
-                        bail!("node process exited before we could connect to it: {err:?}\nProcess output:\n{stdout}\nProcess error output:\n{stderr}");
+                        let (stdout, stderr) = get_output(&mut child, &marker).await?;
+                        bail!("{runtime} process exited before we could connect to it: {err:?}\nProcess output:\n{stdout}\nProcess error output:\n{stderr}");
                     },
                 }
             },
             _ = sleep(timeout) => {
                 let _ = child.start_kill();
-                let (stdout, stderr) = get_output(&mut child).await?;
+                let (stdout, stderr) = get_output(&mut child, &marker).await?;
                 bail!("timed out waiting for the Node.js process to connect ({timeout:?} timeout)\nProcess output:\n{stdout}\nProcess error output:\n{stderr}");
             },
         };
@@ -408,29 +610,39 @@ impl NodeJsPoolProcess {
 
         let stdout_handler = OutputStreamHandler {
             stream: child_stdout,
+            marker: marker.clone(),
             shared: shared_stdout,
             assets_for_source_mapping,
             root: assets_root,
             project_dir,
             final_stream: stdout(),
+            level: ServerLogLevel::Log,
         };
         let stderr_handler = OutputStreamHandler {
             stream: child_stderr,
+            marker,
             shared: shared_stderr,
             assets_for_source_mapping,
             root: assets_root,
             project_dir,
             final_stream: stderr(),
+            level: ServerLogLevel::Error,
         };
 
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
         let mut process = Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
             child: Some(child),
+            running,
             connection,
             assets_for_source_mapping,
             assets_root,
             project_dir,
             stdout_handler,
             stderr_handler,
+            idle_since: Instant::now(),
+            forwarded_output: None,
+            generation: 0,
             debug,
         };
 
@@ -448,6 +660,22 @@ impl NodeJsPoolProcess {
         Ok(process)
     }
 
+    /// Whether the process exited, e. g. because it crashed.
+    fn has_exited(&mut self) -> bool {
+        match &mut self.child {
+            Some(child) => !matches!(child.try_wait(), Ok(None)),
+            None => true,
+        }
+    }
+
+    /// Whether the process exits within `time`.
+    async fn exited_within(&mut self, time: Duration) -> bool {
+        match &mut self.child {
+            Some(child) => timeout(time, child.wait()).await.is_ok(),
+            None => true,
+        }
+    }
+
     async fn recv(&mut self) -> Result<Vec<u8>> {
         let connection = &mut self.connection;
         async fn with_timeout<T, E: Into<anyhow::Error>>(
@@ -470,6 +698,7 @@ impl NodeJsPoolProcess {
             }
         }
         let debug = self.debug;
+        let forwarded_output = self.forwarded_output.as_ref();
         let recv_future = async move {
             let packet_len = with_timeout(debug, false, connection.read_u32())
                 .await
@@ -484,8 +713,8 @@ impl NodeJsPoolProcess {
         };
         let (result, stdout, stderr) = join!(
             recv_future,
-            self.stdout_handler.handle_operation(),
-            self.stderr_handler.handle_operation(),
+            self.stdout_handler.handle_operation(forwarded_output),
+            self.stderr_handler.handle_operation(forwarded_output),
         );
         let result = result?;
         stdout.context("unable to handle stdout from the Node.js process in a structured way")?;
@@ -672,24 +901,151 @@ enum AcquiredPermits {
     },
 }
 
+/// How a [NodeJsPool] boots up processes when there is no idle process for an
+/// operation.
+#[derive(
+    TaskInput, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs,
+)]
+pub enum ScaleUpPolicy {
+    /// Boots up a process when the pending operations are expected to
+    /// complete faster with it than by waiting for a busy process, based on
+    /// the measured bootup and operation times.
+    #[default]
+    Estimated,
+    /// Boots up a process right away, e. g. for CI runs where bootup time is
+    /// cheaper than queueing.
+    Eager,
+}
+
+/// The JavaScript runtime the processes of a [NodeJsPool] run in. They all
+/// run the same entrypoint and speak the same IPC protocol, only the process
+/// is spawned differently.
+#[derive(
+    TaskInput, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum JsRuntime {
+    #[default]
+    Node,
+    /// Runs the entrypoint with `deno run`. Loading the CommonJS chunks
+    /// requires Deno 2, which detects them by the `package.json` emitted next
+    /// to them.
+    Deno,
+    /// Runs the entrypoint with `bun run`.
+    Bun,
+}
+
+impl JsRuntime {
+    fn command(self, entrypoint: &Path, debug: bool) -> Command {
+        let mut cmd = match self {
+            JsRuntime::Node => Command::new("node"),
+            JsRuntime::Deno => {
+                let mut cmd = Command::new("deno");
+                // Like Node.js processes, they are not sandboxed
+                cmd.args(["run", "--allow-all"]);
+                cmd
+            }
+            JsRuntime::Bun => {
+                let mut cmd = Command::new("bun");
+                cmd.arg("run");
+                cmd
+            }
+        };
+        if debug {
+            cmd.arg("--inspect-brk");
+        }
+        cmd.arg(entrypoint);
+        cmd
+    }
+
+    /// Runs `source` instead of an entrypoint on disk. Only Node.js is
+    /// supported.
+    fn eval_command(self, source: &str, debug: bool) -> Result<Command> {
+        if self != JsRuntime::Node {
+            bail!("in-memory entrypoints can only run in Node.js, not in {self}");
+        }
+        let mut cmd = Command::new("node");
+        if debug {
+            cmd.arg("--inspect-brk");
+        }
+        cmd.arg("-e").arg(source);
+        Ok(cmd)
+    }
+
+    /// Variables of the parent process the runtime needs besides `PATH`, e. g.
+    /// to find its module cache.
+    fn inherited_env(self) -> &'static [&'static str] {
+        match self {
+            JsRuntime::Node => &[],
+            JsRuntime::Deno => &["HOME", "USERPROFILE", "DENO_DIR"],
+            JsRuntime::Bun => &["HOME", "USERPROFILE", "BUN_INSTALL"],
+        }
+    }
+}
+
+impl Display for JsRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            JsRuntime::Node => "node",
+            JsRuntime::Deno => "deno",
+            JsRuntime::Bun => "bun",
+        })
+    }
+}
+
+/// Sizing of a [NodeJsPool].
+#[derive(
+    TaskInput, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs,
+)]
+pub struct NodeJsPoolOptions {
+    /// The number of idle processes which are kept running when idle
+    /// processes are stopped after [NodeJsPoolOptions::idle_timeout_ms].
+    pub min_processes: usize,
+    /// The maximum number of processes, and of concurrent operations. Defaults
+    /// to the available parallelism.
+    pub max_processes: Option<usize>,
+    /// Idle processes are stopped after this time, down to
+    /// [NodeJsPoolOptions::min_processes]. By default they are kept running.
+    pub idle_timeout_ms: Option<u64>,
+    /// The number of processes booted as soon as the pool is created, i. e.
+    /// once its entrypoint is emitted, instead of on the first operation, see
+    /// [NodeJsPool::warm_up]. Not in debug mode.
+    pub prefork: usize,
+    pub scale_up: ScaleUpPolicy,
+    /// The runtime the processes run in. Changing it replaces the processes.
+    pub runtime: JsRuntime,
+}
+
+impl NodeJsPoolOptions {
+    fn max_processes(&self) -> usize {
+        self.max_processes
+            .unwrap_or_else(|| available_parallelism().map_or(1, |v| v.get()))
+            .max(1)
+    }
+}
+
 /// A pool of Node.js workers operating on [entrypoint] with specific [cwd] and
 /// [env].
 ///
 /// The pool will spawn processes when needed and reuses old ones. It will never
-/// spawn more then a certain number of concurrent processes. This is specified
-/// with the `concurrency` argument in the constructor.
+/// spawn more then a certain number of concurrent processes. This and when
+/// processes are spawned and stopped is specified with the [NodeJsPoolOptions]
+/// in the constructor.
+///
+/// Processes which crashed are replaced with fresh ones: idle processes when
+/// they are acquired, and the process of an operation before it received a
+/// message, in which case the operation is retried on the fresh process.
 ///
 /// The worker will *not* use the env of the parent process by default. All env
 /// vars need to be provided to make the execution as pure as possible.
 #[turbo_tasks::value(into = "new", cell = "new", serialization = "none", eq = "manual")]
 pub struct NodeJsPool {
-    cwd: PathBuf,
-    entrypoint: PathBuf,
-    env: HashMap<String, String>,
     pub assets_for_source_mapping: Vc<AssetsForSourceMapping>,
     pub assets_root: Vc<FileSystemPath>,
     pub project_dir: Vc<FileSystemPath>,
     #[turbo_tasks(trace_ignore, debug_ignore)]
+    launcher: Arc<ProcessLauncher>,
+    #[turbo_tasks(trace_ignore, debug_ignore)]
     processes: Arc<Mutex<Vec<NodeJsPoolProcess>>>,
     /// Semaphore to limit the number of concurrent operations in general
     #[turbo_tasks(trace_ignore, debug_ignore)]
@@ -702,14 +1058,188 @@ pub struct NodeJsPool {
     #[turbo_tasks(trace_ignore, debug_ignore)]
     idle_process_semaphore: Arc<Semaphore>,
     #[turbo_tasks(trace_ignore, debug_ignore)]
-    shared_stdout: SharedOutputSet,
+    stats: Arc<Mutex<NodeJsPoolStats>>,
+    /// The process which last handled an operation with an affinity key, see
+    /// [NodeJsPool::operation_with_affinity].
     #[turbo_tasks(trace_ignore, debug_ignore)]
+    affinity: Arc<Mutex<IndexMap<String, u64>>>,
+    scale_up: ScaleUpPolicy,
+    max_processes: usize,
+}
+
+/// Boots up the processes of a [NodeJsPool]. Shared with its operations, so
+/// they can replace a process which crashed.
+struct ProcessLauncher {
+    cwd: PathBuf,
+    entrypoint: PathBuf,
+    env: HashMap<String, String>,
+    assets_for_source_mapping: Vc<AssetsForSourceMapping>,
+    assets_root: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+    shared_stdout: SharedOutputSet,
     shared_stderr: SharedOutputSet,
+    runtime: JsRuntime,
     debug: bool,
-    #[turbo_tasks(trace_ignore, debug_ignore)]
-    stats: Arc<Mutex<NodeJsPoolStats>>,
+    /// The files of the entrypoint when it's not on disk.
+    in_memory: Option<InMemoryEntry>,
+    updates: Mutex<ModuleUpdates>,
+}
+
+/// The chunks changed since processes of a [NodeJsPool] were started, see
+/// [NodeJsPool::new_or_updated].
+#[derive(Default)]
+struct ModuleUpdates {
+    /// Incremented with every update.
+    generation: u64,
+    /// The generation in which each chunk changed last.
+    changed_chunks: HashMap<PathBuf, u64>,
+}
+
+/// The answer of a process to a `modulesUpdated` message.
+#[derive(Deserialize)]
+struct ModulesUpdated {
+    applied: bool,
+}
+
+/// How long a process may take to apply an update before it's replaced.
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+impl ProcessLauncher {
+    async fn launch(&self) -> Result<(NodeJsPoolProcess, Duration)> {
+        let start = Instant::now();
+        // Chunks changed while the process starts are loaded again by the next update
+        let generation = self.updates.lock().generation;
+        let process = NodeJsPoolProcess::new(
+            self.cwd.as_path(),
+            &self.env,
+            self.entrypoint.as_path(),
+            self.in_memory.as_ref(),
+            self.assets_for_source_mapping,
+            self.assets_root,
+            self.project_dir,
+            self.shared_stdout.clone(),
+            self.shared_stderr.clone(),
+            self.runtime,
+            self.debug,
+        )
+        .await
+        .context("creating new process");
+        match process {
+            Ok(mut process) => {
+                FAILED_BOOTUPS.store(0, Ordering::Relaxed);
+                process.generation = generation;
+                Ok((process, start.elapsed()))
+            }
+            Err(err) => {
+                FAILED_BOOTUPS.fetch_add(1, Ordering::Relaxed);
+                Err(err)
+            }
+        }
+    }
+
+    /// Records the chunks changed by an update of the pool.
+    fn add_update(&self, changed_chunks: impl IntoIterator<Item = PathBuf>) {
+        let mut updates = self.updates.lock();
+        updates.generation += 1;
+        let generation = updates.generation;
+        updates
+            .changed_chunks
+            .extend(changed_chunks.into_iter().map(|chunk| (chunk, generation)));
+    }
+
+    /// Sends the chunks changed since `process` was started or last updated to
+    /// it. Returns whether the process applied them, otherwise it has to be
+    /// replaced.
+    async fn update(&self, process: &mut NodeJsPoolProcess) -> bool {
+        let (generation, chunks) = {
+            let updates = self.updates.lock();
+            if process.generation == updates.generation {
+                return true;
+            }
+            let chunks = updates
+                .changed_chunks
+                .iter()
+                .filter(|(_, generation)| **generation > process.generation)
+                .map(|(chunk, _)| chunk.clone())
+                .collect::<Vec<_>>();
+            (updates.generation, chunks)
+        };
+        let update = async {
+            let message = json!({ "type": "modulesUpdated", "chunks": chunks });
+            process.send(serde_json::to_vec(&message)?).await?;
+            let answer: ModulesUpdated = serde_json::from_slice(&process.recv().await?)?;
+            anyhow::Ok(answer.applied)
+        };
+        match timeout(UPDATE_TIMEOUT, update).await {
+            Ok(Ok(true)) => {
+                process.generation = generation;
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
+/// The configuration of a pool of [NodeJsPool::new_or_updated]. The pool is
+/// only updated when it didn't change.
+#[derive(PartialEq)]
+struct PoolConfig {
+    cwd: PathBuf,
+    env: HashMap<String, String>,
+    options: NodeJsPoolOptions,
+    debug: bool,
+}
+
+struct LivePool {
+    config: PoolConfig,
+    chunk_hashes: HashMap<PathBuf, u64>,
+    pool: WeakNodeJsPool,
+}
+
+/// The latest pools of [NodeJsPool::new_or_updated] by entrypoint.
+static LIVE_POOLS: Lazy<Mutex<HashMap<PathBuf, LivePool>>> = Lazy::new(Default::default);
+
+/// A [NodeJsPool] which doesn't keep its processes running.
+struct WeakNodeJsPool {
+    launcher: Weak<ProcessLauncher>,
+    processes: Weak<Mutex<Vec<NodeJsPoolProcess>>>,
+    concurrency_semaphore: Weak<Semaphore>,
+    bootup_semaphore: Weak<Semaphore>,
+    idle_process_semaphore: Weak<Semaphore>,
+    stats: Weak<Mutex<NodeJsPoolStats>>,
+    affinity: Weak<Mutex<IndexMap<String, u64>>>,
+    scale_up: ScaleUpPolicy,
+    max_processes: usize,
+}
+
+impl WeakNodeJsPool {
+    fn upgrade(
+        &self,
+        assets_for_source_mapping: Vc<AssetsForSourceMapping>,
+        assets_root: Vc<FileSystemPath>,
+        project_dir: Vc<FileSystemPath>,
+    ) -> Option<NodeJsPool> {
+        Some(NodeJsPool {
+            assets_for_source_mapping,
+            assets_root,
+            project_dir,
+            launcher: self.launcher.upgrade()?,
+            processes: self.processes.upgrade()?,
+            concurrency_semaphore: self.concurrency_semaphore.upgrade()?,
+            bootup_semaphore: self.bootup_semaphore.upgrade()?,
+            idle_process_semaphore: self.idle_process_semaphore.upgrade()?,
+            stats: self.stats.upgrade()?,
+            affinity: self.affinity.upgrade()?,
+            scale_up: self.scale_up,
+            max_processes: self.max_processes,
+        })
+    }
+}
+
+/// The number of affinity keys remembered by a pool. The least recently used
+/// keys are forgotten first.
+const MAX_AFFINITY_KEYS: usize = 1024;
+
 impl NodeJsPool {
     /// * debug: Whether to automatically enable Node's `--inspect-brk` when
     ///   spawning it. Note: automatically overrides concurrency to 1.
@@ -720,91 +1250,378 @@ impl NodeJsPool {
         assets_for_source_mapping: Vc<AssetsForSourceMapping>,
         assets_root: Vc<FileSystemPath>,
         project_dir: Vc<FileSystemPath>,
-        concurrency: usize,
+        options: NodeJsPoolOptions,
         debug: bool,
     ) -> Self {
-        Self {
+        Self::with_launcher(
             cwd,
             entrypoint,
+            None,
             env,
             assets_for_source_mapping,
             assets_root,
             project_dir,
+            options,
+            debug,
+        )
+    }
+
+    /// Like [NodeJsPool::new], for an entrypoint whose files are not on disk.
+    /// The processes load them from memory, see [InMemoryEntry].
+    pub(super) fn new_in_memory(
+        cwd: PathBuf,
+        in_memory: InMemoryEntry,
+        env: HashMap<String, String>,
+        assets_for_source_mapping: Vc<AssetsForSourceMapping>,
+        assets_root: Vc<FileSystemPath>,
+        project_dir: Vc<FileSystemPath>,
+        options: NodeJsPoolOptions,
+        debug: bool,
+    ) -> Self {
+        Self::with_launcher(
+            cwd,
+            in_memory.entry.clone(),
+            Some(in_memory),
+            env,
+            assets_for_source_mapping,
+            assets_root,
+            project_dir,
+            options,
+            debug,
+        )
+    }
+
+    fn with_launcher(
+        cwd: PathBuf,
+        entrypoint: PathBuf,
+        in_memory: Option<InMemoryEntry>,
+        env: HashMap<String, String>,
+        assets_for_source_mapping: Vc<AssetsForSourceMapping>,
+        assets_root: Vc<FileSystemPath>,
+        project_dir: Vc<FileSystemPath>,
+        options: NodeJsPoolOptions,
+        debug: bool,
+    ) -> Self {
+        let concurrency = if debug { 1 } else { options.max_processes() };
+        let pool = Self {
+            assets_for_source_mapping,
+            assets_root,
+            project_dir,
+            launcher: Arc::new(ProcessLauncher {
+                cwd,
+                entrypoint,
+                env,
+                assets_for_source_mapping,
+                assets_root,
+                project_dir,
+                shared_stdout: Arc::new(Mutex::new(IndexSet::new())),
+                shared_stderr: Arc::new(Mutex::new(IndexSet::new())),
+                runtime: options.runtime,
+                debug,
+                in_memory,
+                updates: Default::default(),
+            }),
             processes: Arc::new(Mutex::new(Vec::new())),
-            concurrency_semaphore: Arc::new(Semaphore::new(if debug { 1 } else { concurrency })),
+            concurrency_semaphore: Arc::new(Semaphore::new(concurrency)),
             bootup_semaphore: Arc::new(Semaphore::new(1)),
             idle_process_semaphore: Arc::new(Semaphore::new(0)),
-            shared_stdout: Arc::new(Mutex::new(IndexSet::new())),
-            shared_stderr: Arc::new(Mutex::new(IndexSet::new())),
-            debug,
             stats: Default::default(),
+            affinity: Default::default(),
+            scale_up: options.scale_up,
+            max_processes: concurrency,
+        };
+        if let (Some(idle_timeout_ms), false) = (options.idle_timeout_ms, debug) {
+            pool.stop_idle_processes(
+                Duration::from_millis(idle_timeout_ms),
+                options.min_processes,
+            );
+        }
+        if options.prefork > 0 && !debug {
+            pool.warm_up(options.prefork);
+        }
+        pool
+    }
+
+    /// Like [NodeJsPool::new], but when the previous pool of `entrypoint` is
+    /// still alive and was created with the same configuration, its
+    /// processes are reused. `chunk_hashes` are the hashes of the emitted
+    /// chunks the processes load. The processes are sent the chunks which
+    /// changed since the previous pool, instead of being replaced, so
+    /// renders after an edit don't wait for cold processes.
+    ///
+    /// The update is applied by a process before its next operation. When a
+    /// process can't apply it, e.g. because the runtime changed or because
+    /// its entry doesn't support updates, it's replaced with a fresh one.
+    /// When `entrypoint` itself changed, a new pool is created.
+    pub(super) fn new_or_updated(
+        cwd: PathBuf,
+        entrypoint: PathBuf,
+        env: HashMap<String, String>,
+        chunk_hashes: HashMap<PathBuf, u64>,
+        assets_for_source_mapping: Vc<AssetsForSourceMapping>,
+        assets_root: Vc<FileSystemPath>,
+        project_dir: Vc<FileSystemPath>,
+        options: NodeJsPoolOptions,
+        debug: bool,
+    ) -> Self {
+        let config = PoolConfig {
+            cwd,
+            env,
+            options,
+            debug,
+        };
+        let mut live_pools = LIVE_POOLS.lock();
+        if let Some(live) = live_pools.get_mut(&entrypoint) {
+            let pool = if live.config == config
+                && live.chunk_hashes.get(&entrypoint) == chunk_hashes.get(&entrypoint)
+            {
+                live.pool
+                    .upgrade(assets_for_source_mapping, assets_root, project_dir)
+            } else {
+                None
+            };
+            if let Some(pool) = pool {
+                let changed_chunks = chunk_hashes
+                    .iter()
+                    .filter(|(chunk, hash)| live.chunk_hashes.get(*chunk) != Some(hash))
+                    .map(|(chunk, _)| chunk.clone())
+                    .collect::<Vec<_>>();
+                if !changed_chunks.is_empty() {
+                    pool.launcher.add_update(changed_chunks);
+                }
+                live.chunk_hashes = chunk_hashes;
+                return pool;
+            }
+        }
+
+        let pool = Self::new(
+            config.cwd.clone(),
+            entrypoint.clone(),
+            config.env.clone(),
+            assets_for_source_mapping,
+            assets_root,
+            project_dir,
+            config.options.clone(),
+            config.debug,
+        );
+        live_pools.retain(|_, live| live.pool.launcher.strong_count() > 0);
+        live_pools.insert(
+            entrypoint,
+            LivePool {
+                config,
+                chunk_hashes,
+                pool: pool.downgrade(),
+            },
+        );
+        pool
+    }
+
+    fn downgrade(&self) -> WeakNodeJsPool {
+        WeakNodeJsPool {
+            launcher: Arc::downgrade(&self.launcher),
+            processes: Arc::downgrade(&self.processes),
+            concurrency_semaphore: Arc::downgrade(&self.concurrency_semaphore),
+            bootup_semaphore: Arc::downgrade(&self.bootup_semaphore),
+            idle_process_semaphore: Arc::downgrade(&self.idle_process_semaphore),
+            stats: Arc::downgrade(&self.stats),
+            affinity: Arc::downgrade(&self.affinity),
+            scale_up: self.scale_up,
+            max_processes: self.max_processes,
         }
     }
 
-    async fn acquire_process(&self) -> Result<(NodeJsPoolProcess, AcquiredPermits)> {
+    /// Periodically stops processes which have been idle for `idle_timeout`,
+    /// keeping `min_processes` idle processes. Ends when the pool is dropped.
+    fn stop_idle_processes(&self, idle_timeout: Duration, min_processes: usize) {
+        let processes = Arc::downgrade(&self.processes);
+        let idle_process_semaphore = Arc::downgrade(&self.idle_process_semaphore);
+        let stats = Arc::downgrade(&self.stats);
+        let interval = max(idle_timeout / 2, Duration::from_secs(1));
+        tokio::spawn(async move {
+            loop {
+                sleep(interval).await;
+                let (Some(processes), Some(idle_process_semaphore), Some(stats)) = (
+                    processes.upgrade(),
+                    idle_process_semaphore.upgrade(),
+                    stats.upgrade(),
+                ) else {
+                    return;
+                };
+                let mut processes = processes.lock();
+                while processes.len() > min_processes {
+                    let Some((index, process)) = processes
+                        .iter()
+                        .enumerate()
+                        .min_by_key(|(_, process)| process.idle_since)
+                    else {
+                        break;
+                    };
+                    if process.idle_since.elapsed() < idle_timeout {
+                        break;
+                    }
+                    // The process is only taken when no operation is about to take it
+                    let Ok(permit) = idle_process_semaphore.try_acquire() else {
+                        break;
+                    };
+                    permit.forget();
+                    // Dropping the process kills it
+                    processes.swap_remove(index);
+                    stats.lock().remove_worker();
+                }
+            }
+        });
+    }
+
+    /// Boots up processes in the background until the pool has `processes`
+    /// processes, at most the maximum number of processes. They evaluate the
+    /// entrypoint and wait idle for operations, so the first operations don't
+    /// wait for cold processes.
+    ///
+    /// A process which fails to boot up is dropped, the operation booting up
+    /// a process instead reports the error.
+    pub fn warm_up(&self, processes: usize) {
+        let missing = {
+            let mut stats = self.stats.lock();
+            let missing = processes
+                .min(self.max_processes)
+                .saturating_sub(stats.workers as usize);
+            for _ in 0..missing {
+                stats.add_booting_worker();
+            }
+            missing
+        };
+        for _ in 0..missing {
+            let launcher = self.launcher.clone();
+            let processes = self.processes.clone();
+            let idle_process_semaphore = self.idle_process_semaphore.clone();
+            let stats = self.stats.clone();
+            tokio::spawn(async move {
+                match launcher.launch().await {
+                    Ok((mut process, bootup_time)) => {
+                        {
+                            let mut stats = stats.lock();
+                            stats.add_bootup_time(bootup_time);
+                            stats.finished_booting_worker();
+                        }
+                        process.idle_since = Instant::now();
+                        processes.lock().push(process);
+                        idle_process_semaphore.add_permits(1);
+                    }
+                    Err(_) => {
+                        let mut stats = stats.lock();
+                        stats.finished_booting_worker();
+                        stats.remove_worker();
+                    }
+                }
+            });
+        }
+    }
+
+    /// Acquires an idle process, or boots up a new one. `preferred` is the id
+    /// of the process to take when it's idle.
+    async fn acquire_process(
+        &self,
+        preferred: Option<u64>,
+    ) -> Result<(NodeJsPoolProcess, AcquiredPermits)> {
         {
             self.stats.lock().add_queued_task();
         }
 
         let concurrency_permit = self.concurrency_semaphore.clone().acquire_owned().await?;
 
-        let bootup = async {
-            let permit = self.bootup_semaphore.clone().acquire_owned().await;
-            let wait_time = self.stats.lock().wait_time_before_bootup();
-            tokio::time::sleep(wait_time).await;
-            permit
-        };
-
-        select! {
-            idle_process_permit = self.idle_process_semaphore.clone().acquire_owned() => {
-                let idle_process_permit = idle_process_permit.context("acquiring idle process permit")?;
-                let process = {
-                    let mut processes = self.processes.lock();
-                    processes.pop().unwrap()
+        loop {
+            let bootup = async {
+                let permit = self.bootup_semaphore.clone().acquire_owned().await;
+                let wait_time = match self.scale_up {
+                    ScaleUpPolicy::Estimated => self.stats.lock().wait_time_before_bootup(),
+                    ScaleUpPolicy::Eager => Duration::ZERO,
                 };
-                idle_process_permit.forget();
-                Ok((process, AcquiredPermits::Idle { concurrency_permit }))
-            },
-            bootup_permit = bootup => {
-                let bootup_permit = bootup_permit.context("acquiring bootup permit")?;
-                {
-                    self.stats.lock().add_booting_worker();
-                }
-                let (process, bootup_time) = self.create_process().await?;
-                // Update the worker count
-                {
-                    let mut stats = self.stats.lock();
-                    stats.add_bootup_time(bootup_time);
-                    stats.finished_booting_worker();
+                tokio::time::sleep(wait_time).await;
+                permit
+            };
+
+            select! {
+                idle_process_permit = self.idle_process_semaphore.clone().acquire_owned() => {
+                    let idle_process_permit = idle_process_permit.context("acquiring idle process permit")?;
+                    let mut process = {
+                        let mut processes = self.processes.lock();
+                        let preferred = preferred.and_then(|preferred| {
+                            processes.iter().position(|process| process.id == preferred)
+                        });
+                        match preferred {
+                            Some(index) => processes.swap_remove(index),
+                            None => processes.pop().unwrap(),
+                        }
+                    };
+                    idle_process_permit.forget();
+                    // The process can crash while it's idle, e. g. when user code throws in a
+                    // timer. It's dropped and another process is acquired or booted up instead.
+                    if process.has_exited() {
+                        self.stats.lock().remove_worker();
+                        continue;
+                    }
+                    // The process is replaced when it can't apply the chunks changed since it was
+                    // started, see [NodeJsPool::new_or_updated]
+                    if !self.launcher.update(&mut process).await {
+                        self.stats.lock().remove_worker();
+                        continue;
+                    }
+                    return Ok((process, AcquiredPermits::Idle { concurrency_permit }));
+                },
+                bootup_permit = bootup => {
+                    let bootup_permit = bootup_permit.context("acquiring bootup permit")?;
+                    {
+                        self.stats.lock().add_booting_worker();
+                    }
+                    let (process, bootup_time) = self.create_process().await?;
+                    // Update the worker count
+                    {
+                        let mut stats = self.stats.lock();
+                        stats.add_bootup_time(bootup_time);
+                        stats.finished_booting_worker();
+                    }
+                    // Increase the allowed booting up processes
+                    self.bootup_semaphore.add_permits(1);
+                    return Ok((process, AcquiredPermits::Fresh { concurrency_permit, bootup_permit }));
                 }
-                // Increase the allowed booting up processes
-                self.bootup_semaphore.add_permits(1);
-                Ok((process, AcquiredPermits::Fresh { concurrency_permit, bootup_permit }))
             }
         }
     }
 
     async fn create_process(&self) -> Result<(NodeJsPoolProcess, Duration), anyhow::Error> {
-        let start = Instant::now();
-        let process = NodeJsPoolProcess::new(
-            self.cwd.as_path(),
-            &self.env,
-            self.entrypoint.as_path(),
-            self.assets_for_source_mapping,
-            self.assets_root,
-            self.project_dir,
-            self.shared_stdout.clone(),
-            self.shared_stderr.clone(),
-            self.debug,
-        )
-        .await
-        .context("creating new process")?;
-        Ok((process, start.elapsed()))
+        self.launcher.launch().await
     }
 
     pub async fn operation(&self) -> Result<NodeJsOperation> {
+        self.operation_with_affinity(None).await
+    }
+
+    /// Like [NodeJsPool::operation], but operations with the same
+    /// `affinity_key` (e. g. a session or a page) are routed to the same
+    /// process when it's idle, so in-memory state of the process (mock
+    /// databases, warm caches) is preserved between them.
+    ///
+    /// The affinity is best effort: when the process is busy with another
+    /// operation, or has crashed, another process is used and becomes the
+    /// process of the key. Waiting for the busy process instead would
+    /// serialize concurrent operations of a key, and processes still don't
+    /// survive restarts of the pool.
+    pub async fn operation_with_affinity(
+        &self,
+        affinity_key: Option<&str>,
+    ) -> Result<NodeJsOperation> {
+        let preferred = affinity_key.and_then(|key| self.affinity.lock().get(key).copied());
         // Acquire a running process (handles concurrency limits, boots up the process)
-        let (process, permits) = self.acquire_process().await?;
+        let (mut process, permits) = self.acquire_process(preferred).await?;
+        process.forwarded_output = None;
+        if let Some(key) = affinity_key {
+            let mut affinity = self.affinity.lock();
+            affinity.shift_remove(key);
+            affinity.insert(key.to_string(), process.id);
+            if affinity.len() > MAX_AFFINITY_KEYS {
+                affinity.shift_remove_index(0);
+            }
+        }
 
         Ok(NodeJsOperation {
             process: Some(process),
@@ -814,10 +1631,32 @@ impl NodeJsPool {
             start: Instant::now(),
             stats: self.stats.clone(),
             allow_process_reuse: true,
+            deadline: None,
+            launcher: self.launcher.clone(),
+            retry: Retry::Available(Vec::new()),
         })
     }
 }
 
+/// The error of [NodeJsOperation::recv] when the operation exceeded its
+/// timeout, see [NodeJsOperation::set_timeout].
+#[derive(Debug)]
+pub struct OperationTimeout {
+    pub timeout: Duration,
+}
+
+impl Display for OperationTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the Node.js operation didn't finish within {}ms, so its process was killed",
+            self.timeout.as_millis()
+        )
+    }
+}
+
+impl std::error::Error for OperationTimeout {}
+
 pub struct NodeJsOperation {
     process: Option<NodeJsPoolProcess>,
     // This is used for drop
@@ -828,6 +1667,26 @@ pub struct NodeJsOperation {
     start: Instant,
     stats: Arc<Mutex<NodeJsPoolStats>>,
     allow_process_reuse: bool,
+    /// When the operation times out, and its timeout.
+    deadline: Option<(Instant, Duration)>,
+    launcher: Arc<ProcessLauncher>,
+    retry: Retry,
+}
+
+/// Whether a [NodeJsOperation] whose process crashed can be retried on a
+/// fresh process. Operations are only retried before they received a message,
+/// since the caller may already have acted on received messages, and only
+/// once, since a process crashing twice is likely crashed by the operation
+/// itself.
+#[derive(Default)]
+enum Retry {
+    /// Nothing was received yet. Contains the messages sent so far, which are
+    /// sent again to the fresh process.
+    Available(Vec<Vec<u8>>),
+    /// The operation was retried and nothing was received yet.
+    Retrying,
+    #[default]
+    Unavailable,
 }
 
 impl NodeJsOperation {
@@ -852,15 +1711,47 @@ impl NodeJsOperation {
         result
     }
 
+    /// Limits the time of the operation. When it takes longer, e. g. because
+    /// user code never settles a promise, [NodeJsOperation::recv] fails with
+    /// [OperationTimeout] and the process is killed, so the pool boots a
+    /// fresh one.
+    ///
+    /// Timeouts of single messages still apply when they are shorter.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Some((self.start + timeout, timeout));
+    }
+
     pub async fn recv<M>(&mut self) -> Result<M>
     where
         M: DeserializeOwned,
     {
-        let message = self
-            .with_process(|process| async move {
-                process.recv().await.context("failed to receive message")
-            })
-            .await?;
+        let message = loop {
+            let deadline = self.deadline;
+            let message = self
+                .with_process(|process| async move {
+                    let Some((deadline, timeout)) = deadline else {
+                        return process.recv().await.context("failed to receive message");
+                    };
+                    match timeout_at(deadline.into(), process.recv()).await {
+                        Ok(message) => message.context("failed to receive message"),
+                        Err(_) => Err(OperationTimeout { timeout }.into()),
+                    }
+                })
+                .await;
+            match message {
+                Ok(message) => {
+                    self.retry = Retry::Unavailable;
+                    break message;
+                }
+                Err(err) if err.is::<OperationTimeout>() => {
+                    // The process is stuck in user code, kill it right away instead of when the
+                    // operation is dropped.
+                    drop(self.process.take());
+                    return Err(err);
+                }
+                Err(err) => self.retry_on_fresh_process(err).await?,
+            }
+        };
         let message = std::str::from_utf8(&message).context("message is not valid UTF-8")?;
         parse_json_with_source_context(message).context("failed to deserialize message")
     }
@@ -870,6 +1761,17 @@ impl NodeJsOperation {
         M: Serialize,
     {
         let message = serde_json::to_vec(&message).context("failed to serialize message")?;
+        if let Retry::Available(sent) = &mut self.retry {
+            sent.push(message.clone());
+        }
+        match self.send_packet(message).await {
+            Ok(()) => Ok(()),
+            // The message is sent again with the others
+            Err(err) => self.retry_on_fresh_process(err).await,
+        }
+    }
+
+    async fn send_packet(&mut self, message: Vec<u8>) -> Result<()> {
         self.with_process(|process| async move {
             timeout(Duration::from_secs(30), process.send(message))
                 .await
@@ -880,6 +1782,82 @@ impl NodeJsOperation {
         .await
     }
 
+    /// Handles `error` of the process of the operation. When the process
+    /// crashed and the operation can be retried (see [Retry]), the process is
+    /// replaced with a fresh one and the messages sent so far are sent again.
+    /// When the retried operation fails too, a [ProcessCrashIssue] is
+    /// emitted.
+    async fn retry_on_fresh_process(&mut self, error: anyhow::Error) -> Result<()> {
+        let crashed = match self.process.as_mut() {
+            // The connection can break slightly before the process exits
+            Some(process) => process.exited_within(CRASH_EXIT_TIMEOUT).await,
+            None => false,
+        };
+        let sent = match take(&mut self.retry) {
+            Retry::Available(sent) if crashed => sent,
+            Retry::Retrying => {
+                self.report_crash(&error);
+                return Err(error);
+            }
+            _ => return Err(error),
+        };
+
+        let forwarded_output = self
+            .process
+            .as_ref()
+            .and_then(|p| p.forwarded_output.clone());
+        {
+            self.stats.lock().add_booting_worker();
+        }
+        let (mut process, bootup_time) = match self.launcher.launch().await {
+            Ok(process) => process,
+            Err(launch_error) => {
+                {
+                    let mut stats = self.stats.lock();
+                    stats.finished_booting_worker();
+                    stats.remove_worker();
+                }
+                let error = error.context(format!("{launch_error:#}"));
+                self.report_crash(&error);
+                return Err(error);
+            }
+        };
+        {
+            let mut stats = self.stats.lock();
+            stats.add_bootup_time(bootup_time);
+            stats.finished_booting_worker();
+        }
+        process.forwarded_output = forwarded_output;
+        self.process = Some(process);
+        self.allow_process_reuse = true;
+        self.retry = Retry::Retrying;
+
+        for message in sent {
+            if let Err(error) = self.send_packet(message).await {
+                self.retry = Retry::Unavailable;
+                self.report_crash(&error);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    fn report_crash(&self, error: &anyhow::Error) {
+        ProcessCrashIssue {
+            file_path: self.launcher.project_dir,
+            message: StyledString::Text(format!("{error:#}")).cell(),
+        }
+        .cell()
+        .emit();
+    }
+
+    /// Disables retrying the operation on a fresh process when its process
+    /// crashes, e. g. when it's driven outside of a turbo-tasks task, where
+    /// no issue can be emitted.
+    pub fn disallow_retry(&mut self) {
+        self.retry = Retry::Unavailable;
+    }
+
     pub async fn wait_or_kill(mut self) -> Result<ExitStatus> {
         let mut process = self
             .process
@@ -905,6 +1883,26 @@ impl NodeJsOperation {
         Ok(status)
     }
 
+    /// Forwards the console output of the process during this operation to
+    /// the browsers viewing `page` over the HMR socket, in addition to the
+    /// terminal. Each line is published as a [ServerLog] tagged with the
+    /// page, the operation, its level and the time it was written.
+    pub fn forward_output(&mut self, page: String) {
+        static NEXT_OPERATION: AtomicU64 = AtomicU64::new(0);
+        if let Some(process) = self.process.as_mut() {
+            process.forwarded_output = Some(ForwardedOutput {
+                page,
+                operation: NEXT_OPERATION.fetch_add(1, Ordering::Relaxed),
+            });
+        }
+    }
+
+    /// Whether the process of the operation is still running and can be used
+    /// by another operation after this one.
+    pub fn is_reusable(&self) -> bool {
+        self.process.is_some() && self.allow_process_reuse
+    }
+
     pub fn disallow_reuse(&mut self) {
         if self.allow_process_reuse {
             self.stats.lock().remove_worker();
@@ -927,7 +1925,7 @@ impl NodeJsOperation {
 
 impl Drop for NodeJsOperation {
     fn drop(&mut self) {
-        if let Some(process) = self.process.take() {
+        if let Some(mut process) = self.process.take() {
             let elapsed = self.start.elapsed();
             {
                 let stats = &mut self.stats.lock();
@@ -937,9 +1935,41 @@ impl Drop for NodeJsOperation {
                 }
             }
             if self.allow_process_reuse {
+                process.idle_since = Instant::now();
                 self.processes.lock().push(process);
                 self.idle_process_semaphore.add_permits(1);
             }
         }
     }
 }
+
+/// A Node.js process crashed during an operation, and the operation failed
+/// again on the fresh process it was retried on.
+#[turbo_tasks::value(shared)]
+pub struct ProcessCrashIssue {
+    pub file_path: Vc<FileSystemPath>,
+    pub message: Vc<StyledString>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ProcessCrashIssue {
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text("Node.js process crashed".to_string()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::CodeGen.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(self.message))
+    }
+}
@@ -0,0 +1,130 @@
+use std::fmt::Write;
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+/// Parses the cookies of a request from its `cookie` headers. When a cookie
+/// is sent multiple times, the first value wins, like browsers order the most
+/// specific cookie first.
+pub fn parse_cookies(raw_headers: &[(String, String)]) -> IndexMap<String, String> {
+    let mut cookies = IndexMap::new();
+    for cookie in raw_headers
+        .iter()
+        .filter(|(header, _)| header.eq_ignore_ascii_case("cookie"))
+        .flat_map(|(_, value)| value.split(';'))
+    {
+        let Some((name, value)) = cookie.split_once('=') else {
+            continue;
+        };
+        let name = name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        cookies
+            .entry(name.to_string())
+            .or_insert_with(|| value.to_string());
+    }
+    cookies
+}
+
+/// A cookie set by the page runtime, which is serialized into a `set-cookie`
+/// header of the response.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SetCookie {
+    pub name: String,
+    pub value: String,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub domain: Option<String>,
+    /// In seconds, `0` deletes the cookie.
+    #[serde(default)]
+    pub max_age: Option<i64>,
+    /// An HTTP date.
+    #[serde(default)]
+    pub expires: Option<String>,
+    #[serde(default)]
+    pub http_only: bool,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub same_site: Option<SameSite>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SetCookie {
+    /// The value of the `set-cookie` header. Fails for names and values
+    /// which would break the header, instead of silently setting another
+    /// cookie.
+    pub fn header_value(&self) -> Result<String> {
+        if self.name.is_empty() || !self.name.bytes().all(is_token_byte) {
+            bail!("invalid cookie name `{}`", self.name);
+        }
+        if !self.value.bytes().all(is_cookie_value_byte) {
+            bail!("invalid value for cookie `{}`", self.name);
+        }
+        let mut header = format!("{}={}", self.name, self.value);
+        for (attribute, value) in [
+            ("Path", &self.path),
+            ("Domain", &self.domain),
+            ("Expires", &self.expires),
+        ] {
+            if let Some(value) = value {
+                if value.contains([';', '\r', '\n']) {
+                    bail!("invalid {attribute} for cookie `{}`", self.name);
+                }
+                write!(header, "; {attribute}={value}")?;
+            }
+        }
+        if let Some(max_age) = self.max_age {
+            write!(header, "; Max-Age={max_age}")?;
+        }
+        if self.http_only {
+            header.push_str("; HttpOnly");
+        }
+        if self.secure {
+            header.push_str("; Secure");
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => header.push_str("; SameSite=Strict"),
+            Some(SameSite::Lax) => header.push_str("; SameSite=Lax"),
+            Some(SameSite::None) => header.push_str("; SameSite=None"),
+            None => {}
+        }
+        Ok(header)
+    }
+}
+
+/// Appends a `set-cookie` header for each cookie.
+pub(crate) fn append_set_cookies(
+    headers: &mut Vec<(String, String)>,
+    cookies: &[SetCookie],
+) -> Result<()> {
+    for cookie in cookies {
+        headers.push(("set-cookie".to_string(), cookie.header_value()?));
+    }
+    Ok(())
+}
+
+/// See https://www.rfc-editor.org/rfc/rfc6265#section-4.1.1
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_graphic() && !b"()<>@,;:\\\"/[]?={}".contains(&b)
+}
+
+fn is_cookie_value_byte(b: u8) -> bool {
+    b.is_ascii_graphic() && !b"\",;\\".contains(&b)
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde_json::Value as JsonValue;
+use turbo_tasks::Vc;
+use turbopack_ecmascript::segment_config::{SegmentConfig, SegmentFetchCache};
+
+use super::{RenderStaticIncomingMessage, RenderStaticOutgoingMessage};
+use crate::pool::NodeJsOperation;
+
+struct FetchCacheEntry {
+    data: JsonValue,
+    revalidate: Option<Duration>,
+    stored_at: Instant,
+}
+
+impl FetchCacheEntry {
+    fn is_stale(&self) -> bool {
+        self.revalidate
+            .map_or(false, |revalidate| self.stored_at.elapsed() >= revalidate)
+    }
+}
+
+/// An in-memory cache for `fetch()` calls made while rendering in Node.js.
+///
+/// The Node.js side computes the cache key from the URL and the request
+/// options and stores the serialized response. Entries are served until
+/// their revalidate period has passed, after that they are returned as
+/// stale so the Node.js side can refetch them in the background.
+#[turbo_tasks::value(cell = "new", serialization = "none", eq = "manual")]
+pub struct FetchCache {
+    #[turbo_tasks(trace_ignore, debug_ignore)]
+    entries: Mutex<HashMap<String, FetchCacheEntry>>,
+}
+
+impl FetchCache {
+    /// Returns the cached data for `key` and whether it is stale.
+    pub fn get(&self, key: &str) -> Option<(JsonValue, bool)> {
+        let entries = self.entries.lock();
+        let entry = entries.get(key)?;
+        Some((entry.data.clone(), entry.is_stale()))
+    }
+
+    /// Stores data for `key`. A `revalidate` of `None` caches it until the
+    /// cache is dropped.
+    pub fn set(&self, key: String, data: JsonValue, revalidate: Option<u32>) {
+        self.entries.lock().insert(
+            key,
+            FetchCacheEntry {
+                data,
+                revalidate: revalidate.map(|seconds| Duration::from_secs(seconds.into())),
+                stored_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// Answers a fetch cache message of the Node.js process. Returns the
+    /// message again when it isn't fetch cache related.
+    pub(super) async fn handle_message(
+        &self,
+        operation: &mut NodeJsOperation,
+        segment_config: &SegmentConfig,
+        message: RenderStaticIncomingMessage,
+    ) -> Result<Option<RenderStaticIncomingMessage>> {
+        match message {
+            RenderStaticIncomingMessage::FetchCacheGet { key } => {
+                self.answer_get(operation, segment_config, &key).await?;
+                Ok(None)
+            }
+            RenderStaticIncomingMessage::FetchCacheSet {
+                key,
+                data,
+                revalidate,
+            } => {
+                self.store(segment_config, key, data, revalidate);
+                Ok(None)
+            }
+            message => Ok(Some(message)),
+        }
+    }
+
+    /// Answers a `fetchCacheGet` message with the cached data for `key`.
+    pub(super) async fn answer_get(
+        &self,
+        operation: &mut NodeJsOperation,
+        segment_config: &SegmentConfig,
+        key: &str,
+    ) -> Result<()> {
+        let entry = if reads_from_cache(segment_config) {
+            self.get(key)
+        } else {
+            None
+        };
+        let (data, stale) = match entry {
+            Some((data, stale)) => (Some(data), stale),
+            None => (None, false),
+        };
+        operation
+            .send(RenderStaticOutgoingMessage::FetchCacheResult { data, stale })
+            .await
+    }
+
+    /// Stores the data of a `fetchCacheSet` message, unless the segment
+    /// config disables caching.
+    pub(super) fn store(
+        &self,
+        segment_config: &SegmentConfig,
+        key: String,
+        data: JsonValue,
+        revalidate: Option<u32>,
+    ) {
+        let revalidate = revalidate.or_else(|| segment_config.revalidate_seconds());
+        if writes_to_cache(segment_config, revalidate) {
+            self.set(key, data, revalidate);
+        }
+    }
+}
+
+fn reads_from_cache(config: &SegmentConfig) -> bool {
+    !matches!(
+        config.fetch_cache,
+        Some(SegmentFetchCache::OnlyNoStore | SegmentFetchCache::ForceNoStore)
+    )
+}
+
+fn writes_to_cache(config: &SegmentConfig, revalidate: Option<u32>) -> bool {
+    match config.fetch_cache {
+        Some(SegmentFetchCache::OnlyNoStore | SegmentFetchCache::ForceNoStore) => false,
+        Some(SegmentFetchCache::OnlyCache | SegmentFetchCache::ForceCache) => true,
+        _ => revalidate != Some(0),
+    }
+}
+
+/// The fetch cache shared by all renderings.
+#[turbo_tasks::function]
+pub fn fetch_cache() -> Vc<FetchCache> {
+    FetchCache {
+        entries: Default::default(),
+    }
+    .cell()
+}
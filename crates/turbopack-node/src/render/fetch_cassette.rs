@@ -0,0 +1,139 @@
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use turbo_tasks::{trace::TraceRawVcs, Vc};
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fs::{to_sys_path, FileSystemPath};
+
+use super::{RenderStaticIncomingMessage, RenderStaticOutgoingMessage};
+use crate::pool::NodeJsOperation;
+
+/// Path of the cassette file, relative to the project directory.
+const CASSETTE_ENV: &str = "TURBOPACK_FETCH_CASSETTE";
+/// `record` or `replay` (default).
+const CASSETTE_MODE_ENV: &str = "TURBOPACK_FETCH_CASSETTE_MODE";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TraceRawVcs)]
+pub enum FetchCassetteMode {
+    /// Requests are sent to the network and their responses are written to
+    /// the cassette.
+    Record,
+    /// Requests are answered from the cassette. Requests that were not
+    /// recorded fail.
+    Replay,
+}
+
+/// Records the network requests made by the patched `fetch()` while rendering
+/// and replays them later, which makes prerenders reproducible and allows
+/// offline builds.
+///
+/// It's enabled by setting `TURBOPACK_FETCH_CASSETTE` to the path of the
+/// cassette file and `TURBOPACK_FETCH_CASSETTE_MODE` to `record` or `replay`.
+#[turbo_tasks::value(cell = "new", serialization = "none", eq = "manual")]
+pub struct FetchCassette {
+    pub mode: FetchCassetteMode,
+    #[turbo_tasks(trace_ignore)]
+    path: PathBuf,
+    #[turbo_tasks(trace_ignore, debug_ignore)]
+    entries: Mutex<IndexMap<String, JsonValue>>,
+}
+
+impl FetchCassette {
+    fn record(&self, key: String, data: JsonValue) -> Result<()> {
+        let mut entries = self.entries.lock();
+        entries.insert(key, data);
+        let content = serde_json::to_string_pretty(&*entries)?;
+        std::fs::write(&self.path, content)
+            .with_context(|| format!("writing fetch cassette {}", self.path.display()))
+    }
+
+    /// Answers a cassette message of the Node.js process. Returns the message
+    /// again when it isn't cassette related.
+    pub(super) async fn handle_message(
+        &self,
+        operation: &mut NodeJsOperation,
+        message: RenderStaticIncomingMessage,
+    ) -> Result<Option<RenderStaticIncomingMessage>> {
+        match message {
+            RenderStaticIncomingMessage::FetchRecord { key, data } => {
+                self.store_recording(key, data)?;
+                Ok(None)
+            }
+            RenderStaticIncomingMessage::FetchReplay { key } => {
+                self.answer_replay(operation, &key).await?;
+                Ok(None)
+            }
+            message => Ok(Some(message)),
+        }
+    }
+
+    /// Writes the response of a `fetchRecord` message to the cassette.
+    pub(super) fn store_recording(&self, key: String, data: JsonValue) -> Result<()> {
+        if self.mode != FetchCassetteMode::Record {
+            bail!("received a fetch recording while replaying the fetch cassette");
+        }
+        self.record(key, data)
+    }
+
+    /// Answers a `fetchReplay` message with the recorded response for `key`.
+    pub(super) async fn answer_replay(
+        &self,
+        operation: &mut NodeJsOperation,
+        key: &str,
+    ) -> Result<()> {
+        let data = self.entries.lock().get(key).cloned();
+        operation
+            .send(RenderStaticOutgoingMessage::FetchCacheResult { data, stale: false })
+            .await
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct OptionFetchCassette(Option<Vc<FetchCassette>>);
+
+/// The fetch cassette configured in the environment, if any.
+#[turbo_tasks::function]
+pub async fn fetch_cassette(
+    env: Vc<Box<dyn ProcessEnv>>,
+    project_dir: Vc<FileSystemPath>,
+) -> Result<Vc<OptionFetchCassette>> {
+    let Some(path) = &*env.read(CASSETTE_ENV.to_string()).await? else {
+        return Ok(Vc::cell(None));
+    };
+    let mode = match env.read(CASSETTE_MODE_ENV.to_string()).await?.as_deref() {
+        Some("record") => FetchCassetteMode::Record,
+        Some("replay") | None => FetchCassetteMode::Replay,
+        Some(mode) => bail!("{CASSETTE_MODE_ENV} must be `record` or `replay`, but is `{mode}`"),
+    };
+    let Some(project_dir) = to_sys_path(project_dir).await? else {
+        bail!("a fetch cassette can only be used with a disk filesystem");
+    };
+    let path = project_dir.join(path);
+
+    let entries = match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("parsing fetch cassette {}", path.display()))?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            if mode == FetchCassetteMode::Replay {
+                bail!("fetch cassette {} doesn't exist", path.display());
+            }
+            IndexMap::new()
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("reading fetch cassette {}", path.display()))
+        }
+    };
+
+    Ok(Vc::cell(Some(
+        FetchCassette {
+            mode,
+            path,
+            entries: Mutex::new(entries),
+        }
+        .cell(),
+    )))
+}
@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use indexmap::indexmap;
+use turbo_tasks::{Completion, Value, Vc};
+use turbo_tasks_bytes::stream::SingleValue;
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fs::{json::parse_json_with_source_context, File, FileContent, FileSystemPath};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    chunk::ChunkingContext,
+    context::AssetContext,
+    file_source::FileSource,
+    ident::AssetIdent,
+    reference_type::{EntryReferenceSubType, ReferenceType},
+    virtual_source::VirtualSource,
+};
+
+use crate::{embed_js::embed_file, evaluate::evaluate};
+
+/// Rewrites the HTML document of a server-side rendered page, after the page
+/// has been rendered and before it is served, e. g. to inject an analytics
+/// snippet, inline critical CSS or enforce a policy for meta tags.
+#[turbo_tasks::value_trait]
+pub trait HtmlTransform {
+    /// Returns the transformed document. `pathname` is the pathname of the
+    /// page's route.
+    fn transform(self: Vc<Self>, html: Vc<String>, pathname: Vc<String>) -> Vc<String>;
+}
+
+/// The transforms applied to rendered HTML, in order.
+#[turbo_tasks::value(transparent)]
+pub struct HtmlTransforms(Vec<Vc<Box<dyn HtmlTransform>>>);
+
+#[turbo_tasks::value_impl]
+impl HtmlTransforms {
+    #[turbo_tasks::function]
+    pub fn empty() -> Vc<Self> {
+        Vc::cell(Vec::new())
+    }
+}
+
+/// Applies `transforms` to a rendered HTML page. Other content is passed
+/// through.
+///
+/// The result is a task of the rendered content, so a page is only
+/// transformed again when a new version of it was rendered.
+#[turbo_tasks::function]
+pub async fn apply_html_transforms(
+    content: Vc<AssetContent>,
+    pathname: Vc<String>,
+    transforms: Vc<HtmlTransforms>,
+) -> Result<Vc<AssetContent>> {
+    let transforms = transforms.await?;
+    if transforms.is_empty() {
+        return Ok(content);
+    }
+    let AssetContent::File(file) = *content.await? else {
+        return Ok(content);
+    };
+    let FileContent::Content(file) = &*file.await? else {
+        return Ok(content);
+    };
+    let Some(content_type) = file.content_type() else {
+        return Ok(content);
+    };
+    if content_type.essence_str() != mime::TEXT_HTML.essence_str() {
+        return Ok(content);
+    }
+    let content_type = content_type.clone();
+
+    let mut html = Vc::cell(file.content().to_str()?.into_owned());
+    for &transform in transforms.iter() {
+        html = transform.transform(html, pathname);
+    }
+    let html = html.await?.clone_value();
+    Ok(AssetContent::file(
+        FileContent::Content(File::from(html).with_content_type(content_type)).cell(),
+    ))
+}
+
+/// An [HtmlTransform] implemented in JavaScript and run in the Node.js
+/// process pool. The module's default export is called with the document and
+/// `{ pathname, trackFileDependency, trackDirDependency }` and returns the
+/// transformed document, or a promise of it. Changing a file reported by the
+/// `track*` functions runs the transform again.
+#[turbo_tasks::value]
+pub struct NodeJsHtmlTransform {
+    module_path: Vc<FileSystemPath>,
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    evaluate_context: Vc<Box<dyn AssetContext>>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+}
+
+#[turbo_tasks::value_impl]
+impl NodeJsHtmlTransform {
+    #[turbo_tasks::function]
+    pub fn new(
+        module_path: Vc<FileSystemPath>,
+        cwd: Vc<FileSystemPath>,
+        env: Vc<Box<dyn ProcessEnv>>,
+        evaluate_context: Vc<Box<dyn AssetContext>>,
+        chunking_context: Vc<Box<dyn ChunkingContext>>,
+    ) -> Vc<Self> {
+        NodeJsHtmlTransform {
+            module_path,
+            cwd,
+            env,
+            evaluate_context,
+            chunking_context,
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl HtmlTransform for NodeJsHtmlTransform {
+    #[turbo_tasks::function]
+    async fn transform(&self, html: Vc<String>, pathname: Vc<String>) -> Result<Vc<String>> {
+        let transform_module = self
+            .evaluate_context
+            .process(
+                Vc::upcast(FileSource::new(self.module_path)),
+                Value::new(ReferenceType::Entry(EntryReferenceSubType::Undefined)),
+            )
+            .module();
+        let executor = self
+            .evaluate_context
+            .process(
+                Vc::upcast(VirtualSource::new(
+                    self.module_path.join("html-transform.ts".to_string()),
+                    AssetContent::File(embed_file("transforms/html.ts".to_string())).cell(),
+                )),
+                Value::new(ReferenceType::Internal(Vc::cell(indexmap! {
+                    "TRANSFORM".to_string() => transform_module
+                }))),
+            )
+            .module();
+
+        let result = evaluate(
+            executor,
+            self.cwd,
+            self.env,
+            AssetIdent::from_path(self.module_path),
+            self.evaluate_context,
+            self.chunking_context,
+            None,
+            vec![
+                Vc::cell(html.await?.as_str().into()),
+                Vc::cell(pathname.await?.as_str().into()),
+            ],
+            Completion::immutable(),
+            false,
+        )
+        .await?;
+        let SingleValue::Single(value) = result.try_into_single().await? else {
+            // An error happened, which has already been converted into an issue.
+            return Ok(html);
+        };
+        let html: String = parse_json_with_source_context(value.to_str()?)
+            .context("HTML transforms must return a string")?;
+        Ok(Vc::cell(html))
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{anyhow, Result};
+use indexmap::IndexSet;
+use turbo_tasks::{Value, Vc};
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fs::FileSystemPath;
+use turbopack_core::{
+    introspect::{
+        module::IntrospectableModule, output_asset::IntrospectableOutputAsset, Introspectable,
+        IntrospectableChildren,
+    },
+    issue::IssueDescriptionExt,
+    module::Module,
+    version::VersionedContentExt,
+};
+use turbopack_dev_server::{
+    html::DevHtmlAsset,
+    source::{
+        route_tree::{BaseSegment, RouteTree, RouteType},
+        ContentSource, ContentSourceContent, ContentSourceData, ContentSourceDataVary,
+        GetContentSourceContent, HeaderList, ProxyResult,
+    },
+};
+use turbopack_ecmascript::segment_config::SegmentConfig;
+
+use super::{
+    render_static::{render_static, StaticResult},
+    segment_config::{apply_segment_config_headers, route_segment_config},
+    RenderConfig, RenderData,
+};
+use crate::{get_intermediate_asset, node_entry::NodeEntry, route_matcher::RouteMatcher};
+
+/// The `cache-control` of generated images whose route segment config doesn't
+/// declare one. Images are revalidated on every request, so changes of the
+/// generator show up after a reload.
+const DEFAULT_IMAGE_CACHE_CONTROL: &str = "public, max-age=0, must-revalidate";
+
+/// Creates a [NodeImageContentSource].
+#[turbo_tasks::function]
+pub fn create_node_image_source(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    base_segments: Vec<BaseSegment>,
+    route_type: RouteType,
+    server_root: Vc<FileSystemPath>,
+    route_match: Vc<Box<dyn RouteMatcher>>,
+    pathname: Vc<String>,
+    entry: Vc<Box<dyn NodeEntry>>,
+    fallback_page: Vc<DevHtmlAsset>,
+    render_config: Vc<RenderConfig>,
+    debug: bool,
+) -> Vc<Box<dyn ContentSource>> {
+    Vc::upcast(
+        NodeImageContentSource {
+            cwd,
+            env,
+            base_segments,
+            route_type,
+            server_root,
+            route_match,
+            pathname,
+            entry,
+            fallback_page,
+            render_config,
+            debug,
+        }
+        .cell(),
+    )
+}
+
+/// A content source that serves images generated in Node.js by the passed
+/// `entry`, e. g. an app-router `opengraph-image.tsx` returning an
+/// `ImageResponse`. The generator runs in the renderer pool of the entry and
+/// returns the image through the binary body encoding of the render protocol.
+///
+/// Images only depend on the params of the route, so they are rendered once
+/// per params and not per request.
+#[turbo_tasks::value]
+pub struct NodeImageContentSource {
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    base_segments: Vec<BaseSegment>,
+    route_type: RouteType,
+    server_root: Vc<FileSystemPath>,
+    route_match: Vc<Box<dyn RouteMatcher>>,
+    pathname: Vc<String>,
+    entry: Vc<Box<dyn NodeEntry>>,
+    fallback_page: Vc<DevHtmlAsset>,
+    render_config: Vc<RenderConfig>,
+    debug: bool,
+}
+
+#[turbo_tasks::value_impl]
+impl ContentSource for NodeImageContentSource {
+    #[turbo_tasks::function]
+    async fn get_routes(self: Vc<Self>) -> Result<Vc<RouteTree>> {
+        let this = self.await?;
+        Ok(RouteTree::new_route(
+            this.base_segments.clone(),
+            this.route_type.clone(),
+            Vc::upcast(self),
+        ))
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl GetContentSourceContent for NodeImageContentSource {
+    #[turbo_tasks::function]
+    fn vary(&self) -> Vc<ContentSourceDataVary> {
+        ContentSourceDataVary::default().cell()
+    }
+
+    #[turbo_tasks::function]
+    async fn get(
+        &self,
+        path: String,
+        data: Value<ContentSourceData>,
+    ) -> Result<Vc<ContentSourceContent>> {
+        let Some(params) = &*self.route_match.params(path.clone()).await? else {
+            return Err(anyhow!(
+                "Non matching path ({}) provided for {}",
+                path,
+                self.pathname.await?
+            ));
+        };
+        let Some(server_path) = *self.server_root.join_untrusted(path.clone()).await? else {
+            return Ok(ContentSourceContent::not_found());
+        };
+        let render_data = RenderData::for_params(
+            &*self.render_config.await?,
+            params.clone(),
+            format!("/{}", path),
+        )?;
+        let entry = self.entry.entry(data.clone()).await?;
+        let result = render_static(
+            self.cwd,
+            self.env,
+            server_path,
+            entry.module,
+            entry.runtime_entries,
+            self.fallback_page,
+            entry.chunking_context,
+            entry.intermediate_output_path,
+            entry.output_root,
+            entry.project_dir,
+            render_data.cell(),
+            self.debug,
+        )
+        .issue_file_path(
+            entry.module.ident().path(),
+            format!("generating image {}", self.pathname.await?),
+        )
+        .await?;
+        let segment_config = route_segment_config(entry.module);
+        Ok(match *result.await? {
+            StaticResult::Content {
+                content,
+                status_code,
+                headers,
+            } => ContentSourceContent::static_with_headers(
+                content.versioned(),
+                status_code,
+                image_headers(headers, segment_config),
+            ),
+            StaticResult::StreamedContent {
+                status,
+                headers,
+                ref body,
+            } => ContentSourceContent::HttpProxy(
+                ProxyResult {
+                    status,
+                    headers: image_headers(headers, segment_config).await?.clone_value(),
+                    body: body.clone(),
+                }
+                .cell(),
+            )
+            .cell(),
+            StaticResult::Rewrite(rewrite) => ContentSourceContent::Rewrite(rewrite).cell(),
+            StaticResult::Error { content, .. } => ContentSourceContent::static_with_headers(
+                content.versioned(),
+                500,
+                HeaderList::empty(),
+            ),
+        })
+    }
+}
+
+/// Adds the `cache-control` header of the route segment config to the headers
+/// of a generated image, or [DEFAULT_IMAGE_CACHE_CONTROL] when neither the
+/// image nor the config specify one.
+#[turbo_tasks::function]
+async fn image_headers(
+    headers: Vc<HeaderList>,
+    config: Vc<SegmentConfig>,
+) -> Result<Vc<HeaderList>> {
+    let headers_with_config = apply_segment_config_headers(headers, config);
+    let headers = headers_with_config.await?;
+    if headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("cache-control"))
+    {
+        return Ok(headers_with_config);
+    }
+    let mut headers = headers.clone_value();
+    headers.push((
+        "cache-control".to_string(),
+        DEFAULT_IMAGE_CACHE_CONTROL.to_string(),
+    ));
+    Ok(Vc::cell(headers))
+}
+
+#[turbo_tasks::function]
+fn introspectable_type() -> Vc<String> {
+    Vc::cell("node image content source".to_string())
+}
+
+#[turbo_tasks::value_impl]
+impl Introspectable for NodeImageContentSource {
+    #[turbo_tasks::function]
+    fn ty(&self) -> Vc<String> {
+        introspectable_type()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<String> {
+        self.pathname
+    }
+
+    #[turbo_tasks::function]
+    async fn details(&self) -> Result<Vc<String>> {
+        Ok(Vc::cell(format!(
+            "base: {:?}\ntype: {:?}",
+            self.base_segments, self.route_type
+        )))
+    }
+
+    #[turbo_tasks::function]
+    async fn children(&self) -> Result<Vc<IntrospectableChildren>> {
+        let mut set = IndexSet::new();
+        for &entry in self.entry.entries().await?.iter() {
+            let entry = entry.await?;
+            set.insert((
+                Vc::cell("module".to_string()),
+                IntrospectableModule::new(Vc::upcast(entry.module)),
+            ));
+            set.insert((
+                Vc::cell("intermediate asset".to_string()),
+                IntrospectableOutputAsset::new(get_intermediate_asset(
+                    entry.chunking_context,
+                    entry.module,
+                    entry.runtime_entries,
+                )),
+            ));
+        }
+        Ok(Vc::cell(set))
+    }
+}
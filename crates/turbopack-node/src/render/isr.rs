@@ -0,0 +1,451 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use turbo_tasks::{get_invalidator, Completion, Invalidator, ReadRef, Vc};
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fs::FileSystemPath;
+use turbo_tasks_hash::hash_xxh3_hash64;
+use turbopack_core::chunk::{ChunkingContext, EvaluatableAsset, EvaluatableAssets};
+use turbopack_dev_server::html::DevHtmlAsset;
+
+use super::{
+    render_static::{render_static, render_static_with_data_fetching, StaticResult},
+    RenderData,
+};
+
+/// The maximum number of renders in the [IsrCache]. The least recently
+/// requested ones are evicted first.
+const MAX_ENTRIES: usize = 1000;
+
+/// The renders of a page for one request, e. g. its HTML or its data request.
+struct IsrEntry {
+    /// The render data of the request which created the entry. All
+    /// generations are rendered with it, so requests which only differ in
+    /// e. g. their headers share the renders, see [entry_render_data].
+    data: ReadRef<RenderData>,
+    /// The generation whose render is served. Each generation is rendered by
+    /// a task of its own, see [render_generation].
+    served: Option<u32>,
+    /// The generation rendered in the background, while `served` is served.
+    pending: Option<u32>,
+    /// The tasks serving this entry, which are invalidated when a new
+    /// generation is served or rendering it starts.
+    serving: HashSet<Invalidator>,
+    last_used: Instant,
+}
+
+impl IsrEntry {
+    fn invalidate_serving(&mut self) {
+        for invalidator in self.serving.drain() {
+            invalidator.invalidate();
+        }
+    }
+
+    /// Starts rendering the next generation in the background, unless it is
+    /// already rendering or nothing was rendered yet.
+    fn revalidate(&mut self) {
+        if let (Some(served), None) = (self.served, self.pending) {
+            self.pending = Some(served + 1);
+            self.invalidate_serving();
+        }
+    }
+}
+
+/// The cache of Incremental Static Regeneration (ISR).
+///
+/// Rendered pages are served from the cache until their revalidate period has
+/// passed or they were revalidated on demand. After that, the stale render is
+/// still served while the page is rendered again in the background, and the
+/// new render replaces it once it completes. A failed render keeps the stale
+/// one.
+///
+/// Entries are keyed by the URL path of the page, which
+/// [IsrCache::revalidate] accepts, and a hash of the parts of the
+/// [RenderData] a static render depends on, see [cache_key]. At most
+/// [MAX_ENTRIES] renders are cached.
+#[turbo_tasks::value(cell = "new", serialization = "none", eq = "manual")]
+pub struct IsrCache {
+    #[turbo_tasks(trace_ignore, debug_ignore)]
+    entries: Mutex<HashMap<String, HashMap<u64, IsrEntry>>>,
+}
+
+impl IsrCache {
+    /// Revalidates all renders of the URL path `path`, e. g. `/blog/hello`.
+    pub fn revalidate(&self, path: &str) {
+        if let Some(entries) = self.entries.lock().get_mut(path) {
+            entries.values_mut().for_each(IsrEntry::revalidate);
+        }
+    }
+
+    /// Revalidates all renders.
+    pub fn revalidate_all(&self) {
+        for entries in self.entries.lock().values_mut() {
+            entries.values_mut().for_each(IsrEntry::revalidate);
+        }
+    }
+
+    /// Registers the current task as serving the entry, which is created with
+    /// `data` when it doesn't exist, and returns the served and the pending
+    /// generation.
+    fn register(
+        &self,
+        path: &str,
+        hash: u64,
+        data: &ReadRef<RenderData>,
+    ) -> (Option<u32>, Option<u32>) {
+        let mut entries = self.entries.lock();
+        let entry = entries
+            .entry(path.to_string())
+            .or_default()
+            .entry(hash)
+            .or_insert_with(|| IsrEntry {
+                data: data.clone(),
+                served: None,
+                pending: None,
+                serving: HashSet::new(),
+                last_used: Instant::now(),
+            });
+        entry.serving.insert(get_invalidator());
+        entry.last_used = Instant::now();
+        let generations = (entry.served, entry.pending);
+        evict_least_recently_used(&mut entries);
+        generations
+    }
+
+    /// The render data the generations of the entry are rendered with.
+    fn render_data(&self, path: &str, hash: u64) -> Option<ReadRef<RenderData>> {
+        self.entries
+            .lock()
+            .get(path)
+            .and_then(|entries| entries.get(&hash))
+            .map(|entry| entry.data.clone())
+    }
+
+    /// Serves the completed render of `generation`.
+    fn complete(&self, path: &str, hash: u64, generation: u32) {
+        let mut entries = self.entries.lock();
+        let Some(entry) = entries
+            .get_mut(path)
+            .and_then(|entries| entries.get_mut(&hash))
+        else {
+            return;
+        };
+        if entry.pending == Some(generation) {
+            entry.served = Some(generation);
+            entry.pending = None;
+            entry.invalidate_serving();
+        } else if entry.served.is_none() {
+            // The first render, which the serving task waited for
+            entry.served = Some(generation);
+        }
+    }
+
+    /// Drops the failed render of `generation`, the served one stays.
+    fn fail(&self, path: &str, hash: u64, generation: u32) {
+        let mut entries = self.entries.lock();
+        let Some(entry) = entries
+            .get_mut(path)
+            .and_then(|entries| entries.get_mut(&hash))
+        else {
+            return;
+        };
+        if entry.pending == Some(generation) {
+            entry.pending = None;
+        }
+    }
+
+    /// Revalidates the entry when `generation` is still served after the
+    /// revalidate period.
+    fn expire(&self, path: &str, hash: u64, generation: u32) {
+        let mut entries = self.entries.lock();
+        let Some(entry) = entries
+            .get_mut(path)
+            .and_then(|entries| entries.get_mut(&hash))
+        else {
+            return;
+        };
+        if entry.served == Some(generation) {
+            entry.revalidate();
+        }
+    }
+}
+
+/// Evicts the least recently used render when there are more than
+/// [MAX_ENTRIES]. The tasks serving it are invalidated, so they register a new
+/// entry when they are requested again instead of never being revalidated.
+fn evict_least_recently_used(entries: &mut HashMap<String, HashMap<u64, IsrEntry>>) {
+    if entries.values().map(HashMap::len).sum::<usize>() <= MAX_ENTRIES {
+        return;
+    }
+    let Some((path, hash)) = entries
+        .iter()
+        .flat_map(|(path, renders)| {
+            renders
+                .iter()
+                .map(move |(hash, entry)| (path, *hash, entry.last_used))
+        })
+        .min_by_key(|(_, _, last_used)| *last_used)
+        .map(|(path, hash, _)| (path.clone(), hash))
+    else {
+        return;
+    };
+    let Some(renders) = entries.get_mut(&path) else {
+        return;
+    };
+    if let Some(mut entry) = renders.remove(&hash) {
+        entry.invalidate_serving();
+    }
+    if renders.is_empty() {
+        entries.remove(&path);
+    }
+}
+
+/// The ISR cache shared by all renderings.
+#[turbo_tasks::function]
+pub fn isr_cache() -> Vc<IsrCache> {
+    IsrCache {
+        entries: Default::default(),
+    }
+    .cell()
+}
+
+/// Renders a module like [render_static], or like
+/// [render_static_with_data_fetching] with `data_fetching`, with its result
+/// cached by the [IsrCache].
+///
+/// `revalidate` is the revalidate period in seconds. Without one, the render is
+/// only revalidated on demand. Preview requests always render the page.
+#[turbo_tasks::function]
+pub async fn render_static_isr(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    path: Vc<FileSystemPath>,
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    fallback_page: Vc<DevHtmlAsset>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+    data: Vc<RenderData>,
+    debug: bool,
+    data_fetching: bool,
+    revalidate: Option<u32>,
+) -> Result<Vc<StaticResult>> {
+    let render_data = data.await?;
+    if render_data.preview {
+        return Ok(render_static_uncached(
+            cwd,
+            env,
+            path,
+            module,
+            runtime_entries,
+            fallback_page,
+            chunking_context,
+            intermediate_output_path,
+            output_root,
+            project_dir,
+            data,
+            debug,
+            data_fetching,
+        ));
+    }
+
+    let (url_path, hash) = cache_key(&render_data)?;
+    let render = |generation| {
+        render_generation(
+            cwd,
+            env,
+            path,
+            module,
+            runtime_entries,
+            fallback_page,
+            chunking_context,
+            intermediate_output_path,
+            output_root,
+            project_dir,
+            entry_render_data(url_path.clone(), hash),
+            debug,
+            data_fetching,
+            generation,
+        )
+    };
+    let cache = isr_cache().await?;
+    Ok(match cache.register(&url_path, hash, &render_data) {
+        (Some(served), pending) => {
+            if let Some(pending) = pending {
+                // Not awaited, the served render is returned while it runs
+                let _ = complete_generation(
+                    render(pending),
+                    url_path.clone(),
+                    hash,
+                    pending,
+                    revalidate,
+                );
+            }
+            render(served)
+        }
+        (None, _) => {
+            let result = render(0);
+            complete_generation(result, url_path, hash, 0, revalidate).await?;
+            result
+        }
+    })
+}
+
+/// Serves the render of `generation` once it completed, and expires it after
+/// the `revalidate` period.
+#[turbo_tasks::function]
+async fn complete_generation(
+    result: Vc<StaticResult>,
+    url_path: String,
+    hash: u64,
+    generation: u32,
+    revalidate: Option<u32>,
+) -> Result<Vc<Completion>> {
+    let cache = isr_cache().await?;
+    if generation > 0 && matches!(*result.await?, StaticResult::Error { .. }) {
+        cache.fail(&url_path, hash, generation);
+        return Ok(Completion::new());
+    }
+    cache.complete(&url_path, hash, generation);
+    if let Some(seconds) = revalidate.filter(|&seconds| seconds > 0) {
+        tokio::spawn(expire_after(
+            cache,
+            url_path,
+            hash,
+            generation,
+            Duration::from_secs(seconds.into()),
+        ));
+    }
+    Ok(Completion::new())
+}
+
+async fn expire_after(
+    cache: ReadRef<IsrCache>,
+    url_path: String,
+    hash: u64,
+    generation: u32,
+    revalidate: Duration,
+) {
+    tokio::time::sleep(revalidate).await;
+    cache.expire(&url_path, hash, generation);
+}
+
+/// The render data of the [IsrEntry] of `url_path` and `hash`, in a cell which
+/// is shared by all requests of the entry.
+#[turbo_tasks::function]
+async fn entry_render_data(url_path: String, hash: u64) -> Result<Vc<RenderData>> {
+    Ok(isr_cache()
+        .await?
+        .render_data(&url_path, hash)
+        .with_context(|| format!("{url_path} is not in the ISR cache"))?
+        .clone_value()
+        .cell())
+}
+
+/// Renders `generation` of a page. The render data is copied into a cell of
+/// this task, so each generation is a render task of its own instead of
+/// the cached render of the previous generation.
+#[turbo_tasks::function]
+async fn render_generation(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    path: Vc<FileSystemPath>,
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    fallback_page: Vc<DevHtmlAsset>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+    data: Vc<RenderData>,
+    debug: bool,
+    data_fetching: bool,
+    _generation: u32,
+) -> Result<Vc<StaticResult>> {
+    let data = data.await?.clone_value().cell();
+    Ok(render_static_uncached(
+        cwd,
+        env,
+        path,
+        module,
+        runtime_entries,
+        fallback_page,
+        chunking_context,
+        intermediate_output_path,
+        output_root,
+        project_dir,
+        data,
+        debug,
+        data_fetching,
+    ))
+}
+
+/// Renders a page without the [IsrCache].
+fn render_static_uncached(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    path: Vc<FileSystemPath>,
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    fallback_page: Vc<DevHtmlAsset>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+    data: Vc<RenderData>,
+    debug: bool,
+    data_fetching: bool,
+) -> Vc<StaticResult> {
+    let render = if data_fetching {
+        render_static_with_data_fetching
+    } else {
+        render_static
+    };
+    render(
+        cwd,
+        env,
+        path,
+        module,
+        runtime_entries,
+        fallback_page,
+        chunking_context,
+        intermediate_output_path,
+        output_root,
+        project_dir,
+        data,
+        debug,
+    )
+}
+
+/// The URL path of the page, without its query, and a hash of the route
+/// params, the locale, the build ID and whether the props of the page are
+/// requested. Everything else of a request, e. g. its headers and cookies,
+/// doesn't affect a static render. Data requests
+/// (`/_next/data/<buildId>/<page>.json`) have the URL path of their page, so
+/// revalidating the page revalidates them too.
+fn cache_key(data: &RenderData) -> Result<(String, u64)> {
+    let url_path = data.url.split('?').next().unwrap_or_default();
+    let page = url_path
+        .strip_prefix("/_next/data/")
+        .and_then(|page| page.strip_prefix(data.build_id.as_str()))
+        .and_then(|page| page.strip_suffix(".json"))
+        .filter(|_| data.data_request);
+    let url_path = match page {
+        Some("/index") => "/".to_string(),
+        Some(page) => page.to_string(),
+        None => url_path.to_string(),
+    };
+    let key = serde_json::to_string(&(
+        &data.params,
+        &data.locale,
+        &data.build_id,
+        data.data_request,
+    ))?;
+    Ok((url_path, hash_xxh3_hash64(key)))
+}
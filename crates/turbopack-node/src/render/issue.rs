@@ -1,21 +1,39 @@
 use anyhow::Result;
 use turbo_tasks::Vc;
 use turbo_tasks_fs::FileSystemPath;
-use turbopack_core::issue::{Issue, IssueStage, OptionStyledString, StyledString};
+use turbopack_core::issue::{
+    Issue, IssueSeverity, IssueStage, OptionIssueSource, OptionStyledString, StyledString,
+};
 
+/// A problem of a render: an error failing it, or a warning the page runtime
+/// reported without failing it, e. g. a hydration mismatch.
 #[turbo_tasks::value(shared)]
 #[derive(Copy, Clone)]
 pub struct RenderingIssue {
     pub file_path: Vc<FileSystemPath>,
     pub message: Vc<StyledString>,
     pub status: Option<i32>,
+    /// The location in the original project file which threw, see
+    /// [crate::source_map::trace_issue_source].
+    pub source: Vc<OptionIssueSource>,
+    pub severity: IssueSeverity,
 }
 
 #[turbo_tasks::value_impl]
 impl Issue for RenderingIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        self.severity.cell()
+    }
+
     #[turbo_tasks::function]
     fn title(&self) -> Vc<StyledString> {
-        StyledString::Text("Error during SSR Rendering".to_string()).cell()
+        let title = match self.severity {
+            IssueSeverity::Warning => "Warning during SSR Rendering",
+            IssueSeverity::Info => "Info during SSR Rendering",
+            _ => "Error during SSR Rendering",
+        };
+        StyledString::Text(title.to_string()).cell()
     }
 
     #[turbo_tasks::function]
@@ -46,5 +64,125 @@ impl Issue for RenderingIssue {
         Ok(Vc::cell(Some(StyledString::Stack(details).cell())))
     }
 
-    // TODO parse stack trace into source location
+    #[turbo_tasks::function]
+    fn source(&self) -> Vc<OptionIssueSource> {
+        self.source
+    }
+}
+
+/// A segment of a page whose rendering failed and which was rendered with the
+/// fallback of its error boundary, while the rest of the page was served.
+#[turbo_tasks::value(shared)]
+pub struct ErroredSegmentIssue {
+    pub file_path: Vc<FileSystemPath>,
+    pub page: String,
+    pub segment: String,
+    pub message: Vc<StyledString>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ErroredSegmentIssue {
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(format!(
+            "Error during SSR Rendering of segment {} of {}",
+            self.segment, self.page
+        ))
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::CodeGen.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(self.message))
+    }
+
+    #[turbo_tasks::function]
+    fn detail(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(
+                "The segment was rendered with the fallback of its error boundary.".to_string(),
+            )
+            .cell(),
+        ))
+    }
+}
+
+/// A render which took longer than the slow render threshold, with the hot
+/// stacks of its CPU profile when the page runtime captured one.
+#[turbo_tasks::value(shared)]
+pub struct SlowRenderIssue {
+    pub file_path: Vc<FileSystemPath>,
+    pub page: String,
+    pub duration_ms: u64,
+    pub threshold_ms: u64,
+    /// The frames of each hot stack, innermost first, and the CPU time spent
+    /// in its innermost frame.
+    pub hot_stacks: Vec<(Vec<String>, u64)>,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for SlowRenderIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Warning.cell()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(format!(
+            "Rendering {} took {}ms",
+            self.page, self.duration_ms
+        ))
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Other("render".to_string()).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(
+            StyledString::Text(format!(
+                "The render exceeded the slow render threshold of {}ms.",
+                self.threshold_ms
+            ))
+            .cell(),
+        ))
+    }
+
+    #[turbo_tasks::function]
+    fn detail(&self) -> Vc<OptionStyledString> {
+        if self.hot_stacks.is_empty() {
+            return Vc::cell(None);
+        }
+        let mut details = vec![StyledString::Text("Hot stacks:".to_string())];
+        for (frames, self_micros) in &self.hot_stacks {
+            details.push(StyledString::Text(format!(
+                "{}ms in {}",
+                self_micros / 1000,
+                frames.first().map_or("(unknown)", String::as_str)
+            )));
+            for frame in frames.iter().skip(1) {
+                details.push(StyledString::Text(format!("    at {frame}")));
+            }
+        }
+        Vc::cell(Some(StyledString::Stack(details).cell()))
+    }
 }
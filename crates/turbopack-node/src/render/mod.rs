@@ -1,34 +1,517 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value as JsonValue;
-use turbo_tasks::ReadRef;
+use turbo_tasks::{trace::TraceRawVcs, TaskInput, Vc};
+use turbo_tasks_fs::FileSystemPath;
+use turbopack_core::{
+    issue::{IssueExt, IssueSeverity, StyledString},
+    output::OutputAsset,
+};
+use turbopack_dev_server::source::{
+    headers::Headers, query::Query, ContentSourceData, ContentSourceDataFilter,
+    ContentSourceDataVary,
+};
+use turbopack_ecmascript::i18n::MessageCatalogs;
 
-use crate::{route_matcher::Param, ResponseHeaders, StructuredError};
+use self::{
+    cookies::{parse_cookies, SetCookie},
+    issue::RenderingIssue,
+    stats::{RenderUsage, SlowRenderProfile},
+    styles::CollectedStyle,
+};
+use crate::{
+    pool::NodeJsPoolOptions,
+    route_matcher::Param,
+    source_map::{trace_issue_source, trace_stack},
+    ResponseHeaders, StructuredError,
+};
 
+pub mod cookies;
 pub(crate) mod error_page;
+pub mod fetch_cache;
+pub mod fetch_cassette;
+pub mod html_transform;
+pub mod image_source;
+pub mod isr;
 pub mod issue;
 pub mod node_api_source;
+pub(crate) mod operation;
 pub mod render_proxy;
 pub mod render_static;
 pub mod rendered_source;
+pub mod segment_config;
+pub mod static_export;
+pub mod stats;
+pub mod styles;
+pub mod warm_up;
+
+/// The version of the [RenderData] contract between Rust and the page
+/// runtime. Must be bumped on every incompatible change, so that intermediate
+/// bundles built against another version fail loudly instead of misrendering.
+///
+/// Version 1 was the free-form render data without version negotiation,
+/// version 2 had no parsed cookies, version 3 had no `getInitialProps` and
+/// error page data, version 4 had no data requests, version 5 had no message
+/// catalogs, version 6 had no base64 encoded body chunks, version 7 had no
+/// data fetching phase.
+pub const RENDER_PROTOCOL_VERSION: u32 = 8;
+
+/// Framework data shared by all renders of a page, e. g. the build ID.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug, Default)]
+pub struct RenderConfig {
+    pub build_id: String,
+    /// The locales supported by the app. The locale of a request is detected
+    /// from the first segment of its url.
+    pub locales: Vec<String>,
+    pub default_locale: Option<String>,
+    pub session_affinity: SessionAffinity,
+    /// Renders taking longer are reported as issues, with a CPU profile when
+    /// the page runtime supports it.
+    pub slow_render_threshold_ms: Option<u64>,
+    /// Renders taking longer are aborted, their worker is killed and an issue
+    /// is reported. By default renders can take as long as they need.
+    pub render_timeout_ms: Option<u64>,
+    /// The sizing of the pools of Node.js processes rendering the pages.
+    pub pool_options: NodeJsPoolOptions,
+    /// The i18n messages of each locale, see [RenderData::messages].
+    pub message_catalogs: IndexMap<String, JsonValue>,
+}
+
+#[turbo_tasks::value_impl]
+impl RenderConfig {
+    #[turbo_tasks::function]
+    pub fn new(build_id: String, locales: Vec<String>, default_locale: Option<String>) -> Vc<Self> {
+        RenderConfig {
+            build_id,
+            locales,
+            default_locale,
+            session_affinity: Default::default(),
+            slow_render_threshold_ms: None,
+            render_timeout_ms: None,
+            pool_options: Default::default(),
+            message_catalogs: IndexMap::new(),
+        }
+        .cell()
+    }
+
+    #[turbo_tasks::function]
+    pub async fn with_pool_options(
+        self: Vc<Self>,
+        pool_options: NodeJsPoolOptions,
+    ) -> Result<Vc<Self>> {
+        let mut config = self.await?.clone_value();
+        config.pool_options = pool_options;
+        Ok(config.cell())
+    }
+
+    #[turbo_tasks::function]
+    pub async fn with_slow_render_threshold(
+        self: Vc<Self>,
+        slow_render_threshold_ms: Option<u64>,
+    ) -> Result<Vc<Self>> {
+        let mut config = self.await?.clone_value();
+        config.slow_render_threshold_ms = slow_render_threshold_ms;
+        Ok(config.cell())
+    }
+
+    /// Passes the messages of the locale of each request to the page runtime,
+    /// e. g. the catalogs extracted with
+    /// [turbopack_ecmascript::i18n::extract_message_catalogs].
+    #[turbo_tasks::function]
+    pub async fn with_message_catalogs(
+        self: Vc<Self>,
+        message_catalogs: Vc<MessageCatalogs>,
+    ) -> Result<Vc<Self>> {
+        let mut config = self.await?.clone_value();
+        config.message_catalogs = message_catalogs.await?.clone_value();
+        Ok(config.cell())
+    }
+
+    #[turbo_tasks::function]
+    pub async fn with_render_timeout(
+        self: Vc<Self>,
+        render_timeout_ms: Option<u64>,
+    ) -> Result<Vc<Self>> {
+        let mut config = self.await?.clone_value();
+        config.render_timeout_ms = render_timeout_ms;
+        Ok(config.cell())
+    }
+
+    #[turbo_tasks::function]
+    pub async fn with_session_affinity(
+        self: Vc<Self>,
+        session_affinity: SessionAffinity,
+    ) -> Result<Vc<Self>> {
+        let mut config = self.await?.clone_value();
+        config.session_affinity = session_affinity;
+        Ok(config.cell())
+    }
+}
 
+/// Disables session affinity regardless of the [RenderConfig], e. g. to check
+/// whether a page depends on state of a worker.
+pub const DISABLE_SESSION_AFFINITY_ENV: &str = "TURBOPACK_DISABLE_SESSION_AFFINITY";
+
+/// Routes renders to the Node.js workers of the renderer pool, for dev setups
+/// keeping in-memory state in the workers (mock databases, warm caches).
+///
+/// Routing is best effort, see
+/// [crate::pool::NodeJsPool::operation_with_affinity]: a render goes to another
+/// worker when the worker of its session is busy, and state is lost when a
+/// worker crashes or the pool is recreated after a change. Pages that depend on
+/// it behave differently in production, where requests are spread across
+/// instances.
+#[derive(
+    TaskInput, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs,
+)]
+pub enum SessionAffinity {
+    /// Renders go to any idle worker.
+    #[default]
+    Disabled,
+    /// Renders of the same page go to the same worker.
+    Page,
+    /// Renders of requests with the same value of the cookie go to the same
+    /// worker. Requests without the cookie are routed by page.
+    Cookie(String),
+}
+
+/// The messages of `locale` from the catalogs of the [RenderConfig], falling
+/// back to the default locale.
+fn messages(config: &RenderConfig, locale: Option<&str>) -> Option<JsonValue> {
+    locale
+        .and_then(|locale| config.message_catalogs.get(locale))
+        .or_else(|| {
+            config
+                .default_locale
+                .as_ref()
+                .and_then(|locale| config.message_catalogs.get(locale))
+        })
+        .cloned()
+}
+
+/// The error of a failed render, passed to the custom error page
+/// (`pages/_error`) which is rendered instead.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, TraceRawVcs)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderError {
+    pub status_code: u16,
+    pub message: String,
+}
+
+/// The data passed to the page runtime for each request. Mirrored by
+/// `@vercel/turbopack-node/render-data`, which validates it on the Node.js
+/// side.
 #[turbo_tasks::value(shared)]
+#[derive(Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct RenderData {
+    protocol_version: u32,
     params: IndexMap<String, Param>,
     method: String,
     url: String,
     original_url: String,
+    query: Query,
     raw_query: String,
+    headers: Headers,
     raw_headers: Vec<(String, String)>,
+    /// The cookies of the request, parsed from its `cookie` headers.
+    cookies: IndexMap<String, String>,
+    locale: Option<String>,
+    /// The i18n messages of `locale`, or of the default locale when there is
+    /// no catalog for it. The page runtime passes them to the i18n library
+    /// for the server render and embeds them into the page for hydration.
+    messages: Option<JsonValue>,
+    /// Whether the request has preview (draft) mode enabled via the
+    /// `__prerender_bypass` cookie.
+    preview: bool,
+    build_id: String,
+    /// The A/B experiment arms selected for the request, see
+    /// [experiment_arms].
+    experiment_arms: IndexMap<String, String>,
     path: String,
-    data: Option<ReadRef<JsonValue>>,
+    /// The page runtime should capture a CPU profile of renders taking
+    /// longer, see [RenderConfig::slow_render_threshold_ms].
+    slow_render_threshold_ms: Option<u64>,
+    /// The page defines the legacy `getInitialProps` method. The page runtime
+    /// calls it before rendering and merges its result into the props of the
+    /// page.
+    get_initial_props: bool,
+    /// The error of the failed render of another page when rendering the
+    /// custom error page. The page runtime passes it to the `getInitialProps`
+    /// of the error page as `err`.
+    error: Option<RenderError>,
+    /// The request fetches the props of the page as JSON for a client-side
+    /// navigation (`/_next/data/<buildId>/<page>.json`) instead of its HTML.
+    /// The page runtime responds with the JSON, from the same data fetching
+    /// as the HTML.
+    data_request: bool,
+    /// The result of the data fetching phase of the page (e. g. its
+    /// `getServerSideProps`), see
+    /// [render_static::render_static_with_data_fetching]. The page runtime
+    /// renders the page with it instead of fetching its data again.
+    data: Option<JsonValue>,
+    /// The key renders are routed to workers by, see [SessionAffinity]. Not
+    /// part of the render contract.
+    #[serde(skip)]
+    affinity_key: Option<String>,
+    /// The sizing of the pool rendering the page, see
+    /// [RenderConfig::pool_options]. Not part of the render contract.
+    #[serde(skip)]
+    pool_options: NodeJsPoolOptions,
+    /// See [RenderConfig::render_timeout_ms]. Not part of the render contract.
+    #[serde(skip)]
+    render_timeout_ms: Option<u64>,
+}
+
+impl RenderData {
+    /// Creates the render data for a request. `data` must contain everything
+    /// requested by [RenderData::vary].
+    pub(crate) fn new(
+        config: &RenderConfig,
+        params: IndexMap<String, Param>,
+        data: &ContentSourceData,
+        path: String,
+    ) -> Result<Self> {
+        let ContentSourceData {
+            method: Some(method),
+            url: Some(url),
+            original_url: Some(original_url),
+            query: Some(query),
+            raw_query: Some(raw_query),
+            headers: Some(headers),
+            raw_headers: Some(raw_headers),
+            ..
+        } = data
+        else {
+            bail!("Missing request data");
+        };
+        let locale = url
+            .trim_start_matches('/')
+            .split(['/', '?'])
+            .next()
+            .filter(|segment| config.locales.iter().any(|locale| locale == segment))
+            .map(|segment| segment.to_string())
+            .or_else(|| config.default_locale.clone());
+        let cookies = parse_cookies(raw_headers);
+        let affinity_key = if std::env::var_os(DISABLE_SESSION_AFFINITY_ENV).is_some() {
+            None
+        } else {
+            match &config.session_affinity {
+                SessionAffinity::Disabled => None,
+                SessionAffinity::Page => Some(format!("page:{path}")),
+                SessionAffinity::Cookie(name) => Some(match cookies.get(name) {
+                    Some(session) => format!("session:{session}"),
+                    None => format!("page:{path}"),
+                }),
+            }
+        };
+        let render_data = RenderData {
+            protocol_version: RENDER_PROTOCOL_VERSION,
+            params,
+            method: method.clone(),
+            url: url.clone(),
+            original_url: original_url.clone(),
+            query: query.clone(),
+            raw_query: raw_query.clone(),
+            headers: headers.clone(),
+            raw_headers: raw_headers.clone(),
+            preview: cookies.contains_key("__prerender_bypass"),
+            cookies,
+            messages: messages(config, locale.as_deref()),
+            locale,
+            build_id: config.build_id.clone(),
+            experiment_arms: experiment_arms(raw_headers),
+            path,
+            slow_render_threshold_ms: config.slow_render_threshold_ms,
+            get_initial_props: false,
+            error: None,
+            data_request: false,
+            data: None,
+            affinity_key,
+            pool_options: config.pool_options.clone(),
+            render_timeout_ms: config.render_timeout_ms,
+        };
+        render_data.validate()?;
+        Ok(render_data)
+    }
+
+    /// Creates the render data for a GET request of `path` which only depends
+    /// on the route `params`, e. g. for generated images. Its renders are
+    /// cached by the params, independent of the headers of the request.
+    pub(crate) fn for_params(
+        config: &RenderConfig,
+        params: IndexMap<String, Param>,
+        path: String,
+    ) -> Result<Self> {
+        let render_data = RenderData {
+            protocol_version: RENDER_PROTOCOL_VERSION,
+            params,
+            method: "GET".to_string(),
+            url: path.clone(),
+            original_url: path.clone(),
+            query: Query::default(),
+            raw_query: String::new(),
+            headers: Headers::default(),
+            raw_headers: Vec::new(),
+            cookies: IndexMap::new(),
+            messages: messages(config, config.default_locale.as_deref()),
+            locale: config.default_locale.clone(),
+            preview: false,
+            build_id: config.build_id.clone(),
+            experiment_arms: IndexMap::new(),
+            path,
+            slow_render_threshold_ms: config.slow_render_threshold_ms,
+            get_initial_props: false,
+            error: None,
+            data_request: false,
+            data: None,
+            affinity_key: None,
+            pool_options: config.pool_options.clone(),
+            render_timeout_ms: config.render_timeout_ms,
+        };
+        render_data.validate()?;
+        Ok(render_data)
+    }
+
+    /// The request data needed to create [RenderData].
+    pub(crate) fn vary() -> ContentSourceDataVary {
+        ContentSourceDataVary {
+            method: true,
+            url: true,
+            original_url: true,
+            query: Some(ContentSourceDataFilter::All),
+            raw_query: true,
+            headers: Some(ContentSourceDataFilter::All),
+            raw_headers: true,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn with_get_initial_props(mut self, get_initial_props: bool) -> Self {
+        self.get_initial_props = get_initial_props;
+        self
+    }
+
+    pub(crate) fn with_error(mut self, error: RenderError) -> Self {
+        self.error = Some(error);
+        self
+    }
+
+    pub(crate) fn with_data_request(mut self, data_request: bool) -> Self {
+        self.data_request = data_request;
+        self
+    }
+
+    pub(crate) fn with_data(mut self, data: JsonValue) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// The render data of the data fetching phase of this render. Options
+    /// which only affect the render phase are reset, so HTML and data
+    /// requests of a page share its data fetching.
+    pub(crate) fn for_data_fetching(&self) -> Self {
+        RenderData {
+            get_initial_props: false,
+            data_request: false,
+            data: None,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn affinity_key(&self) -> Option<&str> {
+        self.affinity_key.as_deref()
+    }
+
+    pub(crate) fn pool_options(&self) -> &NodeJsPoolOptions {
+        &self.pool_options
+    }
+
+    pub(crate) fn render_timeout(&self) -> Option<Duration> {
+        self.render_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// The pathname of the page as requested by the browser, which the
+    /// console output of the render is forwarded to.
+    pub(crate) fn page(&self) -> &str {
+        self.original_url
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.method.is_empty() {
+            bail!("render data has an empty method");
+        }
+        for (name, url) in [
+            ("url", &self.url),
+            ("originalUrl", &self.original_url),
+            ("path", &self.path),
+        ] {
+            if !url.starts_with('/') {
+                bail!("render data {name} `{url}` is not absolute");
+            }
+        }
+        if self.build_id.contains('/') {
+            bail!("render data build ID `{}` contains a slash", self.build_id);
+        }
+        Ok(())
+    }
+}
+
+/// Checks the render protocol version reported by the page runtime in its
+/// first response. Intermediate bundles from before the version negotiation
+/// don't report a version at all.
+fn check_protocol_version(version: Option<u32>) -> Result<()> {
+    match version {
+        Some(RENDER_PROTOCOL_VERSION) => Ok(()),
+        Some(version) => bail!(
+            "The page runtime uses render protocol version {version}, but version \
+             {RENDER_PROTOCOL_VERSION} is required. Rebuild the intermediate bundle with a \
+             matching version."
+        ),
+        None => bail!(
+            "The page runtime didn't report a render protocol version, it was probably built for \
+             an older version. Version {RENDER_PROTOCOL_VERSION} is required."
+        ),
+    }
+}
+
+/// Parses the A/B experiment arms selected for a request from the
+/// `x-experiment-arms` header, e. g. `checkout=b, header=a`.
+fn experiment_arms(raw_headers: &[(String, String)]) -> IndexMap<String, String> {
+    raw_headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("x-experiment-arms"))
+        .flat_map(|(_, value)| value.split(','))
+        .filter_map(|pair| {
+            let (experiment, arm) = pair.split_once('=')?;
+            Some((experiment.trim().to_string(), arm.trim().to_string()))
+        })
+        .collect()
 }
 
 #[derive(Serialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 enum RenderStaticOutgoingMessage<'a> {
-    Headers { data: &'a RenderData },
+    Headers {
+        data: &'a RenderData,
+    },
+    /// Starts the data fetching phase of a render, which the page runtime
+    /// answers with a [RenderStaticIncomingMessage::Data] message.
+    DataFetching {
+        data: &'a RenderData,
+    },
+    FetchCacheResult {
+        data: Option<JsonValue>,
+        stale: bool,
+    },
 }
 
 #[derive(Serialize)]
@@ -42,12 +525,167 @@ enum RenderProxyOutgoingMessage<'a> {
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type", rename_all = "camelCase")]
 enum RenderProxyIncomingMessage {
-    Headers { data: ResponseHeaders },
-    BodyChunk { data: Vec<u8> },
-    BodyEnd,
+    #[serde(rename_all = "camelCase")]
+    Headers {
+        data: ResponseHeaders,
+        #[serde(default)]
+        protocol_version: Option<u32>,
+        /// Cookies to set, added as `set-cookie` headers.
+        #[serde(default)]
+        cookies: Vec<SetCookie>,
+    },
+    BodyChunk {
+        #[serde(deserialize_with = "deserialize_body_chunk")]
+        data: Vec<u8>,
+    },
+    BodyEnd {
+        /// The resources used by the render.
+        #[serde(default)]
+        usage: Option<RenderUsage>,
+        /// The CPU profile of a render exceeding the slow render threshold.
+        #[serde(default)]
+        profile: Option<SlowRenderProfile>,
+        /// Non-fatal problems of the render, reported as issues.
+        #[serde(default)]
+        warnings: Vec<RenderWarning>,
+    },
+    /// Like [RenderStaticIncomingMessage::FileDependency]. Changing the file
+    /// runs the API route again.
+    FileDependency {
+        path: String,
+    },
+    /// Like [RenderStaticIncomingMessage::DirDependency].
+    DirDependency {
+        path: String,
+        glob: String,
+    },
+    /// Like [RenderStaticIncomingMessage::FetchCacheGet], sent by the patched
+    /// `fetch()` of the render process.
+    FetchCacheGet {
+        key: String,
+    },
+    /// Like [RenderStaticIncomingMessage::FetchCacheSet].
+    FetchCacheSet {
+        key: String,
+        data: JsonValue,
+        revalidate: Option<u32>,
+    },
+    /// Like [RenderStaticIncomingMessage::FetchRecord].
+    FetchRecord {
+        key: String,
+        data: JsonValue,
+    },
+    /// Like [RenderStaticIncomingMessage::FetchReplay].
+    FetchReplay {
+        key: String,
+    },
     Error(StructuredError),
 }
 
+/// A segment of a page (e. g. a layout or page of an app-router route) whose
+/// rendering failed, and which the page runtime rendered with the fallback of
+/// its error boundary. The rest of the page is served, and the error is
+/// reported as an issue.
+#[derive(Deserialize, Debug)]
+struct ErroredSegment {
+    /// Identifies the segment, e. g. `/dashboard/settings`.
+    segment: String,
+    error: StructuredError,
+}
+
+/// A non-fatal problem of a render, e. g. a hydration mismatch, which the page
+/// runtime reports without failing the render. It's reported as a
+/// [issue::RenderingIssue] of its severity, pointing at the project file the
+/// stack of `error` was raised in.
+#[derive(Deserialize, Debug)]
+struct RenderWarning {
+    #[serde(default)]
+    severity: RenderWarningSeverity,
+    error: StructuredError,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum RenderWarningSeverity {
+    #[default]
+    Warning,
+    Info,
+}
+
+impl From<RenderWarningSeverity> for IssueSeverity {
+    fn from(severity: RenderWarningSeverity) -> Self {
+        match severity {
+            RenderWarningSeverity::Warning => IssueSeverity::Warning,
+            RenderWarningSeverity::Info => IssueSeverity::Info,
+        }
+    }
+}
+
+/// Reports the warnings of a render as issues of `path`.
+async fn report_render_warnings(
+    path: Vc<FileSystemPath>,
+    warnings: Vec<RenderWarning>,
+    intermediate_asset: Vc<Box<dyn OutputAsset>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+) -> Result<()> {
+    for RenderWarning { severity, error } in warnings {
+        let source = trace_issue_source(
+            &error,
+            intermediate_asset,
+            intermediate_output_path,
+            project_dir,
+        )
+        .await?;
+        let trace = trace_stack(
+            error,
+            intermediate_asset,
+            intermediate_output_path,
+            project_dir,
+        )
+        .await?;
+        RenderingIssue {
+            file_path: path,
+            message: StyledString::Text(trace).cell(),
+            status: None,
+            source,
+            severity: severity.into(),
+        }
+        .cell()
+        .emit();
+    }
+    Ok(())
+}
+
+/// Deserializes the bytes of a streamed body chunk, which are sent base64
+/// encoded, or as an array of bytes.
+fn deserialize_body_chunk<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<u8>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BodyChunk {
+        Base64(String),
+        Bytes(Vec<u8>),
+    }
+    match BodyChunk::deserialize(deserializer)? {
+        BodyChunk::Base64(data) => BASE64.decode(data).map_err(serde::de::Error::custom),
+        BodyChunk::Bytes(data) => Ok(data),
+    }
+}
+
+/// How the body of a [RenderStaticIncomingMessage::Response] is encoded.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum BodyEncoding {
+    /// The body is the text of the response.
+    #[default]
+    Utf8,
+    /// The body is the base64 encoded bytes of the response, for binary
+    /// responses like images or PDFs.
+    Base64,
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type", rename_all = "camelCase")]
 enum RenderStaticIncomingMessage {
@@ -56,16 +694,99 @@ enum RenderStaticIncomingMessage {
         status_code: u16,
         headers: Vec<(String, String)>,
         body: String,
+        #[serde(default)]
+        body_encoding: BodyEncoding,
+        #[serde(default)]
+        protocol_version: Option<u32>,
+        /// Cookies to set, added as `set-cookie` headers.
+        #[serde(default)]
+        cookies: Vec<SetCookie>,
+        /// The resources used by the render.
+        #[serde(default)]
+        usage: Option<RenderUsage>,
+        /// The CPU profile of a render exceeding the slow render threshold.
+        #[serde(default)]
+        profile: Option<SlowRenderProfile>,
+        /// Segments rendered with the fallback of their error boundary.
+        #[serde(default)]
+        errored_segments: Vec<ErroredSegment>,
+        /// Non-fatal problems of the render, reported as issues.
+        #[serde(default)]
+        warnings: Vec<RenderWarning>,
+        /// Styles collected by CSS-in-JS libraries, injected into the
+        /// `<head>` of HTML documents.
+        #[serde(default)]
+        styles: Vec<CollectedStyle>,
     },
+    #[serde(rename_all = "camelCase")]
     Headers {
         data: ResponseHeaders,
+        #[serde(default)]
+        protocol_version: Option<u32>,
+        /// Cookies to set, added as `set-cookie` headers.
+        #[serde(default)]
+        cookies: Vec<SetCookie>,
+        /// Styles collected while rendering the shell, injected into the
+        /// `<head>` of the streamed HTML document.
+        #[serde(default)]
+        styles: Vec<CollectedStyle>,
     },
     BodyChunk {
+        #[serde(deserialize_with = "deserialize_body_chunk")]
         data: Vec<u8>,
     },
-    BodyEnd,
+    #[serde(rename_all = "camelCase")]
+    BodyEnd {
+        /// The resources used by the render.
+        #[serde(default)]
+        usage: Option<RenderUsage>,
+        /// The CPU profile of a render exceeding the slow render threshold.
+        #[serde(default)]
+        profile: Option<SlowRenderProfile>,
+        /// Segments rendered with the fallback of their error boundary.
+        #[serde(default)]
+        errored_segments: Vec<ErroredSegment>,
+        /// Non-fatal problems of the render, reported as issues.
+        #[serde(default)]
+        warnings: Vec<RenderWarning>,
+    },
     Rewrite {
         path: String,
     },
+    /// The result of the data fetching phase, passed to the render phase as
+    /// [RenderData::data].
+    #[serde(rename_all = "camelCase")]
+    Data {
+        data: JsonValue,
+        #[serde(default)]
+        protocol_version: Option<u32>,
+    },
+    FetchCacheGet {
+        key: String,
+    },
+    FetchCacheSet {
+        key: String,
+        data: JsonValue,
+        revalidate: Option<u32>,
+    },
+    FetchRecord {
+        key: String,
+        data: JsonValue,
+    },
+    FetchReplay {
+        key: String,
+    },
+    /// A file read by the render, e. g. the markdown content of a page,
+    /// relative to the working directory. Changing it renders the page
+    /// again, without rebuilding its bundles.
+    FileDependency {
+        path: String,
+    },
+    /// Like [RenderStaticIncomingMessage::FileDependency], for the files
+    /// matching `glob` in a directory.
+    DirDependency {
+        path: String,
+        glob: String,
+    },
     Error(StructuredError),
 }
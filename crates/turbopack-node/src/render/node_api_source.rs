@@ -1,6 +1,5 @@
 use anyhow::{anyhow, Result};
 use indexmap::IndexSet;
-use serde_json::Value as JsonValue;
 use turbo_tasks::{Value, Vc};
 use turbo_tasks_env::ProcessEnv;
 use turbo_tasks_fs::FileSystemPath;
@@ -14,7 +13,7 @@ use turbopack_dev_server::source::{
     GetContentSourceContent,
 };
 
-use super::{render_proxy::render_proxy, RenderData};
+use super::{render_proxy::render_proxy, RenderConfig, RenderData};
 use crate::{get_intermediate_asset, node_entry::NodeEntry, route_matcher::RouteMatcher};
 
 /// Creates a [NodeApiContentSource].
@@ -28,7 +27,7 @@ pub fn create_node_api_source(
     route_match: Vc<Box<dyn RouteMatcher>>,
     pathname: Vc<String>,
     entry: Vc<Box<dyn NodeEntry>>,
-    render_data: Vc<JsonValue>,
+    render_config: Vc<RenderConfig>,
     debug: bool,
 ) -> Vc<Box<dyn ContentSource>> {
     Vc::upcast(
@@ -41,7 +40,7 @@ pub fn create_node_api_source(
             pathname,
             route_match,
             entry,
-            render_data,
+            render_config,
             debug,
         }
         .cell(),
@@ -64,7 +63,7 @@ pub struct NodeApiContentSource {
     pathname: Vc<String>,
     route_match: Vc<Box<dyn RouteMatcher>>,
     entry: Vc<Box<dyn NodeEntry>>,
-    render_data: Vc<JsonValue>,
+    render_config: Vc<RenderConfig>,
     debug: bool,
 }
 
@@ -94,14 +93,9 @@ impl GetContentSourceContent for NodeApiContentSource {
     #[turbo_tasks::function]
     fn vary(&self) -> Vc<ContentSourceDataVary> {
         ContentSourceDataVary {
-            method: true,
-            url: true,
-            original_url: true,
-            raw_headers: true,
-            raw_query: true,
             body: true,
             cache_buster: true,
-            ..Default::default()
+            ..RenderData::vary()
         }
         .cell()
     }
@@ -115,41 +109,31 @@ impl GetContentSourceContent for NodeApiContentSource {
         let Some(params) = &*self.route_match.params(path.clone()).await? else {
             return Err(anyhow!("Non matching path provided"));
         };
-        let ContentSourceData {
-            method: Some(method),
-            url: Some(url),
-            original_url: Some(original_url),
-            raw_headers: Some(raw_headers),
-            raw_query: Some(raw_query),
-            body: Some(body),
-            ..
-        } = &*data
-        else {
-            return Err(anyhow!("Missing request data"));
+        let Some(body) = data.body else {
+            return Err(anyhow!("Missing request body"));
         };
+        let Some(server_path) = *self.server_root.join_untrusted(path.clone()).await? else {
+            return Ok(ContentSourceContent::not_found());
+        };
+        let render_data = RenderData::new(
+            &*self.render_config.await?,
+            params.clone(),
+            &data,
+            format!("/{}", path),
+        )?;
         let entry = self.entry.entry(data.clone()).await?;
         Ok(ContentSourceContent::HttpProxy(render_proxy(
             self.cwd,
             self.env,
-            self.server_root.join(path.clone()),
+            server_path,
             entry.module,
             entry.runtime_entries,
             entry.chunking_context,
             entry.intermediate_output_path,
             entry.output_root,
             entry.project_dir,
-            RenderData {
-                params: params.clone(),
-                method: method.clone(),
-                url: url.clone(),
-                original_url: original_url.clone(),
-                raw_query: raw_query.clone(),
-                raw_headers: raw_headers.clone(),
-                path: format!("/{}", path),
-                data: Some(self.render_data.await?),
-            }
-            .cell(),
-            *body,
+            render_data.cell(),
+            body,
             self.debug,
         ))
         .cell())
@@ -0,0 +1,111 @@
+use std::{
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde_json::{json, Value as JsonValue};
+use tokio::{runtime::Handle, time::timeout};
+
+use crate::pool::NodeJsOperation;
+
+/// How long a cancelled render may take to end before its worker is killed.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The messages ending a render.
+const END_MESSAGES: &[&str] = &["response", "bodyEnd", "rewrite", "data", "error"];
+
+/// A render on a worker of the renderer pool, which is cancelled when it's
+/// dropped before the page runtime ended it, e. g. because the browser went
+/// away during a streamed response, or the turbo-tasks invocation was
+/// dropped.
+///
+/// The page runtime is sent a `cancel` message, which aborts `IPC.signal`.
+/// The worker is reused once the page runtime ended the render, or killed
+/// when it doesn't end it in time. Without this the next render on the
+/// worker would receive the remaining messages of the cancelled one.
+pub(crate) struct RenderOperation {
+    operation: Option<NodeJsOperation>,
+    ended: bool,
+}
+
+impl RenderOperation {
+    pub fn new(operation: NodeJsOperation) -> Self {
+        RenderOperation {
+            operation: Some(operation),
+            ended: false,
+        }
+    }
+
+    /// Marks the render as ended by the page runtime, after which dropping
+    /// the operation releases the worker as usual.
+    pub fn end(&mut self) {
+        self.ended = true;
+    }
+
+    /// Takes the operation out, e. g. to wait for its process to exit. It is
+    /// not cancelled anymore.
+    pub fn into_inner(mut self) -> NodeJsOperation {
+        self.ended = true;
+        self.operation
+            .take()
+            .expect("the operation is only taken when dropping")
+    }
+}
+
+impl Deref for RenderOperation {
+    type Target = NodeJsOperation;
+
+    fn deref(&self) -> &NodeJsOperation {
+        self.operation
+            .as_ref()
+            .expect("the operation is only taken when dropping")
+    }
+}
+
+impl DerefMut for RenderOperation {
+    fn deref_mut(&mut self) -> &mut NodeJsOperation {
+        self.operation
+            .as_mut()
+            .expect("the operation is only taken when dropping")
+    }
+}
+
+impl Drop for RenderOperation {
+    fn drop(&mut self) {
+        let Some(mut operation) = self.operation.take() else {
+            return;
+        };
+        if self.ended || !operation.is_reusable() {
+            return;
+        }
+        // The cancellation runs outside of the task of the render
+        operation.disallow_retry();
+        match Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    if !matches!(
+                        timeout(CANCEL_TIMEOUT, cancel(&mut operation)).await,
+                        Ok(Ok(()))
+                    ) {
+                        operation.disallow_reuse();
+                    }
+                });
+            }
+            Err(_) => operation.disallow_reuse(),
+        }
+    }
+}
+
+/// Asks the page runtime to abort the render and waits until it ended it.
+async fn cancel(operation: &mut NodeJsOperation) -> Result<()> {
+    operation.send(json!({ "type": "cancel" })).await?;
+    loop {
+        let message: JsonValue = operation.recv().await?;
+        if let Some(ty) = message.get("type").and_then(JsonValue::as_str) {
+            if END_MESSAGES.contains(&ty) {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Instant;
+
 use anyhow::{anyhow, bail, Result};
 use async_stream::try_stream as generator;
 use futures::{
@@ -12,18 +14,29 @@ use turbo_tasks_fs::FileSystemPath;
 use turbopack_core::{
     chunk::{ChunkingContext, EvaluatableAsset, EvaluatableAssets},
     error::PrettyPrintError,
-    issue::{IssueExt, StyledString},
+    issue::{IssueExt, IssueSeverity, OptionIssueSource, StyledString},
     module::Module,
 };
 use turbopack_dev_server::source::{Body, ProxyResult};
+use turbopack_ecmascript::segment_config::SegmentConfig;
 
 use super::{
-    issue::RenderingIssue, RenderData, RenderProxyIncomingMessage, RenderProxyOutgoingMessage,
-    ResponseHeaders,
+    check_protocol_version,
+    cookies::append_set_cookies,
+    fetch_cache::{fetch_cache, FetchCache},
+    fetch_cassette::{fetch_cassette, FetchCassette},
+    issue::RenderingIssue,
+    report_render_warnings,
+    segment_config::route_segment_config,
+    stats::finish_render,
+    RenderData, RenderProxyIncomingMessage, RenderProxyOutgoingMessage, ResponseHeaders,
 };
 use crate::{
-    get_intermediate_asset, get_renderer_pool, pool::NodeJsOperation,
-    render::error_page::error_html, source_map::trace_stack,
+    dependencies::{track_dir_dependency, track_file_dependency},
+    get_intermediate_asset, get_renderer_pool,
+    pool::NodeJsOperation,
+    render::error_page::error_html,
+    source_map::{trace_issue_source, trace_stack},
 };
 
 /// Renders a module as static HTML in a node.js process.
@@ -92,6 +105,7 @@ pub async fn render_proxy(
 async fn proxy_error(
     path: Vc<FileSystemPath>,
     error: anyhow::Error,
+    source: Vc<OptionIssueSource>,
     operation: Option<NodeJsOperation>,
 ) -> Result<(u16, String)> {
     let message = format!("{}", PrettyPrintError(&error));
@@ -119,6 +133,8 @@ async fn proxy_error(
         file_path: path,
         message: StyledString::Text(message).cell(),
         status: status.and_then(|status| status.code()),
+        source,
+        severity: IssueSeverity::Error,
     }
     .cell()
     .emit();
@@ -234,6 +250,7 @@ async fn render_stream_internal(
             module,
             runtime_entries,
         );
+        let data = data.await?;
         let pool = get_renderer_pool(
             cwd,
             env,
@@ -241,14 +258,23 @@ async fn render_stream_internal(
             intermediate_output_path,
             output_root,
             project_dir,
+            data.pool_options().clone(),
             debug,
         );
 
         // Read this strongly consistent, since we don't want to run inconsistent
         // node.js code.
         let pool = pool.strongly_consistent().await?;
-        let data = data.await?;
-        let mut operation = pool.operation().await?;
+        let fetch_cache = fetch_cache().await?;
+        let segment_config = route_segment_config(module).await?;
+        let cassette = match *fetch_cassette(env, project_dir).await? {
+            Some(cassette) => Some(cassette.await?),
+            None => None,
+        };
+        let cassette = cassette.as_deref();
+        let mut operation = pool.operation_with_affinity(data.affinity_key()).await?;
+        operation.forward_output(data.page().to_string());
+        let start = Instant::now();
 
         // First, send the render data.
         operation
@@ -266,12 +292,31 @@ async fn render_stream_internal(
         let entry = module.ident().to_string().await?;
         let guard = duration_span!("Node.js api execution", entry = display(entry));
 
-        match operation.recv().await? {
-            RenderProxyIncomingMessage::Headers { data } => yield RenderItem::Headers(data),
+        match recv_proxy_message(
+            &mut operation,
+            cwd,
+            &fetch_cache,
+            cassette,
+            &segment_config,
+        )
+        .await?
+        {
+            RenderProxyIncomingMessage::Headers { mut data, protocol_version, cookies } => {
+                check_protocol_version(protocol_version)?;
+                append_set_cookies(&mut data.headers, &cookies)?;
+                yield RenderItem::Headers(data)
+            }
             RenderProxyIncomingMessage::Error(error) => {
                 drop(guard);
                 // If we don't get headers, then something is very wrong. Instead, we send down a
                 // 500 proxy error as if it were the proper result.
+                let source = trace_issue_source(
+                    &error,
+                    intermediate_asset,
+                    intermediate_output_path,
+                    project_dir
+                )
+                .await?;
                 let trace = trace_stack(
                     error,
                     intermediate_asset,
@@ -279,7 +324,7 @@ async fn render_stream_internal(
                     project_dir
                 )
                 .await?;
-                let (status, body) =  proxy_error(path, anyhow!("error rendering: {}", trace), Some(operation)).await?;
+                let (status, body) =  proxy_error(path, anyhow!("error rendering: {}", trace), source, Some(operation)).await?;
                 yield RenderItem::Headers(ResponseHeaders {
                     status,
                     headers: vec![(
@@ -298,11 +343,30 @@ async fn render_stream_internal(
         };
 
         loop {
-            match operation.recv().await? {
+            match recv_proxy_message(
+                &mut operation,
+                cwd,
+                &fetch_cache,
+                cassette,
+                &segment_config,
+            )
+            .await?
+            {
                 RenderProxyIncomingMessage::BodyChunk { data } => {
                     yield RenderItem::BodyChunk(data.into());
                 }
-                RenderProxyIncomingMessage::BodyEnd => break,
+                RenderProxyIncomingMessage::BodyEnd { usage, profile, warnings } => {
+                    finish_render(path, &data, start.elapsed(), usage, profile);
+                    report_render_warnings(
+                        path,
+                        warnings,
+                        intermediate_asset,
+                        intermediate_output_path,
+                        project_dir,
+                    )
+                    .await?;
+                    break;
+                }
                 RenderProxyIncomingMessage::Error(error) => {
                     drop(guard);
                     // We have already started to send a result, so we can't change the
@@ -336,3 +400,50 @@ async fn render_stream_internal(
 
     Ok(Default::default())
 }
+
+/// Receives the next message of the API route, tracking the files it reports
+/// as dependencies and answering `fetch()` cache and cassette requests in
+/// between.
+async fn recv_proxy_message(
+    operation: &mut NodeJsOperation,
+    cwd: Vc<FileSystemPath>,
+    fetch_cache: &FetchCache,
+    cassette: Option<&FetchCassette>,
+    segment_config: &SegmentConfig,
+) -> Result<RenderProxyIncomingMessage> {
+    loop {
+        match operation.recv().await? {
+            RenderProxyIncomingMessage::FetchRecord { key, data } => {
+                let Some(cassette) = cassette else {
+                    bail!("received a fetch recording without a fetch cassette");
+                };
+                cassette.store_recording(key, data)?;
+            }
+            RenderProxyIncomingMessage::FetchReplay { key } => {
+                let Some(cassette) = cassette else {
+                    bail!("received a fetch replay request without a fetch cassette");
+                };
+                cassette.answer_replay(operation, &key).await?;
+            }
+            RenderProxyIncomingMessage::FetchCacheGet { key } => {
+                fetch_cache
+                    .answer_get(operation, segment_config, &key)
+                    .await?;
+            }
+            RenderProxyIncomingMessage::FetchCacheSet {
+                key,
+                data,
+                revalidate,
+            } => {
+                fetch_cache.store(segment_config, key, data, revalidate);
+            }
+            RenderProxyIncomingMessage::FileDependency { path } => {
+                track_file_dependency(cwd, path).await?;
+            }
+            RenderProxyIncomingMessage::DirDependency { path, glob } => {
+                track_dir_dependency(cwd, path, glob).await?;
+            }
+            message => return Ok(message),
+        }
+    }
+}
@@ -1,10 +1,14 @@
+use std::time::Instant;
+
 use anyhow::{anyhow, bail, Context, Result};
 use async_stream::try_stream as generator;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use futures::{
     channel::mpsc::{unbounded, UnboundedSender},
     pin_mut, SinkExt, StreamExt, TryStreamExt,
 };
 use parking_lot::Mutex;
+use serde_json::Value as JsonValue;
 use turbo_tasks::{duration_span, mark_finished, util::SharedError, RawVc, ValueToString, Vc};
 use turbo_tasks_bytes::{Bytes, Stream};
 use turbo_tasks_env::ProcessEnv;
@@ -13,20 +17,36 @@ use turbopack_core::{
     asset::{Asset, AssetContent},
     chunk::{ChunkingContext, EvaluatableAsset, EvaluatableAssets},
     error::PrettyPrintError,
-    issue::{IssueExt, StyledString},
+    issue::{IssueExt, IssueSeverity, OptionIssueSource, StyledString},
     module::Module,
+    output::OutputAsset,
 };
 use turbopack_dev_server::{
     html::DevHtmlAsset,
     source::{Body, HeaderList, Rewrite, RewriteBuilder},
 };
+use turbopack_ecmascript::segment_config::SegmentConfig;
 
 use super::{
-    issue::RenderingIssue, RenderData, RenderStaticIncomingMessage, RenderStaticOutgoingMessage,
+    check_protocol_version,
+    cookies::append_set_cookies,
+    fetch_cache::{fetch_cache, FetchCache},
+    fetch_cassette::{fetch_cassette, FetchCassette},
+    issue::{ErroredSegmentIssue, RenderingIssue},
+    report_render_warnings,
+    segment_config::route_segment_config,
+    stats::finish_render,
+    styles::{inject_into_head, is_html, style_tags, CollectedStyle},
+    BodyEncoding, ErroredSegment, RenderData, RenderStaticIncomingMessage,
+    RenderStaticOutgoingMessage,
 };
 use crate::{
-    get_intermediate_asset, get_renderer_pool, pool::NodeJsOperation,
-    render::error_page::error_html_body, source_map::trace_stack, ResponseHeaders,
+    dependencies::{track_dir_dependency, track_file_dependency},
+    get_intermediate_asset, get_renderer_pool,
+    pool::{NodeJsOperation, OperationTimeout},
+    render::{error_page::error_html_body, operation::RenderOperation},
+    source_map::{trace_issue_source, trace_stack},
+    ResponseHeaders,
 };
 
 #[derive(Clone, Debug)]
@@ -43,6 +63,12 @@ pub enum StaticResult {
         body: Body,
     },
     Rewrite(Vc<Rewrite>),
+    /// Rendering failed. `content` is the built-in error page, `message` the
+    /// error, which is passed to a custom error page when there is one.
+    Error {
+        content: Vc<AssetContent>,
+        message: String,
+    },
 }
 
 #[turbo_tasks::value_impl]
@@ -65,6 +91,11 @@ impl StaticResult {
     pub fn rewrite(rewrite: Vc<Rewrite>) -> Vc<Self> {
         StaticResult::Rewrite(rewrite).cell()
     }
+
+    #[turbo_tasks::function]
+    pub fn error(content: Vc<AssetContent>, message: String) -> Vc<Self> {
+        StaticResult::Error { content, message }.cell()
+    }
 }
 
 /// Renders a module as static HTML in a node.js process.
@@ -131,9 +162,228 @@ pub async fn render_static(
     })
 }
 
+/// The result of the data fetching phase of a render, see
+/// [render_static_with_data_fetching].
+#[turbo_tasks::value(shared)]
+pub enum DataFetchingResult {
+    /// The JSON passed to the render phase as [RenderData::data].
+    Data(JsonValue),
+    /// The data fetching failed. The failure is served like a failed render.
+    Error(Vc<StaticResult>),
+}
+
+/// Renders a module in two phases: first its data is fetched in a node.js
+/// process (e. g. with its `getServerSideProps`), then the module is rendered
+/// with the result as [RenderData::data], like [render_static].
+///
+/// The data fetching phase is a task of its own, which only receives the
+/// request context, so its result is cached separately from the render, e. g.
+/// HTML and data requests of a page share it.
+#[turbo_tasks::function]
+pub async fn render_static_with_data_fetching(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    path: Vc<FileSystemPath>,
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    fallback_page: Vc<DevHtmlAsset>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+    data: Vc<RenderData>,
+    debug: bool,
+) -> Result<Vc<StaticResult>> {
+    let render_data = data.await?;
+    let result = fetch_render_data(
+        cwd,
+        env,
+        path,
+        module,
+        runtime_entries,
+        fallback_page,
+        chunking_context,
+        intermediate_output_path,
+        output_root,
+        project_dir,
+        render_data.for_data_fetching().cell(),
+        debug,
+    )
+    .await?;
+    Ok(match &*result {
+        DataFetchingResult::Data(fetched) => render_static(
+            cwd,
+            env,
+            path,
+            module,
+            runtime_entries,
+            fallback_page,
+            chunking_context,
+            intermediate_output_path,
+            output_root,
+            project_dir,
+            render_data.clone_value().with_data(fetched.clone()).cell(),
+            debug,
+        ),
+        DataFetchingResult::Error(error) => *error,
+    })
+}
+
+/// Runs the data fetching phase of a render in a node.js process, see
+/// [render_static_with_data_fetching].
+#[turbo_tasks::function]
+pub async fn fetch_render_data(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    path: Vc<FileSystemPath>,
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    fallback_page: Vc<DevHtmlAsset>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+    data: Vc<RenderData>,
+    debug: bool,
+) -> Result<Vc<DataFetchingResult>> {
+    let intermediate_asset = get_intermediate_asset(chunking_context, module, runtime_entries);
+    let data = data.await?;
+    let renderer_pool = get_renderer_pool(
+        cwd,
+        env,
+        intermediate_asset,
+        intermediate_output_path,
+        output_root,
+        project_dir,
+        data.pool_options().clone(),
+        debug,
+    );
+
+    // Read this strongly consistent, since we don't want to run inconsistent
+    // node.js code.
+    let pool = renderer_pool.strongly_consistent().await?;
+    let fetch_cache = fetch_cache().await?;
+    let segment_config = route_segment_config(module).await?;
+    let cassette = match *fetch_cassette(env, project_dir).await? {
+        Some(cassette) => Some(cassette.await?),
+        None => None,
+    };
+    let mut operation = pool.operation_with_affinity(data.affinity_key()).await?;
+    operation.forward_output(data.page().to_string());
+    if let Some(timeout) = data.render_timeout() {
+        operation.set_timeout(timeout);
+    }
+
+    operation
+        .send(RenderStaticOutgoingMessage::DataFetching { data: &data })
+        .await
+        .context("sending data fetching request to node.js process")?;
+    let mut operation = RenderOperation::new(operation);
+
+    let entry = module.ident().to_string().await?;
+    let guard = duration_span!("Node.js data fetching", entry = display(entry));
+    let message = recv_render_message(
+        &mut operation,
+        cwd,
+        &fetch_cache,
+        cassette.as_deref(),
+        &segment_config,
+    )
+    .await;
+    drop(guard);
+
+    let result = match message {
+        Err(err) if err.is::<OperationTimeout>() => {
+            // The worker was killed, so there is no exit status to report
+            let message = err.to_string();
+            let content = static_error(path, err, Vc::cell(None), None, fallback_page).await?;
+            DataFetchingResult::Error(StaticResult::error(content, message))
+        }
+        Err(err) => return Err(err),
+        Ok(RenderStaticIncomingMessage::Data {
+            data,
+            protocol_version,
+        }) => {
+            check_protocol_version(protocol_version)?;
+            DataFetchingResult::Data(data)
+        }
+        Ok(RenderStaticIncomingMessage::Error(error)) => {
+            let source = trace_issue_source(
+                &error,
+                intermediate_asset,
+                intermediate_output_path,
+                project_dir,
+            )
+            .await?;
+            let trace = trace_stack(
+                error,
+                intermediate_asset,
+                intermediate_output_path,
+                project_dir,
+            )
+            .await?;
+            let content = static_error(
+                path,
+                anyhow!(trace.clone()),
+                source,
+                Some(operation.into_inner()),
+                fallback_page,
+            )
+            .await?;
+            DataFetchingResult::Error(StaticResult::error(content, trace))
+        }
+        Ok(v) => bail!("unexpected message during data fetching: {:#?}", v),
+    };
+    Ok(result.cell())
+}
+
+/// The content of a complete response of the page runtime, with the content
+/// type declared by its `content-type` header. The header is moved to the
+/// file, so binary responses (e. g. images of og-image routes) are served
+/// with their own content type and their bytes unchanged.
+fn response_content(
+    body: String,
+    encoding: BodyEncoding,
+    headers: &mut Vec<(String, String)>,
+) -> Result<Vc<AssetContent>> {
+    let mut file = match encoding {
+        BodyEncoding::Utf8 => File::from(body),
+        BodyEncoding::Base64 => File::from(
+            BASE64
+                .decode(body)
+                .context("decoding the base64 body of the response")?,
+        ),
+    };
+    if let Some(index) = headers
+        .iter()
+        .position(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+    {
+        if let Ok(content_type) = headers[index].1.parse::<mime::Mime>() {
+            headers.remove(index);
+            file = file.with_content_type(content_type);
+        }
+    }
+    Ok(AssetContent::file(file.into()))
+}
+
+/// Injects the collected `styles` into the head of a complete HTML document.
+/// Other responses are returned unchanged.
+fn with_styles(
+    body: String,
+    encoding: BodyEncoding,
+    headers: &[(String, String)],
+    styles: &[CollectedStyle],
+) -> String {
+    if styles.is_empty() || encoding != BodyEncoding::Utf8 || !is_html(headers) {
+        return body;
+    }
+    inject_into_head(&body, &style_tags(styles)).unwrap_or(body)
+}
+
 async fn static_error(
     path: Vc<FileSystemPath>,
     error: anyhow::Error,
+    source: Vc<OptionIssueSource>,
     operation: Option<NodeJsOperation>,
     fallback_page: Vc<DevHtmlAsset>,
 ) -> Result<Vc<AssetContent>> {
@@ -167,6 +417,8 @@ async fn static_error(
         file_path: path,
         message: StyledString::Text(error).cell(),
         status: status.and_then(|status| status.code()),
+        source,
+        severity: IssueSeverity::Error,
     };
 
     issue.cell().emit();
@@ -176,6 +428,84 @@ async fn static_error(
     Ok(html.content())
 }
 
+/// Reports the segments of a page which were rendered with the fallback of
+/// their error boundary as issues of `path`.
+async fn report_errored_segments(
+    path: Vc<FileSystemPath>,
+    data: &RenderData,
+    errored_segments: Vec<ErroredSegment>,
+    intermediate_asset: Vc<Box<dyn OutputAsset>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+) -> Result<()> {
+    for ErroredSegment { segment, error } in errored_segments {
+        let trace = trace_stack(
+            error,
+            intermediate_asset,
+            intermediate_output_path,
+            project_dir,
+        )
+        .await?;
+        ErroredSegmentIssue {
+            file_path: path,
+            page: data.page().to_string(),
+            segment,
+            message: StyledString::Text(trace).cell(),
+        }
+        .cell()
+        .emit();
+    }
+    Ok(())
+}
+
+/// Receives the next message of the render which isn't handled here: fetch
+/// cache and cassette messages, and the files the render depends on. Reading
+/// them makes them dependencies of the render task, so changing the content
+/// of a page renders it again, while its bundles, which only depend on its
+/// modules, are kept.
+async fn recv_render_message(
+    operation: &mut RenderOperation,
+    cwd: Vc<FileSystemPath>,
+    fetch_cache: &FetchCache,
+    cassette: Option<&FetchCassette>,
+    segment_config: &SegmentConfig,
+) -> Result<RenderStaticIncomingMessage> {
+    loop {
+        let mut message = operation.recv().await?;
+        if let Some(cassette) = cassette {
+            match cassette.handle_message(operation, message).await? {
+                Some(unhandled) => message = unhandled,
+                None => continue,
+            }
+        }
+        match fetch_cache
+            .handle_message(operation, segment_config, message)
+            .await?
+        {
+            Some(RenderStaticIncomingMessage::FileDependency { path }) => {
+                track_file_dependency(cwd, path).await?;
+            }
+            Some(RenderStaticIncomingMessage::DirDependency { path, glob }) => {
+                track_dir_dependency(cwd, path, glob).await?;
+            }
+            Some(message) => {
+                if matches!(
+                    message,
+                    RenderStaticIncomingMessage::Response { .. }
+                        | RenderStaticIncomingMessage::BodyEnd { .. }
+                        | RenderStaticIncomingMessage::Rewrite { .. }
+                        | RenderStaticIncomingMessage::Data { .. }
+                        | RenderStaticIncomingMessage::Error(_)
+                ) {
+                    operation.end();
+                }
+                return Ok(message);
+            }
+            None => {}
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 #[turbo_tasks::value]
 enum RenderItem {
@@ -285,6 +615,7 @@ async fn render_stream_internal(
             module,
             runtime_entries,
         );
+        let data = data.await?;
         let renderer_pool = get_renderer_pool(
             cwd,
             env,
@@ -292,25 +623,67 @@ async fn render_stream_internal(
             intermediate_output_path,
             output_root,
             project_dir,
+            data.pool_options().clone(),
             debug,
         );
 
         // Read this strongly consistent, since we don't want to run inconsistent
         // node.js code.
         let pool = renderer_pool.strongly_consistent().await?;
-        let data = data.await?;
-        let mut operation = pool.operation().await?;
+        let fetch_cache = fetch_cache().await?;
+        let segment_config = route_segment_config(module).await?;
+        let cassette = match *fetch_cassette(env, project_dir).await? {
+            Some(cassette) => Some(cassette.await?),
+            None => None,
+        };
+        let mut operation = pool.operation_with_affinity(data.affinity_key()).await?;
+        operation.forward_output(data.page().to_string());
+        if let Some(timeout) = data.render_timeout() {
+            operation.set_timeout(timeout);
+        }
+        let start = Instant::now();
 
         operation
             .send(RenderStaticOutgoingMessage::Headers { data: &data })
             .await
             .context("sending headers to node.js process")?;
+        let mut operation = RenderOperation::new(operation);
 
         let entry = module.ident().to_string().await?;
         let guard = duration_span!("Node.js rendering", entry = display(entry));
 
-        match operation.recv().await? {
-            RenderStaticIncomingMessage::Headers { data } => yield RenderItem::Headers(data),
+        let cassette = cassette.as_deref();
+        // The `<style>` tags to inject into the head of a streamed document, until the chunk
+        // containing the end of the head was sent.
+        let mut pending_style_tags = None;
+        let message = match recv_render_message(
+            &mut operation,
+            cwd,
+            &fetch_cache,
+            cassette,
+            &segment_config,
+        )
+        .await
+        {
+            Err(err) if err.is::<OperationTimeout>() => {
+                drop(guard);
+                // The worker was killed, so there is no exit status to report
+                let message = err.to_string();
+                let content = static_error(path, err, Vc::cell(None), None, fallback_page).await?;
+                yield RenderItem::Response(StaticResult::error(content, message));
+                return;
+            }
+            message => message?,
+        };
+        match message {
+            RenderStaticIncomingMessage::Headers { mut data, protocol_version, cookies, styles } => {
+                check_protocol_version(protocol_version)?;
+                append_set_cookies(&mut data.headers, &cookies)?;
+                if !styles.is_empty() && is_html(&data.headers) {
+                    pending_style_tags = Some(style_tags(&styles));
+                }
+                yield RenderItem::Headers(data)
+            }
             RenderStaticIncomingMessage::Rewrite { path } => {
                 drop(guard);
                 yield RenderItem::Response(StaticResult::rewrite(RewriteBuilder::new(path).build()));
@@ -318,12 +691,44 @@ async fn render_stream_internal(
             }
             RenderStaticIncomingMessage::Response {
                 status_code,
-                headers,
+                mut headers,
                 body,
+                body_encoding,
+                protocol_version,
+                cookies,
+                usage,
+                profile,
+                errored_segments,
+                warnings,
+                styles,
             } => {
                 drop(guard);
+                check_protocol_version(protocol_version)?;
+                append_set_cookies(&mut headers, &cookies)?;
+                finish_render(path, &data, start.elapsed(), usage, profile);
+                // The page is served with error fallbacks for these segments instead of
+                // replacing it with the error page.
+                report_errored_segments(
+                    path,
+                    &data,
+                    errored_segments,
+                    intermediate_asset,
+                    intermediate_output_path,
+                    project_dir,
+                )
+                .await?;
+                report_render_warnings(
+                    path,
+                    warnings,
+                    intermediate_asset,
+                    intermediate_output_path,
+                    project_dir,
+                )
+                .await?;
+                let body = with_styles(body, body_encoding, &headers, &styles);
+                let content = response_content(body, body_encoding, &mut headers)?;
                 yield RenderItem::Response(StaticResult::content(
-                    AssetContent::file(File::from(body).into()),
+                    content,
                     status_code,
                     Vc::cell(headers),
                 ));
@@ -333,6 +738,13 @@ async fn render_stream_internal(
                 drop(guard);
                 // If we don't get headers, then something is very wrong. Instead, we send down a
                 // 500 proxy error as if it were the proper result.
+                let source = trace_issue_source(
+                    &error,
+                    intermediate_asset,
+                    intermediate_output_path,
+                    project_dir,
+                )
+                .await?;
                 let trace = trace_stack(
                     error,
                     intermediate_asset,
@@ -340,13 +752,9 @@ async fn render_stream_internal(
                     project_dir,
                 )
                 .await?;
-                yield RenderItem::Response(
-                    StaticResult::content(
-                        static_error(path, anyhow!(trace), Some(operation), fallback_page).await?,
-                        500,
-                        HeaderList::empty(),
-                    )
-                );
+                let content =
+                    static_error(path, anyhow!(trace.clone()), source, Some(operation.into_inner()), fallback_page).await?;
+                yield RenderItem::Response(StaticResult::error(content, trace));
                 return;
             }
             v => {
@@ -359,18 +767,84 @@ async fn render_stream_internal(
         // If we get here, then the first message was a Headers. Now we need to stream out the body
         // chunks.
         loop {
-            match operation.recv().await? {
-                RenderStaticIncomingMessage::BodyChunk { data } => {
+            let message = match recv_render_message(
+                &mut operation,
+                cwd,
+                &fetch_cache,
+                cassette,
+                &segment_config,
+            )
+            .await
+            {
+                Err(err) if err.is::<OperationTimeout>() => {
+                    drop(guard);
+                    // The headers were already sent, so the error page can't be served instead
+                    RenderingIssue {
+                        file_path: path,
+                        message: StyledString::Text(err.to_string()).cell(),
+                        status: None,
+                        source: Vc::cell(None),
+                        severity: IssueSeverity::Error,
+                    }
+                    .cell()
+                    .emit();
+                    Err(err)?;
+                    return;
+                }
+                message => message?,
+            };
+            match message {
+                RenderStaticIncomingMessage::BodyChunk { mut data } => {
+                    if let Some(tags) = &pending_style_tags {
+                        if let Some(html) = std::str::from_utf8(&data)
+                            .ok()
+                            .and_then(|html| inject_into_head(html, tags))
+                        {
+                            data = html.into_bytes();
+                            pending_style_tags = None;
+                        }
+                    }
                     yield RenderItem::BodyChunk(data.into());
                 }
-                RenderStaticIncomingMessage::BodyEnd => break,
+                RenderStaticIncomingMessage::BodyEnd { usage, profile, errored_segments, warnings } => {
+                    finish_render(path, &data, start.elapsed(), usage, profile);
+                    report_errored_segments(
+                        path,
+                        &data,
+                        errored_segments,
+                        intermediate_asset,
+                        intermediate_output_path,
+                        project_dir,
+                    )
+                    .await?;
+                    report_render_warnings(
+                        path,
+                        warnings,
+                        intermediate_asset,
+                        intermediate_output_path,
+                        project_dir,
+                    )
+                    .await?;
+                    break;
+                }
                 RenderStaticIncomingMessage::Error(error) => {
                     // We have already started to send a result, so we can't change the
                     // headers/body to a proxy error.
                     operation.disallow_reuse();
+                    let source =
+                        trace_issue_source(&error, intermediate_asset, intermediate_output_path, project_dir).await?;
                     let trace =
                         trace_stack(error, intermediate_asset, intermediate_output_path, project_dir).await?;
-                        drop(guard);
+                    drop(guard);
+                    RenderingIssue {
+                        file_path: path,
+                        message: StyledString::Text(trace.clone()).cell(),
+                        status: None,
+                        source,
+                        severity: IssueSeverity::Error,
+                    }
+                    .cell()
+                    .emit();
                     Err(anyhow!("error during streaming render: {}", trace))?;
                     return;
                 }
@@ -1,10 +1,10 @@
 use anyhow::{anyhow, Result};
 use indexmap::IndexSet;
-use serde_json::Value as JsonValue;
 use turbo_tasks::{Value, Vc};
 use turbo_tasks_env::ProcessEnv;
 use turbo_tasks_fs::FileSystemPath;
 use turbopack_core::{
+    chunk::EvaluatableAsset,
     introspect::{
         module::IntrospectableModule, output_asset::IntrospectableOutputAsset, Introspectable,
         IntrospectableChildren,
@@ -20,15 +20,19 @@ use turbopack_dev_server::{
         asset_graph::AssetGraphContentSource,
         conditional::ConditionalContentSource,
         lazy_instantiated::{GetContentSource, LazyInstantiatedContentSource},
-        route_tree::{BaseSegment, RouteTree, RouteType},
+        route_tree::{BaseSegment, RouteTree, RouteTrees, RouteType},
         ContentSource, ContentSourceContent, ContentSourceData, ContentSourceDataVary,
-        GetContentSourceContent, ProxyResult,
+        GetContentSourceContent, HeaderList, ProxyResult,
     },
 };
+use turbopack_ecmascript::EcmascriptModuleAsset;
 
 use super::{
-    render_static::{render_static, StaticResult},
-    RenderData,
+    html_transform::{apply_html_transforms, HtmlTransforms},
+    isr::render_static_isr,
+    render_static::{render_static, render_static_with_data_fetching, StaticResult},
+    segment_config::{apply_segment_config_headers, route_segment_config},
+    RenderConfig, RenderData, RenderError,
 };
 use crate::{
     external_asset_entrypoints, get_intermediate_asset, node_entry::NodeEntry,
@@ -40,7 +44,17 @@ use crate::{
 /// all assets referenced by the `entry` that are within the `server_root`.
 /// It needs a temporary directory (`intermediate_output_path`) to place file
 /// for Node.js execution during rendering. The `chunking_context` should emit
-/// to this directory.
+/// to this directory. Rendered HTML pages are rewritten by `html_transforms`
+/// before they are served. When rendering fails, the custom `error_entry`
+/// (`pages/_error`) is rendered instead of the built-in error page.
+///
+/// The source also serves the props of the page as JSON for client-side
+/// navigations, at `/_next/data/<buildId>/<page>.json` with the build ID of
+/// the `render_config`.
+///
+/// Pages which declare a `revalidate` route segment config are served from
+/// the [IsrCache](super::isr::IsrCache) and rendered again in the background
+/// after their revalidate period.
 #[turbo_tasks::function]
 pub fn create_node_rendered_source(
     cwd: Vc<FileSystemPath>,
@@ -52,7 +66,9 @@ pub fn create_node_rendered_source(
     pathname: Vc<String>,
     entry: Vc<Box<dyn NodeEntry>>,
     fallback_page: Vc<DevHtmlAsset>,
-    render_data: Vc<JsonValue>,
+    render_config: Vc<RenderConfig>,
+    html_transforms: Vc<HtmlTransforms>,
+    error_entry: Option<Vc<Box<dyn NodeEntry>>>,
     debug: bool,
 ) -> Vc<Box<dyn ContentSource>> {
     let source = NodeRenderContentSource {
@@ -65,7 +81,9 @@ pub fn create_node_rendered_source(
         pathname,
         entry,
         fallback_page,
-        render_data,
+        render_config,
+        html_transforms,
+        error_entry,
         debug,
     }
     .cell();
@@ -92,7 +110,9 @@ pub struct NodeRenderContentSource {
     pathname: Vc<String>,
     entry: Vc<Box<dyn NodeEntry>>,
     fallback_page: Vc<DevHtmlAsset>,
-    render_data: Vc<JsonValue>,
+    render_config: Vc<RenderConfig>,
+    html_transforms: Vc<HtmlTransforms>,
+    error_entry: Option<Vc<Box<dyn NodeEntry>>>,
     debug: bool,
 }
 
@@ -141,27 +161,63 @@ impl ContentSource for NodeRenderContentSource {
     #[turbo_tasks::function]
     async fn get_routes(self: Vc<Self>) -> Result<Vc<RouteTree>> {
         let this = self.await?;
-        Ok(RouteTree::new_route(
-            this.base_segments.clone(),
-            this.route_type.clone(),
-            Vc::upcast(self),
-        ))
+        let build_id = &this.render_config.await?.build_id;
+        Ok(Vc::<RouteTrees>::cell(vec![
+            RouteTree::new_route(
+                this.base_segments.clone(),
+                this.route_type.clone(),
+                Vc::upcast(self),
+            ),
+            RouteTree::new_route(
+                data_route_segments(build_id, &this.base_segments),
+                this.route_type.clone(),
+                Vc::upcast(self),
+            ),
+        ])
+        .merge())
     }
 }
 
+/// The segments of the route serving the props of a page with
+/// `base_segments` as JSON, e. g. `_next/data/<buildId>/blog/[slug].json`.
+/// A dynamic last segment matches the `.json` suffix too.
+fn data_route_segments(build_id: &str, base_segments: &[BaseSegment]) -> Vec<BaseSegment> {
+    let mut segments = vec![
+        BaseSegment::Static("_next".to_string()),
+        BaseSegment::Static("data".to_string()),
+        BaseSegment::Static(build_id.to_string()),
+    ];
+    match base_segments.split_last() {
+        Some((BaseSegment::Static(last), init)) => {
+            segments.extend(init.iter().cloned());
+            segments.push(BaseSegment::Static(format!("{last}.json")));
+        }
+        Some((BaseSegment::Dynamic, _)) => segments.extend(base_segments.iter().cloned()),
+        None => segments.push(BaseSegment::Static("index.json".to_string())),
+    }
+    segments
+}
+
+/// The path of the page whose props are requested by a data request for
+/// `path`, or `None` when `path` isn't a data request.
+fn data_request_page_path(path: &str, build_id: &str) -> Option<String> {
+    let page = path
+        .strip_prefix("_next/data/")?
+        .strip_prefix(build_id)?
+        .strip_prefix('/')?
+        .strip_suffix(".json")?;
+    Some(if page == "index" {
+        String::new()
+    } else {
+        page.to_string()
+    })
+}
+
 #[turbo_tasks::value_impl]
 impl GetContentSourceContent for NodeRenderContentSource {
     #[turbo_tasks::function]
     fn vary(&self) -> Vc<ContentSourceDataVary> {
-        ContentSourceDataVary {
-            method: true,
-            url: true,
-            original_url: true,
-            raw_headers: true,
-            raw_query: true,
-            ..Default::default()
-        }
-        .cell()
+        RenderData::vary().cell()
     }
 
     #[turbo_tasks::function]
@@ -170,6 +226,10 @@ impl GetContentSourceContent for NodeRenderContentSource {
         path: String,
         data: Value<ContentSourceData>,
     ) -> Result<Vc<ContentSourceContent>> {
+        let render_config = self.render_config.await?;
+        let data_request_path = data_request_page_path(&path, &render_config.build_id);
+        let data_request = data_request_path.is_some();
+        let path = data_request_path.unwrap_or(path);
         let Some(params) = &*self.route_match.params(path.clone()).await? else {
             return Err(anyhow!(
                 "Non matching path ({}) provided for {}",
@@ -177,55 +237,114 @@ impl GetContentSourceContent for NodeRenderContentSource {
                 self.pathname.await?
             ));
         };
-        let ContentSourceData {
-            method: Some(method),
-            url: Some(url),
-            original_url: Some(original_url),
-            raw_headers: Some(raw_headers),
-            raw_query: Some(raw_query),
-            ..
-        } = &*data
-        else {
-            return Err(anyhow!("Missing request data"));
+        let Some(server_path) = *self.server_root.join_untrusted(path.clone()).await? else {
+            return Ok(ContentSourceContent::not_found());
         };
         let entry = self.entry.entry(data.clone()).await?;
-        let result = render_static(
-            self.cwd,
-            self.env,
-            self.server_root.join(path.clone()),
-            entry.module,
-            entry.runtime_entries,
-            self.fallback_page,
-            entry.chunking_context,
-            entry.intermediate_output_path,
-            entry.output_root,
-            entry.project_dir,
-            RenderData {
-                params: params.clone(),
-                method: method.clone(),
-                url: url.clone(),
-                original_url: original_url.clone(),
-                raw_query: raw_query.clone(),
-                raw_headers: raw_headers.clone(),
-                path: self.pathname.await?.clone_value(),
-                data: Some(self.render_data.await?),
+        let render_data = RenderData::new(
+            &render_config,
+            params.clone(),
+            &data,
+            self.pathname.await?.clone_value(),
+        )?
+        .with_get_initial_props(*route_uses_get_initial_props(entry.module).await?)
+        .with_data_request(data_request);
+        // Pages with a data fetching function render in two phases
+        let data_fetching = *route_exports_get_server_side_props(entry.module).await?;
+        let segment_config = route_segment_config(entry.module);
+        let config = segment_config.await?;
+        // Pages declaring a revalidate period are served from the ISR cache
+        let result = if config.revalidate.is_some() && !config.is_dynamic() {
+            render_static_isr(
+                self.cwd,
+                self.env,
+                server_path,
+                entry.module,
+                entry.runtime_entries,
+                self.fallback_page,
+                entry.chunking_context,
+                entry.intermediate_output_path,
+                entry.output_root,
+                entry.project_dir,
+                render_data.clone().cell(),
+                self.debug,
+                data_fetching,
+                config.revalidate_seconds(),
+            )
+        } else {
+            let render = if data_fetching {
+                render_static_with_data_fetching
+            } else {
+                render_static
+            };
+            render(
+                self.cwd,
+                self.env,
+                server_path,
+                entry.module,
+                entry.runtime_entries,
+                self.fallback_page,
+                entry.chunking_context,
+                entry.intermediate_output_path,
+                entry.output_root,
+                entry.project_dir,
+                render_data.clone().cell(),
+                self.debug,
+            )
+        };
+        let mut result = result
+            .issue_file_path(
+                entry.module.ident().path(),
+                format!("server-side rendering {}", self.pathname.await?),
+            )
+            .await?;
+        // Client-side navigations fall back to loading the HTML of the page when a data
+        // request fails, which renders the error page.
+        let error_entry = self.error_entry.filter(|_| !data_request);
+        if let (StaticResult::Error { message, .. }, Some(error_entry)) =
+            (&*result.await?, error_entry)
+        {
+            let error_entry = error_entry.entry(data.clone()).await?;
+            let error_data = render_data
+                .with_get_initial_props(*route_uses_get_initial_props(error_entry.module).await?)
+                .with_error(RenderError {
+                    status_code: 500,
+                    message: message.clone(),
+                });
+            let error_result = render_static(
+                self.cwd,
+                self.env,
+                server_path,
+                error_entry.module,
+                error_entry.runtime_entries,
+                self.fallback_page,
+                error_entry.chunking_context,
+                error_entry.intermediate_output_path,
+                error_entry.output_root,
+                error_entry.project_dir,
+                error_data.cell(),
+                self.debug,
+            )
+            .issue_file_path(
+                error_entry.module.ident().path(),
+                format!("rendering the error page of {}", self.pathname.await?),
+            )
+            .await?;
+            // The built-in error page is served when the error page fails too
+            if !matches!(*error_result.await?, StaticResult::Error { .. }) {
+                result = error_result;
             }
-            .cell(),
-            self.debug,
-        )
-        .issue_file_path(
-            entry.module.ident().path(),
-            format!("server-side rendering {}", self.pathname.await?),
-        )
-        .await?;
+        }
         Ok(match *result.await? {
             StaticResult::Content {
                 content,
                 status_code,
                 headers,
-            } => {
-                ContentSourceContent::static_with_headers(content.versioned(), status_code, headers)
-            }
+            } => ContentSourceContent::static_with_headers(
+                apply_html_transforms(content, self.pathname, self.html_transforms).versioned(),
+                status_code,
+                apply_segment_config_headers(headers, segment_config),
+            ),
             StaticResult::StreamedContent {
                 status,
                 headers,
@@ -233,17 +352,54 @@ impl GetContentSourceContent for NodeRenderContentSource {
             } => ContentSourceContent::HttpProxy(
                 ProxyResult {
                     status,
-                    headers: headers.await?.clone_value(),
+                    headers: apply_segment_config_headers(headers, segment_config)
+                        .await?
+                        .clone_value(),
                     body: body.clone(),
                 }
                 .cell(),
             )
             .cell(),
             StaticResult::Rewrite(rewrite) => ContentSourceContent::Rewrite(rewrite).cell(),
+            StaticResult::Error { content, .. } => ContentSourceContent::static_with_headers(
+                content.versioned(),
+                500,
+                HeaderList::empty(),
+            ),
         })
     }
 }
 
+/// Whether the module rendered for a route defines the legacy
+/// `getInitialProps` method.
+#[turbo_tasks::function]
+async fn route_uses_get_initial_props(module: Vc<Box<dyn EvaluatableAsset>>) -> Result<Vc<bool>> {
+    Ok(
+        if let Some(module) = Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(module).await?
+        {
+            module.uses_get_initial_props()
+        } else {
+            Vc::cell(false)
+        },
+    )
+}
+
+/// Whether the module rendered for a route exports a `getServerSideProps`
+/// data fetching function.
+#[turbo_tasks::function]
+async fn route_exports_get_server_side_props(
+    module: Vc<Box<dyn EvaluatableAsset>>,
+) -> Result<Vc<bool>> {
+    Ok(
+        if let Some(module) = Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(module).await?
+        {
+            module.exports_get_server_side_props()
+        } else {
+            Vc::cell(false)
+        },
+    )
+}
+
 #[turbo_tasks::function]
 fn introspectable_type() -> Vc<String> {
     Vc::cell("node render content source".to_string())
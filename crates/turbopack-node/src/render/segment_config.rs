@@ -0,0 +1,43 @@
+use anyhow::Result;
+use turbo_tasks::Vc;
+use turbopack_core::chunk::EvaluatableAsset;
+use turbopack_dev_server::source::HeaderList;
+use turbopack_ecmascript::{segment_config::SegmentConfig, EcmascriptModuleAsset};
+
+/// Returns the route segment config declared by the module that is rendered
+/// for a route. Modules that are not ecmascript modules have an empty config.
+#[turbo_tasks::function]
+pub async fn route_segment_config(
+    module: Vc<Box<dyn EvaluatableAsset>>,
+) -> Result<Vc<SegmentConfig>> {
+    Ok(
+        if let Some(module) = Vc::try_resolve_downcast_type::<EcmascriptModuleAsset>(module).await?
+        {
+            module.segment_config()
+        } else {
+            SegmentConfig::empty()
+        },
+    )
+}
+
+/// Adds a `cache-control` header derived from the route segment config, unless
+/// the rendered response already specified one.
+#[turbo_tasks::function]
+pub(super) async fn apply_segment_config_headers(
+    headers: Vc<HeaderList>,
+    config: Vc<SegmentConfig>,
+) -> Result<Vc<HeaderList>> {
+    let Some(cache_control) = config.await?.cache_control() else {
+        return Ok(headers);
+    };
+    let list = headers.await?;
+    if list
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("cache-control"))
+    {
+        return Ok(headers);
+    }
+    let mut list = list.clone_value();
+    list.push(("cache-control".to_string(), cache_control));
+    Ok(Vc::cell(list))
+}
@@ -0,0 +1,266 @@
+use anyhow::{bail, Result};
+use futures::StreamExt;
+use indexmap::IndexSet;
+use turbo_tasks::{Completion, Completions, Value, Vc};
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fs::{File, FileContent, FileSystemPath};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    chunk::{ChunkingContext, EvaluatableAsset, EvaluatableAssets},
+    issue::IssueDescriptionExt,
+    module::Module,
+    output::OutputAsset,
+    reference::all_assets_from_entries,
+};
+use turbopack_dev_server::html::DevHtmlAsset;
+
+use super::{
+    html_transform::{apply_html_transforms, HtmlTransforms},
+    render_static::{render_static, StaticResult},
+    RenderConfig, RenderData,
+};
+use crate::{external_asset_entrypoints, node_entry::NodeEntry, route_matcher::RouteMatcher};
+
+/// A route exported by [export_static_site].
+#[turbo_tasks::value(shared)]
+pub struct StaticExportRoute {
+    /// The pathname of the route, e. g. `/blog/[slug]`.
+    pub pathname: String,
+    pub route_match: Vc<Box<dyn RouteMatcher>>,
+    pub entry: Vc<Box<dyn NodeEntry>>,
+    /// The paths exported for the route, e. g. `/blog/hello-world`. Routes
+    /// without dynamic segments export their pathname.
+    pub paths: Vec<String>,
+}
+
+#[turbo_tasks::value(transparent)]
+pub struct StaticExportRoutes(Vec<Vc<StaticExportRoute>>);
+
+/// Renders a module to a complete document for a static export, like
+/// [render_static]. Streamed responses are buffered.
+///
+/// Fails when the page can't be exported: its rendering failed, rewrote the
+/// request, or responded with another status than 200.
+#[turbo_tasks::function]
+pub async fn render_static_export(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    path: Vc<FileSystemPath>,
+    module: Vc<Box<dyn EvaluatableAsset>>,
+    runtime_entries: Vc<EvaluatableAssets>,
+    fallback_page: Vc<DevHtmlAsset>,
+    chunking_context: Vc<Box<dyn ChunkingContext>>,
+    intermediate_output_path: Vc<FileSystemPath>,
+    output_root: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+    data: Vc<RenderData>,
+    debug: bool,
+) -> Result<Vc<AssetContent>> {
+    let page = data.await?.page().to_string();
+    let result = render_static(
+        cwd,
+        env,
+        path,
+        module,
+        runtime_entries,
+        fallback_page,
+        chunking_context,
+        intermediate_output_path,
+        output_root,
+        project_dir,
+        data,
+        debug,
+    )
+    .await?;
+    Ok(match &*result {
+        StaticResult::Content {
+            content,
+            status_code,
+            ..
+        } => {
+            if *status_code != 200 {
+                bail!("{page} responded with status {status_code}, only 200 can be exported");
+            }
+            *content
+        }
+        StaticResult::StreamedContent {
+            status,
+            headers,
+            body,
+        } => {
+            if *status != 200 {
+                bail!("{page} responded with status {status}, only 200 can be exported");
+            }
+            let mut bytes = Vec::new();
+            let mut chunks = body.read();
+            while let Some(chunk) = chunks.next().await {
+                bytes.extend_from_slice(&chunk?);
+            }
+            let mut file = File::from(bytes);
+            if let Some(content_type) = headers
+                .await?
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                .and_then(|(_, value)| value.parse::<mime::Mime>().ok())
+            {
+                file = file.with_content_type(content_type);
+            }
+            AssetContent::file(file.into())
+        }
+        StaticResult::Rewrite(_) => bail!("{page} rewrote the request, which can't be exported"),
+        // The error was already reported as an issue
+        StaticResult::Error { message, .. } => bail!("rendering {page} failed: {message}"),
+    })
+}
+
+/// Exports `routes` as a static site into `out_dir`, like `next export`: every
+/// path of every route is rendered with [render_static_export] and written as
+/// HTML file, `/` to `index.html` and `/blog/hello` to `blog/hello.html`.
+/// The client assets of the rendered entries within `server_root` are
+/// written next to the pages, at their path relative to `server_root`.
+///
+/// Pages reference these assets with absolute URLs. They are rewritten to
+/// start with `asset_prefix` (e. g. a CDN), or to be relative to the page
+/// without one, so the site can be served from any directory. URLs which the
+/// runtime computes, e. g. of chunks loaded on demand, are determined by the
+/// chunking context of the entries.
+#[turbo_tasks::function]
+pub async fn export_static_site(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    routes: Vc<StaticExportRoutes>,
+    server_root: Vc<FileSystemPath>,
+    fallback_page: Vc<DevHtmlAsset>,
+    render_config: Vc<RenderConfig>,
+    html_transforms: Vc<HtmlTransforms>,
+    out_dir: Vc<FileSystemPath>,
+    asset_prefix: Option<String>,
+    debug: bool,
+) -> Result<Vc<Completion>> {
+    let routes = routes.await?;
+    let render_config = render_config.await?;
+    let server_root_value = server_root.await?;
+
+    let mut entrypoints = IndexSet::new();
+    for route in routes.iter() {
+        for &entry in route.await?.entry.entries().await?.iter() {
+            let entry = entry.await?;
+            entrypoints.extend(
+                external_asset_entrypoints(
+                    entry.module,
+                    entry.runtime_entries,
+                    entry.chunking_context,
+                    entry.intermediate_output_path,
+                )
+                .await?
+                .iter()
+                .copied(),
+            );
+        }
+    }
+    let mut completions = Vec::new();
+    let mut asset_paths = Vec::new();
+    for &asset in all_assets_from_entries(Vc::cell(entrypoints.into_iter().collect()))
+        .await?
+        .iter()
+    {
+        let path = asset.ident().path().await?;
+        let Some(relative_path) = server_root_value.get_path_to(&path) else {
+            continue;
+        };
+        completions.push(
+            asset
+                .content()
+                .write(out_dir.join(relative_path.to_string())),
+        );
+        asset_paths.push(relative_path.to_string());
+    }
+
+    for route in routes.iter() {
+        let route = route.await?;
+        for path in &route.paths {
+            let Some(params) = &*route.route_match.params(path.clone()).await? else {
+                bail!("{path} is not a path of the route {}", route.pathname);
+            };
+            let entry = route.entry.entry(Value::new(Default::default())).await?;
+            let render_data = RenderData::for_params(&render_config, params.clone(), path.clone())?;
+            let content = render_static_export(
+                cwd,
+                env,
+                server_root.join(path.trim_start_matches('/').to_string()),
+                entry.module,
+                entry.runtime_entries,
+                fallback_page,
+                entry.chunking_context,
+                entry.intermediate_output_path,
+                entry.output_root,
+                entry.project_dir,
+                render_data.cell(),
+                debug,
+            )
+            .issue_file_path(entry.module.ident().path(), format!("exporting {path}"))
+            .await?;
+            let content =
+                apply_html_transforms(content, Vc::cell(route.pathname.clone()), html_transforms);
+            let file_name = export_file_name(path);
+            let prefix = match &asset_prefix {
+                Some(prefix) if prefix.ends_with('/') => prefix.clone(),
+                Some(prefix) => format!("{prefix}/"),
+                None => "../".repeat(file_name.matches('/').count()),
+            };
+            completions.push(
+                rewrite_asset_urls(content, asset_paths.clone(), prefix)
+                    .write(out_dir.join(file_name)),
+            );
+        }
+    }
+
+    Ok(Vc::<Completions>::cell(completions).completed())
+}
+
+/// The file a path is exported to, relative to the output directory.
+fn export_file_name(path: &str) -> String {
+    match path.trim_matches('/') {
+        "" => "index.html".to_string(),
+        path => format!("{path}.html"),
+    }
+}
+
+/// Rewrites the quoted absolute URLs of `asset_paths` in an HTML page to
+/// start with `prefix`. Other content is passed through.
+#[turbo_tasks::function]
+async fn rewrite_asset_urls(
+    content: Vc<AssetContent>,
+    asset_paths: Vec<String>,
+    prefix: String,
+) -> Result<Vc<AssetContent>> {
+    if prefix == "/" {
+        return Ok(content);
+    }
+    let AssetContent::File(file) = *content.await? else {
+        return Ok(content);
+    };
+    let FileContent::Content(file) = &*file.await? else {
+        return Ok(content);
+    };
+    let Some(content_type) = file.content_type() else {
+        return Ok(content);
+    };
+    if content_type.essence_str() != mime::TEXT_HTML.essence_str() {
+        return Ok(content);
+    }
+    let content_type = content_type.clone();
+
+    let mut html = file.content().to_str()?.into_owned();
+    for path in &asset_paths {
+        for quote in ['"', '\''] {
+            html = html.replace(
+                &format!("{quote}/{path}{quote}"),
+                &format!("{quote}{prefix}{path}{quote}"),
+            );
+        }
+    }
+    Ok(AssetContent::file(
+        FileContent::Content(File::from(html).with_content_type(content_type)).cell(),
+    ))
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+use indexmap::IndexMap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::Vc;
+use turbo_tasks_fs::FileSystemPath;
+use turbopack_core::issue::IssueExt;
+
+use super::{issue::SlowRenderIssue, RenderData};
+
+/// The resources a render used in the worker, reported by the page runtime
+/// with its last message of a render.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderUsage {
+    /// User and system CPU time, from `process.cpuUsage()` deltas.
+    pub cpu_micros: u64,
+    /// The peak heap usage during the render, above the heap usage at its
+    /// start.
+    pub peak_memory_bytes: u64,
+}
+
+/// A CPU profile of a slow render, summarized by the page runtime into the
+/// stacks most samples were taken in.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowRenderProfile {
+    pub hot_stacks: Vec<HotStack>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HotStack {
+    /// The frames of the stack, innermost first, e. g.
+    /// `renderList (webpack://app/list.js:12:3)`.
+    pub frames: Vec<String>,
+    /// The CPU time spent in the innermost frame.
+    pub self_micros: u64,
+}
+
+/// The aggregated resource usage of the renders of a page.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageRenderStats {
+    pub renders: u64,
+    /// The number of renders which reported their resource usage.
+    pub measured_renders: u64,
+    pub total_duration_micros: u64,
+    pub total_cpu_micros: u64,
+    pub max_cpu_micros: u64,
+    pub peak_memory_bytes: u64,
+}
+
+static RENDER_STATS: Lazy<Mutex<IndexMap<String, PageRenderStats>>> = Lazy::new(Default::default);
+
+/// Records a finished render, and reports it as an issue of `file_path` when
+/// it took longer than the slow render threshold of the [RenderData].
+pub(crate) fn finish_render(
+    file_path: Vc<FileSystemPath>,
+    data: &RenderData,
+    duration: Duration,
+    usage: Option<RenderUsage>,
+    profile: Option<SlowRenderProfile>,
+) {
+    record_render(&data.path, duration, usage);
+    let Some(threshold_ms) = data.slow_render_threshold_ms else {
+        return;
+    };
+    if duration.as_millis() as u64 > threshold_ms {
+        SlowRenderIssue {
+            file_path,
+            page: data.path.clone(),
+            duration_ms: duration.as_millis() as u64,
+            threshold_ms,
+            hot_stacks: profile
+                .map(|profile| {
+                    profile
+                        .hot_stacks
+                        .into_iter()
+                        .map(|stack| (stack.frames, stack.self_micros))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+        .cell()
+        .emit();
+    }
+}
+
+/// Records a finished render of `page`.
+fn record_render(page: &str, duration: Duration, usage: Option<RenderUsage>) {
+    let mut stats = RENDER_STATS.lock();
+    let stats = stats.entry(page.to_string()).or_default();
+    stats.renders += 1;
+    stats.total_duration_micros += duration.as_micros() as u64;
+    if let Some(usage) = usage {
+        stats.measured_renders += 1;
+        stats.total_cpu_micros += usage.cpu_micros;
+        stats.max_cpu_micros = stats.max_cpu_micros.max(usage.cpu_micros);
+        stats.peak_memory_bytes = stats.peak_memory_bytes.max(usage.peak_memory_bytes);
+    }
+}
+
+/// The render stats of all pages rendered by this process, the most
+/// expensive pages (by total CPU time, then by total duration) first.
+pub fn render_stats() -> Vec<(String, PageRenderStats)> {
+    let mut stats = RENDER_STATS
+        .lock()
+        .iter()
+        .map(|(page, stats)| (page.clone(), stats.clone()))
+        .collect::<Vec<_>>();
+    stats.sort_by(|(_, a), (_, b)| {
+        (b.total_cpu_micros, b.total_duration_micros)
+            .cmp(&(a.total_cpu_micros, a.total_duration_micros))
+    });
+    stats
+}
+
+pub fn reset_render_stats() {
+    RENDER_STATS.lock().clear();
+}
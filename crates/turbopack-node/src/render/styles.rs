@@ -0,0 +1,68 @@
+use std::fmt::Write;
+
+use serde::Deserialize;
+
+/// A style sheet collected by a CSS-in-JS library (e. g. styled-jsx, emotion
+/// or styled-components) while server-side rendering a page, which page
+/// runtimes can pass as `styles` in their `headers` or `response` message.
+/// It is injected into the `<head>` of the document, so the first paint isn't
+/// unstyled.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectedStyle {
+    /// The `id` of the `<style>` tag, which the library uses to take over the
+    /// style sheet when hydrating.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub css: String,
+    #[serde(default)]
+    pub nonce: Option<String>,
+}
+
+/// Serializes `styles` into `<style>` tags.
+pub fn style_tags(styles: &[CollectedStyle]) -> String {
+    let mut tags = String::new();
+    for style in styles {
+        tags.push_str("<style");
+        for (name, value) in [("id", &style.id), ("nonce", &style.nonce)] {
+            if let Some(value) = value {
+                write!(tags, " {name}=\"{}\"", escape_attribute(value)).unwrap();
+            }
+        }
+        tags.push('>');
+        // A `</style` in the CSS would end the tag early.
+        tags.push_str(&style.css.replace("</style", "<\\/style"));
+        tags.push_str("</style>");
+    }
+    tags
+}
+
+/// Inserts `tags` before the end of the `<head>` of `html`. Returns `None`
+/// when `html` doesn't contain the end of the head.
+pub fn inject_into_head(html: &str, tags: &str) -> Option<String> {
+    let index = html.to_ascii_lowercase().find("</head>")?;
+    let mut result = String::with_capacity(html.len() + tags.len());
+    result.push_str(&html[..index]);
+    result.push_str(tags);
+    result.push_str(&html[index..]);
+    Some(result)
+}
+
+/// Whether a response with `headers` is an HTML document, which collected
+/// styles can be injected into.
+pub fn is_html(headers: &[(String, String)]) -> bool {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .and_then(|(_, value)| value.parse::<mime::Mime>().ok())
+        .map_or(false, |content_type| {
+            content_type.essence_str() == mime::TEXT_HTML.essence_str()
+        })
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
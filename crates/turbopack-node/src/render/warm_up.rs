@@ -0,0 +1,53 @@
+use anyhow::Result;
+use turbo_tasks::{Completion, TryJoinIterExt, Vc};
+use turbo_tasks_env::ProcessEnv;
+use turbo_tasks_fs::FileSystemPath;
+
+use super::RenderConfig;
+use crate::{get_intermediate_asset, get_renderer_pool, node_entry::NodeEntry};
+
+/// Creates the renderer pools of the entries of `entry` ahead of their first
+/// render, e. g. when the dev server starts. This emits the intermediate
+/// assets and boots up [NodeJsPoolOptions::prefork] processes per pool, which
+/// evaluate the bootstrap and wait for the renders.
+///
+/// The renders use the same pools as long as they are called with the same
+/// `render_config`, so the first paint doesn't wait for cold processes.
+///
+/// [NodeJsPoolOptions::prefork]: crate::NodeJsPoolOptions::prefork
+#[turbo_tasks::function]
+pub async fn warm_up_renderer(
+    cwd: Vc<FileSystemPath>,
+    env: Vc<Box<dyn ProcessEnv>>,
+    entry: Vc<Box<dyn NodeEntry>>,
+    render_config: Vc<RenderConfig>,
+    debug: bool,
+) -> Result<Vc<Completion>> {
+    let render_config = render_config.await?;
+    let pool_options = &render_config.pool_options;
+    entry
+        .entries()
+        .await?
+        .iter()
+        .map(|entry| async move {
+            let entry = entry.await?;
+            let intermediate_asset =
+                get_intermediate_asset(entry.chunking_context, entry.module, entry.runtime_entries);
+            get_renderer_pool(
+                cwd,
+                env,
+                intermediate_asset,
+                entry.intermediate_output_path,
+                entry.output_root,
+                entry.project_dir,
+                pool_options.clone(),
+                debug,
+            )
+            .strongly_consistent()
+            .await?;
+            anyhow::Ok(())
+        })
+        .try_join()
+        .await?;
+    Ok(Completion::new())
+}
@@ -16,8 +16,12 @@ use turbo_tasks_fs::{
 };
 use turbopack_cli_utils::source_context::format_source_context_lines;
 use turbopack_core::{
-    output::OutputAsset, source_map::GenerateSourceMap, PROJECT_FILESYSTEM_NAME,
-    SOURCE_MAP_ROOT_NAME,
+    file_source::FileSource,
+    issue::{IssueSource, OptionIssueSource},
+    output::OutputAsset,
+    source_map::GenerateSourceMap,
+    source_pos::SourcePos,
+    PROJECT_FILESYSTEM_NAME, SOURCE_MAP_ROOT_NAME,
 };
 use turbopack_ecmascript::magic_identifier::unmangle_identifiers;
 
@@ -328,6 +332,47 @@ pub async fn trace_stack(
     .await
 }
 
+/// The position in the original source of the innermost frame of `error`
+/// which is located in a project file, e. g. to point an issue at the line
+/// which threw instead of at the page. Frames in library code are skipped.
+pub async fn trace_issue_source(
+    error: &StructuredError,
+    root_asset: Vc<Box<dyn OutputAsset>>,
+    output_path: Vc<FileSystemPath>,
+    project_dir: Vc<FileSystemPath>,
+) -> Result<Vc<OptionIssueSource>> {
+    let assets_for_source_mapping = internal_assets_for_source_mapping(root_asset, output_path);
+    for frame in &error.stack {
+        // Errors resolving a frame are already reported by the trace of the stack
+        let Ok(ResolvedSourceMapping::MappedProject {
+            frame,
+            project_path,
+            ..
+        }) = resolve_source_mapping(
+            assets_for_source_mapping,
+            output_path,
+            project_dir.root(),
+            frame,
+        )
+        .await
+        else {
+            continue;
+        };
+        let (line, column) = frame.get_pos().unwrap_or((0, 0));
+        let pos = SourcePos {
+            line: line.saturating_sub(1),
+            column: column.saturating_sub(1),
+        };
+        let path = project_dir.root().join(project_path.path.clone());
+        return Ok(Vc::cell(Some(IssueSource::from_line_col(
+            Vc::upcast(FileSource::new(path)),
+            pos,
+            pos,
+        ))));
+    }
+    Ok(Vc::cell(None))
+}
+
 #[instrument(level = Level::TRACE, skip_all)]
 pub async fn trace_stack_with_source_mapping_assets(
     error: StructuredError,
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{FileContent, FileSystemPath};
+use turbo_tasks_hash::{encode_hex, hash_xxh3_hash64, Xxh3Hash64Hasher};
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedTransform {
+    output: String,
+    /// The files the transform read, relative to the project, with the hash
+    /// of their content at the time.
+    dependencies: Vec<(String, u64)>,
+}
+
+/// A cache of transform results, keyed by the hash of the input content and
+/// of the transform chain, see [transform_cache_key].
+///
+/// Transforms re-run whenever an upstream task re-executes, even when the
+/// bytes they transform are the same. Looking the result up by content skips
+/// the expensive part, e. g. running webpack loaders in Node.js. With a
+/// directory, results are also stored on disk and survive restarts.
+///
+/// Results are only reused while the files the transform read are unchanged.
+#[turbo_tasks::value(cell = "new", serialization = "none", eq = "manual")]
+pub struct TransformCache {
+    #[turbo_tasks(trace_ignore, debug_ignore)]
+    entries: Mutex<HashMap<u64, CachedTransform>>,
+    #[turbo_tasks(trace_ignore)]
+    directory: Option<PathBuf>,
+}
+
+/// The key of transforming `content` with `chain`, which identifies the
+/// transforms and their options, e. g. the configured webpack loaders.
+pub fn transform_cache_key(chain: &str, content: &[u8]) -> u64 {
+    let mut hasher = Xxh3Hash64Hasher::new();
+    hasher.write_value(chain);
+    hasher.write_value(content);
+    hasher.finish()
+}
+
+impl TransformCache {
+    /// Returns the cached output for `key`, when the files it depends on are
+    /// unchanged. The dependencies are read relative to `root`, which makes
+    /// them dependencies of the calling task, like when the transform runs.
+    pub async fn get(&self, key: u64, root: Vc<FileSystemPath>) -> Result<Option<String>> {
+        let mut entry = self.entries.lock().get(&key).cloned();
+        if entry.is_none() {
+            entry = self.read_entry(key).await;
+        }
+        let Some(entry) = entry else {
+            return Ok(None);
+        };
+        for (path, hash) in &entry.dependencies {
+            if content_hash(root.join(path.clone())).await? != *hash {
+                return Ok(None);
+            }
+        }
+        let output = entry.output.clone();
+        self.entries.lock().insert(key, entry);
+        Ok(Some(output))
+    }
+
+    /// Stores `output` for `key`, with the files the transform read relative
+    /// to `root`.
+    ///
+    /// Failing to write the entry to disk is not an error, it's only missing
+    /// after a restart.
+    pub async fn set(
+        &self,
+        key: u64,
+        output: String,
+        dependencies: Vec<String>,
+        root: Vc<FileSystemPath>,
+    ) -> Result<()> {
+        let mut hashed_dependencies = Vec::with_capacity(dependencies.len());
+        for path in dependencies {
+            let hash = content_hash(root.join(path.clone())).await?;
+            hashed_dependencies.push((path, hash));
+        }
+        let entry = CachedTransform {
+            output,
+            dependencies: hashed_dependencies,
+        };
+        if let Some(directory) = &self.directory {
+            if let Ok(json) = serde_json::to_vec(&entry) {
+                if tokio::fs::create_dir_all(directory).await.is_ok() {
+                    let _ = tokio::fs::write(entry_path(directory, key), json).await;
+                }
+            }
+        }
+        self.entries.lock().insert(key, entry);
+        Ok(())
+    }
+
+    /// Reads an entry stored by an earlier process. Missing and unreadable
+    /// entries are cache misses.
+    async fn read_entry(&self, key: u64) -> Option<CachedTransform> {
+        let directory = self.directory.as_ref()?;
+        let json = tokio::fs::read(entry_path(directory, key)).await.ok()?;
+        serde_json::from_slice(&json).ok()
+    }
+}
+
+fn entry_path(directory: &Path, key: u64) -> PathBuf {
+    directory.join(format!("{}.json", encode_hex(key)))
+}
+
+async fn content_hash(path: Vc<FileSystemPath>) -> Result<u64> {
+    Ok(match &*path.read().await? {
+        FileContent::Content(file) => hash_xxh3_hash64(file.content()),
+        FileContent::NotFound => 0,
+    })
+}
+
+/// The transform cache of `directory`, or an in-memory one without a
+/// directory.
+#[turbo_tasks::function]
+pub fn transform_cache(directory: Option<String>) -> Vc<TransformCache> {
+    TransformCache {
+        entries: Default::default(),
+        directory: directory.map(PathBuf::from),
+    }
+    .cell()
+}
@@ -0,0 +1,271 @@
+use std::process::Stdio;
+
+use anyhow::{bail, Context, Result};
+use tokio::io::AsyncWriteExt;
+use turbo_tasks::Vc;
+use turbo_tasks_fs::{to_sys_path, File, FileContent, FileSystemPath};
+use turbopack_core::{
+    asset::{Asset, AssetContent},
+    ident::AssetIdent,
+    issue::{Issue, IssueExt, IssueSeverity, IssueStage, OptionStyledString, StyledString},
+    source::Source,
+    source_transform::SourceTransform,
+};
+
+/// An external command that compiles a file. The source is passed on stdin and
+/// the compiled code is read from stdout. `{file}` in the arguments is
+/// replaced with the path of the file.
+#[turbo_tasks::value(shared)]
+#[derive(Clone, Debug)]
+pub struct ExternalCompilerCommand {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+/// A [SourceTransform] that compiles files with an external command, e. g. a
+/// webpack child compiler. It's an escape hatch for incrementally adopting
+/// Turbopack when not every loader is supported natively.
+///
+/// The output is ingested as is. Only the source content is tracked, files
+/// read by the external command itself don't invalidate the result.
+#[turbo_tasks::value]
+pub struct ExternalCompiler {
+    project_path: Vc<FileSystemPath>,
+    command: Vc<ExternalCompilerCommand>,
+    rename_as: Option<String>,
+}
+
+#[turbo_tasks::value_impl]
+impl ExternalCompiler {
+    #[turbo_tasks::function]
+    pub fn new(
+        project_path: Vc<FileSystemPath>,
+        command: Vc<ExternalCompilerCommand>,
+        rename_as: Option<String>,
+    ) -> Vc<Self> {
+        ExternalCompiler {
+            project_path,
+            command,
+            rename_as,
+        }
+        .cell()
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl SourceTransform for ExternalCompiler {
+    #[turbo_tasks::function]
+    fn transform(self: Vc<Self>, source: Vc<Box<dyn Source>>) -> Vc<Box<dyn Source>> {
+        Vc::upcast(
+            ExternalCompiledSource {
+                transform: self,
+                source,
+            }
+            .cell(),
+        )
+    }
+}
+
+#[turbo_tasks::value]
+struct ExternalCompiledSource {
+    transform: Vc<ExternalCompiler>,
+    source: Vc<Box<dyn Source>>,
+}
+
+#[turbo_tasks::value_impl]
+impl Source for ExternalCompiledSource {
+    #[turbo_tasks::function]
+    async fn ident(&self) -> Result<Vc<AssetIdent>> {
+        Ok(
+            if let Some(rename_as) = self.transform.await?.rename_as.as_deref() {
+                self.source.ident().rename_as(rename_as.to_string())
+            } else {
+                self.source.ident()
+            },
+        )
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl Asset for ExternalCompiledSource {
+    #[turbo_tasks::function]
+    async fn content(&self) -> Result<Vc<AssetContent>> {
+        let transform = self.transform.await?;
+        let AssetContent::File(file) = *self.source.content().await? else {
+            bail!("External compilers only support transforming files");
+        };
+        let FileContent::Content(content) = &*file.await? else {
+            return Ok(AssetContent::File(FileContent::NotFound.cell()).cell());
+        };
+
+        let path = self.source.ident().path();
+        let Some(cwd) = to_sys_path(transform.project_path).await? else {
+            bail!("External compilers can only run on a disk filesystem");
+        };
+        let Some(file_path) = to_sys_path(path).await? else {
+            bail!("External compilers can only compile files on a disk filesystem");
+        };
+        let file_path = file_path.to_string_lossy();
+
+        let command = transform.command.await?;
+        let ExternalCompilerCommand { command, args } = &*command;
+        let result = run_command(
+            command,
+            command_args(args, &file_path),
+            &cwd,
+            content.content().to_bytes()?.into_owned(),
+        )
+        .await;
+        Ok(match result {
+            Ok(output) => AssetContent::File(FileContent::Content(File::from(output)).cell()),
+            Err(error) => {
+                ExternalCompilerIssue {
+                    file_path: path,
+                    command: command.clone(),
+                    message: format!("{error:?}"),
+                }
+                .cell()
+                .emit();
+                AssetContent::File(FileContent::NotFound.cell())
+            }
+        }
+        .cell())
+    }
+}
+
+/// Replaces `{file}` in the arguments with the path of the compiled file.
+fn command_args<'a>(args: &'a [String], file_path: &'a str) -> impl Iterator<Item = String> + 'a {
+    args.iter().map(move |arg| arg.replace("{file}", file_path))
+}
+
+async fn run_command(
+    command: &str,
+    args: impl Iterator<Item = String>,
+    cwd: &std::path::Path,
+    input: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let mut child = tokio::process::Command::new(command)
+        .args(args)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to spawn `{command}`"))?;
+    let mut stdin = child.stdin.take().context("stdin is piped")?;
+    let write = async move {
+        stdin.write_all(&input).await?;
+        // Close stdin so the compiler sees the end of the input
+        drop(stdin);
+        anyhow::Ok(())
+    };
+    let (write, output) = tokio::join!(write, child.wait_with_output());
+    let output = output?;
+    if !output.status.success() {
+        bail!(
+            "`{command}` exited with {}\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    write.context("writing the source to the compiler")?;
+    Ok(output.stdout)
+}
+
+#[turbo_tasks::value(shared)]
+struct ExternalCompilerIssue {
+    file_path: Vc<FileSystemPath>,
+    command: String,
+    message: String,
+}
+
+#[turbo_tasks::value_impl]
+impl Issue for ExternalCompilerIssue {
+    #[turbo_tasks::function]
+    fn severity(&self) -> Vc<IssueSeverity> {
+        IssueSeverity::Error.into()
+    }
+
+    #[turbo_tasks::function]
+    fn title(&self) -> Vc<StyledString> {
+        StyledString::Text(format!("External compiler `{}` failed", self.command)).cell()
+    }
+
+    #[turbo_tasks::function]
+    fn stage(&self) -> Vc<IssueStage> {
+        IssueStage::Transform.into()
+    }
+
+    #[turbo_tasks::function]
+    fn file_path(&self) -> Vc<FileSystemPath> {
+        self.file_path
+    }
+
+    #[turbo_tasks::function]
+    fn description(&self) -> Vc<OptionStyledString> {
+        Vc::cell(Some(StyledString::Text(self.message.clone()).cell()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_file_in_args() {
+        let args = vec![
+            "--filename={file}".to_string(),
+            "{file}".to_string(),
+            "--stdin".to_string(),
+        ];
+        assert_eq!(
+            command_args(&args, "/app/src/a.vue").collect::<Vec<_>>(),
+            ["--filename=/app/src/a.vue", "/app/src/a.vue", "--stdin"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn compiles_stdin_to_stdout() {
+        let output = run_command(
+            "sh",
+            ["-c".to_string(), "tr a-z A-Z".to_string()].into_iter(),
+            &std::env::temp_dir(),
+            b"export default 1".to_vec(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(output, b"EXPORT DEFAULT 1");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn reports_failures_with_stderr() {
+        let error = run_command(
+            "sh",
+            [
+                "-c".to_string(),
+                "echo 'syntax error' >&2; exit 3".to_string(),
+            ]
+            .into_iter(),
+            &std::env::temp_dir(),
+            Vec::new(),
+        )
+        .await
+        .unwrap_err();
+        let message = format!("{error:?}");
+        assert!(message.contains("exited with"), "{message}");
+        assert!(message.contains("syntax error"), "{message}");
+
+        let error = run_command(
+            "turbopack-missing-compiler",
+            std::iter::empty(),
+            &std::env::temp_dir(),
+            Vec::new(),
+        )
+        .await
+        .unwrap_err();
+        assert!(format!("{error:?}").contains("failed to spawn"));
+    }
+}
@@ -1,3 +1,5 @@
+pub mod cache;
+pub mod external_compiler;
 pub mod postcss;
 mod util;
 pub mod webpack;
@@ -319,6 +319,7 @@ impl PostCssTransformedAsset {
             project_path,
             chunking_context,
             env,
+            ..
         } = *this.execution_context.await?;
 
         // For this postcss transform, there is no gaurantee that looking up for the
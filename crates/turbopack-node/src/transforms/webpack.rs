@@ -4,15 +4,11 @@ use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value as JsonValue};
-use turbo_tasks::{
-    trace::TraceRawVcs, Completion, TaskInput, TryJoinIterExt, Value, ValueToString, Vc,
-};
+use turbo_tasks::{trace::TraceRawVcs, Completion, TaskInput, Value, ValueToString, Vc};
 use turbo_tasks_bytes::stream::SingleValue;
 use turbo_tasks_env::ProcessEnv;
-use turbo_tasks_fs::{
-    glob::Glob, json::parse_json_with_source_context, DirectoryEntry, File, FileContent,
-    FileSystemPath, ReadGlobResult,
-};
+use turbo_tasks_fs::{json::parse_json_with_source_context, File, FileContent, FileSystemPath};
+use turbo_tasks_hash::Xxh3Hash64Hasher;
 use turbopack_core::{
     asset::{Asset, AssetContent},
     chunk::ChunkingContext,
@@ -38,9 +34,13 @@ use turbopack_resolve::{
     resolve_options_context::ResolveOptionsContext,
 };
 
-use super::util::{emitted_assets_to_virtual_sources, EmittedAsset};
+use super::{
+    cache::transform_cache_key,
+    util::{emitted_assets_to_virtual_sources, EmittedAsset},
+};
 use crate::{
     debug::should_debug,
+    dependencies::{track_dir_dependency, track_file_dependency},
     embed_js::embed_file_path,
     evaluate::{
         compute, custom_evaluate, get_evaluate_pool, EvaluateContext, EvaluationIssue,
@@ -60,6 +60,12 @@ struct WebpackLoadersProcessingResult {
     map: Option<String>,
     #[turbo_tasks(trace_ignore)]
     assets: Option<Vec<EmittedAsset>>,
+    /// Whether the result only depends on the content and `file_dependencies`,
+    /// so it can be stored in the transform cache.
+    #[serde(default)]
+    cacheable: bool,
+    #[serde(default)]
+    file_dependencies: Vec<String>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, TraceRawVcs, Serialize, Deserialize)]
@@ -181,6 +187,7 @@ impl WebpackLoadersProcessedAsset {
             project_path,
             chunking_context,
             env,
+            transform_cache,
         } = *transform.execution_context.await?;
         let source_content = this.source.content();
         let AssetContent::File(file) = *source_content.await? else {
@@ -207,6 +214,27 @@ impl WebpackLoadersProcessedAsset {
             bail!("Resource path need to be on project filesystem");
         };
         let loaders = transform.loaders.await?;
+        let transform_cache = transform_cache.await?;
+        // Loaders may behave differently depending on the resource, so its path is
+        // part of the transform chain. The code running them is too, so updating a
+        // loader package or Turbopack doesn't reuse stale results.
+        let code_hash = *loaders_code_hash(
+            project_path,
+            transform.loaders,
+            transform.resolve_options_context,
+        )
+        .await?;
+        let cache_key = transform_cache_key(
+            &format!(
+                "webpack-loaders {resource_path} {} {}",
+                json!(*loaders),
+                code_hash
+            ),
+            content.as_bytes(),
+        );
+        if let Some(output) = transform_cache.get(cache_key, project_path).await? {
+            return processing_result(parse_processing_result(&output)?).await;
+        }
         let config_value = custom_evaluate(WebpackLoaderContext {
             module_asset: webpack_loaders_executor,
             cwd: project_path,
@@ -233,29 +261,103 @@ impl WebpackLoadersProcessedAsset {
             }
             .cell());
         };
-        let processed: WebpackLoadersProcessingResult = parse_json_with_source_context(
-            val.to_str()?,
-        )
-        .context("Unable to deserializate response from webpack loaders transform operation")?;
-
-        // handle SourceMap
-        let source_map = if let Some(source_map) = processed.map {
-            SourceMap::new_from_file_content(FileContent::Content(File::from(source_map)).cell())
-                .await?
-                .map(|source_map| source_map.cell())
-        } else {
-            None
-        };
-        let file = File::from(processed.source);
-        let assets = emitted_assets_to_virtual_sources(processed.assets);
-        let content = AssetContent::File(FileContent::Content(file).cell()).cell();
-        Ok(ProcessWebpackLoadersResult {
-            content,
-            assets,
-            source_map,
+        let output = val.to_str()?;
+        let processed = parse_processing_result(&output)?;
+        if processed.cacheable {
+            transform_cache
+                .set(
+                    cache_key,
+                    output.into_owned(),
+                    processed.file_dependencies.clone(),
+                    project_path,
+                )
+                .await?;
+        }
+        processing_result(processed).await
+    }
+}
+
+/// A hash of the code of the webpack loaders and of the executor running them:
+/// the files the loaders resolve to, the `package.json` of loader packages,
+/// which has their version, and the source of the executor. Loaders which
+/// can't be resolved are hashed by their request only.
+#[turbo_tasks::function]
+async fn loaders_code_hash(
+    project_path: Vc<FileSystemPath>,
+    loaders: Vc<WebpackLoaderItems>,
+    resolve_options_context: Vc<ResolveOptionsContext>,
+) -> Result<Vc<u64>> {
+    let options = resolve_options(project_path, resolve_options_context);
+    let mut hasher = Xxh3Hash64Hasher::new();
+    hasher.write_value(
+        *embed_file_path("transforms/webpack-loaders.ts".to_string())
+            .read()
+            .hash()
+            .await?,
+    );
+    for loader in loaders.await?.iter() {
+        let request = loader.loader.split('?').next().unwrap_or_default();
+        let mut requests = vec![request.to_string()];
+        if let Some(package) = package_name(request) {
+            requests.push(format!("{package}/package.json"));
+        }
+        for request in requests {
+            let resolved = resolve(
+                project_path,
+                Value::new(ReferenceType::Undefined),
+                Request::parse_string(request),
+                options,
+            );
+            if let Some(source) = *resolved.first_source().await? {
+                hasher.write_value(*source.content().file_content().hash().await?);
+            }
         }
-        .cell())
     }
+    Ok(Vc::cell(hasher.finish()))
+}
+
+/// The name of the package of a loader request, `None` for paths.
+fn package_name(request: &str) -> Option<&str> {
+    if request.starts_with('.') || request.starts_with('/') {
+        return None;
+    }
+    let mut segments = request.splitn(3, '/');
+    let first = segments.next()?;
+    Some(if first.starts_with('@') {
+        let second = segments.next()?;
+        &request[..first.len() + 1 + second.len()]
+    } else {
+        first
+    })
+}
+
+/// Parses the response of the Node.js process, which is either fresh or from
+/// the transform cache.
+fn parse_processing_result(output: &str) -> Result<WebpackLoadersProcessingResult> {
+    parse_json_with_source_context(output)
+        .context("Unable to deserializate response from webpack loaders transform operation")
+}
+
+async fn processing_result(
+    processed: WebpackLoadersProcessingResult,
+) -> Result<Vc<ProcessWebpackLoadersResult>> {
+    // handle SourceMap
+    let source_map = if let Some(source_map) = processed.map {
+        SourceMap::new_from_file_content(FileContent::Content(File::from(source_map)).cell())
+            .await?
+            .map(|source_map| source_map.cell())
+    } else {
+        None
+    };
+    let file = File::from(processed.source);
+    let assets = emitted_assets_to_virtual_sources(processed.assets);
+    let content = AssetContent::File(FileContent::Content(file).cell()).cell();
+    Ok(ProcessWebpackLoadersResult {
+        content,
+        assets,
+        source_map,
+    }
+    .cell())
 }
 
 #[turbo_tasks::function]
@@ -389,10 +491,7 @@ impl EvaluateContext for WebpackLoaderContext {
     async fn info(&self, data: Self::InfoMessage, pool: &NodeJsPool) -> Result<()> {
         match data {
             InfoMessage::FileDependency { path } => {
-                // TODO We might miss some changes that happened during execution
-                // Read dependencies to make them a dependencies of this task. This task will
-                // execute again when they change.
-                self.cwd.join(path).read().await?;
+                track_file_dependency(self.cwd, path).await?;
             }
             InfoMessage::BuildDependency { path } => {
                 // TODO We might miss some changes that happened during execution
@@ -404,10 +503,7 @@ impl EvaluateContext for WebpackLoaderContext {
                 .emit();
             }
             InfoMessage::DirDependency { path, glob } => {
-                // TODO We might miss some changes that happened during execution
-                // Read dependencies to make them a dependencies of this task. This task will
-                // execute again when they change.
-                dir_dependency(self.cwd.join(path).read_glob(Glob::new(glob), false)).await?;
+                track_dir_dependency(self.cwd, path, glob).await?;
             }
             InfoMessage::EmittedError { error, severity } => {
                 EvaluateEmittedErrorIssue {
@@ -600,45 +696,6 @@ Changing this file or any dependency will not be recognized and might require re
     }
 }
 
-/// A hack to invalidate when any file in a directory changes. Need to be
-/// awaited before files are accessed.
-#[turbo_tasks::function]
-async fn dir_dependency(glob: Vc<ReadGlobResult>) -> Result<Vc<Completion>> {
-    let shallow = dir_dependency_shallow(glob);
-    let glob = glob.await?;
-    glob.inner
-        .values()
-        .map(|&inner| dir_dependency(inner))
-        .try_join()
-        .await?;
-    shallow.await?;
-    Ok(Completion::new())
-}
-
-#[turbo_tasks::function]
-async fn dir_dependency_shallow(glob: Vc<ReadGlobResult>) -> Result<Vc<Completion>> {
-    let glob = glob.await?;
-    for item in glob.results.values() {
-        // Reading all files to add itself as dependency
-        match *item {
-            DirectoryEntry::File(file) => {
-                file.track().await?;
-            }
-            DirectoryEntry::Directory(dir) => {
-                dir_dependency(dir.read_glob(Glob::new("**".to_string()), false)).await?;
-            }
-            DirectoryEntry::Symlink(symlink) => {
-                symlink.read_link().await?;
-            }
-            DirectoryEntry::Other(other) => {
-                other.get_type().await?;
-            }
-            DirectoryEntry::Error => {}
-        }
-    }
-    Ok(Completion::new())
-}
-
 #[turbo_tasks::value(shared)]
 pub struct EvaluateEmittedErrorIssue {
     pub file_path: Vc<FileSystemPath>,
@@ -6,8 +6,8 @@ use turbopack_core::{
     resolve::{
         handle_resolve_error,
         options::{
-            ConditionValue, ResolutionConditions, ResolveInPackage, ResolveIntoPackage,
-            ResolveOptions,
+            ConditionValue, DualPackageVariant, ResolutionConditions, ResolveInPackage,
+            ResolveIntoPackage, ResolveOptions,
         },
         origin::{ResolveOrigin, ResolveOriginExt},
         parse::Request,
@@ -45,20 +45,59 @@ pub async fn apply_esm_specific_options(options: Vc<ResolveOptions>) -> Result<V
     let mut options: ResolveOptions = options.await?.clone_value();
     // TODO set fully_specified when in strict ESM mode
     // options.fully_specified = true;
+    let variant = options
+        .dedupe_dual_packages
+        .unwrap_or(DualPackageVariant::Esm);
+    apply_dual_package_variant(&mut options, variant);
+    Ok(options.into())
+}
+
+/// Sets the `import` and `require` conditions to resolve to `variant`.
+fn apply_dual_package_variant(options: &mut ResolveOptions, variant: DualPackageVariant) {
+    let (import, require) = match variant {
+        DualPackageVariant::Esm => (ConditionValue::Set, ConditionValue::Unset),
+        DualPackageVariant::Cjs => (ConditionValue::Unset, ConditionValue::Set),
+    };
+    for conditions in get_condition_maps(options) {
+        conditions.insert("import".to_string(), import);
+        conditions.insert("require".to_string(), require);
+    }
+}
+
+#[turbo_tasks::function]
+async fn apply_condition(
+    options: Vc<ResolveOptions>,
+    condition: String,
+) -> Result<Vc<ResolveOptions>> {
+    let mut options: ResolveOptions = options.await?.clone_value();
+    let unset = environment_conditions_to_unset(&condition);
     for conditions in get_condition_maps(&mut options) {
-        conditions.insert("import".to_string(), ConditionValue::Set);
-        conditions.insert("require".to_string(), ConditionValue::Unset);
+        for &other in unset {
+            conditions.insert(other.to_string(), ConditionValue::Unset);
+        }
+        conditions.insert(condition.clone(), ConditionValue::Set);
     }
     Ok(options.into())
 }
 
+/// Returns the conditions of the other environment, which the options of
+/// the importing module might have set, when switching to the environment
+/// of `condition`.
+fn environment_conditions_to_unset(condition: &str) -> &'static [&'static str] {
+    match condition {
+        "client" | "browser" => &["server", "node", "react-server"],
+        "server" | "node" | "react-server" => &["client", "browser"],
+        _ => &[],
+    }
+}
+
 #[turbo_tasks::function]
 pub async fn apply_cjs_specific_options(options: Vc<ResolveOptions>) -> Result<Vc<ResolveOptions>> {
     let mut options: ResolveOptions = options.await?.clone_value();
-    for conditions in get_condition_maps(&mut options) {
-        conditions.insert("import".to_string(), ConditionValue::Unset);
-        conditions.insert("require".to_string(), ConditionValue::Set);
-    }
+    let variant = options
+        .dedupe_dual_packages
+        .unwrap_or(DualPackageVariant::Cjs);
+    apply_dual_package_variant(&mut options, variant);
     Ok(options.into())
 }
 
@@ -77,6 +116,31 @@ pub async fn esm_resolve(
     specific_resolve(origin, request, options, ty, issue_severity, issue_source).await
 }
 
+/// Like [esm_resolve], but with `condition` set for the `exports` and
+/// `imports` fields of packages, e. g. for an import with an
+/// `env: "server"` import attribute. The conditions of the other environment
+/// (e. g. `node` and `react-server` for `env: "client"`) are unset. The
+/// conditions only apply to this import, not to the imports of the resolved
+/// module.
+#[turbo_tasks::function]
+pub async fn esm_resolve_with_condition(
+    origin: Vc<Box<dyn ResolveOrigin>>,
+    request: Vc<Request>,
+    ty: Value<EcmaScriptModulesReferenceSubType>,
+    condition: String,
+    issue_severity: Vc<IssueSeverity>,
+    issue_source: Option<Vc<IssueSource>>,
+) -> Result<Vc<ModuleResolveResult>> {
+    let ty = Value::new(ReferenceType::EcmaScriptModules(ty.into_value()));
+    let options = apply_condition(
+        apply_esm_specific_options(origin.resolve_options(ty.clone())),
+        condition,
+    )
+    .resolve()
+    .await?;
+    specific_resolve(origin, request, options, ty, issue_severity, issue_source).await
+}
+
 #[turbo_tasks::function]
 pub async fn cjs_resolve(
     origin: Vc<Box<dyn ResolveOrigin>>,
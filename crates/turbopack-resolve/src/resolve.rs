@@ -251,6 +251,9 @@ async fn base_resolve_options(
         import_map: Some(import_map),
         resolved_map: opt.resolved_map,
         plugins,
+        dedupe_dual_packages: opt.dedupe_dual_packages,
+        workspace_source: opt.workspace_source.clone(),
+        out_of_project_imports: opt.out_of_project_imports.clone(),
         ..Default::default()
     }
     .into())
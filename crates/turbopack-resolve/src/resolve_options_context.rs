@@ -5,7 +5,9 @@ use turbopack_core::{
     condition::ContextCondition,
     environment::Environment,
     resolve::{
-        options::{ImportMap, ResolvedMap},
+        options::{
+            DualPackageVariant, ImportMap, OutOfProjectImportPolicy, ResolvedMap, WorkspaceSource,
+        },
         plugin::ResolvePlugin,
     },
 };
@@ -73,6 +75,17 @@ pub struct ResolveOptionsContext {
     /// resolving.
     pub plugins: Vec<Vc<Box<dyn ResolvePlugin>>>,
     #[serde(default)]
+    /// Resolves the `import` and `require` conditions of dual packages to
+    /// one variant, to avoid including both builds of a package.
+    pub dedupe_dual_packages: Option<DualPackageVariant>,
+    #[serde(default)]
+    /// Resolves workspace packages to their source entry instead of their
+    /// build output.
+    pub workspace_source: Option<WorkspaceSource>,
+    #[serde(default)]
+    /// Warns about or denies imports of files outside of the project.
+    pub out_of_project_imports: Option<OutOfProjectImportPolicy>,
+    #[serde(default)]
     pub placeholder_for_future_extensions: (),
 }
 
@@ -379,7 +379,7 @@ pub async fn type_resolve(
         let m = if let Some(stripped) = m.strip_prefix('@') {
             stripped.replace('/', "__")
         } else {
-            m.clone()
+            m.to_string()
         };
         Some(Request::module(
             format!("@types/{m}"),
@@ -11,7 +11,9 @@ use turbopack_core::{
     resolve::options::{ImportMap, ImportMapping},
 };
 use turbopack_ecmascript::TreeShakingMode;
-use turbopack_node::execution_context::ExecutionContext;
+use turbopack_node::{
+    execution_context::ExecutionContext, native_addons::NativeAddonResolvePlugin,
+};
 use turbopack_resolve::resolve_options_context::ResolveOptionsContext;
 
 use crate::{
@@ -60,6 +62,9 @@ pub async fn node_evaluate_asset_context(
         enable_node_externals: true,
         enable_node_native_modules: true,
         custom_conditions: vec![node_env.clone(), "node".to_string()],
+        plugins: vec![Vc::upcast(NativeAddonResolvePlugin::new(
+            execution_context.project_path().root(),
+        ))],
         ..Default::default()
     };
     // app code context, includes a rule to switch to the node_modules context
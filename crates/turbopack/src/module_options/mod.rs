@@ -17,7 +17,9 @@ use turbopack_core::{
 use turbopack_css::CssModuleAssetType;
 use turbopack_ecmascript::{EcmascriptInputTransform, EcmascriptOptions, SpecifiedModuleType};
 use turbopack_mdx::MdxTransformOptions;
-use turbopack_node::transforms::{postcss::PostCssTransform, webpack::WebpackLoaders};
+use turbopack_node::transforms::{
+    external_compiler::ExternalCompiler, postcss::PostCssTransform, webpack::WebpackLoaders,
+};
 use turbopack_wasm::source::WebAssemblySourceType;
 
 use crate::{
@@ -74,6 +76,7 @@ impl ModuleOptions {
             enable_raw_css,
             ref enable_postcss_transform,
             ref enable_webpack_loaders,
+            ref enable_external_compilers,
             preset_env_versions,
             ref custom_rules,
             execution_context,
@@ -565,6 +568,44 @@ impl ModuleOptions {
             }
         }
 
+        if let Some(external_compilers) = enable_external_compilers {
+            let execution_context = execution_context
+                .context("execution_context is required for external compilers")?;
+            let project_path = execution_context.project_path();
+            for (glob, rule) in external_compilers.await?.iter() {
+                rules.push(ModuleRule::new(
+                    ModuleRuleCondition::All(vec![
+                        if !glob.contains('/') {
+                            ModuleRuleCondition::ResourceBasePathGlob(
+                                Glob::new(glob.clone()).await?,
+                            )
+                        } else {
+                            ModuleRuleCondition::ResourcePathGlob {
+                                base: project_path.await?,
+                                glob: Glob::new(glob.clone()).await?,
+                            }
+                        },
+                        ModuleRuleCondition::not(ModuleRuleCondition::ResourceIsVirtualSource),
+                    ]),
+                    vec![
+                        // Like webpack loaders, external compilers are expected to return
+                        // ecmascript code unless `rename_as` says otherwise.
+                        ModuleRuleEffect::ModuleType(ModuleType::Ecmascript {
+                            transforms: app_transforms,
+                            options: ecmascript_options,
+                        }),
+                        ModuleRuleEffect::SourceTransforms(Vc::cell(vec![Vc::upcast(
+                            ExternalCompiler::new(
+                                project_path,
+                                rule.command,
+                                rule.rename_as.clone(),
+                            ),
+                        )])),
+                    ],
+                ));
+            }
+        }
+
         rules.extend(custom_rules.iter().cloned());
 
         Ok(ModuleOptions::cell(ModuleOptions { rules }))
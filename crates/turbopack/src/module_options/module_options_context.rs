@@ -7,7 +7,10 @@ use turbopack_core::{
 use turbopack_ecmascript::{references::esm::UrlRewriteBehavior, TreeShakingMode};
 use turbopack_node::{
     execution_context::ExecutionContext,
-    transforms::{postcss::PostCssTransformOptions, webpack::WebpackLoaderItems},
+    transforms::{
+        external_compiler::ExternalCompilerCommand, postcss::PostCssTransformOptions,
+        webpack::WebpackLoaderItems,
+    },
 };
 
 use super::ModuleRule;
@@ -37,6 +40,18 @@ pub struct WebpackLoadersOptions {
 #[turbo_tasks::value(transparent)]
 pub struct OptionWebpackLoadersOptions(Option<Vc<WebpackLoadersOptions>>);
 
+#[derive(Clone, PartialEq, Eq, Debug, TraceRawVcs, Serialize, Deserialize)]
+pub struct ExternalCompilerRuleItem {
+    pub command: Vc<ExternalCompilerCommand>,
+    pub rename_as: Option<String>,
+}
+
+/// Files matching the globs are compiled by an external command instead of
+/// Turbopack, see [turbopack_node::transforms::external_compiler].
+#[derive(Default)]
+#[turbo_tasks::value(transparent)]
+pub struct ExternalCompilerRules(IndexMap<String, ExternalCompilerRuleItem>);
+
 /// The kind of decorators transform to use.
 /// [TODO]: might need bikeshed for the name (Ecma)
 #[derive(Clone, PartialEq, Eq, Debug, TraceRawVcs, Serialize, Deserialize)]
@@ -125,6 +140,7 @@ pub struct ModuleOptionsContext {
     pub enable_jsx: Option<Vc<JsxTransformOptions>>,
     pub enable_postcss_transform: Option<Vc<PostCssTransformOptions>>,
     pub enable_webpack_loaders: Option<Vc<WebpackLoadersOptions>>,
+    pub enable_external_compilers: Option<Vc<ExternalCompilerRules>>,
     /// Follow type references and resolve declaration files in additional to
     /// normal resolution.
     pub enable_types: bool,